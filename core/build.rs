@@ -0,0 +1,11 @@
+fn main() {
+    // C-variadic and va_list JNI entry points cannot be written in stable
+    // Rust; they live in a small C shim that forwards to the `...A` forms.
+    println!("cargo:rerun-if-changed=src/native/jni/varargs.c");
+    println!("cargo:rerun-if-changed=include/jni.h");
+    println!("cargo:rerun-if-changed=include/jni_md.h");
+    cc::Build::new()
+        .file("src/native/jni/varargs.c")
+        .include("include")
+        .compile("aria_jni_varargs");
+}
@@ -0,0 +1,17 @@
+//! `cargo +nightly fuzz run class_parser` from `core/`. The seed corpus in
+//! `fuzz/corpus/class_parser` holds javac output covering every constant
+//! tag the parser knows.
+#![no_main]
+
+use aria_core::bytecode::parser::ClassFile;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Any input must come back as `Ok` or `Err`, never a panic.
+    if let Ok(class) = ClassFile::from_bytes(data) {
+        let _ = class.get_class_name(class.this_class);
+        for method in &class.methods {
+            let _ = class.get_utf8(method.name_index);
+        }
+    }
+});
@@ -178,11 +178,12 @@ struct JNINativeInterface_ {
     jmethodID (JNICALL* FromReflectedMethod)(JNIEnv* env, jobject method);
     jfieldID (JNICALL* FromReflectedField)(JNIEnv* env, jobject field);
     jobject (JNICALL* ToReflectedMethod)(JNIEnv* env, jclass cls, jmethodID methodID, jboolean isStatic);
-    jobject (JNICALL* ToReflectedField)(JNIEnv* env, jclass cls, jfieldID fieldID, jboolean isStatic);
 
     jclass (JNICALL* GetSuperclass)(JNIEnv* env, jclass sub);
     jboolean (JNICALL* IsAssignableFrom)(JNIEnv* env, jclass sub, jclass sup);
 
+    jobject (JNICALL* ToReflectedField)(JNIEnv* env, jclass cls, jfieldID fieldID, jboolean isStatic);
+
     jint (JNICALL* Throw)(JNIEnv* env, jthrowable obj);
     jint (JNICALL* ThrowNew)(JNIEnv* env, jclass clazz, const char* msg);
     jthrowable (JNICALL* ExceptionOccurred)(JNIEnv* env);
@@ -0,0 +1,213 @@
+//! Where launcher arguments come from besides the command line: `@argfiles`
+//! and the `JDK_JAVA_OPTIONS` and `JAVA_TOOL_OPTIONS` environment
+//! variables, read with the same rules as the `java` launcher.
+
+use std::fs;
+
+/// Options whose value is the next argument, so that the value is not
+/// mistaken for the main class.
+const OPTIONS_WITH_VALUE: &[&str] = &["-cp", "-classpath", "--class-path", "--source"];
+
+/// Options that may not come from `JDK_JAVA_OPTIONS`.
+const LAUNCHER_ONLY_OPTIONS: &[&str] = &["-jar", "-m", "--module", "--disable-@files"];
+
+/// Replaces each `@file` argument before the main class with the arguments
+/// read from the file. `@@arg` passes `@arg` through, and
+/// `--disable-@files` stops the expansion of the rest.
+pub fn expand_argfiles(args: &[String]) -> Result<Vec<String>, String> {
+    let mut expanded = Vec::with_capacity(args.len());
+    let mut scan = OptionScan::default();
+    let mut iter = args.iter();
+    for arg in iter.by_ref() {
+        if scan.found_main_class() || arg == "--disable-@files" {
+            expanded.push(arg.clone());
+            break;
+        }
+        let read = match arg.strip_prefix('@') {
+            Some(literal) if literal.starts_with('@') => vec![literal.to_string()],
+            Some(path) => read_argfile(path)?,
+            None => vec![arg.clone()],
+        };
+        for arg in read {
+            scan.next(&arg);
+            expanded.push(arg);
+        }
+    }
+    expanded.extend(iter.cloned());
+    Ok(expanded)
+}
+
+fn read_argfile(path: &str) -> Result<Vec<String>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|_| format!("Error: could not open `{}'", path))?;
+    Ok(tokenize(&contents))
+}
+
+/// The arguments in `JDK_JAVA_OPTIONS`, which go before the command line.
+/// They may use `@argfiles` but not name the main class. Prints the
+/// `NOTE: Picked up` line that `java` prints.
+pub fn jdk_java_options() -> Result<Vec<String>, String> {
+    const NAME: &str = "JDK_JAVA_OPTIONS";
+    let Some(value) = env_options(NAME) else {
+        return Ok(Vec::new());
+    };
+    eprintln!("NOTE: Picked up {}: {}", NAME, value);
+    let args = expand_argfiles(&tokenize(&value))?;
+    let mut scan = OptionScan::default();
+    for arg in &args {
+        if LAUNCHER_ONLY_OPTIONS.contains(&arg.as_str()) {
+            return Err(format!(
+                "Error: Option {} is not allowed in environment variable {}",
+                arg, NAME
+            ));
+        }
+        scan.next(arg);
+        if scan.found_main_class() {
+            return Err(format!(
+                "Error: Cannot specify main class in environment variable {}",
+                NAME
+            ));
+        }
+    }
+    Ok(args)
+}
+
+/// The options in `JAVA_TOOL_OPTIONS`, read by the VM itself before any
+/// other option so that the command line overrides them. Only VM options
+/// are allowed; anything else is reported as unrecognized.
+pub fn java_tool_options() -> Result<Vec<String>, String> {
+    const NAME: &str = "JAVA_TOOL_OPTIONS";
+    let Some(value) = env_options(NAME) else {
+        return Ok(Vec::new());
+    };
+    eprintln!("Picked up {}: {}", NAME, value);
+    let options = tokenize(&value);
+    match options.iter().find(|option| !option.starts_with('-')) {
+        Some(option) => Err(format!("Unrecognized option: {}", option)),
+        None => Ok(options),
+    }
+}
+
+fn env_options(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Tracks options as they are read to tell when the main class comes.
+#[derive(Default)]
+struct OptionScan {
+    expects_value: bool,
+    main_class: bool,
+}
+
+impl OptionScan {
+    fn next(&mut self, arg: &str) {
+        if self.main_class {
+            return;
+        }
+        if self.expects_value {
+            self.expects_value = false;
+        } else if OPTIONS_WITH_VALUE.contains(&arg) {
+            self.expects_value = true;
+        } else if !arg.starts_with('-') {
+            self.main_class = true;
+        }
+    }
+
+    fn found_main_class(&self) -> bool {
+        self.main_class
+    }
+}
+
+/// Splits argfile or environment text into arguments. Arguments are
+/// separated by whitespace and may be quoted with `"` or `'`; inside
+/// quotes, `\` escapes the next character and a `\` at the end of a line
+/// joins it to the next. `#` outside an argument starts a comment.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' if !in_arg => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '"' | '\'' => {
+                in_arg = true;
+                let quote = c;
+                while let Some(c) = chars.next() {
+                    match c {
+                        _ if c == quote => break,
+                        '\\' => match chars.next() {
+                            Some('n') => current.push('\n'),
+                            Some('t') => current.push('\t'),
+                            Some('r') => current.push('\r'),
+                            Some('f') => current.push('\u{c}'),
+                            Some('\n' | '\r') => {
+                                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                                    chars.next();
+                                }
+                            }
+                            Some(other) => current.push(other),
+                            None => {}
+                        },
+                        _ => current.push(c),
+                    }
+                }
+            }
+            _ if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            _ => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Parses a size such as `-Xmx` takes: a byte count with an optional `k`,
+/// `m`, `g` or `t` suffix in either case.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let (digits, shift) = match text.as_bytes().last()? {
+        b'k' | b'K' => (&text[..text.len() - 1], 10),
+        b'm' | b'M' => (&text[..text.len() - 1], 20),
+        b'g' | b'G' => (&text[..text.len() - 1], 30),
+        b't' | b'T' => (&text[..text.len() - 1], 40),
+        _ => (text, 0),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value = digits.parse::<u64>().ok()?;
+    value
+        .checked_mul(1 << shift)
+        .filter(|scaled| scaled >> shift == value)
+}
+
+/// Formats a size the way `-XshowSettings` does, such as `64.00M`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [
+        (1 << 40, "T"),
+        (1 << 30, "G"),
+        (1 << 20, "M"),
+        (1 << 10, "K"),
+    ];
+    for (scale, unit) in UNITS {
+        if bytes >= scale {
+            return format!("{:.2}{}", bytes as f64 / scale as f64, unit);
+        }
+    }
+    bytes.to_string()
+}
@@ -0,0 +1,261 @@
+use super::constant_pool::ConstantPoolBuilder;
+use super::error::ClassFormatError;
+
+/// A position in the code, bound once and referenced by any number of
+/// branches before or after that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(usize);
+
+/// The operand type a load, store or return works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+}
+
+impl ValueKind {
+    /// Offset from the `i` form of an opcode family to this kind's form.
+    fn family_offset(self) -> u8 {
+        match self {
+            ValueKind::Int => 0,
+            ValueKind::Long => 1,
+            ValueKind::Float => 2,
+            ValueKind::Double => 3,
+            ValueKind::Reference => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fixup {
+    /// The instruction the offset is relative to.
+    base: usize,
+    /// Where the offset goes.
+    at: usize,
+    label: Label,
+    wide: bool,
+}
+
+/// Emits the bytes of a `Code` attribute, resolving branch targets when
+/// `finish` is called.
+#[derive(Debug, Default)]
+pub struct Assembler {
+    code: Vec<u8>,
+    labels: Vec<Option<usize>>,
+    fixups: Vec<Fixup>,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The bytes emitted so far; branch offsets are still unpatched.
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn position(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn emit(&mut self, opcode: u8) {
+        self.code.push(opcode);
+    }
+
+    pub fn emit_u1(&mut self, value: u8) {
+        self.code.push(value);
+    }
+
+    pub fn emit_u2(&mut self, value: u16) {
+        self.code.extend(value.to_be_bytes());
+    }
+
+    pub fn emit_u4(&mut self, value: u32) {
+        self.code.extend(value.to_be_bytes());
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Binds `label` to the current position.
+    pub fn bind(&mut self, label: Label) -> Result<(), ClassFormatError> {
+        match self.labels.get_mut(label.0) {
+            Some(slot @ None) => {
+                *slot = Some(self.code.len());
+                Ok(())
+            }
+            Some(Some(_)) => Err(ClassFormatError::Invalid(format!(
+                "Label {} bound twice",
+                label.0
+            ))),
+            None => Err(ClassFormatError::Invalid(format!(
+                "Unknown label {}",
+                label.0
+            ))),
+        }
+    }
+
+    pub fn offset(&self, label: Label) -> Option<usize> {
+        self.labels.get(label.0).copied().flatten()
+    }
+
+    /// A branch with a 16-bit offset: `if*`, `goto` or `jsr`.
+    pub fn jump(&mut self, opcode: u8, label: Label) {
+        let base = self.code.len();
+        self.emit(opcode);
+        self.fixup(base, label, false);
+    }
+
+    /// `goto_w` or `jsr_w`.
+    pub fn jump_wide(&mut self, opcode: u8, label: Label) {
+        let base = self.code.len();
+        self.emit(opcode);
+        self.fixup(base, label, true);
+    }
+
+    pub fn tableswitch(&mut self, default: Label, low: i32, targets: &[Label]) {
+        let base = self.code.len();
+        self.emit(0xaa);
+        self.align();
+        self.fixup(base, default, true);
+        let high = low.wrapping_add(targets.len() as i32 - 1);
+        self.emit_u4(low as u32);
+        self.emit_u4(high as u32);
+        for target in targets {
+            self.fixup(base, *target, true);
+        }
+    }
+
+    /// `pairs` must be sorted by key, as JVMS 6.5 requires.
+    pub fn lookupswitch(&mut self, default: Label, pairs: &[(i32, Label)]) {
+        let base = self.code.len();
+        self.emit(0xab);
+        self.align();
+        self.fixup(base, default, true);
+        self.emit_u4(pairs.len() as u32);
+        for (key, target) in pairs {
+            self.emit_u4(*key as u32);
+            self.fixup(base, *target, true);
+        }
+    }
+
+    /// Pushes an `int` with the shortest instruction that holds it.
+    pub fn push_int(&mut self, value: i32, pool: &mut ConstantPoolBuilder) {
+        match value {
+            -1..=5 => self.emit((0x03 + value) as u8),
+            -128..=127 => {
+                self.emit(0x10);
+                self.emit_u1(value as i8 as u8);
+            }
+            -32768..=32767 => {
+                self.emit(0x11);
+                self.emit_u2(value as i16 as u16);
+            }
+            _ => {
+                let index = pool.integer(value);
+                self.ldc(index);
+            }
+        }
+    }
+
+    /// `ldc`, or `ldc_w` when the index needs two bytes.
+    pub fn ldc(&mut self, index: u16) {
+        if let Ok(index) = u8::try_from(index) {
+            self.emit(0x12);
+            self.emit_u1(index);
+        } else {
+            self.emit(0x13);
+            self.emit_u2(index);
+        }
+    }
+
+    /// `ldc2_w` for a `Long` or `Double` constant.
+    pub fn ldc2(&mut self, index: u16) {
+        self.emit(0x14);
+        self.emit_u2(index);
+    }
+
+    pub fn load(&mut self, kind: ValueKind, slot: u16) {
+        self.local(0x15, 0x1a, kind, slot);
+    }
+
+    pub fn store(&mut self, kind: ValueKind, slot: u16) {
+        self.local(0x36, 0x3b, kind, slot);
+    }
+
+    /// `ireturn` through `areturn`, or `return` for `None`.
+    pub fn return_value(&mut self, kind: Option<ValueKind>) {
+        match kind {
+            Some(kind) => self.emit(0xac + kind.family_offset()),
+            None => self.emit(0xb1),
+        }
+    }
+
+    /// The finished code with every branch patched.
+    pub fn finish(mut self) -> Result<Vec<u8>, ClassFormatError> {
+        for fixup in &self.fixups {
+            let target = self.labels[fixup.label.0].ok_or_else(|| {
+                ClassFormatError::Invalid(format!("Label {} never bound", fixup.label.0))
+            })?;
+            let offset = target as i64 - fixup.base as i64;
+            if fixup.wide {
+                self.code[fixup.at..fixup.at + 4].copy_from_slice(&(offset as i32).to_be_bytes());
+            } else {
+                let offset = i16::try_from(offset).map_err(|_| {
+                    ClassFormatError::Invalid(format!(
+                        "Branch offset {} at {} out of range",
+                        offset, fixup.base
+                    ))
+                })?;
+                self.code[fixup.at..fixup.at + 2].copy_from_slice(&offset.to_be_bytes());
+            }
+        }
+        if self.code.len() > 0xffff {
+            return Err(ClassFormatError::Invalid(format!(
+                "Invalid method Code length {}",
+                self.code.len()
+            )));
+        }
+        Ok(self.code)
+    }
+
+    /// `opcode` takes a one-byte slot, `short` is the `_0` form for ints;
+    /// other kinds follow in blocks of four.
+    fn local(&mut self, opcode: u8, short: u8, kind: ValueKind, slot: u16) {
+        let offset = kind.family_offset();
+        if slot <= 3 {
+            self.emit(short + offset * 4 + slot as u8);
+        } else if let Ok(slot) = u8::try_from(slot) {
+            self.emit(opcode + offset);
+            self.emit_u1(slot);
+        } else {
+            self.emit(0xc4);
+            self.emit(opcode + offset);
+            self.emit_u2(slot);
+        }
+    }
+
+    fn fixup(&mut self, base: usize, label: Label, wide: bool) {
+        self.fixups.push(Fixup {
+            base,
+            at: self.code.len(),
+            label,
+            wide,
+        });
+        let width = if wide { 4 } else { 2 };
+        self.code.extend(std::iter::repeat_n(0, width));
+    }
+
+    /// Pads a switch to a four-byte boundary from the start of the code.
+    fn align(&mut self) {
+        while !self.code.len().is_multiple_of(4) {
+            self.code.push(0);
+        }
+    }
+}
@@ -0,0 +1,696 @@
+//! The standard attributes of JVMS 17 §4.7, decoded into typed structures.
+//! Constant-pool references stay as indices, as everywhere else in
+//! `ClassFile`.
+
+use super::error::ClassFormatError;
+use super::parser::{expect_class, expect_utf8, ClassFile, ConstantPoolEntry};
+use super::reader::ClassReader;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    ConstantValue(u16),
+    StackMapTable(Vec<StackMapFrame>),
+    /// Classes named in a method's `throws` clause.
+    Exceptions(Vec<u16>),
+    InnerClasses(Vec<InnerClass>),
+    EnclosingMethod {
+        class_index: u16,
+        /// 0 outside a method.
+        method_index: u16,
+    },
+    Synthetic,
+    Signature(u16),
+    SourceFile(u16),
+    SourceDebugExtension(Vec<u8>),
+    LineNumberTable(Vec<LineNumber>),
+    LocalVariableTable(Vec<LocalVariable>),
+    /// Same layout as `LocalVariableTable`, with `descriptor_index`
+    /// naming a generic signature.
+    LocalVariableTypeTable(Vec<LocalVariable>),
+    Deprecated,
+    RuntimeVisibleAnnotations(Vec<Annotation>),
+    RuntimeInvisibleAnnotations(Vec<Annotation>),
+    /// One list of annotations per formal parameter.
+    RuntimeVisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    RuntimeInvisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    RuntimeVisibleTypeAnnotations(Vec<TypeAnnotation>),
+    RuntimeInvisibleTypeAnnotations(Vec<TypeAnnotation>),
+    AnnotationDefault(ElementValue),
+    BootstrapMethods(Vec<BootstrapMethod>),
+    MethodParameters(Vec<MethodParameter>),
+    Module(Box<Module>),
+    ModulePackages(Vec<u16>),
+    ModuleMainClass(u16),
+    NestHost(u16),
+    NestMembers(Vec<u16>),
+    Record(Vec<RecordComponent>),
+    PermittedSubclasses(Vec<u16>),
+    /// An attribute this parser does not know, kept byte for byte.
+    Unknown {
+        name_index: u16,
+        info: Vec<u8>,
+    },
+}
+
+impl Attribute {
+    /// The attribute's name in the class file; `None` for `Unknown`, whose
+    /// name is in the constant pool.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            Attribute::ConstantValue(_) => "ConstantValue",
+            Attribute::StackMapTable(_) => "StackMapTable",
+            Attribute::Exceptions(_) => "Exceptions",
+            Attribute::InnerClasses(_) => "InnerClasses",
+            Attribute::EnclosingMethod { .. } => "EnclosingMethod",
+            Attribute::Synthetic => "Synthetic",
+            Attribute::Signature(_) => "Signature",
+            Attribute::SourceFile(_) => "SourceFile",
+            Attribute::SourceDebugExtension(_) => "SourceDebugExtension",
+            Attribute::LineNumberTable(_) => "LineNumberTable",
+            Attribute::LocalVariableTable(_) => "LocalVariableTable",
+            Attribute::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+            Attribute::Deprecated => "Deprecated",
+            Attribute::RuntimeVisibleAnnotations(_) => "RuntimeVisibleAnnotations",
+            Attribute::RuntimeInvisibleAnnotations(_) => "RuntimeInvisibleAnnotations",
+            Attribute::RuntimeVisibleParameterAnnotations(_) => {
+                "RuntimeVisibleParameterAnnotations"
+            }
+            Attribute::RuntimeInvisibleParameterAnnotations(_) => {
+                "RuntimeInvisibleParameterAnnotations"
+            }
+            Attribute::RuntimeVisibleTypeAnnotations(_) => "RuntimeVisibleTypeAnnotations",
+            Attribute::RuntimeInvisibleTypeAnnotations(_) => "RuntimeInvisibleTypeAnnotations",
+            Attribute::AnnotationDefault(_) => "AnnotationDefault",
+            Attribute::BootstrapMethods(_) => "BootstrapMethods",
+            Attribute::MethodParameters(_) => "MethodParameters",
+            Attribute::Module(_) => "Module",
+            Attribute::ModulePackages(_) => "ModulePackages",
+            Attribute::ModuleMainClass(_) => "ModuleMainClass",
+            Attribute::NestHost(_) => "NestHost",
+            Attribute::NestMembers(_) => "NestMembers",
+            Attribute::Record(_) => "Record",
+            Attribute::PermittedSubclasses(_) => "PermittedSubclasses",
+            Attribute::Unknown { .. } => return None,
+        })
+    }
+}
+
+/// A `StackMapTable` entry. Offsets are deltas, as stored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackMapFrame {
+    /// `same_frame` and `same_frame_extended`.
+    Same { offset_delta: u16 },
+    /// `same_locals_1_stack_item_frame` and its extended form.
+    SameLocals1StackItem {
+        offset_delta: u16,
+        stack: VerificationType,
+    },
+    /// Drops the last `chopped` (1 to 3) locals.
+    Chop { offset_delta: u16, chopped: u8 },
+    Append {
+        offset_delta: u16,
+        locals: Vec<VerificationType>,
+    },
+    Full {
+        offset_delta: u16,
+        locals: Vec<VerificationType>,
+        stack: Vec<VerificationType>,
+    },
+}
+
+impl StackMapFrame {
+    pub fn offset_delta(&self) -> u16 {
+        match self {
+            StackMapFrame::Same { offset_delta }
+            | StackMapFrame::SameLocals1StackItem { offset_delta, .. }
+            | StackMapFrame::Chop { offset_delta, .. }
+            | StackMapFrame::Append { offset_delta, .. }
+            | StackMapFrame::Full { offset_delta, .. } => *offset_delta,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Double,
+    Long,
+    Null,
+    UninitializedThis,
+    /// A `Class` constant.
+    Object(u16),
+    /// The offset of the `new` that created the value.
+    Uninitialized(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InnerClass {
+    pub inner_class_info_index: u16,
+    pub outer_class_info_index: u16,
+    pub inner_name_index: u16,
+    pub inner_class_access_flags: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineNumber {
+    pub start_pc: u16,
+    pub line_number: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalVariable {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// A field descriptor such as `Ljava/lang/Deprecated;`.
+    pub type_index: u16,
+    /// `(element_name_index, value)` pairs.
+    pub elements: Vec<(u16, ElementValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementValue {
+    /// `B C D F I J S Z` take a numeric constant, `s` a `Utf8` one.
+    Const {
+        tag: u8,
+        index: u16,
+    },
+    Enum {
+        type_name_index: u16,
+        const_name_index: u16,
+    },
+    /// A return descriptor, e.g. `Ljava/lang/String;` or `V`.
+    Class(u16),
+    Annotation(Box<Annotation>),
+    Array(Vec<ElementValue>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotation {
+    pub target_type: u8,
+    pub target: TypeAnnotationTarget,
+    /// `(type_path_kind, type_argument_index)` steps.
+    pub type_path: Vec<(u8, u8)>,
+    pub annotation: Annotation,
+}
+
+/// The `target_info` union, selected by `target_type`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeAnnotationTarget {
+    TypeParameter {
+        index: u8,
+    },
+    /// 65535 is the superclass, anything else an `interfaces` index.
+    Supertype {
+        index: u16,
+    },
+    TypeParameterBound {
+        type_parameter_index: u8,
+        bound_index: u8,
+    },
+    Empty,
+    FormalParameter {
+        index: u8,
+    },
+    Throws {
+        type_index: u16,
+    },
+    /// `(start_pc, length, index)` ranges of a local variable.
+    LocalVar(Vec<(u16, u16, u16)>),
+    Catch {
+        exception_table_index: u16,
+    },
+    Offset {
+        offset: u16,
+    },
+    TypeArgument {
+        offset: u16,
+        type_argument_index: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapMethod {
+    /// A `MethodHandle` constant.
+    pub method_ref: u16,
+    pub arguments: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodParameter {
+    /// 0 for a parameter without a name.
+    pub name_index: u16,
+    pub access_flags: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Module {
+    pub name_index: u16,
+    pub flags: u16,
+    pub version_index: u16,
+    pub requires: Vec<ModuleRequires>,
+    pub exports: Vec<ModulePackage>,
+    pub opens: Vec<ModulePackage>,
+    /// `Class` constants of the services the module uses.
+    pub uses: Vec<u16>,
+    pub provides: Vec<ModuleProvides>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModuleRequires {
+    pub module_index: u16,
+    pub flags: u16,
+    pub version_index: u16,
+}
+
+/// An `exports` or `opens` entry; `to` is empty when unqualified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModulePackage {
+    pub package_index: u16,
+    pub flags: u16,
+    pub to: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleProvides {
+    pub service_index: u16,
+    pub with: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordComponent {
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<Attribute>,
+}
+
+impl ClassFile {
+    /// The class-level attribute with `name`, e.g. `"SourceFile"`.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.name() == Some(name))
+    }
+
+    pub fn source_file(&self) -> Option<&str> {
+        match self.attribute("SourceFile")? {
+            Attribute::SourceFile(index) => self.get_utf8(*index),
+            _ => None,
+        }
+    }
+
+    pub fn bootstrap_methods(&self) -> &[BootstrapMethod] {
+        match self.attribute("BootstrapMethods") {
+            Some(Attribute::BootstrapMethods(methods)) => methods,
+            _ => &[],
+        }
+    }
+}
+
+/// Reads the header and body of one `attribute_info` without decoding it.
+pub(super) fn read_raw<'a>(
+    reader: &mut ClassReader,
+    pool: &'a [ConstantPoolEntry],
+) -> Result<(&'a str, u16, Vec<u8>), ClassFormatError> {
+    let name_index = reader.read_u2()?;
+    let name = expect_utf8(pool, name_index)?;
+    let length = reader.read_u4()?;
+    let info = reader.read_bytes(length as usize)?.to_vec();
+    Ok((name, name_index, info))
+}
+
+/// Reads and decodes one `attribute_info`.
+pub(super) fn read(
+    reader: &mut ClassReader,
+    pool: &[ConstantPoolEntry],
+) -> Result<Attribute, ClassFormatError> {
+    let (name, name_index, info) = read_raw(reader, pool)?;
+    decode(name, name_index, info, pool)
+}
+
+/// Decodes the body of an attribute, which must be used up exactly. Names
+/// outside JVMS 17 are kept as `Attribute::Unknown`.
+pub(super) fn decode(
+    name: &str,
+    name_index: u16,
+    info: Vec<u8>,
+    pool: &[ConstantPoolEntry],
+) -> Result<Attribute, ClassFormatError> {
+    let length = info.len() as u32;
+    let wrong_size = || ClassFormatError::BadAttributeLength {
+        name: name.to_string(),
+        length,
+    };
+    let mut r = ClassReader::from_bytes(info);
+    let attribute = match decode_body(name, &mut r, pool) {
+        Ok(Some(attribute)) => attribute,
+        Ok(None) => {
+            return Ok(Attribute::Unknown {
+                name_index,
+                info: r.into_bytes(),
+            })
+        }
+        Err(ClassFormatError::Truncated { .. }) => return Err(wrong_size()),
+        Err(e) => return Err(e),
+    };
+    if r.has_more() {
+        return Err(wrong_size());
+    }
+    Ok(attribute)
+}
+
+fn decode_body(
+    name: &str,
+    r: &mut ClassReader,
+    pool: &[ConstantPoolEntry],
+) -> Result<Option<Attribute>, ClassFormatError> {
+    Ok(Some(match name {
+        "ConstantValue" => Attribute::ConstantValue(r.read_u2()?),
+        "StackMapTable" => Attribute::StackMapTable(table(r, read_frame)?),
+        "Exceptions" => Attribute::Exceptions(class_list(r, pool)?),
+        "InnerClasses" => Attribute::InnerClasses(table(r, |r| {
+            Ok(InnerClass {
+                inner_class_info_index: r.read_u2()?,
+                outer_class_info_index: r.read_u2()?,
+                inner_name_index: r.read_u2()?,
+                inner_class_access_flags: r.read_u2()?,
+            })
+        })?),
+        "EnclosingMethod" => {
+            let class_index = r.read_u2()?;
+            expect_class(pool, class_index)?;
+            Attribute::EnclosingMethod {
+                class_index,
+                method_index: r.read_u2()?,
+            }
+        }
+        "Synthetic" => Attribute::Synthetic,
+        "Signature" => Attribute::Signature(utf8(r, pool)?),
+        "SourceFile" => Attribute::SourceFile(utf8(r, pool)?),
+        "SourceDebugExtension" => {
+            let remaining = r.remaining();
+            Attribute::SourceDebugExtension(r.read_bytes(remaining)?.to_vec())
+        }
+        "LineNumberTable" => Attribute::LineNumberTable(table(r, |r| {
+            Ok(LineNumber {
+                start_pc: r.read_u2()?,
+                line_number: r.read_u2()?,
+            })
+        })?),
+        "LocalVariableTable" => Attribute::LocalVariableTable(table(r, read_local_variable)?),
+        "LocalVariableTypeTable" => {
+            Attribute::LocalVariableTypeTable(table(r, read_local_variable)?)
+        }
+        "Deprecated" => Attribute::Deprecated,
+        "RuntimeVisibleAnnotations" => {
+            Attribute::RuntimeVisibleAnnotations(table(r, read_annotation)?)
+        }
+        "RuntimeInvisibleAnnotations" => {
+            Attribute::RuntimeInvisibleAnnotations(table(r, read_annotation)?)
+        }
+        "RuntimeVisibleParameterAnnotations" => {
+            Attribute::RuntimeVisibleParameterAnnotations(parameter_annotations(r)?)
+        }
+        "RuntimeInvisibleParameterAnnotations" => {
+            Attribute::RuntimeInvisibleParameterAnnotations(parameter_annotations(r)?)
+        }
+        "RuntimeVisibleTypeAnnotations" => {
+            Attribute::RuntimeVisibleTypeAnnotations(table(r, read_type_annotation)?)
+        }
+        "RuntimeInvisibleTypeAnnotations" => {
+            Attribute::RuntimeInvisibleTypeAnnotations(table(r, read_type_annotation)?)
+        }
+        "AnnotationDefault" => Attribute::AnnotationDefault(read_element_value(r)?),
+        "BootstrapMethods" => Attribute::BootstrapMethods(table(r, |r| {
+            Ok(BootstrapMethod {
+                method_ref: r.read_u2()?,
+                arguments: table(r, ClassReader::read_u2)?,
+            })
+        })?),
+        "MethodParameters" => {
+            let count = r.read_u1()?;
+            let mut parameters = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                parameters.push(MethodParameter {
+                    name_index: r.read_u2()?,
+                    access_flags: r.read_u2()?,
+                });
+            }
+            Attribute::MethodParameters(parameters)
+        }
+        "Module" => Attribute::Module(Box::new(read_module(r)?)),
+        "ModulePackages" => Attribute::ModulePackages(table(r, ClassReader::read_u2)?),
+        "ModuleMainClass" => Attribute::ModuleMainClass(class(r, pool)?),
+        "NestHost" => Attribute::NestHost(class(r, pool)?),
+        "NestMembers" => Attribute::NestMembers(class_list(r, pool)?),
+        "Record" => Attribute::Record(table(r, |r| {
+            Ok(RecordComponent {
+                name_index: utf8(r, pool)?,
+                descriptor_index: utf8(r, pool)?,
+                attributes: table(r, |r| read(r, pool))?,
+            })
+        })?),
+        "PermittedSubclasses" => Attribute::PermittedSubclasses(class_list(r, pool)?),
+        _ => return Ok(None),
+    }))
+}
+
+/// A `u2` count followed by that many items.
+fn table<T>(
+    r: &mut ClassReader,
+    mut item: impl FnMut(&mut ClassReader) -> Result<T, ClassFormatError>,
+) -> Result<Vec<T>, ClassFormatError> {
+    let count = r.read_u2()?;
+    let mut items = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        items.push(item(r)?);
+    }
+    Ok(items)
+}
+
+fn utf8(r: &mut ClassReader, pool: &[ConstantPoolEntry]) -> Result<u16, ClassFormatError> {
+    let index = r.read_u2()?;
+    expect_utf8(pool, index)?;
+    Ok(index)
+}
+
+fn class(r: &mut ClassReader, pool: &[ConstantPoolEntry]) -> Result<u16, ClassFormatError> {
+    let index = r.read_u2()?;
+    expect_class(pool, index)?;
+    Ok(index)
+}
+
+fn class_list(
+    r: &mut ClassReader,
+    pool: &[ConstantPoolEntry],
+) -> Result<Vec<u16>, ClassFormatError> {
+    table(r, |r| class(r, pool))
+}
+
+fn read_frame(r: &mut ClassReader) -> Result<StackMapFrame, ClassFormatError> {
+    let frame_type = r.read_u1()?;
+    Ok(match frame_type {
+        0..=63 => StackMapFrame::Same {
+            offset_delta: u16::from(frame_type),
+        },
+        64..=127 => StackMapFrame::SameLocals1StackItem {
+            offset_delta: u16::from(frame_type - 64),
+            stack: read_verification_type(r)?,
+        },
+        247 => StackMapFrame::SameLocals1StackItem {
+            offset_delta: r.read_u2()?,
+            stack: read_verification_type(r)?,
+        },
+        248..=250 => StackMapFrame::Chop {
+            offset_delta: r.read_u2()?,
+            chopped: 251 - frame_type,
+        },
+        251 => StackMapFrame::Same {
+            offset_delta: r.read_u2()?,
+        },
+        252..=254 => {
+            let offset_delta = r.read_u2()?;
+            let mut locals = Vec::with_capacity(usize::from(frame_type - 251));
+            for _ in 251..frame_type {
+                locals.push(read_verification_type(r)?);
+            }
+            StackMapFrame::Append {
+                offset_delta,
+                locals,
+            }
+        }
+        255 => StackMapFrame::Full {
+            offset_delta: r.read_u2()?,
+            locals: table(r, read_verification_type)?,
+            stack: table(r, read_verification_type)?,
+        },
+        _ => {
+            return Err(ClassFormatError::Invalid(format!(
+                "Unknown StackMapTable frame type {}",
+                frame_type
+            )))
+        }
+    })
+}
+
+fn read_verification_type(r: &mut ClassReader) -> Result<VerificationType, ClassFormatError> {
+    let tag = r.read_u1()?;
+    Ok(match tag {
+        0 => VerificationType::Top,
+        1 => VerificationType::Integer,
+        2 => VerificationType::Float,
+        3 => VerificationType::Double,
+        4 => VerificationType::Long,
+        5 => VerificationType::Null,
+        6 => VerificationType::UninitializedThis,
+        7 => VerificationType::Object(r.read_u2()?),
+        8 => VerificationType::Uninitialized(r.read_u2()?),
+        _ => {
+            return Err(ClassFormatError::Invalid(format!(
+                "Unknown verification type tag {}",
+                tag
+            )))
+        }
+    })
+}
+
+fn read_local_variable(r: &mut ClassReader) -> Result<LocalVariable, ClassFormatError> {
+    Ok(LocalVariable {
+        start_pc: r.read_u2()?,
+        length: r.read_u2()?,
+        name_index: r.read_u2()?,
+        descriptor_index: r.read_u2()?,
+        index: r.read_u2()?,
+    })
+}
+
+fn read_annotation(r: &mut ClassReader) -> Result<Annotation, ClassFormatError> {
+    Ok(Annotation {
+        type_index: r.read_u2()?,
+        elements: table(r, |r| Ok((r.read_u2()?, read_element_value(r)?)))?,
+    })
+}
+
+fn read_element_value(r: &mut ClassReader) -> Result<ElementValue, ClassFormatError> {
+    let tag = r.read_u1()?;
+    Ok(match tag {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => ElementValue::Const {
+            tag,
+            index: r.read_u2()?,
+        },
+        b'e' => ElementValue::Enum {
+            type_name_index: r.read_u2()?,
+            const_name_index: r.read_u2()?,
+        },
+        b'c' => ElementValue::Class(r.read_u2()?),
+        b'@' => ElementValue::Annotation(Box::new(read_annotation(r)?)),
+        b'[' => ElementValue::Array(table(r, read_element_value)?),
+        _ => {
+            return Err(ClassFormatError::Invalid(format!(
+                "Unknown element_value tag {}",
+                tag
+            )))
+        }
+    })
+}
+
+fn parameter_annotations(r: &mut ClassReader) -> Result<Vec<Vec<Annotation>>, ClassFormatError> {
+    let count = r.read_u1()?;
+    let mut parameters = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        parameters.push(table(r, read_annotation)?);
+    }
+    Ok(parameters)
+}
+
+fn read_type_annotation(r: &mut ClassReader) -> Result<TypeAnnotation, ClassFormatError> {
+    let target_type = r.read_u1()?;
+    let target = match target_type {
+        0x00 | 0x01 => TypeAnnotationTarget::TypeParameter {
+            index: r.read_u1()?,
+        },
+        0x10 => TypeAnnotationTarget::Supertype {
+            index: r.read_u2()?,
+        },
+        0x11 | 0x12 => TypeAnnotationTarget::TypeParameterBound {
+            type_parameter_index: r.read_u1()?,
+            bound_index: r.read_u1()?,
+        },
+        0x13..=0x15 => TypeAnnotationTarget::Empty,
+        0x16 => TypeAnnotationTarget::FormalParameter {
+            index: r.read_u1()?,
+        },
+        0x17 => TypeAnnotationTarget::Throws {
+            type_index: r.read_u2()?,
+        },
+        0x40 | 0x41 => TypeAnnotationTarget::LocalVar(table(r, |r| {
+            Ok((r.read_u2()?, r.read_u2()?, r.read_u2()?))
+        })?),
+        0x42 => TypeAnnotationTarget::Catch {
+            exception_table_index: r.read_u2()?,
+        },
+        0x43..=0x46 => TypeAnnotationTarget::Offset {
+            offset: r.read_u2()?,
+        },
+        0x47..=0x4B => TypeAnnotationTarget::TypeArgument {
+            offset: r.read_u2()?,
+            type_argument_index: r.read_u1()?,
+        },
+        _ => {
+            return Err(ClassFormatError::Invalid(format!(
+                "Unknown type annotation target type 0x{:02x}",
+                target_type
+            )))
+        }
+    };
+    let path_length = r.read_u1()?;
+    let mut type_path = Vec::with_capacity(path_length as usize);
+    for _ in 0..path_length {
+        type_path.push((r.read_u1()?, r.read_u1()?));
+    }
+    Ok(TypeAnnotation {
+        target_type,
+        target,
+        type_path,
+        annotation: read_annotation(r)?,
+    })
+}
+
+fn read_module(r: &mut ClassReader) -> Result<Module, ClassFormatError> {
+    let package = |r: &mut ClassReader| {
+        Ok(ModulePackage {
+            package_index: r.read_u2()?,
+            flags: r.read_u2()?,
+            to: table(r, ClassReader::read_u2)?,
+        })
+    };
+    Ok(Module {
+        name_index: r.read_u2()?,
+        flags: r.read_u2()?,
+        version_index: r.read_u2()?,
+        requires: table(r, |r| {
+            Ok(ModuleRequires {
+                module_index: r.read_u2()?,
+                flags: r.read_u2()?,
+                version_index: r.read_u2()?,
+            })
+        })?,
+        exports: table(r, package)?,
+        opens: table(r, package)?,
+        uses: table(r, ClassReader::read_u2)?,
+        provides: table(r, |r| {
+            Ok(ModuleProvides {
+                service_index: r.read_u2()?,
+                with: table(r, ClassReader::read_u2)?,
+            })
+        })?,
+    })
+}
@@ -0,0 +1,215 @@
+use super::error::ClassFormatError;
+use super::parser::ConstantPoolEntry;
+use super::writer::{write_constant, ClassWriter};
+use std::collections::HashMap;
+
+/// The largest number of slots a pool can hold: `constant_pool_count` is a
+/// `u2` and counts the unused slot 0.
+const MAX_ENTRIES: usize = u16::MAX as usize - 1;
+
+/// Builds a constant pool in the layout `ClassFile::constant_pool` uses,
+/// handing out 1-based indices and reusing an equal entry when one exists.
+#[derive(Debug, Clone, Default)]
+pub struct ConstantPoolBuilder {
+    entries: Vec<ConstantPoolEntry>,
+    indices: HashMap<Vec<u8>, u16>,
+    overflowed: bool,
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues an existing pool, e.g. a parsed class's, without moving
+    /// any of its entries.
+    pub fn from_entries(entries: Vec<ConstantPoolEntry>) -> Self {
+        let mut indices = HashMap::new();
+        for (slot, entry) in entries.iter().enumerate() {
+            if !matches!(entry, ConstantPoolEntry::Unusable) {
+                indices.entry(key(entry)).or_insert(slot as u16 + 1);
+            }
+        }
+        Self {
+            entries,
+            indices,
+            overflowed: false,
+        }
+    }
+
+    /// The index of `entry`, appending it when the pool has no equal one.
+    /// Once the pool is full this returns 0 and `finish` fails.
+    pub fn add(&mut self, entry: ConstantPoolEntry) -> u16 {
+        let key = key(&entry);
+        if let Some(index) = self.indices.get(&key) {
+            return *index;
+        }
+        let wide = matches!(
+            entry,
+            ConstantPoolEntry::Long(_) | ConstantPoolEntry::Double(_)
+        );
+        let slots = if wide { 2 } else { 1 };
+        if self.entries.len() + slots > MAX_ENTRIES {
+            self.overflowed = true;
+            return 0;
+        }
+        self.entries.push(entry);
+        let index = self.entries.len() as u16;
+        if wide {
+            self.entries.push(ConstantPoolEntry::Unusable);
+        }
+        self.indices.insert(key, index);
+        index
+    }
+
+    pub fn utf8(&mut self, text: &str) -> u16 {
+        self.add(ConstantPoolEntry::Utf8(text.to_string()))
+    }
+
+    pub fn integer(&mut self, value: i32) -> u16 {
+        self.add(ConstantPoolEntry::Integer(value))
+    }
+
+    pub fn float(&mut self, value: f32) -> u16 {
+        self.add(ConstantPoolEntry::Float(value))
+    }
+
+    pub fn long(&mut self, value: i64) -> u16 {
+        self.add(ConstantPoolEntry::Long(value))
+    }
+
+    pub fn double(&mut self, value: f64) -> u16 {
+        self.add(ConstantPoolEntry::Double(value))
+    }
+
+    /// A `Class` entry for an internal name such as `java/lang/Object`.
+    pub fn class(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.add(ConstantPoolEntry::Class { name_index })
+    }
+
+    pub fn string(&mut self, value: &str) -> u16 {
+        let string_index = self.utf8(value);
+        self.add(ConstantPoolEntry::String { string_index })
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let name_index = self.utf8(name);
+        let descriptor_index = self.utf8(descriptor);
+        self.add(ConstantPoolEntry::NameAndType {
+            name_index,
+            descriptor_index,
+        })
+    }
+
+    pub fn field_ref(&mut self, class_index: u16, name_and_type_index: u16) -> u16 {
+        self.add(ConstantPoolEntry::FieldRef {
+            class_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn method_ref(&mut self, class_index: u16, name_and_type_index: u16) -> u16 {
+        self.add(ConstantPoolEntry::MethodRef {
+            class_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn interface_method_ref(&mut self, class_index: u16, name_and_type_index: u16) -> u16 {
+        self.add(ConstantPoolEntry::InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn method_handle(&mut self, reference_kind: u8, reference_index: u16) -> u16 {
+        self.add(ConstantPoolEntry::MethodHandle {
+            reference_kind,
+            reference_index,
+        })
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> u16 {
+        let descriptor_index = self.utf8(descriptor);
+        self.add(ConstantPoolEntry::MethodType { descriptor_index })
+    }
+
+    pub fn invoke_dynamic(
+        &mut self,
+        bootstrap_method_attr_index: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> u16 {
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolEntry::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn dynamic(
+        &mut self,
+        bootstrap_method_attr_index: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> u16 {
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolEntry::Dynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn module(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.add(ConstantPoolEntry::Module { name_index })
+    }
+
+    pub fn package(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.add(ConstantPoolEntry::Package { name_index })
+    }
+
+    /// The entry at a 1-based index.
+    pub fn get(&self, index: u16) -> Option<&ConstantPoolEntry> {
+        self.entries.get(index.checked_sub(1)? as usize)
+    }
+
+    pub fn entries(&self) -> &[ConstantPoolEntry] {
+        &self.entries
+    }
+
+    /// Slots in use, counting the second slot of each `Long` and `Double`.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn finish(self) -> Result<Vec<ConstantPoolEntry>, ClassFormatError> {
+        if self.overflowed {
+            return Err(ClassFormatError::Invalid(format!(
+                "Constant pool overflow: more than {} entries",
+                MAX_ENTRIES
+            )));
+        }
+        Ok(self.entries)
+    }
+}
+
+/// Two entries are the same constant when they serialize alike, which also
+/// keeps `0.0` and `-0.0`, or two NaNs with different bits, apart.
+fn key(entry: &ConstantPoolEntry) -> Vec<u8> {
+    match entry {
+        ConstantPoolEntry::Utf8(text) => [&[1u8][..], text.as_bytes()].concat(),
+        _ => {
+            let mut w = ClassWriter::new();
+            // Only Utf8 entries can fail to serialize.
+            let _ = write_constant(&mut w, 0, entry);
+            w.into_bytes()
+        }
+    }
+}
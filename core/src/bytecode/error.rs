@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// Why a class file was rejected, worded after HotSpot's
+/// `java.lang.ClassFormatError` messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassFormatError {
+    /// The file could not be read at all.
+    Io(String),
+    /// The data ended inside the structure being read at `offset`.
+    Truncated {
+        offset: usize,
+    },
+    BadMagic(u32),
+    /// A constant-pool entry has a tag this version does not define.
+    UnknownConstantTag {
+        index: u16,
+        tag: u8,
+    },
+    /// A constant-pool reference is 0, out of range, or names an entry of
+    /// the wrong kind.
+    BadConstantIndex {
+        index: u16,
+        expected: &'static str,
+    },
+    /// A `CONSTANT_Utf8` entry is not valid modified UTF-8.
+    IllegalUtf8 {
+        index: u16,
+    },
+    /// An attribute's declared length does not match its contents.
+    BadAttributeLength {
+        name: String,
+        length: u32,
+    },
+    /// Anything else the format forbids.
+    Invalid(String),
+}
+
+impl fmt::Display for ClassFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassFormatError::Io(message) => f.write_str(message),
+            ClassFormatError::Truncated { offset } => {
+                write!(f, "Truncated class file at offset {}", offset)
+            }
+            ClassFormatError::BadMagic(magic) => write!(f, "Incompatible magic value {}", magic),
+            ClassFormatError::UnknownConstantTag { index, tag } => {
+                write!(
+                    f,
+                    "Unknown constant tag {} at constant pool index {}",
+                    tag, index
+                )
+            }
+            ClassFormatError::BadConstantIndex { index, expected } => write!(
+                f,
+                "Invalid constant pool index {}: expected {}",
+                index, expected
+            ),
+            ClassFormatError::IllegalUtf8 { index } => {
+                write!(f, "Illegal UTF8 string in constant pool at index {}", index)
+            }
+            ClassFormatError::BadAttributeLength { name, length } => {
+                write!(f, "Wrong size {} for {} attribute", length, name)
+            }
+            ClassFormatError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ClassFormatError {}
@@ -1,2 +1,7 @@
+pub mod assembler;
+pub mod attributes;
+pub mod constant_pool;
+pub mod error;
 pub mod parser;
 pub mod reader;
+pub mod writer;
@@ -1,8 +1,10 @@
+use super::attributes::{self, Attribute};
+use super::error::ClassFormatError;
 use super::reader::ClassReader;
 
 pub const JAVA_MAGIC: u32 = 0xCAFEBABE;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConstantPoolEntry {
     Utf8(String),
     Integer(i32),
@@ -38,6 +40,10 @@ pub enum ConstantPoolEntry {
     MethodType {
         descriptor_index: u16,
     },
+    Dynamic {
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    },
     InvokeDynamic {
         bootstrap_method_attr_index: u16,
         name_and_type_index: u16,
@@ -49,10 +55,9 @@ pub enum ConstantPoolEntry {
         name_index: u16,
     },
     Unusable,
-    Unknown(u8),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ClassFile {
     pub magic: u32,
     pub minor_version: u16,
@@ -65,33 +70,27 @@ pub struct ClassFile {
     pub interfaces: Vec<u16>,
     pub fields: Vec<FieldInfo>,
     pub methods: Vec<MethodInfo>,
-    pub attributes: Vec<AttributeInfo>,
+    pub attributes: Vec<Attribute>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FieldInfo {
     pub access_flags: u16,
     pub name_index: u16,
     pub descriptor_index: u16,
-    pub attributes: Vec<AttributeInfo>,
+    pub attributes: Vec<Attribute>,
 }
 
-#[derive(Debug, Clone)]
-pub struct AttributeInfo {
-    pub name_index: u16,
-    pub info: Vec<u8>,
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MethodInfo {
     pub access_flags: u16,
     pub name_index: u16,
     pub descriptor_index: u16,
     pub code: Option<CodeAttribute>,
-    pub attributes: Vec<AttributeInfo>,
+    pub attributes: Vec<Attribute>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExceptionTableEntry {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -99,140 +98,81 @@ pub struct ExceptionTableEntry {
     pub catch_type: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CodeAttribute {
     pub max_stack: u16,
     pub max_locals: u16,
     pub code: Vec<u8>,
     pub exception_table: Vec<ExceptionTableEntry>,
-    pub attributes: Vec<AttributeInfo>,
+    pub attributes: Vec<Attribute>,
 }
 
 impl ClassFile {
-    pub fn parse(path: &str) -> Result<Self, String> {
-        let mut reader = ClassReader::from_file(path)
-            .map_err(|e| format!("Failed to read class file: {}", e))?;
+    pub fn parse(path: &str) -> Result<Self, ClassFormatError> {
+        let reader = ClassReader::from_file(path)
+            .map_err(|e| ClassFormatError::Io(format!("Failed to read class file: {}", e)))?;
+        Self::read(reader)
+    }
 
+    /// Parses a class file held in memory, e.g. one taken from a jar.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ClassFormatError> {
+        Self::read(ClassReader::from_bytes(bytes.to_vec()))
+    }
+
+    fn read(mut reader: ClassReader) -> Result<Self, ClassFormatError> {
         // Magic check
-        let magic = reader.read_u4();
+        let magic = reader.read_u4()?;
         if magic != JAVA_MAGIC {
-            return Err(format!("Invalid magic number: {:X}", magic));
+            return Err(ClassFormatError::BadMagic(magic));
         }
 
         // Version info
-        let minor_version = reader.read_u2();
-        let major_version = reader.read_u2();
+        let minor_version = reader.read_u2()?;
+        let major_version = reader.read_u2()?;
 
         // Constant pool
-        let constant_pool_count = reader.read_u2();
-        let mut constant_pool = Vec::with_capacity((constant_pool_count - 1) as usize);
-        let mut i = 1;
-
-        while i < constant_pool_count {
-            let tag = reader.read_u1();
-            let entry = match tag {
-                1 => {
-                    let length = reader.read_u2() as usize;
-                    let bytes: Vec<u8> = (0..length).map(|_| reader.read_u1()).collect();
-                    ConstantPoolEntry::Utf8(String::from_utf8_lossy(&bytes).to_string())
-                }
-                3 => ConstantPoolEntry::Integer(reader.read_u4() as i32),
-                4 => ConstantPoolEntry::Float(f32::from_bits(reader.read_u4())),
-                5 => {
-                    let high = reader.read_u4() as u64;
-                    let low = reader.read_u4() as u64;
-                    ConstantPoolEntry::Long(((high << 32) | low) as i64)
-                }
-                6 => {
-                    let high = reader.read_u4() as u64;
-                    let low = reader.read_u4() as u64;
-                    ConstantPoolEntry::Double(f64::from_bits((high << 32) | low))
-                }
-                7 => ConstantPoolEntry::Class {
-                    name_index: reader.read_u2(),
-                },
-                8 => ConstantPoolEntry::String {
-                    string_index: reader.read_u2(),
-                },
-                9 => ConstantPoolEntry::FieldRef {
-                    class_index: reader.read_u2(),
-                    name_and_type_index: reader.read_u2(),
-                },
-                10 => ConstantPoolEntry::MethodRef {
-                    class_index: reader.read_u2(),
-                    name_and_type_index: reader.read_u2(),
-                },
-                11 => ConstantPoolEntry::InterfaceMethodRef {
-                    class_index: reader.read_u2(),
-                    name_and_type_index: reader.read_u2(),
-                },
-                12 => ConstantPoolEntry::NameAndType {
-                    name_index: reader.read_u2(),
-                    descriptor_index: reader.read_u2(),
-                },
-                15 => ConstantPoolEntry::MethodHandle {
-                    reference_kind: reader.read_u1(),
-                    reference_index: reader.read_u2(),
-                },
-                16 => ConstantPoolEntry::MethodType {
-                    descriptor_index: reader.read_u2(),
-                },
-                18 => ConstantPoolEntry::InvokeDynamic {
-                    bootstrap_method_attr_index: reader.read_u2(),
-                    name_and_type_index: reader.read_u2(),
-                },
-                19 => ConstantPoolEntry::Module {
-                    name_index: reader.read_u2(),
-                },
-                20 => ConstantPoolEntry::Package {
-                    name_index: reader.read_u2(),
-                },
-                _ => ConstantPoolEntry::Unknown(tag),
-            };
-
-            let is_wide = matches!(
-                entry,
-                ConstantPoolEntry::Long(_) | ConstantPoolEntry::Double(_)
-            );
-            constant_pool.push(entry);
-            if is_wide {
-                constant_pool.push(ConstantPoolEntry::Unusable);
-                i += 2;
-            } else {
-                i += 1;
-            }
+        let constant_pool_count = reader.read_u2()?;
+        if constant_pool_count == 0 {
+            return Err(ClassFormatError::Invalid(
+                "Illegal constant pool size 0".to_string(),
+            ));
         }
+        let constant_pool = read_constant_pool(&mut reader, constant_pool_count)?;
+        check_constant_pool(&constant_pool, major_version)?;
+        let pool = constant_pool.as_slice();
 
         // Class info
-        let access_flags = reader.read_u2();
-        let this_class = reader.read_u2();
-        let super_class = reader.read_u2();
+        let access_flags = reader.read_u2()?;
+        let this_class = reader.read_u2()?;
+        expect_class(pool, this_class)?;
+        let super_class = reader.read_u2()?;
+        if super_class != 0 {
+            expect_class(pool, super_class)?;
+        }
 
         // Interfaces
-        let interfaces_count = reader.read_u2();
+        let interfaces_count = reader.read_u2()?;
         let mut interfaces = Vec::with_capacity(interfaces_count as usize);
         for _ in 0..interfaces_count {
-            interfaces.push(reader.read_u2());
+            let interface = reader.read_u2()?;
+            expect_class(pool, interface)?;
+            interfaces.push(interface);
         }
 
         // Fields
-        let fields_count = reader.read_u2();
+        let fields_count = reader.read_u2()?;
         let mut fields = Vec::with_capacity(fields_count as usize);
         for _ in 0..fields_count {
-            let access_flags = reader.read_u2();
-            let name_index = reader.read_u2();
-            let descriptor_index = reader.read_u2();
-            let attributes_count = reader.read_u2();
+            let access_flags = reader.read_u2()?;
+            let name_index = reader.read_u2()?;
+            expect_utf8(pool, name_index)?;
+            let descriptor_index = reader.read_u2()?;
+            expect_utf8(pool, descriptor_index)?;
+            let attributes_count = reader.read_u2()?;
 
             let mut attributes = Vec::with_capacity(attributes_count as usize);
             for _ in 0..attributes_count {
-                let name_index = reader.read_u2();
-                let attr_len = reader.read_u4();
-                let mut info = vec![0u8; attr_len as usize];
-                for i in 0..attr_len as usize {
-                    info[i] = reader.read_u1();
-                }
-                attributes.push(AttributeInfo { name_index, info });
+                attributes.push(attributes::read(&mut reader, pool)?);
             }
 
             fields.push(FieldInfo {
@@ -244,84 +184,31 @@ impl ClassFile {
         }
 
         // Methods
-        let methods_count = reader.read_u2();
+        let methods_count = reader.read_u2()?;
         let mut methods = Vec::with_capacity(methods_count as usize);
 
         for _ in 0..methods_count {
-            let access_flags = reader.read_u2();
-            let name_index = reader.read_u2();
-            let descriptor_index = reader.read_u2();
-            let attributes_count = reader.read_u2();
+            let access_flags = reader.read_u2()?;
+            let name_index = reader.read_u2()?;
+            expect_utf8(pool, name_index)?;
+            let descriptor_index = reader.read_u2()?;
+            expect_utf8(pool, descriptor_index)?;
+            let attributes_count = reader.read_u2()?;
 
             let mut code: Option<CodeAttribute> = None;
             let mut attributes = Vec::with_capacity(attributes_count as usize);
 
             for _ in 0..attributes_count {
-                let name_index_attr = reader.read_u2();
-                let attr_len = reader.read_u4();
-
-                let attr_name = if let Some(ConstantPoolEntry::Utf8(s)) =
-                    constant_pool.get((name_index_attr - 1) as usize)
-                {
-                    s.clone()
-                } else {
-                    String::new()
-                };
-
-                if attr_name == "Code" {
-                    let max_stack = reader.read_u2();
-                    let max_locals = reader.read_u2();
-                    let code_length = reader.read_u4() as usize;
-
-                    let mut code_bytes = vec![0u8; code_length];
-                    for i in 0..code_length {
-                        code_bytes[i] = reader.read_u1();
-                    }
-
-                    // Exception table
-                    let ex_table_len = reader.read_u2();
-                    let mut ex_table = Vec::with_capacity(ex_table_len as usize);
-                    for _ in 0..ex_table_len {
-                        ex_table.push(ExceptionTableEntry {
-                            start_pc: reader.read_u2(),
-                            end_pc: reader.read_u2(),
-                            handler_pc: reader.read_u2(),
-                            catch_type: reader.read_u2(),
-                        });
-                    }
-
-                    // Nested attributes
-                    let code_attr_count = reader.read_u2();
-                    let mut code_attrs = Vec::with_capacity(code_attr_count as usize);
-                    for _ in 0..code_attr_count {
-                        let sub_name_index = reader.read_u2();
-                        let sub_len = reader.read_u4();
-                        let mut info = vec![0u8; sub_len as usize];
-                        for i in 0..sub_len as usize {
-                            info[i] = reader.read_u1();
-                        }
-                        code_attrs.push(AttributeInfo {
-                            name_index: sub_name_index,
-                            info,
-                        });
+                let (name, name_index, info) = attributes::read_raw(&mut reader, pool)?;
+                if name == "Code" {
+                    if code.is_some() {
+                        return Err(ClassFormatError::Invalid(
+                            "Multiple Code attributes in method".to_string(),
+                        ));
                     }
-
-                    code = Some(CodeAttribute {
-                        max_stack,
-                        max_locals,
-                        code: code_bytes,
-                        exception_table: ex_table,
-                        attributes: code_attrs,
-                    });
+                    code = Some(read_code(&info, pool)?);
                 } else {
-                    let mut info = vec![0u8; attr_len as usize];
-                    for i in 0..attr_len as usize {
-                        info[i] = reader.read_u1();
-                    }
-                    attributes.push(AttributeInfo {
-                        name_index: name_index_attr,
-                        info,
-                    });
+                    attributes.push(attributes::decode(name, name_index, info, pool)?);
                 }
             }
 
@@ -335,16 +222,17 @@ impl ClassFile {
         }
 
         // Class attributes
-        let attributes_count = reader.read_u2();
+        let attributes_count = reader.read_u2()?;
         let mut attributes = Vec::with_capacity(attributes_count as usize);
         for _ in 0..attributes_count {
-            let name_index = reader.read_u2();
-            let attr_len = reader.read_u4();
-            let mut info = vec![0u8; attr_len as usize];
-            for i in 0..attr_len as usize {
-                info[i] = reader.read_u1();
-            }
-            attributes.push(AttributeInfo { name_index, info });
+            attributes.push(attributes::read(&mut reader, pool)?);
+        }
+
+        if reader.has_more() {
+            return Err(ClassFormatError::Invalid(format!(
+                "Extra bytes at the end of class file at offset {}",
+                reader.position()
+            )));
         }
 
         Ok(Self {
@@ -363,11 +251,71 @@ impl ClassFile {
         })
     }
 
+    /// Repeats the checks parsing makes between the parts of a class, for
+    /// one rebuilt from elsewhere, such as a shared archive.
+    pub fn check(&self) -> Result<(), ClassFormatError> {
+        let pool = self.constant_pool.as_slice();
+        if usize::from(self.constant_pool_count) != pool.len() + 1 {
+            return Err(ClassFormatError::Invalid(format!(
+                "Illegal constant pool size {}",
+                self.constant_pool_count
+            )));
+        }
+        for (slot, entry) in pool.iter().enumerate() {
+            let after_wide = slot
+                .checked_sub(1)
+                .and_then(|previous| pool.get(previous))
+                .is_some_and(|previous| {
+                    matches!(
+                        previous,
+                        ConstantPoolEntry::Long(_) | ConstantPoolEntry::Double(_)
+                    )
+                });
+            if after_wide != matches!(entry, ConstantPoolEntry::Unusable) {
+                return Err(ClassFormatError::Invalid(format!(
+                    "Invalid constant pool entry {}",
+                    slot + 1
+                )));
+            }
+        }
+        check_constant_pool(pool, self.major_version)?;
+        expect_class(pool, self.this_class)?;
+        if self.super_class != 0 {
+            expect_class(pool, self.super_class)?;
+        }
+        for &interface in &self.interfaces {
+            expect_class(pool, interface)?;
+        }
+        for field in &self.fields {
+            expect_utf8(pool, field.name_index)?;
+            expect_utf8(pool, field.descriptor_index)?;
+        }
+        for method in &self.methods {
+            expect_utf8(pool, method.name_index)?;
+            expect_utf8(pool, method.descriptor_index)?;
+            let Some(code) = &method.code else {
+                continue;
+            };
+            if code.code.is_empty() || code.code.len() > 0xffff {
+                return Err(ClassFormatError::Invalid(format!(
+                    "Invalid method Code length {}",
+                    code.code.len()
+                )));
+            }
+            for entry in &code.exception_table {
+                if entry.catch_type != 0 {
+                    expect_class(pool, entry.catch_type)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_name_and_type(&self, index: u16) -> Option<(&str, &str)> {
         if let Some(ConstantPoolEntry::NameAndType {
             name_index,
             descriptor_index,
-        }) = self.constant_pool.get((index - 1) as usize)
+        }) = self.constant(index)
         {
             let name = self.get_utf8(*name_index)?;
             let desc = self.get_utf8(*descriptor_index)?;
@@ -377,3 +325,319 @@ impl ClassFile {
         }
     }
 }
+
+fn read_constant_pool(
+    reader: &mut ClassReader,
+    count: u16,
+) -> Result<Vec<ConstantPoolEntry>, ClassFormatError> {
+    let mut constant_pool = Vec::with_capacity((count - 1) as usize);
+    let mut i = 1;
+
+    while i < count {
+        let tag = reader.read_u1()?;
+        let entry = match tag {
+            1 => {
+                let length = reader.read_u2()? as usize;
+                let text = decode_modified_utf8(reader.read_bytes(length)?)
+                    .ok_or(ClassFormatError::IllegalUtf8 { index: i })?;
+                ConstantPoolEntry::Utf8(text)
+            }
+            3 => ConstantPoolEntry::Integer(reader.read_u4()? as i32),
+            4 => ConstantPoolEntry::Float(f32::from_bits(reader.read_u4()?)),
+            5 => {
+                let high = reader.read_u4()? as u64;
+                let low = reader.read_u4()? as u64;
+                ConstantPoolEntry::Long(((high << 32) | low) as i64)
+            }
+            6 => {
+                let high = reader.read_u4()? as u64;
+                let low = reader.read_u4()? as u64;
+                ConstantPoolEntry::Double(f64::from_bits((high << 32) | low))
+            }
+            7 => ConstantPoolEntry::Class {
+                name_index: reader.read_u2()?,
+            },
+            8 => ConstantPoolEntry::String {
+                string_index: reader.read_u2()?,
+            },
+            9 => ConstantPoolEntry::FieldRef {
+                class_index: reader.read_u2()?,
+                name_and_type_index: reader.read_u2()?,
+            },
+            10 => ConstantPoolEntry::MethodRef {
+                class_index: reader.read_u2()?,
+                name_and_type_index: reader.read_u2()?,
+            },
+            11 => ConstantPoolEntry::InterfaceMethodRef {
+                class_index: reader.read_u2()?,
+                name_and_type_index: reader.read_u2()?,
+            },
+            12 => ConstantPoolEntry::NameAndType {
+                name_index: reader.read_u2()?,
+                descriptor_index: reader.read_u2()?,
+            },
+            15 => ConstantPoolEntry::MethodHandle {
+                reference_kind: reader.read_u1()?,
+                reference_index: reader.read_u2()?,
+            },
+            16 => ConstantPoolEntry::MethodType {
+                descriptor_index: reader.read_u2()?,
+            },
+            17 => ConstantPoolEntry::Dynamic {
+                bootstrap_method_attr_index: reader.read_u2()?,
+                name_and_type_index: reader.read_u2()?,
+            },
+            18 => ConstantPoolEntry::InvokeDynamic {
+                bootstrap_method_attr_index: reader.read_u2()?,
+                name_and_type_index: reader.read_u2()?,
+            },
+            19 => ConstantPoolEntry::Module {
+                name_index: reader.read_u2()?,
+            },
+            20 => ConstantPoolEntry::Package {
+                name_index: reader.read_u2()?,
+            },
+            _ => return Err(ClassFormatError::UnknownConstantTag { index: i, tag }),
+        };
+
+        let is_wide = matches!(
+            entry,
+            ConstantPoolEntry::Long(_) | ConstantPoolEntry::Double(_)
+        );
+        constant_pool.push(entry);
+        if is_wide {
+            if i == count - 1 {
+                return Err(ClassFormatError::Invalid(format!(
+                    "Invalid constant pool entry {}: 8-byte constant in the last slot",
+                    i
+                )));
+            }
+            constant_pool.push(ConstantPoolEntry::Unusable);
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    Ok(constant_pool)
+}
+
+/// Checks that every reference between constant-pool entries points at an
+/// entry of the kind JVMS 4.4 requires.
+fn check_constant_pool(
+    pool: &[ConstantPoolEntry],
+    major_version: u16,
+) -> Result<(), ClassFormatError> {
+    use ConstantPoolEntry as C;
+
+    for (slot, entry) in pool.iter().enumerate() {
+        match entry {
+            C::Class { name_index } | C::Module { name_index } | C::Package { name_index } => {
+                expect(pool, *name_index, "Utf8", is_utf8)?
+            }
+            C::String { string_index } => expect(pool, *string_index, "Utf8", is_utf8)?,
+            C::MethodType { descriptor_index } => expect(pool, *descriptor_index, "Utf8", is_utf8)?,
+            C::NameAndType {
+                name_index,
+                descriptor_index,
+            } => {
+                expect(pool, *name_index, "Utf8", is_utf8)?;
+                expect(pool, *descriptor_index, "Utf8", is_utf8)?;
+            }
+            C::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | C::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | C::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                expect_class(pool, *class_index)?;
+                expect(pool, *name_and_type_index, "NameAndType", is_name_and_type)?;
+            }
+            C::Dynamic {
+                name_and_type_index,
+                ..
+            }
+            | C::InvokeDynamic {
+                name_and_type_index,
+                ..
+            } => expect(pool, *name_and_type_index, "NameAndType", is_name_and_type)?,
+            C::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => {
+                let index = *reference_index;
+                match reference_kind {
+                    1..=4 => expect(pool, index, "Fieldref", |e| matches!(e, C::FieldRef { .. }))?,
+                    5 | 8 => expect(pool, index, "Methodref", |e| {
+                        matches!(e, C::MethodRef { .. })
+                    })?,
+                    6 | 7 if major_version >= 52 => {
+                        expect(pool, index, "Methodref or InterfaceMethodref", |e| {
+                            matches!(e, C::MethodRef { .. } | C::InterfaceMethodRef { .. })
+                        })?
+                    }
+                    6 | 7 => expect(pool, index, "Methodref", |e| {
+                        matches!(e, C::MethodRef { .. })
+                    })?,
+                    9 => expect(pool, index, "InterfaceMethodref", |e| {
+                        matches!(e, C::InterfaceMethodRef { .. })
+                    })?,
+                    _ => {
+                        return Err(ClassFormatError::Invalid(format!(
+                            "Bad method handle kind {} at constant pool index {}",
+                            reference_kind,
+                            slot + 1
+                        )))
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn expect(
+    pool: &[ConstantPoolEntry],
+    index: u16,
+    expected: &'static str,
+    is_kind: impl Fn(&ConstantPoolEntry) -> bool,
+) -> Result<(), ClassFormatError> {
+    match index
+        .checked_sub(1)
+        .and_then(|slot| pool.get(slot as usize))
+    {
+        Some(entry) if is_kind(entry) => Ok(()),
+        _ => Err(ClassFormatError::BadConstantIndex { index, expected }),
+    }
+}
+
+fn is_utf8(entry: &ConstantPoolEntry) -> bool {
+    matches!(entry, ConstantPoolEntry::Utf8(_))
+}
+
+fn is_name_and_type(entry: &ConstantPoolEntry) -> bool {
+    matches!(entry, ConstantPoolEntry::NameAndType { .. })
+}
+
+pub(super) fn expect_utf8(
+    pool: &[ConstantPoolEntry],
+    index: u16,
+) -> Result<&str, ClassFormatError> {
+    match index
+        .checked_sub(1)
+        .and_then(|slot| pool.get(slot as usize))
+    {
+        Some(ConstantPoolEntry::Utf8(text)) => Ok(text),
+        _ => Err(ClassFormatError::BadConstantIndex {
+            index,
+            expected: "Utf8",
+        }),
+    }
+}
+
+pub(super) fn expect_class(pool: &[ConstantPoolEntry], index: u16) -> Result<(), ClassFormatError> {
+    expect(pool, index, "Class", |e| {
+        matches!(e, ConstantPoolEntry::Class { .. })
+    })
+}
+
+/// Parses the body of a `Code` attribute, which must be used up exactly.
+fn read_code(info: &[u8], pool: &[ConstantPoolEntry]) -> Result<CodeAttribute, ClassFormatError> {
+    let wrong_size = || ClassFormatError::BadAttributeLength {
+        name: "Code".to_string(),
+        length: info.len() as u32,
+    };
+    let mut reader = ClassReader::from_bytes(info.to_vec());
+    let mut body = || -> Result<CodeAttribute, ClassFormatError> {
+        let max_stack = reader.read_u2()?;
+        let max_locals = reader.read_u2()?;
+        let code_length = reader.read_u4()? as usize;
+        if code_length == 0 || code_length > 0xffff {
+            return Err(ClassFormatError::Invalid(format!(
+                "Invalid method Code length {}",
+                code_length
+            )));
+        }
+        let code = reader.read_bytes(code_length)?.to_vec();
+
+        // Exception table
+        let ex_table_len = reader.read_u2()?;
+        let mut exception_table = Vec::with_capacity(ex_table_len as usize);
+        for _ in 0..ex_table_len {
+            let entry = ExceptionTableEntry {
+                start_pc: reader.read_u2()?,
+                end_pc: reader.read_u2()?,
+                handler_pc: reader.read_u2()?,
+                catch_type: reader.read_u2()?,
+            };
+            if entry.catch_type != 0 {
+                expect_class(pool, entry.catch_type)?;
+            }
+            exception_table.push(entry);
+        }
+
+        // Nested attributes
+        let code_attr_count = reader.read_u2()?;
+        let mut attributes = Vec::with_capacity(code_attr_count as usize);
+        for _ in 0..code_attr_count {
+            attributes.push(attributes::read(&mut reader, pool)?);
+        }
+
+        Ok(CodeAttribute {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        })
+    };
+    let code = body().map_err(|e| match e {
+        ClassFormatError::Truncated { .. } => wrong_size(),
+        other => other,
+    })?;
+    if reader.has_more() {
+        return Err(wrong_size());
+    }
+    Ok(code)
+}
+
+/// Decodes a `CONSTANT_Utf8` body (JVMS 4.4.7): no zero bytes, no four-byte
+/// forms, and every multi-byte sequence complete. Unpaired surrogates,
+/// which Rust strings cannot hold, become U+FFFD.
+fn decode_modified_utf8(bytes: &[u8]) -> Option<String> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    let continuation = |i: usize| match bytes.get(i) {
+        Some(&b) if b & 0xc0 == 0x80 => Some(u16::from(b & 0x3f)),
+        _ => None,
+    };
+    while i < bytes.len() {
+        let lead = bytes[i];
+        match lead {
+            0x01..=0x7f => {
+                units.push(u16::from(lead));
+                i += 1;
+            }
+            0xc0..=0xdf => {
+                units.push((u16::from(lead & 0x1f) << 6) | continuation(i + 1)?);
+                i += 2;
+            }
+            0xe0..=0xef => {
+                units.push(
+                    (u16::from(lead & 0x0f) << 12)
+                        | (continuation(i + 1)? << 6)
+                        | continuation(i + 2)?,
+                );
+                i += 3;
+            }
+            _ => return None,
+        }
+    }
+    Some(String::from_utf16_lossy(&units))
+}
@@ -1,6 +1,9 @@
+use super::error::ClassFormatError;
 use std::fs::File;
 use std::io::{self, Read};
 
+/// A big-endian cursor over class file bytes. Every read is bounds
+/// checked and fails with `ClassFormatError::Truncated` past the end.
 #[derive(Debug)]
 pub struct ClassReader {
     data: Vec<u8>,
@@ -25,58 +28,40 @@ impl ClassReader {
         }
     }
 
-    pub fn read_u1(&mut self) -> u8 {
-        if self.remaining() < 1 {
-            panic!(
-                "read_u1() out of bounds: pos={} len={}",
-                self.position,
-                self.data.len()
-            );
-        }
-        let val = self.data[self.position];
-        self.position += 1;
-        val
+    pub fn read_u1(&mut self) -> Result<u8, ClassFormatError> {
+        Ok(self.read_bytes(1)?[0])
     }
 
-    pub fn read_u2(&mut self) -> u16 {
-        if self.remaining() < 2 {
-            panic!(
-                "read_u2() out of bounds: pos={} len={}",
-                self.position,
-                self.data.len()
-            );
-        }
-        let bytes = [self.read_u1(), self.read_u1()];
-        u16::from_be_bytes(bytes)
+    pub fn read_u2(&mut self) -> Result<u16, ClassFormatError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
     }
 
-    pub fn read_u4(&mut self) -> u32 {
-        if self.remaining() < 4 {
-            panic!(
-                "read_u4() out of bounds: pos={} len={}",
-                self.position,
-                self.data.len()
-            );
-        }
-        let bytes = [
-            self.read_u1(),
-            self.read_u1(),
-            self.read_u1(),
-            self.read_u1(),
-        ];
-        u32::from_be_bytes(bytes)
+    pub fn read_u4(&mut self) -> Result<u32, ClassFormatError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
 
-    pub fn skip(&mut self, n: usize) {
+    /// The next `n` bytes. Checked before anything is allocated, so a
+    /// hostile length cannot exhaust memory.
+    pub fn read_bytes(&mut self, n: usize) -> Result<&[u8], ClassFormatError> {
         if self.remaining() < n {
-            panic!(
-                "skip({}) out of bounds: pos={} len={}",
-                n,
-                self.position,
-                self.data.len()
-            );
+            return Err(ClassFormatError::Truncated {
+                offset: self.position,
+            });
         }
+        let start = self.position;
         self.position += n;
+        Ok(&self.data[start..self.position])
+    }
+
+    pub fn skip(&mut self, n: usize) -> Result<(), ClassFormatError> {
+        self.read_bytes(n).map(|_| ())
+    }
+
+    /// The underlying bytes, whatever has been read.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
     }
 
     pub fn position(&self) -> usize {
@@ -87,6 +72,10 @@ impl ClassReader {
         self.data.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn remaining(&self) -> usize {
         self.data.len().saturating_sub(self.position)
     }
@@ -96,19 +85,21 @@ impl ClassReader {
     }
 
     pub fn dump_bytes(&self, count: usize) {
-        let end = usize::min(self.position + count, self.data.len());
-        let slice = &self.data[self.position..end];
-        print!("[{}..{}] ", self.position, end);
+        let start = self.position.min(self.data.len());
+        let end = usize::min(start + count, self.data.len());
+        let slice = &self.data[start..end];
+        print!("[{}..{}] ", start, end);
         for b in slice {
             print!("{:02X} ", b);
         }
         println!();
     }
 
-    pub fn seek(&mut self, pos: usize) {
+    pub fn seek(&mut self, pos: usize) -> Result<(), ClassFormatError> {
         if pos > self.data.len() {
-            panic!("seek({}) out of bounds: len={}", pos, self.data.len());
+            return Err(ClassFormatError::Truncated { offset: pos });
         }
         self.position = pos;
+        Ok(())
     }
 }
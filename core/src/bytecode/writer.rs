@@ -0,0 +1,658 @@
+use super::attributes::{
+    Annotation, Attribute, ElementValue, Module, StackMapFrame, TypeAnnotation,
+    TypeAnnotationTarget, VerificationType,
+};
+use super::error::ClassFormatError;
+use super::parser::{ClassFile, CodeAttribute, ConstantPoolEntry};
+
+/// A big-endian byte sink, the counterpart of `ClassReader`.
+#[derive(Debug, Default)]
+pub struct ClassWriter {
+    data: Vec<u8>,
+}
+
+impl ClassWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_u1(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_u2(&mut self, value: u16) {
+        self.data.extend(value.to_be_bytes());
+    }
+
+    pub fn write_u4(&mut self, value: u32) {
+        self.data.extend(value.to_be_bytes());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// A `u2` count, failing when `len` does not fit.
+    pub fn write_count(&mut self, len: usize, what: &str) -> Result<(), ClassFormatError> {
+        let count = u16::try_from(len)
+            .map_err(|_| ClassFormatError::Invalid(format!("Too many {}: {}", what, len)))?;
+        self.write_u2(count);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl ClassFile {
+    /// Serializes the class. Typed attributes are written under the first
+    /// `Utf8` constant spelling their name, which must exist, so
+    /// `ClassFile::from_bytes(&class.to_bytes()?)` gives back `class`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ClassFormatError> {
+        let pool = self.constant_pool.as_slice();
+        let mut w = ClassWriter::new();
+        w.write_u4(self.magic);
+        w.write_u2(self.minor_version);
+        w.write_u2(self.major_version);
+        w.write_count(pool.len() + 1, "constant pool entries")?;
+        write_constant_pool(&mut w, pool)?;
+
+        w.write_u2(self.access_flags);
+        w.write_u2(self.this_class);
+        w.write_u2(self.super_class);
+        w.write_count(self.interfaces.len(), "interfaces")?;
+        for interface in &self.interfaces {
+            w.write_u2(*interface);
+        }
+
+        w.write_count(self.fields.len(), "fields")?;
+        for field in &self.fields {
+            w.write_u2(field.access_flags);
+            w.write_u2(field.name_index);
+            w.write_u2(field.descriptor_index);
+            write_attributes(&mut w, &field.attributes, pool)?;
+        }
+
+        w.write_count(self.methods.len(), "methods")?;
+        for method in &self.methods {
+            w.write_u2(method.access_flags);
+            w.write_u2(method.name_index);
+            w.write_u2(method.descriptor_index);
+            let count = method.attributes.len() + usize::from(method.code.is_some());
+            w.write_count(count, "method attributes")?;
+            if let Some(code) = &method.code {
+                w.write_u2(name_index(pool, "Code")?);
+                write_length_prefixed(&mut w, |body| write_code(body, code, pool))?;
+            }
+            for attribute in &method.attributes {
+                write_attribute(&mut w, attribute, pool)?;
+            }
+        }
+
+        write_attributes(&mut w, &self.attributes, pool)?;
+        Ok(w.into_bytes())
+    }
+}
+
+fn write_constant_pool(
+    w: &mut ClassWriter,
+    pool: &[ConstantPoolEntry],
+) -> Result<(), ClassFormatError> {
+    for (slot, entry) in pool.iter().enumerate() {
+        write_constant(w, slot + 1, entry)?;
+    }
+    Ok(())
+}
+
+/// Writes one `cp_info`; `index` only goes into error messages.
+pub(super) fn write_constant(
+    w: &mut ClassWriter,
+    index: usize,
+    entry: &ConstantPoolEntry,
+) -> Result<(), ClassFormatError> {
+    use ConstantPoolEntry as C;
+
+    match entry {
+        C::Utf8(text) => {
+            let bytes = encode_modified_utf8(text);
+            let length = u16::try_from(bytes.len()).map_err(|_| {
+                ClassFormatError::Invalid(format!(
+                    "UTF8 string too long at constant pool index {}",
+                    index
+                ))
+            })?;
+            w.write_u1(1);
+            w.write_u2(length);
+            w.write_bytes(&bytes);
+        }
+        C::Integer(value) => {
+            w.write_u1(3);
+            w.write_u4(*value as u32);
+        }
+        C::Float(value) => {
+            w.write_u1(4);
+            w.write_u4(value.to_bits());
+        }
+        C::Long(value) => {
+            w.write_u1(5);
+            w.write_bytes(&value.to_be_bytes());
+        }
+        C::Double(value) => {
+            w.write_u1(6);
+            w.write_bytes(&value.to_bits().to_be_bytes());
+        }
+        C::Class { name_index } => {
+            w.write_u1(7);
+            w.write_u2(*name_index);
+        }
+        C::String { string_index } => {
+            w.write_u1(8);
+            w.write_u2(*string_index);
+        }
+        C::FieldRef {
+            class_index,
+            name_and_type_index,
+        } => {
+            w.write_u1(9);
+            w.write_u2(*class_index);
+            w.write_u2(*name_and_type_index);
+        }
+        C::MethodRef {
+            class_index,
+            name_and_type_index,
+        } => {
+            w.write_u1(10);
+            w.write_u2(*class_index);
+            w.write_u2(*name_and_type_index);
+        }
+        C::InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        } => {
+            w.write_u1(11);
+            w.write_u2(*class_index);
+            w.write_u2(*name_and_type_index);
+        }
+        C::NameAndType {
+            name_index,
+            descriptor_index,
+        } => {
+            w.write_u1(12);
+            w.write_u2(*name_index);
+            w.write_u2(*descriptor_index);
+        }
+        C::MethodHandle {
+            reference_kind,
+            reference_index,
+        } => {
+            w.write_u1(15);
+            w.write_u1(*reference_kind);
+            w.write_u2(*reference_index);
+        }
+        C::MethodType { descriptor_index } => {
+            w.write_u1(16);
+            w.write_u2(*descriptor_index);
+        }
+        C::Dynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => {
+            w.write_u1(17);
+            w.write_u2(*bootstrap_method_attr_index);
+            w.write_u2(*name_and_type_index);
+        }
+        C::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => {
+            w.write_u1(18);
+            w.write_u2(*bootstrap_method_attr_index);
+            w.write_u2(*name_and_type_index);
+        }
+        C::Module { name_index } => {
+            w.write_u1(19);
+            w.write_u2(*name_index);
+        }
+        C::Package { name_index } => {
+            w.write_u1(20);
+            w.write_u2(*name_index);
+        }
+        // The second slot of a Long or Double has no bytes of its own.
+        C::Unusable => {}
+    }
+    Ok(())
+}
+
+/// Looks up the `Utf8` constant an attribute is written under.
+fn name_index(pool: &[ConstantPoolEntry], name: &str) -> Result<u16, ClassFormatError> {
+    pool.iter()
+        .position(|entry| matches!(entry, ConstantPoolEntry::Utf8(text) if text == name))
+        .map(|slot| slot as u16 + 1)
+        .ok_or_else(|| {
+            ClassFormatError::Invalid(format!("No Utf8 constant for attribute {}", name))
+        })
+}
+
+/// Writes a `u4` length followed by whatever `body` writes.
+fn write_length_prefixed(
+    w: &mut ClassWriter,
+    body: impl FnOnce(&mut ClassWriter) -> Result<(), ClassFormatError>,
+) -> Result<(), ClassFormatError> {
+    let mut inner = ClassWriter::new();
+    body(&mut inner)?;
+    let length = u32::try_from(inner.len())
+        .map_err(|_| ClassFormatError::Invalid("Attribute too long".to_string()))?;
+    w.write_u4(length);
+    w.write_bytes(&inner.into_bytes());
+    Ok(())
+}
+
+fn write_attributes(
+    w: &mut ClassWriter,
+    attributes: &[Attribute],
+    pool: &[ConstantPoolEntry],
+) -> Result<(), ClassFormatError> {
+    w.write_count(attributes.len(), "attributes")?;
+    for attribute in attributes {
+        write_attribute(w, attribute, pool)?;
+    }
+    Ok(())
+}
+
+fn write_attribute(
+    w: &mut ClassWriter,
+    attribute: &Attribute,
+    pool: &[ConstantPoolEntry],
+) -> Result<(), ClassFormatError> {
+    let index = match (attribute, attribute.name()) {
+        (Attribute::Unknown { name_index, .. }, _) => *name_index,
+        (_, Some(name)) => name_index(pool, name)?,
+        (_, None) => unreachable!("only Unknown attributes are unnamed"),
+    };
+    w.write_u2(index);
+    write_length_prefixed(w, |w| write_attribute_body(w, attribute, pool))
+}
+
+fn write_attribute_body(
+    w: &mut ClassWriter,
+    attribute: &Attribute,
+    pool: &[ConstantPoolEntry],
+) -> Result<(), ClassFormatError> {
+    match attribute {
+        Attribute::ConstantValue(index)
+        | Attribute::Signature(index)
+        | Attribute::SourceFile(index)
+        | Attribute::ModuleMainClass(index)
+        | Attribute::NestHost(index) => w.write_u2(*index),
+        Attribute::StackMapTable(frames) => {
+            w.write_count(frames.len(), "stack map frames")?;
+            for frame in frames {
+                write_frame(w, frame)?;
+            }
+        }
+        Attribute::Exceptions(indices)
+        | Attribute::ModulePackages(indices)
+        | Attribute::NestMembers(indices)
+        | Attribute::PermittedSubclasses(indices) => write_u2_table(w, indices)?,
+        Attribute::InnerClasses(classes) => {
+            w.write_count(classes.len(), "inner classes")?;
+            for class in classes {
+                w.write_u2(class.inner_class_info_index);
+                w.write_u2(class.outer_class_info_index);
+                w.write_u2(class.inner_name_index);
+                w.write_u2(class.inner_class_access_flags);
+            }
+        }
+        Attribute::EnclosingMethod {
+            class_index,
+            method_index,
+        } => {
+            w.write_u2(*class_index);
+            w.write_u2(*method_index);
+        }
+        Attribute::Synthetic | Attribute::Deprecated => {}
+        Attribute::SourceDebugExtension(bytes) | Attribute::Unknown { info: bytes, .. } => {
+            w.write_bytes(bytes)
+        }
+        Attribute::LineNumberTable(lines) => {
+            w.write_count(lines.len(), "line numbers")?;
+            for line in lines {
+                w.write_u2(line.start_pc);
+                w.write_u2(line.line_number);
+            }
+        }
+        Attribute::LocalVariableTable(locals) | Attribute::LocalVariableTypeTable(locals) => {
+            w.write_count(locals.len(), "local variables")?;
+            for local in locals {
+                w.write_u2(local.start_pc);
+                w.write_u2(local.length);
+                w.write_u2(local.name_index);
+                w.write_u2(local.descriptor_index);
+                w.write_u2(local.index);
+            }
+        }
+        Attribute::RuntimeVisibleAnnotations(annotations)
+        | Attribute::RuntimeInvisibleAnnotations(annotations) => write_annotations(w, annotations)?,
+        Attribute::RuntimeVisibleParameterAnnotations(parameters)
+        | Attribute::RuntimeInvisibleParameterAnnotations(parameters) => {
+            let count = u8::try_from(parameters.len()).map_err(|_| {
+                ClassFormatError::Invalid("Too many annotated parameters".to_string())
+            })?;
+            w.write_u1(count);
+            for annotations in parameters {
+                write_annotations(w, annotations)?;
+            }
+        }
+        Attribute::RuntimeVisibleTypeAnnotations(annotations)
+        | Attribute::RuntimeInvisibleTypeAnnotations(annotations) => {
+            w.write_count(annotations.len(), "type annotations")?;
+            for annotation in annotations {
+                write_type_annotation(w, annotation)?;
+            }
+        }
+        Attribute::AnnotationDefault(value) => write_element_value(w, value)?,
+        Attribute::BootstrapMethods(methods) => {
+            w.write_count(methods.len(), "bootstrap methods")?;
+            for method in methods {
+                w.write_u2(method.method_ref);
+                write_u2_table(w, &method.arguments)?;
+            }
+        }
+        Attribute::MethodParameters(parameters) => {
+            let count = u8::try_from(parameters.len())
+                .map_err(|_| ClassFormatError::Invalid("Too many method parameters".to_string()))?;
+            w.write_u1(count);
+            for parameter in parameters {
+                w.write_u2(parameter.name_index);
+                w.write_u2(parameter.access_flags);
+            }
+        }
+        Attribute::Module(module) => write_module(w, module)?,
+        Attribute::Record(components) => {
+            w.write_count(components.len(), "record components")?;
+            for component in components {
+                w.write_u2(component.name_index);
+                w.write_u2(component.descriptor_index);
+                write_attributes(w, &component.attributes, pool)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_code(
+    w: &mut ClassWriter,
+    code: &CodeAttribute,
+    pool: &[ConstantPoolEntry],
+) -> Result<(), ClassFormatError> {
+    w.write_u2(code.max_stack);
+    w.write_u2(code.max_locals);
+    w.write_u4(code.code.len() as u32);
+    w.write_bytes(&code.code);
+    w.write_count(code.exception_table.len(), "exception handlers")?;
+    for entry in &code.exception_table {
+        w.write_u2(entry.start_pc);
+        w.write_u2(entry.end_pc);
+        w.write_u2(entry.handler_pc);
+        w.write_u2(entry.catch_type);
+    }
+    write_attributes(w, &code.attributes, pool)
+}
+
+fn write_u2_table(w: &mut ClassWriter, values: &[u16]) -> Result<(), ClassFormatError> {
+    w.write_count(values.len(), "table entries")?;
+    for value in values {
+        w.write_u2(*value);
+    }
+    Ok(())
+}
+
+/// Picks the shortest encoding JVMS 4.7.4 allows for the frame.
+fn write_frame(w: &mut ClassWriter, frame: &StackMapFrame) -> Result<(), ClassFormatError> {
+    match frame {
+        StackMapFrame::Same { offset_delta } if *offset_delta <= 63 => {
+            w.write_u1(*offset_delta as u8)
+        }
+        StackMapFrame::Same { offset_delta } => {
+            w.write_u1(251);
+            w.write_u2(*offset_delta);
+        }
+        StackMapFrame::SameLocals1StackItem {
+            offset_delta,
+            stack,
+        } => {
+            if *offset_delta <= 63 {
+                w.write_u1(64 + *offset_delta as u8);
+            } else {
+                w.write_u1(247);
+                w.write_u2(*offset_delta);
+            }
+            write_verification_type(w, stack);
+        }
+        StackMapFrame::Chop {
+            offset_delta,
+            chopped,
+        } => {
+            if !(1..=3).contains(chopped) {
+                return Err(ClassFormatError::Invalid(format!(
+                    "Cannot chop {} locals in one frame",
+                    chopped
+                )));
+            }
+            w.write_u1(251 - chopped);
+            w.write_u2(*offset_delta);
+        }
+        StackMapFrame::Append {
+            offset_delta,
+            locals,
+        } => {
+            if !(1..=3).contains(&locals.len()) {
+                return Err(ClassFormatError::Invalid(format!(
+                    "Cannot append {} locals in one frame",
+                    locals.len()
+                )));
+            }
+            w.write_u1(251 + locals.len() as u8);
+            w.write_u2(*offset_delta);
+            for local in locals {
+                write_verification_type(w, local);
+            }
+        }
+        StackMapFrame::Full {
+            offset_delta,
+            locals,
+            stack,
+        } => {
+            w.write_u1(255);
+            w.write_u2(*offset_delta);
+            w.write_count(locals.len(), "frame locals")?;
+            for local in locals {
+                write_verification_type(w, local);
+            }
+            w.write_count(stack.len(), "frame stack entries")?;
+            for item in stack {
+                write_verification_type(w, item);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_verification_type(w: &mut ClassWriter, ty: &VerificationType) {
+    match ty {
+        VerificationType::Top => w.write_u1(0),
+        VerificationType::Integer => w.write_u1(1),
+        VerificationType::Float => w.write_u1(2),
+        VerificationType::Double => w.write_u1(3),
+        VerificationType::Long => w.write_u1(4),
+        VerificationType::Null => w.write_u1(5),
+        VerificationType::UninitializedThis => w.write_u1(6),
+        VerificationType::Object(index) => {
+            w.write_u1(7);
+            w.write_u2(*index);
+        }
+        VerificationType::Uninitialized(offset) => {
+            w.write_u1(8);
+            w.write_u2(*offset);
+        }
+    }
+}
+
+fn write_annotations(
+    w: &mut ClassWriter,
+    annotations: &[Annotation],
+) -> Result<(), ClassFormatError> {
+    w.write_count(annotations.len(), "annotations")?;
+    for annotation in annotations {
+        write_annotation(w, annotation)?;
+    }
+    Ok(())
+}
+
+fn write_annotation(w: &mut ClassWriter, annotation: &Annotation) -> Result<(), ClassFormatError> {
+    w.write_u2(annotation.type_index);
+    w.write_count(annotation.elements.len(), "annotation elements")?;
+    for (name_index, value) in &annotation.elements {
+        w.write_u2(*name_index);
+        write_element_value(w, value)?;
+    }
+    Ok(())
+}
+
+fn write_element_value(w: &mut ClassWriter, value: &ElementValue) -> Result<(), ClassFormatError> {
+    match value {
+        ElementValue::Const { tag, index } => {
+            w.write_u1(*tag);
+            w.write_u2(*index);
+        }
+        ElementValue::Enum {
+            type_name_index,
+            const_name_index,
+        } => {
+            w.write_u1(b'e');
+            w.write_u2(*type_name_index);
+            w.write_u2(*const_name_index);
+        }
+        ElementValue::Class(index) => {
+            w.write_u1(b'c');
+            w.write_u2(*index);
+        }
+        ElementValue::Annotation(annotation) => {
+            w.write_u1(b'@');
+            write_annotation(w, annotation)?;
+        }
+        ElementValue::Array(values) => {
+            w.write_u1(b'[');
+            w.write_count(values.len(), "array elements")?;
+            for value in values {
+                write_element_value(w, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_type_annotation(
+    w: &mut ClassWriter,
+    annotation: &TypeAnnotation,
+) -> Result<(), ClassFormatError> {
+    w.write_u1(annotation.target_type);
+    match &annotation.target {
+        TypeAnnotationTarget::TypeParameter { index }
+        | TypeAnnotationTarget::FormalParameter { index } => w.write_u1(*index),
+        TypeAnnotationTarget::Supertype { index } => w.write_u2(*index),
+        TypeAnnotationTarget::TypeParameterBound {
+            type_parameter_index,
+            bound_index,
+        } => {
+            w.write_u1(*type_parameter_index);
+            w.write_u1(*bound_index);
+        }
+        TypeAnnotationTarget::Empty => {}
+        TypeAnnotationTarget::Throws { type_index } => w.write_u2(*type_index),
+        TypeAnnotationTarget::LocalVar(ranges) => {
+            w.write_count(ranges.len(), "local variable ranges")?;
+            for (start_pc, length, index) in ranges {
+                w.write_u2(*start_pc);
+                w.write_u2(*length);
+                w.write_u2(*index);
+            }
+        }
+        TypeAnnotationTarget::Catch {
+            exception_table_index,
+        } => w.write_u2(*exception_table_index),
+        TypeAnnotationTarget::Offset { offset } => w.write_u2(*offset),
+        TypeAnnotationTarget::TypeArgument {
+            offset,
+            type_argument_index,
+        } => {
+            w.write_u2(*offset);
+            w.write_u1(*type_argument_index);
+        }
+    }
+    let path_length = u8::try_from(annotation.type_path.len())
+        .map_err(|_| ClassFormatError::Invalid("Type path too long".to_string()))?;
+    w.write_u1(path_length);
+    for (kind, argument) in &annotation.type_path {
+        w.write_u1(*kind);
+        w.write_u1(*argument);
+    }
+    write_annotation(w, &annotation.annotation)
+}
+
+fn write_module(w: &mut ClassWriter, module: &Module) -> Result<(), ClassFormatError> {
+    w.write_u2(module.name_index);
+    w.write_u2(module.flags);
+    w.write_u2(module.version_index);
+    w.write_count(module.requires.len(), "module requires")?;
+    for requires in &module.requires {
+        w.write_u2(requires.module_index);
+        w.write_u2(requires.flags);
+        w.write_u2(requires.version_index);
+    }
+    for packages in [&module.exports, &module.opens] {
+        w.write_count(packages.len(), "module packages")?;
+        for package in packages {
+            w.write_u2(package.package_index);
+            w.write_u2(package.flags);
+            write_u2_table(w, &package.to)?;
+        }
+    }
+    write_u2_table(w, &module.uses)?;
+    w.write_count(module.provides.len(), "module provides")?;
+    for provides in &module.provides {
+        w.write_u2(provides.service_index);
+        write_u2_table(w, &provides.with)?;
+    }
+    Ok(())
+}
+
+/// Encodes a string as a `CONSTANT_Utf8` body: UTF-16 units, with NUL
+/// written as two bytes, as `decode_modified_utf8` expects.
+pub(super) fn encode_modified_utf8(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for unit in text.encode_utf16() {
+        match unit {
+            0x01..=0x7f => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    bytes
+}
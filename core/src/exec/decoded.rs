@@ -0,0 +1,71 @@
+use crate::exec::instructions::Instruction;
+
+/// A method's code decoded once up front. Instructions are addressed by
+/// index; branch offsets are resolved to the index of their target, and
+/// the original byte offsets are kept for exception tables and
+/// diagnostics.
+#[derive(Debug, Clone)]
+pub struct DecodedCode {
+    instructions: Vec<Instruction>,
+    /// Byte offset of each instruction, followed by the code length.
+    offsets: Vec<usize>,
+    /// Branch target of each instruction. `None` for non-branches and for
+    /// offsets that do not start an instruction.
+    targets: Vec<Option<usize>>,
+}
+
+impl DecodedCode {
+    pub fn decode(code: &[u8]) -> Self {
+        let mut instructions = Vec::new();
+        let mut offsets = Vec::new();
+        let mut pc = 0usize;
+        while pc < code.len() {
+            offsets.push(pc);
+            instructions.push(Instruction::from_bytecode(code, &mut pc));
+        }
+        offsets.push(code.len());
+
+        let mut decoded = Self {
+            targets: vec![None; instructions.len()],
+            instructions,
+            offsets,
+        };
+        for ip in 0..decoded.instructions.len() {
+            if let Some(offset) = decoded.instructions[ip].branch_offset() {
+                let target = decoded.offsets[ip] as isize + offset as isize;
+                decoded.targets[ip] = usize::try_from(target)
+                    .ok()
+                    .and_then(|target| decoded.index_of(target));
+            }
+        }
+        decoded
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    pub fn instruction(&self, ip: usize) -> Instruction {
+        self.instructions[ip]
+    }
+
+    /// Byte offset of instruction `ip`; `len()` maps to the code length.
+    pub fn offset(&self, ip: usize) -> usize {
+        self.offsets[ip]
+    }
+
+    /// Index of the instruction a branch at `ip` jumps to.
+    pub fn target(&self, ip: usize) -> Option<usize> {
+        self.targets[ip]
+    }
+
+    /// Index of the instruction starting at byte `offset`. The code length
+    /// maps to `len()`, one past the last instruction.
+    pub fn index_of(&self, offset: usize) -> Option<usize> {
+        self.offsets.binary_search(&offset).ok()
+    }
+}
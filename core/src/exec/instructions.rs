@@ -1,30 +1,100 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub enum Instruction {
     // Constants & Loads
+    AConstNull,
     IConst(i32),
+    LConst(i64),
+    FConst(f32),
+    DConst(f64),
     BiPush(i8),
     SiPush(i16),
     Ldc(u8),
     LdcW(u16),
     Ldc2W(u16),
 
-    ILoad(u8),
-    IStore(u8),
-    ALoad(u8),
-    AStore(u8),
+    ILoad(u16),
+    LLoad(u16),
+    FLoad(u16),
+    DLoad(u16),
+    IStore(u16),
+    LStore(u16),
+    FStore(u16),
+    DStore(u16),
+    ALoad(u16),
+    AStore(u16),
 
     // Stack ops
     Dup,
     Dup2,
     DupX1,
     DupX2,
+    Dup2X1,
+    Dup2X2,
     Pop,
+    Pop2,
+    Swap,
 
     // Arithmetic
     IAdd,
+    LAdd,
+    FAdd,
+    DAdd,
     ISub,
+    LSub,
+    FSub,
+    DSub,
     IMul,
+    LMul,
+    FMul,
+    DMul,
     IDiv,
+    LDiv,
+    FDiv,
+    DDiv,
+    IRem,
+    LRem,
+    FRem,
+    DRem,
+    INeg,
+    LNeg,
+    FNeg,
+    DNeg,
+
+    // Bitwise
+    IShl,
+    LShl,
+    IShr,
+    LShr,
+    IUShr,
+    LUShr,
+    IAnd,
+    LAnd,
+    IOr,
+    LOr,
+    IXor,
+    LXor,
+
+    // Conversions & comparisons
+    I2L,
+    I2F,
+    I2D,
+    L2I,
+    L2F,
+    L2D,
+    F2I,
+    F2L,
+    F2D,
+    D2I,
+    D2L,
+    D2F,
+    I2B,
+    I2C,
+    I2S,
+    LCmp,
+    FCmpL,
+    FCmpG,
+    DCmpL,
+    DCmpG,
 
     // Control flow
     Goto(i16),
@@ -40,9 +110,11 @@ pub enum Instruction {
     IfICmpGe(i16),
     IfICmpGt(i16),
     IfICmpLe(i16),
+    IfACmpEq(i16),
+    IfACmpNe(i16),
     IfNull(i16),
     IfNonNull(i16),
-    IInc(u8, i8),
+    IInc(u16, i16),
 
     // Field & Method
     GetStatic(u16),
@@ -57,12 +129,15 @@ pub enum Instruction {
 
     // Object & Return
     New(u16),
+    CheckCast(u16),
+    InstanceOf(u16),
     IReturn,
     LReturn,
     FReturn,
     DReturn,
     AReturn,
     Return,
+    AThrow,
 
     //Arrays
     NewArray(u8),
@@ -70,15 +145,51 @@ pub enum Instruction {
     ArrayLength,
 
     IALoad,
-    IAStore,
+    LALoad,
+    FALoad,
+    DALoad,
     AALoad,
+    BALoad,
+    CALoad,
+    SALoad,
+    IAStore,
+    LAStore,
+    FAStore,
+    DAStore,
     AAStore,
+    BAStore,
+    CAStore,
+    SAStore,
 
     // Fallback
     Unknown(u8),
 }
 
 impl Instruction {
+    /// Relative offset of a branch instruction, from its own opcode.
+    pub fn branch_offset(&self) -> Option<i16> {
+        match *self {
+            Instruction::Goto(offset)
+            | Instruction::IfEq(offset)
+            | Instruction::IfNe(offset)
+            | Instruction::IfLt(offset)
+            | Instruction::IfGe(offset)
+            | Instruction::IfGt(offset)
+            | Instruction::IfLe(offset)
+            | Instruction::IfICmpEq(offset)
+            | Instruction::IfICmpNe(offset)
+            | Instruction::IfICmpLt(offset)
+            | Instruction::IfICmpGe(offset)
+            | Instruction::IfICmpGt(offset)
+            | Instruction::IfICmpLe(offset)
+            | Instruction::IfACmpEq(offset)
+            | Instruction::IfACmpNe(offset)
+            | Instruction::IfNull(offset)
+            | Instruction::IfNonNull(offset) => Some(offset),
+            _ => None,
+        }
+    }
+
     pub fn from_bytecode(code: &[u8], pc: &mut usize) -> Self {
         if *pc >= code.len() {
             return Instruction::Unknown(0xFF);
@@ -118,8 +229,12 @@ impl Instruction {
 
         match opcode {
             // --- Constants ---
+            0x01 => Instruction::AConstNull,
             0x02 => Instruction::IConst(-1),
             0x03..=0x08 => Instruction::IConst((opcode - 0x03) as i32), // iconst_0..iconst_5
+            0x09 | 0x0A => Instruction::LConst((opcode - 0x09) as i64),
+            0x0B..=0x0D => Instruction::FConst((opcode - 0x0B) as f32),
+            0x0E | 0x0F => Instruction::DConst((opcode - 0x0E) as f64),
             0x10 => Instruction::BiPush(read_u8!() as i8),
             0x11 => {
                 let high = read_u8!() as i16;
@@ -131,20 +246,32 @@ impl Instruction {
             0x14 => Instruction::Ldc2W(read_u16!()),
 
             // --- Load / Store ---
-            0x15 => Instruction::ILoad(read_u8!()),
-            0x36 => Instruction::IStore(read_u8!()),
-            0x19 => Instruction::ALoad(read_u8!()),
-            0x3A => Instruction::AStore(read_u8!()),
+            0x15 => Instruction::ILoad(read_u8!() as u16),
+            0x16 => Instruction::LLoad(read_u8!() as u16),
+            0x17 => Instruction::FLoad(read_u8!() as u16),
+            0x18 => Instruction::DLoad(read_u8!() as u16),
+            0x36 => Instruction::IStore(read_u8!() as u16),
+            0x37 => Instruction::LStore(read_u8!() as u16),
+            0x38 => Instruction::FStore(read_u8!() as u16),
+            0x39 => Instruction::DStore(read_u8!() as u16),
+            0x19 => Instruction::ALoad(read_u8!() as u16),
+            0x3A => Instruction::AStore(read_u8!() as u16),
 
             0x1A => Instruction::ILoad(0),
             0x1B => Instruction::ILoad(1),
             0x1C => Instruction::ILoad(2),
             0x1D => Instruction::ILoad(3),
+            0x1E..=0x21 => Instruction::LLoad((opcode - 0x1E) as u16),
+            0x22..=0x25 => Instruction::FLoad((opcode - 0x22) as u16),
+            0x26..=0x29 => Instruction::DLoad((opcode - 0x26) as u16),
 
             0x3B => Instruction::IStore(0),
             0x3C => Instruction::IStore(1),
             0x3D => Instruction::IStore(2),
             0x3E => Instruction::IStore(3),
+            0x3F..=0x42 => Instruction::LStore((opcode - 0x3F) as u16),
+            0x43..=0x46 => Instruction::FStore((opcode - 0x43) as u16),
+            0x47..=0x4A => Instruction::DStore((opcode - 0x47) as u16),
 
             0x2A => Instruction::ALoad(0),
             0x2B => Instruction::ALoad(1),
@@ -161,18 +288,97 @@ impl Instruction {
             0x5A => Instruction::DupX1,
             0x5B => Instruction::DupX2,
             0x5C => Instruction::Dup2,
+            0x5D => Instruction::Dup2X1,
+            0x5E => Instruction::Dup2X2,
             0x57 => Instruction::Pop,
+            0x58 => Instruction::Pop2,
+            0x5F => Instruction::Swap,
 
             // --- Arithmetic ---
             0x60 => Instruction::IAdd,
+            0x61 => Instruction::LAdd,
+            0x62 => Instruction::FAdd,
+            0x63 => Instruction::DAdd,
             0x64 => Instruction::ISub,
+            0x65 => Instruction::LSub,
+            0x66 => Instruction::FSub,
+            0x67 => Instruction::DSub,
             0x68 => Instruction::IMul,
+            0x69 => Instruction::LMul,
+            0x6A => Instruction::FMul,
+            0x6B => Instruction::DMul,
             0x6C => Instruction::IDiv,
+            0x6D => Instruction::LDiv,
+            0x6E => Instruction::FDiv,
+            0x6F => Instruction::DDiv,
+            0x70 => Instruction::IRem,
+            0x71 => Instruction::LRem,
+            0x72 => Instruction::FRem,
+            0x73 => Instruction::DRem,
+            0x74 => Instruction::INeg,
+            0x75 => Instruction::LNeg,
+            0x76 => Instruction::FNeg,
+            0x77 => Instruction::DNeg,
+
+            // --- Bitwise ---
+            0x78 => Instruction::IShl,
+            0x79 => Instruction::LShl,
+            0x7A => Instruction::IShr,
+            0x7B => Instruction::LShr,
+            0x7C => Instruction::IUShr,
+            0x7D => Instruction::LUShr,
+            0x7E => Instruction::IAnd,
+            0x7F => Instruction::LAnd,
+            0x80 => Instruction::IOr,
+            0x81 => Instruction::LOr,
+            0x82 => Instruction::IXor,
+            0x83 => Instruction::LXor,
+
+            // --- Conversions & comparisons ---
+            0x85 => Instruction::I2L,
+            0x86 => Instruction::I2F,
+            0x87 => Instruction::I2D,
+            0x88 => Instruction::L2I,
+            0x89 => Instruction::L2F,
+            0x8A => Instruction::L2D,
+            0x8B => Instruction::F2I,
+            0x8C => Instruction::F2L,
+            0x8D => Instruction::F2D,
+            0x8E => Instruction::D2I,
+            0x8F => Instruction::D2L,
+            0x90 => Instruction::D2F,
+            0x91 => Instruction::I2B,
+            0x92 => Instruction::I2C,
+            0x93 => Instruction::I2S,
+            0x94 => Instruction::LCmp,
+            0x95 => Instruction::FCmpL,
+            0x96 => Instruction::FCmpG,
+            0x97 => Instruction::DCmpL,
+            0x98 => Instruction::DCmpG,
             0x84 => {
-                let index = read_u8!();
-                let val = read_u8!() as i8;
+                let index = read_u8!() as u16;
+                let val = read_u8!() as i8 as i16;
                 Instruction::IInc(index, val)
             }
+            // wide: the same loads, stores and iinc with 16-bit operands.
+            0xC4 => {
+                let modified = read_u8!();
+                let index = read_u16!();
+                match modified {
+                    0x15 => Instruction::ILoad(index),
+                    0x16 => Instruction::LLoad(index),
+                    0x17 => Instruction::FLoad(index),
+                    0x18 => Instruction::DLoad(index),
+                    0x19 => Instruction::ALoad(index),
+                    0x36 => Instruction::IStore(index),
+                    0x37 => Instruction::LStore(index),
+                    0x38 => Instruction::FStore(index),
+                    0x39 => Instruction::DStore(index),
+                    0x3A => Instruction::AStore(index),
+                    0x84 => Instruction::IInc(index, read_i16!()),
+                    _ => Instruction::Unknown(opcode),
+                }
+            }
 
             // --- Control flow ---
             0xA7 => Instruction::Goto(read_i16!()),
@@ -188,6 +394,8 @@ impl Instruction {
             0xA2 => Instruction::IfICmpGe(read_i16!()),
             0xA3 => Instruction::IfICmpGt(read_i16!()),
             0xA4 => Instruction::IfICmpLe(read_i16!()),
+            0xA5 => Instruction::IfACmpEq(read_i16!()),
+            0xA6 => Instruction::IfACmpNe(read_i16!()),
             0xC6 => Instruction::IfNull(read_i16!()),
             0xC7 => Instruction::IfNonNull(read_i16!()),
 
@@ -219,10 +427,22 @@ impl Instruction {
             0xBE => Instruction::ArrayLength,
 
             0x2E => Instruction::IALoad,
+            0x2F => Instruction::LALoad,
+            0x30 => Instruction::FALoad,
+            0x31 => Instruction::DALoad,
             0x32 => Instruction::AALoad,
+            0x33 => Instruction::BALoad,
+            0x34 => Instruction::CALoad,
+            0x35 => Instruction::SALoad,
 
             0x4F => Instruction::IAStore,
+            0x50 => Instruction::LAStore,
+            0x51 => Instruction::FAStore,
+            0x52 => Instruction::DAStore,
             0x53 => Instruction::AAStore,
+            0x54 => Instruction::BAStore,
+            0x55 => Instruction::CAStore,
+            0x56 => Instruction::SAStore,
 
             // --- Return ---
             0xAC => Instruction::IReturn,
@@ -231,6 +451,9 @@ impl Instruction {
             0xAF => Instruction::DReturn,
             0xB0 => Instruction::AReturn,
             0xB1 => Instruction::Return,
+            0xBF => Instruction::AThrow,
+            0xC0 => Instruction::CheckCast(read_u16!()),
+            0xC1 => Instruction::InstanceOf(read_u16!()),
 
             // --- Fallback ---
             _ => Instruction::Unknown(opcode),
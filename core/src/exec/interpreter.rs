@@ -1,52 +1,593 @@
-use crate::bytecode::parser::{AttributeInfo, ClassFile, ConstantPoolEntry, MethodInfo};
+use crate::bytecode::parser::{ClassFile, CodeAttribute, ConstantPoolEntry};
+use crate::exec::decoded::DecodedCode;
 use crate::exec::instructions::Instruction;
-use crate::loader::class_loader::ClassLoader;
-use crate::native::invoke_native;
+use crate::exec::runtime_class::{CallSite, MethodTarget, RuntimeClass};
+use crate::jdwp::{Agent, Context};
+use crate::jit::runtime::{self as jit_runtime, JitOutcome};
+use crate::jit::{self, CompiledMethod, JitMode};
+use crate::loader::class_loader::{self, ClassLoader, LoadError, LoaderId};
+use crate::native::jni;
+use crate::native::jni::library::{self, NativeLibraries};
+use crate::native::registry::{self, NativeMethod, NativeRegistry};
+use crate::native::{
+    self, java_lang_class, java_lang_invoke, java_lang_object, java_lang_system, java_lang_thread,
+    java_lang_throwable, NativeEnv,
+};
+use crate::runtime::assertions::AssertionStatus;
 use crate::runtime::frame::Frame;
-use crate::runtime::gc::Gc;
+use crate::runtime::gc::{self, Gc};
 use crate::runtime::heap::{Heap, HeapValue};
+use crate::runtime::signals;
 use crate::runtime::stack::Stack;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+/// One active Java method, as seen by stack traces and the debugger.
+#[derive(Clone)]
+pub(crate) struct CallRecord {
+    pub class: Rc<RuntimeClass>,
+    pub method: usize,
+    /// Where the method is and its locals as of its last call out; only
+    /// kept up while a debugger agent is installed.
+    pub pc: usize,
+    pub locals: Vec<HeapValue>,
+}
+
+/// How execution continues after an instruction. Exceptions are left
+/// pending on the interpreter rather than reported here.
+pub(crate) enum Flow {
+    Next,
+    /// Give up on the method without an exception, as after a link failure.
+    Abort,
+}
+
+const ACC_STATIC: u16 = 0x0008;
+const ACC_NATIVE: u16 = 0x0100;
 
 pub struct Interpreter {
     debug_mode: bool,
+    pending_exception: RefCell<Option<HeapValue>>,
+    call_stack: RefCell<Vec<CallRecord>>,
+    shutdown_hooks: RefCell<Vec<HeapValue>>,
+    /// Set once the hooks start running; they can no longer change.
+    shutting_down: Cell<bool>,
+    /// References of frames and native calls suspended while they call
+    /// out, which collections further in must keep alive.
+    suspended_roots: RefCell<Vec<u64>>,
+    natives: RefCell<NativeRegistry>,
+    /// Libraries loaded by `System.load` and `System.loadLibrary`.
+    libraries: NativeLibraries,
+    /// System properties, seeded from the host and `-D` options.
+    properties: RefCell<HashMap<String, String>>,
+    /// Classes prepared for execution, by name.
+    runtime_classes: RefCell<HashMap<String, Rc<RuntimeClass>>>,
+    /// Bumped whenever native bindings change or a class is redefined, so
+    /// that linked call sites relink on their next use.
+    link_epoch: Cell<u64>,
+    jit_mode: Cell<JitMode>,
+    /// Compiled methods by the classes they depend on, invalidated when a
+    /// subclass of one of those classes is loaded.
+    jit_dependents: RefCell<HashMap<String, Vec<Weak<CompiledMethod>>>>,
+    /// Methods compiled so far; numbers `-XX:+PrintCompilation` lines.
+    compilations: Cell<u32>,
+    print_compilation: Cell<bool>,
+    assertions: RefCell<AssertionStatus>,
+    /// The `java/lang/Class` object of each class asked for, by name.
+    class_mirrors: RefCell<HashMap<String, HeapValue>>,
+    /// Generated proxy classes by the interfaces they implement.
+    proxy_classes: RefCell<HashMap<Vec<String>, String>>,
+    /// Resolved `CONSTANT_MethodHandle`, `CONSTANT_MethodType` and
+    /// `CONSTANT_Dynamic` entries, by class and constant-pool index.
+    resolved_constants: RefCell<HashMap<(String, u16), HeapValue>>,
+    /// The JDWP agent, from `-agentlib:jdwp`.
+    debugger: RefCell<Option<Agent>>,
+    /// Whether the agent is installed, checked before each instruction.
+    debugging: Cell<bool>,
+    /// Runtime names of the classes prepared while debugging, in order.
+    prepared: RefCell<Vec<String>>,
 }
 
 impl Interpreter {
     pub fn new(debug_mode: bool) -> Self {
-        Self { debug_mode }
+        Self {
+            debug_mode,
+            pending_exception: RefCell::new(None),
+            call_stack: RefCell::new(Vec::new()),
+            shutdown_hooks: RefCell::new(Vec::new()),
+            shutting_down: Cell::new(false),
+            suspended_roots: RefCell::new(Vec::new()),
+            natives: RefCell::new(NativeRegistry::with_builtins()),
+            libraries: NativeLibraries::default(),
+            properties: RefCell::new(java_lang_system::default_properties()),
+            runtime_classes: RefCell::new(HashMap::new()),
+            link_epoch: Cell::new(0),
+            jit_mode: Cell::new(if jit::is_supported() {
+                JitMode::Mixed
+            } else {
+                JitMode::Interpreted
+            }),
+            jit_dependents: RefCell::new(HashMap::new()),
+            compilations: Cell::new(0),
+            print_compilation: Cell::new(false),
+            assertions: RefCell::new(AssertionStatus::default()),
+            class_mirrors: RefCell::new(HashMap::new()),
+            proxy_classes: RefCell::new(HashMap::new()),
+            resolved_constants: RefCell::new(HashMap::new()),
+            debugger: RefCell::new(None),
+            debugging: Cell::new(false),
+            prepared: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Binds a Rust function to `class_name.method_name descriptor`. It is
+    /// used for methods declared `native` in loaded classes and takes
+    /// precedence over an earlier binding, builtins included.
+    pub fn register_native<F>(
+        &mut self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        method: F,
+    ) where
+        F: Fn(&mut NativeEnv, Option<&HeapValue>, &[HeapValue]) -> Option<HeapValue> + 'static,
+    {
+        self.natives
+            .get_mut()
+            .register(class_name, method_name, descriptor, method);
+        *self.link_epoch.get_mut() += 1;
+    }
+
+    /// Binds a native at run time, as JNI `RegisterNatives` does.
+    pub(crate) fn bind_native(
+        &self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        method: NativeMethod,
+    ) {
+        self.natives
+            .borrow_mut()
+            .bind(class_name, method_name, descriptor, method);
+        self.link_epoch.set(self.link_epoch.get() + 1);
+    }
+
+    /// JNI `UnregisterNatives`: the class falls back to symbol lookup.
+    pub(crate) fn unbind_natives(&self, class_name: &str) {
+        self.natives.borrow_mut().unregister_class(class_name);
+        self.link_epoch.set(self.link_epoch.get() + 1);
+    }
+
+    /// Chooses when methods are compiled. Hosts the compiler does not
+    /// support always interpret.
+    pub fn set_jit_mode(&self, mode: JitMode) {
+        if jit::is_supported() {
+            self.jit_mode.set(mode);
+        }
+    }
+
+    pub fn jit_mode(&self) -> JitMode {
+        self.jit_mode.get()
+    }
+
+    /// Logs compilations and invalidations, as `-XX:+PrintCompilation`.
+    pub fn set_print_compilation(&self, enabled: bool) {
+        self.print_compilation.set(enabled);
+    }
+
+    fn log_not_entrant(&self, compiled: &CompiledMethod) {
+        if self.print_compilation.get() {
+            println!("{:>6}   made not entrant  {}", "", compiled.name);
+        }
+    }
+
+    pub(crate) fn native_libraries(&self) -> &NativeLibraries {
+        &self.libraries
+    }
+
+    /// Sets a system property, as `-Dname=value` does.
+    pub fn set_property(&self, name: &str, value: &str) -> Option<String> {
+        self.properties
+            .borrow_mut()
+            .insert(name.to_string(), value.to_string())
+    }
+
+    pub fn property(&self, name: &str) -> Option<String> {
+        self.properties.borrow().get(name).cloned()
+    }
+
+    /// Every system property, sorted by name.
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties = self
+            .properties
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        properties.sort();
+        properties
+    }
+
+    /// Sets which classes run their assertions, from `-ea` and `-da`.
+    pub fn set_assertion_status(&self, status: AssertionStatus) {
+        *self.assertions.borrow_mut() = status;
+    }
+
+    /// What `Class.desiredAssertionStatus` answers for the class.
+    pub fn desired_assertion_status(&self, class_name: &str) -> bool {
+        self.assertions.borrow().desired_status(class_name)
+    }
+
+    pub(crate) fn clear_property(&self, name: &str) -> Option<String> {
+        self.properties.borrow_mut().remove(name)
+    }
+
+    /// The exception currently propagating, if any. Callers of
+    /// `execute_method` use this to tell a `None` void return from an
+    /// uncaught exception.
+    pub fn pending_exception(&self) -> Option<HeapValue> {
+        self.pending_exception.borrow().clone()
+    }
+
+    pub fn take_pending_exception(&self) -> Option<HeapValue> {
+        self.pending_exception.borrow_mut().take()
+    }
+
+    pub fn throw(&self, exception: HeapValue) {
+        *self.pending_exception.borrow_mut() = Some(exception);
+    }
+
+    /// Raises a new builtin throwable such as `java/lang/ArithmeticException`.
+    pub fn throw_new(&self, heap: &mut Heap, class_name: &str, message: Option<&str>) {
+        let exception =
+            java_lang_throwable::new_throwable(heap, class_name, message, self.backtrace());
+        self.throw(exception);
+    }
+
+    /// Registers a `Thread` to be run when the VM shuts down.
+    pub fn add_shutdown_hook(&self, hook: HeapValue) {
+        self.shutdown_hooks.borrow_mut().push(hook);
+    }
+
+    /// Whether `hook` is registered, by identity.
+    pub fn has_shutdown_hook(&self, hook: &HeapValue) -> bool {
+        self.shutdown_hooks
+            .borrow()
+            .iter()
+            .any(|registered| java_lang_object::same_reference(registered, hook))
+    }
+
+    /// Unregisters `hook`; `false` if it was not registered.
+    pub fn remove_shutdown_hook(&self, hook: &HeapValue) -> bool {
+        let mut hooks = self.shutdown_hooks.borrow_mut();
+        let before = hooks.len();
+        hooks.retain(|registered| !java_lang_object::same_reference(registered, hook));
+        hooks.len() != before
+    }
+
+    /// Whether the shutdown hooks have started running.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.get()
+    }
+
+    /// Runs every registered hook's `run()` once, in registration order.
+    /// Exceptions thrown by a hook are reported and do not stop the others.
+    pub fn run_shutdown_hooks(&self, class_loader: &mut ClassLoader, heap: &mut Heap) {
+        self.shutting_down.set(true);
+        let hooks: Vec<HeapValue> = self.shutdown_hooks.borrow_mut().drain(..).collect();
+        let mark = self.suspend_roots(&hooks);
+        for hook in &hooks {
+            let _ = self.invoke_virtual(class_loader, heap, hook, "run", "()V", &[]);
+            if let Some(exception) = self.take_pending_exception() {
+                let thread = java_lang_thread::name(heap, hook);
+                let mut env = NativeEnv {
+                    interpreter: self,
+                    loader: class_loader,
+                    heap,
+                };
+                let trace = java_lang_throwable::describe(&mut env, &exception);
+                eprintln!("Exception in thread \"{}\" {}", thread, trace);
+            }
+        }
+        self.resume_roots(mark);
+        let agent = self.debugger.borrow_mut().take();
+        if let Some(mut agent) = agent {
+            self.debugging.set(false);
+            agent.vm_death(&mut Context {
+                env: NativeEnv {
+                    interpreter: self,
+                    loader: class_loader,
+                    heap,
+                },
+                frame: None,
+            });
+        }
+    }
+
+    /// Keeps the references among `values` alive until the matching
+    /// `resume_roots`, for values held by a caller suspended in a call.
+    pub(crate) fn suspend_roots<'a>(
+        &self,
+        values: impl IntoIterator<Item = &'a HeapValue>,
+    ) -> usize {
+        let mut roots = self.suspended_roots.borrow_mut();
+        let mark = roots.len();
+        roots.extend(values.into_iter().filter_map(gc::reference_id));
+        mark
+    }
+
+    pub(crate) fn resume_roots(&self, mark: usize) {
+        self.suspended_roots.borrow_mut().truncate(mark);
+    }
+
+    /// Collects everything unreachable from `stack`, the frames of the
+    /// running method, and from the VM's other roots: suspended callers,
+    /// static fields, class loaders, shutdown hooks, the pending exception,
+    /// JNI references and the objects an embedding host holds. `false` if what survives exceeds the heap size.
+    pub fn collect_garbage(
+        &self,
+        class_loader: &ClassLoader,
+        heap: &mut Heap,
+        stack: &Stack,
+    ) -> bool {
+        let mut roots = self.suspended_roots.borrow().clone();
+        roots.extend(stack.iter_frames().flat_map(gc::frame_roots));
+        roots.extend(class_loader.static_values().filter_map(gc::reference_id));
+        roots.extend(class_loader.loader_objects().filter_map(gc::reference_id));
+        roots.extend(
+            self.shutdown_hooks
+                .borrow()
+                .iter()
+                .filter_map(gc::reference_id),
+        );
+        roots.extend(
+            self.pending_exception
+                .borrow()
+                .iter()
+                .filter_map(gc::reference_id),
+        );
+        roots.extend(
+            self.class_mirrors
+                .borrow()
+                .values()
+                .filter_map(gc::reference_id),
+        );
+        roots.extend(
+            self.resolved_constants
+                .borrow()
+                .values()
+                .filter_map(gc::reference_id),
+        );
+        if let Ok(debugger) = self.debugger.try_borrow() {
+            roots.extend(debugger.iter().flat_map(Agent::pinned));
+        }
+        jni::add_roots(&mut roots);
+        roots.extend(heap.host_roots());
+        Gc::new(self.debug_mode).collect(heap, &roots);
+        jni::clear_dead_weak_globals(heap);
+        heap.finish_collection()
+    }
+
+    /// Exits through the shutdown hooks if SIGINT or SIGTERM arrived.
+    /// Called at safepoints.
+    pub(crate) fn check_shutdown_signal(&self, class_loader: &mut ClassLoader, heap: &mut Heap) {
+        if !signals::is_pending() {
+            return;
+        }
+        if let Some(status) = signals::take_exit_status() {
+            let mut env = NativeEnv {
+                interpreter: self,
+                loader: class_loader,
+                heap,
+            };
+            java_lang_system::exit(&mut env, status);
+        }
+    }
+
+    /// The one `Class` object of the class, array class (`[I`) or
+    /// primitive type (`int`) with this name.
+    pub fn class_mirror(&self, heap: &mut Heap, class_name: &str) -> HeapValue {
+        if let Some(mirror) = self.class_mirrors.borrow().get(class_name) {
+            return mirror.clone();
+        }
+        let mirror = java_lang_class::new_mirror(heap, class_name);
+        self.class_mirrors
+            .borrow_mut()
+            .insert(class_name.to_string(), mirror.clone());
+        mirror
+    }
+
+    /// The proxy class already generated for these interfaces.
+    pub(crate) fn proxy_class(&self, interfaces: &[String]) -> Option<String> {
+        self.proxy_classes.borrow().get(interfaces).cloned()
+    }
+
+    pub(crate) fn add_proxy_class(&self, interfaces: Vec<String>, class_name: String) {
+        self.proxy_classes
+            .borrow_mut()
+            .insert(interfaces, class_name);
+    }
+
+    /// Whether a class was generated as a proxy class.
+    pub(crate) fn is_proxy_class(&self, class_name: &str) -> bool {
+        self.proxy_classes
+            .borrow()
+            .values()
+            .any(|name| name == class_name)
+    }
+
+    /// How many proxy classes have been generated.
+    pub(crate) fn proxy_class_count(&self) -> usize {
+        self.proxy_classes.borrow().len()
+    }
+
+    /// The value a constant-pool entry of `class_name` resolved to.
+    pub(crate) fn resolved_constant(&self, class_name: &str, index: u16) -> Option<HeapValue> {
+        self.resolved_constants
+            .borrow()
+            .get(&(class_name.to_string(), index))
+            .cloned()
+    }
+
+    pub(crate) fn add_resolved_constant(&self, class_name: &str, index: u16, value: HeapValue) {
+        self.resolved_constants
+            .borrow_mut()
+            .insert((class_name.to_string(), index), value);
+    }
+
+    /// The class of the innermost Java method, which is the caller of a
+    /// running native.
+    pub fn caller_class(&self) -> Option<String> {
+        self.call_stack
+            .borrow()
+            .last()
+            .map(|record| record.class.name.clone())
+    }
+
+    /// Stack trace lines for the active Java frames, innermost first.
+    pub fn backtrace(&self) -> Vec<String> {
+        self.call_stack
+            .borrow()
+            .iter()
+            .rev()
+            .map(|record| {
+                format!(
+                    "{}.{}({})",
+                    record.class.symbolic_name().replace('/', "."),
+                    record.class.methods[record.method].name,
+                    record
+                        .class
+                        .source_file
+                        .as_deref()
+                        .unwrap_or("Unknown Source")
+                )
+            })
+            .collect()
+    }
+
+    /// Installs a JDWP agent. Debugged code is always interpreted.
+    pub fn attach_debugger(&self, agent: Agent) {
+        *self.debugger.borrow_mut() = Some(agent);
+        self.debugging.set(true);
+        self.jit_mode.set(JitMode::Interpreted);
+    }
+
+    /// Tells the agent the VM has started, which waits for the debugger
+    /// under `suspend=y`.
+    pub fn start_debugger(&self, class_loader: &mut ClassLoader, heap: &mut Heap) {
+        self.with_debugger(class_loader, heap, None, |agent, ctx| agent.start(ctx));
+    }
+
+    fn with_debugger(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        frame: Option<(usize, &mut Frame)>,
+        action: impl FnOnce(&mut Agent, &mut Context),
+    ) {
+        let Ok(mut debugger) = self.debugger.try_borrow_mut() else {
+            return;
+        };
+        if let Some(agent) = debugger.as_mut() {
+            let mut ctx = Context {
+                env: NativeEnv {
+                    interpreter: self,
+                    loader: class_loader,
+                    heap,
+                },
+                frame,
+            };
+            action(agent, &mut ctx);
+        }
+    }
+
+    fn debug_instruction(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        frame: &mut Frame,
+        pc: usize,
+    ) {
+        self.with_debugger(class_loader, heap, Some((pc, frame)), |agent, ctx| {
+            agent.at_instruction(ctx)
+        });
+    }
+
+    fn debug_exception(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        frame: &mut Frame,
+        pc: usize,
+        exception: &HeapValue,
+    ) {
+        self.with_debugger(class_loader, heap, Some((pc, frame)), |agent, ctx| {
+            agent.exception_thrown(ctx, exception)
+        });
+    }
+
+    /// Notes where the innermost method calls out from, for the debugger.
+    fn record_position(&self, frame: &Frame, pc: usize) {
+        if !self.debugging.get() {
+            return;
+        }
+        if let Some(record) = self.call_stack.borrow_mut().last_mut() {
+            record.pc = pc;
+            record.locals = frame.local_vars.clone();
+        }
+    }
+
+    /// The active Java methods, outermost first.
+    pub(crate) fn call_records(&self) -> Vec<CallRecord> {
+        self.call_stack.borrow().clone()
+    }
+
+    /// How many classes have been prepared while debugging.
+    pub(crate) fn prepared_count(&self) -> usize {
+        self.prepared.borrow().len()
+    }
+
+    /// The classes prepared after the first `skip`.
+    pub(crate) fn prepared_classes(&self, skip: usize) -> Vec<String> {
+        self.prepared.borrow()[skip..].to_vec()
+    }
+
+    /// Every class prepared for execution, once each, by name.
+    pub(crate) fn loaded_classes(&self) -> Vec<Rc<RuntimeClass>> {
+        let mut classes: Vec<Rc<RuntimeClass>> = Vec::new();
+        for runtime in self.runtime_classes.borrow().values() {
+            if !classes.iter().any(|class| class.name == runtime.name) {
+                classes.push(runtime.clone());
+            }
+        }
+        classes.sort_by(|a, b| a.name.cmp(&b.name));
+        classes
     }
 
     pub fn execute(&self, class: &ClassFile) {
         println!("Executing main() ...");
 
-        let main_method = class
-            .methods
-            .iter()
-            .find(|m| class.get_utf8(m.name_index).unwrap_or("") == "main");
+        let runtime = RuntimeClass::new(class.clone());
+        let main_method = runtime.methods.iter().position(|m| m.name == "main");
 
-        if let Some(method) = main_method {
-            if let Some(code_attr) = &method.code {
-                let code = &code_attr.code;
+        if let Some(index) = main_method {
+            if let (Some(code_attr), Some(code)) =
+                (&class.methods[index].code, &runtime.methods[index].code)
+            {
                 let mut frame =
                     Frame::new(code_attr.max_locals as usize, code_attr.max_stack as usize);
                 let mut heap = Heap::new();
-                let mut pc = 0;
                 let gc = Gc::new(self.debug_mode);
 
-                while pc < code.len() {
-                    let instr = Instruction::from_bytecode(code, &mut pc);
-                    Self::exec_instr(&mut frame, &mut heap, class, instr);
+                for ip in 0..code.len() {
+                    self.exec_instr(&mut frame, &mut heap, &runtime, code.instruction(ip));
 
                     if heap.object_count() > 128 {
                         if self.debug_mode {
                             println!("GC Triggered (heap size = {})", heap.object_count());
                         }
-                        gc.collect(
-                            &mut heap,
-                            &Stack {
-                                frames: vec![frame.clone()],
-                            },
-                        );
+                        let roots = gc::frame_roots(&frame).collect::<Vec<_>>();
+                        gc.collect(&mut heap, &roots);
                     }
                 }
             } else {
@@ -59,9 +600,9 @@ impl Interpreter {
         println!("Execution finished");
     }
 
-    fn count_args(desc: &str) -> usize {
+    pub(crate) fn count_args(desc: &str) -> usize {
         let mut chars = desc.chars().peekable();
-        while let Some(c) = chars.next() {
+        for c in chars.by_ref() {
             if c == '(' {
                 break;
             }
@@ -78,7 +619,7 @@ impl Interpreter {
                 }
                 if matches!(chars.peek(), Some('L')) {
                     let _ = chars.next();
-                    while let Some(ec) = chars.next() {
+                    for ec in chars.by_ref() {
                         if ec == ';' {
                             break;
                         }
@@ -90,7 +631,7 @@ impl Interpreter {
                 continue;
             }
             if c == 'L' {
-                while let Some(ec) = chars.next() {
+                for ec in chars.by_ref() {
                     if ec == ';' {
                         break;
                     }
@@ -112,117 +653,426 @@ impl Interpreter {
         heap: &mut Heap,
         initial_locals: &[HeapValue],
     ) -> Option<HeapValue> {
-        let mut stack = Stack::new();
-        let gc = Gc::new(self.debug_mode);
+        let class_name = class
+            .get_class_name(class.this_class)
+            .unwrap_or("<unknown>");
+        let cached = self.runtime_classes.borrow().get(class_name).cloned();
+        let runtime = cached.unwrap_or_else(|| {
+            let runtime = Rc::new(RuntimeClass::new(class.clone()));
+            self.runtime_classes
+                .borrow_mut()
+                .insert(runtime.name.clone(), runtime.clone());
+            self.class_prepared(&runtime);
+            runtime
+        });
+        let method = runtime.find_method(name, desc)?;
+        self.invoke_method(class_loader, &runtime, method, heap, initial_locals)
+    }
 
-        let method = class
-            .methods
-            .iter()
-            .find(|m| {
-                class.get_utf8(m.name_index).unwrap_or("") == name
-                    && class.get_utf8(m.descriptor_index).unwrap_or("") == desc
-            })
-            .cloned()?;
+    /// The runtime form of a class, loading it on first use.
+    pub(crate) fn runtime_class(
+        &self,
+        class_loader: &mut ClassLoader,
+        class_name: &str,
+    ) -> Result<Rc<RuntimeClass>, LoadError> {
+        if let Some(runtime) = self.runtime_classes.borrow().get(class_name) {
+            return Ok(runtime.clone());
+        }
+        let class = class_loader.load_class(class_name)?;
+        let runtime = Rc::new(match class_loader.linkage(class_name) {
+            Some(linkage) => RuntimeClass::linked(class, class_name, linkage),
+            None => RuntimeClass::new(class),
+        });
+        {
+            let mut runtime_classes = self.runtime_classes.borrow_mut();
+            runtime_classes.insert(class_name.to_string(), runtime.clone());
+            runtime_classes.insert(runtime.name.clone(), runtime.clone());
+        }
+        self.class_prepared(&runtime);
+        self.deoptimize_dependents(&runtime);
+        Ok(runtime)
+    }
 
-        let code_attr = method.code.as_ref()?;
-        let code = &code_attr.code;
+    fn class_prepared(&self, runtime: &RuntimeClass) {
+        if self.debugging.get() {
+            self.prepared.borrow_mut().push(runtime.name.clone());
+        }
+    }
+
+    /// Redefines loaded class `class_name` from `bytes`, as JVMTI
+    /// `RedefineClasses` does. Calls made from now on run the new code;
+    /// frames already running a method finish it on the old code. Instances
+    /// and statics are kept.
+    pub fn redefine_class(
+        &self,
+        class_loader: &mut ClassLoader,
+        class_name: &str,
+        bytes: &[u8],
+    ) -> Result<(), LoadError> {
+        let class = class_loader.redefine_class(class_name, bytes)?;
+        let runtime = Rc::new(match class_loader.linkage(class_name) {
+            Some(linkage) => RuntimeClass::linked(class, class_name, linkage),
+            None => RuntimeClass::new(class),
+        });
+        for cached in self.runtime_classes.borrow_mut().values_mut() {
+            if cached.name == runtime.name {
+                *cached = runtime.clone();
+            }
+        }
+        self.resolved_constants
+            .borrow_mut()
+            .retain(|(owner, _), _| *owner != runtime.name);
+        self.link_epoch.set(self.link_epoch.get() + 1);
+        Ok(())
+    }
+
+    /// A new class may override methods that compiled code linked against
+    /// in its superclasses and interfaces; such code is invalidated.
+    fn deoptimize_dependents(&self, runtime: &RuntimeClass) {
+        if self.jit_dependents.borrow().is_empty() {
+            return;
+        }
+        let mut pending = runtime.interfaces.clone();
+        pending.extend(runtime.superclass.clone());
+        let mut seen = Vec::new();
+        while let Some(name) = pending.pop() {
+            if seen.contains(&name) {
+                continue;
+            }
+            if let Some(dependents) = self.jit_dependents.borrow_mut().remove(&name) {
+                for compiled in dependents.iter().filter_map(Weak::upgrade) {
+                    if compiled.is_valid() {
+                        compiled.invalidate();
+                        self.log_not_entrant(&compiled);
+                    }
+                }
+            }
+            if !native::is_builtin_class(&name) {
+                if let Some(ancestor) = self.runtime_classes.borrow().get(&name) {
+                    pending.extend(ancestor.superclass.clone());
+                    pending.extend(ancestor.interfaces.iter().cloned());
+                }
+            }
+            seen.push(name);
+        }
+    }
+
+    fn invoke_method(
+        &self,
+        class_loader: &mut ClassLoader,
+        runtime: &Rc<RuntimeClass>,
+        method: usize,
+        heap: &mut Heap,
+        initial_locals: &[HeapValue],
+    ) -> Option<HeapValue> {
+        self.call_stack.borrow_mut().push(CallRecord {
+            class: runtime.clone(),
+            method,
+            pc: 0,
+            locals: Vec::new(),
+        });
+        let result = match self.compiled_code(runtime, method) {
+            Some(compiled) => self.run_compiled(
+                class_loader,
+                runtime,
+                method,
+                &compiled,
+                heap,
+                initial_locals,
+                0,
+            ),
+            None => self.run_method(class_loader, runtime, method, heap, initial_locals),
+        };
+        self.call_stack.borrow_mut().pop();
+        result
+    }
+
+    /// Compiled code for a method about to be invoked, compiling it once
+    /// it is hot, or straight away under `-Xcomp`.
+    fn compiled_code(&self, runtime: &RuntimeClass, method: usize) -> Option<Rc<CompiledMethod>> {
+        let mode = self.jit_mode.get();
+        if mode == JitMode::Interpreted {
+            return None;
+        }
+        let jit = &runtime.methods[method].jit;
+        if let Some(compiled) = jit.current() {
+            return Some(compiled);
+        }
+        if jit.not_compilable.get() {
+            return None;
+        }
+        let invocations = jit.invocations.get().saturating_add(1);
+        jit.invocations.set(invocations);
+        if mode == JitMode::Compiled || invocations >= jit::INVOCATION_THRESHOLD {
+            return self.compile(runtime, method);
+        }
+        None
+    }
+
+    /// Counts a backward branch to `target` and returns the compiled code
+    /// to continue in once the loop is hot.
+    fn osr_code(
+        &self,
+        runtime: &RuntimeClass,
+        method: usize,
+        target: usize,
+    ) -> Option<Rc<CompiledMethod>> {
+        // A pending signal is handled at the interpreter's next safepoint,
+        // not in the compiled loop it just left.
+        if self.jit_mode.get() == JitMode::Interpreted || signals::is_pending() {
+            return None;
+        }
+        let jit = &runtime.methods[method].jit;
+        let compiled = match jit.current() {
+            Some(compiled) => compiled,
+            None if jit.not_compilable.get() => return None,
+            None => {
+                let backedges = jit.backedges.get().saturating_add(1);
+                jit.backedges.set(backedges);
+                if backedges < jit::BACKEDGE_THRESHOLD {
+                    return None;
+                }
+                self.compile(runtime, method)?
+            }
+        };
+        compiled.has_entry(target).then_some(compiled)
+    }
+
+    fn compile(&self, runtime: &RuntimeClass, method: usize) -> Option<Rc<CompiledMethod>> {
+        let jit = &runtime.methods[method].jit;
+        let Some(compiled) = jit::compile(runtime, method) else {
+            jit.not_compilable.set(true);
+            return None;
+        };
+        let compiled = Rc::new(compiled);
+        let id = self.compilations.get() + 1;
+        self.compilations.set(id);
+        if self.print_compilation.get() {
+            println!(
+                "{:>6}   {} ({} bytes)",
+                id, compiled.name, compiled.bytecode_size
+            );
+        }
+        let mut dependents = self.jit_dependents.borrow_mut();
+        for class_name in &compiled.dependencies {
+            dependents
+                .entry(class_name.clone())
+                .or_default()
+                .push(Rc::downgrade(&compiled));
+        }
+        *jit.compiled.borrow_mut() = Some(compiled.clone());
+        Some(compiled)
+    }
+
+    /// Runs compiled code from instruction `entry`, finishing the method in
+    /// the interpreter if the code deoptimizes.
+    #[allow(clippy::too_many_arguments)]
+    fn run_compiled(
+        &self,
+        class_loader: &mut ClassLoader,
+        runtime: &RuntimeClass,
+        method: usize,
+        compiled: &CompiledMethod,
+        heap: &mut Heap,
+        locals: &[HeapValue],
+        entry: usize,
+    ) -> Option<HeapValue> {
+        let outcome = jit_runtime::enter(
+            self,
+            class_loader,
+            heap,
+            runtime,
+            method,
+            compiled,
+            locals,
+            entry,
+        );
+        match outcome {
+            JitOutcome::Returned(value) => value,
+            JitOutcome::Deoptimized { ip, frame } => {
+                if runtime.methods[method].jit.record_deoptimization(compiled) {
+                    self.log_not_entrant(compiled);
+                }
+                self.interpret(class_loader, runtime, method, heap, frame, ip)
+            }
+            JitOutcome::Aborted => None,
+        }
+    }
+
+    fn run_method(
+        &self,
+        class_loader: &mut ClassLoader,
+        runtime: &RuntimeClass,
+        method: usize,
+        heap: &mut Heap,
+        initial_locals: &[HeapValue],
+    ) -> Option<HeapValue> {
+        let code_attr = runtime.class.methods[method].code.as_ref()?;
         let mut entry_frame =
             Frame::new(code_attr.max_locals as usize, code_attr.max_stack as usize);
-        for (idx, value) in initial_locals.iter().enumerate() {
-            entry_frame.set_local(idx, value.clone());
+        // long and double arguments occupy two local slots.
+        let mut slot = 0usize;
+        for value in initial_locals {
+            entry_frame.set_local(slot, value.clone());
+            slot += match value {
+                HeapValue::Long(_) | HeapValue::Double(_) => 2,
+                _ => 1,
+            };
         }
-        stack.push_frame(entry_frame);
+        self.interpret(class_loader, runtime, method, heap, entry_frame, 0)
+    }
+
+    /// Interprets a method from instruction `start` with `frame` as its
+    /// state. A pending exception is dispatched as if thrown at `start`.
+    fn interpret(
+        &self,
+        class_loader: &mut ClassLoader,
+        runtime: &RuntimeClass,
+        method: usize,
+        heap: &mut Heap,
+        frame: Frame,
+        start: usize,
+    ) -> Option<HeapValue> {
+        let mut stack = Stack::new();
+        let class = &runtime.class;
+
+        let code_attr = class.methods[method].code.as_ref()?;
+        let code = runtime.methods[method].code.as_ref()?;
+        stack.push_frame(frame);
+
+        let mut ip = start;
+        let mut current = start;
+        loop {
+            if let Some(exception) = self.pending_exception() {
+                let frame = stack.current_frame_mut().unwrap();
+                let pc = code.offset(current);
+                if self.debugging.get() {
+                    self.debug_exception(class_loader, heap, frame, pc, &exception);
+                }
+                match self.find_handler(class_loader, runtime, code_attr, pc, &exception) {
+                    Some(handler_pc) => {
+                        self.take_pending_exception();
+                        frame.operand_stack.clear();
+                        frame.push(exception);
+                        ip = code.index_of(handler_pc)?;
+                    }
+                    None => {
+                        let _ = stack.pop_frame();
+                        return None;
+                    }
+                }
+            }
+            if ip >= code.len() {
+                break;
+            }
 
-        let mut pc = 0usize;
-        while pc < code.len() {
-            let opcode_pc = pc;
-            let instr = Instruction::from_bytecode(code, &mut pc);
+            current = ip;
+            if self.debugging.get() {
+                let frame = stack.current_frame_mut().unwrap();
+                self.debug_instruction(class_loader, heap, frame, code.offset(current));
+            }
+            let instr = code.instruction(ip);
+            ip += 1;
             let frame = stack.current_frame_mut().unwrap();
 
             match instr {
                 Instruction::Goto(offset) => {
-                    pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                    ip = Self::branch_target(code, current, offset)?;
                 }
                 Instruction::IfEq(offset) => {
                     if frame.pop_int() == 0 {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfNe(offset) => {
                     if frame.pop_int() != 0 {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfLt(offset) => {
                     if frame.pop_int() < 0 {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfGe(offset) => {
                     if frame.pop_int() >= 0 {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfGt(offset) => {
                     if frame.pop_int() > 0 {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfLe(offset) => {
                     if frame.pop_int() <= 0 {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfICmpEq(offset) => {
                     let rhs = frame.pop_int();
                     let lhs = frame.pop_int();
                     if lhs == rhs {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfICmpNe(offset) => {
                     let rhs = frame.pop_int();
                     let lhs = frame.pop_int();
                     if lhs != rhs {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfICmpLt(offset) => {
                     let rhs = frame.pop_int();
                     let lhs = frame.pop_int();
                     if lhs < rhs {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfICmpGe(offset) => {
                     let rhs = frame.pop_int();
                     let lhs = frame.pop_int();
                     if lhs >= rhs {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfICmpGt(offset) => {
                     let rhs = frame.pop_int();
                     let lhs = frame.pop_int();
                     if lhs > rhs {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfICmpLe(offset) => {
                     let rhs = frame.pop_int();
                     let lhs = frame.pop_int();
                     if lhs <= rhs {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
+                    }
+                }
+                Instruction::IfACmpEq(offset) => {
+                    let rhs = frame.pop();
+                    let lhs = frame.pop();
+                    if java_lang_object::same_reference(&lhs, &rhs) {
+                        ip = Self::branch_target(code, current, offset)?;
+                    }
+                }
+                Instruction::IfACmpNe(offset) => {
+                    let rhs = frame.pop();
+                    let lhs = frame.pop();
+                    if !java_lang_object::same_reference(&lhs, &rhs) {
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfNull(offset) => {
                     if frame.pop().is_null() {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IfNonNull(offset) => {
                     if !frame.pop().is_null() {
-                        pc = Self::branch_target(opcode_pc, offset, code.len())?;
+                        ip = Self::branch_target(code, current, offset)?;
                     }
                 }
                 Instruction::IInc(index, delta) => {
@@ -234,360 +1084,1081 @@ impl Interpreter {
                     frame.set_local(index as usize, HeapValue::Int(current + delta as i32));
                 }
 
-                Instruction::InvokeDynamic(index) => {
-                    let Some((_indy_name, descriptor)) = Self::resolve_invoke_dynamic(class, index)
-                    else {
-                        println!("Invalid invokedynamic ref #{}", index);
+                Instruction::InvokeDynamic(_)
+                | Instruction::InvokeStatic(_)
+                | Instruction::InvokeVirtual(_)
+                | Instruction::InvokeSpecial(_)
+                | Instruction::InvokeInterface(_)
+                | Instruction::GetStatic(_)
+                | Instruction::PutStatic(_)
+                | Instruction::New(_)
+                | Instruction::CheckCast(_)
+                | Instruction::InstanceOf(_)
+                | Instruction::NewArray(_)
+                | Instruction::ANewArray(_)
+                | Instruction::IALoad
+                | Instruction::LALoad
+                | Instruction::FALoad
+                | Instruction::DALoad
+                | Instruction::AALoad
+                | Instruction::BALoad
+                | Instruction::CALoad
+                | Instruction::SALoad
+                | Instruction::IAStore
+                | Instruction::LAStore
+                | Instruction::FAStore
+                | Instruction::DAStore
+                | Instruction::AAStore
+                | Instruction::BAStore
+                | Instruction::CAStore
+                | Instruction::SAStore
+                | Instruction::AThrow => {
+                    self.record_position(frame, code.offset(current));
+                    let mark =
+                        self.suspend_roots(frame.local_vars.iter().chain(&frame.operand_stack));
+                    let flow = self.exec_linked(class_loader, heap, runtime, frame, instr, None);
+                    self.resume_roots(mark);
+                    if let Flow::Abort = flow {
                         return None;
-                    };
-                    let arg_count = Self::count_args(descriptor);
-                    let mut args = Vec::with_capacity(arg_count);
-                    for _ in 0..arg_count {
-                        args.push(frame.pop());
-                    }
-                    args.reverse();
-                    if let Some(value) =
-                        Self::execute_invokedynamic(class, index, descriptor, &args, heap)
-                    {
-                        frame.push(value);
-                    } else {
-                        println!("Unsupported invokedynamic #{} {}", index, descriptor);
+                    }
+                }
+                Instruction::Ldc(_) | Instruction::LdcW(_) | Instruction::Ldc2W(_)
+                    if Self::is_resolved_constant(runtime, instr) =>
+                {
+                    self.record_position(frame, code.offset(current));
+                    let mark =
+                        self.suspend_roots(frame.local_vars.iter().chain(&frame.operand_stack));
+                    let flow = self.exec_linked(class_loader, heap, runtime, frame, instr, None);
+                    self.resume_roots(mark);
+                    if let Flow::Abort = flow {
                         return None;
                     }
                 }
 
-                Instruction::InvokeStatic(index)
-                | Instruction::InvokeVirtual(index)
-                | Instruction::InvokeSpecial(index)
-                | Instruction::InvokeInterface(index) => {
-                    let Some((cp_class_name, method_name, descriptor)) =
-                        Self::resolve_method_ref(class, index)
-                    else {
-                        println!("Invalid method ref #{}", index);
-                        return None;
-                    };
+                Instruction::Return => {
+                    let _ = stack.pop_frame();
+                    return None;
+                }
+                Instruction::IReturn
+                | Instruction::LReturn
+                | Instruction::FReturn
+                | Instruction::DReturn
+                | Instruction::AReturn => {
+                    let value = frame.pop();
+                    let _ = stack.pop_frame();
+                    return Some(value);
+                }
 
-                    let needs_this = matches!(
-                        instr,
-                        Instruction::InvokeVirtual(_)
-                            | Instruction::InvokeSpecial(_)
-                            | Instruction::InvokeInterface(_)
+                _ => {
+                    self.exec_instr(frame, heap, runtime, instr);
+                }
+            }
+
+            if ip <= current {
+                if let Some(compiled) = self.osr_code(runtime, method, ip) {
+                    let frame = stack.pop_frame()?;
+                    return self.run_compiled(
+                        class_loader,
+                        runtime,
+                        method,
+                        &compiled,
+                        heap,
+                        &frame.local_vars,
+                        ip,
                     );
-                    let arg_count = Self::count_args(descriptor);
-                    let mut args = Vec::with_capacity(arg_count);
-                    for _ in 0..arg_count {
-                        args.push(frame.pop());
-                    }
-                    args.reverse();
-
-                    let receiver = if needs_this {
-                        let candidate = frame.pop();
-                        if candidate.is_null() {
-                            println!("NullPointerException");
-                            return None;
-                        }
-                        Some(candidate)
-                    } else {
-                        None
-                    };
+                }
+            }
 
-                    if matches!(instr, Instruction::InvokeStatic(_))
-                        && !self.ensure_class_initialized(class_loader, cp_class_name, heap)
-                    {
-                        return None;
+            if heap.needs_collection() && !self.collect_garbage(class_loader, heap, &stack) {
+                self.throw_new(heap, "java/lang/OutOfMemoryError", Some("Java heap space"));
+            }
+            self.check_shutdown_signal(class_loader, heap);
+        }
+
+        None
+    }
+
+    /// Runs an instruction that resolves constant-pool entries, allocates,
+    /// calls or throws. Invokes link through `site`, or through the
+    /// constant-pool entry's own call site when `site` is `None`.
+    pub(crate) fn exec_linked(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        runtime: &RuntimeClass,
+        frame: &mut Frame,
+        instr: Instruction,
+        site: Option<&RefCell<Option<CallSite>>>,
+    ) -> Flow {
+        if let Some(index) = Self::class_entry_index(runtime, instr) {
+            if !self.link_entry(class_loader, heap, runtime, index) {
+                return Flow::Next;
+            }
+        }
+        let class = &runtime.class;
+        match instr {
+            Instruction::InvokeDynamic(index) => {
+                let Some((_indy_name, descriptor)) = Self::resolve_invoke_dynamic(class, index)
+                else {
+                    println!("Invalid invokedynamic ref #{}", index);
+                    return Flow::Abort;
+                };
+                let arg_count = Self::count_args(descriptor);
+                let mut args = Vec::with_capacity(arg_count);
+                for _ in 0..arg_count {
+                    args.push(frame.pop());
+                }
+                args.reverse();
+                if let Some(value) =
+                    Self::execute_invokedynamic(class, index, descriptor, &args, heap)
+                {
+                    frame.push(value);
+                } else {
+                    println!("Unsupported invokedynamic #{} {}", index, descriptor);
+                    return Flow::Abort;
+                }
+            }
+
+            Instruction::InvokeStatic(index)
+            | Instruction::InvokeVirtual(index)
+            | Instruction::InvokeSpecial(index)
+            | Instruction::InvokeInterface(index) => {
+                let Some(method_ref) = runtime.method_ref(index) else {
+                    println!("Invalid method ref #{}", index);
+                    return Flow::Abort;
+                };
+                let cp_class_name = method_ref.class_name.as_str();
+                let method_name = method_ref.name.as_str();
+                let descriptor = method_ref.descriptor.as_str();
+
+                let needs_this = matches!(
+                    instr,
+                    Instruction::InvokeVirtual(_)
+                        | Instruction::InvokeSpecial(_)
+                        | Instruction::InvokeInterface(_)
+                );
+                let mut args = Vec::with_capacity(method_ref.arg_count);
+                for _ in 0..method_ref.arg_count {
+                    args.push(frame.pop());
+                }
+                args.reverse();
+
+                let receiver = if needs_this {
+                    let candidate = frame.pop();
+                    if candidate.is_null() {
+                        self.throw_new(
+                            heap,
+                            "java/lang/NullPointerException",
+                            Some(&format!(
+                                "Cannot invoke \"{}.{}()\" because value is null",
+                                class_loader.symbolic_name(cp_class_name).replace('/', "."),
+                                method_name
+                            )),
+                        );
+                        return Flow::Next;
                     }
+                    Some(candidate)
+                } else {
+                    None
+                };
+
+                if matches!(instr, Instruction::InvokeStatic(_)) && !method_ref.initialized.get() {
+                    if !self.ensure_class_initialized(class_loader, cp_class_name, heap) {
+                        return Flow::Abort;
+                    }
+                    method_ref.initialized.set(true);
+                }
 
-                    if let Some(native_result) = invoke_native(
+                let receiver_class = receiver
+                    .as_ref()
+                    .filter(|_| {
+                        matches!(
+                            instr,
+                            Instruction::InvokeVirtual(_) | Instruction::InvokeInterface(_)
+                        )
+                    })
+                    .map(|this_ref| Self::receiver_class(this_ref, cp_class_name));
+                let site = site.unwrap_or(&method_ref.site);
+                let epoch = self.link_epoch.get();
+                let cached = site
+                    .borrow()
+                    .as_ref()
+                    .filter(|linked| {
+                        linked.epoch == epoch && linked.receiver_class.as_deref() == receiver_class
+                    })
+                    .map(|linked| linked.target.clone());
+                let target = match cached {
+                    Some(target) => target,
+                    None => match self.link_method(
+                        class_loader,
                         cp_class_name,
+                        receiver_class,
                         method_name,
                         descriptor,
-                        receiver.clone(),
-                        &args,
-                        heap,
                     ) {
-                        if let Some(v) = native_result {
-                            frame.push(v);
+                        Some(Ok(target)) => {
+                            *site.borrow_mut() = Some(CallSite {
+                                receiver_class: receiver_class.map(str::to_string),
+                                epoch,
+                                target: target.clone(),
+                            });
+                            target
+                        }
+                        Some(Err(message)) => {
+                            self.throw_new(heap, "java/lang/UnsatisfiedLinkError", Some(&message));
+                            return Flow::Next;
                         }
-                        continue;
+                        None => {
+                            // A builtin the JDK declares but nothing binds
+                            // fails to link; anything else does not exist.
+                            let error = match native::is_builtin_class(cp_class_name) {
+                                true => "java/lang/UnsatisfiedLinkError",
+                                false => "java/lang/NoSuchMethodError",
+                            };
+                            let class_name = class_loader.symbolic_name(cp_class_name);
+                            let message =
+                                registry::describe_method(&class_name, method_name, descriptor);
+                            self.throw_new(heap, error, Some(&message));
+                            return Flow::Next;
+                        }
+                    },
+                };
+
+                if let Some(retval) =
+                    self.invoke_target(class_loader, heap, &target, receiver, args)
+                {
+                    frame.push(retval);
+                }
+            }
+
+            Instruction::GetStatic(index) => {
+                let Some(field) = runtime.field_ref(index) else {
+                    println!("Invalid field ref #{}", index);
+                    return Flow::Abort;
+                };
+                if !field.initialized.get() {
+                    if !self.ensure_class_initialized(class_loader, &field.class_name, heap) {
+                        return Flow::Abort;
                     }
+                    field.initialized.set(true);
+                }
 
-                    let target_class_name = match instr {
-                        Instruction::InvokeVirtual(_) | Instruction::InvokeInterface(_) => {
-                            match receiver.as_ref() {
-                                Some(HeapValue::Object(obj)) => obj.class_name.as_str(),
-                                _ => cp_class_name,
-                            }
-                        }
-                        _ => cp_class_name,
-                    };
+                let value = class_loader
+                    .get_static_field(&field.class_name, &field.name)
+                    .unwrap_or_else(|| Self::default_value_for_descriptor(&field.descriptor));
+                frame.push(value);
+            }
 
-                    let mut locals = Vec::with_capacity(args.len() + receiver.is_some() as usize);
-                    if let Some(this_ref) = receiver.clone() {
-                        locals.push(this_ref);
+            Instruction::PutStatic(index) => {
+                let Some(field) = runtime.field_ref(index) else {
+                    println!("Invalid field ref #{}", index);
+                    return Flow::Abort;
+                };
+                if !field.initialized.get() {
+                    if !self.ensure_class_initialized(class_loader, &field.class_name, heap) {
+                        return Flow::Abort;
                     }
-                    locals.extend(args);
+                    field.initialized.set(true);
+                }
+                let value = frame.pop();
+                class_loader.set_static_field(&field.class_name, &field.name, value);
+            }
 
-                    if let Some((target_class, _target_method)) = Self::resolve_method(
-                        class_loader,
-                        target_class_name,
-                        method_name,
-                        descriptor,
-                    ) {
-                        if let Some(retval) = self.execute_method(
-                            class_loader,
-                            &target_class,
-                            method_name,
-                            descriptor,
-                            heap,
-                            &locals,
-                        ) {
-                            frame.push(retval);
-                        }
-                    } else {
-                        println!(
-                            "Method {}{} not found for {}",
-                            method_name, descriptor, target_class_name
-                        );
-                        return None;
+            Instruction::New(index) => {
+                let Some(new_class) = runtime.class_ref(index) else {
+                    println!("Invalid class ref #{}", index);
+                    return Flow::Abort;
+                };
+                if !new_class.initialized.get() {
+                    if !self.ensure_class_initialized(class_loader, &new_class.name, heap) {
+                        return Flow::Abort;
                     }
+                    new_class.initialized.set(true);
                 }
+                let obj = heap.alloc_object(&new_class.name);
+                frame.push(HeapValue::Object(obj));
+            }
 
-                Instruction::GetStatic(index) => {
-                    let Some((field_class, field_name, field_desc)) =
-                        Self::resolve_field_ref(class, index)
-                    else {
-                        println!("Invalid field ref #{}", index);
-                        return None;
-                    };
-                    let _ = class_loader.load_class(field_class);
-                    if !self.ensure_class_initialized(class_loader, field_class, heap) {
-                        return None;
+            Instruction::NewArray(atype_code) => {
+                let count = frame.pop_int();
+                if count < 0 {
+                    self.throw_new(
+                        heap,
+                        "java/lang/NegativeArraySizeException",
+                        Some(&count.to_string()),
+                    );
+                    return Flow::Next;
+                }
+                use crate::runtime::heap::ArrayType;
+                let element_type = match atype_code {
+                    4 => ArrayType::Boolean,
+                    5 => ArrayType::Char,
+                    6 => ArrayType::Float,
+                    7 => ArrayType::Double,
+                    8 => ArrayType::Byte,
+                    9 => ArrayType::Short,
+                    10 => ArrayType::Int,
+                    11 => ArrayType::Long,
+                    _ => ArrayType::Int,
+                };
+                let arr = heap.alloc_array(count as usize, element_type);
+                frame.push(HeapValue::Array(arr));
+            }
+
+            Instruction::ANewArray(index) => {
+                let count = frame.pop_int();
+                if count < 0 {
+                    self.throw_new(
+                        heap,
+                        "java/lang/NegativeArraySizeException",
+                        Some(&count.to_string()),
+                    );
+                    return Flow::Next;
+                }
+                let component = runtime.class_ref(index);
+                let component = component
+                    .as_ref()
+                    .map_or("java/lang/Object", |component| component.name.as_str());
+                let arr = heap.alloc_reference_array(count as usize, component);
+                frame.push(HeapValue::Array(arr));
+            }
+
+            Instruction::IALoad
+            | Instruction::LALoad
+            | Instruction::FALoad
+            | Instruction::DALoad
+            | Instruction::AALoad
+            | Instruction::BALoad
+            | Instruction::CALoad
+            | Instruction::SALoad => {
+                let idx = frame.pop_int();
+                let arr_ref = frame.pop();
+                let HeapValue::Array(arr) = arr_ref else {
+                    self.throw_new(heap, "java/lang/NullPointerException", None);
+                    return Flow::Next;
+                };
+                let element = heap.get_array(arr.id).and_then(|target_arr| {
+                    (idx >= 0 && (idx as usize) < target_arr.content.len())
+                        .then(|| target_arr.content[idx as usize].clone())
+                });
+                match element {
+                    Some(value) => frame.push(value),
+                    None => {
+                        let len = heap.get_array(arr.id).map_or(0, |a| a.content.len());
+                        self.throw_array_index(heap, idx, len);
                     }
+                }
+            }
+
+            Instruction::IAStore
+            | Instruction::LAStore
+            | Instruction::FAStore
+            | Instruction::DAStore
+            | Instruction::AAStore
+            | Instruction::BAStore
+            | Instruction::CAStore
+            | Instruction::SAStore => {
+                let val = match instr {
+                    Instruction::BAStore => HeapValue::Int(frame.pop_int() as i8 as i32),
+                    Instruction::CAStore => HeapValue::Int(frame.pop_int() as u16 as i32),
+                    Instruction::SAStore => HeapValue::Int(frame.pop_int() as i16 as i32),
+                    _ => frame.pop(),
+                };
+                let idx = frame.pop_int();
+                let arr_ref = frame.pop();
+
+                let HeapValue::Array(arr) = arr_ref else {
+                    self.throw_new(heap, "java/lang/NullPointerException", None);
+                    return Flow::Next;
+                };
+                let len = heap.get_array(arr.id).map_or(0, |a| a.content.len());
+                if idx < 0 || idx as usize >= len {
+                    self.throw_array_index(heap, idx, len);
+                    return Flow::Next;
+                }
+                if let Some(target_arr) = heap.get_array_mut(arr.id) {
+                    target_arr.content[idx as usize] = val;
+                }
+            }
+
+            Instruction::CheckCast(index) | Instruction::InstanceOf(index) => {
+                let Some(target) = runtime.class_ref(index) else {
+                    println!("Invalid class ref #{}", index);
+                    return Flow::Abort;
+                };
+                let value = frame.pop();
+                // `null` passes any cast but is an instance of nothing.
+                let class = java_lang_class::value_class(heap, &value);
+                let fits = class
+                    .as_deref()
+                    .is_some_and(|class| self.is_assignable(class_loader, class, &target.name));
+                match instr {
+                    Instruction::InstanceOf(_) => frame.push(HeapValue::Int(fits as i32)),
+                    _ => match class {
+                        Some(class) if !fits => {
+                            let message = format!(
+                                "class {} cannot be cast to class {}",
+                                class_loader.symbolic_name(&class).replace('/', "."),
+                                class_loader.symbolic_name(&target.name).replace('/', ".")
+                            );
+                            self.throw_new(heap, "java/lang/ClassCastException", Some(&message));
+                        }
+                        _ => frame.push(value),
+                    },
+                }
+            }
+
+            Instruction::AThrow => {
+                let exception = frame.pop();
+                if exception.is_null() {
+                    self.throw_new(heap, "java/lang/NullPointerException", None);
+                } else {
+                    self.throw(exception);
+                }
+            }
+
+            Instruction::Ldc(_) | Instruction::LdcW(_) | Instruction::Ldc2W(_) => {
+                let Some(index) = Self::constant_index(instr) else {
+                    return Flow::Abort;
+                };
+                if let Some(ConstantPoolEntry::Class { .. }) = class.constant(index) {
+                    let class_ref = runtime.class_ref(index);
+                    let class_name = class_ref.as_ref().map_or("", |class| class.name.as_str());
+                    frame.push(self.class_mirror(heap, class_name));
+                    return Flow::Next;
+                }
+                let mut env = NativeEnv {
+                    interpreter: self,
+                    loader: class_loader,
+                    heap,
+                };
+                match java_lang_invoke::resolve_constant(&mut env, runtime, index) {
+                    Some(value) => frame.push(value),
+                    None if self.pending_exception().is_some() => {}
+                    None => {
+                        println!("Unresolvable constant #{}", index);
+                        return Flow::Abort;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Flow::Next
+    }
+
+    fn constant_index(instr: Instruction) -> Option<u16> {
+        match instr {
+            Instruction::Ldc(index) => Some(u16::from(index)),
+            Instruction::LdcW(index) | Instruction::Ldc2W(index) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Whether an `ldc` loads a constant that is resolved through
+    /// `java.lang.invoke`, or a class resolved through a user-defined
+    /// loader, rather than read from the constant pool.
+    fn is_resolved_constant(runtime: &RuntimeClass, instr: Instruction) -> bool {
+        let entry = Self::constant_index(instr).and_then(|index| runtime.class.constant(index));
+        match entry {
+            Some(
+                ConstantPoolEntry::MethodHandle { .. }
+                | ConstantPoolEntry::MethodType { .. }
+                | ConstantPoolEntry::Dynamic { .. },
+            ) => true,
+            Some(ConstantPoolEntry::Class { .. }) => {
+                !class_loader::is_builtin_loader(runtime.loader)
+            }
+            _ => false,
+        }
+    }
+
+    /// The entry naming a class that `instr` links, when the class still
+    /// has to be resolved through its user-defined defining loader.
+    fn class_entry_index(runtime: &RuntimeClass, instr: Instruction) -> Option<u16> {
+        if class_loader::is_builtin_loader(runtime.loader) {
+            return None;
+        }
+        let index = match instr {
+            Instruction::InvokeStatic(index)
+            | Instruction::InvokeVirtual(index)
+            | Instruction::InvokeSpecial(index)
+            | Instruction::InvokeInterface(index)
+            | Instruction::GetStatic(index)
+            | Instruction::PutStatic(index)
+            | Instruction::New(index)
+            | Instruction::ANewArray(index)
+            | Instruction::CheckCast(index)
+            | Instruction::InstanceOf(index) => index,
+            _ => Self::constant_index(instr)?,
+        };
+        (!runtime.is_linked(index)).then_some(index)
+    }
+
+    /// Resolves the class named by entry `index` of a class defined by a
+    /// user-defined loader through that loader, checking the loading
+    /// constraints a member reference into another loader's class places
+    /// on the types of its descriptor. `false` with an exception pending
+    /// if the class cannot be loaded.
+    fn link_entry(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        runtime: &RuntimeClass,
+        index: u16,
+    ) -> bool {
+        let Some(symbolic) = runtime.symbolic_class(index) else {
+            return true;
+        };
+        let Some(resolved) = self.resolve_class_name(class_loader, heap, runtime.loader, symbolic)
+        else {
+            // A class the loader cannot find is a `NoClassDefFoundError`.
+            let pending = self.pending_exception();
+            if let Some(HeapValue::Object(exception)) = &pending {
+                if exception.class_name != "java/lang/ClassNotFoundException" {
+                    return false;
+                }
+                self.take_pending_exception();
+            }
+            self.throw_new(heap, "java/lang/NoClassDefFoundError", Some(symbolic));
+            return false;
+        };
+        let member = match runtime.class.constant(index) {
+            Some(ConstantPoolEntry::FieldRef { .. }) => runtime
+                .field_ref(index)
+                .map(|field| ("field", field.name.clone(), field.descriptor.clone())),
+            Some(
+                ConstantPoolEntry::MethodRef { .. } | ConstantPoolEntry::InterfaceMethodRef { .. },
+            ) => runtime
+                .method_ref(index)
+                .map(|method| ("method", method.name.clone(), method.descriptor.clone())),
+            _ => None,
+        };
+        let other = class_loader.defining_loader(&resolved);
+        if let Some((kind, name, descriptor)) = member.filter(|_| other != runtime.loader) {
+            let (mut types, returns) = java_lang_class::split_method_descriptor(&descriptor);
+            types.push(returns);
+            for element in types.iter().map(|ty| ty.trim_start_matches('[')) {
+                let Some(type_name) = element.strip_prefix('L').and_then(|t| t.strip_suffix(';'))
+                else {
+                    continue;
+                };
+                if class_loader
+                    .add_constraint(type_name, runtime.loader, other)
+                    .is_err()
+                {
+                    let message = format!(
+                        "loader constraint violation: when resolving {} '{}.{}{}' the class \
+                         loaders of the current class, {}, and of {} have different Class \
+                         objects for the type {} used in the signature",
+                        kind,
+                        symbolic.replace('/', "."),
+                        name,
+                        descriptor,
+                        runtime.symbolic_name().replace('/', "."),
+                        symbolic.replace('/', "."),
+                        type_name.replace('/', ".")
+                    );
+                    self.throw_new(heap, "java/lang/LinkageError", Some(&message));
+                    return false;
+                }
+            }
+        }
+        runtime.link(index, &resolved);
+        true
+    }
+
+    /// The runtime name `class_name` resolves to through loader `loader`,
+    /// calling the loader's `loadClass` the first time it is asked for a
+    /// name. `None` with the loader's exception pending if it fails.
+    pub(crate) fn resolve_class_name(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        loader: LoaderId,
+        class_name: &str,
+    ) -> Option<String> {
+        if class_loader::is_builtin_loader(loader) || native::is_builtin_class(class_name) {
+            return Some(class_name.to_string());
+        }
+        if let Some(component) = class_name.strip_prefix('[') {
+            if component.starts_with('[') {
+                let component = self.resolve_class_name(class_loader, heap, loader, component)?;
+                return Some(format!("[{}", component));
+            }
+            return match component
+                .strip_prefix('L')
+                .and_then(|c| c.strip_suffix(';'))
+            {
+                Some(element) => {
+                    let element = self.resolve_class_name(class_loader, heap, loader, element)?;
+                    Some(format!("[L{};", element))
+                }
+                None => Some(class_name.to_string()),
+            };
+        }
+        if let Some(found) = class_loader.find_loaded(loader, class_name) {
+            return Some(found);
+        }
+        let object = class_loader.loader_object(loader)?.clone();
+        let binary_name = heap.alloc_string(&class_name.replace('/', "."));
+        let mut env = NativeEnv {
+            interpreter: self,
+            loader: class_loader,
+            heap,
+        };
+        let mirror = env.invoke_virtual(
+            &object,
+            "loadClass",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            &[binary_name],
+        );
+        if self.pending_exception().is_some() {
+            return None;
+        }
+        let found = mirror.and_then(|mirror| java_lang_class::class_name(heap, &mirror))?;
+        if let Err(error) = class_loader.record_loaded(loader, class_name, &found) {
+            self.throw_new(heap, "java/lang/LinkageError", Some(&error.to_string()));
+            return None;
+        }
+        Some(found)
+    }
+
+    /// Runs one instruction on behalf of compiled code, with invokes linked
+    /// through the code's own inline cache.
+    pub(crate) fn exec_slow(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        runtime: &RuntimeClass,
+        frame: &mut Frame,
+        instr: Instruction,
+        inline_cache: &RefCell<Option<CallSite>>,
+    ) -> Flow {
+        match instr {
+            Instruction::Ldc(_) | Instruction::LdcW(_)
+                if !Self::is_resolved_constant(runtime, instr) =>
+            {
+                self.exec_instr(frame, heap, runtime, instr);
+                Flow::Next
+            }
+            Instruction::GetField(_) | Instruction::PutField(_) | Instruction::ArrayLength => {
+                self.exec_instr(frame, heap, runtime, instr);
+                Flow::Next
+            }
+            _ => self.exec_linked(
+                class_loader,
+                heap,
+                runtime,
+                frame,
+                instr,
+                Some(inline_cache),
+            ),
+        }
+    }
+
+    /// Finds the handler covering `pc` whose catch type accepts `exception`.
+    pub(crate) fn find_handler(
+        &self,
+        class_loader: &mut ClassLoader,
+        runtime: &RuntimeClass,
+        code_attr: &CodeAttribute,
+        pc: usize,
+        exception: &HeapValue,
+    ) -> Option<usize> {
+        let HeapValue::Object(obj) = exception else {
+            return None;
+        };
+        code_attr
+            .exception_table
+            .iter()
+            .find(|entry| {
+                if pc < entry.start_pc as usize || pc >= entry.end_pc as usize {
+                    return false;
+                }
+                if entry.catch_type == 0 {
+                    return true;
+                }
+                let Some(catch_name) = runtime.class.get_class_name(entry.catch_type) else {
+                    return false;
+                };
+                // A catch type the loader never resolved cannot be the
+                // class of anything thrown, unless the system loaders
+                // share it.
+                let catch_name = class_loader
+                    .find_loaded(runtime.loader, catch_name)
+                    .unwrap_or_else(|| catch_name.to_string());
+                self.is_subclass_of(class_loader, &obj.class_name, &catch_name)
+            })
+            .map(|entry| entry.handler_pc as usize)
+    }
 
-                    if field_class == "java/lang/System" && field_name == "out" {
-                        let ps = heap.alloc_object("java/io/PrintStream");
-                        frame.push(HeapValue::Object(ps));
-                        continue;
-                    }
+    pub(crate) fn throw_array_index(&self, heap: &mut Heap, index: i32, len: usize) {
+        self.throw_new(
+            heap,
+            "java/lang/ArrayIndexOutOfBoundsException",
+            Some(&format!("Index {} out of bounds for length {}", index, len)),
+        );
+    }
 
-                    let value = class_loader
-                        .get_static_field(field_class, field_name)
-                        .unwrap_or_else(|| Self::default_value_for_descriptor(field_desc));
-                    frame.push(value);
-                }
+    /// Superclass of a loaded or builtin class; `None` above `java/lang/Object`.
+    pub fn superclass_of(
+        &self,
+        class_loader: &mut ClassLoader,
+        class_name: &str,
+    ) -> Option<String> {
+        if class_name == "java/lang/Object" {
+            return None;
+        }
+        if let Some(parent) = java_lang_throwable::builtin_superclass(class_name) {
+            return Some(parent.to_string());
+        }
+        if native::is_builtin_class(class_name) {
+            return Some("java/lang/Object".to_string());
+        }
+        self.runtime_class(class_loader, class_name)
+            .ok()?
+            .superclass
+            .clone()
+    }
 
-                Instruction::PutStatic(index) => {
-                    let Some((field_class, field_name, _field_desc)) =
-                        Self::resolve_field_ref(class, index)
-                    else {
-                        println!("Invalid field ref #{}", index);
-                        return None;
-                    };
-                    if !self.ensure_class_initialized(class_loader, field_class, heap) {
-                        return None;
-                    }
-                    let value = frame.pop();
-                    class_loader.set_static_field(field_class, field_name, value);
-                }
+    pub fn is_subclass_of(
+        &self,
+        class_loader: &mut ClassLoader,
+        class_name: &str,
+        ancestor: &str,
+    ) -> bool {
+        let mut current = Some(class_name.to_string());
+        while let Some(name) = current {
+            if name == ancestor {
+                return true;
+            }
+            current = self.superclass_of(class_loader, &name);
+        }
+        false
+    }
 
-                Instruction::New(index) => {
-                    let Some(new_class_name) = class.get_class_name(index) else {
-                        println!("Invalid class ref #{}", index);
-                        return None;
-                    };
-                    if !self.ensure_class_initialized(class_loader, new_class_name, heap) {
-                        return None;
+    /// Whether a reference of class `from` may be used where `to` is
+    /// expected: the superclass chain, implemented interfaces, and array
+    /// covariance. Array classes use descriptor names such as `[I`.
+    pub fn is_assignable(&self, class_loader: &mut ClassLoader, from: &str, to: &str) -> bool {
+        if from == to || to == "java/lang/Object" {
+            return true;
+        }
+        if let Some(from_component) = from.strip_prefix('[') {
+            return match to.strip_prefix('[') {
+                Some(to_component) => {
+                    match (
+                        Self::component_class_name(from_component),
+                        Self::component_class_name(to_component),
+                    ) {
+                        (Some(f), Some(t)) => self.is_assignable(class_loader, f, t),
+                        _ => from_component == to_component,
                     }
-                    let obj = heap.alloc_object(new_class_name);
-                    frame.push(HeapValue::Object(obj));
                 }
+                None => matches!(to, "java/lang/Cloneable" | "java/io/Serializable"),
+            };
+        }
+        if to.starts_with('[') {
+            return false;
+        }
 
-                Instruction::NewArray(atype_code) => {
-                    let count = frame.pop_int();
-                    if count < 0 {
-                        println!("NegativeArraySizeException");
-                        return None;
-                    }
-                    use crate::runtime::heap::ArrayType;
-                    let element_type = match atype_code {
-                        4 => ArrayType::Boolean,
-                        5 => ArrayType::Char,
-                        6 => ArrayType::Float,
-                        7 => ArrayType::Double,
-                        8 => ArrayType::Byte,
-                        9 => ArrayType::Short,
-                        10 => ArrayType::Int,
-                        11 => ArrayType::Long,
-                        _ => ArrayType::Int,
-                    };
-                    let arr = heap.alloc_array(count as usize, element_type);
-                    frame.push(HeapValue::Array(arr));
-                }
+        let mut current = Some(from.to_string());
+        while let Some(name) = current {
+            if name == to || self.implements_interface(class_loader, &name, to) {
+                return true;
+            }
+            current = self.superclass_of(class_loader, &name);
+        }
+        false
+    }
 
-                Instruction::ANewArray(_index) => {
-                    let count = frame.pop_int();
-                    if count < 0 {
-                        println!("NegativeArraySizeException");
-                        return None;
-                    }
-                    use crate::runtime::heap::ArrayType;
-                    let arr = heap.alloc_array(count as usize, ArrayType::Reference);
-                    frame.push(HeapValue::Array(arr));
-                }
-
-                Instruction::IALoad => {
-                    let idx = frame.pop_int();
-                    let arr_ref = frame.pop();
-                    if let HeapValue::Array(arr) = arr_ref {
-                        if let Some(target_arr) = heap.get_array_mut(arr.id) {
-                            if idx >= 0 && (idx as usize) < target_arr.content.len() {
-                                frame.push(target_arr.content[idx as usize].clone());
-                            } else {
-                                println!("ArrayIndexOutOfBoundsException");
-                                return None;
-                            }
-                        }
-                    }
-                }
+    /// `Ljava/lang/String;` -> `java/lang/String`, `[I` -> `[I`; `None` for
+    /// primitive components.
+    fn component_class_name(component: &str) -> Option<&str> {
+        if component.starts_with('[') {
+            return Some(component);
+        }
+        component.strip_prefix('L')?.strip_suffix(';')
+    }
 
-                Instruction::AALoad => {
-                    let idx = frame.pop_int();
-                    let arr_ref = frame.pop();
-                    if let HeapValue::Array(arr) = arr_ref {
-                        if let Some(target_arr) = heap.get_array_mut(arr.id) {
-                            if idx >= 0 && (idx as usize) < target_arr.content.len() {
-                                frame.push(target_arr.content[idx as usize].clone());
-                            } else {
-                                println!("ArrayIndexOutOfBoundsException");
-                                return None;
-                            }
-                        }
-                    }
-                }
+    fn implements_interface(
+        &self,
+        class_loader: &mut ClassLoader,
+        class_name: &str,
+        interface: &str,
+    ) -> bool {
+        if native::is_builtin_class(class_name) {
+            return false;
+        }
+        let Ok(runtime) = self.runtime_class(class_loader, class_name) else {
+            return false;
+        };
+        runtime.interfaces.iter().any(|name| {
+            name == interface || self.implements_interface(class_loader, name, interface)
+        })
+    }
 
-                Instruction::IAStore | Instruction::AAStore => {
-                    let val = frame.pop();
-                    let idx = frame.pop_int();
-                    let arr_ref = frame.pop();
+    /// Class used for virtual dispatch on `receiver`.
+    fn receiver_class<'a>(receiver: &'a HeapValue, cp_class_name: &'a str) -> &'a str {
+        match receiver {
+            HeapValue::Object(obj) => &obj.class_name,
+            HeapValue::String(_) => "java/lang/String",
+            HeapValue::Array(_) => "java/lang/Object",
+            _ => cp_class_name,
+        }
+    }
 
-                    if let HeapValue::Array(arr) = arr_ref {
-                        if let Some(target_arr) = heap.get_array_mut(arr.id) {
-                            if idx >= 0 && (idx as usize) < target_arr.content.len() {
-                                target_arr.content[idx as usize] = val;
-                            } else {
-                                println!("ArrayIndexOutOfBoundsException");
-                                return None;
-                            }
-                        }
-                    }
-                }
+    /// Links an invoke of `cp_class_name.method_name descriptor`. Virtual
+    /// calls search from the receiver's runtime class first; interface
+    /// defaults and anything else declared above the referenced class come
+    /// from the referenced class itself. `Err` carries the
+    /// `UnsatisfiedLinkError` message for an unbound native method.
+    fn link_method(
+        &self,
+        class_loader: &mut ClassLoader,
+        cp_class_name: &str,
+        receiver_class: Option<&str>,
+        method_name: &str,
+        descriptor: &str,
+    ) -> Option<Result<MethodTarget, String>> {
+        if let Some(runtime_class) = receiver_class {
+            if let Some(target) =
+                self.resolve_target(class_loader, runtime_class, method_name, descriptor)
+            {
+                return Some(target);
+            }
+        }
+        self.resolve_target(class_loader, cp_class_name, method_name, descriptor)
+    }
 
-                Instruction::Return => {
-                    let _ = stack.pop_frame();
-                    return None;
-                }
-                Instruction::IReturn
-                | Instruction::LReturn
-                | Instruction::FReturn
-                | Instruction::DReturn
-                | Instruction::AReturn => {
-                    let value = frame.pop();
-                    let _ = stack.pop_frame();
-                    return Some(value);
+    /// Walks the superclass chain from `class_name` to the first class that
+    /// implements the method: builtin classes through the native registry,
+    /// loaded classes through a declared method with code, or through the
+    /// registry or a loaded JNI library for a declared `native` method.
+    fn resolve_target(
+        &self,
+        class_loader: &mut ClassLoader,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+    ) -> Option<Result<MethodTarget, String>> {
+        let mut level = Some(class_name.to_string());
+        while let Some(name) = level {
+            if native::is_builtin_class(&name) {
+                let method = self.natives.borrow().lookup(&name, method_name, descriptor);
+                let method = method.or_else(|| {
+                    java_lang_invoke::signature_polymorphic(&name, method_name, descriptor)
+                });
+                if let Some(method) = method {
+                    return Some(Ok(MethodTarget::Native(method)));
                 }
-
-                _ => {
-                    Self::exec_instr(frame, heap, class, instr);
+            } else if let Ok(level_class) = self.runtime_class(class_loader, &name) {
+                if let Some(index) = level_class.find_method(method_name, descriptor) {
+                    let method = &level_class.methods[index];
+                    if method.access_flags & ACC_NATIVE != 0 {
+                        let is_static = method.access_flags & ACC_STATIC != 0;
+                        // `RegisterNatives` binds the class itself; embedder
+                        // bindings and `Java_*` symbols name the class it
+                        // was defined as, whichever loader defined it.
+                        let symbolic = class_loader.symbolic_name(&name).into_owned();
+                        let bound = {
+                            let natives = self.natives.borrow();
+                            natives
+                                .lookup(&name, method_name, descriptor)
+                                .or_else(|| natives.lookup(&symbolic, method_name, descriptor))
+                        };
+                        let linked = bound.or_else(|| {
+                            self.libraries
+                                .find_method(&symbolic, method_name, descriptor)
+                                // SAFETY: the symbol name encodes this method.
+                                .map(|function| unsafe {
+                                    library::bind(function, &name, descriptor, is_static)
+                                })
+                        });
+                        return Some(linked.map(MethodTarget::Native).ok_or_else(|| {
+                            registry::describe_method(&symbolic, method_name, descriptor)
+                        }));
+                    }
+                    if method.code.is_some() {
+                        return Some(Ok(MethodTarget::Bytecode(level_class.clone(), index)));
+                    }
                 }
             }
+            level = self.superclass_of(class_loader, &name);
+        }
+        None
+    }
 
-            if heap.object_count() > 4096 {
-                gc.collect(heap, &stack);
+    /// Runs a linked method.
+    fn invoke_target(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        target: &MethodTarget,
+        receiver: Option<HeapValue>,
+        args: Vec<HeapValue>,
+    ) -> Option<HeapValue> {
+        match target {
+            MethodTarget::Native(method) => {
+                let mark = self.suspend_roots(receiver.iter().chain(&args));
+                let mut env = NativeEnv {
+                    interpreter: self,
+                    loader: class_loader,
+                    heap,
+                };
+                let result = method(&mut env, receiver.as_ref(), &args);
+                self.resume_roots(mark);
+                result
+            }
+            MethodTarget::Bytecode(runtime, method) => {
+                let mut locals = Vec::with_capacity(args.len() + 1);
+                locals.extend(receiver);
+                locals.extend(args);
+                self.invoke_method(class_loader, runtime, *method, heap, &locals)
             }
         }
+    }
 
-        None
+    /// Entry point for natives that need to call back into Java.
+    pub(crate) fn invoke_virtual(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        receiver: &HeapValue,
+        method_name: &str,
+        descriptor: &str,
+        args: &[HeapValue],
+    ) -> Option<HeapValue> {
+        let receiver_class = Self::receiver_class(receiver, "java/lang/Object");
+        let target = match self.link_method(
+            class_loader,
+            "java/lang/Object",
+            Some(receiver_class),
+            method_name,
+            descriptor,
+        )? {
+            Ok(target) => target,
+            Err(message) => {
+                self.throw_new(heap, "java/lang/UnsatisfiedLinkError", Some(&message));
+                return None;
+            }
+        };
+        self.invoke_target(
+            class_loader,
+            heap,
+            &target,
+            Some(receiver.clone()),
+            args.to_vec(),
+        )
     }
 
-    fn resolve_method<'a>(
-        loader: &'a mut ClassLoader,
+    /// Calls an instance method of `class_name`, dispatched on the class of
+    /// `receiver` as `invokevirtual` would.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn invoke_dispatched(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
         class_name: &str,
         method_name: &str,
         descriptor: &str,
-    ) -> Option<(ClassFile, MethodInfo)> {
-        let current_class = loader.load_class(class_name).ok()?;
-
-        if let Some(method) = current_class
-            .methods
-            .iter()
-            .find(|m| {
-                current_class.get_utf8(m.name_index).unwrap_or("") == method_name
-                    && current_class.get_utf8(m.descriptor_index).unwrap_or("") == descriptor
-            })
-            .cloned()
-        {
-            return Some((current_class.clone(), method));
-        }
-
-        if let Some(super_name) = current_class.get_class_name(current_class.super_class) {
-            if super_name != "java/lang/Object"
-                && !super_name.is_empty()
-                && super_name != class_name
-            {
-                return Self::resolve_method(loader, &super_name, method_name, descriptor);
+        receiver: &HeapValue,
+        args: &[HeapValue],
+    ) -> Option<HeapValue> {
+        let receiver_class = Self::receiver_class(receiver, class_name);
+        let target = match self.link_method(
+            class_loader,
+            class_name,
+            Some(receiver_class),
+            method_name,
+            descriptor,
+        )? {
+            Ok(target) => target,
+            Err(message) => {
+                self.throw_new(heap, "java/lang/UnsatisfiedLinkError", Some(&message));
+                return None;
             }
-        }
-        None
+        };
+        self.invoke_target(
+            class_loader,
+            heap,
+            &target,
+            Some(receiver.clone()),
+            args.to_vec(),
+        )
     }
 
-    fn branch_target(opcode_pc: usize, offset: i16, code_len: usize) -> Option<usize> {
-        let target = opcode_pc as isize + offset as isize;
-        if target < 0 || target as usize > code_len {
-            println!("Invalid branch target: pc={} offset={}", opcode_pc, offset);
+    /// Runs `class_name.method_name` without virtual dispatch: a static
+    /// method when `receiver` is `None`, otherwise an instance method as
+    /// `invokespecial` would. Static calls initialize the class first.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn invoke_nonvirtual(
+        &self,
+        class_loader: &mut ClassLoader,
+        heap: &mut Heap,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        receiver: Option<HeapValue>,
+        args: &[HeapValue],
+    ) -> Option<HeapValue> {
+        if receiver.is_none() && !self.ensure_class_initialized(class_loader, class_name, heap) {
             return None;
         }
-        Some(target as usize)
+        let target = match self.resolve_target(class_loader, class_name, method_name, descriptor)? {
+            Ok(target) => target,
+            Err(message) => {
+                self.throw_new(heap, "java/lang/UnsatisfiedLinkError", Some(&message));
+                return None;
+            }
+        };
+        self.invoke_target(class_loader, heap, &target, receiver, args.to_vec())
     }
 
-    fn resolve_method_ref<'a>(
-        class: &'a ClassFile,
-        index: u16,
-    ) -> Option<(&'a str, &'a str, &'a str)> {
-        match Self::safe_cp_get(class, index)? {
-            ConstantPoolEntry::MethodRef {
-                class_index,
-                name_and_type_index,
-            }
-            | ConstantPoolEntry::InterfaceMethodRef {
-                class_index,
-                name_and_type_index,
-            } => {
-                let class_name = class.get_class_name(*class_index)?;
-                let (method_name, descriptor) = class.get_name_and_type(*name_and_type_index)?;
-                Some((class_name, method_name, descriptor))
+    /// The class declaring `method_name descriptor` as seen from
+    /// `class_name`, searching superclasses and then interfaces, with the
+    /// method's access flags. Builtin classes only declare their natives.
+    pub(crate) fn find_method(
+        &self,
+        class_loader: &mut ClassLoader,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+    ) -> Option<(String, u16)> {
+        let mut level = Some(class_name.to_string());
+        let mut interfaces = Vec::new();
+        while let Some(name) = level {
+            if native::is_builtin_class(&name) {
+                let bound = self.natives.borrow().lookup(&name, method_name, descriptor);
+                if bound.is_some() {
+                    return Some((name, ACC_NATIVE));
+                }
+            } else if let Ok(runtime) = self.runtime_class(class_loader, &name) {
+                if let Some(index) = runtime.find_method(method_name, descriptor) {
+                    return Some((name, runtime.methods[index].access_flags));
+                }
+                interfaces.extend(runtime.interfaces.iter().cloned());
             }
-            _ => None,
+            level = self.superclass_of(class_loader, &name);
         }
+        interfaces.iter().find_map(|interface| {
+            self.find_method(class_loader, interface, method_name, descriptor)
+        })
     }
 
-    fn resolve_field_ref<'a>(
-        class: &'a ClassFile,
-        index: u16,
-    ) -> Option<(&'a str, &'a str, &'a str)> {
-        if let ConstantPoolEntry::FieldRef {
-            class_index,
-            name_and_type_index,
-        } = Self::safe_cp_get(class, index)?
-        {
-            let class_name = class.get_class_name(*class_index)?;
-            let (field_name, descriptor) = class.get_name_and_type(*name_and_type_index)?;
-            Some((class_name, field_name, descriptor))
-        } else {
-            None
+    fn branch_target(code: &DecodedCode, ip: usize, offset: i16) -> Option<usize> {
+        let target = code.target(ip);
+        if target.is_none() {
+            println!(
+                "Invalid branch target: pc={} offset={}",
+                code.offset(ip),
+                offset
+            );
         }
+        target
     }
 
-    fn resolve_invoke_dynamic<'a>(class: &'a ClassFile, index: u16) -> Option<(&'a str, &'a str)> {
+    fn resolve_invoke_dynamic(class: &ClassFile, index: u16) -> Option<(&str, &str)> {
         if let ConstantPoolEntry::InvokeDynamic {
             name_and_type_index,
             ..
@@ -599,51 +2170,49 @@ impl Interpreter {
         }
     }
 
-    fn ensure_class_initialized(
+    pub(crate) fn ensure_class_initialized(
         &self,
         class_loader: &mut ClassLoader,
         class_name: &str,
         heap: &mut Heap,
     ) -> bool {
-        let class = match class_loader.load_class(class_name) {
-            Ok(c) => c,
-            Err(_) if Self::is_builtin_runtime_class(class_name) => return true,
+        if class_loader.class_init_started(class_name) {
+            return true;
+        }
+        let runtime = match self.runtime_class(class_loader, class_name) {
+            Ok(runtime) => runtime,
+            Err(_) if native::is_builtin_class(class_name) => {
+                if class_loader.begin_class_init(class_name) {
+                    let mut env = NativeEnv {
+                        interpreter: self,
+                        loader: class_loader,
+                        heap,
+                    };
+                    native::initialize_builtin_class(&mut env, class_name);
+                    class_loader.finish_class_init(class_name);
+                }
+                return true;
+            }
+            Err(LoadError::Verify(e)) => {
+                self.throw_new(heap, "java/lang/VerifyError", Some(&e.to_string()));
+                return false;
+            }
             Err(e) => {
                 println!("Class initialization failed for {}: {}", class_name, e);
                 return false;
             }
         };
 
-        let canonical_name = class
-            .get_class_name(class.this_class)
-            .unwrap_or(class_name)
-            .to_string();
-        if !class_loader.begin_class_init(&canonical_name) {
+        if !class_loader.begin_class_init(&runtime.name) {
             return true;
         }
-
-        let has_clinit = class.methods.iter().any(|m| {
-            class.get_utf8(m.name_index).unwrap_or("") == "<clinit>"
-                && class.get_utf8(m.descriptor_index).unwrap_or("") == "()V"
-        });
-        if has_clinit {
-            let _ = self.execute_method(class_loader, &class, "<clinit>", "()V", heap, &[]);
+        if let Some(clinit) = runtime.find_method("<clinit>", "()V") {
+            let _ = self.invoke_method(class_loader, &runtime, clinit, heap, &[]);
         }
-        class_loader.finish_class_init(&canonical_name);
+        class_loader.finish_class_init(&runtime.name);
         true
     }
 
-    fn is_builtin_runtime_class(class_name: &str) -> bool {
-        matches!(
-            class_name,
-            "java/lang/Object"
-                | "java/lang/String"
-                | "java/lang/System"
-                | "java/io/PrintStream"
-                | "java/lang/Math"
-        )
-    }
-
     fn execute_invokedynamic(
         class: &ClassFile,
         index: u16,
@@ -687,13 +2256,13 @@ impl Interpreter {
             return None;
         };
 
-        let attr = Self::class_attribute(class, "BootstrapMethods")?;
-        let methods = Self::parse_bootstrap_methods(attr)?;
-        let (_, args) = methods.get(*bootstrap_method_attr_index as usize)?;
+        let method = class
+            .bootstrap_methods()
+            .get(*bootstrap_method_attr_index as usize)?;
         let mut constants = Vec::new();
         let mut recipe: Option<String> = None;
 
-        for cp_index in args {
+        for cp_index in &method.arguments {
             if let Some(text) = Self::constant_as_string(class, *cp_index) {
                 if recipe.is_none() {
                     recipe = Some(text);
@@ -705,38 +2274,6 @@ impl Interpreter {
         recipe.map(|r| (r, constants))
     }
 
-    fn class_attribute<'a>(class: &'a ClassFile, name: &str) -> Option<&'a AttributeInfo> {
-        class
-            .attributes
-            .iter()
-            .find(|attr| class.get_utf8(attr.name_index).unwrap_or("") == name)
-    }
-
-    fn parse_bootstrap_methods(attr: &AttributeInfo) -> Option<Vec<(u16, Vec<u16>)>> {
-        let mut cursor = 0usize;
-        let count = Self::read_u16(&attr.info, &mut cursor)? as usize;
-        let mut methods = Vec::with_capacity(count);
-        for _ in 0..count {
-            let method_ref = Self::read_u16(&attr.info, &mut cursor)?;
-            let arg_count = Self::read_u16(&attr.info, &mut cursor)? as usize;
-            let mut args = Vec::with_capacity(arg_count);
-            for _ in 0..arg_count {
-                args.push(Self::read_u16(&attr.info, &mut cursor)?);
-            }
-            methods.push((method_ref, args));
-        }
-        Some(methods)
-    }
-
-    fn read_u16(bytes: &[u8], cursor: &mut usize) -> Option<u16> {
-        if *cursor + 1 >= bytes.len() {
-            return None;
-        }
-        let value = ((bytes[*cursor] as u16) << 8) | (bytes[*cursor + 1] as u16);
-        *cursor += 2;
-        Some(value)
-    }
-
     fn concat_values(args: &[HeapValue], heap: &Heap) -> String {
         let mut out = String::new();
         for value in args {
@@ -802,7 +2339,7 @@ impl Interpreter {
         }
     }
 
-    fn push_constant(frame: &mut Frame, heap: &mut Heap, class: &ClassFile, index: u16) {
+    fn push_constant(&self, frame: &mut Frame, heap: &mut Heap, class: &ClassFile, index: u16) {
         if let Some(entry) = Self::safe_cp_get(class, index) {
             match entry {
                 ConstantPoolEntry::Integer(v) => frame.push(HeapValue::Int(*v)),
@@ -819,7 +2356,7 @@ impl Interpreter {
                 ConstantPoolEntry::Utf8(value) => frame.push(HeapValue::String(value.clone())),
                 ConstantPoolEntry::Class { name_index } => {
                     let class_name = class.get_utf8(*name_index).unwrap_or("");
-                    frame.push(HeapValue::String(class_name.to_string()));
+                    frame.push(self.class_mirror(heap, class_name));
                 }
                 _ => {
                     println!("Unsupported LDC entry {:?}", entry);
@@ -831,7 +2368,7 @@ impl Interpreter {
         }
     }
 
-    fn default_value_for_descriptor(descriptor: &str) -> HeapValue {
+    pub(crate) fn default_value_for_descriptor(descriptor: &str) -> HeapValue {
         match descriptor.chars().next() {
             Some('Z') | Some('B') | Some('C') | Some('S') | Some('I') => HeapValue::Int(0),
             Some('J') => HeapValue::Long(0),
@@ -841,7 +2378,14 @@ impl Interpreter {
         }
     }
 
-    fn exec_instr(frame: &mut Frame, heap: &mut Heap, class: &ClassFile, instr: Instruction) {
+    fn exec_instr(
+        &self,
+        frame: &mut Frame,
+        heap: &mut Heap,
+        runtime: &RuntimeClass,
+        instr: Instruction,
+    ) {
+        let class = &runtime.class;
         match instr {
             Instruction::New(index) => {
                 if let Some(class_name) = class.get_class_name(index) {
@@ -857,6 +2401,91 @@ impl Interpreter {
                     println!("DUP");
                 }
             }
+            Instruction::DupX1 => {
+                let v1 = frame.pop();
+                let v2 = frame.pop();
+                frame.push(v1.clone());
+                frame.push(v2);
+                frame.push(v1);
+            }
+            Instruction::DupX2 => {
+                let v1 = frame.pop();
+                let v2 = frame.pop();
+                if v2.is_wide() {
+                    frame.push(v1.clone());
+                    frame.push(v2);
+                } else {
+                    let v3 = frame.pop();
+                    frame.push(v1.clone());
+                    frame.push(v3);
+                    frame.push(v2);
+                }
+                frame.push(v1);
+            }
+            Instruction::Dup2 => {
+                let v1 = frame.pop();
+                if v1.is_wide() {
+                    frame.push(v1.clone());
+                    frame.push(v1);
+                } else {
+                    let v2 = frame.pop();
+                    frame.push(v2.clone());
+                    frame.push(v1.clone());
+                    frame.push(v2);
+                    frame.push(v1);
+                }
+            }
+            Instruction::Dup2X1 => {
+                let v1 = frame.pop();
+                if v1.is_wide() {
+                    let v2 = frame.pop();
+                    frame.push(v1.clone());
+                    frame.push(v2);
+                    frame.push(v1);
+                } else {
+                    let v2 = frame.pop();
+                    let v3 = frame.pop();
+                    frame.push(v2.clone());
+                    frame.push(v1.clone());
+                    frame.push(v3);
+                    frame.push(v2);
+                    frame.push(v1);
+                }
+            }
+            Instruction::Dup2X2 => {
+                let v1 = frame.pop();
+                let top: Vec<HeapValue> = if v1.is_wide() {
+                    vec![v1]
+                } else {
+                    let v2 = frame.pop();
+                    vec![v2, v1]
+                };
+                let v3 = frame.pop();
+                let below: Vec<HeapValue> = if v3.is_wide() {
+                    vec![v3]
+                } else {
+                    let v4 = frame.pop();
+                    vec![v4, v3]
+                };
+                for value in top.iter().chain(below.iter()).chain(top.iter()) {
+                    frame.push(value.clone());
+                }
+            }
+
+            Instruction::Pop => {
+                let _ = frame.pop();
+            }
+            Instruction::Pop2 => {
+                if !frame.pop().is_wide() {
+                    let _ = frame.pop();
+                }
+            }
+            Instruction::Swap => {
+                let v1 = frame.pop();
+                let v2 = frame.pop();
+                frame.push(v1);
+                frame.push(v2);
+            }
 
             Instruction::AStore(index) => {
                 let val = frame.pop();
@@ -880,11 +2509,30 @@ impl Interpreter {
                     println!("ILOAD[{}] -> {:?}", index, val);
                 }
             }
+            Instruction::LStore(index)
+            | Instruction::FStore(index)
+            | Instruction::DStore(index) => {
+                let val = frame.pop();
+                frame.set_local(index as usize, val);
+            }
+            Instruction::LLoad(index) | Instruction::FLoad(index) | Instruction::DLoad(index) => {
+                let val = frame
+                    .get_local(index as usize)
+                    .cloned()
+                    .unwrap_or(HeapValue::Null);
+                frame.push(val);
+            }
 
+            Instruction::AConstNull => {
+                frame.push(HeapValue::Null);
+            }
             Instruction::IConst(v) => {
                 frame.push(HeapValue::Int(v));
                 println!("ICONST {}", v);
             }
+            Instruction::LConst(v) => frame.push(HeapValue::Long(v)),
+            Instruction::FConst(v) => frame.push(HeapValue::Float(v)),
+            Instruction::DConst(v) => frame.push(HeapValue::Double(v)),
             Instruction::BiPush(v) => {
                 frame.push(HeapValue::Int(v as i32));
                 println!("BIPUSH {}", v);
@@ -896,86 +2544,275 @@ impl Interpreter {
             Instruction::IAdd => {
                 let b = frame.pop();
                 let a = frame.pop();
-                let res = HeapValue::Int(a.as_int() + b.as_int());
+                let res = HeapValue::Int(a.as_int().wrapping_add(b.as_int()));
                 frame.push(res.clone());
                 println!("➕ IADD = {:?}", res);
             }
             Instruction::ISub => {
                 let b = frame.pop();
                 let a = frame.pop();
-                let res = HeapValue::Int(a.as_int() - b.as_int());
+                let res = HeapValue::Int(a.as_int().wrapping_sub(b.as_int()));
                 frame.push(res.clone());
                 println!("ISUB = {:?}", res);
             }
             Instruction::IMul => {
                 let b = frame.pop();
                 let a = frame.pop();
-                let res = HeapValue::Int(a.as_int() * b.as_int());
+                let res = HeapValue::Int(a.as_int().wrapping_mul(b.as_int()));
                 frame.push(res.clone());
                 println!("IMUL = {:?}", res);
             }
-            Instruction::IDiv => {
-                let b = frame.pop();
-                let a = frame.pop();
-                if b.as_int() == 0 {
-                    println!("Division by zero");
+            Instruction::IDiv | Instruction::IRem => {
+                let b = frame.pop_int();
+                let a = frame.pop_int();
+                if b == 0 {
+                    self.throw_new(heap, "java/lang/ArithmeticException", Some("/ by zero"));
+                } else if matches!(instr, Instruction::IDiv) {
+                    frame.push_int(a.wrapping_div(b));
+                } else {
+                    frame.push_int(a.wrapping_rem(b));
+                }
+            }
+            Instruction::INeg => {
+                let a = frame.pop_int();
+                frame.push_int(a.wrapping_neg());
+            }
+            Instruction::IShl | Instruction::IShr | Instruction::IUShr => {
+                let shift = (frame.pop_int() & 0x1F) as u32;
+                let a = frame.pop_int();
+                frame.push_int(match instr {
+                    Instruction::IShl => a.wrapping_shl(shift),
+                    Instruction::IShr => a >> shift,
+                    _ => ((a as u32) >> shift) as i32,
+                });
+            }
+            Instruction::IAnd | Instruction::IOr | Instruction::IXor => {
+                let b = frame.pop_int();
+                let a = frame.pop_int();
+                frame.push_int(match instr {
+                    Instruction::IAnd => a & b,
+                    Instruction::IOr => a | b,
+                    _ => a ^ b,
+                });
+            }
+
+            Instruction::LAdd
+            | Instruction::LSub
+            | Instruction::LMul
+            | Instruction::LAnd
+            | Instruction::LOr
+            | Instruction::LXor => {
+                let b = frame.pop_long();
+                let a = frame.pop_long();
+                frame.push_long(match instr {
+                    Instruction::LAdd => a.wrapping_add(b),
+                    Instruction::LSub => a.wrapping_sub(b),
+                    Instruction::LMul => a.wrapping_mul(b),
+                    Instruction::LAnd => a & b,
+                    Instruction::LOr => a | b,
+                    _ => a ^ b,
+                });
+            }
+            Instruction::LDiv | Instruction::LRem => {
+                let b = frame.pop_long();
+                let a = frame.pop_long();
+                if b == 0 {
+                    self.throw_new(heap, "java/lang/ArithmeticException", Some("/ by zero"));
+                } else if matches!(instr, Instruction::LDiv) {
+                    frame.push_long(a.wrapping_div(b));
                 } else {
-                    let res = HeapValue::Int(a.as_int() / b.as_int());
-                    frame.push(res.clone());
-                    println!("IDIV = {:?}", res);
+                    frame.push_long(a.wrapping_rem(b));
                 }
             }
+            Instruction::LNeg => {
+                let a = frame.pop_long();
+                frame.push_long(a.wrapping_neg());
+            }
+            Instruction::LShl | Instruction::LShr | Instruction::LUShr => {
+                let shift = (frame.pop_int() & 0x3F) as u32;
+                let a = frame.pop_long();
+                frame.push_long(match instr {
+                    Instruction::LShl => a.wrapping_shl(shift),
+                    Instruction::LShr => a >> shift,
+                    _ => ((a as u64) >> shift) as i64,
+                });
+            }
+
+            // Rust's float `%` truncates like C's fmod, which is what
+            // frem/drem specify.
+            Instruction::FAdd
+            | Instruction::FSub
+            | Instruction::FMul
+            | Instruction::FDiv
+            | Instruction::FRem => {
+                let b = frame.pop().as_float();
+                let a = frame.pop().as_float();
+                frame.push(HeapValue::Float(match instr {
+                    Instruction::FAdd => a + b,
+                    Instruction::FSub => a - b,
+                    Instruction::FMul => a * b,
+                    Instruction::FDiv => a / b,
+                    _ => a % b,
+                }));
+            }
+            Instruction::DAdd
+            | Instruction::DSub
+            | Instruction::DMul
+            | Instruction::DDiv
+            | Instruction::DRem => {
+                let b = frame.pop().as_double();
+                let a = frame.pop().as_double();
+                frame.push(HeapValue::Double(match instr {
+                    Instruction::DAdd => a + b,
+                    Instruction::DSub => a - b,
+                    Instruction::DMul => a * b,
+                    Instruction::DDiv => a / b,
+                    _ => a % b,
+                }));
+            }
+            Instruction::FNeg => {
+                let a = frame.pop().as_float();
+                frame.push(HeapValue::Float(-a));
+            }
+            Instruction::DNeg => {
+                let a = frame.pop().as_double();
+                frame.push(HeapValue::Double(-a));
+            }
+
+            // Float-to-integer `as` casts saturate and map NaN to zero,
+            // exactly as f2i/f2l/d2i/d2l require.
+            Instruction::I2L => {
+                let a = frame.pop_int();
+                frame.push_long(a as i64);
+            }
+            Instruction::I2F => {
+                let a = frame.pop_int();
+                frame.push(HeapValue::Float(a as f32));
+            }
+            Instruction::I2D => {
+                let a = frame.pop_int();
+                frame.push(HeapValue::Double(a as f64));
+            }
+            Instruction::L2I => {
+                let a = frame.pop_long();
+                frame.push_int(a as i32);
+            }
+            Instruction::L2F => {
+                let a = frame.pop_long();
+                frame.push(HeapValue::Float(a as f32));
+            }
+            Instruction::L2D => {
+                let a = frame.pop_long();
+                frame.push(HeapValue::Double(a as f64));
+            }
+            Instruction::F2I => {
+                let a = frame.pop().as_float();
+                frame.push_int(a as i32);
+            }
+            Instruction::F2L => {
+                let a = frame.pop().as_float();
+                frame.push_long(a as i64);
+            }
+            Instruction::F2D => {
+                let a = frame.pop().as_float();
+                frame.push(HeapValue::Double(a as f64));
+            }
+            Instruction::D2I => {
+                let a = frame.pop().as_double();
+                frame.push_int(a as i32);
+            }
+            Instruction::D2L => {
+                let a = frame.pop().as_double();
+                frame.push_long(a as i64);
+            }
+            Instruction::D2F => {
+                let a = frame.pop().as_double();
+                frame.push(HeapValue::Float(a as f32));
+            }
+            Instruction::I2B => {
+                let a = frame.pop_int();
+                frame.push_int(a as i8 as i32);
+            }
+            Instruction::I2C => {
+                let a = frame.pop_int();
+                frame.push_int(a as u16 as i32);
+            }
+            Instruction::I2S => {
+                let a = frame.pop_int();
+                frame.push_int(a as i16 as i32);
+            }
+
+            Instruction::LCmp => {
+                let b = frame.pop_long();
+                let a = frame.pop_long();
+                frame.push_int(a.cmp(&b) as i32);
+            }
+            Instruction::FCmpL | Instruction::FCmpG => {
+                let b = frame.pop().as_float();
+                let a = frame.pop().as_float();
+                let nan_result = if matches!(instr, Instruction::FCmpG) {
+                    1
+                } else {
+                    -1
+                };
+                frame.push_int(a.partial_cmp(&b).map_or(nan_result, |o| o as i32));
+            }
+            Instruction::DCmpL | Instruction::DCmpG => {
+                let b = frame.pop().as_double();
+                let a = frame.pop().as_double();
+                let nan_result = if matches!(instr, Instruction::DCmpG) {
+                    1
+                } else {
+                    -1
+                };
+                frame.push_int(a.partial_cmp(&b).map_or(nan_result, |o| o as i32));
+            }
+
+            Instruction::ArrayLength => match frame.pop() {
+                HeapValue::Array(arr) => {
+                    let len = heap
+                        .get_array(arr.id)
+                        .map_or(arr.content.len(), |a| a.content.len());
+                    frame.push_int(len as i32);
+                }
+                _ => self.throw_new(heap, "java/lang/NullPointerException", None),
+            },
 
-            Instruction::Ldc(index) => Self::push_constant(frame, heap, class, u16::from(index)),
+            Instruction::Ldc(index) => self.push_constant(frame, heap, class, u16::from(index)),
             Instruction::LdcW(index) | Instruction::Ldc2W(index) => {
-                Self::push_constant(frame, heap, class, index)
+                self.push_constant(frame, heap, class, index)
             }
 
             Instruction::GetField(index) => {
                 let obj_ref = frame.pop();
-                if let HeapValue::Object(obj) = obj_ref {
-                    if let Some(ConstantPoolEntry::FieldRef {
-                        name_and_type_index,
-                        ..
-                    }) = Self::safe_cp_get(class, index)
-                    {
-                        if let Some(ConstantPoolEntry::NameAndType { name_index, .. }) =
-                            class.constant_pool.get((*name_and_type_index - 1) as usize)
-                        {
-                            let field_name = class.get_utf8(*name_index).unwrap_or("<unknown>");
-                            let val = heap
-                                .get(obj.id)
-                                .and_then(|real| real.fields.get(field_name))
-                                .cloned();
-                            if let Some(val) = val {
-                                frame.push(val.clone());
-                                println!("GETFIELD {} -> {:?}", field_name, val);
-                            }
-                        }
-                    }
+                let HeapValue::Object(obj) = obj_ref else {
+                    self.throw_new(heap, "java/lang/NullPointerException", None);
+                    return;
+                };
+                if let Some(field) = runtime.field_ref(index) {
+                    let val = heap
+                        .get(obj.id)
+                        .and_then(|real| real.fields.get(&field.name))
+                        .cloned()
+                        .unwrap_or_else(|| Self::default_value_for_descriptor(&field.descriptor));
+                    frame.push(val.clone());
+                    println!("GETFIELD {} -> {:?}", field.name, val);
                 }
             }
 
             Instruction::PutField(index) => {
                 let value = frame.pop();
                 let obj_ref = frame.pop();
-                if let HeapValue::Object(mut obj) = obj_ref {
-                    if let Some(ConstantPoolEntry::FieldRef {
-                        name_and_type_index,
-                        ..
-                    }) = Self::safe_cp_get(class, index)
-                    {
-                        if let Some(ConstantPoolEntry::NameAndType { name_index, .. }) =
-                            class.constant_pool.get((*name_and_type_index - 1) as usize)
-                        {
-                            let field_name = class.get_utf8(*name_index).unwrap_or("<unknown>");
-                            obj.fields.insert(field_name.to_string(), value.clone());
-                            if let Some(target) = heap.get_mut(obj.id) {
-                                target.fields.insert(field_name.to_string(), value.clone());
-                            }
-                            println!("PUTFIELD {} = {:?}", field_name, value);
-                        }
+                let HeapValue::Object(mut obj) = obj_ref else {
+                    self.throw_new(heap, "java/lang/NullPointerException", None);
+                    return;
+                };
+                if let Some(field) = runtime.field_ref(index) {
+                    obj.fields.insert(field.name.clone(), value.clone());
+                    if let Some(target) = heap.get_mut(obj.id) {
+                        target.fields.insert(field.name.clone(), value.clone());
                     }
+                    println!("PUTFIELD {} = {:?}", field.name, value);
                 }
             }
 
@@ -993,10 +2830,7 @@ impl Interpreter {
         }
     }
 
-    fn safe_cp_get<'a>(class: &'a ClassFile, index: u16) -> Option<&'a ConstantPoolEntry> {
-        if index == 0 {
-            return None;
-        }
-        class.constant_pool.get((index - 1) as usize)
+    fn safe_cp_get(class: &ClassFile, index: u16) -> Option<&ConstantPoolEntry> {
+        class.constant(index)
     }
 }
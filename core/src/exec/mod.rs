@@ -1,2 +1,4 @@
+pub mod decoded;
 pub mod instructions;
 pub mod interpreter;
+pub mod runtime_class;
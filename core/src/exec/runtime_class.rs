@@ -0,0 +1,291 @@
+use crate::bytecode::parser::{ClassFile, ConstantPoolEntry};
+use crate::exec::decoded::DecodedCode;
+use crate::exec::interpreter::Interpreter;
+use crate::jit::MethodJit;
+use crate::loader::class_loader::{self, Linkage, LoaderId, APP_LOADER};
+use crate::native::registry::NativeMethod;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// A loaded class as the interpreter runs it: methods decoded once, and a
+/// constant-pool cache that remembers what each symbolic reference
+/// resolved to, much like HotSpot's quickened bytecodes.
+pub struct RuntimeClass {
+    pub class: ClassFile,
+    pub name: String,
+    pub source_file: Option<String>,
+    /// `None` above `java/lang/Object`.
+    pub superclass: Option<String>,
+    pub interfaces: Vec<String>,
+    /// Id of the defining loader.
+    pub loader: LoaderId,
+    pub methods: Vec<RuntimeMethod>,
+    pool: RefCell<Vec<Option<Resolved>>>,
+    /// Entries whose class has been resolved through a user-defined
+    /// defining loader; the rest still name their class symbolically.
+    linked: RefCell<Vec<bool>>,
+}
+
+pub struct RuntimeMethod {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+    pub code: Option<DecodedCode>,
+    pub(crate) jit: MethodJit,
+}
+
+/// A resolved constant-pool entry.
+#[derive(Clone)]
+enum Resolved {
+    Class(Rc<ClassRef>),
+    Field(Rc<FieldRef>),
+    Method(Rc<MethodRef>),
+}
+
+pub struct ClassRef {
+    pub name: String,
+    /// Set once the class is known to be initialized.
+    pub initialized: Cell<bool>,
+}
+
+pub struct FieldRef {
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+    pub initialized: Cell<bool>,
+}
+
+pub struct MethodRef {
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+    pub arg_count: usize,
+    pub initialized: Cell<bool>,
+    /// The last link of this call site.
+    pub site: RefCell<Option<CallSite>>,
+}
+
+/// What an invoke instruction was linked to.
+#[derive(Clone)]
+pub enum MethodTarget {
+    Native(NativeMethod),
+    /// Bytecode declared by a class, by method index.
+    Bytecode(Rc<RuntimeClass>, usize),
+}
+
+/// A linked invoke instruction. Virtual call sites remember the receiver
+/// class they were linked for and relink when another one shows up; all
+/// sites relink when natives are rebound or a class is redefined, which
+/// bumps the link epoch.
+#[derive(Clone)]
+pub struct CallSite {
+    pub receiver_class: Option<String>,
+    pub epoch: u64,
+    pub target: MethodTarget,
+}
+
+impl RuntimeClass {
+    pub fn new(class: ClassFile) -> Self {
+        let name = class
+            .get_class_name(class.this_class)
+            .unwrap_or("<unknown>")
+            .to_string();
+        let source_file = class.source_file().map(str::to_string);
+        let superclass = match class.get_class_name(class.super_class) {
+            Some(parent) => Some(parent.to_string()),
+            None if name == "java/lang/Object" => None,
+            None => Some("java/lang/Object".to_string()),
+        };
+        let interfaces = class
+            .interfaces
+            .iter()
+            .filter_map(|index| class.get_class_name(*index).map(str::to_string))
+            .collect();
+        let methods = class
+            .methods
+            .iter()
+            .map(|method| RuntimeMethod {
+                name: class.get_utf8(method.name_index).unwrap_or("").to_string(),
+                descriptor: class
+                    .get_utf8(method.descriptor_index)
+                    .unwrap_or("")
+                    .to_string(),
+                access_flags: method.access_flags,
+                code: method
+                    .code
+                    .as_ref()
+                    .map(|code_attr| DecodedCode::decode(&code_attr.code)),
+                jit: MethodJit::default(),
+            })
+            .collect();
+        let pool = RefCell::new(vec![None; class.constant_pool.len() + 1]);
+        let linked = RefCell::new(vec![false; class.constant_pool.len() + 1]);
+        Self {
+            class,
+            name,
+            source_file,
+            superclass,
+            interfaces,
+            loader: APP_LOADER,
+            methods,
+            pool,
+            linked,
+        }
+    }
+
+    /// A class defined by a user-defined loader, running as `runtime_name`.
+    pub fn linked(class: ClassFile, runtime_name: &str, linkage: &Linkage) -> Self {
+        let mut runtime = Self::new(class);
+        runtime.name = runtime_name.to_string();
+        runtime.superclass = linkage.superclass.clone();
+        runtime.interfaces = linkage.interfaces.clone();
+        runtime.loader = linkage.loader;
+        runtime
+    }
+
+    /// The name the class was defined under, which differs from `name`
+    /// for a class of a user-defined loader.
+    pub fn symbolic_name(&self) -> &str {
+        self.class
+            .get_class_name(self.class.this_class)
+            .unwrap_or(&self.name)
+    }
+
+    /// Index of the method declared as `name descriptor`.
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<usize> {
+        self.methods
+            .iter()
+            .position(|method| method.name == name && method.descriptor == descriptor)
+    }
+
+    pub fn class_ref(&self, index: u16) -> Option<Rc<ClassRef>> {
+        if let Some(Resolved::Class(cached)) = self.cached(index) {
+            return Some(cached);
+        }
+        let resolved = Rc::new(ClassRef {
+            name: self.class.get_class_name(index)?.to_string(),
+            initialized: Cell::new(false),
+        });
+        self.cache(index, Resolved::Class(resolved.clone()));
+        Some(resolved)
+    }
+
+    pub fn field_ref(&self, index: u16) -> Option<Rc<FieldRef>> {
+        if let Some(Resolved::Field(cached)) = self.cached(index) {
+            return Some(cached);
+        }
+        let ConstantPoolEntry::FieldRef {
+            class_index,
+            name_and_type_index,
+        } = self.class.constant(index)?
+        else {
+            return None;
+        };
+        let (name, descriptor) = self.class.get_name_and_type(*name_and_type_index)?;
+        let resolved = Rc::new(FieldRef {
+            class_name: self.class.get_class_name(*class_index)?.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            initialized: Cell::new(false),
+        });
+        self.cache(index, Resolved::Field(resolved.clone()));
+        Some(resolved)
+    }
+
+    pub fn method_ref(&self, index: u16) -> Option<Rc<MethodRef>> {
+        if let Some(Resolved::Method(cached)) = self.cached(index) {
+            return Some(cached);
+        }
+        let (ConstantPoolEntry::MethodRef {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolEntry::InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        }) = self.class.constant(index)?
+        else {
+            return None;
+        };
+        let (name, descriptor) = self.class.get_name_and_type(*name_and_type_index)?;
+        let resolved = Rc::new(MethodRef {
+            class_name: self.class.get_class_name(*class_index)?.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            arg_count: Interpreter::count_args(descriptor),
+            initialized: Cell::new(false),
+            site: RefCell::new(None),
+        });
+        self.cache(index, Resolved::Method(resolved.clone()));
+        Some(resolved)
+    }
+
+    /// Whether the entry at `index` names its class by runtime name.
+    pub fn is_linked(&self, index: u16) -> bool {
+        class_loader::is_builtin_loader(self.loader)
+            || self.linked.borrow().get(index as usize) == Some(&true)
+    }
+
+    /// The class a class, field or method entry names, as written.
+    pub fn symbolic_class(&self, index: u16) -> Option<&str> {
+        match self.class.constant(index)? {
+            ConstantPoolEntry::Class { .. } => self.class.get_class_name(index),
+            ConstantPoolEntry::FieldRef { class_index, .. }
+            | ConstantPoolEntry::MethodRef { class_index, .. }
+            | ConstantPoolEntry::InterfaceMethodRef { class_index, .. } => {
+                self.class.get_class_name(*class_index)
+            }
+            _ => None,
+        }
+    }
+
+    /// Caches the entry at `index` as naming `class_name`, the runtime
+    /// name its class resolved to through the defining loader.
+    pub fn link(&self, index: u16, class_name: &str) {
+        let resolved = match self.class.constant(index) {
+            Some(ConstantPoolEntry::Class { .. }) => Resolved::Class(Rc::new(ClassRef {
+                name: class_name.to_string(),
+                initialized: Cell::new(false),
+            })),
+            Some(ConstantPoolEntry::FieldRef { .. }) => {
+                let Some(field) = self.field_ref(index) else {
+                    return;
+                };
+                Resolved::Field(Rc::new(FieldRef {
+                    class_name: class_name.to_string(),
+                    name: field.name.clone(),
+                    descriptor: field.descriptor.clone(),
+                    initialized: Cell::new(false),
+                }))
+            }
+            Some(_) => {
+                let Some(method) = self.method_ref(index) else {
+                    return;
+                };
+                Resolved::Method(Rc::new(MethodRef {
+                    class_name: class_name.to_string(),
+                    name: method.name.clone(),
+                    descriptor: method.descriptor.clone(),
+                    arg_count: method.arg_count,
+                    initialized: Cell::new(false),
+                    site: RefCell::new(None),
+                }))
+            }
+            None => return,
+        };
+        self.cache(index, resolved);
+        if let Some(linked) = self.linked.borrow_mut().get_mut(index as usize) {
+            *linked = true;
+        }
+    }
+
+    fn cached(&self, index: u16) -> Option<Resolved> {
+        self.pool.borrow().get(index as usize).cloned().flatten()
+    }
+
+    fn cache(&self, index: u16, resolved: Resolved) {
+        if let Some(slot) = self.pool.borrow_mut().get_mut(index as usize) {
+            *slot = Some(resolved);
+        }
+    }
+}
@@ -0,0 +1,890 @@
+//! The command sets the agent answers. Commands outside them, and those
+//! needing capabilities the agent does not claim, are `NOT_IMPLEMENTED`.
+
+use crate::bytecode::attributes::Attribute;
+use crate::loader::class_loader::LoadError;
+use crate::native::{java_lang_class, java_lang_throwable};
+use crate::runtime::heap::HeapValue;
+
+use super::events::{EventRequest, BREAKPOINT, SINGLE_STEP};
+use super::packet::*;
+use super::{
+    descriptor_tag, line_of, line_table, object_value, read_value, reference_id, signature,
+    write_object, write_value, Agent, Context, BUILTIN_TYPES, MAIN_GROUP, MAIN_THREAD,
+};
+
+const ACC_STATIC: u16 = 0x0008;
+
+// Thread statuses.
+const THREAD_RUNNING: i32 = 1;
+const SUSPEND_STATUS_SUSPENDED: i32 = 1;
+
+/// `CapabilitiesNew`, in protocol order: bytecodes, synthetic
+/// attributes, redefinition, instance filters, VM death requests and
+/// source name filters.
+const CAPABILITIES: [bool; 32] = {
+    let mut capabilities = [false; 32];
+    capabilities[2] = true;
+    capabilities[3] = true;
+    capabilities[7] = true;
+    capabilities[11] = true;
+    capabilities[13] = true;
+    capabilities[18] = true;
+    capabilities
+};
+
+pub(super) fn handle(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: &Command,
+) -> Result<Writer, u16> {
+    let mut reader = Reader::new(&command.data);
+    let mut reply = Writer::default();
+    let input = &mut reader;
+    let out = &mut reply;
+    match command.command_set {
+        1 => virtual_machine(agent, ctx, command.command, input, out)?,
+        2 => reference_type(agent, ctx, command.command, input, out)?,
+        3 => class_type(agent, ctx, command.command, input, out)?,
+        6 => method(agent, ctx, command.command, input, out)?,
+        9 => object_reference(agent, ctx, command.command, input, out)?,
+        10 => string_reference(ctx, command.command, input, out)?,
+        11 => thread_reference(agent, ctx, command.command, input, out)?,
+        12 => thread_group_reference(command.command, input, out)?,
+        13 => array_reference(ctx, command.command, input, out)?,
+        15 => event_request(agent, ctx, command.command, input, out)?,
+        16 => stack_frame(agent, ctx, command.command, input, out)?,
+        17 => class_object_reference(agent, ctx, command.command, input, out)?,
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(reply)
+}
+
+fn virtual_machine(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    let interpreter = ctx.env.interpreter;
+    match command {
+        // Version
+        1 => {
+            let version = interpreter
+                .property("java.version")
+                .unwrap_or_else(|| "17".to_string());
+            let vm_name = interpreter
+                .property("java.vm.name")
+                .unwrap_or_else(|| "AriaJDK 64-Bit Server VM".to_string());
+            out.string(&format!(
+                "Java Debug Wire Protocol (Reference Implementation) version 17.0\nJVM Debug Interface version 17.0\nJVM version {} ({}, interpreted mode)",
+                version, vm_name
+            ))
+            .int(17)
+            .int(0)
+            .string(&version)
+            .string(&vm_name);
+        }
+        // ClassesBySignature
+        2 => {
+            let signature = input.string()?;
+            let names = classes_by_signature(ctx, &signature);
+            out.int(names.len() as i32);
+            for name in names {
+                let tag = agent.type_tag(ctx, &name);
+                let status = agent.status(ctx, &name);
+                out.u8(tag).u64(agent.type_id(&name)).int(status);
+            }
+        }
+        // AllClasses and AllClassesWithGeneric
+        3 | 20 => {
+            let names = all_classes(ctx);
+            out.int(names.len() as i32);
+            for name in names {
+                let tag = agent.type_tag(ctx, &name);
+                let status = agent.status(ctx, &name);
+                out.u8(tag)
+                    .u64(agent.type_id(&name))
+                    .string(&signature(ctx.env.loader, &name));
+                if command == 20 {
+                    out.string("");
+                }
+                out.int(status);
+            }
+        }
+        // AllThreads
+        4 => {
+            out.int(1).u64(MAIN_THREAD);
+        }
+        // TopLevelThreadGroups
+        5 => {
+            out.int(1).u64(MAIN_GROUP);
+        }
+        // Dispose: the agent lets go once the reply is out.
+        6 => {}
+        // IDSizes: fields, methods, objects, reference types and frames.
+        7 => {
+            for _ in 0..5 {
+                out.int(8);
+            }
+        }
+        // Suspend
+        8 => agent.suspend(),
+        // Resume
+        9 => agent.resume(),
+        // Exit: the agent exits once the reply is out.
+        10 => {
+            input.int()?;
+        }
+        // CreateString
+        11 => {
+            let value = input.string()?;
+            let string = ctx.env.heap.alloc_string(&value);
+            out.u64(reference_id(&string));
+        }
+        // Capabilities
+        12 => {
+            for capability in &CAPABILITIES[..7] {
+                out.boolean(*capability);
+            }
+        }
+        // ClassPaths
+        13 => {
+            let base = interpreter.property("user.dir").unwrap_or_default();
+            let paths = ctx.env.loader.search_paths();
+            out.string(&base).int(paths.len() as i32);
+            for path in paths {
+                out.string(&path.to_string_lossy());
+            }
+            out.int(0);
+        }
+        // DisposeObjects
+        14 => {
+            for _ in 0..input.int()? {
+                let object = input.u64()?;
+                input.int()?;
+                agent.pinned.remove(&object);
+            }
+        }
+        // HoldEvents, ReleaseEvents and SetDefaultStratum
+        15 | 16 | 19 => {}
+        // CapabilitiesNew
+        17 => {
+            for capability in CAPABILITIES {
+                out.boolean(capability);
+            }
+        }
+        // RedefineClasses
+        18 => {
+            let mut classes = Vec::new();
+            for _ in 0..input.int()? {
+                let type_id = input.u64()?;
+                let length = usize::try_from(input.int()?).map_err(|_| ILLEGAL_ARGUMENT)?;
+                let bytes = input.bytes(length)?;
+                let name = agent.type_name(type_id).ok_or(INVALID_CLASS)?;
+                if agent.class(ctx, type_id)?.is_none() {
+                    return Err(NOT_IMPLEMENTED);
+                }
+                classes.push((name.to_string(), bytes));
+            }
+            for (name, bytes) in classes {
+                interpreter
+                    .redefine_class(ctx.env.loader, &name, bytes)
+                    .map_err(|e| redefinition_error(&e))?;
+            }
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+/// The JDWP error for a redefinition the loader refused.
+fn redefinition_error(error: &LoadError) -> u16 {
+    match error {
+        LoadError::NotFound(_) => INVALID_CLASS,
+        LoadError::Verify(_) => FAILS_VERIFICATION,
+        LoadError::WrongName { .. } => NAMES_DONT_MATCH,
+        LoadError::Redefinition(reason) => match reason.as_str() {
+            "attempted to change superclass or interfaces" => HIERARCHY_CHANGE_NOT_IMPLEMENTED,
+            "attempted to change the class modifiers" => CLASS_MODIFIERS_CHANGE_NOT_IMPLEMENTED,
+            "attempted to add a method" => ADD_METHOD_NOT_IMPLEMENTED,
+            "attempted to delete a method" => DELETE_METHOD_NOT_IMPLEMENTED,
+            "attempted to change method modifiers" => METHOD_MODIFIERS_CHANGE_NOT_IMPLEMENTED,
+            _ => SCHEMA_CHANGE_NOT_IMPLEMENTED,
+        },
+        _ => INVALID_CLASS_FORMAT,
+    }
+}
+
+/// The loaded classes a JNI signature names: one per defining loader.
+fn classes_by_signature(ctx: &mut Context, signature: &str) -> Vec<String> {
+    if signature.starts_with('[') {
+        return vec![signature.to_string()];
+    }
+    let Some(name) = signature
+        .strip_prefix('L')
+        .and_then(|name| name.strip_suffix(';'))
+    else {
+        return Vec::new();
+    };
+    if crate::native::is_builtin_class(name) {
+        return vec![name.to_string()];
+    }
+    ctx.env
+        .interpreter
+        .loaded_classes()
+        .into_iter()
+        .filter(|class| class.symbolic_name() == name)
+        .map(|class| class.name.clone())
+        .collect()
+}
+
+fn all_classes(ctx: &mut Context) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_TYPES
+        .into_iter()
+        .chain(java_lang_throwable::throwable_classes())
+        .map(str::to_string)
+        .collect();
+    names.extend(
+        ctx.env
+            .interpreter
+            .loaded_classes()
+            .into_iter()
+            .map(|class| class.name.clone()),
+    );
+    names
+}
+
+fn reference_type(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    let type_id = input.u64()?;
+    let name = agent.type_name(type_id).ok_or(INVALID_CLASS)?.to_string();
+    let class = agent.class(ctx, type_id)?;
+    match command {
+        // Signature and SignatureWithGeneric
+        1 | 13 => {
+            out.string(&signature(ctx.env.loader, &name));
+            if command == 13 {
+                let generic = class
+                    .as_ref()
+                    .and_then(|class| generic_signature(&class.class, &class.class.attributes));
+                out.string(generic.unwrap_or(""));
+            }
+        }
+        // ClassLoader
+        2 => {
+            let loader = class
+                .as_ref()
+                .and_then(|class| ctx.env.loader.loader_object(class.loader))
+                .map_or(0, reference_id);
+            out.u64(loader);
+        }
+        // Modifiers
+        3 => {
+            let modifiers = java_lang_class::modifiers(&mut ctx.env, &name);
+            out.int(modifiers as i32);
+        }
+        // Fields and FieldsWithGeneric
+        4 | 14 => {
+            let Some(class) = class else {
+                out.int(0);
+                return Ok(());
+            };
+            let file = &class.class;
+            out.int(file.fields.len() as i32);
+            for field in &file.fields {
+                let field_name = file.get_utf8(field.name_index).unwrap_or("");
+                let descriptor = file.get_utf8(field.descriptor_index).unwrap_or("");
+                out.u64(agent.member_id(type_id, field_name, descriptor))
+                    .string(field_name)
+                    .string(descriptor);
+                if command == 14 {
+                    out.string(generic_signature(file, &field.attributes).unwrap_or(""));
+                }
+                out.int(field.access_flags as i32);
+            }
+        }
+        // Methods and MethodsWithGeneric
+        5 | 15 => {
+            let Some(class) = class else {
+                out.int(0);
+                return Ok(());
+            };
+            out.int(class.methods.len() as i32);
+            for (index, method) in class.methods.iter().enumerate() {
+                out.u64(agent.member_id(type_id, &method.name, &method.descriptor))
+                    .string(&method.name)
+                    .string(&method.descriptor);
+                if command == 15 {
+                    let attributes = &class.class.methods[index].attributes;
+                    out.string(generic_signature(&class.class, attributes).unwrap_or(""));
+                }
+                out.int(method.access_flags as i32);
+            }
+        }
+        // GetValues, of static fields.
+        6 => {
+            let count = input.int()?;
+            out.int(count);
+            for _ in 0..count {
+                let (owner, field_name, descriptor) =
+                    agent.member(input.u64()?).ok_or(INVALID_FIELDID)?.clone();
+                let owner = agent.type_name(owner).ok_or(INVALID_FIELDID)?;
+                let value = ctx
+                    .env
+                    .loader
+                    .get_static_field(owner, &field_name)
+                    .unwrap_or_else(|| default_value(&descriptor));
+                write_value(out, descriptor_tag(&descriptor), &value, true);
+            }
+        }
+        // SourceFile
+        7 => {
+            let source = class
+                .as_ref()
+                .and_then(|class| class.source_file.clone())
+                .ok_or(ABSENT_INFORMATION)?;
+            out.string(&source);
+        }
+        // NestedTypes
+        8 => {
+            out.int(0);
+        }
+        // Status
+        9 => {
+            let status = agent.status(ctx, &name);
+            out.int(status);
+        }
+        // Interfaces
+        10 => {
+            let interfaces = class
+                .as_ref()
+                .map(|class| class.interfaces.clone())
+                .unwrap_or_default();
+            out.int(interfaces.len() as i32);
+            for interface in interfaces {
+                out.u64(agent.type_id(&interface));
+            }
+        }
+        // ClassObject
+        11 => {
+            let mirror = ctx.env.interpreter.class_mirror(ctx.env.heap, &name);
+            out.u64(reference_id(&mirror));
+        }
+        // SourceDebugExtension
+        12 => return Err(ABSENT_INFORMATION),
+        // ClassFileVersion
+        17 => {
+            let class = class.ok_or(ABSENT_INFORMATION)?;
+            out.int(class.class.major_version as i32)
+                .int(class.class.minor_version as i32);
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn class_type(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    let type_id = input.u64()?;
+    let name = agent.type_name(type_id).ok_or(INVALID_CLASS)?.to_string();
+    match command {
+        // Superclass
+        1 => {
+            let superclass = if agent.type_tag(ctx, &name) == super::TYPE_CLASS {
+                ctx.env.interpreter.superclass_of(ctx.env.loader, &name)
+            } else {
+                None
+            };
+            out.u64(superclass.map_or(0, |superclass| agent.type_id(&superclass)));
+        }
+        // SetValues, of static fields.
+        2 => {
+            for _ in 0..input.int()? {
+                let (owner, field_name, descriptor) =
+                    agent.member(input.u64()?).ok_or(INVALID_FIELDID)?.clone();
+                let owner = agent.type_name(owner).ok_or(INVALID_FIELDID)?.to_string();
+                let value = read_value(ctx, input, descriptor_tag(&descriptor))?;
+                ctx.env.loader.set_static_field(&owner, &field_name, value);
+            }
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn method(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    let _type_id = input.u64()?;
+    let (class, method) = agent.method(ctx, input.u64()?)?;
+    let info = &class.class.methods[method];
+    match command {
+        // LineTable
+        1 => {
+            let Some(code) = &info.code else {
+                out.u64(u64::MAX).u64(u64::MAX).int(0);
+                return Ok(());
+            };
+            let lines: Vec<_> = line_table(&class, method).collect();
+            out.u64(0)
+                .u64(code.code.len().saturating_sub(1) as u64)
+                .int(lines.len() as i32);
+            for line in lines {
+                out.u64(line.start_pc as u64).int(line.line_number as i32);
+            }
+        }
+        // VariableTable and VariableTableWithGeneric
+        2 | 5 => {
+            let code = info.code.as_ref().ok_or(ABSENT_INFORMATION)?;
+            let table = |name: &str| {
+                code.attributes
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::LocalVariableTable(variables)
+                            if name == "LocalVariableTable" =>
+                        {
+                            Some(variables)
+                        }
+                        Attribute::LocalVariableTypeTable(variables)
+                            if name == "LocalVariableTypeTable" =>
+                        {
+                            Some(variables)
+                        }
+                        _ => None,
+                    })
+            };
+            let variables = table("LocalVariableTable").ok_or(ABSENT_INFORMATION)?;
+            let generics = table("LocalVariableTypeTable");
+            let descriptor = &class.methods[method].descriptor;
+            let (parameters, _) = java_lang_class::split_method_descriptor(descriptor);
+            let mut arguments: i32 = parameters
+                .iter()
+                .map(|parameter| {
+                    if matches!(*parameter, "J" | "D") {
+                        2
+                    } else {
+                        1
+                    }
+                })
+                .sum();
+            if info.access_flags & ACC_STATIC == 0 {
+                arguments += 1;
+            }
+            out.int(arguments).int(variables.len() as i32);
+            let file = &class.class;
+            for variable in variables {
+                out.u64(variable.start_pc as u64)
+                    .string(file.get_utf8(variable.name_index).unwrap_or(""))
+                    .string(file.get_utf8(variable.descriptor_index).unwrap_or(""));
+                if command == 5 {
+                    let generic = generics
+                        .into_iter()
+                        .flatten()
+                        .find(|generic| {
+                            generic.start_pc == variable.start_pc && generic.index == variable.index
+                        })
+                        .and_then(|generic| file.get_utf8(generic.descriptor_index));
+                    out.string(generic.unwrap_or(""));
+                }
+                out.int(variable.length as i32).int(variable.index as i32);
+            }
+        }
+        // Bytecodes
+        3 => {
+            let code = info.code.as_ref().map_or(&[][..], |code| &code.code);
+            out.int(code.len() as i32).bytes(code);
+        }
+        // IsObsolete
+        4 => {
+            out.boolean(false);
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn object_reference(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    let id = input.u64()?;
+    match command {
+        // ReferenceType
+        1 => {
+            let name = match id {
+                MAIN_THREAD => "java/lang/Thread".to_string(),
+                MAIN_GROUP => "java/lang/ThreadGroup".to_string(),
+                _ => {
+                    let object = object_value(ctx, id)?;
+                    java_lang_class::value_class(ctx.env.heap, &object).ok_or(INVALID_OBJECT)?
+                }
+            };
+            let tag = agent.type_tag(ctx, &name);
+            out.u8(tag).u64(agent.type_id(&name));
+        }
+        // GetValues
+        2 => {
+            let object = ctx.env.heap.get(id).ok_or(INVALID_OBJECT)?.clone();
+            let count = input.int()?;
+            out.int(count);
+            for _ in 0..count {
+                let (owner, field_name, descriptor) =
+                    agent.member(input.u64()?).ok_or(INVALID_FIELDID)?.clone();
+                let value = match object.fields.get(&field_name) {
+                    Some(value) => value.clone(),
+                    None => agent
+                        .type_name(owner)
+                        .and_then(|owner| ctx.env.loader.get_static_field(owner, &field_name))
+                        .unwrap_or_else(|| default_value(&descriptor)),
+                };
+                write_value(out, descriptor_tag(&descriptor), &value, true);
+            }
+        }
+        // SetValues
+        3 => {
+            ctx.env.heap.get(id).ok_or(INVALID_OBJECT)?;
+            for _ in 0..input.int()? {
+                let (_, field_name, descriptor) =
+                    agent.member(input.u64()?).ok_or(INVALID_FIELDID)?.clone();
+                let value = read_value(ctx, input, descriptor_tag(&descriptor))?;
+                if let Some(object) = ctx.env.heap.get_mut(id) {
+                    object.fields.insert(field_name, value);
+                }
+            }
+        }
+        // DisableCollection
+        7 => {
+            object_value(ctx, id)?;
+            agent.pinned.insert(id);
+        }
+        // EnableCollection
+        8 => {
+            agent.pinned.remove(&id);
+        }
+        // IsCollected
+        9 => {
+            out.boolean(object_value(ctx, id).is_err());
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn string_reference(
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    if command != 1 {
+        return Err(NOT_IMPLEMENTED);
+    }
+    let string = object_value(ctx, input.u64()?)?;
+    let value = ctx.env.heap.string_value(&string).ok_or(INVALID_OBJECT)?;
+    out.string(&value);
+    Ok(())
+}
+
+fn thread_reference(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    if input.u64()? != MAIN_THREAD {
+        return Err(INVALID_THREAD);
+    }
+    match command {
+        // Name
+        1 => {
+            out.string("main");
+        }
+        // Suspend
+        2 => agent.suspend(),
+        // Resume
+        3 => agent.resume(),
+        // Status
+        4 => {
+            let suspended = if agent.suspend_count > 0 {
+                SUSPEND_STATUS_SUSPENDED
+            } else {
+                0
+            };
+            out.int(THREAD_RUNNING).int(suspended);
+        }
+        // ThreadGroup
+        5 => {
+            out.u64(MAIN_GROUP);
+        }
+        // Frames
+        6 => {
+            let start = input.int()?;
+            let length = input.int()?;
+            if agent.suspend_count == 0 {
+                return Err(THREAD_NOT_SUSPENDED);
+            }
+            let stack = agent.stack(ctx);
+            let start = usize::try_from(start)
+                .ok()
+                .filter(|start| *start <= stack.len())
+                .ok_or(INVALID_INDEX)?;
+            let end = match length {
+                -1 => stack.len(),
+                length => usize::try_from(length)
+                    .ok()
+                    .and_then(|length| start.checked_add(length))
+                    .filter(|end| *end <= stack.len())
+                    .ok_or(INVALID_LENGTH)?,
+            };
+            out.int((end - start) as i32);
+            for (index, entry) in stack.iter().enumerate().take(end).skip(start) {
+                out.u64(agent.frame_id(index));
+                agent.write_location(ctx, out, Some(entry));
+            }
+        }
+        // FrameCount
+        7 => {
+            if agent.suspend_count == 0 {
+                return Err(THREAD_NOT_SUSPENDED);
+            }
+            out.int(ctx.env.interpreter.call_records().len() as i32);
+        }
+        // SuspendCount
+        12 => {
+            out.int(agent.suspend_count as i32);
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn thread_group_reference(command: u8, input: &mut Reader, out: &mut Writer) -> Result<(), u16> {
+    if input.u64()? != MAIN_GROUP {
+        return Err(INVALID_THREAD_GROUP);
+    }
+    match command {
+        // Name
+        1 => {
+            out.string("main");
+        }
+        // Parent
+        2 => {
+            out.u64(0);
+        }
+        // Children: its threads, then its groups.
+        3 => {
+            out.int(1).u64(MAIN_THREAD).int(0);
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn array_reference(
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    let id = input.u64()?;
+    let array = ctx.env.heap.get_array(id).ok_or(INVALID_OBJECT)?;
+    match command {
+        // Length
+        1 => {
+            out.int(array.content.len() as i32);
+        }
+        // GetValues
+        2 => {
+            let first = usize::try_from(input.int()?).map_err(|_| INVALID_INDEX)?;
+            let length = usize::try_from(input.int()?).map_err(|_| INVALID_LENGTH)?;
+            let values = first
+                .checked_add(length)
+                .and_then(|end| array.content.get(first..end))
+                .ok_or(INVALID_LENGTH)?;
+            let class =
+                java_lang_class::value_class(ctx.env.heap, &HeapValue::Array(array.clone()))
+                    .unwrap_or_default();
+            let tag = descriptor_tag(&class[1..]);
+            let primitive = !matches!(tag, b'L' | b'[');
+            out.u8(tag).int(values.len() as i32);
+            for value in values {
+                write_value(out, tag, value, !primitive);
+            }
+        }
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn event_request(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    match command {
+        // Set
+        1 => {
+            let mut request = EventRequest::read(agent.next_request + 1, input)?;
+            if request.kind == BREAKPOINT {
+                let location = request.location().ok_or(ILLEGAL_ARGUMENT)?;
+                let (class, method) = agent.method(ctx, location.method)?;
+                let valid = class.methods[method]
+                    .code
+                    .as_ref()
+                    .and_then(|code| code.index_of(usize::try_from(location.index).ok()?))
+                    .is_some();
+                if !valid {
+                    return Err(INVALID_LOCATION);
+                }
+            }
+            if request.kind == SINGLE_STEP {
+                if agent
+                    .requests
+                    .iter()
+                    .any(|existing| existing.kind == SINGLE_STEP)
+                {
+                    return Err(DUPLICATE);
+                }
+                let stack = agent.stack(ctx);
+                let line = stack
+                    .first()
+                    .and_then(|top| line_of(&top.class, top.method, top.pc));
+                let step = request.step_mut().ok_or(ILLEGAL_ARGUMENT)?;
+                step.frames = stack.len();
+                step.line = line;
+            }
+            agent.next_request += 1;
+            out.int(request.id);
+            agent.requests.push(request);
+        }
+        // Clear
+        2 => {
+            let kind = input.u8()?;
+            let id = input.int()?;
+            agent
+                .requests
+                .retain(|request| request.kind != kind || request.id != id);
+        }
+        // ClearAllBreakpoints
+        3 => agent.requests.retain(|request| request.kind != BREAKPOINT),
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn stack_frame(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    if input.u64()? != MAIN_THREAD {
+        return Err(INVALID_THREAD);
+    }
+    let index = agent.frame_index(ctx, input.u64()?)?;
+    match command {
+        // GetValues
+        1 => {
+            let count = input.int()?;
+            out.int(count);
+            for _ in 0..count {
+                let slot = usize::try_from(input.int()?).map_err(|_| INVALID_SLOT)?;
+                let tag = input.u8()?;
+                let value = agent.local(ctx, index, slot).ok_or(INVALID_SLOT)?;
+                write_value(out, tag, &value, true);
+            }
+        }
+        // SetValues, of the innermost frame only: the others are
+        // snapshots taken when they made their calls.
+        2 => {
+            if index != 0 || ctx.frame.is_none() {
+                return Err(NOT_IMPLEMENTED);
+            }
+            for _ in 0..input.int()? {
+                let slot = usize::try_from(input.int()?).map_err(|_| INVALID_SLOT)?;
+                let tag = input.u8()?;
+                let value = read_value(ctx, input, tag)?;
+                let Some((_, frame)) = ctx.frame.as_mut() else {
+                    return Err(INVALID_FRAMEID);
+                };
+                if slot >= frame.local_vars.len() {
+                    return Err(INVALID_SLOT);
+                }
+                frame.set_local(slot, value);
+            }
+        }
+        // ThisObject
+        3 => match agent.this_object(ctx, index) {
+            Some((id, _)) => {
+                let this = object_value(ctx, id)?;
+                write_object(out, &this);
+            }
+            None => {
+                out.u8(b'L').u64(0);
+            }
+        },
+        _ => return Err(NOT_IMPLEMENTED),
+    }
+    Ok(())
+}
+
+fn class_object_reference(
+    agent: &mut Agent,
+    ctx: &mut Context,
+    command: u8,
+    input: &mut Reader,
+    out: &mut Writer,
+) -> Result<(), u16> {
+    if command != 1 {
+        return Err(NOT_IMPLEMENTED);
+    }
+    let mirror = object_value(ctx, input.u64()?)?;
+    let name = java_lang_class::class_name(ctx.env.heap, &mirror).ok_or(INVALID_OBJECT)?;
+    let tag = agent.type_tag(ctx, &name);
+    out.u8(tag).u64(agent.type_id(&name));
+    Ok(())
+}
+
+/// The generic signature in a `Signature` attribute among `attributes`.
+fn generic_signature<'a>(
+    class: &'a crate::bytecode::parser::ClassFile,
+    attributes: &[Attribute],
+) -> Option<&'a str> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Signature(index) => class.get_utf8(*index),
+        _ => None,
+    })
+}
+
+fn default_value(descriptor: &str) -> HeapValue {
+    match descriptor_tag(descriptor) {
+        b'J' => HeapValue::Long(0),
+        b'F' => HeapValue::Float(0.0),
+        b'D' => HeapValue::Double(0.0),
+        b'B' | b'C' | b'I' | b'S' | b'Z' => HeapValue::Int(0),
+        _ => HeapValue::Null,
+    }
+}
@@ -0,0 +1,186 @@
+//! Event requests and the modifiers that filter them.
+
+use crate::jdwp::packet::{Reader, INVALID_EVENT_TYPE, NOT_IMPLEMENTED};
+
+// Event kinds.
+pub(crate) const SINGLE_STEP: u8 = 1;
+pub(crate) const BREAKPOINT: u8 = 2;
+pub(crate) const EXCEPTION: u8 = 4;
+pub(crate) const THREAD_START: u8 = 6;
+pub(crate) const THREAD_DEATH: u8 = 7;
+pub(crate) const CLASS_PREPARE: u8 = 8;
+pub(crate) const CLASS_UNLOAD: u8 = 9;
+pub(crate) const VM_START: u8 = 90;
+pub(crate) const VM_DEATH: u8 = 99;
+
+// Suspend policies.
+pub(crate) const SUSPEND_NONE: u8 = 0;
+pub(crate) const SUSPEND_ALL: u8 = 2;
+
+// Step sizes and depths; a step over is neither into nor out.
+pub(crate) const STEP_MIN: i32 = 0;
+pub(crate) const STEP_INTO: i32 = 0;
+pub(crate) const STEP_OUT: i32 = 2;
+
+/// A request from `EventRequest.Set`.
+pub(crate) struct EventRequest {
+    pub id: i32,
+    pub kind: u8,
+    pub suspend_policy: u8,
+    pub modifiers: Vec<Modifier>,
+}
+
+/// A code location, with its ids as the debugger sent them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Location {
+    pub class: u64,
+    pub method: u64,
+    pub index: u64,
+}
+
+/// Where a step started, and how far it goes.
+pub(crate) struct Step {
+    pub size: i32,
+    pub depth: i32,
+    /// Frames on the stack when the step started.
+    pub frames: usize,
+    /// The line it started on, if the method has line numbers.
+    pub line: Option<u16>,
+}
+
+pub(crate) enum Modifier {
+    /// Reports only the nth event; counts down to it.
+    Count(i32),
+    ThreadOnly(u64),
+    /// The class, or any subclass.
+    ClassOnly(u64),
+    ClassMatch(String),
+    ClassExclude(String),
+    LocationOnly(Location),
+    ExceptionOnly {
+        class: Option<u64>,
+        caught: bool,
+        uncaught: bool,
+    },
+    Step(Step),
+    InstanceOnly(u64),
+    SourceNameMatch(String),
+}
+
+impl EventRequest {
+    /// Reads `EventRequest.Set`. Steps are given their starting point by
+    /// the agent.
+    pub fn read(id: i32, reader: &mut Reader) -> Result<Self, u16> {
+        let kind = reader.u8()?;
+        let suspend_policy = reader.u8()?;
+        if !matches!(
+            kind,
+            SINGLE_STEP
+                | BREAKPOINT
+                | EXCEPTION
+                | THREAD_START
+                | THREAD_DEATH
+                | CLASS_PREPARE
+                | CLASS_UNLOAD
+                | VM_DEATH
+        ) {
+            return Err(if kind == VM_START {
+                INVALID_EVENT_TYPE
+            } else {
+                NOT_IMPLEMENTED
+            });
+        }
+        let count = reader.int()?;
+        let mut modifiers = Vec::new();
+        for _ in 0..count {
+            let modifier = match reader.u8()? {
+                1 => Modifier::Count(reader.int()?),
+                3 => Modifier::ThreadOnly(reader.u64()?),
+                4 => Modifier::ClassOnly(reader.u64()?),
+                5 => Modifier::ClassMatch(reader.string()?),
+                6 => Modifier::ClassExclude(reader.string()?),
+                7 => Modifier::LocationOnly(read_location(reader)?),
+                8 => {
+                    let class = reader.u64()?;
+                    Modifier::ExceptionOnly {
+                        class: (class != 0).then_some(class),
+                        caught: reader.boolean()?,
+                        uncaught: reader.boolean()?,
+                    }
+                }
+                10 => {
+                    let _thread = reader.u64()?;
+                    Modifier::Step(Step {
+                        size: reader.int()?,
+                        depth: reader.int()?,
+                        frames: 0,
+                        line: None,
+                    })
+                }
+                11 => Modifier::InstanceOnly(reader.u64()?),
+                12 => Modifier::SourceNameMatch(reader.string()?),
+                // Conditional, FieldOnly and PlatformThreadsOnly.
+                _ => return Err(NOT_IMPLEMENTED),
+            };
+            modifiers.push(modifier);
+        }
+        Ok(Self {
+            id,
+            kind,
+            suspend_policy,
+            modifiers,
+        })
+    }
+
+    pub fn step(&self) -> Option<&Step> {
+        self.modifiers.iter().find_map(|modifier| match modifier {
+            Modifier::Step(step) => Some(step),
+            _ => None,
+        })
+    }
+
+    pub fn step_mut(&mut self) -> Option<&mut Step> {
+        self.modifiers
+            .iter_mut()
+            .find_map(|modifier| match modifier {
+                Modifier::Step(step) => Some(step),
+                _ => None,
+            })
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.modifiers.iter().find_map(|modifier| match modifier {
+            Modifier::LocationOnly(location) => Some(*location),
+            _ => None,
+        })
+    }
+
+    /// Whether a `Count` modifier has run out, so that the request never
+    /// reports again.
+    pub fn is_expired(&self) -> bool {
+        self.modifiers
+            .iter()
+            .any(|modifier| matches!(modifier, Modifier::Count(count) if *count <= 0))
+    }
+}
+
+pub(crate) fn read_location(reader: &mut Reader) -> Result<Location, u16> {
+    let _tag = reader.u8()?;
+    Ok(Location {
+        class: reader.u64()?,
+        method: reader.u64()?,
+        index: reader.u64()?,
+    })
+}
+
+/// Matches a binary class name against a `ClassMatch` pattern, which may
+/// start or end with `*`.
+pub(crate) fn class_matches(pattern: &str, class_name: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        class_name.ends_with(suffix)
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
+        class_name.starts_with(prefix)
+    } else {
+        pattern == class_name
+    }
+}
@@ -0,0 +1,962 @@
+//! A JDWP agent, which lets `jdb` and other JDI debuggers debug the
+//! program. `-agentlib:jdwp=transport=dt_socket,...` starts it; it serves
+//! one debugger at a time over a local socket.
+//!
+//! Java code runs on one thread, so the agent runs on it too: while the
+//! agent is installed the interpreter calls in before each instruction and
+//! when an exception is thrown, and the agent answers commands there,
+//! blocking for as long as the debugger keeps the VM suspended.
+
+mod commands;
+mod events;
+mod packet;
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::bytecode::attributes::Attribute;
+use crate::exec::runtime_class::RuntimeClass;
+use crate::loader::class_loader::ClassLoader;
+use crate::native::{java_io_printstream, java_lang_class, NativeEnv};
+use crate::runtime::frame::Frame;
+use crate::runtime::heap::HeapValue;
+use events::{
+    class_matches, EventRequest, Location, Modifier, BREAKPOINT, CLASS_PREPARE, EXCEPTION,
+    SINGLE_STEP, STEP_INTO, STEP_MIN, STEP_OUT, SUSPEND_ALL, SUSPEND_NONE, VM_DEATH, VM_START,
+};
+use packet::{Command, Connection, Message, Writer, INVALID_OBJECT};
+
+/// The only thread and its group. Object ids are heap ids, which stay far
+/// below these.
+pub(crate) const MAIN_THREAD: u64 = 1 << 48;
+pub(crate) const MAIN_GROUP: u64 = MAIN_THREAD + 1;
+
+/// Instructions run between two looks for commands.
+const POLL_INTERVAL: u32 = 1024;
+
+const ACC_INTERFACE: u16 = 0x0200;
+
+// Reference type tags.
+const TYPE_CLASS: u8 = 1;
+const TYPE_INTERFACE: u8 = 2;
+const TYPE_ARRAY: u8 = 3;
+
+// Class status bits.
+const STATUS_VERIFIED: i32 = 1;
+const STATUS_PREPARED: i32 = 2;
+const STATUS_INITIALIZED: i32 = 4;
+
+/// Builtin classes the debugger can always look up, with the builtin
+/// throwables.
+const BUILTIN_TYPES: [&str; 4] = [
+    "java/lang/Object",
+    "java/lang/String",
+    "java/lang/Class",
+    "java/lang/Thread",
+];
+
+/// `-agentlib:jdwp` options.
+pub struct AgentOptions {
+    transport: String,
+    server: bool,
+    address: String,
+    suspend: bool,
+    quiet: bool,
+}
+
+impl AgentOptions {
+    /// Parses `options`, the part after `-agentlib:jdwp=` of `arg`. Errors
+    /// are the lines to report, which quote `arg`.
+    pub fn parse(arg: &str, options: &str) -> Result<Self, String> {
+        let syntax_error = || format!("ERROR: JDWP option syntax error: {}", arg);
+        let yes_no = |value: &str| match value {
+            "y" => Ok(true),
+            "n" => Ok(false),
+            _ => Err(syntax_error()),
+        };
+        let mut transport = None;
+        let mut parsed = Self {
+            transport: String::new(),
+            server: false,
+            address: String::new(),
+            suspend: true,
+            quiet: false,
+        };
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (name, value) = option.split_once('=').ok_or_else(syntax_error)?;
+            match name {
+                "transport" => transport = Some(value.to_string()),
+                "server" => parsed.server = yes_no(value)?,
+                "address" => parsed.address = value.to_string(),
+                "suspend" => parsed.suspend = yes_no(value)?,
+                "quiet" => parsed.quiet = yes_no(value)?,
+                "timeout" if value.parse::<u64>().is_ok() => {}
+                _ => return Err(syntax_error()),
+            }
+        }
+        let Some(transport) = transport else {
+            return Err(format!("ERROR: JDWP no transport specified: {}", arg));
+        };
+        if !parsed.server && parsed.address.is_empty() {
+            return Err(format!(
+                "ERROR: JDWP Non-server transport {} must have a connection address specified through the 'address=' option\nERROR: JDWP invalid option: {}",
+                transport, arg
+            ));
+        }
+        parsed.transport = transport;
+        Ok(parsed)
+    }
+
+    /// A socket address for `address`: a bare port is on the local host,
+    /// and `*` stands for every interface.
+    fn socket_address(&self) -> String {
+        let address = match self.address.as_str() {
+            "" => "0",
+            address => address,
+        };
+        match address.rsplit_once(':') {
+            Some(("*", port)) => format!("0.0.0.0:{}", port),
+            Some(_) => address.to_string(),
+            None => format!("127.0.0.1:{}", address),
+        }
+    }
+}
+
+/// What the agent works on when the interpreter calls in.
+pub(crate) struct Context<'a> {
+    pub env: NativeEnv<'a>,
+    /// The innermost frame and the bytecode offset it is at, when the
+    /// call comes from the interpreter loop.
+    pub frame: Option<(usize, &'a mut Frame)>,
+}
+
+/// A frame as the debugger sees it.
+struct StackEntry {
+    class: Rc<RuntimeClass>,
+    method: usize,
+    pc: usize,
+}
+
+/// What an event is about, for request modifiers to filter on.
+#[derive(Default)]
+struct Event<'a> {
+    class: Option<&'a RuntimeClass>,
+    location: Option<&'a StackEntry>,
+    /// The exception's class, and whether it will be caught.
+    exception: Option<(&'a str, bool)>,
+    this: Option<u64>,
+}
+
+/// The debugger's side of the VM: its connection, event requests and the
+/// ids handed out for types, members and frames.
+pub struct Agent {
+    connection: Connection,
+    suspend_on_start: bool,
+    /// Whether a debugger is connected.
+    attached: bool,
+    /// Suspensions not yet resumed.
+    suspend_count: u32,
+    /// Bumped on every resume, which invalidates frame ids.
+    resumes: u64,
+    requests: Vec<EventRequest>,
+    next_request: i32,
+    /// Id of the last packet the agent sent.
+    next_packet: u32,
+    /// Reference types by id - 1: runtime class names, and descriptors
+    /// for arrays.
+    types: Vec<String>,
+    type_ids: HashMap<String, u64>,
+    /// Methods and fields by id - 1: declaring type id, name and
+    /// descriptor.
+    members: Vec<(u64, String, String)>,
+    member_ids: HashMap<(u64, String, String), u64>,
+    /// Objects the debugger asked to keep alive.
+    pinned: HashSet<u64>,
+    /// Prepared classes already reported or skipped.
+    reported_classes: usize,
+    /// The exception last reported, until the next instruction runs.
+    last_exception: Option<u64>,
+    /// Instructions until the next look for commands.
+    countdown: u32,
+}
+
+impl Agent {
+    /// Opens the transport: listens and prints the address for
+    /// `server=y`, or attaches to the debugger. Errors are the lines to
+    /// report.
+    pub fn open(options: &AgentOptions) -> Result<Self, String> {
+        if options.transport != "dt_socket" {
+            return Err(format!(
+                "ERROR: transport library not found: {0}\nERROR: JDWP Transport {0} failed to initialize, TRANSPORT_LOAD(509)\nJDWP exit error AGENT_ERROR_TRANSPORT_LOAD(196): No transports initialized",
+                options.transport
+            ));
+        }
+        let transport_error = |action: &str, error: std::io::Error| {
+            let message = error.to_string();
+            let message = message.split(" (os error").next().unwrap_or_default();
+            format!(
+                "ERROR: transport error 202: {} failed: {}\nERROR: JDWP Transport dt_socket failed to initialize, TRANSPORT_INIT(510)\nJDWP exit error AGENT_ERROR_TRANSPORT_INIT(197): No transports initialized",
+                action, message
+            )
+        };
+        let address = options.socket_address();
+        let connection = if options.server {
+            let (connection, bound) =
+                Connection::listen(&address).map_err(|e| transport_error("bind", e))?;
+            if !options.quiet {
+                println!(
+                    "Listening for transport dt_socket at address: {}",
+                    bound.port()
+                );
+            }
+            connection
+        } else {
+            Connection::attach(&address).map_err(|e| transport_error("connect", e))?
+        };
+        Ok(Self {
+            connection,
+            suspend_on_start: options.suspend,
+            attached: false,
+            suspend_count: 0,
+            resumes: 0,
+            requests: Vec::new(),
+            next_request: 0,
+            next_packet: 0,
+            types: Vec::new(),
+            type_ids: HashMap::new(),
+            members: Vec::new(),
+            member_ids: HashMap::new(),
+            pinned: HashSet::new(),
+            reported_classes: 0,
+            last_exception: None,
+            countdown: POLL_INTERVAL,
+        })
+    }
+
+    /// Objects the debugger keeps alive, for the collector's roots.
+    pub(crate) fn pinned(&self) -> impl Iterator<Item = u64> + '_ {
+        self.pinned.iter().copied()
+    }
+
+    /// Sends `VMStart` once the VM is up. With `suspend=y` this waits for
+    /// the debugger first, then for it to resume the VM.
+    pub(crate) fn start(&mut self, ctx: &mut Context) {
+        if self.suspend_on_start {
+            while !self.attached {
+                match self.connection.wait() {
+                    Message::Connected => self.connected(ctx),
+                    Message::Closed => break,
+                    Message::Command(_) => {}
+                }
+            }
+        } else {
+            self.poll(ctx);
+        }
+        if !self.attached {
+            return;
+        }
+        let policy = if self.suspend_on_start {
+            SUSPEND_ALL
+        } else {
+            SUSPEND_NONE
+        };
+        let mut event = Writer::default();
+        event.u8(VM_START).int(0).u64(MAIN_THREAD);
+        self.send_events(ctx, policy, vec![event]);
+    }
+
+    /// Called before each instruction of the innermost frame.
+    pub(crate) fn at_instruction(&mut self, ctx: &mut Context) {
+        self.last_exception = None;
+        self.countdown -= 1;
+        if self.countdown == 0 {
+            self.countdown = POLL_INTERVAL;
+            self.poll(ctx);
+        }
+        if !self.attached {
+            return;
+        }
+        if ctx.env.interpreter.prepared_count() > self.reported_classes {
+            self.report_prepared(ctx);
+        }
+        if self
+            .requests
+            .iter()
+            .any(|request| matches!(request.kind, BREAKPOINT | SINGLE_STEP))
+        {
+            self.check_location(ctx);
+        }
+    }
+
+    /// Called when `exception` starts propagating from, or into, the
+    /// innermost frame.
+    pub(crate) fn exception_thrown(&mut self, ctx: &mut Context, exception: &HeapValue) {
+        let HeapValue::Object(thrown) = exception else {
+            return;
+        };
+        if !self.attached || self.last_exception == Some(thrown.id) {
+            return;
+        }
+        self.last_exception = Some(thrown.id);
+        if !self
+            .requests
+            .iter()
+            .any(|request| request.kind == EXCEPTION)
+        {
+            return;
+        }
+        let stack = self.stack(ctx);
+        let Some(top) = stack.first() else {
+            return;
+        };
+        let catch = stack.iter().find_map(|entry| {
+            let code = entry.class.class.methods[entry.method].code.as_ref()?;
+            let handler = ctx.env.interpreter.find_handler(
+                ctx.env.loader,
+                &entry.class,
+                code,
+                entry.pc,
+                exception,
+            )?;
+            Some(StackEntry {
+                class: entry.class.clone(),
+                method: entry.method,
+                pc: handler,
+            })
+        });
+        let event = Event {
+            class: Some(top.class.as_ref()),
+            location: Some(top),
+            exception: Some((&thrown.class_name, catch.is_some())),
+            this: self.this_object(ctx, 0).map(|this| this.0),
+        };
+        let (matched, policy) = self.matching(ctx, EXCEPTION, &event);
+        if matched.is_empty() {
+            return;
+        }
+        let mut events = Vec::new();
+        for id in matched {
+            let mut data = Writer::default();
+            data.u8(EXCEPTION).int(id).u64(MAIN_THREAD);
+            self.write_location(ctx, &mut data, Some(top));
+            write_object(&mut data, exception);
+            self.write_location(ctx, &mut data, catch.as_ref());
+            events.push(data);
+        }
+        self.send_events(ctx, policy, events);
+    }
+
+    /// Sends `VMDeath` and lets the debugger go; the agent is done.
+    pub(crate) fn vm_death(&mut self, ctx: &mut Context) {
+        if !self.attached {
+            self.poll(ctx);
+        }
+        if !self.attached {
+            return;
+        }
+        let (matched, policy) = self.matching(ctx, VM_DEATH, &Event::default());
+        let mut events = Vec::new();
+        for id in std::iter::once(0).chain(matched) {
+            let mut data = Writer::default();
+            data.u8(VM_DEATH).int(id);
+            events.push(data);
+        }
+        self.send_events(ctx, policy, events);
+        self.connection.close();
+        self.attached = false;
+    }
+
+    /// Takes the commands that arrived while the VM ran.
+    fn poll(&mut self, ctx: &mut Context) {
+        while let Some(message) = self.connection.poll() {
+            self.receive(ctx, message);
+        }
+        self.run_suspended(ctx);
+    }
+
+    /// Answers commands until the VM is resumed or the debugger leaves.
+    fn run_suspended(&mut self, ctx: &mut Context) {
+        while self.suspend_count > 0 {
+            let message = self.connection.wait();
+            self.receive(ctx, message);
+        }
+    }
+
+    fn receive(&mut self, ctx: &mut Context, message: Message) {
+        match message {
+            Message::Connected => self.connected(ctx),
+            Message::Command(command) => self.answer(ctx, command),
+            Message::Closed => self.disconnected(),
+        }
+    }
+
+    fn connected(&mut self, ctx: &mut Context) {
+        self.attached = true;
+        self.reported_classes = ctx.env.interpreter.prepared_count();
+    }
+
+    /// Forgets the debugger's requests and lets the VM run, as
+    /// `VirtualMachine.Dispose` does.
+    fn disconnected(&mut self) {
+        self.connection.close();
+        self.attached = false;
+        self.requests.clear();
+        self.pinned.clear();
+        self.suspend_count = 0;
+        self.resumes += 1;
+    }
+
+    fn answer(&mut self, ctx: &mut Context, command: Command) {
+        let reply = match commands::handle(self, ctx, &command) {
+            Ok(data) => packet::reply(command.id, 0, &data.data),
+            Err(error) => packet::reply(command.id, error, &[]),
+        };
+        self.connection.send(&reply);
+        match (command.command_set, command.command) {
+            // VirtualMachine.Dispose
+            (1, 6) => self.disconnected(),
+            // VirtualMachine.Exit
+            (1, 10) => {
+                let status = packet::Reader::new(&command.data).int().unwrap_or(0);
+                java_io_printstream::flush_all();
+                std::process::exit(status);
+            }
+            _ => {}
+        }
+    }
+
+    fn suspend(&mut self) {
+        self.suspend_count += 1;
+    }
+
+    fn resume(&mut self) {
+        if self.suspend_count > 0 {
+            self.suspend_count -= 1;
+            if self.suspend_count == 0 {
+                self.resumes += 1;
+            }
+        }
+    }
+
+    /// Sends an `Event.Composite` and suspends the VM if `policy` says so.
+    fn send_events(&mut self, ctx: &mut Context, policy: u8, events: Vec<Writer>) {
+        let mut composite = Writer::default();
+        composite.u8(policy).int(events.len() as i32);
+        for event in &events {
+            composite.bytes(&event.data);
+        }
+        self.next_packet += 1;
+        self.connection
+            .send(&packet::command(self.next_packet, 64, 100, &composite.data));
+        if policy != SUSPEND_NONE {
+            self.suspend();
+            self.run_suspended(ctx);
+        }
+    }
+
+    /// Reports the classes prepared since the last report.
+    fn report_prepared(&mut self, ctx: &mut Context) {
+        let prepared = ctx.env.interpreter.prepared_classes(self.reported_classes);
+        self.reported_classes += prepared.len();
+        if !self
+            .requests
+            .iter()
+            .any(|request| request.kind == CLASS_PREPARE)
+        {
+            return;
+        }
+        for name in prepared {
+            let Ok(runtime) = ctx.env.interpreter.runtime_class(ctx.env.loader, &name) else {
+                continue;
+            };
+            let event = Event {
+                class: Some(runtime.as_ref()),
+                ..Event::default()
+            };
+            let (matched, policy) = self.matching(ctx, CLASS_PREPARE, &event);
+            if matched.is_empty() {
+                continue;
+            }
+            let type_id = self.type_id(&name);
+            let tag = self.type_tag(ctx, &name);
+            let signature = signature(ctx.env.loader, &name);
+            let status = self.status(ctx, &name);
+            let events = matched
+                .into_iter()
+                .map(|id| {
+                    let mut data = Writer::default();
+                    data.u8(CLASS_PREPARE)
+                        .int(id)
+                        .u64(MAIN_THREAD)
+                        .u8(tag)
+                        .u64(type_id)
+                        .string(&signature)
+                        .int(status);
+                    data
+                })
+                .collect();
+            self.send_events(ctx, policy, events);
+        }
+    }
+
+    /// Reports breakpoints at, and steps that end at, the instruction
+    /// about to run, together as one composite.
+    fn check_location(&mut self, ctx: &mut Context) {
+        let stack = self.stack(ctx);
+        let Some(top) = stack.first() else {
+            return;
+        };
+        let depth = stack.len();
+        let line = line_of(&top.class, top.method, top.pc);
+        let this = self.this_object(ctx, 0).map(|this| this.0);
+        let event = Event {
+            class: Some(top.class.as_ref()),
+            location: Some(top),
+            this,
+            ..Event::default()
+        };
+        let (breakpoints, breakpoint_policy) = self.matching(ctx, BREAKPOINT, &event);
+        let stepping: Vec<usize> = (0..self.requests.len())
+            .filter(|&index| {
+                let request = &self.requests[index];
+                request.kind == SINGLE_STEP
+                    && request.step().is_some_and(|step| {
+                        step_ends(step.size, step.depth, step.frames, step.line, depth, line)
+                    })
+            })
+            .collect();
+        let (steps, step_policy) = self.matching_among(ctx, &stepping, &event);
+        if breakpoints.is_empty() && steps.is_empty() {
+            return;
+        }
+        // A step goes on from where it last ended.
+        if !steps.is_empty() {
+            for step in self.requests.iter_mut().filter_map(EventRequest::step_mut) {
+                step.frames = depth;
+                step.line = line;
+            }
+        }
+        let mut events = Vec::new();
+        for (kind, id) in steps
+            .into_iter()
+            .map(|id| (SINGLE_STEP, id))
+            .chain(breakpoints.into_iter().map(|id| (BREAKPOINT, id)))
+        {
+            let mut data = Writer::default();
+            data.u8(kind).int(id).u64(MAIN_THREAD);
+            self.write_location(ctx, &mut data, Some(top));
+            events.push(data);
+        }
+        self.send_events(ctx, breakpoint_policy.max(step_policy), events);
+    }
+
+    /// The requests of `kind` that `event` gets through, and the strongest
+    /// suspend policy among them.
+    fn matching(&mut self, ctx: &mut Context, kind: u8, event: &Event) -> (Vec<i32>, u8) {
+        let candidates: Vec<usize> = (0..self.requests.len())
+            .filter(|&index| self.requests[index].kind == kind)
+            .collect();
+        self.matching_among(ctx, &candidates, event)
+    }
+
+    fn matching_among(
+        &mut self,
+        ctx: &mut Context,
+        candidates: &[usize],
+        event: &Event,
+    ) -> (Vec<i32>, u8) {
+        let mut matched = Vec::new();
+        let mut policy = SUSPEND_NONE;
+        for &index in candidates {
+            if !self.accepts(ctx, &self.requests[index], event) {
+                continue;
+            }
+            let request = &mut self.requests[index];
+            let mut counted_out = false;
+            for modifier in &mut request.modifiers {
+                if let Modifier::Count(count) = modifier {
+                    *count -= 1;
+                    counted_out |= *count > 0;
+                }
+            }
+            if counted_out {
+                continue;
+            }
+            matched.push(request.id);
+            policy = policy.max(request.suspend_policy);
+        }
+        (matched, policy)
+    }
+
+    /// Whether `event` gets through the modifiers of `request`, other than
+    /// its count.
+    fn accepts(&self, ctx: &mut Context, request: &EventRequest, event: &Event) -> bool {
+        if request.is_expired() {
+            return false;
+        }
+        let interpreter = ctx.env.interpreter;
+        let class_name = event
+            .class
+            .map(|class| class.symbolic_name().replace('/', "."));
+        request.modifiers.iter().all(|modifier| match modifier {
+            Modifier::Count(_) | Modifier::Step(_) => true,
+            Modifier::ThreadOnly(thread) => *thread == MAIN_THREAD,
+            Modifier::ClassOnly(type_id) => match (event.class, self.type_name(*type_id)) {
+                (Some(class), Some(ancestor)) => {
+                    interpreter.is_subclass_of(ctx.env.loader, &class.name, ancestor)
+                }
+                _ => false,
+            },
+            Modifier::ClassMatch(pattern) => class_name
+                .as_deref()
+                .is_some_and(|name| class_matches(pattern, name)),
+            Modifier::ClassExclude(pattern) => !class_name
+                .as_deref()
+                .is_some_and(|name| class_matches(pattern, name)),
+            Modifier::LocationOnly(location) => event
+                .location
+                .is_some_and(|entry| self.is_at(location, entry)),
+            Modifier::ExceptionOnly {
+                class,
+                caught,
+                uncaught,
+            } => {
+                let Some((thrown, is_caught)) = event.exception else {
+                    return false;
+                };
+                let class_accepted = match class {
+                    Some(type_id) => self.type_name(*type_id).is_some_and(|ancestor| {
+                        interpreter.is_subclass_of(ctx.env.loader, thrown, ancestor)
+                    }),
+                    None => true,
+                };
+                class_accepted && if is_caught { *caught } else { *uncaught }
+            }
+            Modifier::InstanceOnly(object) => event.this == Some(*object),
+            Modifier::SourceNameMatch(pattern) => event
+                .class
+                .and_then(|class| class.source_file.as_deref())
+                .is_some_and(|source| class_matches(pattern, source)),
+        })
+    }
+
+    /// Whether a location sent by the debugger is `entry`'s.
+    fn is_at(&self, location: &Location, entry: &StackEntry) -> bool {
+        if location.index != entry.pc as u64
+            || self.type_name(location.class) != Some(&entry.class.name)
+        {
+            return false;
+        }
+        let method = &entry.class.methods[entry.method];
+        self.member(location.method)
+            .is_some_and(|(_, name, descriptor)| {
+                *name == method.name && *descriptor == method.descriptor
+            })
+    }
+
+    /// The Java frames, innermost first.
+    fn stack(&self, ctx: &Context) -> Vec<StackEntry> {
+        let mut stack: Vec<StackEntry> = ctx
+            .env
+            .interpreter
+            .call_records()
+            .into_iter()
+            .rev()
+            .map(|record| StackEntry {
+                class: record.class,
+                method: record.method,
+                pc: record.pc,
+            })
+            .collect();
+        if let (Some(top), Some((pc, _))) = (stack.first_mut(), &ctx.frame) {
+            top.pc = *pc;
+        }
+        stack
+    }
+
+    /// Local `slot` of frame `index`, counted from the innermost.
+    fn local(&self, ctx: &Context, index: usize, slot: usize) -> Option<HeapValue> {
+        if index == 0 {
+            if let Some((_, frame)) = &ctx.frame {
+                return frame.get_local(slot).cloned();
+            }
+        }
+        let records = ctx.env.interpreter.call_records();
+        let record = records
+            .len()
+            .checked_sub(index + 1)
+            .map(|at| &records[at])?;
+        record.locals.get(slot).cloned()
+    }
+
+    /// `this` of frame `index`, and its class; `None` in static methods.
+    fn this_object(&self, ctx: &Context, index: usize) -> Option<(u64, String)> {
+        let records = ctx.env.interpreter.call_records();
+        let record = records
+            .len()
+            .checked_sub(index + 1)
+            .map(|at| &records[at])?;
+        if record.class.methods[record.method].access_flags & 0x0008 != 0 {
+            return None;
+        }
+        match self.local(ctx, index, 0)? {
+            HeapValue::Object(object) => Some((object.id, object.class_name)),
+            HeapValue::Array(array) => Some((array.id, String::new())),
+            _ => None,
+        }
+    }
+
+    fn frame_id(&self, index: usize) -> u64 {
+        (self.resumes << 20) | index as u64
+    }
+
+    /// The frame index behind a frame id, while it is still valid.
+    fn frame_index(&self, ctx: &Context, frame_id: u64) -> Result<usize, u16> {
+        let index = (frame_id & 0xF_FFFF) as usize;
+        if self.suspend_count == 0
+            || frame_id >> 20 != self.resumes
+            || index >= ctx.env.interpreter.call_records().len()
+        {
+            return Err(packet::INVALID_FRAMEID);
+        }
+        Ok(index)
+    }
+
+    /// The id of a reference type, given its runtime name or array
+    /// descriptor.
+    fn type_id(&mut self, name: &str) -> u64 {
+        if let Some(id) = self.type_ids.get(name) {
+            return *id;
+        }
+        self.types.push(name.to_string());
+        let id = self.types.len() as u64;
+        self.type_ids.insert(name.to_string(), id);
+        id
+    }
+
+    fn type_name(&self, id: u64) -> Option<&str> {
+        let index = usize::try_from(id.checked_sub(1)?).ok()?;
+        self.types.get(index).map(String::as_str)
+    }
+
+    fn member_id(&mut self, type_id: u64, name: &str, descriptor: &str) -> u64 {
+        let key = (type_id, name.to_string(), descriptor.to_string());
+        if let Some(id) = self.member_ids.get(&key) {
+            return *id;
+        }
+        self.members.push(key.clone());
+        let id = self.members.len() as u64;
+        self.member_ids.insert(key, id);
+        id
+    }
+
+    fn member(&self, id: u64) -> Option<&(u64, String, String)> {
+        let index = usize::try_from(id.checked_sub(1)?).ok()?;
+        self.members.get(index)
+    }
+
+    /// The class behind a type id; `None` for builtin and array types.
+    fn class(&self, ctx: &mut Context, type_id: u64) -> Result<Option<Rc<RuntimeClass>>, u16> {
+        let name = self.type_name(type_id).ok_or(packet::INVALID_CLASS)?;
+        Ok(java_lang_class::loaded_class(&mut ctx.env, name))
+    }
+
+    /// The method behind a method id.
+    fn method(&self, ctx: &mut Context, method_id: u64) -> Result<(Rc<RuntimeClass>, usize), u16> {
+        let (owner, name, descriptor) = self.member(method_id).ok_or(packet::INVALID_METHODID)?;
+        let class = self.class(ctx, *owner)?.ok_or(packet::INVALID_METHODID)?;
+        let method = class
+            .find_method(name, descriptor)
+            .ok_or(packet::INVALID_METHODID)?;
+        Ok((class, method))
+    }
+
+    fn type_tag(&self, ctx: &mut Context, name: &str) -> u8 {
+        if name.starts_with('[') {
+            TYPE_ARRAY
+        } else if java_lang_class::modifiers(&mut ctx.env, name) & ACC_INTERFACE != 0 {
+            TYPE_INTERFACE
+        } else {
+            TYPE_CLASS
+        }
+    }
+
+    fn status(&self, ctx: &Context, name: &str) -> i32 {
+        let prepared = STATUS_VERIFIED | STATUS_PREPARED;
+        if name.starts_with('[')
+            || crate::native::is_builtin_class(name)
+            || ctx.env.loader.class_init_started(name)
+        {
+            prepared | STATUS_INITIALIZED
+        } else {
+            prepared
+        }
+    }
+
+    /// Writes a location; `None` writes the all-zero location that stands
+    /// for none.
+    fn write_location(&mut self, ctx: &mut Context, data: &mut Writer, entry: Option<&StackEntry>) {
+        let Some(entry) = entry else {
+            data.u8(0).u64(0).u64(0).u64(0);
+            return;
+        };
+        let type_id = self.type_id(&entry.class.name);
+        let method = &entry.class.methods[entry.method];
+        let method_id = self.member_id(type_id, &method.name, &method.descriptor);
+        let tag = self.type_tag(ctx, &entry.class.name);
+        data.u8(tag)
+            .u64(type_id)
+            .u64(method_id)
+            .u64(entry.pc as u64);
+    }
+}
+
+/// Whether a step that started `frames` deep on `line` ends at an
+/// instruction `depth` deep on `now`.
+fn step_ends(
+    size: i32,
+    step_depth: i32,
+    frames: usize,
+    line: Option<u16>,
+    depth: usize,
+    now: Option<u16>,
+) -> bool {
+    if depth > frames {
+        step_depth == STEP_INTO
+    } else if depth < frames {
+        true
+    } else {
+        step_depth != STEP_OUT && (size == STEP_MIN || now.is_none() || now != line)
+    }
+}
+
+/// The source line of the instruction at `pc`.
+fn line_of(class: &RuntimeClass, method: usize, pc: usize) -> Option<u16> {
+    line_table(class, method)
+        .filter(|entry| entry.start_pc as usize <= pc)
+        .max_by_key(|entry| entry.start_pc)
+        .map(|entry| entry.line_number)
+}
+
+fn line_table(
+    class: &RuntimeClass,
+    method: usize,
+) -> impl Iterator<Item = &crate::bytecode::attributes::LineNumber> {
+    class.class.methods[method]
+        .code
+        .iter()
+        .flat_map(|code| &code.attributes)
+        .filter_map(|attribute| match attribute {
+            Attribute::LineNumberTable(lines) => Some(lines),
+            _ => None,
+        })
+        .flatten()
+}
+
+/// The JNI signature of a runtime class name or array descriptor.
+fn signature(loader: &ClassLoader, name: &str) -> String {
+    if name.starts_with('[') {
+        loader.symbolic_name(name).into_owned()
+    } else {
+        format!("L{};", loader.symbolic_name(name))
+    }
+}
+
+/// Writes a tagged object id.
+fn write_object(data: &mut Writer, value: &HeapValue) {
+    match value {
+        HeapValue::Object(object) => {
+            let tag = match object.class_name.as_str() {
+                "java/lang/String" => b's',
+                "java/lang/Class" => b'c',
+                _ => b'L',
+            };
+            data.u8(tag).u64(object.id);
+        }
+        HeapValue::Array(array) => {
+            data.u8(b'[').u64(array.id);
+        }
+        _ => {
+            data.u8(b'L').u64(0);
+        }
+    }
+}
+
+/// Writes a value of the type `tag` names, tagged or not.
+fn write_value(data: &mut Writer, tag: u8, value: &HeapValue, tagged: bool) {
+    let int = match value {
+        HeapValue::Int(value) => *value,
+        _ => 0,
+    };
+    if !matches!(tag, b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z') {
+        if tagged {
+            write_object(data, value);
+        } else {
+            data.u64(reference_id(value));
+        }
+        return;
+    }
+    if tagged {
+        data.u8(tag);
+    }
+    match tag {
+        b'B' => data.u8(int as u8),
+        b'Z' => data.boolean(int != 0),
+        b'C' | b'S' => data.u16(int as u16),
+        b'J' => data.u64(match value {
+            HeapValue::Long(value) => *value as u64,
+            _ => 0,
+        }),
+        b'F' => data.int(match value {
+            HeapValue::Float(value) => value.to_bits() as i32,
+            _ => 0,
+        }),
+        b'D' => data.u64(match value {
+            HeapValue::Double(value) => value.to_bits(),
+            _ => 0,
+        }),
+        _ => data.int(int),
+    };
+}
+
+/// Reads a value of the type `tag` names.
+fn read_value(ctx: &Context, reader: &mut packet::Reader, tag: u8) -> Result<HeapValue, u16> {
+    Ok(match tag {
+        b'B' => HeapValue::Int(reader.u8()? as i8 as i32),
+        b'Z' => HeapValue::Int(reader.boolean()? as i32),
+        b'C' => HeapValue::Int(reader.u16()? as i32),
+        b'S' => HeapValue::Int(reader.u16()? as i16 as i32),
+        b'I' => HeapValue::Int(reader.int()?),
+        b'J' => HeapValue::Long(reader.u64()? as i64),
+        b'F' => HeapValue::Float(f32::from_bits(reader.int()? as u32)),
+        b'D' => HeapValue::Double(f64::from_bits(reader.u64()?)),
+        _ => object_value(ctx, reader.u64()?)?,
+    })
+}
+
+/// The reference an object id stands for.
+fn object_value(ctx: &Context, id: u64) -> Result<HeapValue, u16> {
+    if id == 0 {
+        return Ok(HeapValue::Null);
+    }
+    if let Some(object) = ctx.env.heap.get(id) {
+        return Ok(HeapValue::Object(object.clone()));
+    }
+    if let Some(array) = ctx.env.heap.get_array(id) {
+        return Ok(HeapValue::Array(array.clone()));
+    }
+    Err(INVALID_OBJECT)
+}
+
+fn reference_id(value: &HeapValue) -> u64 {
+    match value {
+        HeapValue::Object(object) => object.id,
+        HeapValue::Array(array) => array.id,
+        _ => 0,
+    }
+}
+
+/// The type tag of a descriptor's first character.
+fn descriptor_tag(descriptor: &str) -> u8 {
+    descriptor.as_bytes().first().copied().unwrap_or(b'V')
+}
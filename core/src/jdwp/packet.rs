@@ -0,0 +1,317 @@
+//! JDWP packets and the socket they travel over.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const HANDSHAKE: &[u8] = b"JDWP-Handshake";
+const HEADER_SIZE: usize = 11;
+const REPLY_FLAG: u8 = 0x80;
+
+// Error codes of replies.
+pub(crate) const INVALID_THREAD: u16 = 10;
+pub(crate) const INVALID_THREAD_GROUP: u16 = 11;
+pub(crate) const THREAD_NOT_SUSPENDED: u16 = 13;
+pub(crate) const INVALID_OBJECT: u16 = 20;
+pub(crate) const INVALID_CLASS: u16 = 21;
+pub(crate) const INVALID_METHODID: u16 = 23;
+pub(crate) const INVALID_LOCATION: u16 = 24;
+pub(crate) const INVALID_FIELDID: u16 = 25;
+pub(crate) const INVALID_FRAMEID: u16 = 30;
+pub(crate) const INVALID_SLOT: u16 = 35;
+pub(crate) const DUPLICATE: u16 = 40;
+pub(crate) const INVALID_CLASS_FORMAT: u16 = 60;
+pub(crate) const FAILS_VERIFICATION: u16 = 62;
+pub(crate) const ADD_METHOD_NOT_IMPLEMENTED: u16 = 63;
+pub(crate) const SCHEMA_CHANGE_NOT_IMPLEMENTED: u16 = 64;
+pub(crate) const HIERARCHY_CHANGE_NOT_IMPLEMENTED: u16 = 66;
+pub(crate) const DELETE_METHOD_NOT_IMPLEMENTED: u16 = 67;
+pub(crate) const NAMES_DONT_MATCH: u16 = 69;
+pub(crate) const CLASS_MODIFIERS_CHANGE_NOT_IMPLEMENTED: u16 = 70;
+pub(crate) const METHOD_MODIFIERS_CHANGE_NOT_IMPLEMENTED: u16 = 71;
+pub(crate) const NOT_IMPLEMENTED: u16 = 99;
+pub(crate) const ABSENT_INFORMATION: u16 = 101;
+pub(crate) const INVALID_EVENT_TYPE: u16 = 102;
+pub(crate) const ILLEGAL_ARGUMENT: u16 = 103;
+pub(crate) const INVALID_INDEX: u16 = 503;
+pub(crate) const INVALID_LENGTH: u16 = 504;
+
+/// A command sent by the debugger.
+pub(crate) struct Command {
+    pub id: u32,
+    pub command_set: u8,
+    pub command: u8,
+    pub data: Vec<u8>,
+}
+
+/// What the connection thread hands to the VM.
+pub(crate) enum Message {
+    /// A debugger attached and completed the handshake.
+    Connected,
+    Command(Command),
+    /// The debugger went away.
+    Closed,
+}
+
+/// The debugger's socket. A thread of its own accepts the connection and
+/// reads commands, which the VM takes when it is ready for them; replies
+/// and events are written from the VM.
+pub(crate) struct Connection {
+    messages: Receiver<Message>,
+    stream: Arc<Mutex<Option<TcpStream>>>,
+}
+
+impl Connection {
+    /// Listens on `address`, accepting one debugger after another. Returns
+    /// the address actually bound, which differs for port 0.
+    pub fn listen(address: &str) -> io::Result<(Self, SocketAddr)> {
+        let listener = TcpListener::bind(address)?;
+        let bound = listener.local_addr()?;
+        let (sender, messages) = mpsc::channel();
+        let stream = Arc::new(Mutex::new(None));
+        let shared = stream.clone();
+        thread::Builder::new()
+            .name("JDWP Transport Listener: dt_socket".to_string())
+            .spawn(move || {
+                for incoming in listener.incoming() {
+                    let Ok(socket) = incoming else {
+                        continue;
+                    };
+                    if !serve(socket, &shared, &sender) {
+                        break;
+                    }
+                }
+            })?;
+        Ok((Self { messages, stream }, bound))
+    }
+
+    /// Attaches to a debugger listening on `address`.
+    pub fn attach(address: &str) -> io::Result<Self> {
+        let socket = TcpStream::connect(address)?;
+        let (sender, messages) = mpsc::channel();
+        let stream = Arc::new(Mutex::new(None));
+        let shared = stream.clone();
+        thread::Builder::new()
+            .name("JDWP Transport Reader: dt_socket".to_string())
+            .spawn(move || {
+                serve(socket, &shared, &sender);
+            })?;
+        Ok(Self { messages, stream })
+    }
+
+    /// The next message, if one has arrived.
+    pub fn poll(&self) -> Option<Message> {
+        match self.messages.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Message::Closed),
+        }
+    }
+
+    /// Waits for the next message.
+    pub fn wait(&self) -> Message {
+        self.messages.recv().unwrap_or(Message::Closed)
+    }
+
+    /// Writes a packet; a debugger that has gone away is noticed by the
+    /// reading side.
+    pub fn send(&self, packet: &[u8]) {
+        if let Ok(mut stream) = self.stream.lock() {
+            if let Some(socket) = stream.as_mut() {
+                let _ = socket.write_all(packet);
+            }
+        }
+    }
+
+    /// Drops the debugger, as `VirtualMachine.Dispose` does.
+    pub fn close(&self) {
+        if let Ok(mut stream) = self.stream.lock() {
+            if let Some(socket) = stream.take() {
+                let _ = socket.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+/// Handshakes with a debugger and forwards its commands until it goes
+/// away. `false` once the VM no longer listens.
+fn serve(
+    mut socket: TcpStream,
+    shared: &Mutex<Option<TcpStream>>,
+    sender: &Sender<Message>,
+) -> bool {
+    let mut greeting = [0u8; HANDSHAKE.len()];
+    if socket.read_exact(&mut greeting).is_err()
+        || greeting != HANDSHAKE
+        || socket.write_all(HANDSHAKE).is_err()
+    {
+        return true;
+    }
+    let Ok(writer) = socket.try_clone() else {
+        return true;
+    };
+    if let Ok(mut stream) = shared.lock() {
+        *stream = Some(writer);
+    }
+    if sender.send(Message::Connected).is_err() {
+        return false;
+    }
+    while let Ok(command) = read_command(&mut socket) {
+        if sender.send(Message::Command(command)).is_err() {
+            return false;
+        }
+    }
+    if let Ok(mut stream) = shared.lock() {
+        stream.take();
+    }
+    sender.send(Message::Closed).is_ok()
+}
+
+/// Reads the next command, skipping replies: the agent sends no commands
+/// that expect one.
+fn read_command(socket: &mut impl Read) -> io::Result<Command> {
+    loop {
+        let mut header = [0u8; HEADER_SIZE];
+        socket.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let Some(size) = length.checked_sub(HEADER_SIZE) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "packet shorter than its header",
+            ));
+        };
+        let mut data = vec![0; size];
+        socket.read_exact(&mut data)?;
+        if header[8] & REPLY_FLAG == 0 {
+            return Ok(Command {
+                id: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+                command_set: header[9],
+                command: header[10],
+                data,
+            });
+        }
+    }
+}
+
+/// The reply packet to command `id`.
+pub(crate) fn reply(id: u32, error: u16, data: &[u8]) -> Vec<u8> {
+    let mut packet = header(data.len(), id, REPLY_FLAG);
+    packet.extend_from_slice(&error.to_be_bytes());
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// A command packet sent by the agent, such as `Event.Composite`.
+pub(crate) fn command(id: u32, command_set: u8, command: u8, data: &[u8]) -> Vec<u8> {
+    let mut packet = header(data.len(), id, 0);
+    packet.extend_from_slice(&[command_set, command]);
+    packet.extend_from_slice(data);
+    packet
+}
+
+fn header(data_length: usize, id: u32, flags: u8) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(HEADER_SIZE + data_length);
+    packet.extend_from_slice(&((HEADER_SIZE + data_length) as u32).to_be_bytes());
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.push(flags);
+    packet
+}
+
+/// Reads the fields of a command's data. Running out of data is
+/// `INVALID_LENGTH`.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8], u16> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(INVALID_LENGTH)?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, u16> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn boolean(&mut self) -> Result<bool, u16> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn int(&mut self) -> Result<i32, u16> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A long, or any of the ids, which are all eight bytes.
+    pub fn u64(&mut self) -> Result<u64, u16> {
+        let mut value = [0u8; 8];
+        value.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(value))
+    }
+
+    pub fn string(&mut self) -> Result<String, u16> {
+        let length = usize::try_from(self.int()?).map_err(|_| ILLEGAL_ARGUMENT)?;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+}
+
+/// Builds the data of a reply or event.
+#[derive(Default)]
+pub(crate) struct Writer {
+    pub data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn u8(&mut self, value: u8) -> &mut Self {
+        self.data.push(value);
+        self
+    }
+
+    pub fn boolean(&mut self, value: bool) -> &mut Self {
+        self.u8(value as u8)
+    }
+
+    pub fn u16(&mut self, value: u16) -> &mut Self {
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn int(&mut self, value: i32) -> &mut Self {
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn string(&mut self, value: &str) -> &mut Self {
+        self.int(value.len() as i32);
+        self.data.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.data.extend_from_slice(value);
+        self
+    }
+}
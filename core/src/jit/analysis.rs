@@ -0,0 +1,346 @@
+use crate::bytecode::parser::ConstantPoolEntry;
+use crate::exec::decoded::DecodedCode;
+use crate::exec::instructions::Instruction;
+use crate::exec::runtime_class::RuntimeClass;
+
+const ACC_STATIC: u16 = 0x0008;
+
+/// What a local or stack slot holds in compiled code: a 32-bit integer,
+/// or a handle to a reference owned by the activation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Int,
+    Ref,
+}
+
+/// The slot layout of a compilable method. Each local keeps one kind for
+/// the whole method, so only the operand stack varies by instruction.
+pub(crate) struct Shape {
+    /// `None` for locals the method never uses.
+    pub locals: Vec<Option<Kind>>,
+    /// Operand stack on entry to each instruction; `None` if unreachable.
+    pub stacks: Vec<Option<Vec<Kind>>>,
+    pub max_stack: usize,
+    /// `None` for `void` methods.
+    pub returns: Option<Kind>,
+}
+
+impl Shape {
+    pub fn stack_at(&self, ip: usize) -> &[Kind] {
+        self.stacks
+            .get(ip)
+            .and_then(|stack| stack.as_deref())
+            .unwrap_or(&[])
+    }
+}
+
+/// Kind of a field or return type; `None` for `long`, `float`, `double`
+/// and `void`.
+fn kind_of(descriptor: &str) -> Option<Kind> {
+    match descriptor.as_bytes().first()? {
+        b'I' | b'Z' | b'B' | b'C' | b'S' => Some(Kind::Int),
+        b'L' | b'[' => Some(Kind::Ref),
+        _ => None,
+    }
+}
+
+/// Parameter kinds and return kind of a method descriptor, the outer
+/// `None` if any of them cannot be compiled.
+fn signature(descriptor: &str) -> Option<(Vec<Kind>, Option<Kind>)> {
+    let (params, ret) = descriptor.strip_prefix('(')?.split_once(')')?;
+    let mut kinds = Vec::new();
+    let bytes = params.as_bytes();
+    let mut at = 0usize;
+    while at < bytes.len() {
+        let start = at;
+        while bytes.get(at) == Some(&b'[') {
+            at += 1;
+        }
+        if bytes.get(at) == Some(&b'L') {
+            at += params[at..].find(';')?;
+        }
+        at += 1;
+        kinds.push(kind_of(params.get(start..at)?)?);
+    }
+    let returns = match ret {
+        "V" => None,
+        other => Some(kind_of(other)?),
+    };
+    Some((kinds, returns))
+}
+
+fn invoke_dynamic_descriptor(runtime: &RuntimeClass, index: u16) -> Option<&str> {
+    let ConstantPoolEntry::InvokeDynamic {
+        name_and_type_index,
+        ..
+    } = runtime.class.constant(index)?
+    else {
+        return None;
+    };
+    Some(runtime.class.get_name_and_type(*name_and_type_index)?.1)
+}
+
+fn constant_kind(runtime: &RuntimeClass, index: u16) -> Option<Kind> {
+    match runtime.class.constant(index)? {
+        ConstantPoolEntry::Integer(_) => Some(Kind::Int),
+        ConstantPoolEntry::String { .. } => Some(Kind::Ref),
+        _ => None,
+    }
+}
+
+/// Works out the kind of every slot at every instruction, or `None` if the
+/// method uses an instruction or a type the compiler does not handle.
+pub(crate) fn analyze(runtime: &RuntimeClass, method: usize) -> Option<Shape> {
+    let declared = &runtime.methods[method];
+    let code_attr = runtime.class.methods[method].code.as_ref()?;
+    let code = declared.code.as_ref()?;
+    let (params, returns) = signature(&declared.descriptor)?;
+
+    let mut locals = vec![None; code_attr.max_locals as usize];
+    let mut incoming = Vec::new();
+    if declared.access_flags & ACC_STATIC == 0 {
+        incoming.push(Kind::Ref);
+    }
+    incoming.extend(params);
+    if incoming.len() > locals.len() {
+        return None;
+    }
+    for (slot, kind) in incoming.into_iter().enumerate() {
+        locals[slot] = Some(kind);
+    }
+    for ip in 0..code.len() {
+        let (index, kind) = match code.instruction(ip) {
+            Instruction::ILoad(index)
+            | Instruction::IStore(index)
+            | Instruction::IInc(index, _) => (index, Kind::Int),
+            Instruction::ALoad(index) | Instruction::AStore(index) => (index, Kind::Ref),
+            _ => continue,
+        };
+        let slot = locals.get_mut(index as usize)?;
+        if slot.is_some_and(|existing| existing != kind) {
+            return None;
+        }
+        *slot = Some(kind);
+    }
+
+    let mut shape = Shape {
+        locals,
+        stacks: vec![None; code.len()],
+        max_stack: code_attr.max_stack as usize,
+        returns,
+    };
+    let mut worklist = vec![0usize];
+    shape.stacks[0] = Some(Vec::new());
+    while let Some(ip) = worklist.pop() {
+        let mut stack = shape.stacks[ip].clone()?;
+        let instr = code.instruction(ip);
+        let falls_through = step(runtime, &shape, instr, &mut stack)?;
+        if stack.len() > shape.max_stack {
+            return None;
+        }
+        let mut successors = Vec::new();
+        if falls_through {
+            successors.push(ip + 1);
+        }
+        if instr.branch_offset().is_some() {
+            successors.push(code.target(ip)?);
+        }
+        for next in successors {
+            // Running off the end of the code is left to the interpreter.
+            let slot = shape.stacks.get_mut(next)?;
+            match slot {
+                Some(existing) if *existing != stack => return None,
+                Some(_) => {}
+                None => {
+                    *slot = Some(stack.clone());
+                    worklist.push(next);
+                }
+            }
+        }
+    }
+    Some(shape)
+}
+
+/// Applies `instr` to the stack kinds; `Some(false)` if control does not
+/// continue with the next instruction.
+fn step(
+    runtime: &RuntimeClass,
+    shape: &Shape,
+    instr: Instruction,
+    stack: &mut Vec<Kind>,
+) -> Option<bool> {
+    fn pop(stack: &mut Vec<Kind>, kind: Kind) -> Option<()> {
+        (stack.pop()? == kind).then_some(())
+    }
+    fn pop_args(stack: &mut Vec<Kind>, params: &[Kind]) -> Option<()> {
+        for kind in params.iter().rev() {
+            pop(stack, *kind)?;
+        }
+        Some(())
+    }
+
+    match instr {
+        Instruction::AConstNull => stack.push(Kind::Ref),
+        Instruction::IConst(_) | Instruction::BiPush(_) | Instruction::SiPush(_) => {
+            stack.push(Kind::Int)
+        }
+        Instruction::Ldc(index) => stack.push(constant_kind(runtime, u16::from(index))?),
+        Instruction::LdcW(index) => stack.push(constant_kind(runtime, index)?),
+        Instruction::ILoad(_) => stack.push(Kind::Int),
+        Instruction::ALoad(_) => stack.push(Kind::Ref),
+        Instruction::IStore(_) => pop(stack, Kind::Int)?,
+        Instruction::AStore(_) => pop(stack, Kind::Ref)?,
+        Instruction::IInc(_, _) => {}
+        Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IRem
+        | Instruction::IShl
+        | Instruction::IShr
+        | Instruction::IUShr
+        | Instruction::IAnd
+        | Instruction::IOr
+        | Instruction::IXor => {
+            pop(stack, Kind::Int)?;
+            pop(stack, Kind::Int)?;
+            stack.push(Kind::Int);
+        }
+        Instruction::INeg | Instruction::I2B | Instruction::I2C | Instruction::I2S => {
+            pop(stack, Kind::Int)?;
+            stack.push(Kind::Int);
+        }
+        Instruction::Dup => {
+            let top = *stack.last()?;
+            stack.push(top);
+        }
+        Instruction::Pop => {
+            stack.pop()?;
+        }
+        Instruction::Swap => {
+            let a = stack.pop()?;
+            let b = stack.pop()?;
+            stack.push(a);
+            stack.push(b);
+        }
+        Instruction::Goto(_) => return Some(false),
+        Instruction::IfEq(_)
+        | Instruction::IfNe(_)
+        | Instruction::IfLt(_)
+        | Instruction::IfGe(_)
+        | Instruction::IfGt(_)
+        | Instruction::IfLe(_) => pop(stack, Kind::Int)?,
+        Instruction::IfICmpEq(_)
+        | Instruction::IfICmpNe(_)
+        | Instruction::IfICmpLt(_)
+        | Instruction::IfICmpGe(_)
+        | Instruction::IfICmpGt(_)
+        | Instruction::IfICmpLe(_) => {
+            pop(stack, Kind::Int)?;
+            pop(stack, Kind::Int)?;
+        }
+        Instruction::IfNull(_) | Instruction::IfNonNull(_) => pop(stack, Kind::Ref)?,
+        Instruction::IReturn | Instruction::AReturn => {
+            pop(stack, shape.returns?)?;
+            return Some(false);
+        }
+        Instruction::Return => {
+            return shape.returns.is_none().then_some(false);
+        }
+        Instruction::AThrow => {
+            pop(stack, Kind::Ref)?;
+            return Some(false);
+        }
+        Instruction::GetStatic(index) => {
+            let field = runtime.field_ref(index)?;
+            stack.push(kind_of(&field.descriptor)?);
+        }
+        Instruction::PutStatic(index) => {
+            let field = runtime.field_ref(index)?;
+            pop(stack, kind_of(&field.descriptor)?)?;
+        }
+        Instruction::GetField(index) => {
+            let field = runtime.field_ref(index)?;
+            pop(stack, Kind::Ref)?;
+            stack.push(kind_of(&field.descriptor)?);
+        }
+        Instruction::PutField(index) => {
+            let field = runtime.field_ref(index)?;
+            pop(stack, kind_of(&field.descriptor)?)?;
+            pop(stack, Kind::Ref)?;
+        }
+        Instruction::InvokeStatic(index)
+        | Instruction::InvokeVirtual(index)
+        | Instruction::InvokeSpecial(index)
+        | Instruction::InvokeInterface(index) => {
+            let method_ref = runtime.method_ref(index)?;
+            let (params, returns) = signature(&method_ref.descriptor)?;
+            pop_args(stack, &params)?;
+            if !matches!(instr, Instruction::InvokeStatic(_)) {
+                pop(stack, Kind::Ref)?;
+            }
+            stack.extend(returns);
+        }
+        Instruction::InvokeDynamic(index) => {
+            let (params, returns) = signature(invoke_dynamic_descriptor(runtime, index)?)?;
+            pop_args(stack, &params)?;
+            stack.extend(returns);
+        }
+        Instruction::New(_) => stack.push(Kind::Ref),
+        Instruction::NewArray(_) | Instruction::ANewArray(_) => {
+            pop(stack, Kind::Int)?;
+            stack.push(Kind::Ref);
+        }
+        Instruction::ArrayLength => {
+            pop(stack, Kind::Ref)?;
+            stack.push(Kind::Int);
+        }
+        Instruction::IALoad
+        | Instruction::BALoad
+        | Instruction::CALoad
+        | Instruction::SALoad
+        | Instruction::AALoad => {
+            pop(stack, Kind::Int)?;
+            pop(stack, Kind::Ref)?;
+            stack.push(if matches!(instr, Instruction::AALoad) {
+                Kind::Ref
+            } else {
+                Kind::Int
+            });
+        }
+        Instruction::IAStore
+        | Instruction::BAStore
+        | Instruction::CAStore
+        | Instruction::SAStore
+        | Instruction::AAStore => {
+            let value = if matches!(instr, Instruction::AAStore) {
+                Kind::Ref
+            } else {
+                Kind::Int
+            };
+            pop(stack, value)?;
+            pop(stack, Kind::Int)?;
+            pop(stack, Kind::Ref)?;
+        }
+        _ => return None,
+    }
+    Some(true)
+}
+
+/// Classes whose subclasses could change what a compiled method's virtual
+/// calls reach: its own class and the classes it invokes virtually.
+pub(crate) fn dependencies(runtime: &RuntimeClass, code: &DecodedCode) -> Vec<String> {
+    let mut classes = vec![runtime.name.clone()];
+    for ip in 0..code.len() {
+        if let Instruction::InvokeVirtual(index) | Instruction::InvokeInterface(index) =
+            code.instruction(ip)
+        {
+            if let Some(method_ref) = runtime.method_ref(index) {
+                if !classes.contains(&method_ref.class_name) {
+                    classes.push(method_ref.class_name.clone());
+                }
+            }
+        }
+    }
+    classes
+}
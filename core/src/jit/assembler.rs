@@ -0,0 +1,240 @@
+//! Just enough of an x86-64 encoder for the baseline compiler. Arithmetic
+//! is 32-bit, which gives Java `int` wraparound for free; 64-bit forms are
+//! only used for pointers.
+
+pub const RAX: u8 = 0;
+pub const RCX: u8 = 1;
+pub const RDX: u8 = 2;
+pub const RBX: u8 = 3;
+pub const RSI: u8 = 6;
+pub const RDI: u8 = 7;
+pub const R8: u8 = 8;
+pub const R9: u8 = 9;
+pub const R10: u8 = 10;
+pub const R11: u8 = 11;
+pub const R12: u8 = 12;
+pub const R13: u8 = 13;
+pub const R14: u8 = 14;
+pub const R15: u8 = 15;
+
+#[derive(Clone, Copy)]
+pub enum Alu {
+    Add = 0x01,
+    Or = 0x09,
+    And = 0x21,
+    Sub = 0x29,
+    Xor = 0x31,
+    Cmp = 0x39,
+}
+
+#[derive(Clone, Copy)]
+pub enum Shift {
+    Shl = 4,
+    Shr = 5,
+    Sar = 7,
+}
+
+/// Condition codes, as the second byte of a near `jcc`.
+#[derive(Clone, Copy)]
+pub enum Cond {
+    Equal = 0x84,
+    NotEqual = 0x85,
+    Less = 0x8C,
+    GreaterEqual = 0x8D,
+    LessEqual = 0x8E,
+    Greater = 0x8F,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Label(usize);
+
+#[derive(Default)]
+pub struct Assembler {
+    code: Vec<u8>,
+    labels: Vec<Option<usize>>,
+    /// Positions of rel32 fields and the label they refer to.
+    fixups: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    pub fn bind(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.code.len());
+    }
+
+    /// The finished code, or `None` if a label was never bound.
+    pub fn finish(mut self) -> Option<Vec<u8>> {
+        for (at, label) in std::mem::take(&mut self.fixups) {
+            let target = self.labels[label.0]?;
+            let rel = target as i64 - (at as i64 + 4);
+            self.code[at..at + 4].copy_from_slice(&i32::try_from(rel).ok()?.to_le_bytes());
+        }
+        Some(self.code)
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn imm32(&mut self, value: i32) {
+        self.emit(&value.to_le_bytes());
+    }
+
+    fn rex(&mut self, wide: bool, reg: u8, base: u8) {
+        let rex = 0x40 | (u8::from(wide) << 3) | ((reg >> 3) << 2) | (base >> 3);
+        if rex != 0x40 {
+            self.code.push(rex);
+        }
+    }
+
+    /// `opcode` with a register-direct ModRM operand.
+    fn op_rr(&mut self, wide: bool, opcode: &[u8], reg: u8, rm: u8) {
+        self.rex(wide, reg, rm);
+        self.emit(opcode);
+        self.code.push(0xC0 | ((reg & 7) << 3) | (rm & 7));
+    }
+
+    /// `opcode` with a `[base + disp32]` ModRM operand.
+    fn op_mem(&mut self, wide: bool, opcode: &[u8], reg: u8, base: u8, disp: i32) {
+        self.rex(wide, reg, base);
+        self.emit(opcode);
+        self.code.push(0x80 | ((reg & 7) << 3) | (base & 7));
+        if base & 7 == 4 {
+            // rsp and r12 need a SIB byte with no index.
+            self.code.push(0x24);
+        }
+        self.imm32(disp);
+    }
+
+    pub fn mov(&mut self, dst: u8, src: u8) {
+        if dst != src {
+            self.op_rr(false, &[0x89], src, dst);
+        }
+    }
+
+    pub fn mov64(&mut self, dst: u8, src: u8) {
+        self.op_rr(true, &[0x89], src, dst);
+    }
+
+    pub fn mov_imm(&mut self, dst: u8, value: i32) {
+        self.rex(false, 0, dst);
+        self.code.push(0xB8 + (dst & 7));
+        self.imm32(value);
+    }
+
+    pub fn mov_imm64(&mut self, dst: u8, value: u64) {
+        self.rex(true, 0, dst);
+        self.code.push(0xB8 + (dst & 7));
+        self.emit(&value.to_le_bytes());
+    }
+
+    pub fn load(&mut self, dst: u8, base: u8, disp: i32) {
+        self.op_mem(false, &[0x8B], dst, base, disp);
+    }
+
+    pub fn load64(&mut self, dst: u8, base: u8, disp: i32) {
+        self.op_mem(true, &[0x8B], dst, base, disp);
+    }
+
+    pub fn store(&mut self, base: u8, disp: i32, src: u8) {
+        self.op_mem(false, &[0x89], src, base, disp);
+    }
+
+    pub fn store_imm(&mut self, base: u8, disp: i32, value: i32) {
+        self.op_mem(false, &[0xC7], 0, base, disp);
+        self.imm32(value);
+    }
+
+    /// `add dword [base + disp], value`.
+    pub fn add_mem_imm(&mut self, base: u8, disp: i32, value: i32) {
+        self.op_mem(false, &[0x81], 0, base, disp);
+        self.imm32(value);
+    }
+
+    pub fn alu(&mut self, op: Alu, dst: u8, src: u8) {
+        self.op_rr(false, &[op as u8], src, dst);
+    }
+
+    pub fn cmp_imm(&mut self, dst: u8, value: i32) {
+        self.op_rr(false, &[0x81], 7, dst);
+        self.imm32(value);
+    }
+
+    pub fn test(&mut self, a: u8, b: u8) {
+        self.op_rr(false, &[0x85], b, a);
+    }
+
+    pub fn imul(&mut self, dst: u8, src: u8) {
+        self.op_rr(false, &[0x0F, 0xAF], dst, src);
+    }
+
+    pub fn neg(&mut self, dst: u8) {
+        self.op_rr(false, &[0xF7], 3, dst);
+    }
+
+    /// Shifts `dst` by `cl`; the hardware masks the count to five bits,
+    /// as Java does.
+    pub fn shift_cl(&mut self, shift: Shift, dst: u8) {
+        self.op_rr(false, &[0xD3], shift as u8, dst);
+    }
+
+    /// Sign-extends `eax` into `edx`.
+    pub fn cdq(&mut self) {
+        self.code.push(0x99);
+    }
+
+    /// Divides `edx:eax` by `src`: quotient in `eax`, remainder in `edx`.
+    pub fn idiv(&mut self, src: u8) {
+        self.op_rr(false, &[0xF7], 7, src);
+    }
+
+    /// `movsx dst, src8`; `src` must be one of `eax`..`ebx`.
+    pub fn movsx8(&mut self, dst: u8, src: u8) {
+        self.op_rr(false, &[0x0F, 0xBE], dst, src);
+    }
+
+    pub fn movsx16(&mut self, dst: u8, src: u8) {
+        self.op_rr(false, &[0x0F, 0xBF], dst, src);
+    }
+
+    pub fn movzx16(&mut self, dst: u8, src: u8) {
+        self.op_rr(false, &[0x0F, 0xB7], dst, src);
+    }
+
+    pub fn push(&mut self, reg: u8) {
+        self.rex(false, 0, reg);
+        self.code.push(0x50 + (reg & 7));
+    }
+
+    pub fn pop(&mut self, reg: u8) {
+        self.rex(false, 0, reg);
+        self.code.push(0x58 + (reg & 7));
+    }
+
+    pub fn call(&mut self, reg: u8) {
+        self.op_rr(false, &[0xFF], 2, reg);
+    }
+
+    pub fn ret(&mut self) {
+        self.code.push(0xC3);
+    }
+
+    pub fn jmp(&mut self, label: Label) {
+        self.code.push(0xE9);
+        self.fixup(label);
+    }
+
+    pub fn jcc(&mut self, cond: Cond, label: Label) {
+        self.emit(&[0x0F, cond as u8]);
+        self.fixup(label);
+    }
+
+    fn fixup(&mut self, label: Label) {
+        self.fixups.push((self.code.len(), label));
+        self.imm32(0);
+    }
+}
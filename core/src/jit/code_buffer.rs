@@ -0,0 +1,52 @@
+use std::ptr;
+
+/// Machine code copied into its own executable mapping.
+pub(crate) struct CodeBuffer {
+    base: *mut libc::c_void,
+    len: usize,
+}
+
+impl CodeBuffer {
+    /// Maps `code` read-write, copies it in and flips the mapping to
+    /// read-execute, so that no page is ever writable and executable.
+    pub fn new(code: &[u8]) -> Option<Self> {
+        let len = code.len().max(1);
+        // SAFETY: an anonymous private mapping has no preconditions; the
+        // result is checked before use.
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return None;
+        }
+        let buffer = Self { base, len };
+        // SAFETY: the mapping is at least `code.len()` bytes and writable.
+        unsafe {
+            ptr::copy_nonoverlapping(code.as_ptr(), base as *mut u8, code.len());
+            if libc::mprotect(base, len, libc::PROT_READ | libc::PROT_EXEC) != 0 {
+                return None;
+            }
+        }
+        Some(buffer)
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.base as *const u8
+    }
+}
+
+impl Drop for CodeBuffer {
+    fn drop(&mut self) {
+        // SAFETY: `base` and `len` describe a mapping this buffer owns.
+        unsafe {
+            libc::munmap(self.base, self.len);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use crate::exec::decoded::DecodedCode;
+use crate::exec::instructions::Instruction;
+use crate::jit::analysis::Shape;
+use crate::jit::assembler::*;
+use crate::jit::runtime::{self, JitFrame, DEOPTIMIZE};
+use crate::runtime::signals;
+use std::mem::offset_of;
+
+/// Registers holding the bottom of the operand stack; deeper slots live
+/// in the frame's stack array.
+const STACK_REGS: [u8; 6] = [R8, R9, R10, R11, R14, R15];
+/// Pinned for the whole method: the `JitFrame`, locals and stack arrays.
+const FRAME: u8 = RBX;
+const LOCALS: u8 = R12;
+const STACK: u8 = R13;
+/// Callee-saved registers the method uses.
+const SAVED: [u8; 5] = [RBX, R12, R13, R14, R15];
+
+const IP: i32 = offset_of!(JitFrame, ip) as i32;
+const RESULT: i32 = offset_of!(JitFrame, result) as i32;
+
+/// Register for stack slot `slot`, if it has one.
+fn slot_reg(slot: usize) -> Option<u8> {
+    STACK_REGS.get(slot).copied()
+}
+
+fn slot_disp(slot: usize) -> i32 {
+    (slot * 4) as i32
+}
+
+fn local_disp(index: u16) -> i32 {
+    i32::from(index) * 4
+}
+
+/// A deoptimization exit emitted after the method body.
+struct Trap {
+    label: Label,
+    ip: usize,
+    depth: usize,
+}
+
+struct Compiler<'a> {
+    asm: Assembler,
+    code: &'a DecodedCode,
+    shape: &'a Shape,
+    labels: Vec<Label>,
+    epilogue: Label,
+    traps: Vec<Trap>,
+}
+
+/// Translates a method into machine code for `extern "C" fn(*mut JitFrame)
+/// -> u32`, also returning the instructions it can be entered at.
+pub(crate) fn compile(code: &DecodedCode, shape: &Shape) -> Option<(Vec<u8>, Vec<usize>)> {
+    let mut asm = Assembler::default();
+    let labels = (0..code.len()).map(|_| asm.new_label()).collect();
+    let epilogue = asm.new_label();
+    let mut compiler = Compiler {
+        asm,
+        code,
+        shape,
+        labels,
+        epilogue,
+        traps: Vec::new(),
+    };
+    let osr_entries = compiler.osr_entries();
+    compiler.prologue(&osr_entries);
+    for ip in 0..code.len() {
+        let label = compiler.labels[ip];
+        compiler.asm.bind(label);
+        if shape.stacks[ip].is_some() {
+            compiler.instruction(ip)?;
+        }
+    }
+    compiler.epilogue();
+    compiler.traps();
+    Some((compiler.asm.finish()?, osr_entries))
+}
+
+impl Compiler<'_> {
+    /// Loop headers with an empty operand stack, where a running
+    /// interpreter frame can move over.
+    fn osr_entries(&self) -> Vec<usize> {
+        let mut entries = Vec::new();
+        for ip in 0..self.code.len() {
+            if self.shape.stacks[ip].is_none() {
+                continue;
+            }
+            if let Some(target) = self.code.target(ip) {
+                if target > 0
+                    && target <= ip
+                    && self.shape.stack_at(target).is_empty()
+                    && !entries.contains(&target)
+                {
+                    entries.push(target);
+                }
+            }
+        }
+        entries
+    }
+
+    fn prologue(&mut self, osr_entries: &[usize]) {
+        for reg in SAVED {
+            self.asm.push(reg);
+        }
+        self.asm.mov64(FRAME, RDI);
+        self.asm
+            .load64(LOCALS, FRAME, offset_of!(JitFrame, locals) as i32);
+        self.asm
+            .load64(STACK, FRAME, offset_of!(JitFrame, stack) as i32);
+        if !osr_entries.is_empty() {
+            self.asm.load(RAX, FRAME, IP);
+            for &entry in osr_entries {
+                self.asm.cmp_imm(RAX, entry as i32);
+                self.asm.jcc(Cond::Equal, self.labels[entry]);
+            }
+        }
+    }
+
+    fn epilogue(&mut self) {
+        self.asm.bind(self.epilogue);
+        for reg in SAVED.iter().rev() {
+            self.asm.pop(*reg);
+        }
+        self.asm.ret();
+    }
+
+    fn traps(&mut self) {
+        for trap in std::mem::take(&mut self.traps) {
+            self.asm.bind(trap.label);
+            self.deoptimize(trap.ip, trap.depth);
+        }
+    }
+
+    /// Writes the register part of the stack back to memory.
+    fn spill(&mut self, depth: usize) {
+        for (slot, &reg) in STACK_REGS.iter().enumerate().take(depth) {
+            self.asm.store(STACK, slot_disp(slot), reg);
+        }
+    }
+
+    fn reload(&mut self, depth: usize) {
+        for (slot, &reg) in STACK_REGS.iter().enumerate().take(depth) {
+            self.asm.load(reg, STACK, slot_disp(slot));
+        }
+    }
+
+    /// Leaves compiled code so that the interpreter resumes at `ip`.
+    fn deoptimize(&mut self, ip: usize, depth: usize) {
+        self.spill(depth);
+        self.asm.store_imm(FRAME, IP, ip as i32);
+        self.asm.mov_imm(RAX, DEOPTIMIZE as i32);
+        self.asm.jmp(self.epilogue);
+    }
+
+    fn trap(&mut self, ip: usize, depth: usize) -> Label {
+        let label = self.asm.new_label();
+        self.traps.push(Trap { label, ip, depth });
+        label
+    }
+
+    /// Leaves for the interpreter, which handles the signal, when SIGINT
+    /// or SIGTERM is pending.
+    fn poll_signal(&mut self, ip: usize, depth: usize) {
+        let trap = self.trap(ip, depth);
+        self.asm.mov_imm64(RAX, signals::pending_address() as u64);
+        self.asm.load(RAX, RAX, 0);
+        self.asm.cmp_imm(RAX, 0);
+        self.asm.jcc(Cond::Greater, trap);
+    }
+
+    fn get(&mut self, slot: usize, dst: u8) {
+        match slot_reg(slot) {
+            Some(reg) => self.asm.mov(dst, reg),
+            None => self.asm.load(dst, STACK, slot_disp(slot)),
+        }
+    }
+
+    fn put(&mut self, slot: usize, src: u8) {
+        match slot_reg(slot) {
+            Some(reg) => self.asm.mov(reg, src),
+            None => self.asm.store(STACK, slot_disp(slot), src),
+        }
+    }
+
+    fn put_imm(&mut self, slot: usize, value: i32) {
+        match slot_reg(slot) {
+            Some(reg) => self.asm.mov_imm(reg, value),
+            None => self.asm.store_imm(STACK, slot_disp(slot), value),
+        }
+    }
+
+    fn branch(&mut self, ip: usize, cond: Cond) -> Option<()> {
+        let target = self.code.target(ip)?;
+        let label = *self.labels.get(target)?;
+        self.asm.jcc(cond, label);
+        Some(())
+    }
+
+    fn instruction(&mut self, ip: usize) -> Option<()> {
+        let depth = self.shape.stack_at(ip).len();
+        let top = depth.wrapping_sub(1);
+        let below = depth.wrapping_sub(2);
+        let instr = self.code.instruction(ip);
+        if self.code.target(ip).is_some_and(|target| target <= ip) {
+            self.poll_signal(ip, depth);
+        }
+        match instr {
+            Instruction::AConstNull => self.put_imm(depth, 0),
+            Instruction::IConst(value) => self.put_imm(depth, value),
+            Instruction::BiPush(value) => self.put_imm(depth, i32::from(value)),
+            Instruction::SiPush(value) => self.put_imm(depth, i32::from(value)),
+            Instruction::ILoad(index) | Instruction::ALoad(index) => match slot_reg(depth) {
+                Some(reg) => self.asm.load(reg, LOCALS, local_disp(index)),
+                None => {
+                    self.asm.load(RAX, LOCALS, local_disp(index));
+                    self.put(depth, RAX);
+                }
+            },
+            Instruction::IStore(index) | Instruction::AStore(index) => {
+                self.get(top, RAX);
+                self.asm.store(LOCALS, local_disp(index), RAX);
+            }
+            Instruction::IInc(index, delta) => {
+                self.asm
+                    .add_mem_imm(LOCALS, local_disp(index), i32::from(delta));
+            }
+            Instruction::IAdd
+            | Instruction::ISub
+            | Instruction::IAnd
+            | Instruction::IOr
+            | Instruction::IXor => {
+                let op = match instr {
+                    Instruction::IAdd => Alu::Add,
+                    Instruction::ISub => Alu::Sub,
+                    Instruction::IAnd => Alu::And,
+                    Instruction::IOr => Alu::Or,
+                    _ => Alu::Xor,
+                };
+                self.get(below, RAX);
+                self.get(top, RCX);
+                self.asm.alu(op, RAX, RCX);
+                self.put(below, RAX);
+            }
+            Instruction::IMul => {
+                self.get(below, RAX);
+                self.get(top, RCX);
+                self.asm.imul(RAX, RCX);
+                self.put(below, RAX);
+            }
+            Instruction::IDiv | Instruction::IRem => {
+                // A zero divisor throws and MIN_VALUE / -1 faults on x86;
+                // both are left to the interpreter.
+                let trap = self.trap(ip, depth);
+                self.get(below, RAX);
+                self.get(top, RCX);
+                self.asm.test(RCX, RCX);
+                self.asm.jcc(Cond::Equal, trap);
+                self.asm.cmp_imm(RCX, -1);
+                self.asm.jcc(Cond::Equal, trap);
+                self.asm.cdq();
+                self.asm.idiv(RCX);
+                let result = if matches!(instr, Instruction::IDiv) {
+                    RAX
+                } else {
+                    RDX
+                };
+                self.put(below, result);
+            }
+            Instruction::IShl | Instruction::IShr | Instruction::IUShr => {
+                let shift = match instr {
+                    Instruction::IShl => Shift::Shl,
+                    Instruction::IShr => Shift::Sar,
+                    _ => Shift::Shr,
+                };
+                self.get(below, RAX);
+                self.get(top, RCX);
+                self.asm.shift_cl(shift, RAX);
+                self.put(below, RAX);
+            }
+            Instruction::INeg => {
+                self.get(top, RAX);
+                self.asm.neg(RAX);
+                self.put(top, RAX);
+            }
+            Instruction::I2B | Instruction::I2C | Instruction::I2S => {
+                self.get(top, RAX);
+                match instr {
+                    Instruction::I2B => self.asm.movsx8(RAX, RAX),
+                    Instruction::I2C => self.asm.movzx16(RAX, RAX),
+                    _ => self.asm.movsx16(RAX, RAX),
+                }
+                self.put(top, RAX);
+            }
+            Instruction::Dup => {
+                self.get(top, RAX);
+                self.put(depth, RAX);
+            }
+            Instruction::Pop => {}
+            Instruction::Swap => {
+                self.get(top, RAX);
+                self.get(below, RCX);
+                self.put(below, RAX);
+                self.put(top, RCX);
+            }
+            Instruction::Goto(_) => {
+                let target = self.code.target(ip)?;
+                let label = *self.labels.get(target)?;
+                self.asm.jmp(label);
+            }
+            Instruction::IfEq(_)
+            | Instruction::IfNe(_)
+            | Instruction::IfLt(_)
+            | Instruction::IfGe(_)
+            | Instruction::IfGt(_)
+            | Instruction::IfLe(_)
+            | Instruction::IfNull(_)
+            | Instruction::IfNonNull(_) => {
+                let cond = match instr {
+                    Instruction::IfEq(_) | Instruction::IfNull(_) => Cond::Equal,
+                    Instruction::IfNe(_) | Instruction::IfNonNull(_) => Cond::NotEqual,
+                    Instruction::IfLt(_) => Cond::Less,
+                    Instruction::IfGe(_) => Cond::GreaterEqual,
+                    Instruction::IfGt(_) => Cond::Greater,
+                    _ => Cond::LessEqual,
+                };
+                self.get(top, RAX);
+                self.asm.test(RAX, RAX);
+                self.branch(ip, cond)?;
+            }
+            Instruction::IfICmpEq(_)
+            | Instruction::IfICmpNe(_)
+            | Instruction::IfICmpLt(_)
+            | Instruction::IfICmpGe(_)
+            | Instruction::IfICmpGt(_)
+            | Instruction::IfICmpLe(_) => {
+                let cond = match instr {
+                    Instruction::IfICmpEq(_) => Cond::Equal,
+                    Instruction::IfICmpNe(_) => Cond::NotEqual,
+                    Instruction::IfICmpLt(_) => Cond::Less,
+                    Instruction::IfICmpGe(_) => Cond::GreaterEqual,
+                    Instruction::IfICmpGt(_) => Cond::Greater,
+                    _ => Cond::LessEqual,
+                };
+                self.get(below, RAX);
+                self.get(top, RCX);
+                self.asm.alu(Alu::Cmp, RAX, RCX);
+                self.branch(ip, cond)?;
+            }
+            Instruction::IReturn | Instruction::AReturn => {
+                self.get(top, RAX);
+                self.asm.store(FRAME, RESULT, RAX);
+                self.asm.mov_imm(RAX, runtime::RETURNED as i32);
+                self.asm.jmp(self.epilogue);
+            }
+            Instruction::Return => {
+                self.asm.mov_imm(RAX, runtime::RETURNED as i32);
+                self.asm.jmp(self.epilogue);
+            }
+            // Everything else goes through the runtime with the whole
+            // stack in memory.
+            _ => {
+                self.spill(depth);
+                self.asm.mov64(RDI, FRAME);
+                self.asm.mov_imm(RSI, ip as i32);
+                self.asm.mov_imm(RDX, depth as i32);
+                self.asm
+                    .mov_imm64(RAX, runtime::slow_path as *const () as usize as u64);
+                self.asm.call(RAX);
+                self.asm.test(RAX, RAX);
+                self.asm.jcc(Cond::NotEqual, self.epilogue);
+                match self.shape.stacks.get(ip + 1) {
+                    Some(Some(after)) => {
+                        let after = after.len();
+                        self.reload(after);
+                    }
+                    // athrow: the runtime always leaves.
+                    _ => self.asm.jmp(self.epilogue),
+                }
+            }
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,168 @@
+//! Baseline compiler for hot methods.
+//!
+//! Methods are interpreted until they have been invoked, or have taken
+//! backward branches, often enough; they are then translated instruction
+//! by instruction into x86-64 machine code. The operand stack lives in
+//! registers, integer arithmetic, locals and branches run natively, and
+//! everything that touches the heap or the class loader calls back into
+//! the interpreter through one slow-path stub. Anything the compiled code
+//! cannot handle (a zero divisor, an exception, code that was invalidated
+//! by a newly loaded class, a signal noticed on a backward branch)
+//! deoptimizes: the frame is rebuilt and the interpreter carries on from
+//! the same instruction.
+//!
+//! Only methods whose values are all `int`-like or references are compiled;
+//! `long`, `float` and `double` code stays interpreted.
+
+mod analysis;
+#[cfg(all(target_arch = "x86_64", unix))]
+mod assembler;
+#[cfg(all(target_arch = "x86_64", unix))]
+mod code_buffer;
+#[cfg(all(target_arch = "x86_64", unix))]
+mod compiler;
+pub(crate) mod runtime;
+
+use crate::exec::runtime_class::{CallSite, RuntimeClass};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+pub(crate) use self::analysis::Kind;
+
+/// Invocations before a method is compiled.
+pub const INVOCATION_THRESHOLD: u32 = 1000;
+/// Backward branches taken before a running method moves to compiled code.
+pub const BACKEDGE_THRESHOLD: u32 = 10_000;
+/// Deoptimizations after which a method is left to the interpreter.
+const DEOPTIMIZATION_LIMIT: u32 = 64;
+
+/// When methods are compiled, as chosen by `-Xint` and `-Xcomp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JitMode {
+    /// Interpret first and compile hot methods.
+    #[default]
+    Mixed,
+    /// Never compile (`-Xint`).
+    Interpreted,
+    /// Compile every method on first invocation (`-Xcomp`).
+    Compiled,
+}
+
+impl JitMode {
+    /// How `java -version` describes the mode.
+    pub fn describe(self) -> &'static str {
+        match self {
+            JitMode::Mixed => "mixed mode",
+            JitMode::Interpreted => "interpreted mode",
+            JitMode::Compiled => "compiled mode",
+        }
+    }
+}
+
+/// Whether this build can generate code for the host.
+pub fn is_supported() -> bool {
+    cfg!(all(target_arch = "x86_64", unix))
+}
+
+/// Profile and compiled code of one method.
+#[derive(Default)]
+pub(crate) struct MethodJit {
+    pub invocations: Cell<u32>,
+    pub backedges: Cell<u32>,
+    pub compiled: RefCell<Option<Rc<CompiledMethod>>>,
+    /// Set once compilation failed or the method deoptimized too often.
+    pub not_compilable: Cell<bool>,
+}
+
+impl MethodJit {
+    /// The compiled code, dropping it first if it was invalidated.
+    pub fn current(&self) -> Option<Rc<CompiledMethod>> {
+        let mut compiled = self.compiled.borrow_mut();
+        if compiled.as_ref().is_some_and(|code| !code.is_valid()) {
+            *compiled = None;
+            self.invocations.set(0);
+            self.backedges.set(0);
+        }
+        compiled.clone()
+    }
+
+    /// Counts a deoptimization of `compiled`; a method that keeps
+    /// deoptimizing goes back to the interpreter for good. True if this
+    /// made the code not entrant.
+    pub fn record_deoptimization(&self, compiled: &CompiledMethod) -> bool {
+        let count = compiled.deoptimizations.get() + 1;
+        compiled.deoptimizations.set(count);
+        if count < DEOPTIMIZATION_LIMIT || !compiled.is_valid() {
+            return false;
+        }
+        compiled.invalidate();
+        self.not_compilable.set(true);
+        true
+    }
+}
+
+/// Machine code for one method, with what it needs at run time.
+pub(crate) struct CompiledMethod {
+    #[cfg(all(target_arch = "x86_64", unix))]
+    code: code_buffer::CodeBuffer,
+    /// `Class::method`, for `-XX:+PrintCompilation`.
+    pub name: String,
+    /// Size of the bytecode compiled.
+    pub bytecode_size: usize,
+    pub shape: analysis::Shape,
+    /// Instructions compiled code may be entered at, besides the first.
+    pub osr_entries: Vec<usize>,
+    /// Inline cache of each invoke, by instruction index.
+    pub inline_caches: Vec<RefCell<Option<CallSite>>>,
+    /// Classes whose subclasses could change what the code linked to.
+    pub dependencies: Vec<String>,
+    valid: Cell<bool>,
+    deoptimizations: Cell<u32>,
+}
+
+impl CompiledMethod {
+    pub fn is_valid(&self) -> bool {
+        self.valid.get()
+    }
+
+    /// Marks the code not entrant. Activations already running it
+    /// deoptimize when they next return from the runtime.
+    pub fn invalidate(&self) {
+        self.valid.set(false);
+    }
+
+    /// Whether the code can be entered at instruction `ip`.
+    pub fn has_entry(&self, ip: usize) -> bool {
+        ip == 0 || self.osr_entries.contains(&ip)
+    }
+}
+
+/// Compiles a method, or `None` if it uses something the compiler does
+/// not support.
+#[cfg(all(target_arch = "x86_64", unix))]
+pub(crate) fn compile(runtime: &RuntimeClass, method: usize) -> Option<CompiledMethod> {
+    let shape = analysis::analyze(runtime, method)?;
+    let code = runtime.methods[method].code.as_ref()?;
+    let (machine_code, osr_entries) = compiler::compile(code, &shape)?;
+    let dependencies = analysis::dependencies(runtime, code);
+    Some(CompiledMethod {
+        code: code_buffer::CodeBuffer::new(&machine_code)?,
+        name: format!(
+            "{}::{}",
+            runtime.name.replace('/', "."),
+            runtime.methods[method].name
+        ),
+        bytecode_size: code.offset(code.len()),
+        shape,
+        osr_entries,
+        inline_caches: (0..code.len()).map(|_| RefCell::new(None)).collect(),
+        dependencies,
+        valid: Cell::new(true),
+        deoptimizations: Cell::new(0),
+    })
+}
+
+#[cfg(not(all(target_arch = "x86_64", unix)))]
+pub(crate) fn compile(_runtime: &RuntimeClass, _method: usize) -> Option<CompiledMethod> {
+    None
+}
@@ -0,0 +1,218 @@
+use crate::exec::interpreter::{Flow, Interpreter};
+use crate::exec::runtime_class::RuntimeClass;
+use crate::jit::{CompiledMethod, Kind};
+use crate::loader::class_loader::ClassLoader;
+use crate::runtime::frame::Frame;
+use crate::runtime::heap::{Heap, HeapValue};
+use std::ffi::c_void;
+
+/// Status codes shared by compiled code and the slow path.
+pub(crate) const RETURNED: u32 = 0;
+pub(crate) const DEOPTIMIZE: u32 = 1;
+pub(crate) const ABORT: u32 = 2;
+
+/// The state compiled code runs on. Slots hold `int`s directly and
+/// references as handles into the activation's table, 0 being `null`.
+#[repr(C)]
+pub(crate) struct JitFrame {
+    pub locals: *mut i32,
+    pub stack: *mut i32,
+    pub result: i32,
+    /// The entry instruction on the way in, the instruction the
+    /// interpreter resumes at after a deoptimization on the way out.
+    pub ip: u32,
+    activation: *mut c_void,
+}
+
+/// How a run of compiled code ended.
+pub(crate) enum JitOutcome {
+    Returned(Option<HeapValue>),
+    /// Continue in the interpreter at `ip` with `frame`; an exception
+    /// may be pending.
+    Deoptimized {
+        ip: usize,
+        frame: Frame,
+    },
+    /// The method gave up, as the interpreter's `Flow::Abort`.
+    Aborted,
+}
+
+/// Everything the slow path needs, reached through `JitFrame::activation`.
+struct Activation<'a> {
+    interpreter: &'a Interpreter,
+    class_loader: &'a mut ClassLoader,
+    heap: &'a mut Heap,
+    runtime: &'a RuntimeClass,
+    method: usize,
+    compiled: &'a CompiledMethod,
+    refs: Vec<HeapValue>,
+}
+
+impl Activation<'_> {
+    fn handle(&mut self, value: &HeapValue) -> i32 {
+        match value {
+            HeapValue::Null => 0,
+            HeapValue::Int(v) => *v,
+            other => {
+                self.refs.push(other.clone());
+                self.refs.len() as i32
+            }
+        }
+    }
+
+    fn value(&self, kind: Kind, slot: i32) -> HeapValue {
+        match kind {
+            Kind::Int => HeapValue::Int(slot),
+            Kind::Ref => usize::try_from(slot)
+                .ok()
+                .and_then(|handle| handle.checked_sub(1))
+                .and_then(|index| self.refs.get(index))
+                .cloned()
+                .unwrap_or(HeapValue::Null),
+        }
+    }
+
+    /// Rebuilds the interpreter frame for instruction `ip`.
+    fn materialize(&self, locals: &[i32], stack: &[i32], ip: usize) -> Frame {
+        let code_attr = self.runtime.class.methods[self.method].code.as_ref();
+        let max_locals = code_attr.map_or(0, |code| code.max_locals as usize);
+        let mut frame = Frame::new(max_locals, self.compiled.shape.max_stack);
+        for (index, kind) in self.compiled.shape.locals.iter().enumerate() {
+            if let Some(kind) = kind {
+                frame.set_local(index, self.value(*kind, locals[index]));
+            }
+        }
+        for (slot, kind) in self.compiled.shape.stack_at(ip).iter().enumerate() {
+            frame.push(self.value(*kind, stack[slot]));
+        }
+        frame
+    }
+}
+
+/// Runs compiled code from instruction `entry`, which must be 0 or an OSR
+/// entry, with `locals` as the method's local variables.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn enter(
+    interpreter: &Interpreter,
+    class_loader: &mut ClassLoader,
+    heap: &mut Heap,
+    runtime: &RuntimeClass,
+    method: usize,
+    compiled: &CompiledMethod,
+    locals: &[HeapValue],
+    entry: usize,
+) -> JitOutcome {
+    let shape = &compiled.shape;
+    let mut activation = Activation {
+        interpreter,
+        class_loader,
+        heap,
+        runtime,
+        method,
+        compiled,
+        refs: Vec::new(),
+    };
+    let mut local_slots = vec![0i32; shape.locals.len().max(1)];
+    for (index, kind) in shape.locals.iter().enumerate() {
+        let (Some(kind), Some(value)) = (kind, locals.get(index)) else {
+            continue;
+        };
+        local_slots[index] = match (kind, value) {
+            (Kind::Int, HeapValue::Int(v)) => *v,
+            (Kind::Ref, value) if !matches!(value, HeapValue::Int(_)) => activation.handle(value),
+            _ => 0,
+        };
+    }
+    let mut stack_slots = vec![0i32; shape.max_stack.max(1)];
+    let mut frame = JitFrame {
+        locals: local_slots.as_mut_ptr(),
+        stack: stack_slots.as_mut_ptr(),
+        result: 0,
+        ip: entry as u32,
+        activation: &mut activation as *mut Activation as *mut c_void,
+    };
+
+    #[cfg(all(target_arch = "x86_64", unix))]
+    let status = {
+        // SAFETY: the buffer holds code generated for exactly this
+        // signature, and `frame` points at arrays sized from the same
+        // shape the code was generated from.
+        let code: extern "C" fn(*mut JitFrame) -> u32 =
+            unsafe { std::mem::transmute(compiled.code.as_ptr()) };
+        code(&mut frame)
+    };
+    #[cfg(not(all(target_arch = "x86_64", unix)))]
+    let status = {
+        frame.ip = entry as u32;
+        DEOPTIMIZE
+    };
+
+    match status {
+        RETURNED => JitOutcome::Returned(
+            shape
+                .returns
+                .map(|kind| activation.value(kind, frame.result)),
+        ),
+        DEOPTIMIZE => {
+            let ip = frame.ip as usize;
+            JitOutcome::Deoptimized {
+                ip,
+                frame: activation.materialize(&local_slots, &stack_slots, ip),
+            }
+        }
+        _ => JitOutcome::Aborted,
+    }
+}
+
+/// Runs instruction `ip` for compiled code, with the `depth` slots of the
+/// operand stack spilled to memory. Returns 0 to continue, or the status
+/// compiled code leaves with.
+pub(crate) extern "C" fn slow_path(frame: *mut JitFrame, ip: u32, depth: u32) -> u32 {
+    // SAFETY: compiled code passes the frame it was entered with, whose
+    // activation outlives the call.
+    let frame = unsafe { &mut *frame };
+    let activation = unsafe { &mut *(frame.activation as *mut Activation) };
+    let ip = ip as usize;
+    let depth = depth as usize;
+    let compiled = activation.compiled;
+    let shape = &compiled.shape;
+    // SAFETY: the stack array holds `max_stack` slots.
+    let stack = unsafe { std::slice::from_raw_parts_mut(frame.stack, shape.max_stack.max(1)) };
+
+    let mut operands = Frame::new(0, shape.max_stack);
+    for (slot, kind) in shape.stack_at(ip).iter().enumerate().take(depth) {
+        operands.push(activation.value(*kind, stack[slot]));
+    }
+    let Some(code) = activation.runtime.methods[activation.method].code.as_ref() else {
+        return ABORT;
+    };
+    let interpreter = activation.interpreter;
+    interpreter.check_shutdown_signal(activation.class_loader, activation.heap);
+    // References only compiled code holds must survive collections the
+    // call runs.
+    let mark = interpreter.suspend_roots(activation.refs.iter().chain(&operands.operand_stack));
+    let flow = interpreter.exec_slow(
+        activation.class_loader,
+        activation.heap,
+        activation.runtime,
+        &mut operands,
+        code.instruction(ip),
+        &compiled.inline_caches[ip],
+    );
+    interpreter.resume_roots(mark);
+    if let Flow::Abort = flow {
+        return ABORT;
+    }
+    if activation.interpreter.pending_exception().is_some() {
+        frame.ip = ip as u32;
+        return DEOPTIMIZE;
+    }
+    for (slot, value) in operands.operand_stack.iter().enumerate() {
+        stack[slot] = activation.handle(value);
+    }
+    if !compiled.is_valid() {
+        frame.ip = (ip + 1) as u32;
+        return DEOPTIMIZE;
+    }
+    RETURNED
+}
@@ -1,13 +1,26 @@
+pub mod args;
 pub mod bytecode;
 pub mod exec;
+pub mod jdwp;
+pub mod jit;
 pub mod loader;
 pub mod native;
 pub mod runtime;
+pub mod source_launcher;
+pub mod verifier;
+pub mod vm;
 
+use crate::bytecode::parser::ClassFile;
 use crate::exec::interpreter::Interpreter;
-use crate::loader::class_loader::ClassLoader;
-use crate::runtime::heap::Heap;
-use std::path::Path;
+use crate::jdwp::{Agent, AgentOptions};
+use crate::jit::JitMode;
+use crate::loader::class_loader::{ClassLoader, LoadError};
+use crate::loader::shared_archive::{self, ArchiveError, ShareMode, SharedArchive};
+use crate::runtime::assertions::AssertionStatus;
+use crate::runtime::heap::{Heap, HeapValue, DEFAULT_MAX_HEAP_SIZE};
+use crate::runtime::signals;
+use crate::source_launcher::SourceCompiler;
+use std::path::{Path, PathBuf};
 
 const ARIA_VERSION: &str = include_str!("../../VERSION");
 const JAVA_VERSION: &str = include_str!("../../VERSION_JAVA");
@@ -18,19 +31,151 @@ fn print_banner() {
     println!("===============================");
 }
 
+const USAGE: &str = "\
+Usage: java [options] <mainclass> [args...]
+           (to execute a class)
+   or  java [options] <sourcefile> [args]
+           (to execute a single source-file program)
+
+ Arguments following the main class or source file are passed as the
+ arguments to main class.
+
+ where options include:
+
+    -cp <class search path of directories>
+    -classpath <class search path of directories>
+    --class-path <class search path of directories>
+                  A : separated list of directories to search for class files.
+    --dry-run     create VM and load main class but do not execute main method.
+    -D<name>=<value>
+                  set a system property
+    -version      print product version to the error stream and exit
+    --version     print product version to the output stream and exit
+    -showversion  print product version to the error stream and continue
+    --show-version
+                  print product version to the output stream and continue
+    -? -h -help
+                  print this help message to the error stream
+    --help        print this help message to the output stream
+    -X            print help on extra options to the error stream
+    --help-extra  print help on extra options to the output stream
+    -ea[:<packagename>...|:<classname>]
+    -enableassertions[:<packagename>...|:<classname>]
+                  enable assertions with specified granularity
+    -da[:<packagename>...|:<classname>]
+    -disableassertions[:<packagename>...|:<classname>]
+                  disable assertions with specified granularity
+    -esa | -enablesystemassertions
+                  enable system assertions
+    -dsa | -disablesystemassertions
+                  disable system assertions
+    @argument files
+                  one or more argument files containing options
+    --disable-@files
+                  prevent further argument file expansion
+    -agentlib:jdwp=<options>
+                  load the JDWP debugger agent, e.g.
+                  -agentlib:jdwp=transport=dt_socket,server=y,address=8000
+    --source <version>
+                  set the version of the source in source-file mode.
+To specify an argument for a long option, you can use --<name>=<value> or
+--<name> <value>.
+";
+
+const EXTRA_USAGE: &str = "\
+    -Xcomp            forces compilation of methods on first invocation
+    -Xint             interpreted mode execution only
+    -Xmixed           mixed mode execution (default)
+    -Xms<size>        set initial Java heap size
+    -Xmx<size>        set maximum Java heap size
+    -Xrs              reduce use of OS signals by Java/VM (see documentation)
+    -Xshare:auto      use shared class data if possible (default)
+    -Xshare:off       do not attempt to use shared class data
+    -Xshare:on        require using shared class data, otherwise fail.
+    -Xshare:dump      write the shared class data archive and exit
+    -XshowSettings    show all settings and continue
+    -XshowSettings:all
+                      show all settings and continue
+    -XshowSettings:vm
+                      show all vm related settings and continue
+    -XshowSettings:system
+                      (Linux Only) show host system or container
+                      configuration and continue
+    -XshowSettings:properties
+                      show all property settings and continue
+    -XshowSettings:locale
+                      show all locale related settings and continue
+    -Xss<size>        set java thread stack size
+                      The actual size may be rounded up to a multiple of the
+                      system page size as required by the operating system.
+    -Xverify:none     do not verify classes (deprecated)
+
+These extra options are subject to change without notice.
+";
+
+/// Smallest `-Xmx` the VM starts with.
+const MIN_MAX_HEAP_SIZE: u64 = 2 * 1024 * 1024;
+/// Smallest nonzero `-Xms` the VM starts with.
+const MIN_INITIAL_HEAP_SIZE: u64 = 1024 * 1024;
+/// `-Xss` range HotSpot accepts on 64-bit Linux; zero means the default.
+const MIN_THREAD_STACK_SIZE: u64 = 136 * 1024;
+const MAX_THREAD_STACK_SIZE: u64 = 1024 * 1024 * 1024;
+/// Interpreted Java frames recurse on the native stack and take more room
+/// than HotSpot's, so the main thread never gets less than this.
+const MIN_NATIVE_STACK_SIZE: u64 = 2 * 1024 * 1024;
+
+/// The `--` forms of help and version print to standard output, the
+/// single-dash forms to standard error.
+fn print_to(text: &str, stdout: bool) {
+    if stdout {
+        print!("{}", text);
+    } else {
+        eprint!("{}", text);
+    }
+}
+
 fn print_usage() {
-    eprintln!("Usage: java [-version] [-cp <path>] <MainClass|path/to/Main.class>");
+    print_to(USAGE, false);
 }
 
-fn print_version() {
+/// `-version` says `openjdk version "17"`, `--version` says `openjdk 17`.
+fn version_text(mode: JitMode, long_form: bool) -> String {
     let java_version = JAVA_VERSION.trim();
     let aria_version = ARIA_VERSION.trim();
-    eprintln!("openjdk version \"{}\" aria", java_version);
-    eprintln!("AriaJDK Runtime Environment (build {})", aria_version);
-    eprintln!(
-        "AriaJDK 64-Bit Server VM (build {}, interpreted mode)",
-        aria_version
-    );
+    let product = if long_form {
+        format!("openjdk {} aria", java_version)
+    } else {
+        format!("openjdk version \"{}\" aria", java_version)
+    };
+    format!(
+        "{}\nAriaJDK Runtime Environment (build {})\nAriaJDK 64-Bit Server VM (build {}, {})\n",
+        product,
+        aria_version,
+        aria_version,
+        mode.describe()
+    )
+}
+
+/// Reports an option the VM rejected and returns the exit code.
+fn vm_creation_failed(message: &str) -> i32 {
+    eprintln!("{}", message);
+    eprintln!("Error: Could not create the Java Virtual Machine.");
+    eprintln!("Error: A fatal exception has occurred. Program will exit.");
+    1
+}
+
+/// Reports options that parsed but cannot start a VM together.
+fn vm_initialization_failed(message: &str) -> i32 {
+    eprintln!("Error occurred during initialization of VM\n{}", message);
+    1
+}
+
+fn default_jit_mode() -> JitMode {
+    if jit::is_supported() {
+        JitMode::Mixed
+    } else {
+        JitMode::Interpreted
+    }
 }
 
 fn classpath_separator() -> char {
@@ -41,70 +186,475 @@ fn classpath_separator() -> char {
     }
 }
 
+/// What the command line asked for, read up to the main class.
+struct LaunchOptions {
+    classpath: Vec<String>,
+    properties: Vec<(String, String)>,
+    target: Option<String>,
+    program_args: Vec<String>,
+    verify: bool,
+    jit_mode: JitMode,
+    print_compilation: bool,
+    share_mode: ShareMode,
+    archive_file: Option<PathBuf>,
+    source_version: Option<String>,
+    assertions: AssertionStatus,
+    max_heap_size: Option<u64>,
+    initial_heap_size: Option<u64>,
+    stack_size: Option<u64>,
+    /// `Some(to_stdout)` for `-version` and `--version`, which exit once
+    /// the VM is up.
+    version: Option<bool>,
+    /// `Some(to_stdout)` for `-showversion` and `--show-version`.
+    show_version: Option<bool>,
+    show_settings: Option<String>,
+    dry_run: bool,
+    /// `-Xrs`: leave SIGINT and SIGTERM to their default action, which
+    /// skips the shutdown hooks.
+    reduce_signals: bool,
+    /// `-agentlib:jdwp` or `-Xrunjdwp`.
+    jdwp: Option<AgentOptions>,
+}
+
+impl LaunchOptions {
+    fn new() -> Self {
+        Self {
+            classpath: vec![String::from(".")],
+            properties: Vec::new(),
+            target: None,
+            program_args: Vec::new(),
+            verify: true,
+            jit_mode: default_jit_mode(),
+            print_compilation: false,
+            share_mode: ShareMode::default(),
+            archive_file: None,
+            source_version: None,
+            assertions: AssertionStatus::default(),
+            max_heap_size: None,
+            initial_heap_size: None,
+            stack_size: None,
+            version: None,
+            show_version: None,
+            show_settings: None,
+            dry_run: false,
+            reduce_signals: false,
+            jdwp: None,
+        }
+    }
+
+    fn add_classpath(&mut self, path: &str) {
+        for entry in path.split(classpath_separator()) {
+            if !entry.is_empty() {
+                self.classpath.push(entry.to_string());
+            }
+        }
+    }
+
+    /// The heap size the VM runs with. Without `-Xmx`, an `-Xms` larger
+    /// than the default raises it.
+    fn heap_size(&self) -> u64 {
+        self.max_heap_size.unwrap_or_else(|| {
+            (DEFAULT_MAX_HEAP_SIZE as u64).max(self.initial_heap_size.unwrap_or(0))
+        })
+    }
+}
+
 pub fn run_cli(args: &[String]) -> i32 {
+    run_cli_with(args, None)
+}
+
+/// `run_cli` for launchers that can compile, which makes `java Foo.java`
+/// and `--source` scripts work.
+pub fn run_cli_with(args: &[String], compiler: Option<SourceCompiler>) -> i32 {
+    let args = match collect_args(args) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
     if args.is_empty() {
         print_usage();
         return 1;
     }
+    let options = match parse_options(&args) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
 
-    if args[0] == "-version" || args[0] == "--version" {
-        print_version();
-        return 0;
+    // Like `java`, run main on a thread of its own when `-Xss` sizes it.
+    let Some(stack_size) = options.stack_size else {
+        return launch(options, compiler);
+    };
+    let main_thread = std::thread::Builder::new()
+        .name("main".to_string())
+        .stack_size(stack_size.max(MIN_NATIVE_STACK_SIZE) as usize)
+        .spawn(move || launch(options, compiler));
+    match main_thread {
+        Ok(handle) => handle
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+        Err(e) => vm_initialization_failed(&format!("Unable to create the main thread: {}", e)),
     }
+}
 
-    if args[0] == "-h" || args[0] == "--help" || args[0] == "-help" {
-        print_usage();
-        return 0;
+/// The command line with `@argfiles` expanded, after the options from
+/// `JAVA_TOOL_OPTIONS` and `JDK_JAVA_OPTIONS`.
+fn collect_args(args: &[String]) -> Result<Vec<String>, i32> {
+    let mut collected = args::java_tool_options().map_err(|e| vm_creation_failed(&e))?;
+    let from_env = args::jdk_java_options().and_then(|env_args| {
+        let command_line = args::expand_argfiles(args)?;
+        Ok(env_args.into_iter().chain(command_line))
+    });
+    match from_env {
+        Ok(rest) => {
+            collected.extend(rest);
+            Ok(collected)
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            Err(1)
+        }
     }
+}
 
+/// Reads the options before the main class. Options that end the launch,
+/// such as `-version`, and rejected options give the exit code instead.
+fn parse_options(args: &[String]) -> Result<LaunchOptions, i32> {
+    let mut options = LaunchOptions::new();
     let mut idx = 0usize;
-    let mut classpath = vec![String::from(".")];
-    let mut target: Option<String> = None;
-
     while idx < args.len() {
         let arg = &args[idx];
         match arg.as_str() {
+            "--source" => {
+                idx += 1;
+                let Some(version) = args.get(idx) else {
+                    eprintln!("Missing value for option: --source");
+                    return Err(1);
+                };
+                options.source_version = Some(version.clone());
+            }
+            // A `#!/usr/bin/java --source 17` line arrives as one argument.
+            _ if arg.starts_with("--source ") || arg.starts_with("--source=") => {
+                options.source_version = Some(arg["--source ".len()..].trim().to_string());
+            }
             "-cp" | "-classpath" | "--class-path" => {
                 idx += 1;
-                if idx >= args.len() {
-                    eprintln!("Missing value for classpath option: {}", arg);
-                    return 1;
+                let Some(path) = args.get(idx) else {
+                    eprintln!("Error: {} requires class path specification", arg);
+                    return Err(1);
+                };
+                options.add_classpath(path);
+            }
+            _ if arg.starts_with("--class-path=") => {
+                options.add_classpath(&arg["--class-path=".len()..]);
+            }
+            "-Xverify:none" | "-noverify" => {
+                eprintln!(
+                    "AriaJDK 64-Bit Server VM warning: Options -Xverify:none and -noverify were deprecated in JDK 13 and will likely be removed in a future release."
+                );
+                options.verify = false;
+            }
+            "-Xverify:all" | "-Xverify:remote" => options.verify = true,
+            "-Xint" => options.jit_mode = JitMode::Interpreted,
+            "-Xrs" => options.reduce_signals = true,
+            "-Xmixed" => options.jit_mode = default_jit_mode(),
+            "-Xcomp" if jit::is_supported() => options.jit_mode = JitMode::Compiled,
+            "-Xcomp" => options.jit_mode = JitMode::Interpreted,
+            "-XX:+PrintCompilation" => options.print_compilation = true,
+            "-XX:-PrintCompilation" => options.print_compilation = false,
+            "-Xshare:off" => options.share_mode = ShareMode::Off,
+            "-Xshare:auto" => options.share_mode = ShareMode::Auto,
+            "-Xshare:on" => options.share_mode = ShareMode::On,
+            "-Xshare:dump" => options.share_mode = ShareMode::Dump,
+            _ if arg.starts_with("-XX:SharedArchiveFile=") => {
+                options.archive_file = Some(PathBuf::from(&arg["-XX:SharedArchiveFile=".len()..]));
+            }
+            _ if arg.starts_with("-Xmx") => {
+                options.max_heap_size = Some(args::parse_size(&arg[4..]).ok_or_else(|| {
+                    vm_creation_failed(&format!("Invalid maximum heap size: {}", arg))
+                })?);
+            }
+            _ if arg.starts_with("-Xms") => {
+                options.initial_heap_size = Some(args::parse_size(&arg[4..]).ok_or_else(|| {
+                    vm_creation_failed(&format!("Invalid initial heap size: {}", arg))
+                })?);
+            }
+            _ if arg.starts_with("-Xss") => {
+                let size = args::parse_size(&arg[4..]).ok_or_else(|| {
+                    vm_creation_failed(&format!("Invalid thread stack size: {}", arg))
+                })?;
+                if size > MAX_THREAD_STACK_SIZE {
+                    return Err(vm_creation_failed(&format!(
+                        "Invalid thread stack size: {}. The specified size exceeds the maximum representable size.",
+                        arg
+                    )));
                 }
-                for entry in args[idx].split(classpath_separator()) {
-                    if !entry.is_empty() {
-                        classpath.push(entry.to_string());
-                    }
+                if size != 0 && size < MIN_THREAD_STACK_SIZE {
+                    return Err(vm_creation_failed(&format!(
+                        "The Java thread stack size specified is too small. Specify at least {}k",
+                        MIN_THREAD_STACK_SIZE / 1024
+                    )));
                 }
+                options.stack_size = (size != 0).then_some(size);
+            }
+            "-XshowSettings" => options.show_settings = Some("all".to_string()),
+            _ if arg.starts_with("-XshowSettings:") => {
+                options.show_settings = Some(arg["-XshowSettings:".len()..].to_string());
+            }
+            "-showversion" => options.show_version = Some(false),
+            "--show-version" => options.show_version = Some(true),
+            "--dry-run" => options.dry_run = true,
+            _ if arg.starts_with("-agentlib:jdwp=") || arg.starts_with("-Xrunjdwp:") => {
+                let agent_options = arg
+                    .strip_prefix("-agentlib:jdwp=")
+                    .or_else(|| arg.strip_prefix("-Xrunjdwp:"))
+                    .unwrap_or_default();
+                options.jdwp = Some(AgentOptions::parse(arg, agent_options).map_err(|e| {
+                    eprintln!("{}", e);
+                    1
+                })?);
+            }
+            // Argfiles were expanded before parsing; nothing is left to do.
+            "--disable-@files" => {}
+            "-version" | "--version" => {
+                options.version = Some(arg == "--version");
+                break;
+            }
+            "-?" | "-h" | "-help" | "--help" => {
+                print_to(USAGE, arg == "--help");
+                return Err(0);
+            }
+            "-X" | "--help-extra" => {
+                print_to(EXTRA_USAGE, arg == "--help-extra");
+                return Err(0);
+            }
+            _ if arg.starts_with("-D") => {
+                let (name, value) = arg[2..].split_once('=').unwrap_or((&arg[2..], ""));
+                options
+                    .properties
+                    .push((name.to_string(), value.to_string()));
+            }
+            _ if options.assertions.apply_option(arg) => {}
+            _ if arg.starts_with("-XX:") => {
+                let name = arg[4..].trim_start_matches(['+', '-']);
+                return Err(vm_creation_failed(&format!(
+                    "Unrecognized VM option '{}'",
+                    name
+                )));
             }
             _ if arg.starts_with('-') => {
-                eprintln!("Unsupported option: {}", arg);
-                return 1;
+                return Err(vm_creation_failed(&format!("Unrecognized option: {}", arg)));
             }
             _ => {
-                target = Some(arg.clone());
+                options.target = Some(arg.clone());
+                options.program_args = args[idx + 1..].to_vec();
                 break;
             }
         }
         idx += 1;
     }
 
-    let target = match target {
-        Some(value) => value,
+    if options
+        .max_heap_size
+        .is_some_and(|size| size < MIN_MAX_HEAP_SIZE)
+    {
+        return Err(vm_initialization_failed("Too small maximum heap"));
+    }
+    if let Some(initial) = options.initial_heap_size {
+        if initial != 0 && initial < MIN_INITIAL_HEAP_SIZE {
+            return Err(vm_initialization_failed("Too small initial heap"));
+        }
+        if options.max_heap_size.is_some_and(|max| initial > max) {
+            return Err(vm_initialization_failed(
+                "Initial heap size set to a larger value than the maximum heap size",
+            ));
+        }
+    }
+    Ok(options)
+}
+
+/// Prints what `-XshowSettings` asked for to standard error. Unknown
+/// categories show everything, as `all` does.
+fn show_settings(category: &str, options: &LaunchOptions, interp: &Interpreter) {
+    let all = !matches!(category, "vm" | "properties" | "locale" | "system");
+    if all || category == "vm" {
+        eprintln!("VM settings:");
+        if let Some(initial) = options.initial_heap_size {
+            eprintln!("    Min. Heap Size: {}", args::format_size(initial));
+        }
+        match options.max_heap_size {
+            Some(max) => eprintln!("    Max. Heap Size: {}", args::format_size(max)),
+            None => eprintln!(
+                "    Max. Heap Size (Estimated): {}",
+                args::format_size(options.heap_size())
+            ),
+        }
+        if let Some(stack) = options.stack_size {
+            eprintln!("    Stack Size: {}", args::format_size(stack));
+        }
+        eprintln!("    Using VM: AriaJDK 64-Bit Server VM");
+        eprintln!();
+    }
+    if all || category == "properties" {
+        eprintln!("Property settings:");
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        for (name, value) in interp.properties() {
+            if name == "line.separator" {
+                let escaped = value.replace('\r', "\\r").replace('\n', "\\n");
+                eprintln!("    {} = {}", name, escaped);
+            } else if name.ends_with(".path") || name.ends_with(".dirs") {
+                let mut entries = value.split(separator);
+                eprintln!("    {} = {}", name, entries.next().unwrap_or(""));
+                for entry in entries {
+                    eprintln!("        {}", entry);
+                }
+            } else {
+                eprintln!("    {} = {}", name, value);
+            }
+        }
+        eprintln!();
+    }
+    if all || category == "locale" {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map(|value| value.split(['.', '@']).next().unwrap_or("").to_string())
+            .filter(|tag| !tag.is_empty() && tag != "C" && tag != "POSIX")
+            .unwrap_or_else(|| "en_US".to_string());
+        eprintln!("Locale settings:");
+        eprintln!("    default locale = {}", locale);
+        eprintln!("    default display locale = {}", locale);
+        eprintln!("    default format locale = {}", locale);
+        eprintln!();
+    }
+    if cfg!(target_os = "linux") && (all || category == "system") {
+        eprintln!("Operating System Metrics:");
+        eprintln!("    No metrics available for this platform");
+        eprintln!();
+    }
+}
+
+fn launch(options: LaunchOptions, compiler: Option<SourceCompiler>) -> i32 {
+    let mut loader = ClassLoader::new();
+    loader.set_verify(options.verify);
+    for entry in &options.classpath {
+        loader.add_classpath(entry);
+    }
+    let archive_file = options
+        .archive_file
+        .clone()
+        .or_else(|| shared_archive::default_path(loader.search_paths()));
+
+    let interp = Interpreter::new(true);
+    interp.set_jit_mode(options.jit_mode);
+    interp.set_print_compilation(options.print_compilation);
+    interp.set_assertion_status(options.assertions.clone());
+    for (name, value) in &options.properties {
+        interp.set_property(name, value);
+    }
+    if let Some(jdwp) = &options.jdwp {
+        match Agent::open(jdwp) {
+            Ok(agent) => interp.attach_debugger(agent),
+            Err(message) => {
+                eprintln!("{}", message);
+                return 2;
+            }
+        }
+    }
+    if let Some(category) = &options.show_settings {
+        show_settings(category, &options, &interp);
+    }
+    if let Some(to_stdout) = options.version.or(options.show_version) {
+        print_to(&version_text(options.jit_mode, to_stdout), to_stdout);
+    }
+    if options.version.is_some() {
+        return 0;
+    }
+
+    if options.share_mode == ShareMode::Dump {
+        // Like `java`, only walk the working directory without `-cp`.
+        let roots = match options.classpath.get(1..) {
+            Some(entries) if !entries.is_empty() => entries.iter().map(PathBuf::from).collect(),
+            _ => vec![PathBuf::from(".")],
+        };
+        let Some(archive_file) = archive_file else {
+            return vm_initialization_failed(
+                "No per-user directory for the shared archive file; use -XX:SharedArchiveFile",
+            );
+        };
+        return match shared_archive::dump(&mut loader, &roots, &archive_file) {
+            Ok(count) => {
+                println!(
+                    "Dumped {} classes to shared archive {}",
+                    count,
+                    archive_file.display()
+                );
+                0
+            }
+            Err(e) => vm_initialization_failed(&e.to_string()),
+        };
+    }
+
+    let target = match &options.target {
+        Some(value) => value.clone(),
         None => {
             print_usage();
             return 1;
         }
     };
 
+    if options.share_mode != ShareMode::Off {
+        let archive = archive_file
+            .ok_or_else(|| {
+                ArchiveError::Io("No per-user directory for the shared archive file".to_string())
+            })
+            .and_then(|path| SharedArchive::open(&path, loader.search_paths()));
+        match archive {
+            Ok(archive) => loader.set_shared_archive(archive),
+            Err(e) if options.share_mode == ShareMode::On => {
+                eprintln!(
+                    "An error has occurred while processing the shared archive file.\n{}\nError occurred during initialization of VM\nUnable to use shared archive.",
+                    e
+                );
+                return 1;
+            }
+            Err(_) => {}
+        }
+    }
+
     print_banner();
 
-    let mut loader = ClassLoader::new();
-    for entry in classpath {
-        loader.add_classpath(entry);
+    let mut heap = Heap::with_max_size(usize::try_from(options.heap_size()).unwrap_or(usize::MAX));
+    interp.start_debugger(&mut loader, &mut heap);
+
+    let source_mode = options.source_version.is_some() || source_launcher::is_source_file(&target);
+    if let Some(version) = &options.source_version {
+        if version.parse::<u32>().is_err() {
+            eprintln!("error: invalid value for --source option: {}", version);
+            return 1;
+        }
     }
 
     println!("Loading class: {}", target);
-    let class_file = if Path::new(&target).exists() {
+    let class_file = if source_mode {
+        let main_class = match compile_source_target(&mut loader, &target, compiler) {
+            Ok(main_class) => main_class,
+            Err(message) => {
+                eprintln!("{}", message);
+                return 1;
+            }
+        };
+        let class_file = loader.load_class(&main_class);
+        if let Ok(class) = &class_file {
+            if !has_main_method(class) {
+                eprintln!(
+                    "error: can't find main(String[]) method in class: {}",
+                    main_class.replace('/', ".")
+                );
+                return 1;
+            }
+        }
+        class_file
+    } else if Path::new(&target).exists() {
         loader.load_class_from_file(&target)
     } else {
         loader.load_class(&target)
@@ -112,14 +662,49 @@ pub fn run_cli(args: &[String]) -> i32 {
 
     let class_file = match class_file {
         Ok(c) => c,
+        Err(LoadError::Verify(e)) => {
+            eprintln!(
+                "Error: Unable to initialize main class {}\nCaused by: java.lang.VerifyError: {}",
+                target.replace('/', "."),
+                e
+            );
+            return 1;
+        }
+        Err(LoadError::NotFound(_)) => {
+            let name = target.replace('/', ".");
+            eprintln!(
+                "Error: Could not find or load main class {}\nCaused by: java.lang.ClassNotFoundException: {}",
+                name, name
+            );
+            return 1;
+        }
+        Err(LoadError::Format(e)) => {
+            eprintln!(
+                "Error: LinkageError occurred while loading main class {}\n\tjava.lang.ClassFormatError: {}",
+                target.replace('/', "."),
+                e
+            );
+            return 1;
+        }
         Err(e) => {
             eprintln!("Failed to load class: {e}");
             return 1;
         }
     };
+    if options.dry_run {
+        return 0;
+    }
+    if !options.reduce_signals {
+        signals::install_handlers();
+    }
 
-    let interp = Interpreter::new(true);
-    let mut heap = Heap::new();
+    let mut main_args = heap.alloc_reference_array(options.program_args.len(), "java/lang/String");
+    for (slot, value) in options.program_args.iter().enumerate() {
+        main_args.content[slot] = heap.alloc_string(value);
+    }
+    if let Some(real) = heap.get_array_mut(main_args.id) {
+        real.content = main_args.content.clone();
+    }
 
     println!("Executing main() ...");
     let result = interp.execute_method(
@@ -128,13 +713,57 @@ pub fn run_cli(args: &[String]) -> i32 {
         "main",
         "([Ljava/lang/String;)V",
         &mut heap,
-        &[],
+        &[HeapValue::Array(main_args)],
     );
 
+    if let Some(exception) = interp.take_pending_exception() {
+        crate::native::java_io_printstream::flush_all();
+        let mut env = crate::native::NativeEnv {
+            interpreter: &interp,
+            loader: &mut loader,
+            heap: &mut heap,
+        };
+        let trace = crate::native::java_lang_throwable::describe(&mut env, &exception);
+        eprintln!("Exception in thread \"main\" {}", trace);
+        interp.run_shutdown_hooks(&mut loader, &mut heap);
+        crate::native::java_io_printstream::flush_all();
+        return 1;
+    }
+
     match result {
         Some(v) => println!("Execution finished, return: {:?}", v),
         None => println!("Execution finished (void return)"),
     }
+    interp.run_shutdown_hooks(&mut loader, &mut heap);
+    crate::native::java_io_printstream::flush_all();
 
     0
 }
+
+/// Compiles a launched source file into `loader` and returns the internal
+/// name of the class to run.
+fn compile_source_target(
+    loader: &mut ClassLoader,
+    target: &str,
+    compiler: Option<SourceCompiler>,
+) -> Result<String, String> {
+    let path = Path::new(target);
+    let source = source_launcher::read_source(path)?;
+    let compile = compiler.ok_or_else(|| {
+        "error: launching source files is not supported by this runtime".to_string()
+    })?;
+    let program = compile(path, &source)
+        .map_err(|diagnostics| format!("{}\nerror: compilation failed", diagnostics.trim_end()))?;
+    for (name, bytes) in program.classes {
+        loader.add_class_bytes(&name, bytes);
+    }
+    Ok(program.main_class)
+}
+
+fn has_main_method(class: &ClassFile) -> bool {
+    class.methods.iter().any(|method| {
+        method.access_flags & 0x0008 != 0
+            && class.get_utf8(method.name_index) == Some("main")
+            && class.get_utf8(method.descriptor_index) == Some("([Ljava/lang/String;)V")
+    })
+}
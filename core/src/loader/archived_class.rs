@@ -0,0 +1,459 @@
+//! The form classes take in a shared archive: the `ClassFile` the parser
+//! produced, written out field by field. Reading one back rebuilds the
+//! structure directly, with none of the class file decoding parsing does,
+//! and then makes the same checks between its parts.
+//!
+//! Enum variants are written as a one-byte tag followed by their fields;
+//! a tag is never reused, so a change here needs a new archive format
+//! version.
+
+use super::shared_archive::{put_str, put_u32, Cursor};
+use crate::bytecode::attributes::{
+    Annotation, Attribute, BootstrapMethod, ElementValue, InnerClass, LineNumber, LocalVariable,
+    MethodParameter, Module, ModulePackage, ModuleProvides, ModuleRequires, RecordComponent,
+    StackMapFrame, TypeAnnotation, TypeAnnotationTarget, VerificationType,
+};
+use crate::bytecode::parser::{
+    ClassFile, CodeAttribute, ConstantPoolEntry, ExceptionTableEntry, FieldInfo, MethodInfo,
+};
+
+/// Writes a parsed class in archive form.
+pub(super) fn write(class: &ClassFile, out: &mut Vec<u8>) {
+    class.put(out);
+}
+
+/// Rebuilds a class written by `write`; `None` if `bytes` is not one.
+pub(super) fn read(bytes: &[u8]) -> Option<ClassFile> {
+    let mut cursor = Cursor { bytes, position: 0 };
+    let class = ClassFile::get(&mut cursor)?;
+    if cursor.position != bytes.len() {
+        return None;
+    }
+    class.check().ok()?;
+    Some(class)
+}
+
+trait Archived: Sized {
+    fn put(&self, out: &mut Vec<u8>);
+    fn get(cursor: &mut Cursor) -> Option<Self>;
+}
+
+impl Archived for u8 {
+    fn put(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        cursor.u8()
+    }
+}
+
+impl Archived for u16 {
+    fn put(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        Some(u16::from_le_bytes(cursor.take(2)?.try_into().ok()?))
+    }
+}
+
+impl Archived for u32 {
+    fn put(&self, out: &mut Vec<u8>) {
+        put_u32(out, *self);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        cursor.u32()
+    }
+}
+
+impl Archived for i32 {
+    fn put(&self, out: &mut Vec<u8>) {
+        (*self as u32).put(out);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        Some(cursor.u32()? as i32)
+    }
+}
+
+impl Archived for i64 {
+    fn put(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        Some(cursor.u64()? as i64)
+    }
+}
+
+// Floats keep their exact bits, NaN payloads included.
+impl Archived for f32 {
+    fn put(&self, out: &mut Vec<u8>) {
+        self.to_bits().put(out);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        Some(f32::from_bits(cursor.u32()?))
+    }
+}
+
+impl Archived for f64 {
+    fn put(&self, out: &mut Vec<u8>) {
+        (self.to_bits() as i64).put(out);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        Some(f64::from_bits(cursor.u64()?))
+    }
+}
+
+impl Archived for String {
+    fn put(&self, out: &mut Vec<u8>) {
+        put_str(out, self);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        cursor.str().map(str::to_string)
+    }
+}
+
+impl<T: Archived> Archived for Vec<T> {
+    fn put(&self, out: &mut Vec<u8>) {
+        put_u32(out, self.len() as u32);
+        for item in self {
+            item.put(out);
+        }
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        let len = cursor.u32()? as usize;
+        // Every item takes at least a byte, which bounds what a corrupt
+        // length can make us reserve.
+        let mut items = Vec::with_capacity(len.min(cursor.remaining()));
+        for _ in 0..len {
+            items.push(T::get(cursor)?);
+        }
+        Some(items)
+    }
+}
+
+impl<T: Archived> Archived for Option<T> {
+    fn put(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.put(out);
+            }
+        }
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        match cursor.u8()? {
+            0 => Some(None),
+            1 => Some(Some(T::get(cursor)?)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Archived> Archived for Box<T> {
+    fn put(&self, out: &mut Vec<u8>) {
+        (**self).put(out);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        T::get(cursor).map(Box::new)
+    }
+}
+
+impl<A: Archived, B: Archived> Archived for (A, B) {
+    fn put(&self, out: &mut Vec<u8>) {
+        self.0.put(out);
+        self.1.put(out);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        Some((A::get(cursor)?, B::get(cursor)?))
+    }
+}
+
+impl<A: Archived, B: Archived, C: Archived> Archived for (A, B, C) {
+    fn put(&self, out: &mut Vec<u8>) {
+        self.0.put(out);
+        self.1.put(out);
+        self.2.put(out);
+    }
+
+    fn get(cursor: &mut Cursor) -> Option<Self> {
+        Some((A::get(cursor)?, B::get(cursor)?, C::get(cursor)?))
+    }
+}
+
+/// Writes a struct as its fields in the order given.
+macro_rules! archived_struct {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl Archived for $name {
+            fn put(&self, out: &mut Vec<u8>) {
+                $(self.$field.put(out);)*
+            }
+
+            fn get(cursor: &mut Cursor) -> Option<Self> {
+                Some($name {
+                    $($field: Archived::get(cursor)?,)*
+                })
+            }
+        }
+    };
+}
+
+/// Writes an enum as the variant's tag followed by its fields.
+macro_rules! archived_enum {
+    ($name:ident {
+        $($tag:literal => $variant:ident
+            $(( $($value:ident),* ))?
+            $({ $($field:ident),* })?),* $(,)?
+    }) => {
+        impl Archived for $name {
+            fn put(&self, out: &mut Vec<u8>) {
+                match self {
+                    $($name::$variant $(( $($value),* ))? $({ $($field),* })? => {
+                        out.push($tag);
+                        $($($value.put(out);)*)?
+                        $($($field.put(out);)*)?
+                    })*
+                }
+            }
+
+            fn get(cursor: &mut Cursor) -> Option<Self> {
+                Some(match cursor.u8()? {
+                    $($tag => $name::$variant
+                        $(( $({
+                            let $value = Archived::get(cursor)?;
+                            $value
+                        }),* ))?
+                        $({ $($field: Archived::get(cursor)?),* })?,)*
+                    _ => return None,
+                })
+            }
+        }
+    };
+}
+
+archived_struct!(ClassFile {
+    magic,
+    minor_version,
+    major_version,
+    constant_pool_count,
+    constant_pool,
+    access_flags,
+    this_class,
+    super_class,
+    interfaces,
+    fields,
+    methods,
+    attributes,
+});
+
+archived_enum!(ConstantPoolEntry {
+    0 => Utf8(text),
+    1 => Integer(value),
+    2 => Float(value),
+    3 => Long(value),
+    4 => Double(value),
+    5 => Class { name_index },
+    6 => String { string_index },
+    7 => FieldRef { class_index, name_and_type_index },
+    8 => MethodRef { class_index, name_and_type_index },
+    9 => InterfaceMethodRef { class_index, name_and_type_index },
+    10 => NameAndType { name_index, descriptor_index },
+    11 => MethodHandle { reference_kind, reference_index },
+    12 => MethodType { descriptor_index },
+    13 => Dynamic { bootstrap_method_attr_index, name_and_type_index },
+    14 => InvokeDynamic { bootstrap_method_attr_index, name_and_type_index },
+    15 => Module { name_index },
+    16 => Package { name_index },
+    17 => Unusable,
+});
+
+archived_struct!(FieldInfo {
+    access_flags,
+    name_index,
+    descriptor_index,
+    attributes,
+});
+
+archived_struct!(MethodInfo {
+    access_flags,
+    name_index,
+    descriptor_index,
+    code,
+    attributes,
+});
+
+archived_struct!(CodeAttribute {
+    max_stack,
+    max_locals,
+    code,
+    exception_table,
+    attributes,
+});
+
+archived_struct!(ExceptionTableEntry {
+    start_pc,
+    end_pc,
+    handler_pc,
+    catch_type,
+});
+
+archived_enum!(Attribute {
+    0 => ConstantValue(index),
+    1 => StackMapTable(frames),
+    2 => Exceptions(classes),
+    3 => InnerClasses(classes),
+    4 => EnclosingMethod { class_index, method_index },
+    5 => Synthetic,
+    6 => Signature(index),
+    7 => SourceFile(index),
+    8 => SourceDebugExtension(bytes),
+    9 => LineNumberTable(lines),
+    10 => LocalVariableTable(variables),
+    11 => LocalVariableTypeTable(variables),
+    12 => Deprecated,
+    13 => RuntimeVisibleAnnotations(annotations),
+    14 => RuntimeInvisibleAnnotations(annotations),
+    15 => RuntimeVisibleParameterAnnotations(annotations),
+    16 => RuntimeInvisibleParameterAnnotations(annotations),
+    17 => RuntimeVisibleTypeAnnotations(annotations),
+    18 => RuntimeInvisibleTypeAnnotations(annotations),
+    19 => AnnotationDefault(value),
+    20 => BootstrapMethods(methods),
+    21 => MethodParameters(parameters),
+    22 => Module(module),
+    23 => ModulePackages(packages),
+    24 => ModuleMainClass(index),
+    25 => NestHost(index),
+    26 => NestMembers(classes),
+    27 => Record(components),
+    28 => PermittedSubclasses(classes),
+    29 => Unknown { name_index, info },
+});
+
+archived_enum!(StackMapFrame {
+    0 => Same { offset_delta },
+    1 => SameLocals1StackItem { offset_delta, stack },
+    2 => Chop { offset_delta, chopped },
+    3 => Append { offset_delta, locals },
+    4 => Full { offset_delta, locals, stack },
+});
+
+archived_enum!(VerificationType {
+    0 => Top,
+    1 => Integer,
+    2 => Float,
+    3 => Double,
+    4 => Long,
+    5 => Null,
+    6 => UninitializedThis,
+    7 => Object(index),
+    8 => Uninitialized(offset),
+});
+
+archived_struct!(InnerClass {
+    inner_class_info_index,
+    outer_class_info_index,
+    inner_name_index,
+    inner_class_access_flags,
+});
+
+archived_struct!(LineNumber {
+    start_pc,
+    line_number,
+});
+
+archived_struct!(LocalVariable {
+    start_pc,
+    length,
+    name_index,
+    descriptor_index,
+    index,
+});
+
+archived_struct!(Annotation {
+    type_index,
+    elements,
+});
+
+archived_enum!(ElementValue {
+    0 => Const { tag, index },
+    1 => Enum { type_name_index, const_name_index },
+    2 => Class(index),
+    3 => Annotation(annotation),
+    4 => Array(values),
+});
+
+archived_struct!(TypeAnnotation {
+    target_type,
+    target,
+    type_path,
+    annotation,
+});
+
+archived_enum!(TypeAnnotationTarget {
+    0 => TypeParameter { index },
+    1 => Supertype { index },
+    2 => TypeParameterBound { type_parameter_index, bound_index },
+    3 => Empty,
+    4 => FormalParameter { index },
+    5 => Throws { type_index },
+    6 => LocalVar(ranges),
+    7 => Catch { exception_table_index },
+    8 => Offset { offset },
+    9 => TypeArgument { offset, type_argument_index },
+});
+
+archived_struct!(BootstrapMethod {
+    method_ref,
+    arguments,
+});
+
+archived_struct!(MethodParameter {
+    name_index,
+    access_flags,
+});
+
+archived_struct!(Module {
+    name_index,
+    flags,
+    version_index,
+    requires,
+    exports,
+    opens,
+    uses,
+    provides,
+});
+
+archived_struct!(ModuleRequires {
+    module_index,
+    flags,
+    version_index,
+});
+
+archived_struct!(ModulePackage {
+    package_index,
+    flags,
+    to,
+});
+
+archived_struct!(ModuleProvides {
+    service_index,
+    with,
+});
+
+archived_struct!(RecordComponent {
+    name_index,
+    descriptor_index,
+    attributes,
+});
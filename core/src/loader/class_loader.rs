@@ -1,19 +1,133 @@
+use crate::bytecode::error::ClassFormatError;
 use crate::bytecode::parser::*;
+use crate::loader::shared_archive::SharedArchive;
+use crate::native::{self, java_lang_throwable};
 use crate::runtime::heap::HeapValue;
-use std::collections::HashMap;
+use crate::verifier::{self, ClassHierarchy, ClassKind, VerifyError};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
 
+const ACC_INTERFACE: u16 = 0x0200;
+
+/// Identifies a class loader. The built-in loaders have fixed ids and
+/// user-defined loaders are numbered after them as they register.
+pub type LoaderId = usize;
+
+/// Defines the classes the VM provides.
+pub const BOOT_LOADER: LoaderId = 0;
+/// Delegates to the boot loader and defines nothing itself.
+pub const PLATFORM_LOADER: LoaderId = 1;
+/// Defines the classes of the search path.
+pub const APP_LOADER: LoaderId = 2;
+const FIRST_USER_LOADER: LoaderId = 3;
+
+/// Whether `loader` is one of the built-in loaders, whose classes run
+/// under their own names and link without going through a loader object.
+pub fn is_builtin_loader(loader: LoaderId) -> bool {
+    loader < FIRST_USER_LOADER
+}
+
+/// The name a class defined by user-defined loader `loader` runs under,
+/// so that same-named classes of two loaders never share statics or a
+/// mirror. `[` cannot occur inside a class name, so no class of the
+/// built-in loaders can take it.
+fn runtime_name(loader: LoaderId, class_name: &str) -> String {
+    format!("{}[{}", class_name, loader)
+}
+
+/// Where a class defined by a user-defined loader links to: its loader
+/// and the runtime names its supertypes resolved to through it.
+#[derive(Debug, Clone)]
+pub struct Linkage {
+    pub loader: LoaderId,
+    pub superclass: Option<String>,
+    pub interfaces: Vec<String>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum ClassInitState {
     Initializing,
     Initialized,
 }
 
+/// Why a class could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// Nothing on the search path has this name.
+    NotFound(String),
+    Format(ClassFormatError),
+    Verify(VerifyError),
+    /// Bytes defined under one name declare another class.
+    WrongName {
+        expected: String,
+        found: String,
+    },
+    /// A class of this name has already been defined.
+    Duplicate(String),
+    /// Two loaders that must agree on a class name resolve it to
+    /// different classes.
+    Constraint(String),
+    /// A redefinition would change more than method bodies and constants.
+    Redefinition(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotFound(name) => write!(f, "Class not found: {}", name),
+            LoadError::Format(e) => write!(f, "Parse error: {}", e),
+            LoadError::Verify(e) => write!(f, "{}", e),
+            LoadError::WrongName { expected, found } => {
+                write!(f, "{} (wrong name: {})", expected, found)
+            }
+            LoadError::Duplicate(name) => {
+                write!(f, "attempted duplicate class definition for {}", name)
+            }
+            LoadError::Constraint(name) => write!(
+                f,
+                "loader constraint violation: loaders have different Class objects for the type {}",
+                name
+            ),
+            LoadError::Redefinition(reason) => {
+                write!(f, "class redefinition failed: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
 pub struct ClassLoader {
     search_paths: Vec<PathBuf>,
     pub loaded_classes: HashMap<String, ClassFile>,
     static_fields: HashMap<String, HeapValue>,
     class_init_state: HashMap<String, ClassInitState>,
+    verify: bool,
+    /// Classes that passed verification, or are being verified.
+    verified: HashSet<String>,
+    /// Classes that failed, so every later use fails the same way.
+    verify_errors: HashMap<String, VerifyError>,
+    /// Consulted before the search path, as `-Xshare:auto` arranges.
+    shared_archive: Option<SharedArchive>,
+    /// Class files defined from memory, found after the search path.
+    memory_classes: HashMap<String, Vec<u8>>,
+    /// Objects of the user-defined class loaders, from the first user
+    /// loader id on.
+    user_loaders: Vec<HeapValue>,
+    /// The runtime name each user-defined loader resolved a name to.
+    namespaces: HashMap<(LoaderId, String), String>,
+    /// Classes defined by user-defined loaders, by runtime name.
+    linkages: HashMap<String, Linkage>,
+    /// Names that two loaders must resolve to the same class.
+    constraints: Vec<(String, LoaderId, LoaderId)>,
+}
+
+impl Default for ClassLoader {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ClassLoader {
@@ -23,66 +137,403 @@ impl ClassLoader {
             loaded_classes: HashMap::new(),
             static_fields: HashMap::new(),
             class_init_state: HashMap::new(),
+            verify: true,
+            verified: HashSet::new(),
+            verify_errors: HashMap::new(),
+            shared_archive: None,
+            memory_classes: HashMap::new(),
+            user_loaders: Vec::new(),
+            namespaces: HashMap::new(),
+            linkages: HashMap::new(),
+            constraints: Vec::new(),
         }
     }
 
-    pub fn load_class_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<ClassFile, String> {
+    /// Turns bytecode verification off, as `-Xverify:none` does.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    pub fn verifies(&self) -> bool {
+        self.verify
+    }
+
+    /// Takes classes from `archive` ahead of the search path. The archive
+    /// must have been opened for this loader's search path.
+    pub fn set_shared_archive(&mut self, archive: SharedArchive) {
+        self.shared_archive = Some(archive);
+    }
+
+    pub fn shared_archive(&self) -> Option<&SharedArchive> {
+        self.shared_archive.as_ref()
+    }
+
+    pub fn load_class_from_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<ClassFile, LoadError> {
         let path_ref = path.as_ref();
         if !path_ref.exists() {
-            return Err(format!("Class file not found: {}", path_ref.display()));
+            return Err(LoadError::NotFound(path_ref.display().to_string()));
         }
 
         let path_str = path_ref.to_string_lossy().to_string();
-        match ClassFile::parse(&path_str) {
-            Ok(class) => {
-                if let Some(name) = class.get_class_name(class.this_class) {
-                    self.init_static_fields_for_class(name, &class);
-                    self.loaded_classes.insert(name.to_string(), class.clone());
-                }
-                Ok(class)
-            }
-            Err(e) => Err(format!("Failed to parse class file: {}", e)),
-        }
+        let class = ClassFile::parse(&path_str).map_err(LoadError::Format)?;
+        let Some(name) = class.get_class_name(class.this_class) else {
+            return Ok(class);
+        };
+        let name = name.to_string();
+        self.init_static_fields_for_class(&name, &class);
+        self.loaded_classes.insert(name.clone(), class.clone());
+        self.link_class(&name, &class)?;
+        Ok(class)
     }
 
     pub fn add_classpath<P: AsRef<Path>>(&mut self, path: P) {
         self.search_paths.push(path.as_ref().to_path_buf());
     }
 
-    pub fn load_class(&mut self, class_name: &str) -> Result<ClassFile, String> {
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// Makes a class file held in memory, such as one compiled from a
+    /// launched source file, loadable under its internal name. Classes on
+    /// the search path take precedence, as with a child class loader.
+    pub fn add_class_bytes(&mut self, class_name: &str, bytes: Vec<u8>) {
+        self.memory_classes.insert(class_name.to_string(), bytes);
+    }
+
+    /// Defines a class from the bytes of its class file, as
+    /// `ClassLoader.defineClass` does, and verifies it. `name`, when given,
+    /// is the binary or internal name the bytes must declare.
+    pub fn define_class_bytes(
+        &mut self,
+        name: Option<&str>,
+        bytes: &[u8],
+    ) -> Result<ClassFile, LoadError> {
+        let class_file = Self::parse_class_bytes(name, bytes)?;
+        let internal_name = class_file
+            .get_class_name(class_file.this_class)
+            .unwrap_or_default()
+            .to_string();
+        if self.loaded_classes.contains_key(&internal_name)
+            || native::is_builtin_class(&internal_name)
+        {
+            return Err(LoadError::Duplicate(internal_name.replace('/', ".")));
+        }
+        println!("Loading class: {} source: memory", internal_name);
+        self.init_static_fields_for_class(&internal_name, &class_file);
+        if let Some(super_name) = class_file.get_class_name(class_file.super_class) {
+            if super_name != "java/lang/Object" {
+                let _ = self.define_class(super_name);
+            }
+        }
+        self.loaded_classes
+            .insert(internal_name.clone(), class_file.clone());
+        self.link_class(&internal_name, &class_file)?;
+        Ok(class_file)
+    }
+
+    /// Parses class file bytes that must declare `name`, when given.
+    pub fn parse_class_bytes(name: Option<&str>, bytes: &[u8]) -> Result<ClassFile, LoadError> {
+        let class_file = ClassFile::from_bytes(bytes).map_err(LoadError::Format)?;
+        let internal_name = class_file
+            .get_class_name(class_file.this_class)
+            .unwrap_or_default();
+        if let Some(expected) = name {
+            if expected.replace('.', "/") != internal_name {
+                return Err(LoadError::WrongName {
+                    expected: expected.replace('/', "."),
+                    found: internal_name.to_string(),
+                });
+            }
+        }
+        Ok(class_file)
+    }
+
+    /// Defines a parsed class in the namespace of user-defined loader
+    /// `linkage.loader`, whose supertypes have already been resolved
+    /// through it, and verifies it. Returns the runtime name.
+    pub fn define_user_class(
+        &mut self,
+        class_file: ClassFile,
+        linkage: Linkage,
+    ) -> Result<String, LoadError> {
+        let loader = linkage.loader;
+        let class_name = class_file
+            .get_class_name(class_file.this_class)
+            .unwrap_or_default()
+            .to_string();
+        if self.namespaces.contains_key(&(loader, class_name.clone())) {
+            return Err(LoadError::Duplicate(class_name.replace('/', ".")));
+        }
+        let runtime = runtime_name(loader, &class_name);
+        self.record_loaded(loader, &class_name, &runtime)?;
+        self.init_static_fields_for_class(&runtime, &class_file);
+        self.loaded_classes
+            .insert(runtime.clone(), class_file.clone());
+        self.linkages.insert(runtime.clone(), linkage);
+        self.link_class(&runtime, &class_file)?;
+        Ok(runtime)
+    }
+
+    /// Replaces the methods and constant pool of loaded class `class_name`,
+    /// a runtime name, with those of `bytes`, as JVMTI `RedefineClasses`
+    /// does. Statics and initialization state carry over; a class that
+    /// changes its supertypes, fields or set of methods is refused.
+    pub fn redefine_class(
+        &mut self,
+        class_name: &str,
+        bytes: &[u8],
+    ) -> Result<ClassFile, LoadError> {
+        let symbolic = self.symbolic_name(class_name).into_owned();
+        let Some(old) = self.loaded_classes.get(class_name) else {
+            return Err(LoadError::NotFound(symbolic.replace('/', ".")));
+        };
+        let class_file = Self::parse_class_bytes(Some(&symbolic), bytes)?;
+        if let Some(reason) = schema_change(old, &class_file) {
+            return Err(LoadError::Redefinition(reason.to_string()));
+        }
+        if self.verify {
+            self.verify_class(class_name, &class_file)
+                .map_err(LoadError::Verify)?;
+        }
+        let dotted = symbolic.replace('/', ".");
+        for key in [class_name, &dotted] {
+            if let Some(entry) = self.loaded_classes.get_mut(key) {
+                *entry = class_file.clone();
+            }
+        }
+        Ok(class_file)
+    }
+
+    /// Gives a user-defined loader object its loader id.
+    pub fn register_loader(&mut self, object: HeapValue) -> LoaderId {
+        self.user_loaders.push(object);
+        FIRST_USER_LOADER + self.user_loaders.len() - 1
+    }
+
+    pub fn loader_object(&self, loader: LoaderId) -> Option<&HeapValue> {
+        self.user_loaders
+            .get(loader.checked_sub(FIRST_USER_LOADER)?)
+    }
+
+    /// The user-defined loader objects, for the collector's roots.
+    pub(crate) fn loader_objects(&self) -> impl Iterator<Item = &HeapValue> {
+        self.user_loaders.iter()
+    }
+
+    pub fn linkage(&self, runtime_name: &str) -> Option<&Linkage> {
+        self.linkages.get(runtime_name)
+    }
+
+    /// The loader that defined a class, by runtime name. Array classes
+    /// belong to the loader of their element class.
+    pub fn defining_loader(&self, runtime_name: &str) -> LoaderId {
+        let element = runtime_name.trim_start_matches('[');
+        let element = match element.strip_prefix('L') {
+            Some(rest) if element.len() < runtime_name.len() => {
+                rest.strip_suffix(';').unwrap_or(rest)
+            }
+            _ if element.len() < runtime_name.len() => return BOOT_LOADER,
+            _ => element,
+        };
+        match self.linkages.get(element) {
+            Some(linkage) => linkage.loader,
+            None if native::is_builtin_class(element) => BOOT_LOADER,
+            None => APP_LOADER,
+        }
+    }
+
+    /// The name a class was defined under, by runtime name: `Plugin` for
+    /// the runtime name of a `Plugin` a user-defined loader defined, and
+    /// `[LPlugin;` for an array of those.
+    pub fn symbolic_name<'a>(&self, runtime_name: &'a str) -> Cow<'a, str> {
+        let element = runtime_name.trim_start_matches('[');
+        let dimensions = runtime_name.len() - element.len();
+        let class_name = match element.strip_prefix('L') {
+            Some(rest) if dimensions > 0 => rest.strip_suffix(';').unwrap_or(rest),
+            _ => element,
+        };
+        let Some(class_file) = self
+            .linkages
+            .contains_key(class_name)
+            .then(|| self.loaded_classes.get(class_name))
+            .flatten()
+        else {
+            return Cow::Borrowed(runtime_name);
+        };
+        let symbolic = class_file
+            .get_class_name(class_file.this_class)
+            .unwrap_or(class_name);
+        match dimensions {
+            0 => Cow::Owned(symbolic.to_string()),
+            _ => Cow::Owned(format!("{}L{};", &runtime_name[..dimensions], symbolic)),
+        }
+    }
+
+    /// The runtime name `class_name` already resolved to through `loader`.
+    pub fn find_loaded(&self, loader: LoaderId, class_name: &str) -> Option<String> {
+        let builtin = native::is_builtin_class(class_name);
+        match loader {
+            BOOT_LOADER | PLATFORM_LOADER => return builtin.then(|| class_name.to_string()),
+            APP_LOADER => {
+                let defined = self.loaded_classes.contains_key(class_name)
+                    && !self.linkages.contains_key(class_name);
+                return (builtin || defined).then(|| class_name.to_string());
+            }
+            _ => {}
+        }
+        self.namespaces
+            .get(&(loader, class_name.to_string()))
+            .cloned()
+    }
+
+    /// Records that `loader` resolved `class_name` to the class `runtime`,
+    /// unless that breaks a loading constraint.
+    pub fn record_loaded(
+        &mut self,
+        loader: LoaderId,
+        class_name: &str,
+        runtime: &str,
+    ) -> Result<(), LoadError> {
+        for (name, first, second) in &self.constraints {
+            let other = match (*first == loader, *second == loader) {
+                (true, _) => *second,
+                (_, true) => *first,
+                _ => continue,
+            };
+            if name == class_name
+                && self
+                    .find_loaded(other, name)
+                    .is_some_and(|found| found != runtime)
+            {
+                return Err(LoadError::Constraint(class_name.replace('/', ".")));
+            }
+        }
+        if !is_builtin_loader(loader) {
+            self.namespaces
+                .insert((loader, class_name.to_string()), runtime.to_string());
+        }
+        Ok(())
+    }
+
+    /// Requires `first` and `second` to resolve `class_name` to the same
+    /// class, as linking a member reference across the two loaders does.
+    pub fn add_constraint(
+        &mut self,
+        class_name: &str,
+        first: LoaderId,
+        second: LoaderId,
+    ) -> Result<(), LoadError> {
+        if first == second || native::is_builtin_class(class_name) {
+            return Ok(());
+        }
+        let found = (
+            self.find_loaded(first, class_name),
+            self.find_loaded(second, class_name),
+        );
+        if let (Some(a), Some(b)) = found {
+            if a != b {
+                return Err(LoadError::Constraint(class_name.replace('/', ".")));
+            }
+        }
+        self.constraints
+            .push((class_name.to_string(), first, second));
+        Ok(())
+    }
+
+    /// Loads a class and verifies it the first time it is asked for.
+    pub fn load_class(&mut self, class_name: &str) -> Result<ClassFile, LoadError> {
+        let class_file = self.define_class(class_name)?;
+        let internal_name = match self.linkages.contains_key(class_name) {
+            true => class_name,
+            false => class_file
+                .get_class_name(class_file.this_class)
+                .unwrap_or(class_name),
+        }
+        .to_string();
+        self.link_class(&internal_name, &class_file)?;
+        Ok(class_file)
+    }
+
+    /// Finds and parses a class, and its superclasses, without verifying
+    /// anything.
+    fn define_class(&mut self, class_name: &str) -> Result<ClassFile, LoadError> {
         if let Some(cached) = self.loaded_classes.get(class_name) {
             return Ok(cached.clone());
         }
 
-        let file_path = class_name.replace('.', "/") + ".class";
-        for base in &self.search_paths {
-            let candidate = base.join(&file_path);
-            if candidate.exists() {
-                println!("Loading class: {}", candidate.display());
-                let path = candidate.to_string_lossy().to_string();
-                let class_file =
-                    ClassFile::parse(&path).map_err(|e| format!("Parse error: {}", e))?;
-
-                let internal_name = class_file
-                    .get_class_name(class_file.this_class)
-                    .unwrap_or(class_name);
-                self.init_static_fields_for_class(internal_name, &class_file);
-
-                if let Some(super_name) = class_file.get_class_name(class_file.super_class) {
-                    if super_name != "java/lang/Object" {
-                        let _ = self.load_class(super_name);
-                    }
-                }
-
-                self.loaded_classes
-                    .insert(class_name.to_string(), class_file.clone());
-                self.loaded_classes
-                    .insert(internal_name.to_string(), class_file.clone());
-                return Ok(class_file);
+        let file_path = class_name.replace('.', "/");
+        let archived = self
+            .shared_archive
+            .as_ref()
+            .and_then(|archive| archive.class(&file_path));
+        let class_file = if let Some(class_file) = archived {
+            println!("Loading class: {} source: shared objects file", file_path);
+            class_file
+        } else if let Some(candidate) = self
+            .search_paths
+            .iter()
+            .map(|base| base.join(format!("{}.class", file_path)))
+            .find(|candidate| candidate.exists())
+        {
+            println!("Loading class: {}", candidate.display());
+            let path = candidate.to_string_lossy().to_string();
+            ClassFile::parse(&path).map_err(LoadError::Format)?
+        } else if let Some(bytes) = self.memory_classes.get(&file_path) {
+            println!("Loading class: {} source: memory", file_path);
+            ClassFile::from_bytes(bytes).map_err(LoadError::Format)?
+        } else {
+            return Err(LoadError::NotFound(class_name.to_string()));
+        };
+
+        let internal_name = class_file
+            .get_class_name(class_file.this_class)
+            .unwrap_or(class_name);
+        self.init_static_fields_for_class(internal_name, &class_file);
+
+        if let Some(super_name) = class_file.get_class_name(class_file.super_class) {
+            if super_name != "java/lang/Object" {
+                let _ = self.define_class(super_name);
             }
         }
 
-        Err(format!("Class not found: {}", class_name))
+        self.loaded_classes
+            .insert(class_name.to_string(), class_file.clone());
+        self.loaded_classes
+            .insert(internal_name.to_string(), class_file.clone());
+        Ok(class_file)
+    }
+
+    /// Verifies a defined class once; a failure sticks to the class.
+    fn link_class(&mut self, class_name: &str, class: &ClassFile) -> Result<(), LoadError> {
+        if let Some(error) = self.verify_errors.get(class_name) {
+            return Err(LoadError::Verify(error.clone()));
+        }
+        if !self.verify || !self.verified.insert(class_name.to_string()) {
+            return Ok(());
+        }
+        self.verify_class(class_name, class).map_err(|error| {
+            self.verify_errors
+                .insert(class_name.to_string(), error.clone());
+            LoadError::Verify(error)
+        })
+    }
+
+    /// Verifies a class against the hierarchy its defining loader sees.
+    fn verify_class(&mut self, class_name: &str, class: &ClassFile) -> Result<(), VerifyError> {
+        match self.linkages.get(class_name).map(|linkage| linkage.loader) {
+            Some(loader) => verifier::verify_class(
+                class,
+                &mut LoaderView {
+                    classes: self,
+                    loader,
+                },
+            ),
+            None => verifier::verify_class(class, self),
+        }
     }
 
     pub fn preload_core_classes(&mut self) {
@@ -102,11 +553,21 @@ impl ClassLoader {
             .cloned()
     }
 
+    /// Every static field's value, for the collector's roots.
+    pub(crate) fn static_values(&self) -> impl Iterator<Item = &HeapValue> {
+        self.static_fields.values()
+    }
+
     pub fn set_static_field(&mut self, class_name: &str, field_name: &str, value: HeapValue) {
         self.static_fields
             .insert(Self::static_field_key(class_name, field_name), value);
     }
 
+    /// Whether initialization of the class has begun or completed.
+    pub fn class_init_started(&self, class_name: &str) -> bool {
+        self.class_init_state.contains_key(class_name)
+    }
+
     pub fn begin_class_init(&mut self, class_name: &str) -> bool {
         match self.class_init_state.get(class_name) {
             Some(ClassInitState::Initializing) | Some(ClassInitState::Initialized) => false,
@@ -151,3 +612,126 @@ impl ClassLoader {
         }
     }
 }
+
+/// Why `new` cannot replace `old` by redefinition, if it cannot. Only
+/// method bodies and the constant pool may change.
+fn schema_change(old: &ClassFile, new: &ClassFile) -> Option<&'static str> {
+    let supertypes = |class: &ClassFile| {
+        let mut names = vec![class.get_class_name(class.super_class).map(str::to_string)];
+        names.extend(
+            class
+                .interfaces
+                .iter()
+                .map(|index| class.get_class_name(*index).map(str::to_string)),
+        );
+        names
+    };
+    let fields = |class: &ClassFile| {
+        class
+            .fields
+            .iter()
+            .map(|field| {
+                (
+                    class.get_utf8(field.name_index).map(str::to_string),
+                    class.get_utf8(field.descriptor_index).map(str::to_string),
+                    field.access_flags,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let methods = |class: &ClassFile| {
+        class
+            .methods
+            .iter()
+            .map(|method| {
+                let name = class.get_utf8(method.name_index).unwrap_or_default();
+                let descriptor = class.get_utf8(method.descriptor_index).unwrap_or_default();
+                (format!("{}{}", name, descriptor), method.access_flags)
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    if supertypes(old) != supertypes(new) {
+        return Some("attempted to change superclass or interfaces");
+    }
+    if old.access_flags != new.access_flags {
+        return Some("attempted to change the class modifiers");
+    }
+    if fields(old) != fields(new) {
+        return Some("attempted to change the schema (add/remove fields)");
+    }
+    let (old_methods, new_methods) = (methods(old), methods(new));
+    if new_methods.keys().any(|key| !old_methods.contains_key(key)) {
+        return Some("attempted to add a method");
+    }
+    if old_methods.keys().any(|key| !new_methods.contains_key(key)) {
+        return Some("attempted to delete a method");
+    }
+    if old_methods
+        .iter()
+        .any(|(key, flags)| new_methods[key] != *flags)
+    {
+        return Some("attempted to change method modifiers");
+    }
+    None
+}
+
+impl ClassHierarchy for ClassLoader {
+    fn class_kind(&mut self, name: &str) -> ClassKind {
+        if name == "java/lang/Object" {
+            return ClassKind::Class { superclass: None };
+        }
+        if let Some(superclass) = java_lang_throwable::builtin_superclass(name) {
+            return ClassKind::Class {
+                superclass: Some(superclass.to_string()),
+            };
+        }
+        if native::is_builtin_class(name)
+            || (!self.loaded_classes.contains_key(name) && self.define_class(name).is_err())
+        {
+            return ClassKind::Unknown;
+        }
+        let class = &self.loaded_classes[name];
+        if class.access_flags & ACC_INTERFACE != 0 {
+            return ClassKind::Interface;
+        }
+        ClassKind::Class {
+            superclass: Some(
+                class
+                    .get_class_name(class.super_class)
+                    .unwrap_or("java/lang/Object")
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// The class hierarchy as code defined by a user-defined loader sees it:
+/// names resolve through the loader, and names it has not resolved yet are
+/// left to the type checks at run time.
+struct LoaderView<'a> {
+    classes: &'a mut ClassLoader,
+    loader: LoaderId,
+}
+
+impl ClassHierarchy for LoaderView<'_> {
+    fn class_kind(&mut self, name: &str) -> ClassKind {
+        if native::is_builtin_class(name) {
+            return self.classes.class_kind(name);
+        }
+        let Some(runtime) = self.classes.find_loaded(self.loader, name) else {
+            return ClassKind::Unknown;
+        };
+        let Some(linkage) = self.classes.linkages.get(&runtime) else {
+            return self.classes.class_kind(&runtime);
+        };
+        if self.classes.loaded_classes[&runtime].access_flags & ACC_INTERFACE != 0 {
+            return ClassKind::Interface;
+        }
+        ClassKind::Class {
+            superclass: linkage
+                .superclass
+                .as_deref()
+                .map(|superclass| self.classes.symbolic_name(superclass).into_owned()),
+        }
+    }
+}
@@ -1,15 +1,21 @@
 use crate::bytecode::parser::*;
 
 impl ClassFile {
+    /// The entry at a 1-based constant-pool index; `None` for index 0 and
+    /// anything past the end.
+    pub fn constant(&self, index: u16) -> Option<&ConstantPoolEntry> {
+        self.constant_pool.get(index.checked_sub(1)? as usize)
+    }
+
     pub fn get_utf8(&self, index: u16) -> Option<&str> {
-        match self.constant_pool.get((index - 1) as usize)? {
+        match self.constant(index)? {
             ConstantPoolEntry::Utf8(s) => Some(s),
             _ => None,
         }
     }
 
     pub fn get_class_name(&self, index: u16) -> Option<&str> {
-        match self.constant_pool.get((index - 1) as usize)? {
+        match self.constant(index)? {
             ConstantPoolEntry::Class { name_index } => self.get_utf8(*name_index),
             _ => None,
         }
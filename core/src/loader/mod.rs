@@ -1,2 +1,4 @@
+mod archived_class;
 pub mod class_loader;
 pub mod constant_pool;
+pub mod shared_archive;
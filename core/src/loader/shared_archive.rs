@@ -0,0 +1,548 @@
+//! Class data sharing. `-Xshare:dump` loads every class on the class path,
+//! verifies it, and writes the parsed classes into one archive; later runs
+//! map the archive and rebuild classes from it instead of searching the
+//! class path and decoding each file.
+//!
+//! The archive records the size and modification time of every class file
+//! it was built from, and what every class path directory held. Opening it
+//! checks them all, so an archive whose class path was touched since is
+//! never used.
+//!
+//! An archive is only a file, so nothing in it is taken on trust: it must
+//! belong to the user running the VM and be writable by nobody else, and
+//! the classes rebuilt from it are checked and verified like any other.
+
+use crate::bytecode::parser::ClassFile;
+use crate::loader::archived_class;
+use crate::loader::class_loader::ClassLoader;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const MAGIC: &[u8; 8] = b"ARIACDS\0";
+const FORMAT_VERSION: u32 = 3;
+const ARIA_VERSION: &str = include_str!("../../../VERSION");
+
+/// What `-Xshare` asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShareMode {
+    /// Ignore any archive (`-Xshare:off`).
+    Off,
+    /// Use the archive if it is usable (`-Xshare:auto`).
+    #[default]
+    Auto,
+    /// Fail to start without a usable archive (`-Xshare:on`).
+    On,
+    /// Write the archive and exit (`-Xshare:dump`).
+    Dump,
+}
+
+/// Why an archive could not be written or used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveError {
+    Io(String),
+    /// Not an archive, or one written by another build.
+    Incompatible(String),
+    /// Dumped for a different class path.
+    ClassPathMismatch,
+    /// A class path file or directory changed after the dump.
+    Stale(PathBuf),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Io(message) => write!(f, "{}", message),
+            ArchiveError::Incompatible(reason) => write!(f, "{}", reason),
+            ArchiveError::ClassPathMismatch => write!(f, "shared class paths mismatch"),
+            ArchiveError::Stale(path) => write!(
+                f,
+                "A jar file or class path directory is not the one used while building the shared archive file: {}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+/// Where an archive for `class_path` lives unless `-XX:SharedArchiveFile`
+/// says otherwise: one file per class path in the user's cache directory,
+/// never a shared one like the temp directory. `None` without a home.
+pub fn default_path(class_path: &[PathBuf]) -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            let home = PathBuf::from(std::env::var_os("HOME")?);
+            home.is_absolute().then(|| home.join(".cache"))
+        })?;
+    let names = canonical_class_path(class_path)
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    Some(
+        cache
+            .join("aria")
+            .join(format!("cds-{:016x}.jsa", fnv1a(&names))),
+    )
+}
+
+/// FNV-1a over `names`, which unlike `DefaultHasher` is the same in every
+/// build.
+fn fnv1a(names: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in names.iter().flat_map(|name| name.bytes().chain([0])) {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Loads every class under `roots`, which should be on the loader's search
+/// path, and writes those that load into an archive at `path`. Returns how
+/// many were archived.
+pub fn dump(
+    loader: &mut ClassLoader,
+    roots: &[PathBuf],
+    path: &Path,
+) -> Result<usize, ArchiveError> {
+    let class_path = canonical_class_path(loader.search_paths());
+    let mut stamps = Vec::new();
+    let mut files = Vec::new();
+    for entry in &canonical_class_path(roots) {
+        stamps.push((entry.clone(), stamp(entry)));
+        if entry.is_dir() {
+            collect_class_files(entry, entry, &mut stamps, &mut files)
+                .map_err(|e| io_error("read", entry, e))?;
+        }
+    }
+
+    let mut classes: Vec<(String, Vec<u8>)> = Vec::new();
+    for (name, _) in files {
+        if classes.iter().any(|(archived, _)| *archived == name) {
+            continue;
+        }
+        match loader.load_class(&name) {
+            Ok(class) => {
+                let mut bytes = Vec::new();
+                archived_class::write(&class, &mut bytes);
+                classes.push((name, bytes));
+            }
+            Err(error) => eprintln!("Preload Warning: Skipping {}: {}", name, error),
+        }
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    put_u32(&mut out, FORMAT_VERSION);
+    put_str(&mut out, ARIA_VERSION.trim());
+    put_u32(&mut out, class_path.len() as u32);
+    for entry in &class_path {
+        put_str(&mut out, &entry.to_string_lossy());
+    }
+    put_u32(&mut out, stamps.len() as u32);
+    for (path, (size, modified)) in &stamps {
+        put_str(&mut out, &path.to_string_lossy());
+        put_u64(&mut out, *size);
+        put_u64(&mut out, *modified);
+    }
+    // Class data follows the index, so offsets are known once the index
+    // size is.
+    let index_len: usize = classes.iter().map(|(name, _)| 4 + name.len() + 16).sum();
+    let mut offset = (out.len() + 4 + index_len) as u64;
+    put_u32(&mut out, classes.len() as u32);
+    for (name, bytes) in &classes {
+        put_str(&mut out, name);
+        put_u64(&mut out, offset);
+        put_u64(&mut out, bytes.len() as u64);
+        offset += bytes.len() as u64;
+    }
+    for (_, bytes) in &classes {
+        out.extend_from_slice(bytes);
+    }
+
+    if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        private_dir(parent).map_err(|e| io_error("write", path, e))?;
+    }
+    // Written aside and renamed, so a running VM never maps half a file.
+    let partial = path.with_extension("jsa.tmp");
+    let _ = fs::remove_file(&partial);
+    write_private(&partial, &out).map_err(|e| io_error("write", &partial, e))?;
+    fs::rename(&partial, path).map_err(|e| io_error("write", path, e))?;
+    Ok(classes.len())
+}
+
+/// A mapped archive whose class path is known to be unchanged.
+pub struct SharedArchive {
+    path: PathBuf,
+    mapping: Mapping,
+    classes: HashMap<String, Range<usize>>,
+}
+
+impl SharedArchive {
+    /// Maps the archive at `path` and checks that only the current user
+    /// could have written it, that it was dumped by this build for
+    /// `class_path`, and that nothing on the class path changed since.
+    pub fn open(path: &Path, class_path: &[PathBuf]) -> Result<Self, ArchiveError> {
+        let file = fs::File::open(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ArchiveError::Io(format!(
+                "Specified shared archive not found ({})",
+                path.display()
+            )),
+            _ => io_error("map", path, e),
+        })?;
+        // The file just opened, not whatever `path` names by now.
+        let metadata = file.metadata().map_err(|e| io_error("map", path, e))?;
+        check_owner(path, &metadata)?;
+        let mapping = Mapping::open(file, &metadata).map_err(|e| io_error("map", path, e))?;
+        let classes = read_index(mapping.bytes(), class_path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            mapping,
+            classes,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    pub fn contains(&self, class_name: &str) -> bool {
+        self.classes.contains_key(class_name)
+    }
+
+    /// The archived class with this internal name, if it passes the checks
+    /// parsing would have made. It still has to be verified.
+    pub fn class(&self, class_name: &str) -> Option<ClassFile> {
+        let range = self.classes.get(class_name)?.clone();
+        archived_class::read(&self.mapping.bytes()[range])
+    }
+}
+
+fn read_index(
+    bytes: &[u8],
+    class_path: &[PathBuf],
+) -> Result<HashMap<String, Range<usize>>, ArchiveError> {
+    let mut cursor = Cursor { bytes, position: 0 };
+    if cursor.take(MAGIC.len()) != Some(MAGIC.as_slice()) {
+        return Err(ArchiveError::Incompatible(
+            "The shared archive file has a bad magic number".to_string(),
+        ));
+    }
+    let corrupt = || ArchiveError::Incompatible("The shared archive file is corrupt".to_string());
+    if cursor.u32() != Some(FORMAT_VERSION) || cursor.str() != Some(ARIA_VERSION.trim()) {
+        return Err(ArchiveError::Incompatible(
+            "The shared archive file was created by a different version or build of AriaJDK"
+                .to_string(),
+        ));
+    }
+
+    let expected = canonical_class_path(class_path);
+    let count = cursor.u32().ok_or_else(corrupt)? as usize;
+    if count != expected.len() {
+        return Err(ArchiveError::ClassPathMismatch);
+    }
+    for entry in &expected {
+        if cursor.str().ok_or_else(corrupt)? != entry.to_string_lossy() {
+            return Err(ArchiveError::ClassPathMismatch);
+        }
+    }
+
+    let mut stamped = HashSet::new();
+    for _ in 0..cursor.u32().ok_or_else(corrupt)? {
+        let path = PathBuf::from(cursor.str().ok_or_else(corrupt)?);
+        let recorded = (
+            cursor.u64().ok_or_else(corrupt)?,
+            cursor.u64().ok_or_else(corrupt)?,
+        );
+        if stamp(&path) != recorded {
+            return Err(ArchiveError::Stale(path));
+        }
+        stamped.insert(path);
+    }
+
+    let count = cursor.u32().ok_or_else(corrupt)? as usize;
+    let mut classes = HashMap::with_capacity(count);
+    for _ in 0..count {
+        let name = cursor.str().ok_or_else(corrupt)?;
+        let start = cursor.u64().ok_or_else(corrupt)? as usize;
+        let len = cursor.u64().ok_or_else(corrupt)? as usize;
+        let end = start.checked_add(len).filter(|&end| end <= bytes.len());
+        let end = end.ok_or_else(corrupt)?;
+        // A class whose file was not stamped could not be checked for
+        // staleness.
+        let file = format!("{}.class", name);
+        if !expected
+            .iter()
+            .any(|entry| stamped.contains(&entry.join(&file)))
+        {
+            return Err(ArchiveError::Stale(PathBuf::from(file)));
+        }
+        classes.insert(name.to_string(), start..end);
+    }
+    Ok(classes)
+}
+
+/// Adds the class files under `dir`, in a stable order, and stamps every
+/// directory on the way so that added or removed classes are noticed.
+fn collect_class_files(
+    root: &Path,
+    dir: &Path,
+    stamps: &mut Vec<(PathBuf, (u64, u64))>,
+    files: &mut Vec<(String, PathBuf)>,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            stamps.push((path.clone(), stamp(&path)));
+            collect_class_files(root, &path, stamps, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "class") {
+            let Ok(relative) = path
+                .with_extension("")
+                .strip_prefix(root)
+                .map(Path::to_path_buf)
+            else {
+                continue;
+            };
+            let name = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            stamps.push((path.clone(), stamp(&path)));
+            files.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+fn canonical_class_path(class_path: &[PathBuf]) -> Vec<PathBuf> {
+    class_path
+        .iter()
+        .map(|entry| fs::canonicalize(entry).unwrap_or_else(|_| entry.clone()))
+        .collect()
+}
+
+/// Size and modification time in nanoseconds for a file. A directory is
+/// stamped by the names of the classes and directories it holds rather
+/// than its time, which would change whenever anything else, such as the
+/// archive itself, is written there. A missing path has its own stamp, so
+/// creating it later also invalidates the archive.
+fn stamp(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return (u64::MAX, 0);
+    };
+    if metadata.is_dir() {
+        let mut names = fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| {
+                let path = entry.path();
+                path.is_dir() || path.extension().is_some_and(|ext| ext == "class")
+            })
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        return (fnv1a(&names), u64::MAX);
+    }
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos() as u64);
+    (metadata.len(), modified)
+}
+
+/// Refuses an archive another user could have written: one they own, or
+/// one that group or others may write.
+#[cfg(unix)]
+fn check_owner(path: &Path, metadata: &fs::Metadata) -> Result<(), ArchiveError> {
+    use std::os::unix::fs::MetadataExt;
+
+    // SAFETY: `geteuid` has no preconditions and cannot fail.
+    let uid = unsafe { libc::geteuid() };
+    if metadata.uid() != uid {
+        return Err(ArchiveError::Incompatible(format!(
+            "The shared archive file {} is not owned by the current user",
+            path.display()
+        )));
+    }
+    if metadata.mode() & 0o022 != 0 {
+        return Err(ArchiveError::Incompatible(format!(
+            "The shared archive file {} is writable by other users",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_owner(_path: &Path, _metadata: &fs::Metadata) -> Result<(), ArchiveError> {
+    Ok(())
+}
+
+/// Creates `dir` if needed, readable only by its owner when it is new.
+fn private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Writes a new file that only its owner may write.
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o644);
+    io::Write::write_all(&mut options.open(path)?, bytes)
+}
+
+fn io_error(action: &str, path: &Path, error: io::Error) -> ArchiveError {
+    ArchiveError::Io(format!(
+        "Unable to {} shared archive file {}: {}",
+        action,
+        path.display(),
+        error
+    ))
+}
+
+pub(super) fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(super) fn put_str(out: &mut Vec<u8>, text: &str) {
+    put_u32(out, text.len() as u32);
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// A little-endian reader over the archive; `None` past the end.
+pub(super) struct Cursor<'a> {
+    pub bytes: &'a [u8],
+    pub position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.position)
+    }
+
+    pub fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(n)?;
+        let bytes = self.bytes.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub fn str(&mut self) -> Option<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).ok()
+    }
+}
+
+/// The archive file, mapped read-only where the platform allows.
+#[cfg(unix)]
+struct Mapping {
+    base: *mut libc::c_void,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl Mapping {
+    fn open(file: fs::File, metadata: &fs::Metadata) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = metadata.len() as usize;
+        if len == 0 {
+            return Ok(Self {
+                base: std::ptr::null_mut(),
+                len,
+            });
+        }
+        // SAFETY: a private read-only mapping of a file we opened; the
+        // result is checked before use and the mapping outlives the fd.
+        let base = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { base, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        if self.base.is_null() {
+            return &[];
+        }
+        // SAFETY: the mapping is `len` readable bytes until dropped.
+        unsafe { std::slice::from_raw_parts(self.base as *const u8, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if !self.base.is_null() {
+            // SAFETY: `base` and `len` describe a mapping this value owns.
+            unsafe {
+                libc::munmap(self.base, self.len);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Mapping(Vec<u8>);
+
+#[cfg(not(unix))]
+impl Mapping {
+    fn open(mut file: fs::File, _metadata: &fs::Metadata) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        io::Read::read_to_end(&mut file, &mut bytes)?;
+        Ok(Self(bytes))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
@@ -0,0 +1,75 @@
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{Heap, HeapValue};
+use std::io::Read;
+
+/// Allocates the `java/io/BufferedInputStream` behind `System.in`. Only
+/// file descriptor 0 is backed by a real source; Rust's stdin already
+/// buffers, so reads go straight through.
+pub fn new_stream(heap: &mut Heap, fd: i32) -> HeapValue {
+    let mut obj = heap.alloc_object("java/io/BufferedInputStream");
+    obj.set_field("fd", HeapValue::Int(fd));
+    if let Some(real) = heap.get_mut(obj.id) {
+        real.set_field("fd", HeapValue::Int(fd));
+    }
+    HeapValue::Object(obj)
+}
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("read", "()I", read_byte),
+    ("read", "([B)I", read_bytes),
+    ("read", "([BII)I", read_bytes),
+    ("available", "()I", |_, _, _| Some(Some(HeapValue::Int(0)))),
+    ("close", "()V", |_, _, _| Some(None)),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/io/InputStream", METHODS);
+}
+
+fn read_byte(
+    _env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let mut byte = [0u8; 1];
+    let n = std::io::stdin().read(&mut byte).unwrap_or(0);
+    let value = if n == 0 { -1 } else { byte[0] as i32 };
+    Some(Some(HeapValue::Int(value)))
+}
+
+/// `read([B)` and `read([BII)`.
+fn read_bytes(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let HeapValue::Array(arr) = args.first()? else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    let capacity = env.heap.get_array(arr.id)?.content.len();
+    let (offset, len) = match args {
+        [_, off, len] => (off.as_int(), len.as_int()),
+        _ => (0, capacity as i32),
+    };
+    if offset < 0 || len < 0 || offset as usize + len as usize > capacity {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/IndexOutOfBoundsException", None);
+        return Some(None);
+    }
+    if len == 0 {
+        return Some(Some(HeapValue::Int(0)));
+    }
+    let mut buffer = vec![0u8; len as usize];
+    let n = std::io::stdin().read(&mut buffer).unwrap_or(0);
+    if n == 0 {
+        return Some(Some(HeapValue::Int(-1)));
+    }
+    let target = env.heap.get_array_mut(arr.id)?;
+    for (i, byte) in buffer[..n].iter().enumerate() {
+        target.content[offset as usize + i] = HeapValue::Int(*byte as i8 as i32);
+    }
+    Some(Some(HeapValue::Int(n as i32)))
+}
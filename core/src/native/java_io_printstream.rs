@@ -1,47 +1,381 @@
+use crate::native::java_lang_boxing::{double_to_string, float_to_string};
+use crate::native::java_lang_system::line_separator;
+use crate::native::java_util_formatter;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
 use crate::runtime::heap::{Heap, HeapValue};
+use std::io::Write;
+use std::sync::Mutex;
 
-pub fn invoke(
-    method_name: &str,
-    descriptor: &str,
+/// `System.out`/`System.err` wrap a `BufferedOutputStream(fos, 128)` with
+/// autoflush enabled, so output sits in a small buffer until a newline,
+/// a `println`, a byte-array write or an explicit flush.
+const BUFFER_SIZE: usize = 128;
+
+struct StreamBuffer {
+    fd: i32,
+    bytes: Vec<u8>,
+}
+
+static BUFFERS: Mutex<Vec<StreamBuffer>> = Mutex::new(Vec::new());
+
+/// Allocates a `java/io/PrintStream` bound to a file descriptor.
+pub fn new_stream(heap: &mut Heap, fd: i32) -> HeapValue {
+    let mut obj = heap.alloc_object("java/io/PrintStream");
+    obj.set_field("fd", HeapValue::Int(fd));
+    if let Some(real) = heap.get_mut(obj.id) {
+        real.set_field("fd", HeapValue::Int(fd));
+    }
+    HeapValue::Object(obj)
+}
+
+const PRINT_STREAM: &str = "java/io/PrintStream";
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("println", "()V", println),
+    (
+        "printf",
+        "(Ljava/lang/String;[Ljava/lang/Object;)Ljava/io/PrintStream;",
+        printf,
+    ),
+    (
+        "printf",
+        "(Ljava/util/Locale;Ljava/lang/String;[Ljava/lang/Object;)Ljava/io/PrintStream;",
+        printf,
+    ),
+    (
+        "format",
+        "(Ljava/lang/String;[Ljava/lang/Object;)Ljava/io/PrintStream;",
+        printf,
+    ),
+    (
+        "format",
+        "(Ljava/util/Locale;Ljava/lang/String;[Ljava/lang/Object;)Ljava/io/PrintStream;",
+        printf,
+    ),
+    ("append", "(C)Ljava/io/PrintStream;", append_char),
+    (
+        "append",
+        "(Ljava/lang/CharSequence;)Ljava/io/PrintStream;",
+        append_chars,
+    ),
+    ("write", "(I)V", write_byte),
+    ("write", "([B)V", write_array),
+    ("write", "([BII)V", write_array),
+    ("flush", "()V", flush_stream),
+    ("close", "()V", flush_stream),
+    ("checkError", "()Z", check_error),
+];
+
+/// Renders a `print` argument of one declared type.
+type Render = fn(&mut NativeEnv, &HeapValue) -> Option<String>;
+
+/// The `print` and `println` overloads by descriptor.
+const PRINTED: &[(&str, Render)] = &[
+    ("(Z)V", render_boolean),
+    ("(C)V", render_char),
+    ("(I)V", render_int),
+    ("(J)V", render_long),
+    ("(F)V", render_float),
+    ("(D)V", render_double),
+    ("([C)V", render_chars),
+    ("(Ljava/lang/String;)V", render_object),
+    ("(Ljava/lang/Object;)V", render_object),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all(PRINT_STREAM, METHODS);
+    for &(descriptor, render) in PRINTED {
+        registry.register_builtin(PRINT_STREAM, "print", descriptor, move |env, this, args| {
+            print(env, this, args, render, false)
+        });
+        registry.register_builtin(
+            PRINT_STREAM,
+            "println",
+            descriptor,
+            move |env, this, args| print(env, this, args, render, true),
+        );
+    }
+}
+
+/// The file descriptor a `PrintStream` writes to.
+fn stream_fd(env: &NativeEnv, receiver: Option<&HeapValue>) -> Option<i32> {
+    let Some(HeapValue::Object(stream)) = receiver else {
+        return None;
+    };
+    Some(
+        env.heap
+            .get(stream.id)
+            .and_then(|real| real.get_field("fd"))
+            .or_else(|| stream.get_field("fd"))
+            .map(|v| v.as_int())
+            .unwrap_or(1),
+    )
+}
+
+fn println(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let fd = stream_fd(env, receiver)?;
+    write_bytes(fd, line_separator().as_bytes(), true);
+    Some(None)
+}
+
+fn print(
+    env: &mut NativeEnv,
     receiver: Option<&HeapValue>,
     args: &[HeapValue],
-    heap: &mut Heap,
+    render: Render,
+    newline: bool,
 ) -> Option<Option<HeapValue>> {
-    if !matches!(receiver, Some(HeapValue::Object(_))) {
-        return None;
+    let fd = stream_fd(env, receiver)?;
+    // Only a null `char[]` has no rendering.
+    let Some(mut text) = render(env, args.first()?) else {
+        return throw(env, "java/lang/NullPointerException");
+    };
+    if newline {
+        text.push_str(line_separator());
     }
-    match (method_name, descriptor) {
-        ("println", "(I)V") => {
-            let val = args.first().map(|v| v.as_int()).unwrap_or(0);
-            println!("{}", val);
-            Some(None)
+    let flush = newline || text.contains('\n');
+    write_bytes(fd, text.as_bytes(), flush);
+    Some(None)
+}
+
+/// `printf` and `format`. The optional leading Locale is ignored;
+/// formatting is en-US.
+fn printf(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let fd = stream_fd(env, receiver)?;
+    let (pattern, varargs) = match args {
+        [_, pattern, varargs] | [pattern, varargs] => (pattern, varargs),
+        _ => return None,
+    };
+    let pattern = env.heap.string_value(pattern)?;
+    let values = match varargs {
+        HeapValue::Array(arr) => env
+            .heap
+            .get_array(arr.id)
+            .map(|a| a.content.clone())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    match java_util_formatter::format(env, &pattern, &values) {
+        Ok(text) => write_bytes(fd, text.as_bytes(), text.contains('\n')),
+        Err(err) => {
+            env.interpreter
+                .throw_new(env.heap, err.class_name, Some(&err.message));
+            return Some(None);
         }
-        ("println", "(J)V") => {
-            let val = match args.first() {
-                Some(HeapValue::Long(v)) => *v,
-                Some(v) => v.as_long(),
-                None => 0,
-            };
-            println!("{}", val);
-            Some(None)
+    }
+    Some(receiver.cloned())
+}
+
+fn append_char(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    append(env, receiver, args, render_char)
+}
+
+fn append_chars(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    append(env, receiver, args, render_object)
+}
+
+fn append(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+    render: Render,
+) -> Option<Option<HeapValue>> {
+    let fd = stream_fd(env, receiver)?;
+    let text = render(env, args.first()?)?;
+    write_bytes(fd, text.as_bytes(), text.contains('\n'));
+    Some(receiver.cloned())
+}
+
+fn write_byte(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let fd = stream_fd(env, receiver)?;
+    let byte = args.first()?.as_int() as u8;
+    write_bytes(fd, &[byte], byte == b'\n');
+    Some(None)
+}
+
+/// `write([B)` and `write([BII)`.
+fn write_array(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let fd = stream_fd(env, receiver)?;
+    let HeapValue::Array(arr) = args.first()? else {
+        return throw(env, "java/lang/NullPointerException");
+    };
+    let content = env.heap.get_array(arr.id)?.content.clone();
+    let (offset, len) = match args {
+        [_, off, len] => (off.as_int() as i64, len.as_int() as i64),
+        _ => (0, content.len() as i64),
+    };
+    if offset < 0 || len < 0 || offset + len > content.len() as i64 {
+        return throw(env, "java/lang/IndexOutOfBoundsException");
+    }
+    let bytes: Vec<u8> = content[offset as usize..(offset + len) as usize]
+        .iter()
+        .map(|v| v.as_int() as u8)
+        .collect();
+    write_bytes(fd, &bytes, true);
+    Some(None)
+}
+
+/// `flush` and `close`.
+fn flush_stream(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    flush(stream_fd(env, receiver)?);
+    Some(None)
+}
+
+fn check_error(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    flush(stream_fd(env, receiver)?);
+    Some(Some(HeapValue::Int(0)))
+}
+
+fn throw(env: &mut NativeEnv, class_name: &str) -> Option<Option<HeapValue>> {
+    env.interpreter.throw_new(env.heap, class_name, None);
+    Some(None)
+}
+
+fn render_boolean(_env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    Some((value.as_int() != 0).to_string())
+}
+
+fn render_char(_env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    Some(
+        char::from_u32(value.as_int() as u32 & 0xFFFF)
+            .unwrap_or('\u{FFFD}')
+            .to_string(),
+    )
+}
+
+fn render_int(_env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    Some(value.as_int().to_string())
+}
+
+fn render_long(_env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    Some(value.as_long().to_string())
+}
+
+fn render_float(_env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    Some(match value {
+        HeapValue::Float(v) => float_to_string(*v),
+        other => float_to_string(other.as_int() as f32),
+    })
+}
+
+fn render_double(_env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    Some(match value {
+        HeapValue::Double(v) => double_to_string(*v),
+        HeapValue::Float(v) => double_to_string(*v as f64),
+        other => double_to_string(other.as_long() as f64),
+    })
+}
+
+fn render_chars(env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    let HeapValue::Array(arr) = value else {
+        return None;
+    };
+    let units: Vec<u16> = env
+        .heap
+        .get_array(arr.id)?
+        .content
+        .iter()
+        .map(|v| v.as_int() as u16)
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+fn render_object(env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    Some(env.to_java_string(value))
+}
+
+fn write_bytes(fd: i32, data: &[u8], flush_after: bool) {
+    let mut buffers = BUFFERS.lock().unwrap_or_else(|e| e.into_inner());
+    let index = match buffers.iter().position(|b| b.fd == fd) {
+        Some(index) => index,
+        None => {
+            buffers.push(StreamBuffer {
+                fd,
+                bytes: Vec::with_capacity(BUFFER_SIZE),
+            });
+            buffers.len() - 1
         }
-        ("println", "(Ljava/lang/String;)V") => {
-            let rendered = match args.first() {
-                Some(HeapValue::Object(obj)) if obj.class_name == "java/lang/String" => heap
-                    .get(obj.id)
-                    .and_then(|real| real.get_field("value"))
-                    .map(|v| match v {
-                        HeapValue::String(s) => s.clone(),
-                        other => other.to_string(),
-                    })
-                    .unwrap_or_else(|| "<string>".to_string()),
-                Some(HeapValue::String(s)) => s.clone(),
-                Some(v) => v.to_string(),
-                None => "".to_string(),
-            };
-            println!("{}", rendered);
-            Some(None)
+    };
+    let buffer = &mut buffers[index];
+    if buffer.bytes.len() + data.len() >= BUFFER_SIZE {
+        raw_write(fd, &buffer.bytes);
+        buffer.bytes.clear();
+        if data.len() >= BUFFER_SIZE {
+            raw_write(fd, data);
+        } else {
+            buffer.bytes.extend_from_slice(data);
         }
-        _ => None,
+    } else {
+        buffer.bytes.extend_from_slice(data);
+    }
+    if flush_after {
+        raw_write(fd, &buffer.bytes);
+        buffer.bytes.clear();
     }
 }
+
+pub fn flush(fd: i32) {
+    let mut buffers = BUFFERS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(buffer) = buffers.iter_mut().find(|b| b.fd == fd) {
+        raw_write(fd, &buffer.bytes);
+        buffer.bytes.clear();
+    }
+}
+
+/// Flushes every stream buffer, used when the VM shuts down.
+pub fn flush_all() {
+    let mut buffers = BUFFERS.lock().unwrap_or_else(|e| e.into_inner());
+    for buffer in buffers.iter_mut() {
+        raw_write(buffer.fd, &buffer.bytes);
+        buffer.bytes.clear();
+    }
+}
+
+fn raw_write(fd: i32, bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+    let _ = match fd {
+        2 => {
+            let mut err = std::io::stderr();
+            err.write_all(bytes).and_then(|_| err.flush())
+        }
+        _ => {
+            let mut out = std::io::stdout();
+            out.write_all(bytes).and_then(|_| out.flush())
+        }
+    };
+}
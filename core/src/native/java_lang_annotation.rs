@@ -0,0 +1,863 @@
+use crate::bytecode::attributes::{Annotation, Attribute, ElementValue};
+use crate::bytecode::parser::{ClassFile, ConstantPoolEntry};
+use crate::native::java_lang_class::{self, split_method_descriptor};
+use crate::native::registry::NativeRegistry;
+use crate::native::{
+    java_lang_boxing, java_lang_enum, java_lang_object, java_lang_reflect, java_lang_reflect_proxy,
+    NativeEnv,
+};
+use crate::runtime::heap::{ArrayType, Heap, HeapValue};
+
+pub const ANNOTATION: &str = "java/lang/annotation/Annotation";
+const HANDLER: &str = "sun/reflect/annotation/AnnotationInvocationHandler";
+const INHERITED: &str = "Ljava/lang/annotation/Inherited;";
+const ACC_STATIC: u16 = 0x0008;
+const ACC_ANNOTATION: u16 = 0x2000;
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all(
+        HANDLER,
+        &[(
+            "invoke",
+            "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;",
+            |env, this, args| {
+                let HeapValue::Object(handler) = this? else {
+                    return None;
+                };
+                invoke(env, handler.id, args.first()?, args.get(1)?, args.get(2)?)
+            },
+        )],
+    );
+}
+
+/// Finds the annotations of an annotated element: only those written on
+/// it when the flag is set, otherwise all it has.
+pub type AnnotationSource = fn(&mut NativeEnv, &HeapValue, bool) -> Option<Vec<HeapValue>>;
+
+/// Binds `AnnotatedElement`, shared by `Class` and the reflection members,
+/// over the annotations `source` finds.
+pub fn register_annotated_element(
+    registry: &mut NativeRegistry,
+    class_name: &str,
+    source: AnnotationSource,
+) {
+    for (method_name, declared) in [("getAnnotation", false), ("getDeclaredAnnotation", true)] {
+        registry.register_builtin(
+            class_name,
+            method_name,
+            "(Ljava/lang/Class;)Ljava/lang/annotation/Annotation;",
+            move |env, this, args| {
+                let Some(wanted) = annotation_class(env, args.first()?) else {
+                    return Some(None);
+                };
+                let annotations = source(env, this?, declared)?;
+                let found = find_annotation(env.heap, annotations, &wanted);
+                Some(Some(found.unwrap_or(HeapValue::Null)))
+            },
+        );
+    }
+    for (method_name, declared) in [("getAnnotations", false), ("getDeclaredAnnotations", true)] {
+        registry.register_builtin(
+            class_name,
+            method_name,
+            "()[Ljava/lang/annotation/Annotation;",
+            move |env, this, _| {
+                let annotations = source(env, this?, declared)?;
+                Some(Some(java_lang_class::reference_array(
+                    env.heap,
+                    ANNOTATION,
+                    annotations,
+                )))
+            },
+        );
+    }
+    registry.register_builtin(
+        class_name,
+        "isAnnotationPresent",
+        "(Ljava/lang/Class;)Z",
+        move |env, this, args| {
+            let Some(wanted) = annotation_class(env, args.first()?) else {
+                return Some(None);
+            };
+            let annotations = source(env, this?, false)?;
+            let found = find_annotation(env.heap, annotations, &wanted);
+            Some(Some(HeapValue::Int(found.is_some() as i32)))
+        },
+    );
+}
+
+/// The annotation type a `Class` argument names; throws
+/// `NullPointerException` and returns `None` for `null`.
+fn annotation_class(env: &mut NativeEnv, class: &HeapValue) -> Option<String> {
+    let wanted = java_lang_class::class_name(env.heap, class);
+    if wanted.is_none() {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+    }
+    wanted
+}
+
+fn find_annotation(heap: &Heap, annotations: Vec<HeapValue>, wanted: &str) -> Option<HeapValue> {
+    annotations
+        .into_iter()
+        .find(|annotation| annotation_type(heap, annotation).as_deref() == Some(wanted))
+}
+
+pub fn is_annotation_class(class_name: &str) -> bool {
+    matches!(class_name, ANNOTATION | HANDLER)
+}
+
+/// Where an annotated element sits in its class file.
+#[derive(Debug, Clone, Copy)]
+pub enum Element<'a> {
+    Class,
+    Field(&'a str),
+    /// A method or constructor, by name and descriptor.
+    Method(&'a str, &'a str),
+}
+
+fn attributes<'c>(class: &'c ClassFile, element: Element) -> &'c [Attribute] {
+    let found = match element {
+        Element::Class => Some(&class.attributes),
+        Element::Field(name) => class
+            .fields
+            .iter()
+            .find(|field| class.get_utf8(field.name_index) == Some(name))
+            .map(|field| &field.attributes),
+        Element::Method(name, descriptor) => class
+            .methods
+            .iter()
+            .find(|method| {
+                class.get_utf8(method.name_index) == Some(name)
+                    && class.get_utf8(method.descriptor_index) == Some(descriptor)
+            })
+            .map(|method| &method.attributes),
+    };
+    found.map_or(&[], Vec::as_slice)
+}
+
+fn visible(attributes: &[Attribute]) -> &[Annotation] {
+    attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::RuntimeVisibleAnnotations(annotations) => Some(annotations.as_slice()),
+            _ => None,
+        })
+        .unwrap_or(&[])
+}
+
+/// The runtime-visible annotations written on an element, skipping any
+/// whose type cannot be loaded.
+pub fn declared_annotations(
+    env: &mut NativeEnv,
+    class_name: &str,
+    element: Element,
+) -> Vec<HeapValue> {
+    let Some(runtime) = java_lang_class::loaded_class(env, class_name) else {
+        return Vec::new();
+    };
+    materialize_all(
+        env,
+        &runtime.class,
+        visible(attributes(&runtime.class, element)),
+    )
+}
+
+/// `Class.getAnnotations`: the class's own annotations, after those it
+/// inherits from its superclasses through `@Inherited`.
+pub fn class_annotations(env: &mut NativeEnv, class_name: &str) -> Vec<HeapValue> {
+    let inherited = match env.interpreter.superclass_of(env.loader, class_name) {
+        Some(parent) => class_annotations(env, &parent),
+        None => Vec::new(),
+    };
+    let mark = keep(env, &inherited);
+    let mut annotations: Vec<HeapValue> = inherited
+        .into_iter()
+        .filter(|annotation| {
+            annotation_type(env.heap, annotation).is_some_and(|kind| is_inherited(env, &kind))
+        })
+        .collect();
+    for annotation in declared_annotations(env, class_name, Element::Class) {
+        let kind = annotation_type(env.heap, &annotation);
+        let existing = annotations
+            .iter()
+            .position(|other| annotation_type(env.heap, other) == kind);
+        match existing {
+            Some(index) => annotations[index] = annotation,
+            None => annotations.push(annotation),
+        }
+    }
+    env.interpreter.resume_roots(mark);
+    annotations
+}
+
+/// `getParameterAnnotations`: one list per parameter. Synthetic leading
+/// parameters, such as an inner class's outer instance, get none.
+pub fn parameter_annotations(
+    env: &mut NativeEnv,
+    class_name: &str,
+    method_name: &str,
+    descriptor: &str,
+) -> HeapValue {
+    let count = split_method_descriptor(descriptor).0.len();
+    let runtime = java_lang_class::loaded_class(env, class_name);
+    let lists = runtime.as_ref().and_then(|runtime| {
+        let element = Element::Method(method_name, descriptor);
+        attributes(&runtime.class, element)
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::RuntimeVisibleParameterAnnotations(lists) => Some(lists.clone()),
+                _ => None,
+            })
+    });
+    let lists = lists.unwrap_or_default();
+    let mark = env.interpreter.suspend_roots(std::iter::empty());
+    let mut parameters = Vec::with_capacity(count);
+    for _ in lists.len()..count {
+        let empty = java_lang_class::reference_array(env.heap, ANNOTATION, Vec::new());
+        keep(env, [&empty]);
+        parameters.push(empty);
+    }
+    if let Some(runtime) = &runtime {
+        for list in &lists {
+            let annotations = materialize_all(env, &runtime.class, list);
+            let array = java_lang_class::reference_array(env.heap, ANNOTATION, annotations);
+            keep(env, [&array]);
+            parameters.push(array);
+        }
+    }
+    env.interpreter.resume_roots(mark);
+    java_lang_class::reference_array(env.heap, "[Ljava/lang/annotation/Annotation;", parameters)
+}
+
+/// `Method.getDefaultValue`: the default of an annotation member, or
+/// `null`.
+pub fn default_value(
+    env: &mut NativeEnv,
+    class_name: &str,
+    method_name: &str,
+    descriptor: &str,
+) -> Option<HeapValue> {
+    let Some(runtime) = java_lang_class::loaded_class(env, class_name) else {
+        return Some(HeapValue::Null);
+    };
+    let element = Element::Method(method_name, descriptor);
+    let default =
+        attributes(&runtime.class, element)
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::AnnotationDefault(value) => Some(value),
+                _ => None,
+            });
+    match default {
+        Some(value) => decode(
+            env,
+            &runtime.class,
+            value,
+            split_method_descriptor(descriptor).1,
+        ),
+        None => Some(HeapValue::Null),
+    }
+}
+
+/// The annotation interface an annotation instance implements.
+pub fn annotation_type(heap: &Heap, annotation: &HeapValue) -> Option<String> {
+    let handler = handler_of(heap, annotation)?;
+    java_lang_class::class_name(heap, &field(heap, handler, "type"))
+}
+
+/// The handler behind an annotation proxy.
+fn handler_of(heap: &Heap, value: &HeapValue) -> Option<u64> {
+    let HeapValue::Object(proxy) = value else {
+        return None;
+    };
+    match field(heap, proxy.id, "h") {
+        HeapValue::Object(handler) if handler.class_name == HANDLER => Some(handler.id),
+        _ => None,
+    }
+}
+
+fn is_inherited(env: &mut NativeEnv, annotation_type: &str) -> bool {
+    let Some(runtime) = java_lang_class::loaded_class(env, annotation_type) else {
+        return false;
+    };
+    let class = &runtime.class;
+    visible(&class.attributes)
+        .iter()
+        .any(|annotation| class.get_utf8(annotation.type_index) == Some(INHERITED))
+}
+
+/// Roots values while natives build more of them: materializing can
+/// run class initializers, and with them the collector.
+fn keep<'a>(env: &mut NativeEnv, values: impl IntoIterator<Item = &'a HeapValue>) -> usize {
+    env.interpreter.suspend_roots(values)
+}
+
+fn materialize_all(
+    env: &mut NativeEnv,
+    class: &ClassFile,
+    annotations: &[Annotation],
+) -> Vec<HeapValue> {
+    let mark = env.interpreter.suspend_roots(std::iter::empty());
+    let mut instances = Vec::with_capacity(annotations.len());
+    for annotation in annotations {
+        if let Some(instance) = materialize(env, class, annotation) {
+            keep(env, [&instance]);
+            instances.push(instance);
+        }
+    }
+    env.interpreter.resume_roots(mark);
+    instances
+}
+
+/// An annotation instance: a proxy of the annotation interface whose
+/// handler holds every member value, defaults included. `None` when the
+/// type or an enum constant it names cannot be found.
+fn materialize(
+    env: &mut NativeEnv,
+    class: &ClassFile,
+    annotation: &Annotation,
+) -> Option<HeapValue> {
+    let descriptor = class.get_utf8(annotation.type_index)?;
+    let type_name = descriptor.strip_prefix('L')?.strip_suffix(';')?.to_string();
+    let annotation_class = java_lang_class::loaded_class(env, &type_name)?;
+    if annotation_class.class.access_flags & ACC_ANNOTATION == 0 {
+        return None;
+    }
+
+    let mark = env.interpreter.suspend_roots(std::iter::empty());
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    for method in &annotation_class.methods {
+        if method.access_flags & ACC_STATIC != 0 || method.name.starts_with('<') {
+            continue;
+        }
+        let explicit = annotation
+            .elements
+            .iter()
+            .find(|(name_index, _)| class.get_utf8(*name_index) == Some(method.name.as_str()));
+        let value = match explicit {
+            Some((_, value)) => decode(
+                env,
+                class,
+                value,
+                split_method_descriptor(&method.descriptor).1,
+            ),
+            None => default_value(env, &type_name, &method.name, &method.descriptor)
+                .filter(|value| !value.is_null()),
+        };
+        let Some(value) = value else {
+            env.interpreter.resume_roots(mark);
+            return None;
+        };
+        let name = env.heap.alloc_string(&method.name);
+        keep(env, [&name, &value]);
+        names.push(name);
+        values.push(value);
+    }
+
+    let type_mirror = env.interpreter.class_mirror(env.heap, &type_name);
+    let names = java_lang_class::reference_array(env.heap, "java/lang/String", names);
+    let values = java_lang_class::reference_array(env.heap, "java/lang/Object", values);
+    let handler = env.heap.alloc_object(HANDLER);
+    if let Some(real) = env.heap.get_mut(handler.id) {
+        real.set_field("type", type_mirror);
+        real.set_field("names", names);
+        real.set_field("values", values);
+    }
+    let handler = HeapValue::Object(handler);
+    keep(env, [&handler]);
+    let instance = java_lang_reflect_proxy::new_instance(env, &[type_name], handler);
+    env.interpreter.resume_roots(mark);
+    instance
+}
+
+/// The Java value of an `element_value` for a member of type
+/// `descriptor`. Primitives come back boxed, as `Method.invoke` would
+/// return them.
+fn decode(
+    env: &mut NativeEnv,
+    class: &ClassFile,
+    value: &ElementValue,
+    descriptor: &str,
+) -> Option<HeapValue> {
+    match value {
+        ElementValue::Const { tag, index } => {
+            let tag = char::from(*tag);
+            if tag == 's' {
+                return Some(env.heap.alloc_string(class.get_utf8(*index)?));
+            }
+            let raw = match class.constant(*index)? {
+                ConstantPoolEntry::Integer(v) => HeapValue::Int(*v),
+                ConstantPoolEntry::Long(v) => HeapValue::Long(*v),
+                ConstantPoolEntry::Float(v) => HeapValue::Float(*v),
+                ConstantPoolEntry::Double(v) => HeapValue::Double(*v),
+                _ => return None,
+            };
+            let box_class = java_lang_boxing::box_class(&tag.to_string())?;
+            Some(java_lang_boxing::box_value(env.heap, box_class, raw))
+        }
+        ElementValue::Enum {
+            type_name_index,
+            const_name_index,
+        } => {
+            let type_name = class
+                .get_utf8(*type_name_index)?
+                .strip_prefix('L')?
+                .strip_suffix(';')?
+                .to_string();
+            let constant = class.get_utf8(*const_name_index)?;
+            if !env
+                .interpreter
+                .ensure_class_initialized(env.loader, &type_name, env.heap)
+            {
+                return None;
+            }
+            env.loader
+                .get_static_field(&type_name, constant)
+                .filter(|value| !value.is_null())
+        }
+        ElementValue::Class(index) => {
+            let type_name = java_lang_class::descriptor_type_name(class.get_utf8(*index)?);
+            Some(env.interpreter.class_mirror(env.heap, type_name))
+        }
+        ElementValue::Annotation(annotation) => materialize(env, class, annotation),
+        ElementValue::Array(items) => {
+            let component = descriptor.strip_prefix('[')?;
+            let mark = env.interpreter.suspend_roots(std::iter::empty());
+            let values: Option<Vec<HeapValue>> = items
+                .iter()
+                .map(|item| {
+                    let value = decode(env, class, item, component)?;
+                    keep(env, [&value]);
+                    Some(value)
+                })
+                .collect();
+            env.interpreter.resume_roots(mark);
+            Some(new_array(env.heap, component, values?))
+        }
+    }
+}
+
+/// A `component[]` of `values`, unboxing them for a primitive component.
+fn new_array(heap: &mut Heap, component: &str, values: Vec<HeapValue>) -> HeapValue {
+    let element_type = match component {
+        "Z" => ArrayType::Boolean,
+        "B" => ArrayType::Byte,
+        "C" => ArrayType::Char,
+        "S" => ArrayType::Short,
+        "I" => ArrayType::Int,
+        "J" => ArrayType::Long,
+        "F" => ArrayType::Float,
+        "D" => ArrayType::Double,
+        _ => {
+            let component = java_lang_class::descriptor_type_name(component);
+            return java_lang_class::reference_array(heap, component, values);
+        }
+    };
+    let mut array = heap.alloc_array(values.len(), element_type);
+    array.content = values
+        .iter()
+        .map(|value| match value {
+            HeapValue::Object(boxed) => {
+                java_lang_boxing::unbox(heap, boxed).unwrap_or(HeapValue::Int(0))
+            }
+            other => other.clone(),
+        })
+        .collect();
+    if let Some(real) = heap.get_array_mut(array.id) {
+        real.content = array.content.clone();
+    }
+    HeapValue::Array(array)
+}
+
+/// `AnnotationInvocationHandler.invoke`: member values, and `equals`,
+/// `hashCode`, `toString` and `annotationType` as `Annotation` defines
+/// them.
+fn invoke(
+    env: &mut NativeEnv,
+    handler: u64,
+    proxy: &HeapValue,
+    method: &HeapValue,
+    args: &HeapValue,
+) -> Option<Option<HeapValue>> {
+    let name = java_lang_reflect::member_name(env.heap, method)?;
+    let args = match args {
+        HeapValue::Array(arr) => env
+            .heap
+            .get_array(arr.id)
+            .map(|real| real.content.clone())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let result = match (name.as_str(), args.as_slice()) {
+        ("equals", [other]) => {
+            let equal = equals(env, handler, proxy, other);
+            java_lang_boxing::box_value(env.heap, "java/lang/Boolean", HeapValue::Int(equal as i32))
+        }
+        ("hashCode", []) => {
+            let hash = hash_code(env.heap, handler);
+            java_lang_boxing::box_value(env.heap, "java/lang/Integer", HeapValue::Int(hash))
+        }
+        ("toString", []) => {
+            let text = to_string(env, handler);
+            env.heap.alloc_string(&text)
+        }
+        ("annotationType", []) => field(env.heap, handler, "type"),
+        (_, []) => {
+            let value = members(env.heap, handler)
+                .into_iter()
+                .find(|(member, _)| *member == name)
+                .map(|(_, value)| value)?;
+            // Callers may not change the arrays behind an annotation.
+            match &value {
+                HeapValue::Array(arr) => java_lang_object::clone_array(env.heap, arr)?,
+                _ => value,
+            }
+        }
+        _ => {
+            let message = format!("Too many parameters for an annotation method: {}", name);
+            env.interpreter
+                .throw_new(env.heap, "java/lang/AssertionError", Some(&message));
+            return Some(None);
+        }
+    };
+    Some(Some(result))
+}
+
+/// The member names and values a handler holds, in declaration order.
+fn members(heap: &Heap, handler: u64) -> Vec<(String, HeapValue)> {
+    let contents = |name: &str| match field(heap, handler, name) {
+        HeapValue::Array(arr) => heap
+            .get_array(arr.id)
+            .map(|real| real.content.clone())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    contents("names")
+        .iter()
+        .map(|name| heap.string_value(name).unwrap_or_default())
+        .zip(contents("values"))
+        .collect()
+}
+
+/// `Annotation.equals`: `other` implements the same annotation type and
+/// every member is equal. Other implementations are asked for their
+/// member values.
+fn equals(env: &mut NativeEnv, handler: u64, proxy: &HeapValue, other: &HeapValue) -> bool {
+    if java_lang_object::same_reference(proxy, other) {
+        return true;
+    }
+    let Some(type_name) = java_lang_class::class_name(env.heap, &field(env.heap, handler, "type"))
+    else {
+        return false;
+    };
+    let fits = java_lang_class::value_class(env.heap, other).is_some_and(|class| {
+        env.interpreter
+            .is_assignable(env.loader, &class, &type_name)
+    });
+    if !fits {
+        return false;
+    }
+    let mine = members(env.heap, handler);
+    if let Some(other_handler) = handler_of(env.heap, other) {
+        let theirs = members(env.heap, other_handler);
+        return mine
+            .iter()
+            .zip(&theirs)
+            .all(|((_, a), (_, b))| values_equal(env.heap, a, b));
+    }
+    let Some(runtime) = java_lang_class::loaded_class(env, &type_name) else {
+        return false;
+    };
+    let mark = keep(env, [other]);
+    let mut equal = true;
+    for (name, value) in &mine {
+        let Some(method) = runtime
+            .methods
+            .iter()
+            .find(|method| method.name == *name && method.access_flags & ACC_STATIC == 0)
+        else {
+            equal = false;
+            break;
+        };
+        let theirs = env.interpreter.invoke_dispatched(
+            env.loader,
+            env.heap,
+            &type_name,
+            name,
+            &method.descriptor,
+            other,
+            &[],
+        );
+        let ret = split_method_descriptor(&method.descriptor).1;
+        equal = match theirs {
+            Some(theirs) if env.interpreter.pending_exception().is_none() => {
+                let theirs = java_lang_reflect::box_result(env.heap, theirs, ret);
+                values_equal(env.heap, value, &theirs)
+            }
+            _ => false,
+        };
+        if !equal {
+            break;
+        }
+    }
+    env.interpreter.resume_roots(mark);
+    equal
+}
+
+fn values_equal(heap: &Heap, a: &HeapValue, b: &HeapValue) -> bool {
+    match (a, b) {
+        (HeapValue::Null, HeapValue::Null) => true,
+        (HeapValue::Int(x), HeapValue::Int(y)) => x == y,
+        (HeapValue::Long(x), HeapValue::Long(y)) => x == y,
+        // `Float.equals` and `Double.equals` compare bits.
+        (HeapValue::Float(x), HeapValue::Float(y)) => x.to_bits() == y.to_bits(),
+        (HeapValue::Double(x), HeapValue::Double(y)) => x.to_bits() == y.to_bits(),
+        (HeapValue::Object(x), HeapValue::Object(y)) => {
+            if x.id == y.id {
+                return true;
+            }
+            if java_lang_boxing::is_box_class(&x.class_name) {
+                return x.class_name == y.class_name
+                    && match (
+                        java_lang_boxing::unbox(heap, x),
+                        java_lang_boxing::unbox(heap, y),
+                    ) {
+                        (Some(p), Some(q)) => values_equal(heap, &p, &q),
+                        _ => false,
+                    };
+            }
+            if let (Some(s), Some(t)) = (heap.string_value(a), heap.string_value(b)) {
+                return s == t;
+            }
+            match (handler_of(heap, a), handler_of(heap, b)) {
+                (Some(p), Some(q)) => {
+                    let (mine, theirs) = (members(heap, p), members(heap, q));
+                    field_class(heap, p) == field_class(heap, q)
+                        && mine.len() == theirs.len()
+                        && mine
+                            .iter()
+                            .zip(&theirs)
+                            .all(|((_, m), (_, t))| values_equal(heap, m, t))
+                }
+                _ => false,
+            }
+        }
+        (HeapValue::Array(x), HeapValue::Array(y)) => {
+            let (Some(p), Some(q)) = (heap.get_array(x.id), heap.get_array(y.id)) else {
+                return false;
+            };
+            java_lang_object::array_class_name(p) == java_lang_object::array_class_name(q)
+                && p.content.len() == q.content.len()
+                && p.content
+                    .iter()
+                    .zip(&q.content)
+                    .all(|(m, t)| values_equal(heap, m, t))
+        }
+        _ => false,
+    }
+}
+
+fn field_class(heap: &Heap, handler: u64) -> Option<String> {
+    java_lang_class::class_name(heap, &field(heap, handler, "type"))
+}
+
+/// `Annotation.hashCode`: the sum over members of
+/// `(127 * name.hashCode()) ^ value.hashCode()`, arrays hashing as
+/// `Arrays.hashCode` does.
+fn hash_code(heap: &Heap, handler: u64) -> i32 {
+    members(heap, handler)
+        .iter()
+        .fold(0i32, |sum, (name, value)| {
+            let member = 127i32.wrapping_mul(string_hash(name)) ^ value_hash(heap, value, None);
+            sum.wrapping_add(member)
+        })
+}
+
+/// The hash of a member value; `element` is the primitive type of an
+/// array element, whose raw value is hashed as its box would be.
+fn value_hash(heap: &Heap, value: &HeapValue, element: Option<&str>) -> i32 {
+    match value {
+        HeapValue::Int(v) if element == Some("Z") => {
+            if *v != 0 {
+                1231
+            } else {
+                1237
+            }
+        }
+        HeapValue::Int(v) => *v,
+        HeapValue::Long(v) => (v ^ ((*v as u64) >> 32) as i64) as i32,
+        HeapValue::Float(v) => v.to_bits() as i32,
+        HeapValue::Double(v) => {
+            let bits = v.to_bits();
+            (bits ^ (bits >> 32)) as i32
+        }
+        HeapValue::Object(obj) if java_lang_boxing::is_box_class(&obj.class_name) => {
+            let primitive = java_lang_boxing::primitive_descriptor(&obj.class_name);
+            java_lang_boxing::unbox(heap, obj)
+                .map_or(0, |raw| value_hash(heap, &raw, Some(primitive)))
+        }
+        HeapValue::Object(_) => {
+            if let Some(text) = heap.string_value(value) {
+                return string_hash(&text);
+            }
+            match handler_of(heap, value) {
+                Some(handler) => hash_code(heap, handler),
+                None => java_lang_object::identity_hash(value),
+            }
+        }
+        HeapValue::Array(arr) => {
+            let Some(real) = heap.get_array(arr.id) else {
+                return 0;
+            };
+            let class = java_lang_object::array_class_name(real);
+            let element = class.strip_prefix('[').filter(|tag| tag.len() == 1);
+            real.content.iter().fold(1i32, |hash, item| {
+                hash.wrapping_mul(31)
+                    .wrapping_add(value_hash(heap, item, element))
+            })
+        }
+        _ => 0,
+    }
+}
+
+fn string_hash(text: &str) -> i32 {
+    text.encode_utf16()
+        .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32))
+}
+
+/// `Annotation.toString` in source form, e.g.
+/// `@app.Route(path="/users", methods={GET, POST})`. A lone `value`
+/// member is written without its name.
+fn to_string(env: &mut NativeEnv, handler: u64) -> String {
+    let type_name = field_class(env.heap, handler).unwrap_or_default();
+    let members = members(env.heap, handler);
+    let lone_value = members.len() == 1 && members[0].0 == "value";
+    let rendered: Vec<String> = members
+        .iter()
+        .map(|(name, value)| {
+            let text = value_string(env, value, None);
+            if lone_value {
+                text
+            } else {
+                format!("{}={}", name, text)
+            }
+        })
+        .collect();
+    format!("@{}({})", type_name.replace('/', "."), rendered.join(", "))
+}
+
+fn value_string(env: &mut NativeEnv, value: &HeapValue, element: Option<&str>) -> String {
+    match value {
+        HeapValue::Null => "null".to_string(),
+        HeapValue::Object(obj) if java_lang_boxing::is_box_class(&obj.class_name) => {
+            let primitive = java_lang_boxing::primitive_descriptor(&obj.class_name);
+            match java_lang_boxing::unbox(env.heap, obj) {
+                Some(raw) => value_string(env, &raw, Some(primitive)),
+                None => "null".to_string(),
+            }
+        }
+        HeapValue::Int(v) => match element {
+            Some("Z") => (*v != 0).to_string(),
+            Some("C") => format!("'{}'", quote(char::from_u32(*v as u32).unwrap_or('?'))),
+            Some("B") => format!("(byte)0x{:02x}", *v as u8),
+            _ => v.to_string(),
+        },
+        HeapValue::Long(v) => format!("{}L", v),
+        HeapValue::Float(v) => {
+            if v.is_nan() {
+                "0.0f/0.0f".to_string()
+            } else if v.is_infinite() {
+                format!("{}1.0f/0.0f", if *v < 0.0 { "-" } else { "" })
+            } else {
+                format!("{}f", java_lang_boxing::float_to_string(*v))
+            }
+        }
+        HeapValue::Double(v) => {
+            if v.is_nan() {
+                "0.0/0.0".to_string()
+            } else if v.is_infinite() {
+                format!("{}1.0/0.0", if *v < 0.0 { "-" } else { "" })
+            } else {
+                java_lang_boxing::double_to_string(*v)
+            }
+        }
+        HeapValue::Object(obj) => {
+            if let Some(text) = env.heap.string_value(value) {
+                return format!("\"{}\"", text.chars().map(quote).collect::<String>());
+            }
+            if obj.class_name == "java/lang/Class" {
+                let name = java_lang_class::class_name(env.heap, value).unwrap_or_default();
+                return format!("{}.class", canonical_name(&name));
+            }
+            if let Some(handler) = handler_of(env.heap, value) {
+                return to_string(env, handler);
+            }
+            if env
+                .interpreter
+                .is_subclass_of(env.loader, &obj.class_name, "java/lang/Enum")
+            {
+                if let Some(name) = java_lang_enum::constant_name(env.heap, value) {
+                    return name;
+                }
+            }
+            env.to_java_string(value)
+        }
+        HeapValue::Array(arr) => {
+            let Some(real) = env.heap.get_array(arr.id).cloned() else {
+                return "{}".to_string();
+            };
+            let class = java_lang_object::array_class_name(&real);
+            let element = class.strip_prefix('[').filter(|tag| tag.len() == 1);
+            let items: Vec<String> = real
+                .content
+                .iter()
+                .map(|item| value_string(env, item, element))
+                .collect();
+            format!("{{{}}}", items.join(", "))
+        }
+        HeapValue::String(text) => format!("\"{}\"", text.chars().map(quote).collect::<String>()),
+    }
+}
+
+/// A character as it would appear in a Java literal.
+fn quote(ch: char) -> String {
+    match ch {
+        '\u{8}' => "\\b".to_string(),
+        '\u{c}' => "\\f".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        '\'' => "\\'".to_string(),
+        '"' => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        ' '..='~' => ch.to_string(),
+        _ => {
+            let mut units = [0u16; 2];
+            ch.encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("\\u{:04x}", unit))
+                .collect()
+        }
+    }
+}
+
+/// The source spelling of a class: `java.util.Map.Entry`, `int[]`.
+fn canonical_name(class_name: &str) -> String {
+    match class_name.strip_prefix('[') {
+        Some(component) => format!(
+            "{}[]",
+            canonical_name(java_lang_class::descriptor_type_name(component))
+        ),
+        None => class_name.replace(['/', '$'], "."),
+    }
+}
+
+fn field(heap: &Heap, id: u64, name: &str) -> HeapValue {
+    heap.get(id)
+        .and_then(|obj| obj.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
@@ -0,0 +1,431 @@
+use crate::native::java_lang_class;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{Heap, HeapValue, ObjectRef};
+
+const BOX_CLASSES: [&str; 8] = [
+    "java/lang/Integer",
+    "java/lang/Long",
+    "java/lang/Short",
+    "java/lang/Byte",
+    "java/lang/Character",
+    "java/lang/Boolean",
+    "java/lang/Float",
+    "java/lang/Double",
+];
+
+/// `java.lang.Void`, which only holds `Void.TYPE`.
+pub const VOID: &str = "java/lang/Void";
+
+pub fn is_box_class(class_name: &str) -> bool {
+    BOX_CLASSES.contains(&class_name)
+}
+
+/// The descriptor of the primitive a box class wraps.
+pub fn primitive_descriptor(class_name: &str) -> &'static str {
+    match class_name {
+        "java/lang/Long" => "J",
+        "java/lang/Short" => "S",
+        "java/lang/Byte" => "B",
+        "java/lang/Character" => "C",
+        "java/lang/Boolean" => "Z",
+        "java/lang/Float" => "F",
+        "java/lang/Double" => "D",
+        _ => "I",
+    }
+}
+
+/// The box class for a primitive descriptor such as `I`; `None` for
+/// references and `V`.
+pub fn box_class(descriptor: &str) -> Option<&'static str> {
+    BOX_CLASSES
+        .into_iter()
+        .find(|class_name| primitive_descriptor(class_name) == descriptor)
+}
+
+/// A box class's `<clinit>`, and `Void`'s: `TYPE` holds the primitive's
+/// class, as in `Integer.TYPE == int.class`.
+pub fn initialize(env: &mut NativeEnv, class_name: &str) {
+    let primitive = match class_name {
+        VOID => "void",
+        _ => java_lang_class::descriptor_type_name(primitive_descriptor(class_name)),
+    };
+    let mirror = env.interpreter.class_mirror(env.heap, primitive);
+    env.loader.set_static_field(class_name, "TYPE", mirror);
+}
+
+/// The `Number` accessors of the numeric boxes.
+const NUMBER_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("intValue", "()I", |env, this, _| {
+        let v = int_value(&unbox_receiver(env, this)?);
+        Some(Some(HeapValue::Int(v)))
+    }),
+    ("longValue", "()J", |env, this, _| {
+        let v = as_long(&unbox_receiver(env, this)?);
+        Some(Some(HeapValue::Long(v)))
+    }),
+    ("floatValue", "()F", |env, this, _| {
+        let v = as_double(&unbox_receiver(env, this)?);
+        Some(Some(HeapValue::Float(v as f32)))
+    }),
+    ("doubleValue", "()D", |env, this, _| {
+        let v = as_double(&unbox_receiver(env, this)?);
+        Some(Some(HeapValue::Double(v)))
+    }),
+    ("shortValue", "()S", |env, this, _| {
+        let v = int_value(&unbox_receiver(env, this)?);
+        Some(Some(HeapValue::Int(v as i16 as i32)))
+    }),
+    ("byteValue", "()B", |env, this, _| {
+        let v = int_value(&unbox_receiver(env, this)?);
+        Some(Some(HeapValue::Int(v as i8 as i32)))
+    }),
+];
+
+const CHARACTER_METHODS: &[(&str, &str, BuiltinMethod)] =
+    &[("charValue", "()C", |env, this, _| {
+        let v = int_value(&unbox_receiver(env, this)?);
+        Some(Some(HeapValue::Int(v as u16 as i32)))
+    })];
+
+const BOOLEAN_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("booleanValue", "()Z", |env, this, _| {
+        Some(Some(HeapValue::Int(int_value(&unbox_receiver(env, this)?))))
+    }),
+    ("parseBoolean", "(Ljava/lang/String;)Z", |env, _, args| {
+        let text = env.heap.string_value(args.first()?).unwrap_or_default();
+        Some(Some(HeapValue::Int(
+            text.eq_ignore_ascii_case("true") as i32
+        )))
+    }),
+];
+
+const INTEGER_METHODS: &[(&str, &str, BuiltinMethod)] =
+    &[("parseInt", "(Ljava/lang/String;)I", |env, _, args| {
+        let text = env.heap.string_value(args.first()?);
+        let parsed = text.as_deref().and_then(parse_integer);
+        Some(
+            parsed
+                .map(HeapValue::Int)
+                .or_else(|| number_format(env, text)),
+        )
+    })];
+
+const LONG_METHODS: &[(&str, &str, BuiltinMethod)] =
+    &[("parseLong", "(Ljava/lang/String;)J", |env, _, args| {
+        let text = env.heap.string_value(args.first()?);
+        let parsed = text.as_deref().and_then(parse_integer);
+        Some(
+            parsed
+                .map(HeapValue::Long)
+                .or_else(|| number_format(env, text)),
+        )
+    })];
+
+const DOUBLE_METHODS: &[(&str, &str, BuiltinMethod)] =
+    &[("parseDouble", "(Ljava/lang/String;)D", |env, _, args| {
+        let Some(text) = env.heap.string_value(args.first()?) else {
+            env.interpreter
+                .throw_new(env.heap, "java/lang/NullPointerException", None);
+            return Some(None);
+        };
+        match text.trim().parse() {
+            Ok(value) => Some(Some(HeapValue::Double(value))),
+            Err(_) => Some(number_format(env, Some(text))),
+        }
+    })];
+
+/// Binds the box classes' natives. Every box has `valueOf`, a static
+/// `toString` of its primitive and the `Object` overrides; the numeric
+/// boxes also carry the `Number` accessors.
+pub fn register(registry: &mut NativeRegistry) {
+    for class_name in BOX_CLASSES {
+        let primitive = primitive_descriptor(class_name);
+        let value_of = format!("({})L{};", primitive, class_name);
+        registry.register_builtin(class_name, "valueOf", &value_of, move |env, _, args| {
+            let value = args.first()?.clone();
+            Some(Some(box_value(env.heap, class_name, value)))
+        });
+        let to_string = format!("({})Ljava/lang/String;", primitive);
+        registry.register_builtin(class_name, "toString", &to_string, move |env, _, args| {
+            let rendered = render(class_name, args.first()?);
+            Some(Some(env.heap.alloc_string(&rendered)))
+        });
+        registry.register_builtin(
+            class_name,
+            "toString",
+            "()Ljava/lang/String;",
+            move |env, this, _| {
+                let rendered = render(class_name, &unbox_receiver(env, this)?);
+                Some(Some(env.heap.alloc_string(&rendered)))
+            },
+        );
+        registry.register_builtin(class_name, "hashCode", "()I", move |env, this, _| {
+            let hash = box_hash(class_name, &unbox_receiver(env, this)?);
+            Some(Some(HeapValue::Int(hash)))
+        });
+        registry.register_all(class_name, &[("equals", "(Ljava/lang/Object;)Z", equals)]);
+        let methods = match class_name {
+            "java/lang/Character" => CHARACTER_METHODS,
+            "java/lang/Boolean" => BOOLEAN_METHODS,
+            _ => NUMBER_METHODS,
+        };
+        registry.register_all(class_name, methods);
+        let parse = match class_name {
+            "java/lang/Integer" => INTEGER_METHODS,
+            "java/lang/Long" => LONG_METHODS,
+            "java/lang/Double" => DOUBLE_METHODS,
+            _ => &[],
+        };
+        registry.register_all(class_name, parse);
+    }
+}
+
+/// The primitive a box receiver holds.
+fn unbox_receiver(env: &NativeEnv, receiver: Option<&HeapValue>) -> Option<HeapValue> {
+    match receiver? {
+        HeapValue::Object(obj) => unbox(env.heap, obj),
+        _ => None,
+    }
+}
+
+fn equals(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(HeapValue::Object(obj)) = receiver else {
+        return None;
+    };
+    let value = unbox(env.heap, obj)?;
+    let equal = match args.first()? {
+        HeapValue::Object(other) if other.class_name == obj.class_name => {
+            unbox(env.heap, other).is_some_and(|v| same_bits(&v, &value))
+        }
+        _ => false,
+    };
+    Some(Some(HeapValue::Int(equal as i32)))
+}
+
+/// `Integer.parseInt`/`Long.parseLong` accept an optional leading sign but
+/// no surrounding whitespace.
+fn parse_integer<T: std::str::FromStr>(text: &str) -> Option<T> {
+    if text.starts_with("+-") || text.starts_with("++") {
+        return None;
+    }
+    text.trim_start_matches('+').parse().ok()
+}
+
+/// Raises the `NumberFormatException` the parse methods throw for bad
+/// input.
+fn number_format(env: &mut NativeEnv, text: Option<String>) -> Option<HeapValue> {
+    let message = match text {
+        Some(text) => format!("For input string: \"{}\"", text),
+        None => "Cannot parse null string: null".to_string(),
+    };
+    env.interpreter
+        .throw_new(env.heap, "java/lang/NumberFormatException", Some(&message));
+    None
+}
+
+pub fn box_value(heap: &mut Heap, class_name: &str, value: HeapValue) -> HeapValue {
+    let value = match class_name {
+        "java/lang/Long" => HeapValue::Long(as_long(&value)),
+        "java/lang/Float" => HeapValue::Float(as_double(&value) as f32),
+        "java/lang/Double" => HeapValue::Double(as_double(&value)),
+        _ => HeapValue::Int(value.as_int()),
+    };
+    let mut obj = heap.alloc_object(class_name);
+    obj.set_field("value", value.clone());
+    if let Some(real) = heap.get_mut(obj.id) {
+        real.set_field("value", value);
+    }
+    HeapValue::Object(obj)
+}
+
+/// Primitive payload of a box object, `None` for anything that is not a box.
+pub fn unbox(heap: &Heap, obj: &ObjectRef) -> Option<HeapValue> {
+    if !is_box_class(&obj.class_name) {
+        return None;
+    }
+    heap.get(obj.id)
+        .and_then(|real| real.get_field("value"))
+        .or_else(|| obj.get_field("value"))
+        .cloned()
+}
+
+pub fn box_to_string(heap: &Heap, obj: &ObjectRef) -> Option<String> {
+    let value = unbox(heap, obj)?;
+    Some(render(&obj.class_name, &value))
+}
+
+fn render(class_name: &str, value: &HeapValue) -> String {
+    match class_name {
+        "java/lang/Boolean" => (value.as_int() != 0).to_string(),
+        "java/lang/Character" => char::from_u32(value.as_int() as u32 & 0xFFFF)
+            .unwrap_or('\u{FFFD}')
+            .to_string(),
+        "java/lang/Float" => float_to_string(as_double(value) as f32),
+        "java/lang/Double" => double_to_string(as_double(value)),
+        "java/lang/Long" => as_long(value).to_string(),
+        _ => value.as_int().to_string(),
+    }
+}
+
+fn int_value(value: &HeapValue) -> i32 {
+    match value {
+        HeapValue::Float(f) => *f as i32,
+        HeapValue::Double(d) => *d as i32,
+        other => as_long(other) as i32,
+    }
+}
+
+fn as_long(value: &HeapValue) -> i64 {
+    match value {
+        HeapValue::Float(f) => *f as i64,
+        HeapValue::Double(d) => *d as i64,
+        other => other.as_long(),
+    }
+}
+
+fn as_double(value: &HeapValue) -> f64 {
+    match value {
+        HeapValue::Int(v) => *v as f64,
+        HeapValue::Long(v) => *v as f64,
+        HeapValue::Float(v) => *v as f64,
+        HeapValue::Double(v) => *v,
+        _ => 0.0,
+    }
+}
+
+fn same_bits(a: &HeapValue, b: &HeapValue) -> bool {
+    match (a, b) {
+        (HeapValue::Float(x), HeapValue::Float(y)) => x.to_bits() == y.to_bits(),
+        (HeapValue::Double(x), HeapValue::Double(y)) => x.to_bits() == y.to_bits(),
+        (HeapValue::Long(x), HeapValue::Long(y)) => x == y,
+        (HeapValue::Int(x), HeapValue::Int(y)) => x == y,
+        _ => false,
+    }
+}
+
+fn box_hash(class_name: &str, value: &HeapValue) -> i32 {
+    match class_name {
+        "java/lang/Boolean" => {
+            if value.as_int() != 0 {
+                1231
+            } else {
+                1237
+            }
+        }
+        "java/lang/Long" => {
+            let v = as_long(value);
+            (v ^ (v >> 32)) as i32
+        }
+        "java/lang/Float" => (as_double(value) as f32).to_bits() as i32,
+        "java/lang/Double" => {
+            let bits = as_double(value).to_bits();
+            (bits ^ (bits >> 32)) as i32
+        }
+        _ => value.as_int(),
+    }
+}
+
+/// `Double.toString(double)`: plain notation for 10^-3 <= |d| < 10^7,
+/// computerized scientific notation otherwise, always with a fraction digit.
+/// A one-digit shortest form gives way to the closest two-digit decimal
+/// that still rounds to `d`, so `Double.MIN_VALUE` is `4.9E-324`.
+pub fn double_to_string(d: f64) -> String {
+    if d.is_nan() {
+        return "NaN".to_string();
+    }
+    if d.is_infinite() {
+        return if d > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if d == 0.0 {
+        return if d.is_sign_negative() { "-0.0" } else { "0.0" }.to_string();
+    }
+    let mut parts = shortest_digits(&format!("{:e}", d));
+    if parts.1.len() == 1 {
+        let two = format!("{:.1e}", d);
+        if two.parse::<f64>() == Ok(d) {
+            parts = shortest_digits(&two);
+        }
+    }
+    let (negative, digits, exponent) = parts;
+    java_decimal_layout(negative, digits.trim_end_matches('0'), exponent)
+}
+
+/// `Float.toString(float)`, using the shortest digits that identify the
+/// float rather than the widened double.
+pub fn float_to_string(f: f32) -> String {
+    if f.is_nan() {
+        return "NaN".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if f == 0.0 {
+        return if f.is_sign_negative() { "-0.0" } else { "0.0" }.to_string();
+    }
+    let mut parts = shortest_digits(&format!("{:e}", f));
+    if parts.1.len() == 1 {
+        let two = format!("{:.1e}", f);
+        if two.parse::<f32>() == Ok(f) {
+            parts = shortest_digits(&two);
+        }
+    }
+    let (negative, digits, exponent) = parts;
+    java_decimal_layout(negative, digits.trim_end_matches('0'), exponent)
+}
+
+/// Splits Rust's `{:e}` rendering ("-1.25e-3") into sign, significant
+/// digits and the decimal exponent of the first digit.
+pub fn shortest_digits(rendered: &str) -> (bool, String, i32) {
+    let (negative, rest) = match rendered.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, rendered),
+    };
+    let (mantissa, exponent) = rest.split_once('e').unwrap_or((rest, "0"));
+    let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+    (negative, digits, exponent.parse().unwrap_or(0))
+}
+
+fn java_decimal_layout(negative: bool, digits: &str, exponent: i32) -> String {
+    let mut out = String::new();
+    if negative {
+        out.push('-');
+    }
+    if (-3..7).contains(&exponent) {
+        if exponent < 0 {
+            out.push_str("0.");
+            for _ in 0..(-exponent - 1) {
+                out.push('0');
+            }
+            out.push_str(digits);
+        } else {
+            let int_len = exponent as usize + 1;
+            if digits.len() > int_len {
+                out.push_str(&digits[..int_len]);
+                out.push('.');
+                out.push_str(&digits[int_len..]);
+            } else {
+                out.push_str(digits);
+                for _ in digits.len()..int_len {
+                    out.push('0');
+                }
+                out.push_str(".0");
+            }
+        }
+    } else {
+        out.push_str(&digits[..1]);
+        out.push('.');
+        if digits.len() > 1 {
+            out.push_str(&digits[1..]);
+        } else {
+            out.push('0');
+        }
+        out.push('E');
+        out.push_str(&exponent.to_string());
+    }
+    out
+}
@@ -0,0 +1,925 @@
+use crate::bytecode::attributes::Attribute;
+use crate::exec::runtime_class::RuntimeClass;
+use crate::loader::class_loader::{APP_LOADER, BOOT_LOADER, PLATFORM_LOADER};
+use crate::native::java_lang_annotation::{self, Element};
+use crate::native::java_lang_classloader;
+use crate::native::java_lang_object::array_class_name;
+use crate::native::java_lang_reflect::{self, MemberKind};
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::{self, NativeEnv};
+use crate::runtime::heap::{Heap, HeapValue};
+use std::rc::Rc;
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_SUPER: u16 = 0x0020;
+const ACC_BRIDGE: u16 = 0x0040;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_ANNOTATION: u16 = 0x2000;
+
+/// Primitive types by descriptor character and source name.
+const PRIMITIVES: &[(&str, &str)] = &[
+    ("Z", "boolean"),
+    ("B", "byte"),
+    ("C", "char"),
+    ("S", "short"),
+    ("I", "int"),
+    ("J", "long"),
+    ("F", "float"),
+    ("D", "double"),
+    ("V", "void"),
+];
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    // javac reads this once in `<clinit>` into `$assertionsDisabled`.
+    ("desiredAssertionStatus", "()Z", |env, this, _| {
+        let name = this_class(env, this)?;
+        let enabled = env.interpreter.desired_assertion_status(&name);
+        Some(Some(HeapValue::Int(enabled as i32)))
+    }),
+    (
+        "forName",
+        "(Ljava/lang/String;)Ljava/lang/Class;",
+        |env, _, args| {
+            let caller = env.interpreter.caller_class().unwrap_or_default();
+            let loader = java_lang_classloader::class_loader_of(env, &caller);
+            Some(for_name(env, args.first()?, true, &loader))
+        },
+    ),
+    (
+        "forName",
+        "(Ljava/lang/String;ZLjava/lang/ClassLoader;)Ljava/lang/Class;",
+        |env, _, args| {
+            let initialize = args.get(1)?.as_int() != 0;
+            Some(for_name(env, args.first()?, initialize, args.get(2)?))
+        },
+    ),
+    ("getName", "()Ljava/lang/String;", |env, this, _| {
+        let name = this_class(env, this)?;
+        let binary_name = env.loader.symbolic_name(&name).replace('/', ".");
+        Some(Some(env.heap.alloc_string(&binary_name)))
+    }),
+    ("getSimpleName", "()Ljava/lang/String;", |env, this, _| {
+        let name = this_class(env, this)?;
+        let simple = simple_name(env, &name);
+        Some(Some(env.heap.alloc_string(&simple)))
+    }),
+    ("toString", "()Ljava/lang/String;", |env, this, _| {
+        let name = this_class(env, this)?;
+        let text = to_string(env, &name);
+        Some(Some(env.heap.alloc_string(&text)))
+    }),
+    ("getSuperclass", "()Ljava/lang/Class;", get_superclass),
+    ("getInterfaces", "()[Ljava/lang/Class;", get_interfaces),
+    ("getComponentType", "()Ljava/lang/Class;", |env, this, _| {
+        let name = this_class(env, this)?;
+        Some(Some(match name.strip_prefix('[') {
+            Some(component) => env
+                .interpreter
+                .class_mirror(env.heap, descriptor_type_name(component)),
+            None => HeapValue::Null,
+        }))
+    }),
+    (
+        "getClassLoader",
+        "()Ljava/lang/ClassLoader;",
+        |env, this, _| {
+            let name = this_class(env, this)?;
+            Some(Some(java_lang_classloader::class_loader_of(env, &name)))
+        },
+    ),
+    ("getModifiers", "()I", |env, this, _| {
+        let name = this_class(env, this)?;
+        Some(Some(HeapValue::Int(modifiers(env, &name) as i32)))
+    }),
+    ("isInstance", "(Ljava/lang/Object;)Z", is_instance),
+    (
+        "isAssignableFrom",
+        "(Ljava/lang/Class;)Z",
+        is_assignable_from,
+    ),
+    ("isInterface", "()Z", |env, this, _| {
+        let name = this_class(env, this)?;
+        let interface = modifiers(env, &name) & ACC_INTERFACE != 0;
+        Some(Some(HeapValue::Int(interface as i32)))
+    }),
+    ("isAnnotation", "()Z", |env, this, _| {
+        let name = this_class(env, this)?;
+        let annotation = modifiers(env, &name) & ACC_ANNOTATION != 0;
+        Some(Some(HeapValue::Int(annotation as i32)))
+    }),
+    ("isArray", "()Z", |env, this, _| {
+        let name = this_class(env, this)?;
+        Some(Some(HeapValue::Int(name.starts_with('[') as i32)))
+    }),
+    ("isPrimitive", "()Z", |env, this, _| {
+        let name = this_class(env, this)?;
+        Some(Some(HeapValue::Int(is_primitive(&name) as i32)))
+    }),
+    (
+        "getDeclaredFields",
+        "()[Ljava/lang/reflect/Field;",
+        |env, this, _| {
+            let fields = declared_fields(env, &this_class(env, this)?);
+            Some(Some(member_array(env, MemberKind::Field, &fields)))
+        },
+    ),
+    (
+        "getDeclaredMethods",
+        "()[Ljava/lang/reflect/Method;",
+        |env, this, _| {
+            let name = this_class(env, this)?;
+            let methods = declared_methods(env, &name, MemberKind::Method);
+            Some(Some(member_array(env, MemberKind::Method, &methods)))
+        },
+    ),
+    (
+        "getDeclaredConstructors",
+        "()[Ljava/lang/reflect/Constructor;",
+        |env, this, _| {
+            let name = this_class(env, this)?;
+            let constructors = declared_methods(env, &name, MemberKind::Constructor);
+            Some(Some(member_array(
+                env,
+                MemberKind::Constructor,
+                &constructors,
+            )))
+        },
+    ),
+    (
+        "getDeclaredField",
+        "(Ljava/lang/String;)Ljava/lang/reflect/Field;",
+        |env, this, args| {
+            let fields = declared_fields(env, &this_class(env, this)?);
+            Some(find_field(env, fields, args.first()?))
+        },
+    ),
+    (
+        "getDeclaredMethod",
+        "(Ljava/lang/String;[Ljava/lang/Class;)Ljava/lang/reflect/Method;",
+        |env, this, args| {
+            let name = this_class(env, this)?;
+            let candidates = declared_methods(env, &name, MemberKind::Method);
+            get_method(env, &name, args, candidates)
+        },
+    ),
+    (
+        "getDeclaredConstructor",
+        "([Ljava/lang/Class;)Ljava/lang/reflect/Constructor;",
+        |env, this, args| {
+            let name = this_class(env, this)?;
+            let candidates = declared_methods(env, &name, MemberKind::Constructor);
+            get_constructor(env, &name, args, candidates)
+        },
+    ),
+    (
+        "getFields",
+        "()[Ljava/lang/reflect/Field;",
+        |env, this, _| {
+            let fields = public_fields(env, &this_class(env, this)?);
+            Some(Some(member_array(env, MemberKind::Field, &fields)))
+        },
+    ),
+    (
+        "getMethods",
+        "()[Ljava/lang/reflect/Method;",
+        |env, this, _| {
+            let methods = public_methods(env, &this_class(env, this)?);
+            Some(Some(member_array(env, MemberKind::Method, &methods)))
+        },
+    ),
+    (
+        "getConstructors",
+        "()[Ljava/lang/reflect/Constructor;",
+        |env, this, _| {
+            let name = this_class(env, this)?;
+            let mut constructors = declared_methods(env, &name, MemberKind::Constructor);
+            constructors.retain(MemberInfo::is_public);
+            Some(Some(member_array(
+                env,
+                MemberKind::Constructor,
+                &constructors,
+            )))
+        },
+    ),
+    (
+        "getField",
+        "(Ljava/lang/String;)Ljava/lang/reflect/Field;",
+        |env, this, args| {
+            let fields = public_fields(env, &this_class(env, this)?);
+            Some(find_field(env, fields, args.first()?))
+        },
+    ),
+    (
+        "getMethod",
+        "(Ljava/lang/String;[Ljava/lang/Class;)Ljava/lang/reflect/Method;",
+        |env, this, args| {
+            let name = this_class(env, this)?;
+            let candidates = public_methods(env, &name);
+            get_method(env, &name, args, candidates)
+        },
+    ),
+    (
+        "getConstructor",
+        "([Ljava/lang/Class;)Ljava/lang/reflect/Constructor;",
+        |env, this, args| {
+            let name = this_class(env, this)?;
+            let mut candidates = declared_methods(env, &name, MemberKind::Constructor);
+            candidates.retain(MemberInfo::is_public);
+            get_constructor(env, &name, args, candidates)
+        },
+    ),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/lang/Class", METHODS);
+    java_lang_annotation::register_annotated_element(registry, "java/lang/Class", annotations);
+}
+
+/// The class a `Class` receiver stands for.
+fn this_class(env: &NativeEnv, receiver: Option<&HeapValue>) -> Option<String> {
+    class_name(env.heap, receiver?)
+}
+
+fn get_superclass(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let name = this_class(env, receiver)?;
+    let parent = if is_primitive(&name) || modifiers(env, &name) & ACC_INTERFACE != 0 {
+        None
+    } else if name.starts_with('[') {
+        Some("java/lang/Object".to_string())
+    } else {
+        env.interpreter.superclass_of(env.loader, &name)
+    };
+    Some(Some(match parent {
+        Some(parent) => env.interpreter.class_mirror(env.heap, &parent),
+        None => HeapValue::Null,
+    }))
+}
+
+fn get_interfaces(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let name = this_class(env, receiver)?;
+    let interfaces = interfaces(env, &name);
+    let mirrors = interfaces
+        .iter()
+        .map(|interface| env.interpreter.class_mirror(env.heap, interface))
+        .collect();
+    Some(Some(reference_array(env.heap, "java/lang/Class", mirrors)))
+}
+
+fn is_instance(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let name = this_class(env, receiver)?;
+    let instance = match value_class(env.heap, args.first()?) {
+        Some(class) if !is_primitive(&name) => {
+            env.interpreter.is_assignable(env.loader, &class, &name)
+        }
+        _ => false,
+    };
+    Some(Some(HeapValue::Int(instance as i32)))
+}
+
+fn is_assignable_from(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let name = this_class(env, receiver)?;
+    let Some(other) = class_name(env.heap, args.first()?) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    let assignable = if is_primitive(&name) || is_primitive(&other) {
+        name == other
+    } else {
+        env.interpreter.is_assignable(env.loader, &other, &name)
+    };
+    Some(Some(HeapValue::Int(assignable as i32)))
+}
+
+/// An array of `Field`, `Method` or `Constructor` objects for `members`.
+fn member_array(env: &mut NativeEnv, kind: MemberKind, members: &[MemberInfo]) -> HeapValue {
+    let objects = to_objects(env, kind, members);
+    reference_array(env.heap, kind.class_name(), objects)
+}
+
+/// `getMethod` and `getDeclaredMethod` over their candidates.
+fn get_method(
+    env: &mut NativeEnv,
+    class_name: &str,
+    args: &[HeapValue],
+    candidates: Vec<MemberInfo>,
+) -> Option<Option<HeapValue>> {
+    let Some(method_name) = env.heap.string_value(args.first()?) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    Some(find_method(
+        env,
+        class_name,
+        MemberKind::Method,
+        &method_name,
+        args.get(1)?,
+        candidates,
+    ))
+}
+
+/// `getConstructor` and `getDeclaredConstructor` over their candidates.
+fn get_constructor(
+    env: &mut NativeEnv,
+    class_name: &str,
+    args: &[HeapValue],
+    candidates: Vec<MemberInfo>,
+) -> Option<Option<HeapValue>> {
+    Some(find_method(
+        env,
+        class_name,
+        MemberKind::Constructor,
+        "<init>",
+        args.first()?,
+        candidates,
+    ))
+}
+
+/// A class's annotations for `AnnotatedElement`.
+fn annotations(env: &mut NativeEnv, class: &HeapValue, declared: bool) -> Option<Vec<HeapValue>> {
+    let name = class_name(env.heap, class)?;
+    Some(match declared {
+        true => java_lang_annotation::declared_annotations(env, &name, Element::Class),
+        false => java_lang_annotation::class_annotations(env, &name),
+    })
+}
+
+/// A new `Class` object for `class_name`. The interpreter keeps one per
+/// class; use `Interpreter::class_mirror` to get it.
+pub fn new_mirror(heap: &mut Heap, class_name: &str) -> HeapValue {
+    let obj = heap.alloc_object("java/lang/Class");
+    if let Some(real) = heap.get_mut(obj.id) {
+        real.set_field("name", HeapValue::String(class_name.to_string()));
+    }
+    HeapValue::Object(obj)
+}
+
+/// Name of the class a `Class` object stands for: the internal name, an
+/// array descriptor such as `[I`, or a primitive type such as `int`.
+pub fn class_name(heap: &Heap, value: &HeapValue) -> Option<String> {
+    let HeapValue::Object(obj) = value else {
+        return None;
+    };
+    match heap.get(obj.id)?.get_field("name")? {
+        HeapValue::String(name) if obj.class_name == "java/lang/Class" => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// The class of a reference value, as `getClass` reports it.
+pub fn value_class(heap: &Heap, value: &HeapValue) -> Option<String> {
+    match value {
+        HeapValue::Object(obj) => Some(obj.class_name.clone()),
+        HeapValue::Array(arr) => Some(array_class_name(heap.get_array(arr.id).unwrap_or(arr))),
+        HeapValue::String(_) => Some("java/lang/String".to_string()),
+        _ => None,
+    }
+}
+
+pub fn is_primitive(class_name: &str) -> bool {
+    PRIMITIVES.iter().any(|(_, name)| *name == class_name)
+}
+
+/// The class name a field descriptor denotes: `I` is `int`,
+/// `Ljava/lang/String;` is `java/lang/String`, and arrays keep their
+/// descriptor.
+pub fn descriptor_type_name(descriptor: &str) -> &str {
+    if let Some((_, name)) = PRIMITIVES.iter().find(|(tag, _)| *tag == descriptor) {
+        return name;
+    }
+    descriptor
+        .strip_prefix('L')
+        .and_then(|rest| rest.strip_suffix(';'))
+        .unwrap_or(descriptor)
+}
+
+/// The field descriptor of a class name, the inverse of
+/// `descriptor_type_name`.
+pub fn type_descriptor(class_name: &str) -> String {
+    if let Some((tag, _)) = PRIMITIVES.iter().find(|(_, name)| *name == class_name) {
+        return tag.to_string();
+    }
+    if class_name.starts_with('[') {
+        return class_name.to_string();
+    }
+    format!("L{};", class_name)
+}
+
+/// The parameter descriptors and return descriptor of a method
+/// descriptor.
+pub fn split_method_descriptor(descriptor: &str) -> (Vec<&str>, &str) {
+    let (params, ret) = descriptor
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .unwrap_or(("", descriptor));
+    let mut types = Vec::new();
+    let mut rest = params;
+    while !rest.is_empty() {
+        let dims = rest.len() - rest.trim_start_matches('[').len();
+        let end = match rest[dims..].chars().next() {
+            Some('L') => rest.find(';').map_or(rest.len(), |end| end + 1),
+            Some(_) => dims + 1,
+            None => rest.len(),
+        };
+        types.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    (types, ret)
+}
+
+/// The Java source spelling of a field descriptor, such as `int[]` or
+/// `java.lang.String`.
+pub fn source_type_name(descriptor: &str) -> String {
+    match descriptor.strip_prefix('[') {
+        Some(component) => format!("{}[]", source_type_name(component)),
+        None => descriptor_type_name(descriptor).replace('/', "."),
+    }
+}
+
+/// The loaded class file behind a class name; `None` for builtin,
+/// array and primitive classes.
+pub fn loaded_class(env: &mut NativeEnv, class_name: &str) -> Option<Rc<RuntimeClass>> {
+    if class_name.starts_with('[')
+        || is_primitive(class_name)
+        || native::is_builtin_class(class_name)
+    {
+        return None;
+    }
+    env.interpreter.runtime_class(env.loader, class_name).ok()
+}
+
+/// `Class.getModifiers`: the access flags of a class, taken from its
+/// `InnerClasses` entry for a nested class.
+pub fn modifiers(env: &mut NativeEnv, class_name: &str) -> u16 {
+    if is_primitive(class_name) {
+        return ACC_PUBLIC | ACC_FINAL | ACC_ABSTRACT;
+    }
+    if let Some(component) = class_name.strip_prefix('[') {
+        let component = modifiers(env, descriptor_type_name(component));
+        return (component & ACC_PUBLIC) | ACC_FINAL | ACC_ABSTRACT;
+    }
+    if native::is_builtin_interface(class_name) {
+        return ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT;
+    }
+    let Some(runtime) = loaded_class(env, class_name) else {
+        return ACC_PUBLIC;
+    };
+    let class = &runtime.class;
+    let class_name = env.loader.symbolic_name(class_name);
+    if let Some(Attribute::InnerClasses(entries)) = class.attribute("InnerClasses") {
+        let entry = entries
+            .iter()
+            .find(|entry| class.get_class_name(entry.inner_class_info_index) == Some(&class_name));
+        if let Some(entry) = entry {
+            return entry.inner_class_access_flags & !ACC_SUPER;
+        }
+    }
+    class.access_flags & !ACC_SUPER & !ACC_STATIC
+}
+
+/// `Class.toString`: `class java.lang.String`, `interface java.lang.Runnable`
+/// or `int`.
+pub fn to_string(env: &mut NativeEnv, class_name: &str) -> String {
+    let kind = if is_primitive(class_name) {
+        ""
+    } else if modifiers(env, class_name) & ACC_INTERFACE != 0 {
+        "interface "
+    } else {
+        "class "
+    };
+    let class_name = env.loader.symbolic_name(class_name);
+    format!("{}{}", kind, class_name.replace('/', "."))
+}
+
+/// `Class.getSimpleName`: `String[]`, `Entry` for `Map$Entry`, and an
+/// empty name for anonymous classes.
+pub fn simple_name(env: &mut NativeEnv, class_name: &str) -> String {
+    if let Some(component) = class_name.strip_prefix('[') {
+        return format!("{}[]", simple_name(env, descriptor_type_name(component)));
+    }
+    let runtime = loaded_class(env, class_name);
+    let class_name = env.loader.symbolic_name(class_name);
+    if let Some(runtime) = runtime {
+        let class = &runtime.class;
+        if let Some(Attribute::InnerClasses(entries)) = class.attribute("InnerClasses") {
+            let entry = entries.iter().find(|entry| {
+                class.get_class_name(entry.inner_class_info_index) == Some(&class_name)
+            });
+            if let Some(entry) = entry {
+                // Anonymous classes have no simple name.
+                return class
+                    .get_utf8(entry.inner_name_index)
+                    .unwrap_or("")
+                    .to_string();
+            }
+        }
+    }
+    class_name
+        .rsplit_once('/')
+        .map_or(&*class_name, |(_, simple)| simple)
+        .to_string()
+}
+
+fn interfaces(env: &mut NativeEnv, class_name: &str) -> Vec<String> {
+    if class_name.starts_with('[') {
+        return vec![
+            "java/lang/Cloneable".to_string(),
+            "java/io/Serializable".to_string(),
+        ];
+    }
+    match loaded_class(env, class_name) {
+        Some(runtime) => runtime.interfaces.clone(),
+        None => Vec::new(),
+    }
+}
+
+/// `Class.forName`: loads a class by binary name through `loader`, the
+/// boot loader when null, initializing it if asked. Unknown names throw
+/// `ClassNotFoundException`.
+pub fn for_name(
+    env: &mut NativeEnv,
+    name: &HeapValue,
+    initialize: bool,
+    loader: &HeapValue,
+) -> Option<HeapValue> {
+    let Some(binary_name) = env.heap.string_value(name) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return None;
+    };
+    let internal = binary_name.replace('.', "/");
+    let class_name = match java_lang_classloader::loader_id(env, loader) {
+        // No binary name has a `[` past the dimensions of an array.
+        _ if binary_name.contains('/') || binary_name.trim_start_matches('[').contains('[') => None,
+        Some(APP_LOADER) => system_class(env, &internal).then(|| internal.clone()),
+        Some(BOOT_LOADER | PLATFORM_LOADER) | None => {
+            boot_class(&internal).then(|| internal.clone())
+        }
+        Some(id) => {
+            let found = env
+                .interpreter
+                .resolve_class_name(env.loader, env.heap, id, &internal);
+            if env.interpreter.pending_exception().is_some() {
+                return None;
+            }
+            found
+        }
+    };
+    let Some(class_name) = class_name else {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/ClassNotFoundException",
+            Some(&binary_name),
+        );
+        return None;
+    };
+    if initialize
+        && !class_name.starts_with('[')
+        && !env
+            .interpreter
+            .ensure_class_initialized(env.loader, &class_name, env.heap)
+    {
+        return None;
+    }
+    if env.interpreter.pending_exception().is_some() {
+        return None;
+    }
+    Some(env.interpreter.class_mirror(env.heap, &class_name))
+}
+
+/// Whether the system loaders can load a class, or an array class of
+/// one, from the builtin classes or the search path.
+pub fn system_class(env: &mut NativeEnv, internal: &str) -> bool {
+    match array_element(internal) {
+        Some(Some(element)) => class_exists(env, element),
+        Some(None) => true,
+        None => class_exists(env, internal),
+    }
+}
+
+/// Whether the boot loader, which holds only the builtin classes, can
+/// load a class.
+fn boot_class(internal: &str) -> bool {
+    match array_element(internal) {
+        Some(Some(element)) => native::is_builtin_class(element),
+        Some(None) => true,
+        None => native::is_builtin_class(internal),
+    }
+}
+
+/// For an array class, its element class, or `None` for a primitive
+/// element; `None` for anything else. Malformed array names have no
+/// element at all.
+fn array_element(internal: &str) -> Option<Option<&str>> {
+    let element = internal.trim_start_matches('[');
+    if element.len() == internal.len() {
+        return None;
+    }
+    match element
+        .strip_prefix('L')
+        .and_then(|rest| rest.strip_suffix(';'))
+    {
+        Some(class) => Some(Some(class)),
+        None if element.len() == 1 && "ZBCSIJFD".contains(element) => Some(None),
+        None => Some(Some("")),
+    }
+}
+
+fn class_exists(env: &mut NativeEnv, class_name: &str) -> bool {
+    !class_name.is_empty()
+        && (native::is_builtin_class(class_name)
+            || env
+                .interpreter
+                .runtime_class(env.loader, class_name)
+                .is_ok())
+}
+
+/// A field, method or constructor as reflection reports it.
+struct MemberInfo {
+    declaring: String,
+    name: String,
+    descriptor: String,
+    access_flags: u16,
+}
+
+impl MemberInfo {
+    /// The parameter part of a method descriptor, e.g. `(ILjava/lang/String;)`.
+    fn parameters(&self) -> &str {
+        self.descriptor
+            .find(')')
+            .map_or(&*self.descriptor, |end| &self.descriptor[..=end])
+    }
+
+    fn is_public(&self) -> bool {
+        self.access_flags & ACC_PUBLIC != 0
+    }
+
+    fn to_object(&self, env: &mut NativeEnv, kind: MemberKind) -> HeapValue {
+        java_lang_reflect::new_member(
+            env,
+            kind,
+            &self.declaring,
+            &self.name,
+            &self.descriptor,
+            self.access_flags,
+        )
+    }
+}
+
+fn to_objects(env: &mut NativeEnv, kind: MemberKind, members: &[MemberInfo]) -> Vec<HeapValue> {
+    members
+        .iter()
+        .map(|member| member.to_object(env, kind))
+        .collect()
+}
+
+fn is_interface(runtime: &RuntimeClass) -> bool {
+    runtime.class.access_flags & ACC_INTERFACE != 0
+}
+
+fn declared_fields(env: &mut NativeEnv, class_name: &str) -> Vec<MemberInfo> {
+    let Some(runtime) = loaded_class(env, class_name) else {
+        return Vec::new();
+    };
+    let class = &runtime.class;
+    class
+        .fields
+        .iter()
+        .map(|field| MemberInfo {
+            declaring: class_name.to_string(),
+            name: class.get_utf8(field.name_index).unwrap_or("").to_string(),
+            descriptor: class
+                .get_utf8(field.descriptor_index)
+                .unwrap_or("")
+                .to_string(),
+            access_flags: field.access_flags,
+        })
+        .collect()
+}
+
+/// The declared methods, or the constructors, of a class in declaration
+/// order.
+fn declared_methods(env: &mut NativeEnv, class_name: &str, kind: MemberKind) -> Vec<MemberInfo> {
+    let Some(runtime) = loaded_class(env, class_name) else {
+        return Vec::new();
+    };
+    runtime
+        .methods
+        .iter()
+        .filter(|method| match kind {
+            MemberKind::Constructor => method.name == "<init>",
+            _ => !method.name.starts_with('<'),
+        })
+        .map(|method| MemberInfo {
+            declaring: class_name.to_string(),
+            name: method.name.clone(),
+            descriptor: method.descriptor.clone(),
+            access_flags: method.access_flags,
+        })
+        .collect()
+}
+
+/// `getFields`: the public fields of a class and its supertypes, in the
+/// order field resolution searches them: the class, its superinterfaces,
+/// then its superclass.
+fn public_fields(env: &mut NativeEnv, class_name: &str) -> Vec<MemberInfo> {
+    let mut fields = Vec::new();
+    let mut visited = Vec::new();
+    collect_public_fields(env, class_name, &mut visited, &mut fields);
+    fields
+}
+
+fn collect_public_fields(
+    env: &mut NativeEnv,
+    class_name: &str,
+    visited: &mut Vec<String>,
+    fields: &mut Vec<MemberInfo>,
+) {
+    if visited.iter().any(|seen| seen == class_name) {
+        return;
+    }
+    visited.push(class_name.to_string());
+    let Some(runtime) = loaded_class(env, class_name) else {
+        return;
+    };
+    fields.extend(
+        declared_fields(env, class_name)
+            .into_iter()
+            .filter(MemberInfo::is_public),
+    );
+    for interface in &runtime.interfaces {
+        collect_public_fields(env, interface, visited, fields);
+    }
+    if let Some(superclass) = runtime
+        .superclass
+        .as_deref()
+        .filter(|_| !is_interface(&runtime))
+    {
+        collect_public_fields(env, superclass, visited, fields);
+    }
+}
+
+/// `getMethods`: the public methods of a class and its supertypes. The
+/// superclass chain is searched before the superinterfaces, and a method
+/// hides those with its name and parameter types further up. Static
+/// methods of superinterfaces are not inherited.
+fn public_methods(env: &mut NativeEnv, class_name: &str) -> Vec<MemberInfo> {
+    let mut types = Vec::new();
+    let mut current = Some(class_name.to_string());
+    while let Some(name) = current {
+        let Some(runtime) = loaded_class(env, &name) else {
+            break;
+        };
+        current = match is_interface(&runtime) {
+            true => None,
+            false => runtime.superclass.clone(),
+        };
+        types.push(runtime);
+    }
+    let mut next = 0;
+    while next < types.len() {
+        for interface in types[next].interfaces.clone() {
+            if types.iter().any(|runtime| runtime.name == interface) {
+                continue;
+            }
+            if let Some(runtime) = loaded_class(env, &interface) {
+                types.push(runtime);
+            }
+        }
+        next += 1;
+    }
+
+    let mut methods: Vec<MemberInfo> = Vec::new();
+    for (index, runtime) in types.iter().enumerate() {
+        let inherited_interface = index > 0 && is_interface(runtime);
+        let mut declared = declared_methods(env, &runtime.name, MemberKind::Method);
+        // A covariant override's bridge has the same parameters; the
+        // real method is the one to report.
+        declared.sort_by_key(|method| method.access_flags & ACC_BRIDGE != 0);
+        for method in declared {
+            if !method.is_public() || inherited_interface && method.access_flags & ACC_STATIC != 0 {
+                continue;
+            }
+            let hidden = methods
+                .iter()
+                .any(|seen| seen.name == method.name && seen.parameters() == method.parameters());
+            if !hidden {
+                methods.push(method);
+            }
+        }
+    }
+    methods
+}
+
+/// The field named by a `getField` or `getDeclaredField` argument, or
+/// `NoSuchFieldException`.
+fn find_field(
+    env: &mut NativeEnv,
+    fields: Vec<MemberInfo>,
+    field_name: &HeapValue,
+) -> Option<HeapValue> {
+    let Some(field_name) = env.heap.string_value(field_name) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return None;
+    };
+    match fields.iter().find(|field| field.name == field_name) {
+        Some(field) => Some(field.to_object(env, MemberKind::Field)),
+        None => {
+            env.interpreter.throw_new(
+                env.heap,
+                "java/lang/NoSuchFieldException",
+                Some(&field_name),
+            );
+            None
+        }
+    }
+}
+
+/// `getMethod`, `getDeclaredMethod` and their constructor forms: the
+/// first of `candidates` with these parameter types, or
+/// `NoSuchMethodException`.
+fn find_method(
+    env: &mut NativeEnv,
+    declaring: &str,
+    kind: MemberKind,
+    method_name: &str,
+    parameter_types: &HeapValue,
+    candidates: Vec<MemberInfo>,
+) -> Option<HeapValue> {
+    let types: Vec<Option<String>> = match parameter_types {
+        HeapValue::Array(arr) => env
+            .heap
+            .get_array(arr.id)
+            .map(|real| {
+                real.content
+                    .iter()
+                    .map(|value| class_name(env.heap, value))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let parameters = types
+        .iter()
+        .map(|name| name.as_deref().map(type_descriptor))
+        .collect::<Option<Vec<_>>>()
+        .map(|params| format!("({})", params.concat()));
+    let found = parameters.and_then(|parameters| {
+        candidates
+            .iter()
+            .find(|method| method.name == method_name && method.parameters() == parameters)
+    });
+    match found {
+        Some(method) => Some(method.to_object(env, kind)),
+        None => {
+            let params = types
+                .iter()
+                .map(|name| {
+                    name.as_deref()
+                        .map_or("null".to_string(), |n| n.replace('/', "."))
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            let message = format!(
+                "{}.{}({})",
+                declaring.replace('/', "."),
+                method_name,
+                params
+            );
+            env.interpreter
+                .throw_new(env.heap, "java/lang/NoSuchMethodException", Some(&message));
+            None
+        }
+    }
+}
+
+/// A new `component[]` holding `values`.
+pub fn reference_array(heap: &mut Heap, component: &str, values: Vec<HeapValue>) -> HeapValue {
+    let mut arr = heap.alloc_reference_array(values.len(), component);
+    arr.content = values;
+    if let Some(real) = heap.get_array_mut(arr.id) {
+        real.content = arr.content.clone();
+    }
+    HeapValue::Array(arr)
+}
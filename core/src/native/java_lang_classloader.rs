@@ -0,0 +1,428 @@
+use crate::loader::class_loader::{
+    self, ClassLoader, Linkage, LoadError, LoaderId, APP_LOADER, BOOT_LOADER, PLATFORM_LOADER,
+};
+use crate::native::java_lang_class;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::{self, NativeEnv};
+use crate::runtime::heap::HeapValue;
+
+pub const CLASS_LOADER: &str = "java/lang/ClassLoader";
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("<init>", "()V", |env, this, _| {
+        let parent = system_loader(env);
+        init(env, this, HeapValue::Null, parent)
+    }),
+    ("<init>", "(Ljava/lang/ClassLoader;)V", |env, this, args| {
+        init(env, this, HeapValue::Null, args.first()?.clone())
+    }),
+    (
+        "<init>",
+        "(Ljava/lang/String;Ljava/lang/ClassLoader;)V",
+        |env, this, args| init(env, this, args.first()?.clone(), args.get(1)?.clone()),
+    ),
+    (
+        "getSystemClassLoader",
+        "()Ljava/lang/ClassLoader;",
+        |env, _, _| Some(Some(system_loader(env))),
+    ),
+    (
+        "getPlatformClassLoader",
+        "()Ljava/lang/ClassLoader;",
+        |env, _, _| Some(Some(platform_loader(env))),
+    ),
+    ("getParent", "()Ljava/lang/ClassLoader;", |env, this, _| {
+        Some(Some(field(env, &this_loader(this)?, "parent")))
+    }),
+    ("getName", "()Ljava/lang/String;", |env, this, _| {
+        Some(Some(field(env, &this_loader(this)?, "name")))
+    }),
+    (
+        "loadClass",
+        "(Ljava/lang/String;)Ljava/lang/Class;",
+        |env, this, args| {
+            let this = this_loader(this)?;
+            let args = [args.first()?.clone(), HeapValue::Int(0)];
+            let loaded = env.invoke_virtual(&this, "loadClass", LOAD_CLASS, &args);
+            Some(loaded.filter(|_| env.interpreter.pending_exception().is_none()))
+        },
+    ),
+    ("loadClass", LOAD_CLASS, |env, this, args| {
+        Some(load_class(env, &this_loader(this)?, args.first()?))
+    }),
+    (
+        "findClass",
+        "(Ljava/lang/String;)Ljava/lang/Class;",
+        find_class,
+    ),
+    (
+        "findLoadedClass",
+        "(Ljava/lang/String;)Ljava/lang/Class;",
+        |env, this, args| {
+            let this = this_loader(this)?;
+            let name = env.heap.string_value(args.first()?)?.replace('.', "/");
+            Some(Some(match find_loaded(env, &this, &name) {
+                Some(found) => env.interpreter.class_mirror(env.heap, &found),
+                None => HeapValue::Null,
+            }))
+        },
+    ),
+    (
+        "defineClass",
+        "(Ljava/lang/String;[BII)Ljava/lang/Class;",
+        define_class_native,
+    ),
+    ("resolveClass", "(Ljava/lang/Class;)V", |env, this, args| {
+        this_loader(this)?;
+        if args.first()?.is_null() {
+            env.interpreter
+                .throw_new(env.heap, "java/lang/NullPointerException", None);
+        }
+        Some(None)
+    }),
+];
+
+const LOAD_CLASS: &str = "(Ljava/lang/String;Z)Ljava/lang/Class;";
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all(CLASS_LOADER, METHODS);
+}
+
+/// A `ClassLoader` receiver.
+fn this_loader(receiver: Option<&HeapValue>) -> Option<HeapValue> {
+    match receiver? {
+        this @ HeapValue::Object(_) => Some(this.clone()),
+        _ => None,
+    }
+}
+
+/// The `ClassLoader` constructors, which register the new loader.
+fn init(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    name: HeapValue,
+    parent: HeapValue,
+) -> Option<Option<HeapValue>> {
+    let this = this_loader(receiver)?;
+    let HeapValue::Object(this_ref) = &this else {
+        return None;
+    };
+    let id = env.loader.register_loader(this.clone());
+    let real = env.heap.get_mut(this_ref.id)?;
+    real.set_field("name", name);
+    real.set_field("parent", parent);
+    real.set_field("loaderId", HeapValue::Int(id as i32));
+    Some(None)
+}
+
+fn find_class(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let this = this_loader(receiver)?;
+    let Some(name) = env.heap.string_value(args.first()?) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    let internal = name.replace('.', "/");
+    if loader_id(env, &this) == Some(APP_LOADER)
+        && !name.contains(['/', '['])
+        && java_lang_class::system_class(env, &internal)
+    {
+        return Some(Some(env.interpreter.class_mirror(env.heap, &internal)));
+    }
+    env.interpreter
+        .throw_new(env.heap, "java/lang/ClassNotFoundException", Some(&name));
+    Some(None)
+}
+
+/// `defineClass(String, byte[], int, int)`.
+fn define_class_native(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let this = this_loader(receiver)?;
+    let name = env.heap.string_value(args.first()?);
+    let HeapValue::Array(array) = args.get(1)? else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    let (offset, length) = (args.get(2)?.as_int(), args.get(3)?.as_int());
+    let content = &env.heap.get_array(array.id)?.content;
+    let end = offset.checked_add(length).unwrap_or(-1);
+    if offset < 0 || length < 0 || end as usize > content.len() {
+        let len = content.len();
+        env.interpreter.throw_array_index(env.heap, end, len);
+        return Some(None);
+    }
+    let bytes: Vec<u8> = content[offset as usize..end as usize]
+        .iter()
+        .map(|byte| byte.as_int() as u8)
+        .collect();
+    let defined = match loader_id(env, &this) {
+        Some(id) if !class_loader::is_builtin_loader(id) => {
+            define_user_class(env, id, name.as_deref(), &bytes)
+        }
+        _ => define_class(env, name.as_deref(), &bytes),
+    };
+    Some(defined.map(|defined| env.interpreter.class_mirror(env.heap, &defined)))
+}
+
+/// `ClassLoader.<clinit>`: creates the platform loader, which sees only the
+/// classes the VM provides, and the application loader below it, which
+/// `getSystemClassLoader` returns and which stands for the search path.
+pub fn initialize(env: &mut NativeEnv) {
+    if env.loader.get_static_field(CLASS_LOADER, "scl").is_some() {
+        return;
+    }
+    let mut parent = HeapValue::Null;
+    let loaders = [
+        ("platform", "platform", PLATFORM_LOADER),
+        ("app", "scl", APP_LOADER),
+    ];
+    for (name, field_name, id) in loaders {
+        let loader = HeapValue::Object(env.heap.alloc_object(CLASS_LOADER));
+        let name = env.heap.alloc_string(name);
+        if let HeapValue::Object(obj) = &loader {
+            if let Some(real) = env.heap.get_mut(obj.id) {
+                real.set_field("name", name);
+                real.set_field("parent", parent);
+                real.set_field("loaderId", HeapValue::Int(id as i32));
+            }
+        }
+        env.loader
+            .set_static_field(CLASS_LOADER, field_name, loader.clone());
+        parent = loader;
+    }
+}
+
+pub fn system_loader(env: &mut NativeEnv) -> HeapValue {
+    if let Some(loader) = env.loader.get_static_field(CLASS_LOADER, "scl") {
+        return loader;
+    }
+    initialize(env);
+    system_loader(env)
+}
+
+fn platform_loader(env: &mut NativeEnv) -> HeapValue {
+    system_loader(env);
+    env.loader
+        .get_static_field(CLASS_LOADER, "platform")
+        .unwrap_or(HeapValue::Null)
+}
+
+/// The id of a loader object; `None` for null, the boot loader.
+pub fn loader_id(env: &mut NativeEnv, loader: &HeapValue) -> Option<LoaderId> {
+    match field(env, loader, "loaderId") {
+        HeapValue::Int(id) => Some(id as usize),
+        _ => None,
+    }
+}
+
+/// `Class.getClassLoader`: null for the classes the VM provides itself,
+/// the defining loader for the rest.
+pub fn class_loader_of(env: &mut NativeEnv, class_name: &str) -> HeapValue {
+    let element = match class_name.strip_prefix('[') {
+        Some(_) => {
+            let element = class_name.trim_start_matches('[');
+            match element.strip_prefix('L') {
+                Some(rest) => rest.strip_suffix(';').unwrap_or(rest),
+                // A primitive descriptor such as the `I` of `[I`.
+                None => return HeapValue::Null,
+            }
+        }
+        None => class_name,
+    };
+    if java_lang_class::is_primitive(element) || native::is_builtin_class(element) {
+        return HeapValue::Null;
+    }
+    match env.loader.defining_loader(element) {
+        BOOT_LOADER => HeapValue::Null,
+        PLATFORM_LOADER => platform_loader(env),
+        APP_LOADER => system_loader(env),
+        id => env
+            .loader
+            .loader_object(id)
+            .cloned()
+            .unwrap_or(HeapValue::Null),
+    }
+}
+
+/// `loadClass(String, boolean)`: the loaded class if there is one, else
+/// the parent's, or a builtin class for a loader without a parent, else
+/// whatever `findClass` finds.
+fn load_class(env: &mut NativeEnv, this: &HeapValue, name: &HeapValue) -> Option<HeapValue> {
+    let Some(binary_name) = env.heap.string_value(name) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return None;
+    };
+    let internal = binary_name.replace('.', "/");
+    if let Some(found) = find_loaded(env, this, &internal) {
+        return Some(env.interpreter.class_mirror(env.heap, &found));
+    }
+    let parent = field(env, this, "parent");
+    if parent.is_null() {
+        if native::is_builtin_class(&internal) {
+            return Some(env.interpreter.class_mirror(env.heap, &internal));
+        }
+    } else {
+        let args = [name.clone(), HeapValue::Int(0)];
+        let found = env.invoke_virtual(&parent, "loadClass", LOAD_CLASS, &args);
+        match env.interpreter.pending_exception() {
+            None => return found,
+            Some(HeapValue::Object(exception))
+                if env.interpreter.is_subclass_of(
+                    env.loader,
+                    &exception.class_name,
+                    "java/lang/ClassNotFoundException",
+                ) =>
+            {
+                env.interpreter.take_pending_exception();
+            }
+            Some(_) => return None,
+        }
+    }
+    let found = env.invoke_virtual(
+        this,
+        "findClass",
+        "(Ljava/lang/String;)Ljava/lang/Class;",
+        std::slice::from_ref(name),
+    );
+    found.filter(|_| env.interpreter.pending_exception().is_none())
+}
+
+/// `findLoadedClass`: the class `loader` has already loaded by this name.
+fn find_loaded(env: &mut NativeEnv, loader: &HeapValue, internal: &str) -> Option<String> {
+    match loader_id(env, loader)? {
+        BOOT_LOADER | PLATFORM_LOADER => None,
+        id => env.loader.find_loaded(id, internal),
+    }
+}
+
+fn field(env: &mut NativeEnv, object: &HeapValue, name: &str) -> HeapValue {
+    let HeapValue::Object(obj) = object else {
+        return HeapValue::Null;
+    };
+    env.heap
+        .get(obj.id)
+        .and_then(|real| real.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
+
+/// Defines a class from its class file bytes, throwing what
+/// `ClassLoader.defineClass` throws on failure. Returns the internal name.
+pub fn define_class(env: &mut NativeEnv, name: Option<&str>, bytes: &[u8]) -> Option<String> {
+    match env.loader.define_class_bytes(name, bytes) {
+        Ok(class_file) => class_file
+            .get_class_name(class_file.this_class)
+            .map(str::to_string),
+        Err(error) => {
+            throw_load_error(env, &error);
+            None
+        }
+    }
+}
+
+/// Defines a class in the namespace of user-defined loader `loader`,
+/// resolving its superclass and interfaces through the loader first.
+/// Returns the runtime name.
+fn define_user_class(
+    env: &mut NativeEnv,
+    loader: LoaderId,
+    name: Option<&str>,
+    bytes: &[u8],
+) -> Option<String> {
+    if !permitted_name(env, &name.unwrap_or_default().replace('.', "/")) {
+        return None;
+    }
+    let class_file = match ClassLoader::parse_class_bytes(name, bytes) {
+        Ok(class_file) => class_file,
+        Err(error) => {
+            throw_load_error(env, &error);
+            return None;
+        }
+    };
+    let class_name = class_file
+        .get_class_name(class_file.this_class)
+        .unwrap_or_default();
+    if !permitted_name(env, class_name) {
+        return None;
+    }
+    let superclass = match class_file.get_class_name(class_file.super_class) {
+        Some(superclass) => Some(resolve_supertype(env, loader, superclass)?),
+        None => None,
+    };
+    let mut interfaces = Vec::new();
+    for index in &class_file.interfaces {
+        let interface = class_file.get_class_name(*index).unwrap_or_default();
+        interfaces.push(resolve_supertype(env, loader, interface)?);
+    }
+    let linkage = Linkage {
+        loader,
+        superclass,
+        interfaces,
+    };
+    match env.loader.define_user_class(class_file.clone(), linkage) {
+        Ok(runtime) => Some(runtime),
+        Err(error) => {
+            throw_load_error(env, &error);
+            None
+        }
+    }
+}
+
+/// Only the VM defines classes in `java.*`; anything else throws
+/// `SecurityException`.
+fn permitted_name(env: &mut NativeEnv, class_name: &str) -> bool {
+    if !class_name.starts_with("java/") {
+        return true;
+    }
+    let package = class_name
+        .rsplit_once('/')
+        .map_or("", |(package, _)| package);
+    let message = format!("Prohibited package name: {}", package.replace('/', "."));
+    env.interpreter
+        .throw_new(env.heap, "java/lang/SecurityException", Some(&message));
+    false
+}
+
+/// Loads a supertype of a class being defined; one the loader cannot
+/// find is a `NoClassDefFoundError`.
+fn resolve_supertype(env: &mut NativeEnv, loader: LoaderId, class_name: &str) -> Option<String> {
+    let found = env
+        .interpreter
+        .resolve_class_name(env.loader, env.heap, loader, class_name);
+    if found.is_none() {
+        if let Some(HeapValue::Object(exception)) = env.interpreter.pending_exception() {
+            if exception.class_name != "java/lang/ClassNotFoundException" {
+                return None;
+            }
+            env.interpreter.take_pending_exception();
+        }
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NoClassDefFoundError", Some(class_name));
+    }
+    found
+}
+
+fn throw_load_error(env: &mut NativeEnv, error: &LoadError) {
+    let class_name = match error {
+        LoadError::NotFound(_) | LoadError::WrongName { .. } => "java/lang/NoClassDefFoundError",
+        LoadError::Format(_) => "java/lang/ClassFormatError",
+        LoadError::Verify(_) => "java/lang/VerifyError",
+        LoadError::Duplicate(_) | LoadError::Constraint(_) => "java/lang/LinkageError",
+        LoadError::Redefinition(_) => "java/lang/UnsupportedOperationException",
+    };
+    let message = match error {
+        LoadError::Format(e) => e.to_string(),
+        _ => error.to_string(),
+    };
+    env.interpreter
+        .throw_new(env.heap, class_name, Some(&message));
+}
@@ -0,0 +1,151 @@
+use crate::native::java_lang_class;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{Heap, HeapValue};
+
+const ENUM: &str = "java/lang/Enum";
+const ACC_ENUM: u16 = 0x4000;
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("<init>", "(Ljava/lang/String;I)V", |env, this, args| {
+        let real = env.heap.get_mut(this_id(this)?)?;
+        real.set_field("name", args.first()?.clone());
+        real.set_field("ordinal", args.get(1)?.clone());
+        Some(None)
+    }),
+    ("name", "()Ljava/lang/String;", |env, this, _| {
+        Some(Some(field(env.heap, this_id(this)?, "name")))
+    }),
+    ("toString", "()Ljava/lang/String;", |env, this, _| {
+        Some(Some(field(env.heap, this_id(this)?, "name")))
+    }),
+    ("ordinal", "()I", |env, this, _| {
+        Some(Some(field(env.heap, this_id(this)?, "ordinal")))
+    }),
+    ("compareTo", "(Ljava/lang/Enum;)I", compare_to),
+    ("compareTo", "(Ljava/lang/Object;)I", compare_to),
+    (
+        "getDeclaringClass",
+        "()Ljava/lang/Class;",
+        |env, this, _| {
+            let Some(HeapValue::Object(this)) = this else {
+                return None;
+            };
+            let class_name = declaring_class(env, &this.class_name);
+            Some(Some(env.interpreter.class_mirror(env.heap, &class_name)))
+        },
+    ),
+    (
+        "valueOf",
+        "(Ljava/lang/Class;Ljava/lang/String;)Ljava/lang/Enum;",
+        |env, _, args| Some(value_of(env, args.first()?, args.get(1)?)),
+    ),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all(ENUM, METHODS);
+}
+
+fn this_id(receiver: Option<&HeapValue>) -> Option<u64> {
+    match receiver? {
+        HeapValue::Object(this) => Some(this.id),
+        _ => None,
+    }
+}
+
+fn compare_to(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(HeapValue::Object(this)) = receiver else {
+        return None;
+    };
+    let other = match args.first()? {
+        HeapValue::Object(other) => other,
+        _ => {
+            env.interpreter
+                .throw_new(env.heap, "java/lang/NullPointerException", None);
+            return Some(None);
+        }
+    };
+    let mine = declaring_class(env, &this.class_name);
+    let theirs = declaring_class(env, &other.class_name);
+    if mine != theirs {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/ClassCastException", None);
+        return Some(None);
+    }
+    let ordinal = field(env.heap, this.id, "ordinal").as_int();
+    let other_ordinal = field(env.heap, other.id, "ordinal").as_int();
+    Some(Some(HeapValue::Int(ordinal - other_ordinal)))
+}
+
+/// The name of an enum constant.
+pub fn constant_name(heap: &Heap, value: &HeapValue) -> Option<String> {
+    let HeapValue::Object(obj) = value else {
+        return None;
+    };
+    heap.string_value(&field(heap, obj.id, "name"))
+}
+
+/// The enum type of a constant; constants with a body are instances of
+/// an anonymous subclass.
+fn declaring_class(env: &mut NativeEnv, class_name: &str) -> String {
+    match env.interpreter.superclass_of(env.loader, class_name) {
+        Some(parent) if parent != ENUM => parent,
+        _ => class_name.to_string(),
+    }
+}
+
+/// `Enum.valueOf`: the constant of `enum_class` called `name`.
+fn value_of(env: &mut NativeEnv, enum_class: &HeapValue, name: &HeapValue) -> Option<HeapValue> {
+    let Some(class_name) = java_lang_class::class_name(env.heap, enum_class) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return None;
+    };
+    let Some(name) = env.heap.string_value(name) else {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/NullPointerException",
+            Some("Name is null"),
+        );
+        return None;
+    };
+    let is_constant = java_lang_class::loaded_class(env, &class_name).is_some_and(|runtime| {
+        runtime.class.fields.iter().any(|field| {
+            field.access_flags & ACC_ENUM != 0
+                && runtime.class.get_utf8(field.name_index) == Some(name.as_str())
+        })
+    });
+    if is_constant
+        && env
+            .interpreter
+            .ensure_class_initialized(env.loader, &class_name, env.heap)
+    {
+        if let Some(constant) = env.loader.get_static_field(&class_name, &name) {
+            return Some(constant);
+        }
+    }
+    if env.interpreter.pending_exception().is_none() {
+        let message = format!(
+            "No enum constant {}.{}",
+            class_name.replace(['/', '$'], "."),
+            name
+        );
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some(&message),
+        );
+    }
+    None
+}
+
+fn field(heap: &Heap, id: u64, name: &str) -> HeapValue {
+    heap.get(id)
+        .and_then(|obj| obj.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
@@ -0,0 +1,2670 @@
+use crate::bytecode::parser::ConstantPoolEntry;
+use crate::exec::interpreter::Interpreter;
+use crate::exec::runtime_class::RuntimeClass;
+use crate::native::java_lang_class::{
+    self, descriptor_type_name, split_method_descriptor, type_descriptor,
+};
+use crate::native::java_lang_reflect::{self, Member, MemberKind};
+use crate::native::registry::{BuiltinMethod, NativeMethod, NativeRegistry};
+use crate::native::{
+    self, java_lang_boxing, java_lang_invoke_varhandle, java_lang_object, java_lang_throwable,
+    NativeEnv,
+};
+use crate::runtime::heap::{ArrayType, Heap, HeapValue};
+use std::rc::Rc;
+
+pub const METHOD_HANDLE: &str = "java/lang/invoke/MethodHandle";
+pub const METHOD_TYPE: &str = "java/lang/invoke/MethodType";
+const METHOD_HANDLES: &str = "java/lang/invoke/MethodHandles";
+const LOOKUP: &str = "java/lang/invoke/MethodHandles$Lookup";
+const CONSTANT_BOOTSTRAPS: &str = "java/lang/invoke/ConstantBootstraps";
+pub const WRONG_METHOD_TYPE: &str = "java/lang/invoke/WrongMethodTypeException";
+const OBJECT: &str = "Ljava/lang/Object;";
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_PROTECTED: u16 = 0x0004;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_VARARGS: u16 = 0x0080;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+
+/// JVMS reference kinds, as in `CONSTANT_MethodHandle`.
+pub const REF_GET_FIELD: u8 = 1;
+pub const REF_GET_STATIC: u8 = 2;
+pub const REF_PUT_FIELD: u8 = 3;
+pub const REF_PUT_STATIC: u8 = 4;
+pub const REF_INVOKE_VIRTUAL: u8 = 5;
+pub const REF_INVOKE_STATIC: u8 = 6;
+pub const REF_INVOKE_SPECIAL: u8 = 7;
+pub const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+pub const REF_INVOKE_INTERFACE: u8 = 9;
+
+const REF_KIND_NAMES: [&str; 10] = [
+    "",
+    "getField",
+    "getStatic",
+    "putField",
+    "putStatic",
+    "invokeVirtual",
+    "invokeStatic",
+    "invokeSpecial",
+    "newInvokeSpecial",
+    "invokeInterface",
+];
+
+const METHOD_TYPE_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    (
+        "methodType",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        |env, _, args| method_type(env, args, |_, _| Some(Vec::new())),
+    ),
+    (
+        "methodType",
+        "(Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        |env, _, args| {
+            method_type(env, args, |env, args| {
+                let params = single_array(env.heap, args.get(1)?);
+                class_descriptors(env, &params)
+            })
+        },
+    ),
+    (
+        "methodType",
+        "(Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        |env, _, args| method_type(env, args, |env, args| class_descriptors(env, args.get(1)?)),
+    ),
+    (
+        "methodType",
+        "(Ljava/lang/Class;Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        |env, _, args| {
+            method_type(env, args, |env, args| {
+                let first = single_array(env.heap, args.get(1)?);
+                let first = class_descriptors(env, &first);
+                let rest = class_descriptors(env, args.get(2)?);
+                first.zip(rest).map(|(mut first, rest)| {
+                    first.extend(rest);
+                    first
+                })
+            })
+        },
+    ),
+    (
+        "methodType",
+        "(Ljava/lang/Class;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodType;",
+        |env, _, args| {
+            method_type(env, args, |env, args| {
+                let other = method_type_descriptor(env.heap, args.get(1)?)?;
+                let (params, _) = split_method_descriptor(&other);
+                Some(params.iter().map(|param| param.to_string()).collect())
+            })
+        },
+    ),
+    (
+        "genericMethodType",
+        "(I)Ljava/lang/invoke/MethodType;",
+        |env, _, args| {
+            let count = args.first()?.as_int().max(0) as usize;
+            let descriptor = method_descriptor(&vec![OBJECT; count], OBJECT);
+            Some(Some(new_method_type(env.heap, &descriptor)))
+        },
+    ),
+    (
+        "fromMethodDescriptorString",
+        "(Ljava/lang/String;Ljava/lang/ClassLoader;)Ljava/lang/invoke/MethodType;",
+        from_method_descriptor_string,
+    ),
+    ("returnType", "()Ljava/lang/Class;", |env, this, _| {
+        let this = this_type(env, this)?;
+        let (_, ret) = split_method_descriptor(&this);
+        Some(Some(mirror(env, ret)))
+    }),
+    ("parameterType", "(I)Ljava/lang/Class;", parameter_type),
+    ("parameterCount", "()I", |env, this, _| {
+        let this = this_type(env, this)?;
+        let (params, _) = split_method_descriptor(&this);
+        Some(Some(HeapValue::Int(params.len() as i32)))
+    }),
+    ("parameterArray", "()[Ljava/lang/Class;", |env, this, _| {
+        let this = this_type(env, this)?;
+        let (params, _) = split_method_descriptor(&this);
+        let mirrors = params.iter().map(|param| mirror(env, param)).collect();
+        Some(Some(java_lang_class::reference_array(
+            env.heap,
+            "java/lang/Class",
+            mirrors,
+        )))
+    }),
+    (
+        "changeReturnType",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        |env, this, args| {
+            let this = this_type(env, this)?;
+            let (params, _) = split_method_descriptor(&this);
+            let Some(ret) = class_descriptor(env, args.first()?) else {
+                return Some(None);
+            };
+            changed_type(env, &params, &ret)
+        },
+    ),
+    (
+        "changeParameterType",
+        "(ILjava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        change_parameter_type,
+    ),
+    (
+        "appendParameterTypes",
+        "([Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        |env, this, args| {
+            let this = this_type(env, this)?;
+            let (params, _) = split_method_descriptor(&this);
+            insert_parameter_types(env, &this, params.len() as i32, args.first()?)
+        },
+    ),
+    (
+        "insertParameterTypes",
+        "(I[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        |env, this, args| {
+            let this = this_type(env, this)?;
+            insert_parameter_types(env, &this, args.first()?.as_int(), args.get(1)?)
+        },
+    ),
+    (
+        "dropParameterTypes",
+        "(II)Ljava/lang/invoke/MethodType;",
+        drop_parameter_types,
+    ),
+    (
+        "erase",
+        "()Ljava/lang/invoke/MethodType;",
+        |env, this, _| {
+            let this = this_type(env, this)?;
+            erased_type(env, &this, false)
+        },
+    ),
+    (
+        "generic",
+        "()Ljava/lang/invoke/MethodType;",
+        |env, this, _| {
+            let this = this_type(env, this)?;
+            erased_type(env, &this, true)
+        },
+    ),
+    (
+        "toMethodDescriptorString",
+        "()Ljava/lang/String;",
+        |env, this, _| {
+            let this = this_type(env, this)?;
+            Some(Some(env.heap.alloc_string(&this)))
+        },
+    ),
+    ("toString", "()Ljava/lang/String;", |env, this, _| {
+        let this = this_type(env, this)?;
+        let text = type_string(env, &this);
+        Some(Some(env.heap.alloc_string(&text)))
+    }),
+    ("equals", "(Ljava/lang/Object;)Z", |env, this, args| {
+        let this = this_type(env, this)?;
+        let other = method_type_descriptor(env.heap, args.first()?);
+        let equal = other.as_deref() == Some(this.as_str());
+        Some(Some(HeapValue::Int(equal as i32)))
+    }),
+    ("hashCode", "()I", |env, this, _| {
+        let this = this_type(env, this)?;
+        Some(Some(HeapValue::Int(string_hash(&this))))
+    }),
+];
+
+const METHOD_HANDLES_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    (
+        "lookup",
+        "()Ljava/lang/invoke/MethodHandles$Lookup;",
+        |env, _, _| {
+            let caller = env.interpreter.caller_class()?;
+            Some(Some(new_lookup(env, &caller, true)))
+        },
+    ),
+    (
+        "publicLookup",
+        "()Ljava/lang/invoke/MethodHandles$Lookup;",
+        |env, _, _| Some(Some(new_lookup(env, "java/lang/Object", false))),
+    ),
+    (
+        "privateLookupIn",
+        "(Ljava/lang/Class;Ljava/lang/invoke/MethodHandles$Lookup;)Ljava/lang/invoke/MethodHandles$Lookup;",
+        |env, _, args| {
+            let Some(target) = java_lang_class::class_name(env.heap, args.first()?) else {
+                return Some(null_pointer(env));
+            };
+            if Lookup::read(env.heap, args.get(1)?).is_none() {
+                return Some(null_pointer(env));
+            }
+            Some(Some(new_lookup(env, &target, true)))
+        },
+    ),
+    (
+        "constant",
+        "(Ljava/lang/Class;Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;",
+        constant,
+    ),
+    (
+        "identity",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        identity,
+    ),
+    (
+        "insertArguments",
+        "(Ljava/lang/invoke/MethodHandle;I[Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;",
+        |env, _, args| {
+            let target = args.first()?.clone();
+            let position = args.get(1)?.as_int();
+            let values = contents(env.heap, args.get(2)?);
+            Some(insert_arguments(env, target, position, values))
+        },
+    ),
+    (
+        "dropArguments",
+        "(Ljava/lang/invoke/MethodHandle;I[Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        drop_arguments,
+    ),
+    (
+        "filterReturnValue",
+        "(Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodHandle;)Ljava/lang/invoke/MethodHandle;",
+        filter_return_value,
+    ),
+    (
+        "filterArguments",
+        "(Ljava/lang/invoke/MethodHandle;I[Ljava/lang/invoke/MethodHandle;)Ljava/lang/invoke/MethodHandle;",
+        filter_arguments,
+    ),
+    (
+        "guardWithTest",
+        "(Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodHandle;)Ljava/lang/invoke/MethodHandle;",
+        guard_with_test,
+    ),
+    (
+        "permuteArguments",
+        "(Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;[I)Ljava/lang/invoke/MethodHandle;",
+        permute_arguments,
+    ),
+    (
+        "arrayElementGetter",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, _, args| {
+            array_handle(env, args, "arrayGet", |array, component| {
+                format!("({}I){}", array, component)
+            })
+        },
+    ),
+    (
+        "arrayElementSetter",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, _, args| {
+            array_handle(env, args, "arraySet", |array, component| {
+                format!("({}I{})V", array, component)
+            })
+        },
+    ),
+    (
+        "arrayLength",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, _, args| array_handle(env, args, "arrayLength", |array, _| format!("({})I", array)),
+    ),
+    (
+        "arrayElementVarHandle",
+        "(Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, _, args| {
+            let Some(array_class) = java_lang_class::class_name(env.heap, args.first()?) else {
+                return Some(null_pointer(env));
+            };
+            if !array_class.starts_with('[') {
+                illegal_argument(
+                    env,
+                    &format!("not an array class: {}", array_class.replace('/', ".")),
+                );
+                return Some(None);
+            }
+            Some(Some(java_lang_invoke_varhandle::new_array_handle(
+                env,
+                &array_class,
+            )))
+        },
+    ),
+];
+
+const LOOKUP_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("lookupClass", "()Ljava/lang/Class;", |env, this, _| {
+        let lookup = Lookup::read(env.heap, this?)?;
+        Some(Some(env.interpreter.class_mirror(env.heap, &lookup.class)))
+    }),
+    ("toString", "()Ljava/lang/String;", |env, this, _| {
+        let lookup = Lookup::read(env.heap, this?)?;
+        let text = match lookup.full {
+            true => lookup.class.replace('/', "."),
+            false => "java.lang.Object/publicLookup".to_string(),
+        };
+        Some(Some(env.heap.alloc_string(&text)))
+    }),
+    (
+        "findVirtual",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| {
+            let (refc, name, method_type) = (args.first()?, args.get(1)?, args.get(2)?);
+            lookup_method(env, this, REF_INVOKE_VIRTUAL, refc, Some(name), method_type, None)
+        },
+    ),
+    (
+        "findStatic",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| {
+            let (refc, name, method_type) = (args.first()?, args.get(1)?, args.get(2)?);
+            lookup_method(env, this, REF_INVOKE_STATIC, refc, Some(name), method_type, None)
+        },
+    ),
+    (
+        "findSpecial",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| {
+            let (refc, name, method_type) = (args.first()?, args.get(1)?, args.get(2)?);
+            let caller = args.get(3)?;
+            lookup_method(
+                env,
+                this,
+                REF_INVOKE_SPECIAL,
+                refc,
+                Some(name),
+                method_type,
+                Some(caller),
+            )
+        },
+    ),
+    (
+        "findConstructor",
+        "(Ljava/lang/Class;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| {
+            let (refc, method_type) = (args.first()?, args.get(1)?);
+            lookup_method(env, this, REF_NEW_INVOKE_SPECIAL, refc, None, method_type, None)
+        },
+    ),
+    (
+        "findGetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| lookup_field(env, this, args, REF_GET_FIELD, false),
+    ),
+    (
+        "findSetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| lookup_field(env, this, args, REF_PUT_FIELD, false),
+    ),
+    (
+        "findStaticGetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| lookup_field(env, this, args, REF_GET_STATIC, false),
+    ),
+    (
+        "findStaticSetter",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| lookup_field(env, this, args, REF_PUT_STATIC, false),
+    ),
+    (
+        "findVarHandle",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, this, args| lookup_field(env, this, args, REF_GET_FIELD, true),
+    ),
+    (
+        "findStaticVarHandle",
+        "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, this, args| lookup_field(env, this, args, REF_GET_STATIC, true),
+    ),
+    (
+        "unreflect",
+        "(Ljava/lang/reflect/Method;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| unreflect(env, this, args, Unreflect::Method),
+    ),
+    (
+        "unreflectConstructor",
+        "(Ljava/lang/reflect/Constructor;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| unreflect(env, this, args, Unreflect::Constructor),
+    ),
+    (
+        "unreflectGetter",
+        "(Ljava/lang/reflect/Field;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| unreflect(env, this, args, Unreflect::Getter),
+    ),
+    (
+        "unreflectSetter",
+        "(Ljava/lang/reflect/Field;)Ljava/lang/invoke/MethodHandle;",
+        |env, this, args| unreflect(env, this, args, Unreflect::Setter),
+    ),
+    (
+        "unreflectVarHandle",
+        "(Ljava/lang/reflect/Field;)Ljava/lang/invoke/VarHandle;",
+        |env, this, args| unreflect(env, this, args, Unreflect::VarHandle),
+    ),
+];
+
+const METHOD_HANDLE_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("type", "()Ljava/lang/invoke/MethodType;", |env, this, _| {
+        let (this, _) = this_handle(env, this)?;
+        Some(Some(field(env.heap, object_id(this)?, "type")))
+    }),
+    (
+        "bindTo",
+        "(Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;",
+        bind_to,
+    ),
+    (
+        "asType",
+        "(Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/MethodHandle;",
+        as_type,
+    ),
+    (
+        "asSpreader",
+        "(Ljava/lang/Class;I)Ljava/lang/invoke/MethodHandle;",
+        as_spreader,
+    ),
+    (
+        "invokeWithArguments",
+        "([Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, this, args| {
+            let (this, _) = this_handle(env, this)?;
+            let values = contents(env.heap, args.first()?);
+            Some(invoke_with_arguments(env, this, values))
+        },
+    ),
+    ("isVarargsCollector", "()Z", |env, this, _| {
+        let (this, _) = this_handle(env, this)?;
+        Some(Some(HeapValue::Int(
+            is_varargs_handle(env.heap, this) as i32
+        )))
+    }),
+    ("toString", "()Ljava/lang/String;", |env, this, _| {
+        let (_, descriptor) = this_handle(env, this)?;
+        let text = format!("MethodHandle{}", type_string(env, &descriptor));
+        Some(Some(env.heap.alloc_string(&text)))
+    }),
+];
+
+const CONSTANT_BOOTSTRAPS_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    (
+        "nullConstant",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/Object;",
+        |env, _, args| {
+            bootstrap(env, args, |env, _, _, constant_type| {
+                if java_lang_class::is_primitive(&constant_type) {
+                    illegal_argument(env, &format!("not reference: {}", constant_type));
+                    return Some(None);
+                }
+                Some(Some(HeapValue::Null))
+            })
+        },
+    ),
+    (
+        "primitiveClass",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/Class;",
+        |env, _, args| {
+            bootstrap(env, args, |env, _, name, _| {
+                let primitive = descriptor_type_name(&name);
+                if !java_lang_class::is_primitive(primitive) {
+                    illegal_argument(env, &format!("not primitive: {}", name));
+                    return Some(None);
+                }
+                Some(Some(env.interpreter.class_mirror(env.heap, primitive)))
+            })
+        },
+    ),
+    (
+        "enumConstant",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/Enum;",
+        |env, _, args| {
+            bootstrap(env, args, |env, _, name, constant_type| {
+                static_final(env, &name, &constant_type, &constant_type)
+            })
+        },
+    ),
+    (
+        "getStaticFinal",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/Object;",
+        |env, _, args| {
+            bootstrap(env, args, |env, _, name, constant_type| {
+                static_final(env, &name, &constant_type, &constant_type)
+            })
+        },
+    ),
+    (
+        "getStaticFinal",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/Object;",
+        |env, _, args| {
+            bootstrap(env, args, |env, args, name, constant_type| {
+                let Some(declaring) = java_lang_class::class_name(env.heap, args.get(3)?) else {
+                    return Some(null_pointer(env));
+                };
+                static_final(env, &name, &constant_type, &declaring)
+            })
+        },
+    ),
+    (
+        "invoke",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/invoke/MethodHandle;[Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, _, args| {
+            bootstrap(env, args, |env, args, _, _| {
+                let handle = args.get(3)?;
+                let values = contents(env.heap, args.get(4)?);
+                Some(invoke_with_arguments(env, handle, values))
+            })
+        },
+    ),
+    (
+        "fieldVarHandle",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, _, args| {
+            bootstrap(env, args, |env, args, name, _| {
+                field_var_handle(env, args, &name, REF_GET_FIELD)
+            })
+        },
+    ),
+    (
+        "staticFieldVarHandle",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, _, args| {
+            bootstrap(env, args, |env, args, name, _| {
+                field_var_handle(env, args, &name, REF_GET_STATIC)
+            })
+        },
+    ),
+    (
+        "arrayVarHandle",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/invoke/VarHandle;",
+        |env, _, args| {
+            bootstrap(env, args, |env, args, _, _| {
+                let Some(array_class) = java_lang_class::class_name(env.heap, args.get(3)?) else {
+                    return Some(null_pointer(env));
+                };
+                Some(Some(java_lang_invoke_varhandle::new_array_handle(
+                    env,
+                    &array_class,
+                )))
+            })
+        },
+    ),
+];
+
+/// Builtin methods that take a variable number of arguments when a
+/// bootstrap method handle refers to them.
+const VARARGS_NATIVES: &[(&str, &str)] = &[(CONSTANT_BOOTSTRAPS, "invoke")];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all(METHOD_TYPE, METHOD_TYPE_METHODS);
+    registry.register_all(METHOD_HANDLES, METHOD_HANDLES_METHODS);
+    registry.register_all(LOOKUP, LOOKUP_METHODS);
+    registry.register_all(METHOD_HANDLE, METHOD_HANDLE_METHODS);
+    registry.register_all(CONSTANT_BOOTSTRAPS, CONSTANT_BOOTSTRAPS_METHODS);
+}
+
+pub fn is_invoke_class(class_name: &str) -> bool {
+    matches!(
+        class_name,
+        METHOD_HANDLE | METHOD_TYPE | METHOD_HANDLES | LOOKUP | CONSTANT_BOOTSTRAPS
+    ) || class_name == java_lang_invoke_varhandle::VAR_HANDLE
+}
+
+/// The native behind a signature-polymorphic method, linked once per
+/// call-site descriptor: `MethodHandle.invokeExact` and `invoke`, and the
+/// `VarHandle` access modes.
+pub fn signature_polymorphic(
+    class_name: &str,
+    method_name: &str,
+    descriptor: &str,
+) -> Option<NativeMethod> {
+    let descriptor = descriptor.to_string();
+    match (class_name, method_name) {
+        (METHOD_HANDLE, "invokeExact" | "invoke") => {
+            let exact = method_name == "invokeExact";
+            Some(Rc::new(move |env, receiver, args| {
+                invoke_polymorphic(env, receiver?, args, &descriptor, exact)
+            }))
+        }
+        (java_lang_invoke_varhandle::VAR_HANDLE, _) => {
+            java_lang_invoke_varhandle::access_mode(method_name, descriptor)
+        }
+        _ => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// MethodType
+
+/// A new `MethodType` for a method descriptor.
+pub fn new_method_type(heap: &mut Heap, descriptor: &str) -> HeapValue {
+    let obj = heap.alloc_object(METHOD_TYPE);
+    if let Some(real) = heap.get_mut(obj.id) {
+        real.set_field("descriptor", HeapValue::String(descriptor.to_string()));
+    }
+    HeapValue::Object(obj)
+}
+
+/// The method descriptor a `MethodType` stands for.
+pub fn method_type_descriptor(heap: &Heap, value: &HeapValue) -> Option<String> {
+    match value {
+        HeapValue::Object(obj) if obj.class_name == METHOD_TYPE => {
+            match field(heap, obj.id, "descriptor") {
+                HeapValue::String(descriptor) => Some(descriptor),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `MethodType.toString`: `(int,String)void`, with simple class names.
+pub fn type_string(env: &mut NativeEnv, descriptor: &str) -> String {
+    let (params, ret) = split_method_descriptor(descriptor);
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| java_lang_class::simple_name(env, descriptor_type_name(param)))
+        .collect();
+    let ret = java_lang_class::simple_name(env, descriptor_type_name(ret));
+    format!("({}){}", params.join(","), ret)
+}
+
+/// `MethodType.erase` maps references to `Object`; `generic` maps
+/// primitives too.
+fn erase(descriptor: &str, generic: bool) -> &str {
+    match descriptor.len() {
+        1 if !generic => descriptor,
+        _ => OBJECT,
+    }
+}
+
+fn method_descriptor(params: &[&str], ret: &str) -> String {
+    format!("({}){}", params.concat(), ret)
+}
+
+/// The descriptor of the type a `Class` object denotes; throws
+/// `NullPointerException` for `null`.
+fn class_descriptor(env: &mut NativeEnv, value: &HeapValue) -> Option<String> {
+    match java_lang_class::class_name(env.heap, value) {
+        Some(name) => Some(type_descriptor(&name)),
+        None => {
+            env.interpreter
+                .throw_new(env.heap, "java/lang/NullPointerException", None);
+            None
+        }
+    }
+}
+
+/// The descriptors of the `Class` objects in a `Class[]`; `void` is not
+/// a parameter type.
+fn class_descriptors(env: &mut NativeEnv, array: &HeapValue) -> Option<Vec<String>> {
+    let mut descriptors = Vec::new();
+    for class in contents(env.heap, array) {
+        let descriptor = class_descriptor(env, &class)?;
+        if descriptor == "V" {
+            illegal_argument(env, "parameter type cannot be void");
+            return None;
+        }
+        descriptors.push(descriptor);
+    }
+    Some(descriptors)
+}
+
+/// `MethodType.methodType`: the return type, then the parameter types
+/// `params` reads from the remaining arguments.
+fn method_type(
+    env: &mut NativeEnv,
+    args: &[HeapValue],
+    params: fn(&mut NativeEnv, &[HeapValue]) -> Option<Vec<String>>,
+) -> Option<Option<HeapValue>> {
+    let Some(ret) = class_descriptor(env, args.first()?) else {
+        return Some(None);
+    };
+    let Some(params) = params(env, args) else {
+        return Some(None);
+    };
+    let params: Vec<&str> = params.iter().map(String::as_str).collect();
+    changed_type(env, &params, &ret)
+}
+
+fn from_method_descriptor_string(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(text) = env.heap.string_value(args.first()?) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    if !text.starts_with('(') || !text.contains(')') {
+        illegal_argument(env, &format!("not a method descriptor: {}", text));
+        return Some(None);
+    }
+    Some(Some(new_method_type(env.heap, &text)))
+}
+
+/// The descriptor of the `MethodType` a method is called on.
+fn this_type(env: &NativeEnv, receiver: Option<&HeapValue>) -> Option<String> {
+    method_type_descriptor(env.heap, receiver?)
+}
+
+fn mirror(env: &mut NativeEnv, descriptor: &str) -> HeapValue {
+    env.interpreter
+        .class_mirror(env.heap, descriptor_type_name(descriptor))
+}
+
+/// A new `MethodType` with the given parameter and return types.
+fn changed_type(env: &mut NativeEnv, params: &[&str], ret: &str) -> Option<Option<HeapValue>> {
+    Some(Some(new_method_type(
+        env.heap,
+        &method_descriptor(params, ret),
+    )))
+}
+
+fn parameter_type(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let this = this_type(env, receiver)?;
+    let (params, _) = split_method_descriptor(&this);
+    let index = args.first()?.as_int();
+    match usize::try_from(index).ok().and_then(|i| params.get(i)) {
+        Some(param) => Some(Some(mirror(env, param))),
+        None => {
+            index_out_of_bounds(env, index, params.len());
+            Some(None)
+        }
+    }
+}
+
+fn change_parameter_type(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let this = this_type(env, receiver)?;
+    let (mut params, ret) = split_method_descriptor(&this);
+    let index = args.first()?.as_int();
+    let Some(param) = class_descriptor(env, args.get(1)?) else {
+        return Some(None);
+    };
+    let Some(slot) = usize::try_from(index).ok().filter(|i| *i < params.len()) else {
+        index_out_of_bounds(env, index, params.len());
+        return Some(None);
+    };
+    params[slot] = &param;
+    changed_type(env, &params, ret)
+}
+
+/// `MethodType.insertParameterTypes`, and `appendParameterTypes` at the
+/// end.
+fn insert_parameter_types(
+    env: &mut NativeEnv,
+    this: &str,
+    position: i32,
+    added: &HeapValue,
+) -> Option<Option<HeapValue>> {
+    let (mut params, ret) = split_method_descriptor(this);
+    let Some(added) = class_descriptors(env, added) else {
+        return Some(None);
+    };
+    let Some(position) = usize::try_from(position)
+        .ok()
+        .filter(|p| *p <= params.len())
+    else {
+        index_out_of_bounds(env, position, params.len());
+        return Some(None);
+    };
+    for (offset, param) in added.iter().enumerate() {
+        params.insert(position + offset, param);
+    }
+    changed_type(env, &params, ret)
+}
+
+fn drop_parameter_types(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let this = this_type(env, receiver)?;
+    let (mut params, ret) = split_method_descriptor(&this);
+    let (start, end) = (args.first()?.as_int(), args.get(1)?.as_int());
+    if start < 0 || end < start || end as usize > params.len() {
+        index_out_of_bounds(env, start, params.len());
+        return Some(None);
+    }
+    params.drain(start as usize..end as usize);
+    changed_type(env, &params, ret)
+}
+
+/// `MethodType.erase`, or `generic` when `generic` is set.
+fn erased_type(env: &mut NativeEnv, this: &str, generic: bool) -> Option<Option<HeapValue>> {
+    let (params, ret) = split_method_descriptor(this);
+    let params: Vec<&str> = params.iter().map(|param| erase(param, generic)).collect();
+    changed_type(env, &params, erase(ret, generic))
+}
+
+// ---------------------------------------------------------------------------
+// Lookup
+
+/// A `MethodHandles.Lookup`: the class it looks up from, and whether it
+/// has that class's full access or only sees public members.
+struct Lookup {
+    class: String,
+    full: bool,
+}
+
+impl Lookup {
+    fn read(heap: &Heap, value: &HeapValue) -> Option<Self> {
+        let HeapValue::Object(obj) = value else {
+            return None;
+        };
+        Some(Self {
+            class: java_lang_class::class_name(heap, &field(heap, obj.id, "lookupClass"))?,
+            full: int_field(heap, obj.id, "full") != 0,
+        })
+    }
+
+    /// `from class Main`, or `from public Lookup`.
+    fn origin(&self) -> String {
+        match self.full {
+            true => format!("from class {}", self.class.replace('/', ".")),
+            false => "from public Lookup".to_string(),
+        }
+    }
+}
+
+/// A lookup on `class_name`, with full access unless `full` is false.
+pub fn new_lookup(env: &mut NativeEnv, class_name: &str, full: bool) -> HeapValue {
+    let mirror = env.interpreter.class_mirror(env.heap, class_name);
+    let obj = env.heap.alloc_object(LOOKUP);
+    if let Some(real) = env.heap.get_mut(obj.id) {
+        real.set_field("lookupClass", mirror);
+        real.set_field("full", HeapValue::Int(full as i32));
+    }
+    HeapValue::Object(obj)
+}
+
+/// `Lookup.findVirtual` and its kin: a handle on the method `kind`
+/// refers to, or on a constructor when there is no `name`.
+fn lookup_method(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    kind: u8,
+    refc: &HeapValue,
+    name: Option<&HeapValue>,
+    method_type: &HeapValue,
+    special_caller: Option<&HeapValue>,
+) -> Option<Option<HeapValue>> {
+    let lookup = Lookup::read(env.heap, receiver?)?;
+    let Some(refc) = java_lang_class::class_name(env.heap, refc) else {
+        return Some(null_pointer(env));
+    };
+    let name = match name {
+        Some(name) => match env.heap.string_value(name) {
+            Some(name) => name,
+            None => return Some(null_pointer(env)),
+        },
+        None => "<init>".to_string(),
+    };
+    let Some(descriptor) = method_type_descriptor(env.heap, method_type) else {
+        return Some(null_pointer(env));
+    };
+    let special_caller =
+        special_caller.and_then(|caller| java_lang_class::class_name(env.heap, caller));
+    Some(find_method(
+        env,
+        &lookup,
+        kind,
+        &refc,
+        &name,
+        &descriptor,
+        special_caller.as_deref(),
+    ))
+}
+
+/// `Lookup.findGetter` and its kin: a handle on the field `kind` refers
+/// to, or a `VarHandle` on it when `var_handle` is set.
+fn lookup_field(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+    kind: u8,
+    var_handle: bool,
+) -> Option<Option<HeapValue>> {
+    let lookup = Lookup::read(env.heap, receiver?)?;
+    let refc = java_lang_class::class_name(env.heap, args.first()?);
+    let name = env.heap.string_value(args.get(1)?);
+    let field_type = java_lang_class::class_name(env.heap, args.get(2)?);
+    let (Some(refc), Some(name), Some(field_type)) = (refc, name, field_type) else {
+        return Some(null_pointer(env));
+    };
+    let descriptor = type_descriptor(&field_type);
+    let Some((declaring, flags)) = find_field(env, &lookup, kind, &refc, &name, &descriptor) else {
+        return Some(None);
+    };
+    if var_handle {
+        return Some(Some(java_lang_invoke_varhandle::new_field_handle(
+            env,
+            &declaring,
+            &name,
+            &descriptor,
+            flags,
+        )));
+    }
+    let member = Direct {
+        kind,
+        class: declaring,
+        name,
+        descriptor,
+    };
+    Some(Some(direct_handle(env, &member, &refc, false)))
+}
+
+fn constant(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(ret) = class_descriptor(env, args.first()?) else {
+        return Some(None);
+    };
+    let value = args.get(1)?.clone();
+    if ret == "V" {
+        illegal_argument(env, "void type");
+        return Some(None);
+    }
+    convert(env, value.clone(), OBJECT, &ret)?;
+    let handle_type = format!("(){}", ret);
+    Some(Some(new_handle(
+        env,
+        &handle_type,
+        "constant",
+        &[("value", value)],
+    )))
+}
+
+fn identity(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(descriptor) = class_descriptor(env, args.first()?) else {
+        return Some(None);
+    };
+    if descriptor == "V" {
+        illegal_argument(env, "void type");
+        return Some(None);
+    }
+    let handle_type = format!("({}){}", descriptor, descriptor);
+    Some(Some(new_handle(env, &handle_type, "identity", &[])))
+}
+
+fn drop_arguments(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let target = args.first()?;
+    let position = args.get(1)?.as_int();
+    let Some(target_type) = handle_descriptor(env.heap, target) else {
+        return Some(null_pointer(env));
+    };
+    let Some(dropped) = class_descriptors(env, args.get(2)?) else {
+        return Some(None);
+    };
+    let (mut params, ret) = split_method_descriptor(&target_type);
+    let Some(slot) = usize::try_from(position)
+        .ok()
+        .filter(|slot| *slot <= params.len())
+    else {
+        illegal_argument(env, &format!("bad argument index {}", position));
+        return Some(None);
+    };
+    for (offset, param) in dropped.iter().enumerate() {
+        params.insert(slot + offset, param);
+    }
+    Some(Some(new_handle(
+        env,
+        &method_descriptor(&params, ret),
+        "drop",
+        &[
+            ("target", target.clone()),
+            ("position", HeapValue::Int(position)),
+            ("count", HeapValue::Int(dropped.len() as i32)),
+        ],
+    )))
+}
+
+fn filter_return_value(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let (target, filter) = (args.first()?, args.get(1)?);
+    let (Some(target_type), Some(filter_type)) = (
+        handle_descriptor(env.heap, target),
+        handle_descriptor(env.heap, filter),
+    ) else {
+        return Some(null_pointer(env));
+    };
+    let (params, ret) = split_method_descriptor(&target_type);
+    let (filter_params, filter_ret) = split_method_descriptor(&filter_type);
+    let fits = match ret {
+        "V" => filter_params.is_empty(),
+        _ => filter_params == [ret],
+    };
+    if !fits {
+        let message = format!(
+            "target and filter types do not match: {}, {}",
+            type_string(env, &target_type),
+            type_string(env, &filter_type)
+        );
+        illegal_argument(env, &message);
+        return Some(None);
+    }
+    Some(Some(new_handle(
+        env,
+        &method_descriptor(&params, filter_ret),
+        "filterReturn",
+        &[("target", target.clone()), ("filter", filter.clone())],
+    )))
+}
+
+fn filter_arguments(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let target = args.first()?;
+    let position = args.get(1)?.as_int();
+    let filters = contents(env.heap, args.get(2)?);
+    let Some(target_type) = handle_descriptor(env.heap, target) else {
+        return Some(null_pointer(env));
+    };
+    let (params, ret) = split_method_descriptor(&target_type);
+    let mut params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+    let start = usize::try_from(position).unwrap_or(usize::MAX);
+    if start.saturating_add(filters.len()) > params.len() {
+        illegal_argument(env, "too many filters");
+        return Some(None);
+    }
+    for (offset, filter) in filters.iter().enumerate() {
+        if filter.is_null() {
+            continue;
+        }
+        let filter_type = handle_descriptor(env.heap, filter)?;
+        let (filter_params, filter_ret) = split_method_descriptor(&filter_type);
+        if filter_params.len() != 1 || filter_ret != params[start + offset] {
+            let message = format!(
+                "target and filter types do not match: {}, {}",
+                type_string(env, &target_type),
+                type_string(env, &filter_type)
+            );
+            illegal_argument(env, &message);
+            return Some(None);
+        }
+        params[start + offset] = filter_params[0].to_string();
+    }
+    let params: Vec<&str> = params.iter().map(String::as_str).collect();
+    let filters = java_lang_class::reference_array(env.heap, METHOD_HANDLE, filters);
+    Some(Some(new_handle(
+        env,
+        &method_descriptor(&params, ret),
+        "filterArguments",
+        &[
+            ("target", target.clone()),
+            ("position", HeapValue::Int(position)),
+            ("filters", filters),
+        ],
+    )))
+}
+
+fn guard_with_test(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let (test, target, fallback) = (args.first()?, args.get(1)?, args.get(2)?);
+    let types = (
+        handle_descriptor(env.heap, test),
+        handle_descriptor(env.heap, target),
+        handle_descriptor(env.heap, fallback),
+    );
+    let (Some(test_type), Some(target_type), Some(fallback_type)) = types else {
+        return Some(null_pointer(env));
+    };
+    let (test_params, test_ret) = split_method_descriptor(&test_type);
+    let (target_params, _) = split_method_descriptor(&target_type);
+    if test_ret != "Z" || target_type != fallback_type || !target_params.starts_with(&test_params) {
+        let message = format!(
+            "target and test types do not match: {}, {}",
+            type_string(env, &target_type),
+            type_string(env, &test_type)
+        );
+        illegal_argument(env, &message);
+        return Some(None);
+    }
+    Some(Some(new_handle(
+        env,
+        &target_type,
+        "guard",
+        &[
+            ("test", test.clone()),
+            ("target", target.clone()),
+            ("fallback", fallback.clone()),
+        ],
+    )))
+}
+
+fn permute_arguments(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let target = args.first()?;
+    let (Some(target_type), Some(new_type)) = (
+        handle_descriptor(env.heap, target),
+        method_type_descriptor(env.heap, args.get(1)?),
+    ) else {
+        return Some(null_pointer(env));
+    };
+    let order = contents(env.heap, args.get(2)?);
+    let (target_params, target_ret) = split_method_descriptor(&target_type);
+    let (new_params, new_ret) = split_method_descriptor(&new_type);
+    let valid = order.len() == target_params.len()
+        && target_ret == new_ret
+        && order.iter().zip(&target_params).all(|(index, param)| {
+            usize::try_from(index.as_int())
+                .ok()
+                .and_then(|i| new_params.get(i))
+                == Some(param)
+        });
+    if !valid {
+        illegal_argument(env, "bad reorder array");
+        return Some(None);
+    }
+    let order = match args.get(2)? {
+        HeapValue::Array(arr) => java_lang_object::clone_array(env.heap, arr)?,
+        _ => return Some(null_pointer(env)),
+    };
+    Some(Some(new_handle(
+        env,
+        &new_type,
+        "permute",
+        &[("target", target.clone()), ("order", order)],
+    )))
+}
+
+/// `MethodHandles.arrayElementGetter` and its kin: a handle of the given
+/// form, typed by `handle_type` from the array class and its component.
+fn array_handle(
+    env: &mut NativeEnv,
+    args: &[HeapValue],
+    form: &str,
+    handle_type: fn(&str, &str) -> String,
+) -> Option<Option<HeapValue>> {
+    let Some(array_class) = java_lang_class::class_name(env.heap, args.first()?) else {
+        return Some(null_pointer(env));
+    };
+    let Some(component) = array_class.strip_prefix('[') else {
+        illegal_argument(
+            env,
+            &format!("not an array class: {}", array_class.replace('/', ".")),
+        );
+        return Some(None);
+    };
+    let handle_type = handle_type(&array_class, component);
+    Some(Some(new_handle(env, &handle_type, form, &[])))
+}
+
+/// A JVMS reference to a field or method: what a direct method handle
+/// does when invoked.
+struct Direct {
+    kind: u8,
+    /// The class the member is resolved from: the class declaring a
+    /// field, the referenced class of a method.
+    class: String,
+    name: String,
+    /// The field or method descriptor.
+    descriptor: String,
+}
+
+impl Direct {
+    /// `Main.add(int,int)int/invokeVirtual` or `Main.count/int/getField`,
+    /// as lookup failures describe a member.
+    fn describe(&self, env: &mut NativeEnv) -> String {
+        let class = self.class.replace('/', ".");
+        let kind = REF_KIND_NAMES[self.kind as usize];
+        match self.kind {
+            REF_GET_FIELD..=REF_PUT_STATIC => {
+                let field_type =
+                    java_lang_class::simple_name(env, descriptor_type_name(&self.descriptor));
+                format!("{}.{}/{}/{}", class, self.name, field_type, kind)
+            }
+            _ => {
+                let method_type = type_string(env, &self.descriptor);
+                format!("{}.{}{}/{}", class, self.name, method_type, kind)
+            }
+        }
+    }
+}
+
+/// The type of a direct handle on `member`; `receiver` is the class the
+/// leading parameter of an instance member takes.
+fn direct_type(member: &Direct, receiver: &str) -> String {
+    let receiver = type_descriptor(receiver);
+    let descriptor = &member.descriptor;
+    match member.kind {
+        REF_GET_FIELD => format!("({}){}", receiver, descriptor),
+        REF_GET_STATIC => format!("(){}", descriptor),
+        REF_PUT_FIELD => format!("({}{})V", receiver, descriptor),
+        REF_PUT_STATIC => format!("({})V", descriptor),
+        REF_INVOKE_STATIC => descriptor.clone(),
+        REF_NEW_INVOKE_SPECIAL => {
+            let (params, _) = descriptor.split_once(')').unwrap_or((descriptor, ""));
+            format!("{}){}", params, receiver)
+        }
+        _ => format!("({}{}", receiver, &descriptor[1..]),
+    }
+}
+
+fn direct_handle(env: &mut NativeEnv, member: &Direct, receiver: &str, varargs: bool) -> HeapValue {
+    let handle_type = direct_type(member, receiver);
+    let class = env.interpreter.class_mirror(env.heap, &member.class);
+    new_handle(
+        env,
+        &handle_type,
+        "direct",
+        &[
+            ("refKind", HeapValue::Int(member.kind as i32)),
+            ("clazz", class),
+            ("name", HeapValue::String(member.name.clone())),
+            (
+                "memberDescriptor",
+                HeapValue::String(member.descriptor.clone()),
+            ),
+            ("varargs", HeapValue::Int(varargs as i32)),
+        ],
+    )
+}
+
+/// Whether `lookup` may use a member of `declaring` with access `flags`.
+/// Throws `IllegalAccessException` naming the member otherwise.
+fn check_access(
+    env: &mut NativeEnv,
+    lookup: &Lookup,
+    declaring: &str,
+    flags: u16,
+    member: &Direct,
+) -> bool {
+    let allowed = match lookup.full {
+        true => java_lang_reflect::is_accessible(env, &lookup.class, declaring, flags),
+        false => {
+            flags & ACC_PUBLIC != 0 && java_lang_class::modifiers(env, declaring) & ACC_PUBLIC != 0
+        }
+    };
+    if !allowed {
+        let access = if flags & ACC_PRIVATE != 0 {
+            "private"
+        } else if flags & ACC_PROTECTED != 0 {
+            "protected"
+        } else {
+            "private to package"
+        };
+        let message = format!(
+            "member is {}: {}, {}",
+            access,
+            member.describe(env),
+            lookup.origin()
+        );
+        env.interpreter
+            .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+    }
+    allowed
+}
+
+/// `findVirtual`, `findStatic`, `findSpecial` and `findConstructor`.
+fn find_method(
+    env: &mut NativeEnv,
+    lookup: &Lookup,
+    kind: u8,
+    refc: &str,
+    name: &str,
+    descriptor: &str,
+    special_caller: Option<&str>,
+) -> Option<HeapValue> {
+    let mut member = Direct {
+        kind,
+        class: refc.to_string(),
+        name: name.to_string(),
+        descriptor: descriptor.to_string(),
+    };
+    let found = env
+        .interpreter
+        .find_method(env.loader, refc, name, descriptor)
+        .filter(|(declaring, _)| kind != REF_NEW_INVOKE_SPECIAL || declaring == refc);
+    let Some((declaring, flags)) = found else {
+        let what = match kind {
+            REF_NEW_INVOKE_SPECIAL => "constructor",
+            _ => "method",
+        };
+        let message = format!("no such {}: {}", what, member.describe(env));
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NoSuchMethodException", Some(&message));
+        return None;
+    };
+    // Natives of builtin classes are public, and static or not as asked.
+    let builtin = native::is_builtin_class(&declaring);
+    let flags = match builtin {
+        true => {
+            ACC_PUBLIC
+                | if kind == REF_INVOKE_STATIC {
+                    ACC_STATIC
+                } else {
+                    0
+                }
+        }
+        false => flags,
+    };
+    if (flags & ACC_STATIC != 0) != (kind == REF_INVOKE_STATIC) {
+        let message = format!("no such method: {}", member.describe(env));
+        env.interpreter
+            .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+        return None;
+    }
+    if !check_access(env, lookup, &declaring, flags, &member) {
+        return None;
+    }
+    let mut receiver = refc.to_string();
+    match kind {
+        REF_INVOKE_SPECIAL => {
+            let caller = special_caller.unwrap_or_default();
+            if !lookup.full || caller != lookup.class {
+                let message = format!(
+                    "no private access for invokespecial: class {}, {}",
+                    caller.replace('/', "."),
+                    lookup.origin()
+                );
+                env.interpreter.throw_new(
+                    env.heap,
+                    "java/lang/IllegalAccessException",
+                    Some(&message),
+                );
+                return None;
+            }
+            receiver = caller.to_string();
+        }
+        REF_INVOKE_VIRTUAL if flags & ACC_PRIVATE != 0 => member.kind = REF_INVOKE_SPECIAL,
+        REF_INVOKE_VIRTUAL if java_lang_class::modifiers(env, refc) & ACC_INTERFACE != 0 => {
+            member.kind = REF_INVOKE_INTERFACE
+        }
+        REF_NEW_INVOKE_SPECIAL
+            if java_lang_class::modifiers(env, refc) & (ACC_ABSTRACT | ACC_INTERFACE) != 0 =>
+        {
+            let message = format!("no such constructor: {}", member.describe(env));
+            env.interpreter
+                .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+            return None;
+        }
+        _ => {}
+    }
+    let varargs = is_varargs(env, &declaring, name, descriptor, flags);
+    Some(direct_handle(env, &member, &receiver, varargs))
+}
+
+fn is_varargs(
+    env: &mut NativeEnv,
+    declaring: &str,
+    name: &str,
+    descriptor: &str,
+    flags: u16,
+) -> bool {
+    if native::is_builtin_class(declaring) {
+        return VARARGS_NATIVES.contains(&(declaring, name));
+    }
+    flags & ACC_VARARGS != 0
+        && java_lang_class::loaded_class(env, declaring).is_some_and(|runtime| {
+            runtime
+                .methods
+                .iter()
+                .any(|method| method.name == name && method.descriptor == descriptor)
+        })
+}
+
+/// The class declaring field `name` as seen from `class_name`, searching
+/// superinterfaces before the superclass, with its access flags.
+fn field_owner(
+    env: &mut NativeEnv,
+    class_name: &str,
+    name: &str,
+    descriptor: &str,
+) -> Option<(String, u16)> {
+    let runtime = java_lang_class::loaded_class(env, class_name)?;
+    let class = &runtime.class;
+    let declared = class.fields.iter().find(|field| {
+        class.get_utf8(field.name_index) == Some(name)
+            && class.get_utf8(field.descriptor_index) == Some(descriptor)
+    });
+    if let Some(field) = declared {
+        return Some((class_name.to_string(), field.access_flags));
+    }
+    for interface in runtime.interfaces.clone() {
+        if let Some(found) = field_owner(env, &interface, name, descriptor) {
+            return Some(found);
+        }
+    }
+    field_owner(env, runtime.superclass.as_deref()?, name, descriptor)
+}
+
+/// Resolves a field for a getter, setter or `VarHandle`, checking that
+/// it exists, is static or not as `kind` says, is accessible, and is not
+/// final when set.
+fn find_field(
+    env: &mut NativeEnv,
+    lookup: &Lookup,
+    kind: u8,
+    refc: &str,
+    name: &str,
+    descriptor: &str,
+) -> Option<(String, u16)> {
+    let mut member = Direct {
+        kind,
+        class: refc.to_string(),
+        name: name.to_string(),
+        descriptor: descriptor.to_string(),
+    };
+    let Some((declaring, flags)) = field_owner(env, refc, name, descriptor) else {
+        let message = format!("no such field: {}", member.describe(env));
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NoSuchFieldException", Some(&message));
+        return None;
+    };
+    member.class = declaring.clone();
+    let is_static = matches!(kind, REF_GET_STATIC | REF_PUT_STATIC);
+    if (flags & ACC_STATIC != 0) != is_static {
+        let message = format!("no such field: {}", member.describe(env));
+        env.interpreter
+            .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+        return None;
+    }
+    if !check_access(env, lookup, &declaring, flags, &member) {
+        return None;
+    }
+    if matches!(kind, REF_PUT_FIELD | REF_PUT_STATIC) && flags & ACC_FINAL != 0 {
+        let message = format!(
+            "unexpected set of a final field: {}, {}",
+            member.describe(env),
+            lookup.origin()
+        );
+        env.interpreter
+            .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+        return None;
+    }
+    Some((declaring, flags))
+}
+
+/// What a `Lookup.unreflect*` method makes of its reflected member.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Unreflect {
+    Method,
+    Constructor,
+    Getter,
+    Setter,
+    VarHandle,
+}
+
+/// `Lookup.unreflect*`: a handle on a reflected member, checked against
+/// the lookup unless the member was made accessible.
+fn unreflect(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+    wanted: Unreflect,
+) -> Option<Option<HeapValue>> {
+    let lookup = Lookup::read(env.heap, receiver?)?;
+    let Some(member) = Member::read(env.heap, args.first()?) else {
+        return Some(null_pointer(env));
+    };
+    let is_static = member.modifiers & ACC_STATIC != 0;
+    let kind = match (wanted, member.kind) {
+        (Unreflect::Method, MemberKind::Method) if is_static => REF_INVOKE_STATIC,
+        (Unreflect::Method, MemberKind::Method) if member.modifiers & ACC_PRIVATE != 0 => {
+            REF_INVOKE_SPECIAL
+        }
+        (Unreflect::Method, MemberKind::Method) => {
+            match java_lang_class::modifiers(env, &member.declaring) & ACC_INTERFACE {
+                0 => REF_INVOKE_VIRTUAL,
+                _ => REF_INVOKE_INTERFACE,
+            }
+        }
+        (Unreflect::Constructor, MemberKind::Constructor) => REF_NEW_INVOKE_SPECIAL,
+        (Unreflect::Getter | Unreflect::VarHandle, MemberKind::Field) if is_static => {
+            REF_GET_STATIC
+        }
+        (Unreflect::Getter | Unreflect::VarHandle, MemberKind::Field) => REF_GET_FIELD,
+        (Unreflect::Setter, MemberKind::Field) if is_static => REF_PUT_STATIC,
+        (Unreflect::Setter, MemberKind::Field) => REF_PUT_FIELD,
+        _ => {
+            illegal_argument(env, "not a member of the expected kind");
+            return Some(None);
+        }
+    };
+    let direct = Direct {
+        kind,
+        class: member.declaring.clone(),
+        name: member.name.clone(),
+        descriptor: member.descriptor.clone(),
+    };
+    if !member.accessible
+        && !check_access(env, &lookup, &member.declaring, member.modifiers, &direct)
+    {
+        return Some(None);
+    }
+    let setting_final = matches!(kind, REF_PUT_FIELD | REF_PUT_STATIC)
+        && member.modifiers & ACC_FINAL != 0
+        && (is_static || !member.accessible);
+    if setting_final {
+        let message = format!(
+            "unexpected set of a final field: {}, {}",
+            direct.describe(env),
+            lookup.origin()
+        );
+        env.interpreter
+            .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+        return Some(None);
+    }
+    if wanted == Unreflect::VarHandle {
+        return Some(Some(java_lang_invoke_varhandle::new_field_handle(
+            env,
+            &member.declaring,
+            &member.name,
+            &member.descriptor,
+            member.modifiers,
+        )));
+    }
+    let varargs = is_varargs(
+        env,
+        &member.declaring,
+        &member.name,
+        &member.descriptor,
+        member.modifiers,
+    );
+    Some(Some(direct_handle(
+        env,
+        &direct,
+        &member.declaring,
+        varargs,
+    )))
+}
+
+// ---------------------------------------------------------------------------
+// MethodHandle
+
+fn new_handle(
+    env: &mut NativeEnv,
+    descriptor: &str,
+    form: &str,
+    fields: &[(&str, HeapValue)],
+) -> HeapValue {
+    let method_type = new_method_type(env.heap, descriptor);
+    let obj = env.heap.alloc_object(METHOD_HANDLE);
+    if let Some(real) = env.heap.get_mut(obj.id) {
+        real.set_field("type", method_type);
+        real.set_field("form", HeapValue::String(form.to_string()));
+        for (name, value) in fields {
+            real.set_field(name, value.clone());
+        }
+    }
+    HeapValue::Object(obj)
+}
+
+/// The method descriptor of a method handle's type.
+pub fn handle_descriptor(heap: &Heap, handle: &HeapValue) -> Option<String> {
+    match handle {
+        HeapValue::Object(obj) if obj.class_name == METHOD_HANDLE => {
+            method_type_descriptor(heap, &field(heap, obj.id, "type"))
+        }
+        _ => None,
+    }
+}
+
+/// The method handle a method is called on, and its type's descriptor.
+fn this_handle<'a>(
+    env: &NativeEnv,
+    receiver: Option<&'a HeapValue>,
+) -> Option<(&'a HeapValue, String)> {
+    let this = receiver?;
+    Some((this, handle_descriptor(env.heap, this)?))
+}
+
+fn bind_to(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let (this, descriptor) = this_handle(env, receiver)?;
+    let (params, _) = split_method_descriptor(&descriptor);
+    if params.first().is_none_or(|param| param.len() == 1) {
+        let message = format!(
+            "no leading reference parameter: {}",
+            env.to_java_string(args.first()?)
+        );
+        illegal_argument(env, &message);
+        return Some(None);
+    }
+    Some(insert_arguments(
+        env,
+        this.clone(),
+        0,
+        vec![args.first()?.clone()],
+    ))
+}
+
+fn as_type(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let (this, descriptor) = this_handle(env, receiver)?;
+    let Some(new_type) = method_type_descriptor(env.heap, args.first()?) else {
+        return Some(null_pointer(env));
+    };
+    if new_type == descriptor {
+        return Some(Some(this.clone()));
+    }
+    if !type_convertible(env, &new_type, &descriptor) {
+        cannot_convert(env, &descriptor, &new_type);
+        return Some(None);
+    }
+    Some(Some(new_handle(
+        env,
+        &new_type,
+        "asType",
+        &[("target", this.clone())],
+    )))
+}
+
+fn as_spreader(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let (this, descriptor) = this_handle(env, receiver)?;
+    let Some(array_class) = java_lang_class::class_name(env.heap, args.first()?) else {
+        return Some(null_pointer(env));
+    };
+    let length = args.get(1)?.as_int();
+    let (params, ret) = split_method_descriptor(&descriptor);
+    let Some(fixed) = usize::try_from(length)
+        .ok()
+        .and_then(|length| params.len().checked_sub(length))
+    else {
+        illegal_argument(env, &format!("bad spread array length {}", length));
+        return Some(None);
+    };
+    let Some(component) = array_class.strip_prefix('[') else {
+        illegal_argument(env, "not an array type");
+        return Some(None);
+    };
+    if !params[fixed..]
+        .iter()
+        .all(|param| value_convertible(env, component, param))
+    {
+        cannot_convert(env, &descriptor, &descriptor);
+        return Some(None);
+    }
+    let mut spread_params = params[..fixed].to_vec();
+    spread_params.push(&array_class);
+    Some(Some(new_handle(
+        env,
+        &method_descriptor(&spread_params, ret),
+        "spread",
+        &[("target", this.clone()), ("count", HeapValue::Int(length))],
+    )))
+}
+
+fn is_varargs_handle(heap: &Heap, handle: &HeapValue) -> bool {
+    match handle {
+        HeapValue::Object(obj) => int_field(heap, obj.id, "varargs") != 0,
+        _ => false,
+    }
+}
+
+/// `MethodHandles.insertArguments`, and `bindTo` at position 0: checks
+/// the values against the parameters they fill.
+fn insert_arguments(
+    env: &mut NativeEnv,
+    target: HeapValue,
+    position: i32,
+    values: Vec<HeapValue>,
+) -> Option<HeapValue> {
+    let Some(target_type) = handle_descriptor(env.heap, &target) else {
+        return null_pointer(env);
+    };
+    let (params, ret) = split_method_descriptor(&target_type);
+    let Some(start) = usize::try_from(position)
+        .ok()
+        .filter(|start| start + values.len() <= params.len())
+    else {
+        illegal_argument(env, "too many values to insert");
+        return None;
+    };
+    for (value, param) in values.iter().zip(&params[start..]) {
+        convert(env, value.clone(), OBJECT, param)?;
+    }
+    let mut remaining = params.clone();
+    remaining.drain(start..start + values.len());
+    let values = java_lang_class::reference_array(env.heap, "java/lang/Object", values);
+    Some(new_handle(
+        env,
+        &method_descriptor(&remaining, ret),
+        "insert",
+        &[
+            ("target", target),
+            ("position", HeapValue::Int(position)),
+            ("values", values),
+        ],
+    ))
+}
+
+/// `invokeExact` and `invoke` at a call site of type `site`. Exact
+/// invocation requires the handle's type to match; `invoke` adapts the
+/// arguments and result as `asType` would.
+fn invoke_polymorphic(
+    env: &mut NativeEnv,
+    handle: &HeapValue,
+    args: &[HeapValue],
+    site: &str,
+    exact: bool,
+) -> Option<HeapValue> {
+    let handle_type = handle_descriptor(env.heap, handle)?;
+    let site_ret = split_method_descriptor(site).1;
+    let result = if handle_type == site {
+        call(env, handle, args.to_vec())?
+    } else if exact {
+        let message = format!(
+            "expected {} but found {}",
+            type_string(env, &handle_type),
+            type_string(env, site)
+        );
+        env.interpreter
+            .throw_new(env.heap, WRONG_METHOD_TYPE, Some(&message));
+        return None;
+    } else {
+        let converted = adapt_arguments(env, site, &handle_type, args)?;
+        let result = call(env, handle, converted)?;
+        convert(
+            env,
+            result,
+            split_method_descriptor(&handle_type).1,
+            site_ret,
+        )?
+    };
+    (site_ret != "V").then_some(result)
+}
+
+/// The arguments of a call site of type `site`, converted to the
+/// parameter types of `target`. Throws `WrongMethodTypeException` if
+/// the types cannot be adapted to each other.
+pub fn adapt_arguments(
+    env: &mut NativeEnv,
+    site: &str,
+    target: &str,
+    args: &[HeapValue],
+) -> Option<Vec<HeapValue>> {
+    if !type_convertible(env, site, target) {
+        cannot_convert(env, target, site);
+        return None;
+    }
+    let (site_params, _) = split_method_descriptor(site);
+    let (params, _) = split_method_descriptor(target);
+    let mut converted = Vec::with_capacity(args.len());
+    for ((value, from), to) in args.iter().zip(&site_params).zip(&params) {
+        converted.push(convert(env, value.clone(), from, to)?);
+    }
+    Some(converted)
+}
+
+/// `invokeWithArguments`: boxed arguments in, boxed result out. A
+/// varargs handle collects trailing arguments into its array parameter.
+pub fn invoke_with_arguments(
+    env: &mut NativeEnv,
+    handle: &HeapValue,
+    mut values: Vec<HeapValue>,
+) -> Option<HeapValue> {
+    let Some(handle_type) = handle_descriptor(env.heap, handle) else {
+        return null_pointer(env);
+    };
+    let (params, ret) = split_method_descriptor(&handle_type);
+    if let Some(array_type) = params
+        .last()
+        .filter(|_| is_varargs_handle(env.heap, handle))
+    {
+        // The arguments are all `Object`s, which never pass as the array
+        // itself, so the trailing ones are always collected.
+        let fixed = params.len() - 1;
+        if values.len() >= fixed {
+            let component = &array_type[1..];
+            let mut elements = Vec::new();
+            for value in values.split_off(fixed) {
+                elements.push(convert(env, value, OBJECT, component)?);
+            }
+            values.push(new_array(env.heap, component, elements));
+        }
+    }
+    if values.len() != params.len() {
+        let site = method_descriptor(&vec![OBJECT; values.len()], OBJECT);
+        cannot_convert(env, &handle_type, &site);
+        return None;
+    }
+    let mut args = Vec::with_capacity(values.len());
+    for (value, param) in values.into_iter().zip(&params) {
+        args.push(convert(env, value, OBJECT, param)?);
+    }
+    let result = call(env, handle, args)?;
+    Some(java_lang_reflect::box_result(env.heap, result, ret))
+}
+
+/// Runs a method handle on arguments of exactly its parameter types.
+/// Returns the result, `null` for `void`, or `None` once an exception is
+/// pending.
+pub fn call(env: &mut NativeEnv, handle: &HeapValue, args: Vec<HeapValue>) -> Option<HeapValue> {
+    let HeapValue::Object(obj) = handle else {
+        return null_pointer(env);
+    };
+    let mark = env
+        .interpreter
+        .suspend_roots(args.iter().chain(std::iter::once(handle)));
+    let result = run(env, obj.id, args);
+    env.interpreter.resume_roots(mark);
+    result.filter(|_| env.interpreter.pending_exception().is_none())
+}
+
+fn run(env: &mut NativeEnv, id: u64, mut args: Vec<HeapValue>) -> Option<HeapValue> {
+    let form = match field(env.heap, id, "form") {
+        HeapValue::String(form) => form,
+        _ => return None,
+    };
+    let target = field(env.heap, id, "target");
+    let position = int_field(env.heap, id, "position").max(0) as usize;
+    let handle_type = method_type_descriptor(env.heap, &field(env.heap, id, "type"))?;
+    match form.as_str() {
+        "direct" => invoke_direct(env, id, args),
+        "constant" => {
+            let value = field(env.heap, id, "value");
+            convert(env, value, OBJECT, split_method_descriptor(&handle_type).1)
+        }
+        "identity" => args.into_iter().next(),
+        "insert" => {
+            let target_type = handle_descriptor(env.heap, &target)?;
+            let (params, _) = split_method_descriptor(&target_type);
+            let values = contents(env.heap, &field(env.heap, id, "values"));
+            for (offset, value) in values.into_iter().enumerate() {
+                let slot = position + offset;
+                let value = convert(env, value, OBJECT, params.get(slot)?)?;
+                args.insert(slot, value);
+            }
+            call(env, &target, args)
+        }
+        "drop" => {
+            let count = int_field(env.heap, id, "count").max(0) as usize;
+            args.drain(position..position + count);
+            call(env, &target, args)
+        }
+        "asType" => {
+            let target_type = handle_descriptor(env.heap, &target)?;
+            let (from_params, from_ret) = split_method_descriptor(&handle_type);
+            let (to_params, to_ret) = split_method_descriptor(&target_type);
+            let mut converted = Vec::with_capacity(args.len());
+            for ((value, from), to) in args.into_iter().zip(&from_params).zip(&to_params) {
+                converted.push(convert(env, value, from, to)?);
+            }
+            let result = call(env, &target, converted)?;
+            convert(env, result, to_ret, from_ret)
+        }
+        "filterReturn" => {
+            let target_type = handle_descriptor(env.heap, &target)?;
+            let result = call(env, &target, args)?;
+            let filter = field(env.heap, id, "filter");
+            match split_method_descriptor(&target_type).1 {
+                "V" => call(env, &filter, Vec::new()),
+                _ => call(env, &filter, vec![result]),
+            }
+        }
+        "filterArguments" => {
+            let filters = contents(env.heap, &field(env.heap, id, "filters"));
+            let mark = env.interpreter.suspend_roots(std::iter::empty());
+            for (offset, filter) in filters.iter().enumerate() {
+                if filter.is_null() {
+                    continue;
+                }
+                let slot = position + offset;
+                let Some(value) = call(env, filter, vec![args[slot].clone()]) else {
+                    env.interpreter.resume_roots(mark);
+                    return None;
+                };
+                env.interpreter.suspend_roots([&value]);
+                args[slot] = value;
+            }
+            env.interpreter.resume_roots(mark);
+            call(env, &target, args)
+        }
+        "guard" => {
+            let test = field(env.heap, id, "test");
+            let test_type = handle_descriptor(env.heap, &test)?;
+            let count = split_method_descriptor(&test_type).0.len();
+            let passed = call(env, &test, args[..count].to_vec())?;
+            match passed.as_int() {
+                0 => call(env, &field(env.heap, id, "fallback"), args),
+                _ => call(env, &target, args),
+            }
+        }
+        "permute" => {
+            let order = contents(env.heap, &field(env.heap, id, "order"));
+            let permuted = order
+                .iter()
+                .map(|index| args.get(index.as_int() as usize).cloned())
+                .collect::<Option<Vec<_>>>()?;
+            call(env, &target, permuted)
+        }
+        "spread" => {
+            let count = int_field(env.heap, id, "count").max(0) as usize;
+            let array = args.pop()?;
+            let elements = match &array {
+                HeapValue::Null if count == 0 => Vec::new(),
+                HeapValue::Null => return null_pointer(env),
+                _ => contents(env.heap, &array),
+            };
+            if elements.len() != count {
+                illegal_argument(env, &format!("array is not of length {}", count));
+                return None;
+            }
+            let array_class = java_lang_class::value_class(env.heap, &array)
+                .unwrap_or_else(|| "[Ljava/lang/Object;".to_string());
+            let component = array_class[1..].to_string();
+            let target_type = handle_descriptor(env.heap, &target)?;
+            let (params, _) = split_method_descriptor(&target_type);
+            let fixed = args.len();
+            for (offset, element) in elements.into_iter().enumerate() {
+                args.push(convert(
+                    env,
+                    element,
+                    &component,
+                    params.get(fixed + offset)?,
+                )?);
+            }
+            call(env, &target, args)
+        }
+        "arrayGet" | "arraySet" | "arrayLength" => {
+            let array = args.first()?.clone();
+            let HeapValue::Array(arr) = &array else {
+                return null_pointer(env);
+            };
+            let length = env.heap.get_array(arr.id)?.content.len();
+            if form == "arrayLength" {
+                return Some(HeapValue::Int(length as i32));
+            }
+            let index = args.get(1)?.as_int();
+            let Some(slot) = usize::try_from(index).ok().filter(|slot| *slot < length) else {
+                env.interpreter.throw_array_index(env.heap, index, length);
+                return None;
+            };
+            if form == "arrayGet" {
+                return env.heap.get_array(arr.id)?.content.get(slot).cloned();
+            }
+            let value = args.get(2)?.clone();
+            if !check_array_store(env, arr.id, &value) {
+                return None;
+            }
+            env.heap.get_array_mut(arr.id)?.content[slot] = value;
+            Some(HeapValue::Null)
+        }
+        _ => None,
+    }
+}
+
+/// Throws `ArrayStoreException` unless `value` may be stored in the
+/// reference array `id`.
+pub fn check_array_store(env: &mut NativeEnv, id: u64, value: &HeapValue) -> bool {
+    let component = env
+        .heap
+        .get_array(id)
+        .and_then(|real| real.component_class.clone());
+    let (Some(component), Some(class)) = (component, java_lang_class::value_class(env.heap, value))
+    else {
+        return true;
+    };
+    if fits(env, &class, &component) {
+        return true;
+    }
+    env.interpreter.throw_new(
+        env.heap,
+        "java/lang/ArrayStoreException",
+        Some(&class.replace('/', ".")),
+    );
+    false
+}
+
+fn invoke_direct(env: &mut NativeEnv, id: u64, mut args: Vec<HeapValue>) -> Option<HeapValue> {
+    let kind = int_field(env.heap, id, "refKind") as u8;
+    let class = java_lang_class::class_name(env.heap, &field(env.heap, id, "clazz"))?;
+    let (HeapValue::String(name), HeapValue::String(descriptor)) = (
+        field(env.heap, id, "name"),
+        field(env.heap, id, "memberDescriptor"),
+    ) else {
+        return None;
+    };
+    let interpreter = env.interpreter;
+    let result = match kind {
+        REF_GET_STATIC | REF_PUT_STATIC | REF_INVOKE_STATIC | REF_NEW_INVOKE_SPECIAL
+            if !interpreter.ensure_class_initialized(env.loader, &class, env.heap) =>
+        {
+            return None;
+        }
+        REF_GET_STATIC => env
+            .loader
+            .get_static_field(&class, &name)
+            .unwrap_or_else(|| Interpreter::default_value_for_descriptor(&descriptor)),
+        REF_PUT_STATIC => {
+            let value = args.pop()?;
+            env.loader.set_static_field(&class, &name, value);
+            HeapValue::Null
+        }
+        REF_GET_FIELD | REF_PUT_FIELD => {
+            let Some(receiver) = object_id(args.first()?) else {
+                return null_pointer(env);
+            };
+            if kind == REF_GET_FIELD {
+                env.heap
+                    .get(receiver)
+                    .and_then(|obj| obj.get_field(&name))
+                    .cloned()
+                    .unwrap_or_else(|| Interpreter::default_value_for_descriptor(&descriptor))
+            } else {
+                let value = args.pop()?;
+                env.heap.get_mut(receiver)?.set_field(&name, value);
+                HeapValue::Null
+            }
+        }
+        REF_INVOKE_STATIC => interpreter
+            .invoke_nonvirtual(
+                env.loader,
+                env.heap,
+                &class,
+                &name,
+                &descriptor,
+                None,
+                &args,
+            )
+            .unwrap_or(HeapValue::Null),
+        REF_NEW_INVOKE_SPECIAL => {
+            let instance = HeapValue::Object(env.heap.alloc_object(&class));
+            let mark = interpreter.suspend_roots([&instance]);
+            interpreter.invoke_nonvirtual(
+                env.loader,
+                env.heap,
+                &class,
+                "<init>",
+                &descriptor,
+                Some(instance.clone()),
+                &args,
+            );
+            interpreter.resume_roots(mark);
+            instance
+        }
+        _ => {
+            if args.is_empty() {
+                return None;
+            }
+            let receiver = args.remove(0);
+            if receiver.is_null() {
+                return null_pointer(env);
+            }
+            let result = match kind {
+                REF_INVOKE_SPECIAL => interpreter.invoke_nonvirtual(
+                    env.loader,
+                    env.heap,
+                    &class,
+                    &name,
+                    &descriptor,
+                    Some(receiver),
+                    &args,
+                ),
+                _ => interpreter.invoke_dispatched(
+                    env.loader,
+                    env.heap,
+                    &class,
+                    &name,
+                    &descriptor,
+                    &receiver,
+                    &args,
+                ),
+            };
+            result.unwrap_or(HeapValue::Null)
+        }
+    };
+    Some(result)
+}
+
+// ---------------------------------------------------------------------------
+// Conversions
+
+fn is_primitive(descriptor: &str) -> bool {
+    descriptor.len() == 1
+}
+
+/// Whether widening takes primitive `from` to primitive `to`.
+fn widens(from: &str, to: &str) -> bool {
+    from == to
+        || matches!(
+            (from, to),
+            ("B", "S" | "I" | "J" | "F" | "D")
+                | ("S" | "C", "I" | "J" | "F" | "D")
+                | ("I", "J" | "F" | "D")
+                | ("J", "F" | "D")
+                | ("F", "D")
+        )
+}
+
+fn widen(value: &HeapValue, to: &str) -> HeapValue {
+    let as_long = match value {
+        HeapValue::Long(v) => *v,
+        other => other.as_int() as i64,
+    };
+    let as_double = match value {
+        HeapValue::Float(v) => *v as f64,
+        HeapValue::Double(v) => *v,
+        _ => as_long as f64,
+    };
+    match to {
+        "J" => HeapValue::Long(as_long),
+        "F" => HeapValue::Float(as_double as f32),
+        "D" => HeapValue::Double(as_double),
+        _ => value.clone(),
+    }
+}
+
+/// Whether an object of class `from` may be used as a `to`; boxes also
+/// count as `Number`, `Comparable` and `Serializable`.
+fn fits(env: &mut NativeEnv, from: &str, to: &str) -> bool {
+    if env.interpreter.is_assignable(env.loader, from, to) {
+        return true;
+    }
+    match to {
+        "java/lang/Number" => {
+            java_lang_boxing::is_box_class(from)
+                && !matches!(from, "java/lang/Boolean" | "java/lang/Character")
+        }
+        "java/lang/Comparable" | "java/io/Serializable" => {
+            java_lang_boxing::is_box_class(from) || from == "java/lang/String"
+        }
+        "java/lang/CharSequence" => from == "java/lang/String",
+        _ => false,
+    }
+}
+
+/// Whether a value of type `from` converts to `to` under `asType` rules.
+/// Reference casts and unboxing from a supertype of the boxes are
+/// checked when a value arrives.
+fn value_convertible(env: &mut NativeEnv, from: &str, to: &str) -> bool {
+    if from == to || from == "V" || to == "V" {
+        return true;
+    }
+    match (is_primitive(from), is_primitive(to)) {
+        (true, true) => widens(from, to),
+        (true, false) => match java_lang_boxing::box_class(from) {
+            Some(boxed) => fits(env, boxed, descriptor_type_name(to)),
+            None => false,
+        },
+        (false, true) => {
+            let class = descriptor_type_name(from);
+            if java_lang_boxing::is_box_class(class) {
+                widens(java_lang_boxing::primitive_descriptor(class), to)
+            } else {
+                matches!(
+                    class,
+                    "java/lang/Object"
+                        | "java/lang/Number"
+                        | "java/lang/Comparable"
+                        | "java/io/Serializable"
+                )
+            }
+        }
+        (false, false) => true,
+    }
+}
+
+/// Whether a handle of type `to` can be adapted to type `from`.
+fn type_convertible(env: &mut NativeEnv, from: &str, to: &str) -> bool {
+    let (from_params, from_ret) = split_method_descriptor(from);
+    let (to_params, to_ret) = split_method_descriptor(to);
+    from_params.len() == to_params.len()
+        && from_params
+            .iter()
+            .zip(&to_params)
+            .all(|(from, to)| value_convertible(env, from, to))
+        && value_convertible(env, to_ret, from_ret)
+}
+
+/// Converts `value` of type `from` to type `to`: widening, boxing,
+/// unboxing and reference casts. Throws `ClassCastException` or
+/// `NullPointerException` and returns `None` when the value does not fit.
+pub fn convert(env: &mut NativeEnv, value: HeapValue, from: &str, to: &str) -> Option<HeapValue> {
+    if from == to {
+        return Some(value);
+    }
+    if to == "V" {
+        return Some(HeapValue::Null);
+    }
+    if from == "V" {
+        return Some(Interpreter::default_value_for_descriptor(to));
+    }
+    match (is_primitive(from), is_primitive(to)) {
+        (true, true) => Some(widen(&value, to)),
+        (true, false) => {
+            let boxed = java_lang_boxing::box_class(from)?;
+            Some(java_lang_boxing::box_value(env.heap, boxed, value))
+        }
+        (false, true) => {
+            let HeapValue::Object(obj) = &value else {
+                return null_pointer(env);
+            };
+            let raw = java_lang_boxing::unbox(env.heap, obj)
+                .filter(|_| widens(java_lang_boxing::primitive_descriptor(&obj.class_name), to));
+            match raw {
+                Some(raw) => Some(widen(&raw, to)),
+                None => {
+                    let target = java_lang_boxing::box_class(to).unwrap_or("java/lang/Number");
+                    class_cast(env, &obj.class_name, target)
+                }
+            }
+        }
+        (false, false) => {
+            let Some(class) = java_lang_class::value_class(env.heap, &value) else {
+                return Some(value);
+            };
+            let target = descriptor_type_name(to);
+            if fits(env, &class, target) {
+                Some(value)
+            } else {
+                class_cast(env, &class, target)
+            }
+        }
+    }
+}
+
+fn class_cast(env: &mut NativeEnv, from: &str, to: &str) -> Option<HeapValue> {
+    let message = format!(
+        "Cannot cast {} to {}",
+        from.replace('/', "."),
+        to.replace('/', ".")
+    );
+    env.interpreter
+        .throw_new(env.heap, "java/lang/ClassCastException", Some(&message));
+    None
+}
+
+fn cannot_convert(env: &mut NativeEnv, handle_type: &str, site: &str) {
+    let message = format!(
+        "cannot convert MethodHandle{} to {}",
+        type_string(env, handle_type),
+        type_string(env, site)
+    );
+    env.interpreter
+        .throw_new(env.heap, WRONG_METHOD_TYPE, Some(&message));
+}
+
+/// A new `component[]` holding `values`, which are already of the
+/// component type.
+pub fn new_array(heap: &mut Heap, component: &str, values: Vec<HeapValue>) -> HeapValue {
+    let element_type = match component {
+        "Z" => ArrayType::Boolean,
+        "B" => ArrayType::Byte,
+        "C" => ArrayType::Char,
+        "S" => ArrayType::Short,
+        "I" => ArrayType::Int,
+        "J" => ArrayType::Long,
+        "F" => ArrayType::Float,
+        "D" => ArrayType::Double,
+        _ => {
+            return java_lang_class::reference_array(heap, descriptor_type_name(component), values)
+        }
+    };
+    let mut array = heap.alloc_array(values.len(), element_type);
+    array.content = values;
+    if let Some(real) = heap.get_array_mut(array.id) {
+        real.content = array.content.clone();
+    }
+    HeapValue::Array(array)
+}
+
+// ---------------------------------------------------------------------------
+// Constants and bootstrap methods
+
+/// The value of a `CONSTANT_MethodHandle`, `CONSTANT_MethodType` or
+/// `CONSTANT_Dynamic` entry of `runtime`, resolved on first use and the
+/// same object ever after. `None` once an exception is pending.
+pub fn resolve_constant(
+    env: &mut NativeEnv,
+    runtime: &RuntimeClass,
+    index: u16,
+) -> Option<HeapValue> {
+    if let Some(value) = env.interpreter.resolved_constant(&runtime.name, index) {
+        return Some(value);
+    }
+    let class = &runtime.class;
+    let value = match class.constant(index)? {
+        ConstantPoolEntry::MethodType { descriptor_index } => {
+            new_method_type(env.heap, class.get_utf8(*descriptor_index)?)
+        }
+        ConstantPoolEntry::MethodHandle {
+            reference_kind,
+            reference_index,
+        } => {
+            let (class_index, name_and_type_index) = match class.constant(*reference_index)? {
+                ConstantPoolEntry::FieldRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolEntry::MethodRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolEntry::InterfaceMethodRef {
+                    class_index,
+                    name_and_type_index,
+                } => (*class_index, *name_and_type_index),
+                _ => return None,
+            };
+            let refc = class.get_class_name(class_index)?.to_string();
+            let (name, descriptor) = class.get_name_and_type(name_and_type_index)?;
+            let member = Direct {
+                kind: *reference_kind,
+                class: refc.clone(),
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            };
+            let flags = env
+                .interpreter
+                .find_method(env.loader, &refc, name, descriptor)
+                .map_or(0, |(_, flags)| flags);
+            let varargs = is_varargs(env, &refc, name, descriptor, flags);
+            direct_handle(env, &member, &refc, varargs)
+        }
+        ConstantPoolEntry::Dynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => dynamic_constant(
+            env,
+            runtime,
+            *bootstrap_method_attr_index,
+            *name_and_type_index,
+        )?,
+        _ => return None,
+    };
+    env.interpreter
+        .add_resolved_constant(&runtime.name, index, value.clone());
+    Some(value)
+}
+
+/// A static argument of a bootstrap method, boxed.
+fn static_argument(env: &mut NativeEnv, runtime: &RuntimeClass, index: u16) -> Option<HeapValue> {
+    let class = &runtime.class;
+    let (boxed, raw) = match class.constant(index)? {
+        ConstantPoolEntry::Integer(v) => ("java/lang/Integer", HeapValue::Int(*v)),
+        ConstantPoolEntry::Long(v) => ("java/lang/Long", HeapValue::Long(*v)),
+        ConstantPoolEntry::Float(v) => ("java/lang/Float", HeapValue::Float(*v)),
+        ConstantPoolEntry::Double(v) => ("java/lang/Double", HeapValue::Double(*v)),
+        ConstantPoolEntry::String { string_index } => {
+            return Some(env.heap.alloc_string(class.get_utf8(*string_index)?));
+        }
+        ConstantPoolEntry::Class { name_index } => {
+            let name = class.get_utf8(*name_index)?;
+            return Some(env.interpreter.class_mirror(env.heap, name));
+        }
+        ConstantPoolEntry::Dynamic {
+            name_and_type_index,
+            ..
+        } => {
+            let (_, descriptor) = class.get_name_and_type(*name_and_type_index)?;
+            let descriptor = descriptor.to_string();
+            let value = resolve_constant(env, runtime, index)?;
+            return Some(java_lang_reflect::box_result(env.heap, value, &descriptor));
+        }
+        _ => return resolve_constant(env, runtime, index),
+    };
+    Some(java_lang_boxing::box_value(env.heap, boxed, raw))
+}
+
+/// Resolves a `CONSTANT_Dynamic`: calls its bootstrap method with a
+/// lookup on the class, the constant's name and type, and the static
+/// arguments, then converts the result to the constant's type.
+fn dynamic_constant(
+    env: &mut NativeEnv,
+    runtime: &RuntimeClass,
+    bootstrap_index: u16,
+    name_and_type_index: u16,
+) -> Option<HeapValue> {
+    let class = &runtime.class;
+    let bootstrap = class
+        .bootstrap_methods()
+        .get(bootstrap_index as usize)?
+        .clone();
+    let (name, descriptor) = class.get_name_and_type(name_and_type_index)?;
+    let (name, descriptor) = (name.to_string(), descriptor.to_string());
+
+    let mark = env.interpreter.suspend_roots(std::iter::empty());
+    let result = (|| {
+        let handle = resolve_constant(env, runtime, bootstrap.method_ref)?;
+        env.interpreter.suspend_roots([&handle]);
+        let mut args = vec![
+            new_lookup(env, &runtime.name, true),
+            env.heap.alloc_string(&name),
+            env.interpreter
+                .class_mirror(env.heap, descriptor_type_name(&descriptor)),
+        ];
+        env.interpreter.suspend_roots(&args);
+        for index in &bootstrap.arguments {
+            let arg = static_argument(env, runtime, *index)?;
+            env.interpreter.suspend_roots([&arg]);
+            args.push(arg);
+        }
+        let value = invoke_with_arguments(env, &handle, args)?;
+        convert(env, value, OBJECT, &descriptor)
+    })();
+    env.interpreter.resume_roots(mark);
+    if result.is_none() {
+        wrap_bootstrap_exception(env);
+    }
+    result
+}
+
+/// Rethrows an exception from a bootstrap method as the cause of a
+/// `BootstrapMethodError`; errors pass through unchanged.
+fn wrap_bootstrap_exception(env: &mut NativeEnv) {
+    let Some(exception) = env.interpreter.take_pending_exception() else {
+        return;
+    };
+    let is_error = java_lang_class::value_class(env.heap, &exception).is_some_and(|class| {
+        env.interpreter
+            .is_subclass_of(env.loader, &class, "java/lang/Error")
+    });
+    if is_error {
+        env.interpreter.throw(exception);
+        return;
+    }
+    let backtrace = env.interpreter.backtrace();
+    let wrapper = java_lang_throwable::new_throwable(
+        env.heap,
+        "java/lang/BootstrapMethodError",
+        Some("bootstrap method initialization exception"),
+        backtrace,
+    );
+    if let HeapValue::Object(obj) = &wrapper {
+        if let Some(real) = env.heap.get_mut(obj.id) {
+            real.set_field("cause", exception);
+        }
+    }
+    env.interpreter.throw(wrapper);
+}
+
+/// The body of a `ConstantBootstraps` method, given its name and type.
+type Bootstrap = fn(&mut NativeEnv, &[HeapValue], String, String) -> Option<Option<HeapValue>>;
+
+/// Runs a `ConstantBootstraps` method on the name and type each one
+/// takes after the lookup; throws `NullPointerException` if either is
+/// `null`.
+fn bootstrap(
+    env: &mut NativeEnv,
+    args: &[HeapValue],
+    body: Bootstrap,
+) -> Option<Option<HeapValue>> {
+    let name = env.heap.string_value(args.get(1)?);
+    let constant_type = java_lang_class::class_name(env.heap, args.get(2)?);
+    let (Some(name), Some(constant_type)) = (name, constant_type) else {
+        return Some(null_pointer(env));
+    };
+    body(env, args, name, constant_type)
+}
+
+/// `ConstantBootstraps.getStaticFinal`: the value of a static final
+/// field `declaring` declares or inherits.
+fn static_final(
+    env: &mut NativeEnv,
+    name: &str,
+    constant_type: &str,
+    declaring: &str,
+) -> Option<Option<HeapValue>> {
+    let field_type = type_descriptor(constant_type);
+    let owner =
+        field_owner(env, declaring, name, &field_type).filter(|(_, flags)| flags & ACC_STATIC != 0);
+    let Some((owner, flags)) = owner else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NoSuchFieldError", Some(name));
+        return Some(None);
+    };
+    if flags & ACC_FINAL == 0 {
+        let message = format!("not a final field: {}", name);
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IncompatibleClassChangeError",
+            Some(&message),
+        );
+        return Some(None);
+    }
+    if !env
+        .interpreter
+        .ensure_class_initialized(env.loader, &owner, env.heap)
+    {
+        return Some(None);
+    }
+    let value = env
+        .loader
+        .get_static_field(&owner, name)
+        .unwrap_or_else(|| Interpreter::default_value_for_descriptor(&field_type));
+    Some(Some(java_lang_reflect::box_result(
+        env.heap,
+        value,
+        &field_type,
+    )))
+}
+
+/// `ConstantBootstraps.fieldVarHandle`, or `staticFieldVarHandle` for
+/// `REF_GET_STATIC`.
+fn field_var_handle(
+    env: &mut NativeEnv,
+    args: &[HeapValue],
+    name: &str,
+    kind: u8,
+) -> Option<Option<HeapValue>> {
+    let declaring = java_lang_class::class_name(env.heap, args.get(3)?);
+    let field_type = java_lang_class::class_name(env.heap, args.get(4)?);
+    let (Some(declaring), Some(field_type)) = (declaring, field_type) else {
+        return Some(null_pointer(env));
+    };
+    let Some(lookup) = Lookup::read(env.heap, args.first()?) else {
+        return Some(null_pointer(env));
+    };
+    let descriptor = type_descriptor(&field_type);
+    let Some((owner, flags)) = find_field(env, &lookup, kind, &declaring, name, &descriptor) else {
+        return Some(None);
+    };
+    Some(Some(java_lang_invoke_varhandle::new_field_handle(
+        env,
+        &owner,
+        name,
+        &descriptor,
+        flags,
+    )))
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+
+fn null_pointer(env: &mut NativeEnv) -> Option<HeapValue> {
+    env.interpreter
+        .throw_new(env.heap, "java/lang/NullPointerException", None);
+    None
+}
+
+fn illegal_argument(env: &mut NativeEnv, message: &str) {
+    env.interpreter.throw_new(
+        env.heap,
+        "java/lang/IllegalArgumentException",
+        Some(message),
+    );
+}
+
+fn index_out_of_bounds(env: &mut NativeEnv, index: i32, length: usize) {
+    let message = format!("Index {} out of bounds for length {}", index, length);
+    env.interpreter.throw_new(
+        env.heap,
+        "java/lang/IndexOutOfBoundsException",
+        Some(&message),
+    );
+}
+
+/// A one-element `Class[]` holding `class`, so that the single-class
+/// overloads share the array paths.
+fn single_array(heap: &mut Heap, class: &HeapValue) -> HeapValue {
+    java_lang_class::reference_array(heap, "java/lang/Class", vec![class.clone()])
+}
+
+fn contents(heap: &Heap, array: &HeapValue) -> Vec<HeapValue> {
+    match array {
+        HeapValue::Array(arr) => heap
+            .get_array(arr.id)
+            .map(|real| real.content.clone())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn object_id(value: &HeapValue) -> Option<u64> {
+    match value {
+        HeapValue::Object(obj) => Some(obj.id),
+        _ => None,
+    }
+}
+
+fn string_hash(text: &str) -> i32 {
+    text.encode_utf16()
+        .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32))
+}
+
+/// An `int` field, 0 when the handle's form does not set it.
+fn int_field(heap: &Heap, id: u64, name: &str) -> i32 {
+    match field(heap, id, name) {
+        HeapValue::Int(value) => value,
+        _ => 0,
+    }
+}
+
+fn field(heap: &Heap, id: u64, name: &str) -> HeapValue {
+    heap.get(id)
+        .and_then(|obj| obj.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
@@ -0,0 +1,420 @@
+use crate::exec::interpreter::Interpreter;
+use crate::native::java_lang_class::{self, descriptor_type_name, split_method_descriptor};
+use crate::native::registry::{BuiltinMethod, NativeMethod, NativeRegistry};
+use crate::native::{java_lang_invoke, java_lang_object, NativeEnv};
+use crate::runtime::heap::{Heap, HeapValue};
+use std::rc::Rc;
+
+pub const VAR_HANDLE: &str = "java/lang/invoke/VarHandle";
+const RECEIVER: &str = "Ljava/lang/invoke/VarHandle;";
+
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("varType", "()Ljava/lang/Class;", |env, this, _| {
+        let handle = VarHandle::read(env.heap, this?)?;
+        let var_class = descriptor_type_name(&handle.var_type);
+        Some(Some(env.interpreter.class_mirror(env.heap, var_class)))
+    }),
+    ("toString", "()Ljava/lang/String;", to_string),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all(VAR_HANDLE, METHODS);
+}
+
+/// The shape of an access mode's type, and what it does to the variable.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Get,
+    Set,
+    CompareAndSet,
+    CompareAndExchange,
+    GetAndSet,
+    GetAndAdd,
+    GetAndBitwise(Bitwise),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bitwise {
+    Or,
+    And,
+    Xor,
+}
+
+impl Mode {
+    /// The mode behind an access-mode method name; the memory-ordering
+    /// variants behave as their plain form on this single-threaded VM.
+    fn from_name(name: &str) -> Option<Self> {
+        let mode = match name {
+            "get" | "getVolatile" | "getOpaque" | "getAcquire" => Mode::Get,
+            "set" | "setVolatile" | "setOpaque" | "setRelease" => Mode::Set,
+            "compareAndSet"
+            | "weakCompareAndSet"
+            | "weakCompareAndSetPlain"
+            | "weakCompareAndSetAcquire"
+            | "weakCompareAndSetRelease" => Mode::CompareAndSet,
+            "compareAndExchange" | "compareAndExchangeAcquire" | "compareAndExchangeRelease" => {
+                Mode::CompareAndExchange
+            }
+            _ => {
+                let base = name
+                    .strip_suffix("Acquire")
+                    .or_else(|| name.strip_suffix("Release"))
+                    .unwrap_or(name);
+                match base {
+                    "getAndSet" => Mode::GetAndSet,
+                    "getAndAdd" => Mode::GetAndAdd,
+                    "getAndBitwiseOr" => Mode::GetAndBitwise(Bitwise::Or),
+                    "getAndBitwiseAnd" => Mode::GetAndBitwise(Bitwise::And),
+                    "getAndBitwiseXor" => Mode::GetAndBitwise(Bitwise::Xor),
+                    _ => return None,
+                }
+            }
+        };
+        Some(mode)
+    }
+
+    /// The mode's parameter types after the coordinates, and its return
+    /// type, for a variable of type `var_type`.
+    fn signature(self, var_type: &str) -> (String, &str) {
+        match self {
+            Mode::Get => (String::new(), var_type),
+            Mode::Set => (var_type.to_string(), "V"),
+            Mode::CompareAndSet => (var_type.repeat(2), "Z"),
+            Mode::CompareAndExchange => (var_type.repeat(2), var_type),
+            _ => (var_type.to_string(), var_type),
+        }
+    }
+
+    /// Whether the mode applies to a variable of this type: arithmetic
+    /// needs a number, bitwise operations an integral type or `boolean`.
+    fn supports(self, var_type: &str, is_final: bool) -> bool {
+        match self {
+            Mode::Get => true,
+            _ if is_final => false,
+            Mode::GetAndAdd => matches!(var_type, "B" | "S" | "C" | "I" | "J" | "F" | "D"),
+            Mode::GetAndBitwise(_) => matches!(var_type, "Z" | "B" | "S" | "C" | "I" | "J"),
+            _ => true,
+        }
+    }
+}
+
+/// The native behind a `VarHandle` access mode at a call site of type
+/// `descriptor`.
+pub fn access_mode(method_name: &str, descriptor: String) -> Option<NativeMethod> {
+    let mode = Mode::from_name(method_name)?;
+    Some(Rc::new(move |env, receiver, args| {
+        access(env, receiver?, mode, args, &descriptor)
+    }))
+}
+
+/// A `VarHandle` read back from its fields.
+struct VarHandle {
+    /// `field`, `static` or `array`.
+    form: String,
+    /// The declaring class of a field, or the array class.
+    class: String,
+    name: String,
+    var_type: String,
+    is_final: bool,
+}
+
+impl VarHandle {
+    fn read(heap: &Heap, value: &HeapValue) -> Option<Self> {
+        let HeapValue::Object(obj) = value else {
+            return None;
+        };
+        let (HeapValue::String(form), HeapValue::String(var_type)) =
+            (field(heap, obj.id, "form"), field(heap, obj.id, "varType"))
+        else {
+            return None;
+        };
+        Some(Self {
+            form,
+            class: java_lang_class::class_name(heap, &field(heap, obj.id, "clazz"))?,
+            name: heap
+                .string_value(&field(heap, obj.id, "name"))
+                .unwrap_or_default(),
+            var_type,
+            is_final: field(heap, obj.id, "final").as_int() != 0,
+        })
+    }
+
+    /// The descriptors of the coordinates that locate the variable.
+    fn coordinates(&self) -> Vec<String> {
+        match self.form.as_str() {
+            "field" => vec![java_lang_class::type_descriptor(&self.class)],
+            "array" => vec![self.class.clone(), "I".to_string()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn new_handle(
+    env: &mut NativeEnv,
+    form: &str,
+    class: &str,
+    name: &str,
+    var_type: &str,
+    is_final: bool,
+) -> HeapValue {
+    let mirror = env.interpreter.class_mirror(env.heap, class);
+    let name = env.heap.alloc_string(name);
+    let obj = env.heap.alloc_object(VAR_HANDLE);
+    if let Some(real) = env.heap.get_mut(obj.id) {
+        real.set_field("form", HeapValue::String(form.to_string()));
+        real.set_field("clazz", mirror);
+        real.set_field("name", name);
+        real.set_field("varType", HeapValue::String(var_type.to_string()));
+        real.set_field("final", HeapValue::Int(is_final as i32));
+    }
+    HeapValue::Object(obj)
+}
+
+/// A handle on field `name` of `declaring`, static or not as `flags` say.
+pub fn new_field_handle(
+    env: &mut NativeEnv,
+    declaring: &str,
+    name: &str,
+    descriptor: &str,
+    flags: u16,
+) -> HeapValue {
+    let form = match flags & ACC_STATIC {
+        0 => "field",
+        _ => "static",
+    };
+    new_handle(
+        env,
+        form,
+        declaring,
+        name,
+        descriptor,
+        flags & ACC_FINAL != 0,
+    )
+}
+
+/// A handle on the elements of arrays of class `array_class`, such as `[I`.
+pub fn new_array_handle(env: &mut NativeEnv, array_class: &str) -> HeapValue {
+    new_handle(env, "array", array_class, "", &array_class[1..], false)
+}
+
+fn to_string(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let handle = VarHandle::read(env.heap, receiver?)?;
+    let coordinates: Vec<String> = handle
+        .coordinates()
+        .iter()
+        .map(|coordinate| java_lang_class::to_string(env, descriptor_type_name(coordinate)))
+        .collect();
+    let text = format!(
+        "VarHandle[varType={}, coord=[{}]]",
+        descriptor_type_name(&handle.var_type).replace('/', "."),
+        coordinates.join(", ")
+    );
+    Some(Some(env.heap.alloc_string(&text)))
+}
+
+/// Where the variable a handle's coordinates locate lives.
+enum Location {
+    Field(u64),
+    Static,
+    Element(u64, usize),
+}
+
+/// Runs access mode `mode` of `handle` for a call site of type `site`.
+fn access(
+    env: &mut NativeEnv,
+    handle_value: &HeapValue,
+    mode: Mode,
+    args: &[HeapValue],
+    site: &str,
+) -> Option<HeapValue> {
+    let handle = VarHandle::read(env.heap, handle_value)?;
+    let var_type = handle.var_type.as_str();
+    if !mode.supports(var_type, handle.is_final) {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/UnsupportedOperationException", None);
+        return None;
+    }
+    let (operands, ret) = mode.signature(var_type);
+    let mode_type = format!(
+        "({}{}{}){}",
+        RECEIVER,
+        handle.coordinates().concat(),
+        operands,
+        ret
+    );
+    let site_type = format!("({}{}", RECEIVER, &site[1..]);
+    let mut with_receiver = vec![handle_value.clone()];
+    with_receiver.extend_from_slice(args);
+    let args = java_lang_invoke::adapt_arguments(env, &site_type, &mode_type, &with_receiver)?;
+    let coordinates = handle.coordinates().len();
+    let (coordinate_args, operands) = args[1..].split_at(coordinates);
+
+    let location = locate(env, &handle, coordinate_args)?;
+    let current = read(env, &handle, &location);
+    let (result, update) = match mode {
+        Mode::Get => (current, None),
+        Mode::Set => (HeapValue::Null, Some(operands[0].clone())),
+        Mode::CompareAndSet | Mode::CompareAndExchange => {
+            let matched = same_value(&current, &operands[0]);
+            let update = matched.then(|| operands[1].clone());
+            match mode {
+                Mode::CompareAndSet => (HeapValue::Int(matched as i32), update),
+                _ => (current, update),
+            }
+        }
+        Mode::GetAndSet => (current, Some(operands[0].clone())),
+        Mode::GetAndAdd => {
+            let sum = add(&current, &operands[0], var_type);
+            (current, Some(sum))
+        }
+        Mode::GetAndBitwise(op) => {
+            let combined = bitwise(&current, &operands[0], var_type, op);
+            (current, Some(combined))
+        }
+    };
+    if let Some(value) = update {
+        if !write(env, &location, &handle, value) {
+            return None;
+        }
+    }
+    let site_ret = split_method_descriptor(site).1;
+    let result = java_lang_invoke::convert(env, result, ret, site_ret)?;
+    (site_ret != "V").then_some(result)
+}
+
+fn locate(env: &mut NativeEnv, handle: &VarHandle, coordinates: &[HeapValue]) -> Option<Location> {
+    match handle.form.as_str() {
+        "static" => env
+            .interpreter
+            .ensure_class_initialized(env.loader, &handle.class, env.heap)
+            .then_some(Location::Static),
+        "field" => match coordinates.first() {
+            Some(HeapValue::Object(obj)) => Some(Location::Field(obj.id)),
+            _ => null_pointer(env),
+        },
+        _ => {
+            let HeapValue::Array(arr) = coordinates.first()? else {
+                return null_pointer(env);
+            };
+            let index = coordinates.get(1)?.as_int();
+            let length = env.heap.get_array(arr.id)?.content.len();
+            match usize::try_from(index).ok().filter(|slot| *slot < length) {
+                Some(slot) => Some(Location::Element(arr.id, slot)),
+                None => {
+                    env.interpreter.throw_array_index(env.heap, index, length);
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn read(env: &mut NativeEnv, handle: &VarHandle, location: &Location) -> HeapValue {
+    let value = match location {
+        Location::Field(id) => env
+            .heap
+            .get(*id)
+            .and_then(|obj| obj.get_field(&handle.name))
+            .cloned(),
+        Location::Static => env.loader.get_static_field(&handle.class, &handle.name),
+        Location::Element(id, slot) => env
+            .heap
+            .get_array(*id)
+            .and_then(|arr| arr.content.get(*slot))
+            .cloned(),
+    };
+    value.unwrap_or_else(|| Interpreter::default_value_for_descriptor(&handle.var_type))
+}
+
+fn write(env: &mut NativeEnv, location: &Location, handle: &VarHandle, value: HeapValue) -> bool {
+    match location {
+        Location::Field(id) => {
+            if let Some(obj) = env.heap.get_mut(*id) {
+                obj.set_field(&handle.name, value);
+            }
+        }
+        Location::Static => env
+            .loader
+            .set_static_field(&handle.class, &handle.name, value),
+        Location::Element(id, slot) => {
+            if !java_lang_invoke::check_array_store(env, *id, &value) {
+                return false;
+            }
+            if let Some(arr) = env.heap.get_array_mut(*id) {
+                arr.content[*slot] = value;
+            }
+        }
+    }
+    true
+}
+
+/// `compareAndSet` equality: identity for references, bitwise for
+/// floating-point values.
+fn same_value(a: &HeapValue, b: &HeapValue) -> bool {
+    match (a, b) {
+        (HeapValue::Int(x), HeapValue::Int(y)) => x == y,
+        (HeapValue::Long(x), HeapValue::Long(y)) => x == y,
+        (HeapValue::Float(x), HeapValue::Float(y)) => x.to_bits() == y.to_bits(),
+        (HeapValue::Double(x), HeapValue::Double(y)) => x.to_bits() == y.to_bits(),
+        _ => java_lang_object::same_reference(a, b),
+    }
+}
+
+/// Narrows an `int` result to a sub-word variable type.
+fn narrow(value: i32, var_type: &str) -> HeapValue {
+    HeapValue::Int(match var_type {
+        "Z" => value & 1,
+        "B" => value as i8 as i32,
+        "S" => value as i16 as i32,
+        "C" => value as u16 as i32,
+        _ => value,
+    })
+}
+
+fn add(a: &HeapValue, b: &HeapValue, var_type: &str) -> HeapValue {
+    match (a, b) {
+        (HeapValue::Long(x), HeapValue::Long(y)) => HeapValue::Long(x.wrapping_add(*y)),
+        (HeapValue::Float(x), HeapValue::Float(y)) => HeapValue::Float(x + y),
+        (HeapValue::Double(x), HeapValue::Double(y)) => HeapValue::Double(x + y),
+        _ => narrow(a.as_int().wrapping_add(b.as_int()), var_type),
+    }
+}
+
+fn bitwise(a: &HeapValue, b: &HeapValue, var_type: &str, op: Bitwise) -> HeapValue {
+    if let (HeapValue::Long(x), HeapValue::Long(y)) = (a, b) {
+        return HeapValue::Long(match op {
+            Bitwise::Or => x | y,
+            Bitwise::And => x & y,
+            Bitwise::Xor => x ^ y,
+        });
+    }
+    let (x, y) = (a.as_int(), b.as_int());
+    narrow(
+        match op {
+            Bitwise::Or => x | y,
+            Bitwise::And => x & y,
+            Bitwise::Xor => x ^ y,
+        },
+        var_type,
+    )
+}
+
+fn null_pointer(env: &mut NativeEnv) -> Option<Location> {
+    env.interpreter
+        .throw_new(env.heap, "java/lang/NullPointerException", None);
+    None
+}
+
+fn field(heap: &Heap, id: u64, name: &str) -> HeapValue {
+    heap.get(id)
+        .and_then(|obj| obj.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
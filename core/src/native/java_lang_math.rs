@@ -1,15 +1,475 @@
+use crate::native::fdlibm;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
 use crate::runtime::heap::HeapValue;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn invoke(
-    method_name: &str,
-    descriptor: &str,
-    args: &[HeapValue],
-) -> Option<Option<HeapValue>> {
-    match (method_name, descriptor) {
-        ("abs", "(I)I") => {
-            let val = args.first().cloned().unwrap_or(HeapValue::Int(0));
-            Some(Some(val.abs()))
+/// `java.lang.Math` and `java.lang.StrictMath` alike, all but `copySign`.
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("abs", "(I)I", |_, _, a| {
+        ok(HeapValue::Int(int(a, 0).wrapping_abs()))
+    }),
+    ("abs", "(J)J", |_, _, a| {
+        ok(HeapValue::Long(long(a, 0).wrapping_abs()))
+    }),
+    ("abs", "(F)F", |_, _, a| {
+        ok(HeapValue::Float(float(a, 0).abs()))
+    }),
+    ("abs", "(D)D", |_, _, a| {
+        ok(HeapValue::Double(double(a, 0).abs()))
+    }),
+    ("max", "(II)I", |_, _, a| {
+        ok(HeapValue::Int(int(a, 0).max(int(a, 1))))
+    }),
+    ("max", "(JJ)J", |_, _, a| {
+        ok(HeapValue::Long(long(a, 0).max(long(a, 1))))
+    }),
+    ("max", "(FF)F", |_, _, a| {
+        let max = java_max(float(a, 0) as f64, float(a, 1) as f64);
+        ok(HeapValue::Float(max as f32))
+    }),
+    ("max", "(DD)D", |_, _, a| {
+        ok(HeapValue::Double(java_max(double(a, 0), double(a, 1))))
+    }),
+    ("min", "(II)I", |_, _, a| {
+        ok(HeapValue::Int(int(a, 0).min(int(a, 1))))
+    }),
+    ("min", "(JJ)J", |_, _, a| {
+        ok(HeapValue::Long(long(a, 0).min(long(a, 1))))
+    }),
+    ("min", "(FF)F", |_, _, a| {
+        let min = java_min(float(a, 0) as f64, float(a, 1) as f64);
+        ok(HeapValue::Float(min as f32))
+    }),
+    ("min", "(DD)D", |_, _, a| {
+        ok(HeapValue::Double(java_min(double(a, 0), double(a, 1))))
+    }),
+    ("sqrt", "(D)D", |_, _, a| unary(a, libm::sqrt)),
+    ("cbrt", "(D)D", |_, _, a| unary(a, fdlibm::cbrt)),
+    ("pow", "(DD)D", |_, _, a| binary(a, fdlibm::pow)),
+    ("exp", "(D)D", |_, _, a| unary(a, fdlibm::exp)),
+    ("expm1", "(D)D", |_, _, a| unary(a, fdlibm::expm1)),
+    ("log", "(D)D", |_, _, a| unary(a, fdlibm::log)),
+    ("log10", "(D)D", |_, _, a| unary(a, fdlibm::log10)),
+    ("log1p", "(D)D", |_, _, a| unary(a, fdlibm::log1p)),
+    ("sin", "(D)D", |_, _, a| unary(a, fdlibm::sin)),
+    ("cos", "(D)D", |_, _, a| unary(a, fdlibm::cos)),
+    ("tan", "(D)D", |_, _, a| unary(a, fdlibm::tan)),
+    ("asin", "(D)D", |_, _, a| unary(a, fdlibm::asin)),
+    ("acos", "(D)D", |_, _, a| unary(a, fdlibm::acos)),
+    ("atan", "(D)D", |_, _, a| unary(a, fdlibm::atan)),
+    ("atan2", "(DD)D", |_, _, a| binary(a, fdlibm::atan2)),
+    ("sinh", "(D)D", |_, _, a| unary(a, fdlibm::sinh)),
+    ("cosh", "(D)D", |_, _, a| unary(a, fdlibm::cosh)),
+    ("tanh", "(D)D", |_, _, a| unary(a, fdlibm::tanh)),
+    ("hypot", "(DD)D", |_, _, a| binary(a, fdlibm::hypot)),
+    ("IEEEremainder", "(DD)D", |_, _, a| {
+        binary(a, fdlibm::remainder)
+    }),
+    ("floor", "(D)D", |_, _, a| unary(a, f64::floor)),
+    ("ceil", "(D)D", |_, _, a| unary(a, f64::ceil)),
+    ("rint", "(D)D", |_, _, a| unary(a, f64::round_ties_even)),
+    ("round", "(D)J", |_, _, a| {
+        ok(HeapValue::Long(round_half_up(double(a, 0)) as i64))
+    }),
+    ("round", "(F)I", |_, _, a| {
+        ok(HeapValue::Int(round_half_up(float(a, 0) as f64) as i32))
+    }),
+    ("signum", "(D)D", |_, _, a| unary(a, signum)),
+    ("signum", "(F)F", |_, _, a| {
+        ok(HeapValue::Float(signum(float(a, 0) as f64) as f32))
+    }),
+    ("toRadians", "(D)D", |_, _, a| {
+        ok(HeapValue::Double(double(a, 0) * DEGREES_TO_RADIANS))
+    }),
+    ("toDegrees", "(D)D", |_, _, a| {
+        ok(HeapValue::Double(double(a, 0) * RADIANS_TO_DEGREES))
+    }),
+    ("ulp", "(D)D", |_, _, a| unary(a, ulp)),
+    ("ulp", "(F)F", |_, _, a| {
+        ok(HeapValue::Float(ulp_f32(float(a, 0))))
+    }),
+    ("nextUp", "(D)D", |_, _, a| unary(a, f64::next_up)),
+    ("nextUp", "(F)F", |_, _, a| {
+        ok(HeapValue::Float(float(a, 0).next_up()))
+    }),
+    ("nextDown", "(D)D", |_, _, a| unary(a, f64::next_down)),
+    ("nextDown", "(F)F", |_, _, a| {
+        ok(HeapValue::Float(float(a, 0).next_down()))
+    }),
+    ("nextAfter", "(DD)D", |_, _, a| binary(a, next_after)),
+    ("nextAfter", "(FD)F", |_, _, a| {
+        ok(HeapValue::Float(next_after_f32(float(a, 0), double(a, 1))))
+    }),
+    ("getExponent", "(D)I", |_, _, a| {
+        ok(HeapValue::Int(
+            ((double(a, 0).to_bits() >> 52) & 0x7FF) as i32 - 1023,
+        ))
+    }),
+    ("getExponent", "(F)I", |_, _, a| {
+        ok(HeapValue::Int(
+            ((float(a, 0).to_bits() >> 23) & 0xFF) as i32 - 127,
+        ))
+    }),
+    ("scalb", "(DI)D", |_, _, a| {
+        ok(HeapValue::Double(libm::scalbn(double(a, 0), int(a, 1))))
+    }),
+    ("scalb", "(FI)F", |_, _, a| {
+        ok(HeapValue::Float(libm::scalbnf(float(a, 0), int(a, 1))))
+    }),
+    ("fma", "(DDD)D", |_, _, a| {
+        ok(HeapValue::Double(libm::fma(
+            double(a, 0),
+            double(a, 1),
+            double(a, 2),
+        )))
+    }),
+    ("fma", "(FFF)F", |_, _, a| {
+        ok(HeapValue::Float(libm::fmaf(
+            float(a, 0),
+            float(a, 1),
+            float(a, 2),
+        )))
+    }),
+    ("random", "()D", |_, _, _| {
+        ok(HeapValue::Double(next_random_double()))
+    }),
+    ("addExact", "(II)I", |env, _, a| {
+        ok(exact_int(env, int(a, 0).checked_add(int(a, 1))))
+    }),
+    ("addExact", "(JJ)J", |env, _, a| {
+        ok(exact_long(env, long(a, 0).checked_add(long(a, 1))))
+    }),
+    ("subtractExact", "(II)I", |env, _, a| {
+        ok(exact_int(env, int(a, 0).checked_sub(int(a, 1))))
+    }),
+    ("subtractExact", "(JJ)J", |env, _, a| {
+        ok(exact_long(env, long(a, 0).checked_sub(long(a, 1))))
+    }),
+    ("multiplyExact", "(II)I", |env, _, a| {
+        ok(exact_int(env, int(a, 0).checked_mul(int(a, 1))))
+    }),
+    ("multiplyExact", "(JI)J", |env, _, a| {
+        ok(exact_long(env, long(a, 0).checked_mul(int(a, 1) as i64)))
+    }),
+    ("multiplyExact", "(JJ)J", |env, _, a| {
+        ok(exact_long(env, long(a, 0).checked_mul(long(a, 1))))
+    }),
+    ("incrementExact", "(I)I", |env, _, a| {
+        ok(exact_int(env, int(a, 0).checked_add(1)))
+    }),
+    ("incrementExact", "(J)J", |env, _, a| {
+        ok(exact_long(env, long(a, 0).checked_add(1)))
+    }),
+    ("decrementExact", "(I)I", |env, _, a| {
+        ok(exact_int(env, int(a, 0).checked_sub(1)))
+    }),
+    ("decrementExact", "(J)J", |env, _, a| {
+        ok(exact_long(env, long(a, 0).checked_sub(1)))
+    }),
+    ("negateExact", "(I)I", |env, _, a| {
+        ok(exact_int(env, int(a, 0).checked_neg()))
+    }),
+    ("negateExact", "(J)J", |env, _, a| {
+        ok(exact_long(env, long(a, 0).checked_neg()))
+    }),
+    ("toIntExact", "(J)I", |env, _, a| {
+        ok(exact_int(env, i32::try_from(long(a, 0)).ok()))
+    }),
+    ("absExact", "(I)I", |env, _, a| {
+        match int(a, 0).checked_abs() {
+            Some(v) => ok(HeapValue::Int(v)),
+            None => throw_arithmetic(
+                env,
+                "Overflow to represent absolute value of Integer.MIN_VALUE",
+            ),
         }
-        _ => None,
+    }),
+    ("absExact", "(J)J", |env, _, a| {
+        match long(a, 0).checked_abs() {
+            Some(v) => ok(HeapValue::Long(v)),
+            None => throw_arithmetic(
+                env,
+                "Overflow to represent absolute value of Long.MIN_VALUE",
+            ),
+        }
+    }),
+    ("multiplyFull", "(II)J", |_, _, a| {
+        ok(HeapValue::Long(int(a, 0) as i64 * int(a, 1) as i64))
+    }),
+    ("multiplyHigh", "(JJ)J", |_, _, a| {
+        let product = long(a, 0) as i128 * long(a, 1) as i128;
+        ok(HeapValue::Long((product >> 64) as i64))
+    }),
+    ("floorDiv", "(II)I", |env, _, a| {
+        let quotient = floor_div(int(a, 0) as i64, int(a, 1) as i64);
+        divided(env, quotient.map(|v| HeapValue::Int(v as i32)))
+    }),
+    ("floorDiv", "(JI)J", |env, _, a| {
+        let quotient = floor_div(long(a, 0), int(a, 1) as i64);
+        divided(env, quotient.map(HeapValue::Long))
+    }),
+    ("floorDiv", "(JJ)J", |env, _, a| {
+        let quotient = floor_div(long(a, 0), long(a, 1));
+        divided(env, quotient.map(HeapValue::Long))
+    }),
+    ("floorMod", "(II)I", |env, _, a| {
+        let modulus = floor_mod(int(a, 0) as i64, int(a, 1) as i64);
+        divided(env, modulus.map(|v| HeapValue::Int(v as i32)))
+    }),
+    ("floorMod", "(JI)I", |env, _, a| {
+        let modulus = floor_mod(long(a, 0), int(a, 1) as i64);
+        divided(env, modulus.map(|v| HeapValue::Int(v as i32)))
+    }),
+    ("floorMod", "(JJ)J", |env, _, a| {
+        let modulus = floor_mod(long(a, 0), long(a, 1));
+        divided(env, modulus.map(HeapValue::Long))
+    }),
+];
+
+/// `Math.copySign` passes a NaN sign through.
+const MATH_COPY_SIGN: &[(&str, &str, BuiltinMethod)] = &[
+    ("copySign", "(DD)D", |_, _, a| {
+        ok(HeapValue::Double(double(a, 0).copysign(double(a, 1))))
+    }),
+    ("copySign", "(FF)F", |_, _, a| {
+        ok(HeapValue::Float(float(a, 0).copysign(float(a, 1))))
+    }),
+];
+
+/// `StrictMath.copySign` treats a NaN sign as positive.
+const STRICT_COPY_SIGN: &[(&str, &str, BuiltinMethod)] = &[
+    ("copySign", "(DD)D", |_, _, a| {
+        let sign = double(a, 1);
+        let sign = if sign.is_nan() { 1.0 } else { sign };
+        ok(HeapValue::Double(double(a, 0).copysign(sign)))
+    }),
+    ("copySign", "(FF)F", |_, _, a| {
+        let sign = float(a, 1);
+        let sign = if sign.is_nan() { 1.0 } else { sign };
+        ok(HeapValue::Float(float(a, 0).copysign(sign)))
+    }),
+];
+
+/// `java.lang.Math` and `java.lang.StrictMath`. The transcendental functions
+/// of `Math` use the same fdlibm code as `StrictMath`; HotSpot's own `Math`
+/// delegates most of them there too, and the spec only asks `Math` to stay
+/// within an ulp or so. `StrictMath` must reproduce fdlibm 5.3 exactly.
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/lang/Math", METHODS);
+    registry.register_all("java/lang/Math", MATH_COPY_SIGN);
+    registry.register_all("java/lang/StrictMath", METHODS);
+    registry.register_all("java/lang/StrictMath", STRICT_COPY_SIGN);
+}
+
+fn int(args: &[HeapValue], i: usize) -> i32 {
+    args.get(i).map_or(0, HeapValue::as_int)
+}
+
+fn long(args: &[HeapValue], i: usize) -> i64 {
+    args.get(i).map_or(0, HeapValue::as_long)
+}
+
+fn float(args: &[HeapValue], i: usize) -> f32 {
+    args.get(i).map_or(0.0, HeapValue::as_float)
+}
+
+fn double(args: &[HeapValue], i: usize) -> f64 {
+    args.get(i).map_or(0.0, HeapValue::as_double)
+}
+
+fn ok(value: HeapValue) -> Option<Option<HeapValue>> {
+    Some(Some(value))
+}
+
+fn unary(args: &[HeapValue], f: fn(f64) -> f64) -> Option<Option<HeapValue>> {
+    ok(HeapValue::Double(f(double(args, 0))))
+}
+
+fn binary(args: &[HeapValue], f: fn(f64, f64) -> f64) -> Option<Option<HeapValue>> {
+    ok(HeapValue::Double(f(double(args, 0), double(args, 1))))
+}
+
+/// A `floorDiv` or `floorMod` result; `None` was a division by zero.
+fn divided(env: &mut NativeEnv, value: Option<HeapValue>) -> Option<Option<HeapValue>> {
+    match value {
+        Some(value) => ok(value),
+        None => throw_arithmetic(env, "/ by zero"),
+    }
+}
+
+const DEGREES_TO_RADIANS: f64 = 0.017453292519943295;
+const RADIANS_TO_DEGREES: f64 = 57.29577951308232;
+
+fn throw_arithmetic(env: &mut NativeEnv, message: &str) -> Option<Option<HeapValue>> {
+    env.interpreter
+        .throw_new(env.heap, "java/lang/ArithmeticException", Some(message));
+    Some(None)
+}
+
+/// Unwraps a checked int result, raising `ArithmeticException` on overflow.
+/// The placeholder pushed alongside a pending exception is never observed.
+fn exact_int(env: &mut NativeEnv, value: Option<i32>) -> HeapValue {
+    value.map(HeapValue::Int).unwrap_or_else(|| {
+        throw_arithmetic(env, "integer overflow");
+        HeapValue::Int(0)
+    })
+}
+
+fn exact_long(env: &mut NativeEnv, value: Option<i64>) -> HeapValue {
+    value.map(HeapValue::Long).unwrap_or_else(|| {
+        throw_arithmetic(env, "long overflow");
+        HeapValue::Long(0)
+    })
+}
+
+/// Quotient rounded toward negative infinity; `MIN / -1` wraps like the
+/// JDK's implementation. `None` on division by zero.
+fn floor_div(x: i64, y: i64) -> Option<i64> {
+    if y == 0 {
+        return None;
+    }
+    let q = x.wrapping_div(y);
+    if (x.wrapping_rem(y) != 0) && ((x ^ y) < 0) {
+        Some(q - 1)
+    } else {
+        Some(q)
+    }
+}
+
+fn floor_mod(x: i64, y: i64) -> Option<i64> {
+    if y == 0 {
+        return None;
+    }
+    let r = x.wrapping_rem(y);
+    if r != 0 && ((r ^ y) < 0) {
+        Some(r + y)
+    } else {
+        Some(r)
+    }
+}
+
+/// `Math.max`: NaN wins, and +0.0 is larger than -0.0.
+fn java_max(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        return f64::NAN;
+    }
+    if a == 0.0 && b == 0.0 {
+        return if a.is_sign_negative() { b } else { a };
+    }
+    if a >= b {
+        a
+    } else {
+        b
+    }
+}
+
+fn java_min(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        return f64::NAN;
+    }
+    if a == 0.0 && b == 0.0 {
+        return if a.is_sign_negative() { a } else { b };
+    }
+    if a <= b {
+        a
+    } else {
+        b
+    }
+}
+
+/// `floor(x + 0.5)` computed without the double rounding that breaks
+/// `round(0.49999999999999994)`. NaN yields 0 and out-of-range values
+/// saturate once the caller casts.
+fn round_half_up(x: f64) -> f64 {
+    if x.is_nan() {
+        return 0.0;
+    }
+    let floor = x.floor();
+    if x - floor >= 0.5 {
+        floor + 1.0
+    } else {
+        floor
+    }
+}
+
+fn signum(x: f64) -> f64 {
+    if x.is_nan() || x == 0.0 {
+        x
+    } else {
+        1.0f64.copysign(x)
+    }
+}
+
+fn ulp(x: f64) -> f64 {
+    let x = x.abs();
+    if x.is_nan() || x.is_infinite() {
+        return x;
+    }
+    if x == f64::MAX {
+        return 2f64.powi(971);
+    }
+    x.next_up() - x
+}
+
+fn ulp_f32(x: f32) -> f32 {
+    let x = x.abs();
+    if x.is_nan() || x.is_infinite() {
+        return x;
+    }
+    if x == f32::MAX {
+        return 2f32.powi(104);
+    }
+    x.next_up() - x
+}
+
+fn next_after(start: f64, direction: f64) -> f64 {
+    if start.is_nan() || direction.is_nan() {
+        f64::NAN
+    } else if start == direction {
+        direction
+    } else if start < direction {
+        start.next_up()
+    } else {
+        start.next_down()
     }
 }
+
+fn next_after_f32(start: f32, direction: f64) -> f32 {
+    if start.is_nan() || direction.is_nan() {
+        f32::NAN
+    } else if start as f64 == direction {
+        direction as f32
+    } else if (start as f64) < direction {
+        start.next_up()
+    } else {
+        start.next_down()
+    }
+}
+
+/// The `java.util.Random` behind `Math.random()`: a 48-bit LCG seeded
+/// once from the clock.
+static RANDOM_SEED: Mutex<Option<u64>> = Mutex::new(None);
+
+const MULTIPLIER: u64 = 0x5DEECE66D;
+const ADDEND: u64 = 0xB;
+const MASK: u64 = (1 << 48) - 1;
+
+fn next_bits(seed: &mut u64, bits: u32) -> u64 {
+    *seed = (seed.wrapping_mul(MULTIPLIER).wrapping_add(ADDEND)) & MASK;
+    *seed >> (48 - bits)
+}
+
+fn next_random_double() -> f64 {
+    let mut guard = RANDOM_SEED.lock().unwrap_or_else(|e| e.into_inner());
+    let seed = guard.get_or_insert_with(|| {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        (nanos ^ 0x5DEECE66D ^ 0x1ED8B55FAC9DEC) & MASK
+    });
+    let high = next_bits(seed, 26);
+    let low = next_bits(seed, 27);
+    ((high << 27) + low) as f64 * (1.0 / (1u64 << 53) as f64)
+}
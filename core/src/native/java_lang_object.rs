@@ -0,0 +1,161 @@
+use crate::loader::class_loader::ClassLoader;
+use crate::native::java_lang_class;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{ArrayRef, ArrayType, Heap, HeapValue, ObjectRef};
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("<init>", "()V", |_, _, _| Some(None)),
+    ("hashCode", "()I", hash_code),
+    ("equals", "(Ljava/lang/Object;)Z", equals),
+    ("toString", "()Ljava/lang/String;", to_string),
+    ("getClass", "()Ljava/lang/Class;", get_class),
+    ("clone", "()Ljava/lang/Object;", |env, this, _| {
+        Some(clone(env, this?))
+    }),
+];
+
+/// `java.lang.Record` adds nothing at runtime beyond its constructor.
+const RECORD_METHODS: &[(&str, &str, BuiltinMethod)] = &[("<init>", "()V", |_, _, _| Some(None))];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/lang/Object", METHODS);
+    registry.register_all("java/lang/Record", RECORD_METHODS);
+}
+
+fn hash_code(
+    _env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let id = receiver.map(identity_hash).unwrap_or(0);
+    Some(Some(HeapValue::Int(id)))
+}
+
+fn equals(
+    _env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let same = match (receiver, args.first()) {
+        (Some(a), Some(b)) => same_reference(a, b),
+        _ => false,
+    };
+    Some(Some(HeapValue::Int(same as i32)))
+}
+
+fn to_string(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let rendered = match receiver? {
+        HeapValue::Object(obj) => default_to_string(env.loader, obj),
+        HeapValue::Array(arr) => array_to_string(arr),
+        other => env.to_java_string(other),
+    };
+    Some(Some(env.heap.alloc_string(&rendered)))
+}
+
+fn get_class(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let class_name = java_lang_class::value_class(env.heap, receiver?)?;
+    Some(Some(env.interpreter.class_mirror(env.heap, &class_name)))
+}
+
+/// A shallow copy of an array, or of an object whose class implements
+/// `Cloneable`.
+fn clone(env: &mut NativeEnv, value: &HeapValue) -> Option<HeapValue> {
+    match value {
+        HeapValue::Array(arr) => clone_array(env.heap, arr),
+        HeapValue::Object(obj) => {
+            if !env
+                .interpreter
+                .is_assignable(env.loader, &obj.class_name, "java/lang/Cloneable")
+            {
+                let name = obj.class_name.replace('/', ".");
+                env.interpreter.throw_new(
+                    env.heap,
+                    "java/lang/CloneNotSupportedException",
+                    Some(&name),
+                );
+                return None;
+            }
+            let fields = env.heap.get(obj.id)?.fields.clone();
+            let mut copy = env.heap.alloc_object(&obj.class_name);
+            copy.fields = fields;
+            if let Some(target) = env.heap.get_mut(copy.id) {
+                target.fields = copy.fields.clone();
+            }
+            Some(HeapValue::Object(copy))
+        }
+        _ => None,
+    }
+}
+
+/// A shallow copy of an array, of the same class.
+pub fn clone_array(heap: &mut Heap, arr: &ArrayRef) -> Option<HeapValue> {
+    let real = heap.get_array(arr.id)?.clone();
+    let mut copy = match &real.component_class {
+        Some(component) => heap.alloc_reference_array(real.content.len(), component),
+        None => heap.alloc_array(real.content.len(), real.element_type),
+    };
+    copy.content = real.content;
+    if let Some(target) = heap.get_array_mut(copy.id) {
+        target.content = copy.content.clone();
+    }
+    Some(HeapValue::Array(copy))
+}
+
+/// Object identity is the heap id, which never changes for the lifetime of
+/// the object.
+pub fn identity_hash(value: &HeapValue) -> i32 {
+    match value {
+        HeapValue::Object(obj) => obj.id as i32,
+        HeapValue::Array(arr) => arr.id as i32,
+        _ => 0,
+    }
+}
+
+pub fn same_reference(a: &HeapValue, b: &HeapValue) -> bool {
+    match (a, b) {
+        (HeapValue::Object(x), HeapValue::Object(y)) => x.id == y.id,
+        (HeapValue::Array(x), HeapValue::Array(y)) => x.id == y.id,
+        (HeapValue::Null, HeapValue::Null) => true,
+        _ => false,
+    }
+}
+
+pub fn default_to_string(loader: &ClassLoader, obj: &ObjectRef) -> String {
+    let class_name = loader.symbolic_name(&obj.class_name);
+    format!("{}@{:x}", class_name.replace('/', "."), obj.id)
+}
+
+pub fn array_to_string(arr: &ArrayRef) -> String {
+    format!("{}@{:x}", array_class_name(arr).replace('/', "."), arr.id)
+}
+
+/// Binary name of an array's class, e.g. `[I` or `[Ljava/lang/String;`.
+pub fn array_class_name(arr: &ArrayRef) -> String {
+    let prefix = match arr.element_type {
+        ArrayType::Boolean => "[Z",
+        ArrayType::Char => "[C",
+        ArrayType::Float => "[F",
+        ArrayType::Double => "[D",
+        ArrayType::Byte => "[B",
+        ArrayType::Short => "[S",
+        ArrayType::Int => "[I",
+        ArrayType::Long => "[J",
+        ArrayType::Reference => {
+            return match arr.component_class.as_deref() {
+                Some(component) if component.starts_with('[') => format!("[{}", component),
+                Some(component) => format!("[L{};", component),
+                None => "[Ljava/lang/Object;".to_string(),
+            };
+        }
+    };
+    prefix.to_string()
+}
@@ -0,0 +1,935 @@
+use crate::exec::interpreter::Interpreter;
+use crate::native::java_lang_annotation::{self, Element};
+use crate::native::java_lang_boxing;
+use crate::native::java_lang_class::{self, split_method_descriptor};
+use crate::native::java_lang_throwable;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{Heap, HeapValue};
+
+pub const FIELD: &str = "java/lang/reflect/Field";
+pub const METHOD: &str = "java/lang/reflect/Method";
+pub const CONSTRUCTOR: &str = "java/lang/reflect/Constructor";
+const MODIFIER: &str = "java/lang/reflect/Modifier";
+const INVOCATION_TARGET_EXCEPTION: &str = "java/lang/reflect/InvocationTargetException";
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_PROTECTED: u16 = 0x0004;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_INTERFACE: u16 = 0x0200;
+
+/// The modifiers `getModifiers` reports for each kind of member, which
+/// leaves out flags such as `ACC_BRIDGE` that share bits with others.
+const FIELD_MODIFIERS: u16 = 0x00DF;
+const METHOD_MODIFIERS: u16 = 0x0D3F;
+const CONSTRUCTOR_MODIFIERS: u16 = 0x0007;
+
+/// `Modifier.toString` order.
+const MODIFIER_NAMES: &[(u16, &str)] = &[
+    (0x0001, "public"),
+    (0x0004, "protected"),
+    (0x0002, "private"),
+    (0x0400, "abstract"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0080, "transient"),
+    (0x0040, "volatile"),
+    (0x0020, "synchronized"),
+    (0x0100, "native"),
+    (0x0800, "strictfp"),
+    (0x0200, "interface"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+    Field,
+    Method,
+    Constructor,
+}
+
+impl MemberKind {
+    pub fn class_name(self) -> &'static str {
+        match self {
+            MemberKind::Field => FIELD,
+            MemberKind::Method => METHOD,
+            MemberKind::Constructor => CONSTRUCTOR,
+        }
+    }
+}
+
+const MEMBER_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("getName", "()Ljava/lang/String;", |env, this, _| {
+        let member = this_member(env, this)?;
+        let name = match member.kind {
+            MemberKind::Constructor => member.declaring.replace('/', "."),
+            _ => member.name,
+        };
+        Some(Some(env.heap.alloc_string(&name)))
+    }),
+    ("getModifiers", "()I", |env, this, _| {
+        let member = this_member(env, this)?;
+        Some(Some(HeapValue::Int(member.modifiers as i32)))
+    }),
+    (
+        "getDeclaringClass",
+        "()Ljava/lang/Class;",
+        |env, this, _| {
+            let member = this_member(env, this)?;
+            Some(Some(
+                env.interpreter.class_mirror(env.heap, &member.declaring),
+            ))
+        },
+    ),
+    ("setAccessible", "(Z)V", |env, this, args| {
+        let member = this_member(env, this)?;
+        let flag = args.first()?.as_int();
+        env.heap
+            .get_mut(member.id)?
+            .set_field("override", HeapValue::Int(flag));
+        Some(None)
+    }),
+    ("trySetAccessible", "()Z", |env, this, _| {
+        let member = this_member(env, this)?;
+        env.heap
+            .get_mut(member.id)?
+            .set_field("override", HeapValue::Int(1));
+        Some(Some(HeapValue::Int(1)))
+    }),
+    ("isAccessible", "()Z", |env, this, _| {
+        let member = this_member(env, this)?;
+        Some(Some(HeapValue::Int(member.accessible as i32)))
+    }),
+    ("toString", "()Ljava/lang/String;", |env, this, _| {
+        let text = this_member(env, this)?.describe();
+        Some(Some(env.heap.alloc_string(&text)))
+    }),
+];
+
+const FIELD_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("getType", "()Ljava/lang/Class;", |env, this, _| {
+        let member = this_member(env, this)?;
+        let type_name = java_lang_class::descriptor_type_name(&member.descriptor);
+        Some(Some(env.interpreter.class_mirror(env.heap, type_name)))
+    }),
+    (
+        "get",
+        "(Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, this, args| {
+            let member = this_member(env, this)?;
+            Some(get_field(env, &member, args.first()?))
+        },
+    ),
+    (
+        "set",
+        "(Ljava/lang/Object;Ljava/lang/Object;)V",
+        |env, this, args| {
+            let member = this_member(env, this)?;
+            let value = args.get(1)?;
+            let description = value_description(env.heap, value);
+            set_field(env, &member, args.first()?, value, &description);
+            Some(None)
+        },
+    ),
+];
+
+/// The primitive types of `Field.getInt`, `Field.setInt` and their kin.
+const TYPED_ACCESSORS: &[(&str, &str, &str)] = &[
+    ("getBoolean", "setBoolean", "Z"),
+    ("getByte", "setByte", "B"),
+    ("getChar", "setChar", "C"),
+    ("getShort", "setShort", "S"),
+    ("getInt", "setInt", "I"),
+    ("getLong", "setLong", "J"),
+    ("getFloat", "setFloat", "F"),
+    ("getDouble", "setDouble", "D"),
+];
+
+/// What `Method` and `Constructor` share as executables.
+const EXECUTABLE_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    (
+        "getParameterTypes",
+        "()[Ljava/lang/Class;",
+        |env, this, _| {
+            let member = this_member(env, this)?;
+            let types = member
+                .parameters()
+                .iter()
+                .map(|param| {
+                    let type_name = java_lang_class::descriptor_type_name(param);
+                    env.interpreter.class_mirror(env.heap, type_name)
+                })
+                .collect();
+            Some(Some(java_lang_class::reference_array(
+                env.heap,
+                "java/lang/Class",
+                types,
+            )))
+        },
+    ),
+    ("getParameterCount", "()I", |env, this, _| {
+        let member = this_member(env, this)?;
+        Some(Some(HeapValue::Int(member.parameters().len() as i32)))
+    }),
+    (
+        "getParameterAnnotations",
+        "()[[Ljava/lang/annotation/Annotation;",
+        |env, this, _| {
+            let member = this_member(env, this)?;
+            Some(Some(java_lang_annotation::parameter_annotations(
+                env,
+                &member.declaring,
+                &member.name,
+                &member.descriptor,
+            )))
+        },
+    ),
+];
+
+const METHOD_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("getReturnType", "()Ljava/lang/Class;", |env, this, _| {
+        let member = this_member(env, this)?;
+        let (_, ret) = split_method_descriptor(&member.descriptor);
+        let type_name = java_lang_class::descriptor_type_name(ret);
+        Some(Some(env.interpreter.class_mirror(env.heap, type_name)))
+    }),
+    ("getDefaultValue", "()Ljava/lang/Object;", |env, this, _| {
+        let member = this_member(env, this)?;
+        Some(java_lang_annotation::default_value(
+            env,
+            &member.declaring,
+            &member.name,
+            &member.descriptor,
+        ))
+    }),
+    (
+        "invoke",
+        "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;",
+        |env, this, args| {
+            let member = this_member(env, this)?;
+            Some(invoke_method(env, &member, args.first()?, args.get(1)?))
+        },
+    ),
+];
+
+const CONSTRUCTOR_METHODS: &[(&str, &str, BuiltinMethod)] = &[(
+    "newInstance",
+    "([Ljava/lang/Object;)Ljava/lang/Object;",
+    |env, this, args| {
+        let member = this_member(env, this)?;
+        Some(new_instance(env, &member, args.first()?))
+    },
+)];
+
+const MODIFIER_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("isPublic", "(I)Z", |_, _, args| {
+        has_modifier(args, ACC_PUBLIC)
+    }),
+    ("isPrivate", "(I)Z", |_, _, args| {
+        has_modifier(args, ACC_PRIVATE)
+    }),
+    ("isProtected", "(I)Z", |_, _, args| {
+        has_modifier(args, ACC_PROTECTED)
+    }),
+    ("isStatic", "(I)Z", |_, _, args| {
+        has_modifier(args, ACC_STATIC)
+    }),
+    ("isFinal", "(I)Z", |_, _, args| {
+        has_modifier(args, ACC_FINAL)
+    }),
+    ("isAbstract", "(I)Z", |_, _, args| {
+        has_modifier(args, ACC_ABSTRACT)
+    }),
+    ("isInterface", "(I)Z", |_, _, args| {
+        has_modifier(args, ACC_INTERFACE)
+    }),
+    ("toString", "(I)Ljava/lang/String;", |env, _, args| {
+        let text = modifier_string(args.first()?.as_int() as u16);
+        Some(Some(env.heap.alloc_string(&text)))
+    }),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    for class_name in [FIELD, METHOD, CONSTRUCTOR] {
+        registry.register_all(class_name, MEMBER_METHODS);
+        java_lang_annotation::register_annotated_element(registry, class_name, annotations);
+    }
+    registry.register_all(FIELD, FIELD_METHODS);
+    for &(getter, setter, descriptor) in TYPED_ACCESSORS {
+        let get = format!("(Ljava/lang/Object;){}", descriptor);
+        registry.register_builtin(FIELD, getter, &get, move |env, this, args| {
+            let member = this_member(env, this)?;
+            Some(get_typed(env, &member, args.first()?, descriptor))
+        });
+        let set = format!("(Ljava/lang/Object;{})V", descriptor);
+        registry.register_builtin(FIELD, setter, &set, move |env, this, args| {
+            let member = this_member(env, this)?;
+            set_typed(env, &member, args.first()?, args.get(1)?, descriptor);
+            Some(None)
+        });
+    }
+    registry.register_all(METHOD, EXECUTABLE_METHODS);
+    registry.register_all(METHOD, METHOD_METHODS);
+    registry.register_all(CONSTRUCTOR, EXECUTABLE_METHODS);
+    registry.register_all(CONSTRUCTOR, CONSTRUCTOR_METHODS);
+    registry.register_all(MODIFIER, MODIFIER_METHODS);
+    registry.register_all(
+        INVOCATION_TARGET_EXCEPTION,
+        &[(
+            "getTargetException",
+            "()Ljava/lang/Throwable;",
+            |env, this, _| {
+                let HeapValue::Object(this) = this? else {
+                    return None;
+                };
+                Some(Some(field(env.heap, this.id, "cause")))
+            },
+        )],
+    );
+}
+
+fn this_member(env: &NativeEnv, receiver: Option<&HeapValue>) -> Option<Member> {
+    Member::read(env.heap, receiver?)
+}
+
+/// A member's annotations for `AnnotatedElement`. Members inherit none,
+/// so all of them are the declared ones.
+fn annotations(env: &mut NativeEnv, member: &HeapValue, _declared: bool) -> Option<Vec<HeapValue>> {
+    let member = Member::read(env.heap, member)?;
+    let element = match member.kind {
+        MemberKind::Field => Element::Field(&member.name),
+        _ => Element::Method(&member.name, &member.descriptor),
+    };
+    Some(java_lang_annotation::declared_annotations(
+        env,
+        &member.declaring,
+        element,
+    ))
+}
+
+/// `Modifier.isPublic` and the other single-flag tests.
+fn has_modifier(args: &[HeapValue], flag: u16) -> Option<Option<HeapValue>> {
+    let modifiers = args.first()?.as_int() as u16;
+    Some(Some(HeapValue::Int((modifiers & flag != 0) as i32)))
+}
+
+pub fn is_reflect_class(class_name: &str) -> bool {
+    matches!(class_name, FIELD | METHOD | CONSTRUCTOR | MODIFIER)
+}
+
+/// A new `Field`, `Method` or `Constructor` for a member declared by
+/// `declaring`.
+pub fn new_member(
+    env: &mut NativeEnv,
+    kind: MemberKind,
+    declaring: &str,
+    name: &str,
+    descriptor: &str,
+    access_flags: u16,
+) -> HeapValue {
+    let mask = match kind {
+        MemberKind::Field => FIELD_MODIFIERS,
+        MemberKind::Method => METHOD_MODIFIERS,
+        MemberKind::Constructor => CONSTRUCTOR_MODIFIERS,
+    };
+    let class = env.interpreter.class_mirror(env.heap, declaring);
+    let name = env.heap.alloc_string(name);
+    let obj = env.heap.alloc_object(kind.class_name());
+    if let Some(real) = env.heap.get_mut(obj.id) {
+        real.set_field("clazz", class);
+        real.set_field("name", name);
+        real.set_field("descriptor", HeapValue::String(descriptor.to_string()));
+        real.set_field("modifiers", HeapValue::Int((access_flags & mask) as i32));
+        real.set_field("override", HeapValue::Int(0));
+    }
+    HeapValue::Object(obj)
+}
+
+/// The name of a `Field`, `Method` or `Constructor`.
+pub fn member_name(heap: &Heap, member: &HeapValue) -> Option<String> {
+    let HeapValue::Object(obj) = member else {
+        return None;
+    };
+    heap.string_value(&field(heap, obj.id, "name"))
+}
+
+/// What a reflection object stands for, read back from its fields.
+pub struct Member {
+    id: u64,
+    pub kind: MemberKind,
+    pub declaring: String,
+    pub name: String,
+    pub descriptor: String,
+    pub modifiers: u16,
+    /// Whether `setAccessible(true)` suppressed access checks.
+    pub accessible: bool,
+}
+
+impl Member {
+    pub fn read(heap: &Heap, value: &HeapValue) -> Option<Self> {
+        let HeapValue::Object(obj) = value else {
+            return None;
+        };
+        let kind = match obj.class_name.as_str() {
+            FIELD => MemberKind::Field,
+            METHOD => MemberKind::Method,
+            CONSTRUCTOR => MemberKind::Constructor,
+            _ => return None,
+        };
+        let HeapValue::String(descriptor) = field(heap, obj.id, "descriptor") else {
+            return None;
+        };
+        Some(Self {
+            id: obj.id,
+            kind,
+            declaring: java_lang_class::class_name(heap, &field(heap, obj.id, "clazz"))?,
+            name: heap.string_value(&field(heap, obj.id, "name"))?,
+            descriptor,
+            modifiers: field(heap, obj.id, "modifiers").as_int() as u16,
+            accessible: field(heap, obj.id, "override").as_int() != 0,
+        })
+    }
+
+    fn is_static(&self) -> bool {
+        self.modifiers & ACC_STATIC != 0
+    }
+
+    /// The parameter descriptors of a method or constructor.
+    fn parameters(&self) -> Vec<&str> {
+        split_method_descriptor(&self.descriptor).0
+    }
+
+    /// `Field.toString` and friends, e.g. `public static int Main.add(int,int)`.
+    fn describe(&self) -> String {
+        let mut text = modifier_string(self.modifiers);
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        let declaring = self.declaring.replace('/', ".");
+        let parameters = || {
+            self.parameters()
+                .iter()
+                .map(|param| java_lang_class::source_type_name(param))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        match self.kind {
+            MemberKind::Field => {
+                let field_type = java_lang_class::source_type_name(&self.descriptor);
+                text.push_str(&format!("{} {}.{}", field_type, declaring, self.name));
+            }
+            MemberKind::Method => {
+                let (_, ret) = split_method_descriptor(&self.descriptor);
+                text.push_str(&format!(
+                    "{} {}.{}({})",
+                    java_lang_class::source_type_name(ret),
+                    declaring,
+                    self.name,
+                    parameters()
+                ));
+            }
+            MemberKind::Constructor => {
+                text.push_str(&format!("{}({})", declaring, parameters()));
+            }
+        }
+        text
+    }
+}
+
+/// `Modifier.toString`: the modifier keywords, space separated.
+pub fn modifier_string(modifiers: u16) -> String {
+    MODIFIER_NAMES
+        .iter()
+        .filter(|(flag, _)| modifiers & flag != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Checks that the calling class may use `member`, as the language
+/// would allow it, unless `setAccessible(true)` was called. Throws
+/// `IllegalAccessException` and returns false otherwise.
+fn check_access(env: &mut NativeEnv, member: &Member) -> bool {
+    if member.accessible {
+        return true;
+    }
+    let Some(caller) = env.interpreter.caller_class() else {
+        return true;
+    };
+    if is_accessible(env, &caller, &member.declaring, member.modifiers) {
+        return true;
+    }
+    let message = format!(
+        "class {} cannot access a member of class {} with modifiers \"{}\"",
+        caller.replace('/', "."),
+        member.declaring.replace('/', "."),
+        modifier_string(member.modifiers)
+    );
+    env.interpreter
+        .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+    false
+}
+
+/// Whether code in `caller` may use a member of `declaring` with these
+/// modifiers under the language's access rules.
+pub fn is_accessible(env: &mut NativeEnv, caller: &str, declaring: &str, modifiers: u16) -> bool {
+    if caller == declaring {
+        return true;
+    }
+    let same_package = package(caller) == package(declaring);
+    if java_lang_class::modifiers(env, declaring) & ACC_PUBLIC == 0 && !same_package {
+        return false;
+    }
+    if modifiers & ACC_PUBLIC != 0 {
+        return true;
+    }
+    if modifiers & ACC_PRIVATE != 0 {
+        // Nested classes share private access with their enclosing class.
+        return top_level(caller) == top_level(declaring);
+    }
+    same_package
+        || (modifiers & ACC_PROTECTED != 0
+            && env
+                .interpreter
+                .is_subclass_of(env.loader, caller, declaring))
+}
+
+fn package(class_name: &str) -> &str {
+    class_name
+        .rsplit_once('/')
+        .map_or("", |(package, _)| package)
+}
+
+fn top_level(class_name: &str) -> &str {
+    class_name.split('$').next().unwrap_or(class_name)
+}
+
+/// The object an instance member is used on, checked against the
+/// declaring class. Throws and returns `None` if it does not fit.
+fn check_receiver(
+    env: &mut NativeEnv,
+    member: &Member,
+    receiver: &HeapValue,
+    mismatch: impl FnOnce(&str) -> String,
+) -> Option<u64> {
+    let Some(class) = java_lang_class::value_class(env.heap, receiver) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return None;
+    };
+    let id = match receiver {
+        HeapValue::Object(obj) => obj.id,
+        HeapValue::Array(arr) => arr.id,
+        _ => 0,
+    };
+    if !env
+        .interpreter
+        .is_assignable(env.loader, &class, &member.declaring)
+    {
+        let message = mismatch(&class.replace('/', "."));
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some(&message),
+        );
+        return None;
+    }
+    Some(id)
+}
+
+/// `Can not set static final int field Main.LIMIT to java.lang.Integer`,
+/// the message for every misuse of a field.
+fn field_mismatch(member: &Member, value: &str, show_final: bool) -> String {
+    let mut qualifiers = String::new();
+    if member.is_static() {
+        qualifiers.push_str("static ");
+    }
+    if show_final && member.modifiers & ACC_FINAL != 0 {
+        qualifiers.push_str("final ");
+    }
+    format!(
+        "Can not set {}{} field {}.{} to {}",
+        qualifiers,
+        java_lang_class::source_type_name(&member.descriptor),
+        member.declaring.replace('/', "."),
+        member.name,
+        value
+    )
+}
+
+fn value_description(heap: &Heap, value: &HeapValue) -> String {
+    match java_lang_class::value_class(heap, value) {
+        Some(class) => class.replace('/', "."),
+        None => "null value".to_string(),
+    }
+}
+
+fn get_field(env: &mut NativeEnv, member: &Member, receiver: &HeapValue) -> Option<HeapValue> {
+    let value = read_field(env, member, receiver)?;
+    Some(box_result(env.heap, value, &member.descriptor))
+}
+
+/// `Field.getInt` and its kin: the field's value widened to the primitive
+/// type `descriptor`.
+fn get_typed(
+    env: &mut NativeEnv,
+    member: &Member,
+    receiver: &HeapValue,
+    descriptor: &str,
+) -> Option<HeapValue> {
+    let target = descriptor.chars().next()?;
+    let source = match member.descriptor.as_str() {
+        primitive if primitive.len() == 1 => primitive.chars().next()?,
+        _ => ' ',
+    };
+    if !widens(source, target) {
+        let message = format!(
+            "Attempt to get {} field \"{}.{}\" with illegal data type conversion to {}",
+            java_lang_class::source_type_name(&member.descriptor),
+            member.declaring.replace('/', "."),
+            member.name,
+            java_lang_class::source_type_name(descriptor)
+        );
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some(&message),
+        );
+        return None;
+    }
+    let value = read_field(env, member, receiver)?;
+    Some(widen(&value, target))
+}
+
+/// `Field.setInt` and its kin: sets the field to a value of the
+/// primitive type `descriptor`, widened to the field's type.
+fn set_typed(
+    env: &mut NativeEnv,
+    member: &Member,
+    receiver: &HeapValue,
+    value: &HeapValue,
+    descriptor: &str,
+) {
+    let boxed = box_result(env.heap, value.clone(), descriptor);
+    let description = format!(
+        "({}){}",
+        java_lang_class::source_type_name(descriptor),
+        env.to_java_string(&boxed)
+    );
+    if member.descriptor.len() != 1 {
+        let message = field_mismatch(member, &description, false);
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some(&message),
+        );
+        return;
+    }
+    set_field(env, member, receiver, &boxed, &description);
+}
+
+/// A field's value as stored, after the access and receiver checks.
+fn read_field(env: &mut NativeEnv, member: &Member, receiver: &HeapValue) -> Option<HeapValue> {
+    if !check_access(env, member) {
+        return None;
+    }
+    let value = if member.is_static() {
+        if !env
+            .interpreter
+            .ensure_class_initialized(env.loader, &member.declaring, env.heap)
+        {
+            return None;
+        }
+        env.loader
+            .get_static_field(&member.declaring, &member.name)
+            .unwrap_or_else(|| Interpreter::default_value_for_descriptor(&member.descriptor))
+    } else {
+        let id = check_receiver(env, member, receiver, |class| {
+            field_mismatch(member, class, false)
+        })?;
+        env.heap
+            .get(id)
+            .and_then(|obj| obj.get_field(&member.name))
+            .cloned()
+            .unwrap_or_else(|| Interpreter::default_value_for_descriptor(&member.descriptor))
+    };
+    Some(value)
+}
+
+/// Sets a field to `value`, which `description` names in the messages
+/// of the exceptions a mismatch throws.
+fn set_field(
+    env: &mut NativeEnv,
+    member: &Member,
+    receiver: &HeapValue,
+    value: &HeapValue,
+    description: &str,
+) {
+    if !check_access(env, member) {
+        return;
+    }
+    let id = if member.is_static() {
+        None
+    } else {
+        match check_receiver(env, member, receiver, |class| {
+            field_mismatch(member, class, false)
+        }) {
+            Some(id) => Some(id),
+            None => return,
+        }
+    };
+    let is_final = member.modifiers & ACC_FINAL != 0;
+    if is_final && (member.is_static() || !member.accessible) {
+        let message = field_mismatch(member, description, true);
+        env.interpreter
+            .throw_new(env.heap, "java/lang/IllegalAccessException", Some(&message));
+        return;
+    }
+    let Some(converted) = coerce(env, value, &member.descriptor) else {
+        let message = field_mismatch(member, description, false);
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some(&message),
+        );
+        return;
+    };
+    match id {
+        Some(id) => {
+            if let Some(obj) = env.heap.get_mut(id) {
+                obj.set_field(&member.name, converted);
+            }
+        }
+        None => {
+            if env
+                .interpreter
+                .ensure_class_initialized(env.loader, &member.declaring, env.heap)
+            {
+                env.loader
+                    .set_static_field(&member.declaring, &member.name, converted);
+            }
+        }
+    }
+}
+
+/// The elements of an `Object[]` argument list; `null` passes none.
+/// Checks them against the parameter types, throwing
+/// `IllegalArgumentException` on a mismatch.
+fn arguments(env: &mut NativeEnv, member: &Member, args: &HeapValue) -> Option<Vec<HeapValue>> {
+    let values = match args {
+        HeapValue::Array(arr) => env
+            .heap
+            .get_array(arr.id)
+            .map(|real| real.content.clone())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let parameters = member.parameters();
+    if values.len() != parameters.len() {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some("wrong number of arguments"),
+        );
+        return None;
+    }
+    let converted = values
+        .iter()
+        .zip(&parameters)
+        .map(|(value, param)| coerce(env, value, param))
+        .collect::<Option<Vec<_>>>();
+    if converted.is_none() {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some("argument type mismatch"),
+        );
+    }
+    converted
+}
+
+fn invoke_method(
+    env: &mut NativeEnv,
+    member: &Member,
+    receiver: &HeapValue,
+    args: &HeapValue,
+) -> Option<HeapValue> {
+    if !check_access(env, member) {
+        return None;
+    }
+    let receiver = if member.is_static() {
+        None
+    } else {
+        check_receiver(env, member, receiver, |_| {
+            "object is not an instance of declaring class".to_string()
+        })?;
+        Some(receiver.clone())
+    };
+    let arguments = arguments(env, member, args)?;
+    let result = match &receiver {
+        Some(this) if member.modifiers & ACC_PRIVATE == 0 => env.interpreter.invoke_dispatched(
+            env.loader,
+            env.heap,
+            &member.declaring,
+            &member.name,
+            &member.descriptor,
+            this,
+            &arguments,
+        ),
+        _ => env.interpreter.invoke_nonvirtual(
+            env.loader,
+            env.heap,
+            &member.declaring,
+            &member.name,
+            &member.descriptor,
+            receiver,
+            &arguments,
+        ),
+    };
+    if wrap_target_exception(env) {
+        return None;
+    }
+    let (_, ret) = split_method_descriptor(&member.descriptor);
+    Some(match result {
+        Some(value) if ret != "V" => box_result(env.heap, value, ret),
+        _ => HeapValue::Null,
+    })
+}
+
+fn new_instance(env: &mut NativeEnv, member: &Member, args: &HeapValue) -> Option<HeapValue> {
+    if !check_access(env, member) {
+        return None;
+    }
+    if java_lang_class::modifiers(env, &member.declaring) & (ACC_ABSTRACT | ACC_INTERFACE) != 0 {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/InstantiationException", None);
+        return None;
+    }
+    let arguments = arguments(env, member, args)?;
+    if !env
+        .interpreter
+        .ensure_class_initialized(env.loader, &member.declaring, env.heap)
+    {
+        return None;
+    }
+    let instance = HeapValue::Object(env.heap.alloc_object(&member.declaring));
+    env.interpreter.invoke_nonvirtual(
+        env.loader,
+        env.heap,
+        &member.declaring,
+        "<init>",
+        &member.descriptor,
+        Some(instance.clone()),
+        &arguments,
+    );
+    if wrap_target_exception(env) {
+        return None;
+    }
+    Some(instance)
+}
+
+/// Rethrows an exception the invoked code threw as the cause of an
+/// `InvocationTargetException`. True if there was one.
+fn wrap_target_exception(env: &mut NativeEnv) -> bool {
+    let Some(target) = env.interpreter.take_pending_exception() else {
+        return false;
+    };
+    let backtrace = env.interpreter.backtrace();
+    let wrapper =
+        java_lang_throwable::new_throwable(env.heap, INVOCATION_TARGET_EXCEPTION, None, backtrace);
+    if let HeapValue::Object(obj) = &wrapper {
+        if let Some(real) = env.heap.get_mut(obj.id) {
+            real.set_field("cause", target);
+        }
+    }
+    env.interpreter.throw(wrapper);
+    true
+}
+
+/// Converts `value` for a parameter or field of type `descriptor`:
+/// unboxes and widens for primitive types, and checks the class of
+/// references. `None` if it does not fit.
+fn coerce(env: &mut NativeEnv, value: &HeapValue, descriptor: &str) -> Option<HeapValue> {
+    if descriptor.starts_with('L') || descriptor.starts_with('[') {
+        if value.is_null() {
+            return Some(HeapValue::Null);
+        }
+        let class = java_lang_class::value_class(env.heap, value)?;
+        let target = java_lang_class::descriptor_type_name(descriptor);
+        return env
+            .interpreter
+            .is_assignable(env.loader, &class, target)
+            .then(|| value.clone());
+    }
+    let HeapValue::Object(obj) = value else {
+        return None;
+    };
+    if !java_lang_boxing::is_box_class(&obj.class_name) {
+        return None;
+    }
+    let source = java_lang_boxing::primitive_descriptor(&obj.class_name)
+        .chars()
+        .next()?;
+    let raw = java_lang_boxing::unbox(env.heap, obj)?;
+    let target = descriptor.chars().next()?;
+    widens(source, target).then(|| widen(&raw, target))
+}
+
+/// Whether a primitive of type `source` converts to `target` by identity
+/// or widening.
+fn widens(source: char, target: char) -> bool {
+    source == target
+        || matches!(
+            (source, target),
+            ('B', 'S' | 'I' | 'J' | 'F' | 'D')
+                | ('S' | 'C', 'I' | 'J' | 'F' | 'D')
+                | ('I', 'J' | 'F' | 'D')
+                | ('J', 'F' | 'D')
+                | ('F', 'D')
+        )
+}
+
+fn widen(raw: &HeapValue, target: char) -> HeapValue {
+    match target {
+        'J' => HeapValue::Long(as_long(raw)),
+        'F' => HeapValue::Float(as_double(raw) as f32),
+        'D' => HeapValue::Double(as_double(raw)),
+        _ => HeapValue::Int(raw.as_int()),
+    }
+}
+
+/// Boxes a primitive read from a field or returned by a method of type
+/// `descriptor`; references pass through.
+pub fn box_result(heap: &mut Heap, value: HeapValue, descriptor: &str) -> HeapValue {
+    match java_lang_boxing::box_class(descriptor) {
+        Some(box_class) => java_lang_boxing::box_value(heap, box_class, value),
+        None => value,
+    }
+}
+
+fn as_long(value: &HeapValue) -> i64 {
+    match value {
+        HeapValue::Long(v) => *v,
+        HeapValue::Float(v) => *v as i64,
+        HeapValue::Double(v) => *v as i64,
+        other => other.as_int() as i64,
+    }
+}
+
+fn as_double(value: &HeapValue) -> f64 {
+    match value {
+        HeapValue::Long(v) => *v as f64,
+        HeapValue::Float(v) => *v as f64,
+        HeapValue::Double(v) => *v,
+        other => other.as_int() as f64,
+    }
+}
+
+fn field(heap: &Heap, id: u64, name: &str) -> HeapValue {
+    heap.get(id)
+        .and_then(|obj| obj.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
@@ -0,0 +1,606 @@
+use crate::bytecode::assembler::{Assembler, ValueKind};
+use crate::bytecode::attributes::{Attribute, StackMapFrame, VerificationType};
+use crate::bytecode::constant_pool::ConstantPoolBuilder;
+use crate::bytecode::error::ClassFormatError;
+use crate::bytecode::parser::{
+    ClassFile, CodeAttribute, ExceptionTableEntry, FieldInfo, MethodInfo,
+};
+use crate::native::java_lang_class::{self, split_method_descriptor};
+use crate::native::java_lang_reflect::{self, MemberKind};
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::{java_lang_boxing, java_lang_classloader, NativeEnv};
+use crate::runtime::heap::HeapValue;
+
+pub const PROXY: &str = "java/lang/reflect/Proxy";
+pub const INVOCATION_HANDLER: &str = "java/lang/reflect/InvocationHandler";
+const HANDLER_DESCRIPTOR: &str = "Ljava/lang/reflect/InvocationHandler;";
+const INVOKE_DESCRIPTOR: &str =
+    "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;";
+/// Proxy classes are named `jdk.proxy1.$Proxy0`, `jdk.proxy1.$Proxy1`...
+const PROXY_PREFIX: &str = "jdk/proxy1/$Proxy";
+const UNDECLARED_THROWABLE: &str = "java/lang/reflect/UndeclaredThrowableException";
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_SUPER: u16 = 0x0020;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+
+/// Every proxy forwards these to its handler, ahead of the interface
+/// methods.
+const OBJECT_METHODS: &[(&str, &str)] = &[
+    ("hashCode", "()I"),
+    ("equals", "(Ljava/lang/Object;)Z"),
+    ("toString", "()Ljava/lang/String;"),
+];
+
+/// What a handler may throw from any method without it being wrapped in
+/// an `UndeclaredThrowableException`.
+const UNCHECKED: &[&str] = &["java/lang/Error", "java/lang/RuntimeException"];
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    (
+        "newProxyInstance",
+        "(Ljava/lang/ClassLoader;[Ljava/lang/Class;Ljava/lang/reflect/InvocationHandler;)Ljava/lang/Object;",
+        new_proxy_instance,
+    ),
+    ("isProxyClass", "(Ljava/lang/Class;)Z", |env, _, args| {
+        let Some(class_name) = java_lang_class::class_name(env.heap, args.first()?) else {
+            env.interpreter
+                .throw_new(env.heap, "java/lang/NullPointerException", None);
+            return Some(None);
+        };
+        let proxy = env.interpreter.is_proxy_class(&class_name);
+        Some(Some(HeapValue::Int(proxy as i32)))
+    }),
+    (
+        "getInvocationHandler",
+        "(Ljava/lang/Object;)Ljava/lang/reflect/InvocationHandler;",
+        get_invocation_handler,
+    ),
+];
+
+/// Unlike other throwables, an `UndeclaredThrowableException` made from a
+/// cause has no message of its own.
+const UNDECLARED_THROWABLE_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("<init>", "(Ljava/lang/Throwable;)V", init_undeclared),
+    (
+        "<init>",
+        "(Ljava/lang/Throwable;Ljava/lang/String;)V",
+        init_undeclared,
+    ),
+    (
+        "getUndeclaredThrowable",
+        "()Ljava/lang/Throwable;",
+        |env, this, _| {
+            let HeapValue::Object(this) = this? else {
+                return None;
+            };
+            let cause = env.heap.get(this.id)?.get_field("cause").cloned();
+            Some(Some(cause.unwrap_or(HeapValue::Null)))
+        },
+    ),
+];
+
+/// The abstract methods of builtin interfaces, which have no class file
+/// to read them from.
+const BUILTIN_INTERFACE_METHODS: &[(&str, &str, &str)] = &[
+    ("java/lang/Runnable", "run", "()V"),
+    (
+        "java/lang/annotation/Annotation",
+        "annotationType",
+        "()Ljava/lang/Class;",
+    ),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register(
+        PROXY,
+        "<init>",
+        "(Ljava/lang/reflect/InvocationHandler;)V",
+        |env, receiver, args| {
+            if let (Some(HeapValue::Object(this)), Some(handler)) = (receiver, args.first()) {
+                if let Some(real) = env.heap.get_mut(this.id) {
+                    real.set_field("h", handler.clone());
+                }
+            }
+            None
+        },
+    );
+    registry.register_all(PROXY, METHODS);
+    registry.register_all(UNDECLARED_THROWABLE, UNDECLARED_THROWABLE_METHODS);
+}
+
+fn new_proxy_instance(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let handler = args.get(2)?.clone();
+    let HeapValue::Array(array) = args.get(1)? else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    if handler.is_null() {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    }
+    let mirrors = env.heap.get_array(array.id)?.content.clone();
+    let mut interfaces = Vec::with_capacity(mirrors.len());
+    for mirror in &mirrors {
+        let Some(interface) = java_lang_class::class_name(env.heap, mirror) else {
+            env.interpreter
+                .throw_new(env.heap, "java/lang/NullPointerException", None);
+            return Some(None);
+        };
+        interfaces.push(interface);
+    }
+    Some(new_instance(env, &interfaces, handler))
+}
+
+fn get_invocation_handler(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let handler = match args.first()? {
+        HeapValue::Object(obj) if env.interpreter.is_proxy_class(&obj.class_name) => env
+            .heap
+            .get(obj.id)
+            .and_then(|real| real.get_field("h"))
+            .cloned(),
+        _ => None,
+    };
+    if handler.is_none() {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some("not a proxy instance"),
+        );
+    }
+    Some(handler)
+}
+
+/// The `UndeclaredThrowableException` constructors.
+fn init_undeclared(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let HeapValue::Object(this) = receiver? else {
+        return None;
+    };
+    let backtrace = env.interpreter.backtrace();
+    let real = env.heap.get_mut(this.id)?;
+    real.set_field(
+        "detailMessage",
+        args.get(1).cloned().unwrap_or(HeapValue::Null),
+    );
+    real.set_field("cause", args.first()?.clone());
+    real.set_field("backtrace", HeapValue::String(backtrace.join("\n")));
+    Some(None)
+}
+
+/// A method a proxy class implements.
+struct ProxyMethod {
+    declaring: String,
+    name: String,
+    descriptor: String,
+    access_flags: u16,
+    /// The checked exceptions the handler may throw as they are.
+    exceptions: Vec<String>,
+}
+
+/// A new instance of the proxy class for `interfaces`, forwarding every
+/// call to `handler`. Throws `IllegalArgumentException` for a class that
+/// is not an interface or is named twice.
+pub fn new_instance(
+    env: &mut NativeEnv,
+    interfaces: &[String],
+    handler: HeapValue,
+) -> Option<HeapValue> {
+    let class_name = proxy_class(env, interfaces)?;
+    let obj = env.heap.alloc_object(&class_name);
+    if let Some(real) = env.heap.get_mut(obj.id) {
+        real.set_field("h", handler);
+    }
+    Some(HeapValue::Object(obj))
+}
+
+/// The proxy class implementing `interfaces`, generated and loaded the
+/// first time it is asked for.
+pub fn proxy_class(env: &mut NativeEnv, interfaces: &[String]) -> Option<String> {
+    if let Some(class_name) = env.interpreter.proxy_class(interfaces) {
+        return Some(class_name);
+    }
+    for (index, interface) in interfaces.iter().enumerate() {
+        let message = if java_lang_class::modifiers(env, interface) & ACC_INTERFACE == 0 {
+            format!("{} is not an interface", interface.replace('/', "."))
+        } else if interfaces[..index].contains(interface) {
+            format!("repeated interface: {}", interface.replace('/', "."))
+        } else {
+            continue;
+        };
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some(&message),
+        );
+        return None;
+    }
+
+    let mut methods: Vec<ProxyMethod> = OBJECT_METHODS
+        .iter()
+        .map(|(name, descriptor)| ProxyMethod {
+            declaring: "java/lang/Object".to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            access_flags: ACC_PUBLIC,
+            exceptions: Vec::new(),
+        })
+        .collect();
+    for interface in interfaces {
+        collect_methods(env, interface, &mut methods);
+    }
+
+    let class_name = format!("{}{}", PROXY_PREFIX, env.interpreter.proxy_class_count());
+    let bytes = match generate(&class_name, interfaces, &methods) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            let message = format!("{}: {}", class_name.replace('/', "."), error);
+            env.interpreter
+                .throw_new(env.heap, "java/lang/InternalError", Some(&message));
+            return None;
+        }
+    };
+    java_lang_classloader::define_class(env, Some(&class_name), &bytes)?;
+    if !env
+        .interpreter
+        .ensure_class_initialized(env.loader, &class_name, env.heap)
+    {
+        return None;
+    }
+    for (index, method) in methods.iter().enumerate() {
+        let member = java_lang_reflect::new_member(
+            env,
+            MemberKind::Method,
+            &method.declaring,
+            &method.name,
+            &method.descriptor,
+            method.access_flags,
+        );
+        env.loader
+            .set_static_field(&class_name, &format!("m{}", index), member);
+    }
+    env.interpreter
+        .add_proxy_class(interfaces.to_vec(), class_name.clone());
+    Some(class_name)
+}
+
+/// Adds the instance methods of an interface and its superinterfaces
+/// that are not already there.
+fn collect_methods(env: &mut NativeEnv, interface: &str, methods: &mut Vec<ProxyMethod>) {
+    let mut found = Vec::new();
+    let mut superinterfaces = Vec::new();
+    if let Some(runtime) = java_lang_class::loaded_class(env, interface) {
+        let class = &runtime.class;
+        for method in &class.methods {
+            let name = class.get_utf8(method.name_index).unwrap_or_default();
+            if name.starts_with('<') || method.access_flags & (ACC_STATIC | ACC_PRIVATE) != 0 {
+                continue;
+            }
+            let exceptions = method
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::Exceptions(indexes) => Some(
+                        indexes
+                            .iter()
+                            .filter_map(|index| class.get_class_name(*index).map(str::to_string))
+                            .collect(),
+                    ),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            found.push(ProxyMethod {
+                declaring: interface.to_string(),
+                name: name.to_string(),
+                descriptor: class
+                    .get_utf8(method.descriptor_index)
+                    .unwrap_or_default()
+                    .to_string(),
+                access_flags: method.access_flags,
+                exceptions,
+            });
+        }
+        superinterfaces.extend(runtime.interfaces.iter().cloned());
+    } else {
+        found.extend(
+            BUILTIN_INTERFACE_METHODS
+                .iter()
+                .filter(|(declaring, _, _)| *declaring == interface)
+                .map(|(_, name, descriptor)| ProxyMethod {
+                    declaring: interface.to_string(),
+                    name: name.to_string(),
+                    descriptor: descriptor.to_string(),
+                    access_flags: ACC_PUBLIC | ACC_ABSTRACT,
+                    exceptions: Vec::new(),
+                }),
+        );
+    }
+    for method in found {
+        add_method(env, methods, method);
+    }
+    for superinterface in superinterfaces {
+        collect_methods(env, &superinterface, methods);
+    }
+}
+
+/// Adds `method` unless one with the same signature is there already, in
+/// which case only the exceptions both may throw stay declared.
+fn add_method(env: &mut NativeEnv, methods: &mut Vec<ProxyMethod>, method: ProxyMethod) {
+    let Some(existing) = methods
+        .iter_mut()
+        .find(|existing| existing.name == method.name && existing.descriptor == method.descriptor)
+    else {
+        methods.push(method);
+        return;
+    };
+    let mut narrowed = Vec::new();
+    for (ours, theirs) in [
+        (&existing.exceptions, &method.exceptions),
+        (&method.exceptions, &existing.exceptions),
+    ] {
+        for exception in ours {
+            if !narrowed.contains(exception)
+                && theirs
+                    .iter()
+                    .any(|other| env.interpreter.is_subclass_of(env.loader, exception, other))
+            {
+                narrowed.push(exception.clone());
+            }
+        }
+    }
+    existing.exceptions = narrowed;
+}
+
+/// The class file of a proxy class: a subclass of `Proxy` whose every
+/// method calls `h.invoke(this, m<i>, args)` with the arguments boxed,
+/// then unboxes or casts the result.
+fn generate(
+    class_name: &str,
+    interfaces: &[String],
+    methods: &[ProxyMethod],
+) -> Result<Vec<u8>, ClassFormatError> {
+    let mut pool = ConstantPoolBuilder::new();
+    pool.utf8("Code");
+    let this_class = pool.class(class_name);
+    let proxy = pool.class(PROXY);
+    let interface_indexes = interfaces
+        .iter()
+        .map(|interface| pool.class(interface))
+        .collect();
+    let handler_field = pool.name_and_type("h", HANDLER_DESCRIPTOR);
+    let handler_field = pool.field_ref(proxy, handler_field);
+    let handler = pool.class(INVOCATION_HANDLER);
+    let invoke = pool.name_and_type("invoke", INVOKE_DESCRIPTOR);
+    let invoke = pool.interface_method_ref(handler, invoke);
+    let object = pool.class("java/lang/Object");
+
+    let mut fields = Vec::new();
+    let mut class_methods = Vec::new();
+
+    let super_init = pool.name_and_type("<init>", &format!("({})V", HANDLER_DESCRIPTOR));
+    let super_init = pool.method_ref(proxy, super_init);
+    let mut asm = Assembler::new();
+    asm.load(ValueKind::Reference, 0);
+    asm.load(ValueKind::Reference, 1);
+    asm.emit(0xb7); // invokespecial
+    asm.emit_u2(super_init);
+    asm.return_value(None);
+    class_methods.push(MethodInfo {
+        access_flags: ACC_PUBLIC,
+        name_index: pool.utf8("<init>"),
+        descriptor_index: pool.utf8(&format!("({})V", HANDLER_DESCRIPTOR)),
+        code: Some(code(asm, 2, 2)?),
+        attributes: Vec::new(),
+    });
+
+    for (index, method) in methods.iter().enumerate() {
+        let field_name = format!("m{}", index);
+        fields.push(FieldInfo {
+            access_flags: ACC_PRIVATE | ACC_STATIC,
+            name_index: pool.utf8(&field_name),
+            descriptor_index: pool.utf8("Ljava/lang/reflect/Method;"),
+            attributes: Vec::new(),
+        });
+        let member = pool.name_and_type(&field_name, "Ljava/lang/reflect/Method;");
+        let member = pool.field_ref(this_class, member);
+
+        let (params, ret) = split_method_descriptor(&method.descriptor);
+        let mut asm = Assembler::new();
+        asm.load(ValueKind::Reference, 0);
+        asm.emit(0xb4); // getfield
+        asm.emit_u2(handler_field);
+        asm.load(ValueKind::Reference, 0);
+        asm.emit(0xb2); // getstatic
+        asm.emit_u2(member);
+        let mut slot = 1;
+        if params.is_empty() {
+            asm.emit(0x01); // aconst_null
+        } else {
+            asm.push_int(params.len() as i32, &mut pool);
+            asm.emit(0xbd); // anewarray
+            asm.emit_u2(object);
+            for (position, param) in params.iter().enumerate() {
+                asm.emit(0x59); // dup
+                asm.push_int(position as i32, &mut pool);
+                let kind = value_kind(param);
+                asm.load(kind, slot);
+                slot += if matches!(kind, ValueKind::Long | ValueKind::Double) {
+                    2
+                } else {
+                    1
+                };
+                if let Some(box_class) = java_lang_boxing::box_class(param) {
+                    let box_index = pool.class(box_class);
+                    let value_of =
+                        pool.name_and_type("valueOf", &format!("({})L{};", param, box_class));
+                    let value_of = pool.method_ref(box_index, value_of);
+                    asm.emit(0xb8); // invokestatic
+                    asm.emit_u2(value_of);
+                }
+                asm.emit(0x53); // aastore
+            }
+        }
+        asm.emit(0xb9); // invokeinterface
+        asm.emit_u2(invoke);
+        asm.emit_u1(4);
+        asm.emit_u1(0);
+        match ret {
+            "V" => {
+                asm.emit(0x57); // pop
+                asm.return_value(None);
+            }
+            _ => match java_lang_boxing::box_class(ret) {
+                Some(box_class) => {
+                    let box_index = pool.class(box_class);
+                    asm.emit(0xc0); // checkcast
+                    asm.emit_u2(box_index);
+                    let accessor = format!("{}Value", java_lang_class::descriptor_type_name(ret));
+                    let accessor = pool.name_and_type(&accessor, &format!("(){}", ret));
+                    let accessor = pool.method_ref(box_index, accessor);
+                    asm.emit(0xb6); // invokevirtual
+                    asm.emit_u2(accessor);
+                    asm.return_value(Some(value_kind(ret)));
+                }
+                None => {
+                    let target = pool.class(java_lang_class::descriptor_type_name(ret));
+                    asm.emit(0xc0); // checkcast
+                    asm.emit_u2(target);
+                    asm.return_value(Some(ValueKind::Reference));
+                }
+            },
+        }
+        let (exception_table, attributes) =
+            catch_undeclared(&mut asm, &mut pool, &method.exceptions, slot);
+        let mut code = code(asm, 8, slot + 1)?;
+        code.exception_table = exception_table;
+        code.attributes = attributes;
+        class_methods.push(MethodInfo {
+            access_flags: ACC_PUBLIC | ACC_FINAL,
+            name_index: pool.utf8(&method.name),
+            descriptor_index: pool.utf8(&method.descriptor),
+            code: Some(code),
+            attributes: Vec::new(),
+        });
+    }
+
+    let constant_pool = pool.finish()?;
+    ClassFile {
+        magic: 0xCAFEBABE,
+        minor_version: 0,
+        major_version: 52,
+        constant_pool_count: constant_pool.len() as u16 + 1,
+        constant_pool,
+        access_flags: ACC_PUBLIC | ACC_FINAL | ACC_SUPER,
+        this_class,
+        super_class: proxy,
+        interfaces: interface_indexes,
+        fields,
+        methods: class_methods,
+        attributes: Vec::new(),
+    }
+    .to_bytes()
+}
+
+/// Appends handlers that rethrow unchecked and declared exceptions and
+/// wrap any other in an `UndeclaredThrowableException`, covering the code
+/// emitted so far. `slot` is the first free local.
+fn catch_undeclared(
+    asm: &mut Assembler,
+    pool: &mut ConstantPoolBuilder,
+    exceptions: &[String],
+    slot: u16,
+) -> (Vec<ExceptionTableEntry>, Vec<Attribute>) {
+    if exceptions
+        .iter()
+        .any(|exception| exception == "java/lang/Throwable")
+    {
+        return (Vec::new(), Vec::new());
+    }
+    let end_pc = asm.position() as u16;
+    let throwable = pool.class("java/lang/Throwable");
+    let rethrow = asm.position() as u16;
+    asm.emit(0xbf); // athrow
+    let wrap = asm.position() as u16;
+    let undeclared = pool.class(UNDECLARED_THROWABLE);
+    let init = pool.name_and_type("<init>", "(Ljava/lang/Throwable;)V");
+    let init = pool.method_ref(undeclared, init);
+    asm.store(ValueKind::Reference, slot);
+    asm.emit(0xbb); // new
+    asm.emit_u2(undeclared);
+    asm.emit(0x59); // dup
+    asm.load(ValueKind::Reference, slot);
+    asm.emit(0xb7); // invokespecial
+    asm.emit_u2(init);
+    asm.emit(0xbf); // athrow
+
+    let mut table: Vec<ExceptionTableEntry> = UNCHECKED
+        .iter()
+        .copied()
+        .chain(exceptions.iter().map(String::as_str))
+        .map(|exception| ExceptionTableEntry {
+            start_pc: 0,
+            end_pc,
+            handler_pc: rethrow,
+            catch_type: pool.class(exception),
+        })
+        .collect();
+    table.push(ExceptionTableEntry {
+        start_pc: 0,
+        end_pc,
+        handler_pc: wrap,
+        catch_type: throwable,
+    });
+    pool.utf8("StackMapTable");
+    let frames = vec![
+        StackMapFrame::SameLocals1StackItem {
+            offset_delta: rethrow,
+            stack: VerificationType::Object(throwable),
+        },
+        StackMapFrame::SameLocals1StackItem {
+            offset_delta: wrap - rethrow - 1,
+            stack: VerificationType::Object(throwable),
+        },
+    ];
+    (table, vec![Attribute::StackMapTable(frames)])
+}
+
+fn code(
+    asm: Assembler,
+    max_stack: u16,
+    max_locals: u16,
+) -> Result<CodeAttribute, ClassFormatError> {
+    Ok(CodeAttribute {
+        max_stack,
+        max_locals,
+        code: asm.finish()?,
+        exception_table: Vec::new(),
+        attributes: Vec::new(),
+    })
+}
+
+fn value_kind(descriptor: &str) -> ValueKind {
+    match descriptor {
+        "J" => ValueKind::Long,
+        "F" => ValueKind::Float,
+        "D" => ValueKind::Double,
+        "Z" | "B" | "C" | "S" | "I" => ValueKind::Int,
+        _ => ValueKind::Reference,
+    }
+}
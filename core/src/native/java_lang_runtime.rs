@@ -0,0 +1,132 @@
+use crate::native::java_io_printstream;
+use crate::native::java_lang_system;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::HeapValue;
+use crate::runtime::stack::Stack;
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("getRuntime", "()Ljava/lang/Runtime;", |env, _, _| {
+        Some(Some(current(env)))
+    }),
+    (
+        "addShutdownHook",
+        "(Ljava/lang/Thread;)V",
+        add_shutdown_hook,
+    ),
+    (
+        "removeShutdownHook",
+        "(Ljava/lang/Thread;)Z",
+        remove_shutdown_hook,
+    ),
+    ("exit", "(I)V", |env, _, args| {
+        java_lang_system::exit(env, args.first().map_or(0, HeapValue::as_int))
+    }),
+    // Ends the VM without running the hooks. Output already written
+    // still reaches the stream, as it would unbuffered.
+    ("halt", "(I)V", |_, _, args| {
+        java_io_printstream::flush_all();
+        std::process::exit(args.first().map_or(0, HeapValue::as_int))
+    }),
+    ("availableProcessors", "()I", |_, _, _| {
+        let count = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Some(Some(HeapValue::Int(count as i32)))
+    }),
+    ("totalMemory", "()J", |env, _, _| {
+        Some(Some(bytes(env.heap.committed_size())))
+    }),
+    ("freeMemory", "()J", |env, _, _| {
+        let free = env
+            .heap
+            .committed_size()
+            .saturating_sub(env.heap.used_size());
+        Some(Some(bytes(free)))
+    }),
+    ("maxMemory", "()J", |env, _, _| {
+        Some(Some(bytes(env.heap.max_size())))
+    }),
+    // The caller's frames are rooted through the references they
+    // suspended on the way here.
+    ("gc", "()V", |env, _, _| {
+        env.interpreter
+            .collect_garbage(env.loader, env.heap, &Stack::new());
+        Some(None)
+    }),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/lang/Runtime", METHODS);
+}
+
+fn add_shutdown_hook(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let hook = args.first()?.clone();
+    if let Some((class_name, message)) = check_hook_change(env, &hook) {
+        env.interpreter.throw_new(env.heap, class_name, message);
+    } else if env.interpreter.has_shutdown_hook(&hook) {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/IllegalArgumentException",
+            Some("Hook already registered"),
+        );
+    } else {
+        env.interpreter.add_shutdown_hook(hook);
+    }
+    Some(None)
+}
+
+fn remove_shutdown_hook(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let hook = args.first()?;
+    if let Some((class_name, message)) = check_hook_change(env, hook) {
+        env.interpreter.throw_new(env.heap, class_name, message);
+        return Some(Some(HeapValue::Int(0)));
+    }
+    let removed = env.interpreter.remove_shutdown_hook(hook);
+    Some(Some(HeapValue::Int(removed as i32)))
+}
+
+/// `Runtime.<clinit>`: creates the singleton `getRuntime` returns.
+pub fn initialize(env: &mut NativeEnv) {
+    let runtime = HeapValue::Object(env.heap.alloc_object("java/lang/Runtime"));
+    env.loader
+        .set_static_field("java/lang/Runtime", "currentRuntime", runtime);
+}
+
+fn current(env: &mut NativeEnv) -> HeapValue {
+    if let Some(runtime) = env
+        .loader
+        .get_static_field("java/lang/Runtime", "currentRuntime")
+    {
+        return runtime;
+    }
+    initialize(env);
+    current(env)
+}
+
+/// The exception adding or removing `hook` throws, if any.
+fn check_hook_change(
+    env: &NativeEnv,
+    hook: &HeapValue,
+) -> Option<(&'static str, Option<&'static str>)> {
+    if hook.is_null() {
+        return Some(("java/lang/NullPointerException", None));
+    }
+    if env.interpreter.is_shutting_down() {
+        return Some((
+            "java/lang/IllegalStateException",
+            Some("Shutdown in progress"),
+        ));
+    }
+    None
+}
+
+fn bytes(size: usize) -> HeapValue {
+    HeapValue::Long(size.min(i64::MAX as usize) as i64)
+}
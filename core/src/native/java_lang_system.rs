@@ -1,19 +1,544 @@
-use crate::runtime::heap::HeapValue;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::native::java_io_inputstream;
+use crate::native::java_io_printstream;
+use crate::native::java_lang_object::{array_class_name, identity_hash};
+use crate::native::jni::library;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{ArrayRef, ArrayType, HeapValue};
+use crate::runtime::stack::Stack;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-pub fn invoke(
-    method_name: &str,
-    descriptor: &str,
+const JAVA_VERSION: &str = include_str!("../../../VERSION_JAVA");
+
+/// Directories searched after `LD_LIBRARY_PATH`, as in HotSpot on Linux.
+const DEFAULT_LIBRARY_PATH: &str = "/usr/java/packages/lib:/usr/lib64:/lib64:/lib:/usr/lib";
+
+/// Origin for `nanoTime`, fixed the first time the clock is read.
+static NANO_ORIGIN: OnceLock<Instant> = OnceLock::new();
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("currentTimeMillis", "()J", current_time_millis),
+    ("nanoTime", "()J", nano_time),
+    (
+        "arraycopy",
+        "(Ljava/lang/Object;ILjava/lang/Object;II)V",
+        |env, _, args| {
+            if let [src, src_pos, dest, dest_pos, length] = args {
+                arraycopy(
+                    env,
+                    src,
+                    src_pos.as_int(),
+                    dest,
+                    dest_pos.as_int(),
+                    length.as_int(),
+                );
+            }
+            Some(None)
+        },
+    ),
+    ("identityHashCode", "(Ljava/lang/Object;)I", |_, _, args| {
+        let hash = args.first().map(identity_hash).unwrap_or(0);
+        Some(Some(HeapValue::Int(hash)))
+    }),
+    ("exit", "(I)V", |env, _, args| {
+        exit(env, args.first().map_or(0, HeapValue::as_int))
+    }),
+    ("lineSeparator", "()Ljava/lang/String;", |env, _, _| {
+        Some(Some(env.heap.alloc_string(line_separator())))
+    }),
+    ("getenv", "(Ljava/lang/String;)Ljava/lang/String;", getenv),
+    ("setOut", "(Ljava/io/PrintStream;)V", |env, _, args| {
+        set_stream(env, "out", args)
+    }),
+    ("setErr", "(Ljava/io/PrintStream;)V", |env, _, args| {
+        set_stream(env, "err", args)
+    }),
+    ("setIn", "(Ljava/io/InputStream;)V", |env, _, args| {
+        set_stream(env, "in", args)
+    }),
+    ("loadLibrary", "(Ljava/lang/String;)V", |env, _, args| {
+        load_library(env, args);
+        Some(None)
+    }),
+    ("load", "(Ljava/lang/String;)V", |env, _, args| {
+        load(env, args);
+        Some(None)
+    }),
+    (
+        "mapLibraryName",
+        "(Ljava/lang/String;)Ljava/lang/String;",
+        map_library_name,
+    ),
+    (
+        "getProperty",
+        "(Ljava/lang/String;)Ljava/lang/String;",
+        get_property,
+    ),
+    (
+        "getProperty",
+        "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+        get_property_or,
+    ),
+    (
+        "setProperty",
+        "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+        set_property,
+    ),
+    (
+        "clearProperty",
+        "(Ljava/lang/String;)Ljava/lang/String;",
+        clear_property,
+    ),
+    ("gc", "()V", |env, _, _| {
+        env.interpreter
+            .collect_garbage(env.loader, env.heap, &Stack::new());
+        Some(None)
+    }),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/lang/System", METHODS);
+}
+
+fn current_time_millis(
+    _env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    Some(Some(HeapValue::Long(millis)))
+}
+
+fn nano_time(
+    _env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
     _args: &[HeapValue],
 ) -> Option<Option<HeapValue>> {
-    match (method_name, descriptor) {
-        ("currentTimeMillis", "()J") => {
-            let millis = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as i64;
-            Some(Some(HeapValue::Long(millis)))
+    let origin = NANO_ORIGIN.get_or_init(Instant::now);
+    Some(Some(HeapValue::Long(origin.elapsed().as_nanos() as i64)))
+}
+
+fn getenv(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(name) = args.first().and_then(|v| env.heap.string_value(v)) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    let value = match std::env::var(name) {
+        Ok(value) => env.heap.alloc_string(&value),
+        Err(_) => HeapValue::Null,
+    };
+    Some(Some(value))
+}
+
+fn map_library_name(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(name) = args.first().and_then(|v| env.heap.string_value(v)) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    Some(Some(
+        env.heap.alloc_string(&library::map_library_name(&name)),
+    ))
+}
+
+fn get_property(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(key) = property_key(env, args) else {
+        return Some(None);
+    };
+    let value = env.interpreter.property(&key);
+    Some(Some(optional_string(env, value)))
+}
+
+/// `getProperty(key, def)`.
+fn get_property_or(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(key) = property_key(env, args) else {
+        return Some(None);
+    };
+    match env.interpreter.property(&key) {
+        Some(value) => Some(Some(env.heap.alloc_string(&value))),
+        None => Some(Some(args.get(1).cloned().unwrap_or(HeapValue::Null))),
+    }
+}
+
+fn set_property(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(key) = property_key(env, args) else {
+        return Some(None);
+    };
+    let Some(value) = args.get(1).and_then(|v| env.heap.string_value(v)) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return Some(None);
+    };
+    let previous = env.interpreter.set_property(&key, &value);
+    Some(Some(optional_string(env, previous)))
+}
+
+fn clear_property(
+    env: &mut NativeEnv,
+    _receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let Some(key) = property_key(env, args) else {
+        return Some(None);
+    };
+    let previous = env.interpreter.clear_property(&key);
+    Some(Some(optional_string(env, previous)))
+}
+
+/// `System.<clinit>`: binds `in`, `out` and `err` to file descriptors 0, 1
+/// and 2.
+pub fn initialize(env: &mut NativeEnv) {
+    let input = java_io_inputstream::new_stream(env.heap, 0);
+    let out = java_io_printstream::new_stream(env.heap, 1);
+    let err = java_io_printstream::new_stream(env.heap, 2);
+    env.loader.set_static_field("java/lang/System", "in", input);
+    env.loader.set_static_field("java/lang/System", "out", out);
+    env.loader.set_static_field("java/lang/System", "err", err);
+}
+
+pub fn line_separator() -> &'static str {
+    if cfg!(windows) {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// `System.exit`: runs the registered shutdown hooks, flushes the standard
+/// streams and terminates the process. Never returns.
+pub fn exit(env: &mut NativeEnv, status: i32) -> ! {
+    env.interpreter.run_shutdown_hooks(env.loader, env.heap);
+    java_io_printstream::flush_all();
+    std::process::exit(status)
+}
+
+/// The properties a fresh VM starts with.
+pub fn default_properties() -> HashMap<String, String> {
+    let mut library_path = std::env::var("LD_LIBRARY_PATH").unwrap_or_default();
+    if !library_path.is_empty() {
+        library_path.push(':');
+    }
+    library_path.push_str(DEFAULT_LIBRARY_PATH);
+    let java_version = JAVA_VERSION.trim();
+    let specification = java_version.split('.').next().unwrap_or(java_version);
+    let home = std::env::var("HOME").unwrap_or_else(|_| "?".to_string());
+    let user = std::env::var("USER").unwrap_or_else(|_| "?".to_string());
+    let dir = std::env::current_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let os_name = match std::env::consts::OS {
+        "linux" => "Linux",
+        "macos" => "Mac OS X",
+        "windows" => "Windows",
+        other => other,
+    };
+    let os_arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        other => other,
+    };
+    [
+        ("java.version", java_version),
+        ("java.specification.version", specification),
+        ("java.vm.name", "AriaJDK 64-Bit Server VM"),
+        ("java.library.path", &library_path),
+        ("line.separator", line_separator()),
+        ("file.separator", std::path::MAIN_SEPARATOR_STR),
+        ("path.separator", if cfg!(windows) { ";" } else { ":" }),
+        ("user.dir", &dir),
+        ("user.home", &home),
+        ("user.name", &user),
+        ("os.name", os_name),
+        ("os.arch", os_arch),
+        ("java.io.tmpdir", "/tmp"),
+        ("file.encoding", "UTF-8"),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect()
+}
+
+/// The key of a property accessor, with `System.checkKey`'s exceptions.
+fn property_key(env: &mut NativeEnv, args: &[HeapValue]) -> Option<String> {
+    let key = args.first().and_then(|v| env.heap.string_value(v));
+    let message = match &key {
+        None => "key can't be null",
+        Some(key) if key.is_empty() => "key can't be empty",
+        Some(_) => return key,
+    };
+    let class_name = if key.is_none() {
+        "java/lang/NullPointerException"
+    } else {
+        "java/lang/IllegalArgumentException"
+    };
+    env.interpreter
+        .throw_new(env.heap, class_name, Some(message));
+    None
+}
+
+fn optional_string(env: &mut NativeEnv, value: Option<String>) -> HeapValue {
+    match value {
+        Some(value) => env.heap.alloc_string(&value),
+        None => HeapValue::Null,
+    }
+}
+
+fn throw_unsatisfied_link(env: &mut NativeEnv, message: &str) {
+    env.interpreter
+        .throw_new(env.heap, "java/lang/UnsatisfiedLinkError", Some(message));
+}
+
+/// `System.loadLibrary`: searches `java.library.path` for the mapped name.
+fn load_library(env: &mut NativeEnv, args: &[HeapValue]) {
+    let Some(name) = args.first().and_then(|v| env.heap.string_value(v)) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return;
+    };
+    if name.contains(std::path::MAIN_SEPARATOR) || name.contains('/') {
+        let message = format!(
+            "Directory separator should not appear in library name: {}",
+            name
+        );
+        throw_unsatisfied_link(env, &message);
+        return;
+    }
+    let library_path = env
+        .interpreter
+        .property("java.library.path")
+        .unwrap_or_default();
+    let Some(path) = library::find_library(&name, &library_path) else {
+        let message = format!("no {} in java.library.path: {}", name, library_path);
+        throw_unsatisfied_link(env, &message);
+        return;
+    };
+    if let Err(message) = library::load(env, &path) {
+        throw_unsatisfied_link(env, &message);
+    }
+}
+
+/// `System.load`: the path must be absolute.
+fn load(env: &mut NativeEnv, args: &[HeapValue]) {
+    let Some(name) = args.first().and_then(|v| env.heap.string_value(v)) else {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return;
+    };
+    if !Path::new(&name).is_absolute() {
+        let message = format!("Expecting an absolute path of the library: {}", name);
+        throw_unsatisfied_link(env, &message);
+        return;
+    }
+    if let Err(message) = library::load(env, Path::new(&name)) {
+        throw_unsatisfied_link(env, &message);
+    }
+}
+
+fn set_stream(env: &mut NativeEnv, field: &str, args: &[HeapValue]) -> Option<Option<HeapValue>> {
+    let stream = args.first().cloned().unwrap_or(HeapValue::Null);
+    env.loader
+        .set_static_field("java/lang/System", field, stream);
+    Some(None)
+}
+
+/// `System.arraycopy` with the checks and messages of HotSpot's
+/// `copy_array`: null and non-array operands, component type mismatches,
+/// then index and length bounds. Overlapping ranges behave as if the source
+/// were first copied to a temporary array.
+fn arraycopy(
+    env: &mut NativeEnv,
+    src: &HeapValue,
+    src_pos: i32,
+    dest: &HeapValue,
+    dest_pos: i32,
+    length: i32,
+) {
+    if src.is_null() || dest.is_null() {
+        env.interpreter
+            .throw_new(env.heap, "java/lang/NullPointerException", None);
+        return;
+    }
+    let (HeapValue::Array(src_ref), HeapValue::Array(dest_ref)) = (src, dest) else {
+        let (role, value) = if matches!(src, HeapValue::Array(_)) {
+            ("destination", dest)
+        } else {
+            ("source", src)
+        };
+        let message = format!(
+            "arraycopy: {} type {} is not an array",
+            role,
+            value_class_name(env, value)
+        );
+        throw_array_store(env, &message);
+        return;
+    };
+    let (Some(src_arr), Some(dest_arr)) = (
+        env.heap.get_array(src_ref.id).cloned(),
+        env.heap.get_array(dest_ref.id).cloned(),
+    ) else {
+        return;
+    };
+
+    let src_is_ref = src_arr.element_type == ArrayType::Reference;
+    let dest_is_ref = dest_arr.element_type == ArrayType::Reference;
+    if src_is_ref != dest_is_ref || (!src_is_ref && src_arr.element_type != dest_arr.element_type) {
+        let message = format!(
+            "arraycopy: type mismatch: can not copy {}[] into {}[]",
+            element_type_name(&src_arr),
+            element_type_name(&dest_arr)
+        );
+        throw_array_store(env, &message);
+        return;
+    }
+
+    let src_len = src_arr.content.len() as i64;
+    let dest_len = dest_arr.content.len() as i64;
+    let bounds_error = if src_pos < 0 {
+        Some(format!(
+            "arraycopy: source index {} out of bounds for {}[{}]",
+            src_pos,
+            element_type_name(&src_arr),
+            src_len
+        ))
+    } else if dest_pos < 0 {
+        Some(format!(
+            "arraycopy: destination index {} out of bounds for {}[{}]",
+            dest_pos,
+            element_type_name(&dest_arr),
+            dest_len
+        ))
+    } else if length < 0 {
+        Some(format!("arraycopy: length {} is negative", length))
+    } else if src_pos as i64 + length as i64 > src_len {
+        Some(format!(
+            "arraycopy: last source index {} out of bounds for {}[{}]",
+            src_pos as i64 + length as i64,
+            element_type_name(&src_arr),
+            src_len
+        ))
+    } else if dest_pos as i64 + length as i64 > dest_len {
+        Some(format!(
+            "arraycopy: last destination index {} out of bounds for {}[{}]",
+            dest_pos as i64 + length as i64,
+            element_type_name(&dest_arr),
+            dest_len
+        ))
+    } else {
+        None
+    };
+    if let Some(message) = bounds_error {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/ArrayIndexOutOfBoundsException",
+            Some(&message),
+        );
+        return;
+    }
+
+    let (src_pos, dest_pos, length) = (src_pos as usize, dest_pos as usize, length as usize);
+    let elements = src_arr.content[src_pos..src_pos + length].to_vec();
+
+    // Reference copies stop at the first element the destination's
+    // component type cannot hold; everything before it stays copied.
+    let dest_component = dest_arr
+        .component_class
+        .clone()
+        .unwrap_or_else(|| "java/lang/Object".to_string());
+    let src_component = src_arr
+        .component_class
+        .clone()
+        .unwrap_or_else(|| "java/lang/Object".to_string());
+    let check_elements = dest_is_ref
+        && !env
+            .interpreter
+            .is_assignable(env.loader, &src_component, &dest_component);
+    let mut copied = elements.len();
+    if check_elements {
+        for (i, element) in elements.iter().enumerate() {
+            if element.is_null() {
+                continue;
+            }
+            let class_name = value_class_name(env, element).replace('.', "/");
+            if !env
+                .interpreter
+                .is_assignable(env.loader, &class_name, &dest_component)
+            {
+                copied = i;
+                break;
+            }
         }
-        _ => None,
+    }
+
+    if let Some(target) = env.heap.get_array_mut(dest_arr.id) {
+        target.content[dest_pos..dest_pos + copied].clone_from_slice(&elements[..copied]);
+    }
+    if copied < elements.len() {
+        let message = format!(
+            "arraycopy: element type mismatch: can not cast one of the elements of {}[] to the type of the destination array, {}",
+            src_component.replace('/', "."),
+            dest_component.replace('/', ".")
+        );
+        throw_array_store(env, &message);
+    }
+}
+
+fn throw_array_store(env: &mut NativeEnv, message: &str) {
+    env.interpreter
+        .throw_new(env.heap, "java/lang/ArrayStoreException", Some(message));
+}
+
+/// HotSpot's spelling of an array's element type in arraycopy messages.
+fn element_type_name(arr: &ArrayRef) -> &'static str {
+    match arr.element_type {
+        ArrayType::Boolean => "boolean",
+        ArrayType::Char => "char",
+        ArrayType::Float => "float",
+        ArrayType::Double => "double",
+        ArrayType::Byte => "byte",
+        ArrayType::Short => "short",
+        ArrayType::Int => "int",
+        ArrayType::Long => "long",
+        ArrayType::Reference => "object array",
+    }
+}
+
+/// `getClass().getName()` of a reference value.
+fn value_class_name(env: &NativeEnv, value: &HeapValue) -> String {
+    match value {
+        HeapValue::Object(obj) => obj.class_name.replace('/', "."),
+        HeapValue::String(_) => "java.lang.String".to_string(),
+        HeapValue::Array(arr) => env
+            .heap
+            .get_array(arr.id)
+            .map(array_class_name)
+            .unwrap_or_else(|| array_class_name(arr))
+            .replace('/', "."),
+        _ => "null".to_string(),
     }
 }
@@ -0,0 +1,129 @@
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{Heap, HeapValue};
+
+/// Enough of `Thread` for shutdown hooks: a name and a `Runnable` target.
+/// There is a single Java thread, so nothing here starts one.
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("<init>", "()V", |env, this, _| {
+        init(env, this, HeapValue::Null, None)
+    }),
+    ("<init>", "(Ljava/lang/Runnable;)V", |env, this, args| {
+        init(env, this, args.first()?.clone(), None)
+    }),
+    ("<init>", "(Ljava/lang/String;)V", |env, this, args| {
+        init(env, this, HeapValue::Null, Some(args.first()?.clone()))
+    }),
+    (
+        "<init>",
+        "(Ljava/lang/Runnable;Ljava/lang/String;)V",
+        |env, this, args| init(env, this, args.first()?.clone(), Some(args.get(1)?.clone())),
+    ),
+    ("run", "()V", run),
+    ("getName", "()Ljava/lang/String;", |env, this, _| {
+        Some(Some(field(env.heap, this_id(this)?, "name")))
+    }),
+    ("setName", "(Ljava/lang/String;)V", set_name),
+];
+
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/lang/Thread", METHODS);
+}
+
+fn this_id(receiver: Option<&HeapValue>) -> Option<u64> {
+    match receiver {
+        Some(HeapValue::Object(this)) => Some(this.id),
+        _ => None,
+    }
+}
+
+/// The `Thread` constructors; an unnamed thread gets the next `Thread-n`.
+fn init(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    target: HeapValue,
+    name: Option<HeapValue>,
+) -> Option<Option<HeapValue>> {
+    let id = this_id(receiver)?;
+    if name.as_ref().is_some_and(HeapValue::is_null) {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/NullPointerException",
+            Some("'name' is null"),
+        );
+        return Some(None);
+    }
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let number = next_thread_number(env);
+            env.heap.alloc_string(&format!("Thread-{}", number))
+        }
+    };
+    let real = env.heap.get_mut(id)?;
+    real.set_field("name", name);
+    real.set_field("target", target);
+    Some(None)
+}
+
+fn run(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    _args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let target = field(env.heap, this_id(receiver)?, "target");
+    if !target.is_null() {
+        env.interpreter
+            .invoke_virtual(env.loader, env.heap, &target, "run", "()V", &[]);
+    }
+    Some(None)
+}
+
+fn set_name(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let id = this_id(receiver)?;
+    let name = args.first()?.clone();
+    if name.is_null() {
+        env.interpreter.throw_new(
+            env.heap,
+            "java/lang/NullPointerException",
+            Some("'name' is null"),
+        );
+        return Some(None);
+    }
+    env.heap.get_mut(id)?.set_field("name", name);
+    Some(None)
+}
+
+/// Numbers unnamed threads `Thread-0`, `Thread-1`, ... in creation order.
+fn next_thread_number(env: &mut NativeEnv) -> i32 {
+    let number = env
+        .loader
+        .get_static_field("java/lang/Thread", "threadInitNumber")
+        .map_or(0, |value| value.as_int());
+    env.loader.set_static_field(
+        "java/lang/Thread",
+        "threadInitNumber",
+        HeapValue::Int(number + 1),
+    );
+    number
+}
+
+fn field(heap: &Heap, id: u64, name: &str) -> HeapValue {
+    heap.get(id)
+        .and_then(|obj| obj.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
+
+/// The name of `thread` for messages; `Thread-0` if it has none.
+pub fn name(heap: &Heap, thread: &HeapValue) -> String {
+    let HeapValue::Object(obj) = thread else {
+        return "Thread-0".to_string();
+    };
+    heap.string_value(&field(heap, obj.id, "name"))
+        .unwrap_or_else(|| "Thread-0".to_string())
+}
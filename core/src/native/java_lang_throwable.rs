@@ -0,0 +1,407 @@
+use crate::native::java_lang_object::same_reference;
+use crate::native::registry::{BuiltinMethod, NativeRegistry};
+use crate::native::NativeEnv;
+use crate::runtime::heap::{Heap, HeapValue};
+
+/// Superclass links for the throwables the VM itself can raise or that
+/// user code commonly extends. None of these have a `.class` file.
+const THROWABLE_HIERARCHY: &[(&str, &str)] = &[
+    ("java/lang/Throwable", "java/lang/Object"),
+    ("java/lang/Exception", "java/lang/Throwable"),
+    ("java/lang/Error", "java/lang/Throwable"),
+    ("java/lang/RuntimeException", "java/lang/Exception"),
+    (
+        "java/lang/ArithmeticException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/ArrayStoreException",
+        "java/lang/RuntimeException",
+    ),
+    ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+    (
+        "java/lang/IllegalArgumentException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalStateException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalMonitorStateException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IndexOutOfBoundsException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/ArrayIndexOutOfBoundsException",
+        "java/lang/IndexOutOfBoundsException",
+    ),
+    (
+        "java/lang/StringIndexOutOfBoundsException",
+        "java/lang/IndexOutOfBoundsException",
+    ),
+    (
+        "java/lang/NegativeArraySizeException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/NullPointerException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/NumberFormatException",
+        "java/lang/IllegalArgumentException",
+    ),
+    ("java/lang/SecurityException", "java/lang/RuntimeException"),
+    (
+        "java/lang/UnsupportedOperationException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/invoke/WrongMethodTypeException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/CloneNotSupportedException",
+        "java/lang/Exception",
+    ),
+    ("java/lang/InterruptedException", "java/lang/Exception"),
+    (
+        "java/lang/ReflectiveOperationException",
+        "java/lang/Exception",
+    ),
+    (
+        "java/lang/ClassNotFoundException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/IllegalAccessException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/InstantiationException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/NoSuchFieldException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/NoSuchMethodException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/reflect/InvocationTargetException",
+        "java/lang/ReflectiveOperationException",
+    ),
+    (
+        "java/lang/reflect/UndeclaredThrowableException",
+        "java/lang/RuntimeException",
+    ),
+    ("java/io/IOException", "java/lang/Exception"),
+    ("java/io/UncheckedIOException", "java/lang/RuntimeException"),
+    (
+        "java/util/NoSuchElementException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/util/ConcurrentModificationException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/util/IllegalFormatException",
+        "java/lang/IllegalArgumentException",
+    ),
+    (
+        "java/util/IllegalFormatCodePointException",
+        "java/util/IllegalFormatException",
+    ),
+    (
+        "java/util/IllegalFormatConversionException",
+        "java/util/IllegalFormatException",
+    ),
+    (
+        "java/util/IllegalFormatFlagsException",
+        "java/util/IllegalFormatException",
+    ),
+    (
+        "java/util/IllegalFormatPrecisionException",
+        "java/util/IllegalFormatException",
+    ),
+    (
+        "java/util/MissingFormatArgumentException",
+        "java/util/IllegalFormatException",
+    ),
+    (
+        "java/util/MissingFormatWidthException",
+        "java/util/IllegalFormatException",
+    ),
+    (
+        "java/util/UnknownFormatConversionException",
+        "java/util/IllegalFormatException",
+    ),
+    ("java/lang/LinkageError", "java/lang/Error"),
+    ("java/lang/ClassFormatError", "java/lang/LinkageError"),
+    (
+        "java/lang/ExceptionInInitializerError",
+        "java/lang/LinkageError",
+    ),
+    ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+    ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+    ("java/lang/BootstrapMethodError", "java/lang/LinkageError"),
+    ("java/lang/VerifyError", "java/lang/LinkageError"),
+    (
+        "java/lang/IncompatibleClassChangeError",
+        "java/lang/LinkageError",
+    ),
+    (
+        "java/lang/AbstractMethodError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/NoSuchFieldError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/NoSuchMethodError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    ("java/lang/AssertionError", "java/lang/Error"),
+    ("java/lang/VirtualMachineError", "java/lang/Error"),
+    ("java/lang/InternalError", "java/lang/VirtualMachineError"),
+    (
+        "java/lang/OutOfMemoryError",
+        "java/lang/VirtualMachineError",
+    ),
+    (
+        "java/lang/StackOverflowError",
+        "java/lang/VirtualMachineError",
+    ),
+];
+
+/// The builtin throwable classes, superclasses first.
+pub fn throwable_classes() -> impl Iterator<Item = &'static str> {
+    THROWABLE_HIERARCHY.iter().map(|(name, _)| *name)
+}
+
+pub fn is_throwable_class(class_name: &str) -> bool {
+    THROWABLE_HIERARCHY
+        .iter()
+        .any(|(name, _)| *name == class_name)
+}
+
+pub fn builtin_superclass(class_name: &str) -> Option<&'static str> {
+    THROWABLE_HIERARCHY
+        .iter()
+        .find(|(name, _)| *name == class_name)
+        .map(|(_, parent)| *parent)
+}
+
+/// Allocates a throwable with the given message and backtrace without
+/// running any constructor.
+pub fn new_throwable(
+    heap: &mut Heap,
+    class_name: &str,
+    message: Option<&str>,
+    backtrace: Vec<String>,
+) -> HeapValue {
+    let obj = heap.alloc_object(class_name);
+    let message = match message {
+        Some(text) => heap.alloc_string(text),
+        None => HeapValue::Null,
+    };
+    if let Some(real) = heap.get_mut(obj.id) {
+        real.set_field("detailMessage", message);
+        real.set_field("cause", HeapValue::Null);
+        real.set_field("backtrace", HeapValue::String(backtrace.join("\n")));
+    }
+    HeapValue::Object(obj)
+}
+
+const METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("<init>", "()V", |env, this, _| {
+        init(env, this, HeapValue::Null, HeapValue::Null)
+    }),
+    ("<init>", "(Ljava/lang/String;)V", |env, this, args| {
+        init(env, this, args.first()?.clone(), HeapValue::Null)
+    }),
+    (
+        "<init>",
+        "(Ljava/lang/String;Ljava/lang/Throwable;)V",
+        |env, this, args| init(env, this, args.first()?.clone(), args.get(1)?.clone()),
+    ),
+    ("<init>", "(Ljava/lang/Throwable;)V", |env, this, args| {
+        let cause = args.first()?.clone();
+        let message = if cause.is_null() {
+            HeapValue::Null
+        } else {
+            let text = describe_one(env, &cause);
+            env.heap.alloc_string(&text)
+        };
+        init(env, this, message, cause)
+    }),
+    ("getMessage", "()Ljava/lang/String;", |env, this, _| {
+        Some(Some(field(env, this_id(this)?, "detailMessage")))
+    }),
+    (
+        "getLocalizedMessage",
+        "()Ljava/lang/String;",
+        |env, this, _| Some(Some(field(env, this_id(this)?, "detailMessage"))),
+    ),
+    ("getCause", "()Ljava/lang/Throwable;", |env, this, _| {
+        let cause = field(env, this_id(this)?, "cause");
+        if same_reference(&cause, this?) {
+            return Some(Some(HeapValue::Null));
+        }
+        Some(Some(cause))
+    }),
+    (
+        "initCause",
+        "(Ljava/lang/Throwable;)Ljava/lang/Throwable;",
+        |env, this, args| {
+            let cause = args.first()?.clone();
+            env.heap.get_mut(this_id(this)?)?.set_field("cause", cause);
+            Some(Some(this?.clone()))
+        },
+    ),
+    (
+        "fillInStackTrace",
+        "()Ljava/lang/Throwable;",
+        |env, this, _| {
+            let backtrace = env.interpreter.backtrace();
+            env.heap
+                .get_mut(this_id(this)?)?
+                .set_field("backtrace", HeapValue::String(backtrace.join("\n")));
+            Some(Some(this?.clone()))
+        },
+    ),
+    ("addSuppressed", "(Ljava/lang/Throwable;)V", |_, this, _| {
+        this_id(this)?;
+        Some(None)
+    }),
+    ("toString", "()Ljava/lang/String;", |env, this, _| {
+        this_id(this)?;
+        let text = describe_one(env, this?);
+        Some(Some(env.heap.alloc_string(&text)))
+    }),
+    ("printStackTrace", "()V", |env, this, _| {
+        this_id(this)?;
+        let text = describe(env, this?);
+        crate::native::java_io_printstream::flush(1);
+        eprintln!("{}", text);
+        Some(None)
+    }),
+];
+
+/// `AssertionError`'s constructors for `assert cond : detail`.
+const ASSERTION_ERROR_METHODS: &[(&str, &str, BuiltinMethod)] = &[
+    ("<init>", "(Ljava/lang/Object;)V", init_assertion_error),
+    ("<init>", "(Z)V", init_assertion_error),
+    ("<init>", "(C)V", init_assertion_error),
+    ("<init>", "(I)V", init_assertion_error),
+    ("<init>", "(J)V", init_assertion_error),
+    ("<init>", "(F)V", init_assertion_error),
+    ("<init>", "(D)V", init_assertion_error),
+];
+
+/// Subclasses inherit these bindings: native lookup walks the builtin
+/// superclass chain, constructors included.
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register_all("java/lang/Throwable", METHODS);
+    registry.register_all("java/lang/AssertionError", ASSERTION_ERROR_METHODS);
+}
+
+fn this_id(receiver: Option<&HeapValue>) -> Option<u64> {
+    match receiver? {
+        HeapValue::Object(this) => Some(this.id),
+        _ => None,
+    }
+}
+
+/// The `Throwable` constructors, which also record the backtrace.
+fn init(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    message: HeapValue,
+    cause: HeapValue,
+) -> Option<Option<HeapValue>> {
+    let id = this_id(receiver)?;
+    let backtrace = env.interpreter.backtrace();
+    let real = env.heap.get_mut(id)?;
+    real.set_field("detailMessage", message);
+    real.set_field("cause", cause);
+    real.set_field("backtrace", HeapValue::String(backtrace.join("\n")));
+    Some(None)
+}
+
+/// `AssertionError(Object)` and friends: the detail's string form is the
+/// message, and a throwable detail is also the cause.
+fn init_assertion_error(
+    env: &mut NativeEnv,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<Option<HeapValue>> {
+    let detail = args.first()?.clone();
+    let text = env.to_java_string(&detail);
+    let cause = match &detail {
+        HeapValue::Object(obj) if is_throwable(env, &obj.class_name) => detail.clone(),
+        _ => HeapValue::Null,
+    };
+    let message = env.heap.alloc_string(&text);
+    init(env, receiver, message, cause)
+}
+
+fn field(env: &NativeEnv, id: u64, name: &str) -> HeapValue {
+    env.heap
+        .get(id)
+        .and_then(|obj| obj.get_field(name))
+        .cloned()
+        .unwrap_or(HeapValue::Null)
+}
+
+fn is_throwable(env: &mut NativeEnv, class_name: &str) -> bool {
+    env.interpreter
+        .is_subclass_of(env.loader, class_name, "java/lang/Throwable")
+}
+
+/// `Throwable.toString()`: the binary class name, then the message if any.
+pub fn describe_one(env: &mut NativeEnv, throwable: &HeapValue) -> String {
+    let HeapValue::Object(obj) = throwable else {
+        return "null".to_string();
+    };
+    let name = env.loader.symbolic_name(&obj.class_name).replace('/', ".");
+    let message = field(env, obj.id, "detailMessage");
+    match env.heap.string_value(&message) {
+        Some(text) => format!("{}: {}", name, text),
+        None => name,
+    }
+}
+
+/// The text `printStackTrace()` writes: the throwable, its frames and the
+/// chain of causes.
+pub fn describe(env: &mut NativeEnv, throwable: &HeapValue) -> String {
+    let mut out = String::new();
+    let mut current = throwable.clone();
+    let mut seen = Vec::new();
+    while let HeapValue::Object(obj) = &current {
+        if seen.contains(&obj.id) {
+            break;
+        }
+        seen.push(obj.id);
+        if !out.is_empty() {
+            out.push_str("\nCaused by: ");
+        }
+        out.push_str(&describe_one(env, &current));
+        if let HeapValue::String(trace) = field(env, obj.id, "backtrace") {
+            for line in trace.lines() {
+                out.push_str("\n\tat ");
+                out.push_str(line);
+            }
+        }
+        current = field(env, obj.id, "cause");
+    }
+    out
+}
@@ -0,0 +1,635 @@
+use crate::native::java_lang_boxing::{shortest_digits, unbox};
+use crate::native::NativeEnv;
+use crate::runtime::heap::HeapValue;
+
+/// A `java.util.IllegalFormatException` subclass and its detail message.
+#[derive(Debug, Clone)]
+pub struct FormatError {
+    pub class_name: &'static str,
+    pub message: String,
+}
+
+impl FormatError {
+    fn new(class_name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            class_name,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Flags {
+    left: bool,
+    alternate: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    group: bool,
+    paren: bool,
+}
+
+#[derive(Debug)]
+struct Spec {
+    text: String,
+    index: ArgIndex,
+    flags: Flags,
+    width: Option<usize>,
+    precision: Option<usize>,
+    conversion: char,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ArgIndex {
+    Ordinary,
+    Explicit(usize),
+    Previous,
+}
+
+/// Formats `pattern` against the already unpacked varargs array, following
+/// `java.util.Formatter` in the default (en-US) locale. Date/time
+/// conversions are not supported.
+pub fn format(
+    env: &mut NativeEnv,
+    pattern: &str,
+    args: &[HeapValue],
+) -> Result<String, FormatError> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::new();
+    let mut i = 0usize;
+    let mut ordinary = 0usize;
+    let mut last: Option<usize> = None;
+
+    while i < chars.len() {
+        if chars[i] != '%' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let spec = parse_spec(&chars, &mut i)?;
+        match spec.conversion {
+            '%' => {
+                if spec.precision.is_some() {
+                    return Err(FormatError::new(
+                        "java/util/IllegalFormatPrecisionException",
+                        spec.precision.unwrap_or(0).to_string(),
+                    ));
+                }
+                out.push_str(&justify("%".to_string(), &spec));
+                continue;
+            }
+            'n' => {
+                out.push('\n');
+                continue;
+            }
+            _ => {}
+        }
+
+        let arg_index = match spec.index {
+            ArgIndex::Ordinary => {
+                ordinary += 1;
+                ordinary - 1
+            }
+            ArgIndex::Explicit(n) => n - 1,
+            ArgIndex::Previous => last.ok_or_else(|| {
+                FormatError::new(
+                    "java/util/MissingFormatArgumentException",
+                    format!("Format specifier '{}'", spec.text),
+                )
+            })?,
+        };
+        last = Some(arg_index);
+        let Some(arg) = args.get(arg_index) else {
+            return Err(FormatError::new(
+                "java/util/MissingFormatArgumentException",
+                format!("Format specifier '{}'", spec.text),
+            ));
+        };
+
+        let rendered = convert(env, &spec, arg)?;
+        out.push_str(&rendered);
+    }
+    Ok(out)
+}
+
+fn parse_spec(chars: &[char], i: &mut usize) -> Result<Spec, FormatError> {
+    let start = *i;
+    *i += 1;
+
+    let read_number = |i: &mut usize| -> Option<usize> {
+        let from = *i;
+        while *i < chars.len() && chars[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        if *i == from {
+            None
+        } else {
+            chars[from..*i].iter().collect::<String>().parse().ok()
+        }
+    };
+
+    let mut index = ArgIndex::Ordinary;
+    let mut flags = Flags::default();
+
+    // An explicit index looks like a width until the '$' shows up.
+    let checkpoint = *i;
+    if let Some(n) = read_number(i) {
+        if *i < chars.len() && chars[*i] == '$' && n > 0 {
+            index = ArgIndex::Explicit(n);
+            *i += 1;
+        } else {
+            *i = checkpoint;
+        }
+    }
+
+    while *i < chars.len() {
+        match chars[*i] {
+            '-' => flags.left = true,
+            '#' => flags.alternate = true,
+            '+' => flags.plus = true,
+            ' ' => flags.space = true,
+            '0' => flags.zero = true,
+            ',' => flags.group = true,
+            '(' => flags.paren = true,
+            '<' => index = ArgIndex::Previous,
+            _ => break,
+        }
+        *i += 1;
+    }
+
+    let width = read_number(i);
+    let mut precision = None;
+    if *i < chars.len() && chars[*i] == '.' {
+        *i += 1;
+        precision = Some(read_number(i).ok_or_else(|| {
+            FormatError::new(
+                "java/util/UnknownFormatConversionException",
+                "Conversion = '.'".to_string(),
+            )
+        })?);
+    }
+
+    let Some(&conversion) = chars.get(*i) else {
+        return Err(FormatError::new(
+            "java/util/UnknownFormatConversionException",
+            "Conversion = '%'",
+        ));
+    };
+    *i += 1;
+    if conversion == 't' || conversion == 'T' {
+        let suffix = chars.get(*i).copied().unwrap_or('%');
+        return Err(FormatError::new(
+            "java/util/UnknownFormatConversionException",
+            format!("Conversion = '{}{}'", conversion, suffix),
+        ));
+    }
+    if !"bBhHsScCdoxXeEfgGaA%n".contains(conversion) {
+        return Err(FormatError::new(
+            "java/util/UnknownFormatConversionException",
+            format!("Conversion = '{}'", conversion),
+        ));
+    }
+
+    let text: String = chars[start..*i].iter().collect();
+    if (flags.left || flags.zero) && width.is_none() {
+        return Err(FormatError::new(
+            "java/util/MissingFormatWidthException",
+            text,
+        ));
+    }
+    if flags.left && flags.zero {
+        return Err(FormatError::new(
+            "java/util/IllegalFormatFlagsException",
+            "Flags = '-0'",
+        ));
+    }
+
+    Ok(Spec {
+        text,
+        index,
+        flags,
+        width,
+        precision,
+        conversion,
+    })
+}
+
+fn convert(env: &mut NativeEnv, spec: &Spec, arg: &HeapValue) -> Result<String, FormatError> {
+    let upper = spec.conversion.is_ascii_uppercase();
+    let conversion = spec.conversion.to_ascii_lowercase();
+    let boxed = match arg {
+        HeapValue::Object(obj) => unbox(env.heap, obj).map(|v| (obj.class_name.clone(), v)),
+        _ => None,
+    };
+
+    let body = match conversion {
+        'b' => {
+            let truth = match (&boxed, arg) {
+                (_, HeapValue::Null) => false,
+                (Some((class, value)), _) if class == "java/lang/Boolean" => value.as_int() != 0,
+                _ => true,
+            };
+            truncate(truth.to_string(), spec.precision)
+        }
+        's' => truncate(env.to_java_string(arg), spec.precision),
+        'h' => {
+            let rendered = match arg {
+                HeapValue::Null => "null".to_string(),
+                _ => format!("{:x}", hash_code(env, arg)),
+            };
+            truncate(rendered, spec.precision)
+        }
+        'c' => {
+            if spec.precision.is_some() {
+                return Err(precision_error(spec));
+            }
+            match (&boxed, arg) {
+                (_, HeapValue::Null) => "null".to_string(),
+                (Some((class, value)), _)
+                    if matches!(
+                        class.as_str(),
+                        "java/lang/Character"
+                            | "java/lang/Integer"
+                            | "java/lang/Short"
+                            | "java/lang/Byte"
+                    ) =>
+                {
+                    let code = value.as_int() as u32;
+                    match char::from_u32(code) {
+                        Some(c) => c.to_string(),
+                        None => {
+                            return Err(FormatError::new(
+                                "java/util/IllegalFormatCodePointException",
+                                format!("Code point = 0x{:x}", code),
+                            ))
+                        }
+                    }
+                }
+                _ => return Err(conversion_error(env, spec, arg)),
+            }
+        }
+        'd' | 'o' | 'x' => {
+            if spec.precision.is_some() {
+                return Err(precision_error(spec));
+            }
+            match (&boxed, arg) {
+                (_, HeapValue::Null) => "null".to_string(),
+                (Some((class, value)), _) => {
+                    let bits = match class.as_str() {
+                        "java/lang/Byte" => 8,
+                        "java/lang/Short" => 16,
+                        "java/lang/Integer" => 32,
+                        "java/lang/Long" => 64,
+                        _ => return Err(conversion_error(env, spec, arg)),
+                    };
+                    let v = match value {
+                        HeapValue::Long(v) => *v,
+                        other => other.as_int() as i64,
+                    };
+                    return Ok(apply_case(
+                        format_integral(spec, conversion, v, bits),
+                        upper,
+                    ));
+                }
+                _ => return Err(conversion_error(env, spec, arg)),
+            }
+        }
+        'e' | 'f' | 'g' | 'a' => match (&boxed, arg) {
+            (_, HeapValue::Null) => "null".to_string(),
+            (Some((class, value)), _)
+                if class == "java/lang/Double" || class == "java/lang/Float" =>
+            {
+                let v = match value {
+                    HeapValue::Float(f) => *f as f64,
+                    HeapValue::Double(d) => *d,
+                    _ => 0.0,
+                };
+                return Ok(apply_case(format_floating(spec, conversion, v), upper));
+            }
+            _ => return Err(conversion_error(env, spec, arg)),
+        },
+        _ => unreachable!("conversion validated by parse_spec"),
+    };
+
+    Ok(apply_case(justify(body, spec), upper))
+}
+
+fn apply_case(text: String, upper: bool) -> String {
+    if upper {
+        text.to_uppercase()
+    } else {
+        text
+    }
+}
+
+fn truncate(text: String, precision: Option<usize>) -> String {
+    match precision {
+        Some(p) => text.chars().take(p).collect(),
+        None => text,
+    }
+}
+
+fn justify(text: String, spec: &Spec) -> String {
+    let len = text.chars().count();
+    let Some(width) = spec.width.filter(|w| *w > len) else {
+        return text;
+    };
+    let pad = " ".repeat(width - len);
+    if spec.flags.left {
+        text + &pad
+    } else {
+        pad + &text
+    }
+}
+
+fn precision_error(spec: &Spec) -> FormatError {
+    FormatError::new(
+        "java/util/IllegalFormatPrecisionException",
+        spec.precision.unwrap_or(0).to_string(),
+    )
+}
+
+fn conversion_error(env: &NativeEnv, spec: &Spec, arg: &HeapValue) -> FormatError {
+    let class_name = match arg {
+        HeapValue::Object(obj) => obj.class_name.replace('/', "."),
+        HeapValue::Array(_) => "[Ljava.lang.Object;".to_string(),
+        _ if env.heap.string_value(arg).is_some() => "java.lang.String".to_string(),
+        _ => "java.lang.Object".to_string(),
+    };
+    FormatError::new(
+        "java/util/IllegalFormatConversionException",
+        format!("{} != {}", spec.conversion, class_name),
+    )
+}
+
+fn hash_code(env: &mut NativeEnv, arg: &HeapValue) -> u32 {
+    if let Some(s) = env.heap.string_value(arg) {
+        return s
+            .encode_utf16()
+            .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32)) as u32;
+    }
+    env.invoke_virtual(arg, "hashCode", "()I", &[])
+        .map(|v| v.as_int() as u32)
+        .unwrap_or(0)
+}
+
+fn format_integral(spec: &Spec, conversion: char, value: i64, bits: u32) -> String {
+    let flags = spec.flags;
+    let (sign, digits, prefix) = match conversion {
+        'd' => {
+            let magnitude = value.unsigned_abs().to_string();
+            let magnitude = if flags.group {
+                group_thousands(&magnitude)
+            } else {
+                magnitude
+            };
+            (sign_prefix(value < 0, &flags), magnitude, "")
+        }
+        _ => {
+            let mask = if bits == 64 {
+                u64::MAX
+            } else {
+                (1u64 << bits) - 1
+            };
+            let raw = (value as u64) & mask;
+            let digits = if conversion == 'o' {
+                format!("{:o}", raw)
+            } else {
+                format!("{:x}", raw)
+            };
+            let prefix = match (conversion, flags.alternate) {
+                ('o', true) => "0",
+                ('x', true) => "0x",
+                _ => "",
+            };
+            (String::new(), digits, prefix)
+        }
+    };
+    let suffix = if conversion == 'd' && flags.paren && value < 0 {
+        ")"
+    } else {
+        ""
+    };
+    pad_number(spec, &(sign + prefix), &digits, suffix)
+}
+
+fn sign_prefix(negative: bool, flags: &Flags) -> String {
+    if negative {
+        if flags.paren {
+            "(".to_string()
+        } else {
+            "-".to_string()
+        }
+    } else if flags.plus {
+        "+".to_string()
+    } else if flags.space {
+        " ".to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Zero padding goes between the sign/prefix and the digits; space padding
+/// goes outside of everything.
+fn pad_number(spec: &Spec, lead: &str, digits: &str, trail: &str) -> String {
+    let len = lead.chars().count() + digits.chars().count() + trail.chars().count();
+    let width = spec.width.unwrap_or(0);
+    if width <= len {
+        return format!("{}{}{}", lead, digits, trail);
+    }
+    let fill = width - len;
+    if spec.flags.zero {
+        format!("{}{}{}{}", lead, "0".repeat(fill), digits, trail)
+    } else if spec.flags.left {
+        format!("{}{}{}{}", lead, digits, trail, " ".repeat(fill))
+    } else {
+        format!("{}{}{}{}", " ".repeat(fill), lead, digits, trail)
+    }
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut out = String::new();
+    let len = digits.len();
+    for (idx, ch) in digits.chars().enumerate() {
+        if idx > 0 && (len - idx).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+fn format_floating(spec: &Spec, conversion: char, value: f64) -> String {
+    let flags = spec.flags;
+    if value.is_nan() || value.is_infinite() {
+        let text = if value.is_nan() {
+            "NaN".to_string()
+        } else if value < 0.0 {
+            if flags.paren {
+                "(Infinity)".to_string()
+            } else {
+                "-Infinity".to_string()
+            }
+        } else {
+            sign_prefix(false, &flags) + "Infinity"
+        };
+        return justify(text, spec);
+    }
+
+    if conversion == 'a' {
+        return justify(
+            sign_prefix(value.is_sign_negative(), &flags) + &hex_float(value.abs()),
+            spec,
+        );
+    }
+
+    let negative = value.is_sign_negative();
+    let magnitude = value.abs();
+    let body = match conversion {
+        'f' => fixed_notation(magnitude, spec.precision.unwrap_or(6), flags.group),
+        'e' => scientific_notation(magnitude, spec.precision.unwrap_or(6)),
+        _ => {
+            let precision = match spec.precision.unwrap_or(6) {
+                0 => 1,
+                p => p,
+            };
+            let (digits, exponent) = round_significant(magnitude, precision);
+            let rounded_exp = if digits.iter().all(|d| *d == 0) {
+                0
+            } else {
+                exponent
+            };
+            if magnitude != 0.0 && (rounded_exp < -4 || rounded_exp >= precision as i32) {
+                scientific_notation(magnitude, precision - 1)
+            } else {
+                let fraction = (precision as i32 - rounded_exp - 1).max(0) as usize;
+                fixed_notation(magnitude, fraction, flags.group)
+            }
+        }
+    };
+    let suffix = if flags.paren && negative { ")" } else { "" };
+    pad_number(spec, &sign_prefix(negative, &flags), &body, suffix)
+}
+
+/// Decimal digits of `value` (shortest repr) with the exponent of the
+/// first digit.
+fn decimal_digits(value: f64) -> (Vec<u8>, i32) {
+    if value == 0.0 {
+        return (vec![0], 0);
+    }
+    let (_, digits, exponent) = shortest_digits(&format!("{:e}", value));
+    (digits.bytes().map(|b| b - b'0').collect(), exponent)
+}
+
+/// Rounds `digits` HALF_UP so that `keep` digits remain, returning whether
+/// the carry produced an extra leading digit.
+fn round_half_up(digits: &mut Vec<u8>, keep: usize) -> bool {
+    if digits.len() <= keep {
+        digits.resize(keep, 0);
+        return false;
+    }
+    let round_up = digits[keep] >= 5;
+    digits.truncate(keep);
+    if !round_up {
+        return false;
+    }
+    for d in digits.iter_mut().rev() {
+        if *d == 9 {
+            *d = 0;
+        } else {
+            *d += 1;
+            return false;
+        }
+    }
+    digits.insert(0, 1);
+    true
+}
+
+fn round_significant(value: f64, precision: usize) -> (Vec<u8>, i32) {
+    let (mut digits, mut exponent) = decimal_digits(value);
+    if round_half_up(&mut digits, precision) {
+        digits.truncate(precision);
+        exponent += 1;
+    }
+    (digits, exponent)
+}
+
+fn fixed_notation(value: f64, precision: usize, group: bool) -> String {
+    let (digits, exponent) = decimal_digits(value);
+    // Line the digits up so that index 0 is the units digit.
+    let mut aligned: Vec<u8> = Vec::new();
+    let int_len;
+    if exponent >= 0 {
+        aligned.extend_from_slice(&digits);
+        int_len = exponent as usize + 1;
+        if aligned.len() < int_len {
+            aligned.resize(int_len, 0);
+        }
+    } else {
+        aligned.push(0);
+        aligned.extend(std::iter::repeat_n(0, (-exponent - 1) as usize));
+        aligned.extend_from_slice(&digits);
+        int_len = 1;
+    }
+    let mut int_len = int_len;
+    if round_half_up(&mut aligned, int_len + precision) {
+        int_len += 1;
+    }
+
+    let int_part: String = aligned[..int_len]
+        .iter()
+        .map(|d| (b'0' + d) as char)
+        .collect();
+    let int_part = if group {
+        group_thousands(&int_part)
+    } else {
+        int_part
+    };
+    if precision == 0 {
+        return int_part;
+    }
+    let frac_part: String = aligned[int_len..]
+        .iter()
+        .map(|d| (b'0' + d) as char)
+        .collect();
+    format!("{}.{}", int_part, frac_part)
+}
+
+fn scientific_notation(value: f64, precision: usize) -> String {
+    let (digits, exponent) = if value == 0.0 {
+        (vec![0; precision + 1], 0)
+    } else {
+        round_significant(value, precision + 1)
+    };
+    let mut out = String::new();
+    out.push((b'0' + digits[0]) as char);
+    if precision > 0 {
+        out.push('.');
+        for d in &digits[1..] {
+            out.push((b'0' + d) as char);
+        }
+    }
+    out.push('e');
+    out.push(if exponent < 0 { '-' } else { '+' });
+    out.push_str(&format!("{:02}", exponent.abs()));
+    out
+}
+
+/// `Double.toHexString` for a non-negative finite value.
+fn hex_float(value: f64) -> String {
+    if value == 0.0 {
+        return "0x0.0p0".to_string();
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7FF) as i32;
+    let significand = bits & ((1u64 << 52) - 1);
+    let mut hex = format!("{:013x}", significand);
+    while hex.len() > 1 && hex.ends_with('0') {
+        hex.pop();
+    }
+    if exponent == 0 {
+        format!("0x0.{}p-1022", hex)
+    } else {
+        format!("0x1.{}p{}", hex, exponent - 1023)
+    }
+}
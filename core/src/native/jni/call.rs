@@ -0,0 +1,245 @@
+//! Calls from the VM into JNI functions whose signature is only known at
+//! run time.
+//!
+//! Arguments are classified the way the C calling convention does it:
+//! integers and pointers go to the integer argument registers, `float` and
+//! `double` to the vector registers, and whatever does not fit is passed in
+//! 8-byte stack slots in argument order. The target is then called through
+//! a fixed signature with enough integer, vector and stack parameters to
+//! cover every classification, so unused slots are simply ignored.
+
+use super::{jobject, new_local, resolve, signature_kinds, with_env, JNIEnv};
+use crate::native::NativeEnv;
+use crate::runtime::heap::HeapValue;
+use std::ffi::c_void;
+
+/// Integer registers used for arguments: rdi..r9 on x86-64, x0..x7 on
+/// AArch64.
+#[cfg(target_arch = "x86_64")]
+const INT_REGISTERS: usize = 6;
+#[cfg(not(target_arch = "x86_64"))]
+const INT_REGISTERS: usize = 8;
+
+/// Vector registers used for arguments: xmm0..xmm7 or v0..v7.
+const FLOAT_REGISTERS: usize = 8;
+
+/// Integer parameters of the fixed call signature. Those beyond
+/// `INT_REGISTERS` are the first stack slots.
+const WORDS: usize = 32;
+
+/// One native argument after conversion from the Java value.
+#[derive(Clone, Copy)]
+pub enum Argument {
+    Word(u64),
+    Float(f32),
+    Double(f64),
+}
+
+/// The raw return register contents.
+#[derive(Clone, Copy)]
+pub enum Returned {
+    Word(u64),
+    Float(f32),
+    Double(f64),
+}
+
+/// Calls `function` with `arguments`. `return_kind` is a descriptor kind
+/// from `signature_kinds`. `None` if the arguments need more stack slots
+/// than the fixed signature provides.
+///
+/// # Safety
+/// `function` must be a C function whose parameters match `arguments` and
+/// whose return type matches `return_kind`.
+#[cfg(any(
+    all(target_arch = "x86_64", unix),
+    all(target_arch = "aarch64", target_os = "linux")
+))]
+pub unsafe fn call(
+    function: *const c_void,
+    arguments: &[Argument],
+    return_kind: u8,
+) -> Option<Returned> {
+    let mut registers = Vec::with_capacity(INT_REGISTERS);
+    let mut floats = [0f64; FLOAT_REGISTERS];
+    let mut float_count = 0;
+    let mut stack = Vec::new();
+    for argument in arguments {
+        match *argument {
+            Argument::Word(word) if registers.len() < INT_REGISTERS => registers.push(word),
+            Argument::Word(word) => stack.push(word),
+            // The callee reads a float from the low half of the register.
+            Argument::Float(value) if float_count < FLOAT_REGISTERS => {
+                floats[float_count] = f64::from_bits(u64::from(value.to_bits()));
+                float_count += 1;
+            }
+            Argument::Float(value) => stack.push(u64::from(value.to_bits())),
+            Argument::Double(value) if float_count < FLOAT_REGISTERS => {
+                floats[float_count] = value;
+                float_count += 1;
+            }
+            Argument::Double(value) => stack.push(value.to_bits()),
+        }
+    }
+    registers.resize(INT_REGISTERS, 0);
+    registers.extend(stack);
+    if registers.len() > WORDS {
+        return None;
+    }
+    registers.resize(WORDS, 0);
+    let w = &registers;
+    let f = &floats;
+
+    macro_rules! invoke {
+        ($ret:ty) => {{
+            let target: unsafe extern "C" fn(
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                u64,
+                f64,
+                f64,
+                f64,
+                f64,
+                f64,
+                f64,
+                f64,
+                f64,
+            ) -> $ret = std::mem::transmute(function);
+            target(
+                w[0], w[1], w[2], w[3], w[4], w[5], w[6], w[7], w[8], w[9], w[10], w[11], w[12],
+                w[13], w[14], w[15], w[16], w[17], w[18], w[19], w[20], w[21], w[22], w[23], w[24],
+                w[25], w[26], w[27], w[28], w[29], w[30], w[31], f[0], f[1], f[2], f[3], f[4],
+                f[5], f[6], f[7],
+            )
+        }};
+    }
+
+    Some(match return_kind {
+        b'F' => Returned::Float(invoke!(f32)),
+        b'D' => Returned::Double(invoke!(f64)),
+        _ => Returned::Word(invoke!(u64)),
+    })
+}
+
+/// Native calls need a calling convention this module knows.
+#[cfg(not(any(
+    all(target_arch = "x86_64", unix),
+    all(target_arch = "aarch64", target_os = "linux")
+)))]
+pub unsafe fn call(
+    _function: *const c_void,
+    _arguments: &[Argument],
+    _return_kind: u8,
+) -> Option<Returned> {
+    None
+}
+
+/// Runs the JNI implementation `function` of a `native` method declared by
+/// `class_name` with the given descriptor. Static methods receive their
+/// class as the second argument, instance methods `this`.
+///
+/// # Safety
+/// `function` must implement a method with `descriptor` as its JNI
+/// signature.
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn invoke(
+    env: &mut NativeEnv,
+    function: *const c_void,
+    class_name: &str,
+    descriptor: &str,
+    is_static: bool,
+    receiver: Option<&HeapValue>,
+    args: &[HeapValue],
+) -> Option<HeapValue> {
+    let (params, return_kind) = signature_kinds(descriptor);
+    let this = if is_static {
+        env.interpreter.class_mirror(env.heap, class_name)
+    } else {
+        receiver.cloned().unwrap_or(HeapValue::Null)
+    };
+    let returned = with_env(env, |jni_env| {
+        // SAFETY: `jni_env` is live for the duration of this closure.
+        unsafe {
+            let mut arguments = vec![
+                Argument::Word(jni_env as u64),
+                Argument::Word(new_local(jni_env, this) as u64),
+            ];
+            for (kind, value) in params.iter().zip(args) {
+                arguments.push(to_argument(jni_env, *kind, value));
+            }
+            let returned = call(function, &arguments, return_kind)?;
+            Some(from_returned(jni_env, return_kind, returned))
+        }
+    });
+    match returned {
+        Some(value) => value,
+        None => {
+            let message = format!("too many arguments for native method {}", descriptor);
+            env.interpreter
+                .throw_new(env.heap, "java/lang/InternalError", Some(&message));
+            None
+        }
+    }
+}
+
+/// Narrow integer types are sign- or zero-extended as C callers do.
+unsafe fn to_argument(env: *mut JNIEnv, kind: u8, value: &HeapValue) -> Argument {
+    match kind {
+        b'Z' => Argument::Word(u64::from(value.as_int() != 0)),
+        b'B' => Argument::Word(value.as_int() as i8 as i64 as u64),
+        b'C' => Argument::Word(u64::from(value.as_int() as u16)),
+        b'S' => Argument::Word(value.as_int() as i16 as i64 as u64),
+        b'J' => Argument::Word(value.as_long() as u64),
+        b'F' => Argument::Float(value.as_float()),
+        b'D' => Argument::Double(value.as_double()),
+        b'L' => Argument::Word(new_local(env, value.clone()) as u64),
+        _ => Argument::Word(value.as_int() as i64 as u64),
+    }
+}
+
+/// Only the low bits of the return register belong to a narrow result.
+unsafe fn from_returned(env: *mut JNIEnv, kind: u8, returned: Returned) -> Option<HeapValue> {
+    let word = match returned {
+        Returned::Float(value) => return Some(HeapValue::Float(value)),
+        Returned::Double(value) => return Some(HeapValue::Double(value)),
+        Returned::Word(word) => word,
+    };
+    Some(match kind {
+        b'V' => return None,
+        b'Z' => HeapValue::Int(i32::from(word as u8 != 0)),
+        b'B' => HeapValue::Int(i32::from(word as i8)),
+        b'C' => HeapValue::Int(i32::from(word as u16)),
+        b'S' => HeapValue::Int(i32::from(word as i16)),
+        b'J' => HeapValue::Long(word as i64),
+        b'L' => resolve(env, word as usize as jobject),
+        _ => HeapValue::Int(word as i32),
+    })
+}
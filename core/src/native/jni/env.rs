@@ -0,0 +1,1689 @@
+//! The `JNIEnv` function table.
+//!
+//! Every slot of `struct JNINativeInterface_` is present; functions the VM
+//! does not provide abort with a fatal error naming the function. The
+//! variadic and `va_list` forms come from `varargs.c` and forward to the
+//! `...A` forms implemented here.
+
+#![allow(non_snake_case)]
+
+use super::invocation::{java_vm, JavaVM};
+use super::{
+    delete_global, delete_local, field_id, jboolean, jbyte, jchar, jclass, jdouble, jfieldID,
+    jfloat, jint, jlong, jmethodID, jobject, jshort, jsize, library, method_id, native, new_global,
+    new_local, pop_local_frame, push_local_frame, ref_type, resolve, resolve_class,
+    signature_kinds, JNIEnv, JValue, JNI_ABORT, JNI_COMMIT, JNI_ERR, JNI_FALSE, JNI_OK, JNI_TRUE,
+    JNI_VERSION_17,
+};
+use crate::exec::interpreter::Interpreter;
+use crate::native::java_lang_object::{array_class_name, same_reference};
+use crate::native::registry;
+use crate::native::{is_builtin_class, java_io_printstream, java_lang_throwable};
+use crate::runtime::heap::{ArrayType, HeapValue};
+use std::ffi::{c_char, c_void, CStr};
+use std::sync::OnceLock;
+
+const ACC_STATIC: u16 = 0x0008;
+const ACC_NATIVE: u16 = 0x0100;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+
+macro_rules! function_table {
+    ($($name:ident),* $(,)?) => {
+        /// `struct JNINativeInterface_` from `include/jni.h`, slot for slot.
+        #[repr(C)]
+        pub struct JNINativeInterface {
+            $(pub $name: *const c_void,)*
+        }
+
+        /// One stub per slot, so an unsupported call names itself.
+        mod unsupported {
+            $(
+                pub(super) extern "C" fn $name() {
+                    super::unsupported_function(stringify!($name));
+                }
+            )*
+        }
+
+        impl JNINativeInterface {
+            fn unsupported() -> Self {
+                Self {
+                    $($name: unsupported::$name as *const c_void,)*
+                }
+            }
+        }
+    };
+}
+
+function_table! {
+    reserved0, reserved1, reserved2, reserved3, GetVersion, DefineClass, FindClass,
+    FromReflectedMethod, FromReflectedField, ToReflectedMethod, GetSuperclass,
+    IsAssignableFrom, ToReflectedField, Throw, ThrowNew, ExceptionOccurred, ExceptionDescribe,
+    ExceptionClear, FatalError, PushLocalFrame, PopLocalFrame, NewGlobalRef, DeleteGlobalRef,
+    DeleteLocalRef, IsSameObject, NewLocalRef, EnsureLocalCapacity, AllocObject, NewObject,
+    NewObjectV, NewObjectA, GetObjectClass, IsInstanceOf, GetMethodID, CallObjectMethod,
+    CallObjectMethodV, CallObjectMethodA, CallBooleanMethod, CallBooleanMethodV,
+    CallBooleanMethodA, CallByteMethod, CallByteMethodV, CallByteMethodA, CallCharMethod,
+    CallCharMethodV, CallCharMethodA, CallShortMethod, CallShortMethodV, CallShortMethodA,
+    CallIntMethod, CallIntMethodV, CallIntMethodA, CallLongMethod, CallLongMethodV,
+    CallLongMethodA, CallFloatMethod, CallFloatMethodV, CallFloatMethodA, CallDoubleMethod,
+    CallDoubleMethodV, CallDoubleMethodA, CallVoidMethod, CallVoidMethodV, CallVoidMethodA,
+    CallNonvirtualObjectMethod, CallNonvirtualObjectMethodV, CallNonvirtualObjectMethodA,
+    CallNonvirtualBooleanMethod, CallNonvirtualBooleanMethodV, CallNonvirtualBooleanMethodA,
+    CallNonvirtualByteMethod, CallNonvirtualByteMethodV, CallNonvirtualByteMethodA,
+    CallNonvirtualCharMethod, CallNonvirtualCharMethodV, CallNonvirtualCharMethodA,
+    CallNonvirtualShortMethod, CallNonvirtualShortMethodV, CallNonvirtualShortMethodA,
+    CallNonvirtualIntMethod, CallNonvirtualIntMethodV, CallNonvirtualIntMethodA,
+    CallNonvirtualLongMethod, CallNonvirtualLongMethodV, CallNonvirtualLongMethodA,
+    CallNonvirtualFloatMethod, CallNonvirtualFloatMethodV, CallNonvirtualFloatMethodA,
+    CallNonvirtualDoubleMethod, CallNonvirtualDoubleMethodV, CallNonvirtualDoubleMethodA,
+    CallNonvirtualVoidMethod, CallNonvirtualVoidMethodV, CallNonvirtualVoidMethodA, GetFieldID,
+    GetObjectField, GetBooleanField, GetByteField, GetCharField, GetShortField, GetIntField,
+    GetLongField, GetFloatField, GetDoubleField, SetObjectField, SetBooleanField, SetByteField,
+    SetCharField, SetShortField, SetIntField, SetLongField, SetFloatField, SetDoubleField,
+    GetStaticMethodID, CallStaticObjectMethod, CallStaticObjectMethodV,
+    CallStaticObjectMethodA, CallStaticBooleanMethod, CallStaticBooleanMethodV,
+    CallStaticBooleanMethodA, CallStaticByteMethod, CallStaticByteMethodV,
+    CallStaticByteMethodA, CallStaticCharMethod, CallStaticCharMethodV, CallStaticCharMethodA,
+    CallStaticShortMethod, CallStaticShortMethodV, CallStaticShortMethodA, CallStaticIntMethod,
+    CallStaticIntMethodV, CallStaticIntMethodA, CallStaticLongMethod, CallStaticLongMethodV,
+    CallStaticLongMethodA, CallStaticFloatMethod, CallStaticFloatMethodV,
+    CallStaticFloatMethodA, CallStaticDoubleMethod, CallStaticDoubleMethodV,
+    CallStaticDoubleMethodA, CallStaticVoidMethod, CallStaticVoidMethodV,
+    CallStaticVoidMethodA, GetStaticFieldID, GetStaticObjectField, GetStaticBooleanField,
+    GetStaticByteField, GetStaticCharField, GetStaticShortField, GetStaticIntField,
+    GetStaticLongField, GetStaticFloatField, GetStaticDoubleField, SetStaticObjectField,
+    SetStaticBooleanField, SetStaticByteField, SetStaticCharField, SetStaticShortField,
+    SetStaticIntField, SetStaticLongField, SetStaticFloatField, SetStaticDoubleField,
+    NewString, GetStringLength, GetStringChars, ReleaseStringChars, NewStringUTF,
+    GetStringUTFLength, GetStringUTFChars, ReleaseStringUTFChars, GetArrayLength,
+    NewObjectArray, GetObjectArrayElement, SetObjectArrayElement, NewBooleanArray,
+    NewByteArray, NewCharArray, NewShortArray, NewIntArray, NewLongArray, NewFloatArray,
+    NewDoubleArray, GetBooleanArrayElements, GetByteArrayElements, GetCharArrayElements,
+    GetShortArrayElements, GetIntArrayElements, GetLongArrayElements, GetFloatArrayElements,
+    GetDoubleArrayElements, ReleaseBooleanArrayElements, ReleaseByteArrayElements,
+    ReleaseCharArrayElements, ReleaseShortArrayElements, ReleaseIntArrayElements,
+    ReleaseLongArrayElements, ReleaseFloatArrayElements, ReleaseDoubleArrayElements,
+    GetBooleanArrayRegion, GetByteArrayRegion, GetCharArrayRegion, GetShortArrayRegion,
+    GetIntArrayRegion, GetLongArrayRegion, GetFloatArrayRegion, GetDoubleArrayRegion,
+    SetBooleanArrayRegion, SetByteArrayRegion, SetCharArrayRegion, SetShortArrayRegion,
+    SetIntArrayRegion, SetLongArrayRegion, SetFloatArrayRegion, SetDoubleArrayRegion,
+    RegisterNatives, UnregisterNatives, MonitorEnter, MonitorExit, GetJavaVM, GetStringRegion,
+    GetStringUTFRegion, GetPrimitiveArrayCritical, ReleasePrimitiveArrayCritical,
+    GetStringCritical, ReleaseStringCritical, NewWeakGlobalRef, DeleteWeakGlobalRef,
+    ExceptionCheck, NewDirectByteBuffer, GetDirectBufferAddress, GetDirectBufferCapacity,
+    GetObjectRefType, GetModule,
+}
+
+fn unsupported_function(name: &str) -> ! {
+    java_io_printstream::flush_all();
+    eprintln!(
+        "FATAL ERROR in native method: JNI function {} is not supported",
+        name
+    );
+    std::process::abort()
+}
+
+extern "C" {
+    fn aria_jni_install_varargs(table: *mut JNINativeInterface);
+}
+
+struct FunctionTable(JNINativeInterface);
+
+// SAFETY: the table is immutable once built and only holds code addresses.
+unsafe impl Send for FunctionTable {}
+unsafe impl Sync for FunctionTable {}
+
+static FUNCTIONS: OnceLock<FunctionTable> = OnceLock::new();
+
+macro_rules! install {
+    ($table:ident; $($name:ident),* $(,)?) => {
+        $($table.$name = $name as *const c_void;)*
+    };
+}
+
+pub fn function_table() -> *const JNINativeInterface {
+    &FUNCTIONS
+        .get_or_init(|| {
+            let mut table = JNINativeInterface::unsupported();
+            table.reserved0 = std::ptr::null();
+            table.reserved1 = std::ptr::null();
+            table.reserved2 = std::ptr::null();
+            table.reserved3 = std::ptr::null();
+            install!(table;
+                GetVersion, FindClass, GetSuperclass, IsAssignableFrom,
+                Throw, ThrowNew, ExceptionOccurred, ExceptionDescribe, ExceptionClear, FatalError,
+                PushLocalFrame, PopLocalFrame, NewGlobalRef, DeleteGlobalRef, DeleteLocalRef,
+                IsSameObject, NewLocalRef, EnsureLocalCapacity,
+                AllocObject, NewObjectA, GetObjectClass, IsInstanceOf,
+                GetMethodID, GetStaticMethodID, GetFieldID, GetStaticFieldID,
+                CallObjectMethodA, CallBooleanMethodA, CallByteMethodA, CallCharMethodA,
+                CallShortMethodA, CallIntMethodA, CallLongMethodA, CallFloatMethodA,
+                CallDoubleMethodA, CallVoidMethodA,
+                CallNonvirtualObjectMethodA, CallNonvirtualBooleanMethodA,
+                CallNonvirtualByteMethodA, CallNonvirtualCharMethodA,
+                CallNonvirtualShortMethodA, CallNonvirtualIntMethodA,
+                CallNonvirtualLongMethodA, CallNonvirtualFloatMethodA,
+                CallNonvirtualDoubleMethodA, CallNonvirtualVoidMethodA,
+                CallStaticObjectMethodA, CallStaticBooleanMethodA, CallStaticByteMethodA,
+                CallStaticCharMethodA, CallStaticShortMethodA, CallStaticIntMethodA,
+                CallStaticLongMethodA, CallStaticFloatMethodA, CallStaticDoubleMethodA,
+                CallStaticVoidMethodA,
+                GetObjectField, GetBooleanField, GetByteField, GetCharField, GetShortField,
+                GetIntField, GetLongField, GetFloatField, GetDoubleField,
+                SetObjectField, SetBooleanField, SetByteField, SetCharField, SetShortField,
+                SetIntField, SetLongField, SetFloatField, SetDoubleField,
+                GetStaticObjectField, GetStaticBooleanField, GetStaticByteField,
+                GetStaticCharField, GetStaticShortField, GetStaticIntField, GetStaticLongField,
+                GetStaticFloatField, GetStaticDoubleField,
+                SetStaticObjectField, SetStaticBooleanField, SetStaticByteField,
+                SetStaticCharField, SetStaticShortField, SetStaticIntField, SetStaticLongField,
+                SetStaticFloatField, SetStaticDoubleField,
+                NewString, GetStringLength, GetStringChars, ReleaseStringChars,
+                NewStringUTF, GetStringUTFLength, GetStringUTFChars, ReleaseStringUTFChars,
+                GetStringRegion, GetStringUTFRegion, GetStringCritical, ReleaseStringCritical,
+                GetArrayLength, NewObjectArray, GetObjectArrayElement, SetObjectArrayElement,
+                NewBooleanArray, NewByteArray, NewCharArray, NewShortArray,
+                NewIntArray, NewLongArray, NewFloatArray, NewDoubleArray,
+                GetBooleanArrayElements, GetByteArrayElements, GetCharArrayElements,
+                GetShortArrayElements, GetIntArrayElements, GetLongArrayElements,
+                GetFloatArrayElements, GetDoubleArrayElements,
+                ReleaseBooleanArrayElements, ReleaseByteArrayElements,
+                ReleaseCharArrayElements, ReleaseShortArrayElements, ReleaseIntArrayElements,
+                ReleaseLongArrayElements, ReleaseFloatArrayElements, ReleaseDoubleArrayElements,
+                GetBooleanArrayRegion, GetByteArrayRegion, GetCharArrayRegion,
+                GetShortArrayRegion, GetIntArrayRegion, GetLongArrayRegion,
+                GetFloatArrayRegion, GetDoubleArrayRegion,
+                SetBooleanArrayRegion, SetByteArrayRegion, SetCharArrayRegion,
+                SetShortArrayRegion, SetIntArrayRegion, SetLongArrayRegion,
+                SetFloatArrayRegion, SetDoubleArrayRegion,
+                GetPrimitiveArrayCritical, ReleasePrimitiveArrayCritical,
+                RegisterNatives, UnregisterNatives, MonitorEnter, MonitorExit, GetJavaVM,
+                NewWeakGlobalRef, DeleteWeakGlobalRef, ExceptionCheck, GetObjectRefType,
+            );
+            // SAFETY: the C side only assigns slots of the same layout.
+            unsafe { aria_jni_install_varargs(&mut table) };
+            FunctionTable(table)
+        })
+        .0
+}
+
+// ---- helpers ---------------------------------------------------------------
+
+unsafe fn throw(env: *mut JNIEnv, class_name: &str, message: Option<&str>) {
+    let native = native(env);
+    native
+        .interpreter
+        .throw_new(native.heap, class_name, message);
+}
+
+fn has_pending_exception(env: *mut JNIEnv) -> bool {
+    // SAFETY: callers pass the live `env` they were given.
+    unsafe { native(env).interpreter.pending_exception().is_some() }
+}
+
+/// A NUL-terminated modified UTF-8 argument.
+unsafe fn utf_argument(chars: *const c_char) -> Option<String> {
+    (!chars.is_null()).then(|| decode_modified_utf8(CStr::from_ptr(chars).to_bytes()))
+}
+
+/// Java's modified UTF-8: NUL as two bytes and supplementary characters as
+/// two encoded surrogates.
+fn encode_modified_utf8(units: &[u16]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(units.len());
+    for &unit in units {
+        match unit {
+            0x0001..=0x007f => out.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                out.push(0xc0 | (unit >> 6) as u8);
+                out.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                out.push(0xe0 | (unit >> 12) as u8);
+                out.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                out.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    out
+}
+
+/// Decodes modified UTF-8, also accepting the standard four-byte form.
+fn decode_modified_utf8(bytes: &[u8]) -> String {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    let continuation = |i: usize| u32::from(bytes.get(i).copied().unwrap_or(0x80) & 0x3f);
+    while i < bytes.len() {
+        let lead = bytes[i];
+        let (code, width) = match lead {
+            0x00..=0x7f => (u32::from(lead), 1),
+            0xc0..=0xdf => ((u32::from(lead & 0x1f) << 6) | continuation(i + 1), 2),
+            0xe0..=0xef => (
+                (u32::from(lead & 0x0f) << 12) | (continuation(i + 1) << 6) | continuation(i + 2),
+                3,
+            ),
+            0xf0..=0xf7 => (
+                (u32::from(lead & 0x07) << 18)
+                    | (continuation(i + 1) << 12)
+                    | (continuation(i + 2) << 6)
+                    | continuation(i + 3),
+                4,
+            ),
+            _ => (0xfffd, 1),
+        };
+        match char::from_u32(code) {
+            Some(c) if code > 0xffff => {
+                let mut pair = [0u16; 2];
+                units.extend_from_slice(c.encode_utf16(&mut pair));
+            }
+            _ => units.push(code as u16),
+        }
+        i += width;
+    }
+    String::from_utf16_lossy(&units)
+}
+
+unsafe fn string_units(env: *mut JNIEnv, string: jobject) -> Option<Vec<u16>> {
+    let value = resolve(env, string);
+    let text = native(env).heap.string_value(&value)?;
+    Some(text.encode_utf16().collect())
+}
+
+unsafe fn new_string(env: *mut JNIEnv, text: &str) -> jobject {
+    let value = native(env).heap.alloc_string(text);
+    new_local(env, value)
+}
+
+/// A `malloc`ed copy of `items`, released with `libc::free`.
+unsafe fn malloc_copy<T: Copy>(items: &[T], terminator: Option<T>) -> *mut T {
+    let count = items.len() + usize::from(terminator.is_some());
+    let buffer = libc::malloc(count.max(1) * std::mem::size_of::<T>()) as *mut T;
+    if buffer.is_null() {
+        return buffer;
+    }
+    std::ptr::copy_nonoverlapping(items.as_ptr(), buffer, items.len());
+    if let Some(terminator) = terminator {
+        buffer.add(items.len()).write(terminator);
+    }
+    buffer
+}
+
+unsafe fn set_is_copy(is_copy: *mut jboolean) {
+    if !is_copy.is_null() {
+        *is_copy = JNI_TRUE;
+    }
+}
+
+/// The class of a reference, as `GetObjectClass` reports it.
+fn class_of(value: &HeapValue) -> Option<String> {
+    match value {
+        HeapValue::Object(obj) => Some(obj.class_name.clone()),
+        HeapValue::Array(arr) => Some(array_class_name(arr)),
+        HeapValue::String(_) => Some("java/lang/String".to_string()),
+        _ => None,
+    }
+}
+
+fn class_access_flags(env: *mut JNIEnv, class_name: &str) -> u16 {
+    // SAFETY: callers pass the live `env` they were given.
+    let native = unsafe { native(env) };
+    if class_name.starts_with('[') || is_builtin_class(class_name) {
+        return 0;
+    }
+    native
+        .loader
+        .load_class(class_name)
+        .map_or(0, |class| class.access_flags)
+}
+
+/// Converts a `jvalue` argument array using the method's descriptor.
+unsafe fn arguments(env: *mut JNIEnv, descriptor: &str, args: *const JValue) -> Vec<HeapValue> {
+    let (kinds, _) = signature_kinds(descriptor);
+    kinds
+        .iter()
+        .enumerate()
+        .map(|(index, kind)| {
+            if args.is_null() {
+                return Interpreter::default_value_for_descriptor(
+                    std::str::from_utf8(&[*kind]).unwrap_or("L"),
+                );
+            }
+            let arg = *args.add(index);
+            match kind {
+                b'Z' => HeapValue::Int(i32::from(arg.z != 0)),
+                b'B' => HeapValue::Int(i32::from(arg.b)),
+                b'C' => HeapValue::Int(i32::from(arg.c)),
+                b'S' => HeapValue::Int(i32::from(arg.s)),
+                b'J' => HeapValue::Long(arg.j),
+                b'F' => HeapValue::Float(arg.f),
+                b'D' => HeapValue::Double(arg.d),
+                b'L' => resolve(env, arg.l),
+                _ => HeapValue::Int(arg.i),
+            }
+        })
+        .collect()
+}
+
+/// Runs a method for the `Call*MethodA` family. `class_name` selects a
+/// nonvirtual or static call; `None` dispatches on the receiver. Yields
+/// `null` when the call throws.
+unsafe fn call_method(
+    env: *mut JNIEnv,
+    receiver: Option<HeapValue>,
+    class_name: Option<String>,
+    method: jmethodID,
+    args: *const JValue,
+) -> HeapValue {
+    let Some(method) = method.as_ref() else {
+        throw(env, "java/lang/NullPointerException", None);
+        return HeapValue::Null;
+    };
+    let values = arguments(env, &method.descriptor, args);
+    if receiver.as_ref().is_some_and(HeapValue::is_null) {
+        throw(env, "java/lang/NullPointerException", None);
+        return HeapValue::Null;
+    }
+    let native = native(env);
+    let result = match (class_name, receiver) {
+        (None, Some(receiver)) => {
+            native.invoke_virtual(&receiver, &method.name, &method.descriptor, &values)
+        }
+        (class_name, receiver) => native.interpreter.invoke_nonvirtual(
+            native.loader,
+            native.heap,
+            class_name.as_deref().unwrap_or(&method.class_name),
+            &method.name,
+            &method.descriptor,
+            receiver,
+            &values,
+        ),
+    };
+    if has_pending_exception(env) {
+        return HeapValue::Null;
+    }
+    result.unwrap_or(HeapValue::Null)
+}
+
+unsafe fn call_virtual(
+    env: *mut JNIEnv,
+    obj: jobject,
+    method: jmethodID,
+    args: *const JValue,
+) -> HeapValue {
+    call_method(env, Some(resolve(env, obj)), None, method, args)
+}
+
+unsafe fn call_nonvirtual(
+    env: *mut JNIEnv,
+    obj: jobject,
+    clazz: jclass,
+    method: jmethodID,
+    args: *const JValue,
+) -> HeapValue {
+    call_method(
+        env,
+        Some(resolve(env, obj)),
+        resolve_class(env, clazz),
+        method,
+        args,
+    )
+}
+
+unsafe fn call_static(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    method: jmethodID,
+    args: *const JValue,
+) -> HeapValue {
+    call_method(env, None, resolve_class(env, clazz), method, args)
+}
+
+/// A primitive JNI type and its heap representation.
+trait Primitive: Copy + Default {
+    const ARRAY_TYPE: ArrayType;
+    fn from_heap(value: &HeapValue) -> Self;
+    fn to_heap(self) -> HeapValue;
+}
+
+fn int_of(value: &HeapValue) -> i32 {
+    match value {
+        HeapValue::Int(v) => *v,
+        HeapValue::Long(v) => *v as i32,
+        _ => 0,
+    }
+}
+
+macro_rules! int_primitive {
+    ($($ty:ty => $array_type:ident;)*) => {$(
+        impl Primitive for $ty {
+            const ARRAY_TYPE: ArrayType = ArrayType::$array_type;
+            fn from_heap(value: &HeapValue) -> Self {
+                int_of(value) as $ty
+            }
+            fn to_heap(self) -> HeapValue {
+                HeapValue::Int(self as i32)
+            }
+        }
+    )*};
+}
+
+int_primitive! {
+    jbyte => Byte;
+    jchar => Char;
+    jshort => Short;
+    jint => Int;
+}
+
+impl Primitive for jboolean {
+    const ARRAY_TYPE: ArrayType = ArrayType::Boolean;
+    fn from_heap(value: &HeapValue) -> Self {
+        jboolean::from(int_of(value) != 0)
+    }
+    fn to_heap(self) -> HeapValue {
+        HeapValue::Int(i32::from(self != 0))
+    }
+}
+
+impl Primitive for jlong {
+    const ARRAY_TYPE: ArrayType = ArrayType::Long;
+    fn from_heap(value: &HeapValue) -> Self {
+        match value {
+            HeapValue::Long(v) => *v,
+            HeapValue::Int(v) => i64::from(*v),
+            _ => 0,
+        }
+    }
+    fn to_heap(self) -> HeapValue {
+        HeapValue::Long(self)
+    }
+}
+
+impl Primitive for jfloat {
+    const ARRAY_TYPE: ArrayType = ArrayType::Float;
+    fn from_heap(value: &HeapValue) -> Self {
+        match value {
+            HeapValue::Float(v) => *v,
+            HeapValue::Double(v) => *v as f32,
+            _ => 0.0,
+        }
+    }
+    fn to_heap(self) -> HeapValue {
+        HeapValue::Float(self)
+    }
+}
+
+impl Primitive for jdouble {
+    const ARRAY_TYPE: ArrayType = ArrayType::Double;
+    fn from_heap(value: &HeapValue) -> Self {
+        match value {
+            HeapValue::Double(v) => *v,
+            HeapValue::Float(v) => f64::from(*v),
+            _ => 0.0,
+        }
+    }
+    fn to_heap(self) -> HeapValue {
+        HeapValue::Double(self)
+    }
+}
+
+// ---- version and classes -----------------------------------------------------
+
+unsafe extern "C" fn GetVersion(_env: *mut JNIEnv) -> jint {
+    JNI_VERSION_17
+}
+
+unsafe extern "C" fn FindClass(env: *mut JNIEnv, name: *const c_char) -> jclass {
+    let Some(name) = utf_argument(name) else {
+        throw(env, "java/lang/NoClassDefFoundError", None);
+        return std::ptr::null_mut();
+    };
+    let native = native(env);
+    let found =
+        name.starts_with('[') || is_builtin_class(&name) || native.loader.load_class(&name).is_ok();
+    if !found {
+        throw(env, "java/lang/NoClassDefFoundError", Some(&name));
+        return std::ptr::null_mut();
+    }
+    if !name.starts_with('[') {
+        native
+            .interpreter
+            .ensure_class_initialized(native.loader, &name, native.heap);
+        if has_pending_exception(env) {
+            return std::ptr::null_mut();
+        }
+    }
+    new_local(env, native.interpreter.class_mirror(native.heap, &name))
+}
+
+/// `null` for `java/lang/Object` and interfaces.
+unsafe extern "C" fn GetSuperclass(env: *mut JNIEnv, clazz: jclass) -> jclass {
+    let Some(name) = resolve_class(env, clazz) else {
+        return std::ptr::null_mut();
+    };
+    if class_access_flags(env, &name) & ACC_INTERFACE != 0 {
+        return std::ptr::null_mut();
+    }
+    let native = native(env);
+    let parent = if name.starts_with('[') {
+        Some("java/lang/Object".to_string())
+    } else {
+        native.interpreter.superclass_of(native.loader, &name)
+    };
+    match parent {
+        Some(parent) => new_local(env, native.interpreter.class_mirror(native.heap, &parent)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn IsAssignableFrom(env: *mut JNIEnv, sub: jclass, sup: jclass) -> jboolean {
+    let (Some(sub), Some(sup)) = (resolve_class(env, sub), resolve_class(env, sup)) else {
+        return JNI_FALSE;
+    };
+    let native = native(env);
+    jboolean::from(native.interpreter.is_assignable(native.loader, &sub, &sup))
+}
+
+unsafe extern "C" fn GetObjectClass(env: *mut JNIEnv, obj: jobject) -> jclass {
+    let native = native(env);
+    match class_of(&resolve(env, obj)) {
+        Some(name) => new_local(env, native.interpreter.class_mirror(native.heap, &name)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `null` is an instance of every class.
+unsafe extern "C" fn IsInstanceOf(env: *mut JNIEnv, obj: jobject, clazz: jclass) -> jboolean {
+    let value = resolve(env, obj);
+    if value.is_null() {
+        return JNI_TRUE;
+    }
+    let (Some(from), Some(to)) = (class_of(&value), resolve_class(env, clazz)) else {
+        return JNI_FALSE;
+    };
+    let native = native(env);
+    jboolean::from(native.interpreter.is_assignable(native.loader, &from, &to))
+}
+
+// ---- exceptions --------------------------------------------------------------
+
+unsafe extern "C" fn Throw(env: *mut JNIEnv, obj: jobject) -> jint {
+    let exception = resolve(env, obj);
+    if exception.is_null() {
+        return JNI_ERR;
+    }
+    native(env).interpreter.throw(exception);
+    JNI_OK
+}
+
+/// Constructs the throwable through its `(String)` constructor, as
+/// `new clazz(message)` would.
+unsafe extern "C" fn ThrowNew(env: *mut JNIEnv, clazz: jclass, message: *const c_char) -> jint {
+    let Some(class_name) = resolve_class(env, clazz) else {
+        return JNI_ERR;
+    };
+    let message = utf_argument(message);
+    let native = native(env);
+    let message = match message {
+        Some(text) => native.heap.alloc_string(&text),
+        None => HeapValue::Null,
+    };
+    let exception = HeapValue::Object(native.heap.alloc_object(&class_name));
+    native.interpreter.invoke_nonvirtual(
+        native.loader,
+        native.heap,
+        &class_name,
+        "<init>",
+        "(Ljava/lang/String;)V",
+        Some(exception.clone()),
+        &[message],
+    );
+    if has_pending_exception(env) {
+        return JNI_ERR;
+    }
+    native.interpreter.throw(exception);
+    JNI_OK
+}
+
+unsafe extern "C" fn ExceptionOccurred(env: *mut JNIEnv) -> jobject {
+    let pending = native(env).interpreter.pending_exception();
+    new_local(env, pending.unwrap_or(HeapValue::Null))
+}
+
+/// Prints the pending exception and its backtrace to `System.err`, then
+/// clears it.
+unsafe extern "C" fn ExceptionDescribe(env: *mut JNIEnv) {
+    let native = native(env);
+    if let Some(exception) = native.interpreter.take_pending_exception() {
+        let text = java_lang_throwable::describe(native, &exception);
+        java_io_printstream::flush(1);
+        eprintln!("{}", text);
+    }
+}
+
+unsafe extern "C" fn ExceptionClear(env: *mut JNIEnv) {
+    native(env).interpreter.take_pending_exception();
+}
+
+unsafe extern "C" fn ExceptionCheck(env: *mut JNIEnv) -> jboolean {
+    jboolean::from(has_pending_exception(env))
+}
+
+unsafe extern "C" fn FatalError(_env: *mut JNIEnv, message: *const c_char) {
+    java_io_printstream::flush_all();
+    eprintln!(
+        "FATAL ERROR in native method: {}",
+        utf_argument(message).unwrap_or_default()
+    );
+    std::process::abort()
+}
+
+// ---- references --------------------------------------------------------------
+
+unsafe extern "C" fn PushLocalFrame(env: *mut JNIEnv, capacity: jint) -> jint {
+    if capacity < 0 {
+        return JNI_ERR;
+    }
+    push_local_frame(env);
+    JNI_OK
+}
+
+/// Releases the frame and returns `result` as a local of the outer frame.
+unsafe extern "C" fn PopLocalFrame(env: *mut JNIEnv, result: jobject) -> jobject {
+    let value = resolve(env, result);
+    pop_local_frame(env);
+    new_local(env, value)
+}
+
+unsafe extern "C" fn NewGlobalRef(env: *mut JNIEnv, obj: jobject) -> jobject {
+    new_global(resolve(env, obj), false)
+}
+
+unsafe extern "C" fn DeleteGlobalRef(_env: *mut JNIEnv, global: jobject) {
+    delete_global(global, false);
+}
+
+unsafe extern "C" fn NewWeakGlobalRef(env: *mut JNIEnv, obj: jobject) -> jobject {
+    new_global(resolve(env, obj), true)
+}
+
+unsafe extern "C" fn DeleteWeakGlobalRef(_env: *mut JNIEnv, weak: jobject) {
+    delete_global(weak, true);
+}
+
+unsafe extern "C" fn DeleteLocalRef(env: *mut JNIEnv, obj: jobject) {
+    delete_local(env, obj);
+}
+
+unsafe extern "C" fn NewLocalRef(env: *mut JNIEnv, obj: jobject) -> jobject {
+    let value = resolve(env, obj);
+    new_local(env, value)
+}
+
+unsafe extern "C" fn EnsureLocalCapacity(_env: *mut JNIEnv, capacity: jint) -> jint {
+    if capacity < 0 {
+        JNI_ERR
+    } else {
+        JNI_OK
+    }
+}
+
+unsafe extern "C" fn IsSameObject(env: *mut JNIEnv, a: jobject, b: jobject) -> jboolean {
+    jboolean::from(same_reference(&resolve(env, a), &resolve(env, b)))
+}
+
+unsafe extern "C" fn GetObjectRefType(env: *mut JNIEnv, obj: jobject) -> jint {
+    ref_type(env, obj)
+}
+
+// ---- objects -----------------------------------------------------------------
+
+unsafe fn allocate(env: *mut JNIEnv, clazz: jclass) -> Option<(String, HeapValue)> {
+    let class_name = resolve_class(env, clazz)?;
+    if class_name.starts_with('[')
+        || class_access_flags(env, &class_name) & (ACC_INTERFACE | ACC_ABSTRACT) != 0
+    {
+        throw(
+            env,
+            "java/lang/InstantiationException",
+            Some(&class_name.replace('/', ".")),
+        );
+        return None;
+    }
+    let native = native(env);
+    native
+        .interpreter
+        .ensure_class_initialized(native.loader, &class_name, native.heap);
+    if has_pending_exception(env) {
+        return None;
+    }
+    let obj = HeapValue::Object(native.heap.alloc_object(&class_name));
+    Some((class_name, obj))
+}
+
+unsafe extern "C" fn AllocObject(env: *mut JNIEnv, clazz: jclass) -> jobject {
+    match allocate(env, clazz) {
+        Some((_, obj)) => new_local(env, obj),
+        None => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn NewObjectA(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    method: jmethodID,
+    args: *const JValue,
+) -> jobject {
+    let Some((class_name, obj)) = allocate(env, clazz) else {
+        return std::ptr::null_mut();
+    };
+    call_method(env, Some(obj.clone()), Some(class_name), method, args);
+    if has_pending_exception(env) {
+        return std::ptr::null_mut();
+    }
+    new_local(env, obj)
+}
+
+// ---- methods -----------------------------------------------------------------
+
+unsafe fn get_method_id(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    name: *const c_char,
+    signature: *const c_char,
+    is_static: bool,
+) -> jmethodID {
+    let (Some(class_name), Some(name), Some(signature)) = (
+        resolve_class(env, clazz),
+        utf_argument(name),
+        utf_argument(signature),
+    ) else {
+        throw(env, "java/lang/NoSuchMethodError", None);
+        return std::ptr::null_mut();
+    };
+    let native = native(env);
+    if !class_name.starts_with('[') {
+        native
+            .interpreter
+            .ensure_class_initialized(native.loader, &class_name, native.heap);
+        if has_pending_exception(env) {
+            return std::ptr::null_mut();
+        }
+    }
+    let found = native
+        .interpreter
+        .find_method(native.loader, &class_name, &name, &signature)
+        .is_some_and(|(owner, flags)| {
+            is_builtin_class(&owner) || (flags & ACC_STATIC != 0) == is_static
+        });
+    if !found {
+        throw(env, "java/lang/NoSuchMethodError", Some(&name));
+        return std::ptr::null_mut();
+    }
+    method_id(&class_name, &name, &signature, is_static)
+}
+
+unsafe extern "C" fn GetMethodID(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    name: *const c_char,
+    signature: *const c_char,
+) -> jmethodID {
+    get_method_id(env, clazz, name, signature, false)
+}
+
+unsafe extern "C" fn GetStaticMethodID(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    name: *const c_char,
+    signature: *const c_char,
+) -> jmethodID {
+    get_method_id(env, clazz, name, signature, true)
+}
+
+macro_rules! primitive_calls {
+    ($($ty:ty => $call:ident, $nonvirtual:ident, $static_call:ident;)*) => {$(
+        unsafe extern "C" fn $call(
+            env: *mut JNIEnv,
+            obj: jobject,
+            method: jmethodID,
+            args: *const JValue,
+        ) -> $ty {
+            <$ty as Primitive>::from_heap(&call_virtual(env, obj, method, args))
+        }
+
+        unsafe extern "C" fn $nonvirtual(
+            env: *mut JNIEnv,
+            obj: jobject,
+            clazz: jclass,
+            method: jmethodID,
+            args: *const JValue,
+        ) -> $ty {
+            <$ty as Primitive>::from_heap(&call_nonvirtual(env, obj, clazz, method, args))
+        }
+
+        unsafe extern "C" fn $static_call(
+            env: *mut JNIEnv,
+            clazz: jclass,
+            method: jmethodID,
+            args: *const JValue,
+        ) -> $ty {
+            <$ty as Primitive>::from_heap(&call_static(env, clazz, method, args))
+        }
+    )*};
+}
+
+primitive_calls! {
+    jboolean => CallBooleanMethodA, CallNonvirtualBooleanMethodA, CallStaticBooleanMethodA;
+    jbyte => CallByteMethodA, CallNonvirtualByteMethodA, CallStaticByteMethodA;
+    jchar => CallCharMethodA, CallNonvirtualCharMethodA, CallStaticCharMethodA;
+    jshort => CallShortMethodA, CallNonvirtualShortMethodA, CallStaticShortMethodA;
+    jint => CallIntMethodA, CallNonvirtualIntMethodA, CallStaticIntMethodA;
+    jlong => CallLongMethodA, CallNonvirtualLongMethodA, CallStaticLongMethodA;
+    jfloat => CallFloatMethodA, CallNonvirtualFloatMethodA, CallStaticFloatMethodA;
+    jdouble => CallDoubleMethodA, CallNonvirtualDoubleMethodA, CallStaticDoubleMethodA;
+}
+
+unsafe extern "C" fn CallObjectMethodA(
+    env: *mut JNIEnv,
+    obj: jobject,
+    method: jmethodID,
+    args: *const JValue,
+) -> jobject {
+    let result = call_virtual(env, obj, method, args);
+    new_local(env, result)
+}
+
+unsafe extern "C" fn CallNonvirtualObjectMethodA(
+    env: *mut JNIEnv,
+    obj: jobject,
+    clazz: jclass,
+    method: jmethodID,
+    args: *const JValue,
+) -> jobject {
+    let result = call_nonvirtual(env, obj, clazz, method, args);
+    new_local(env, result)
+}
+
+unsafe extern "C" fn CallStaticObjectMethodA(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    method: jmethodID,
+    args: *const JValue,
+) -> jobject {
+    let result = call_static(env, clazz, method, args);
+    new_local(env, result)
+}
+
+unsafe extern "C" fn CallVoidMethodA(
+    env: *mut JNIEnv,
+    obj: jobject,
+    method: jmethodID,
+    args: *const JValue,
+) {
+    call_virtual(env, obj, method, args);
+}
+
+unsafe extern "C" fn CallNonvirtualVoidMethodA(
+    env: *mut JNIEnv,
+    obj: jobject,
+    clazz: jclass,
+    method: jmethodID,
+    args: *const JValue,
+) {
+    call_nonvirtual(env, obj, clazz, method, args);
+}
+
+unsafe extern "C" fn CallStaticVoidMethodA(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    method: jmethodID,
+    args: *const JValue,
+) {
+    call_static(env, clazz, method, args);
+}
+
+// ---- fields ------------------------------------------------------------------
+
+/// The class in `class_name`'s superclass chain declaring the field.
+/// Builtin classes have no field table and accept any name.
+fn declaring_class(
+    env: *mut JNIEnv,
+    class_name: &str,
+    name: &str,
+    descriptor: &str,
+    is_static: bool,
+) -> Option<String> {
+    // SAFETY: callers pass the live `env` they were given.
+    let native = unsafe { native(env) };
+    let mut level = Some(class_name.to_string());
+    while let Some(current) = level {
+        if is_builtin_class(&current) {
+            return (current != "java/lang/Object").then_some(current);
+        }
+        if let Ok(class) = native.loader.load_class(&current) {
+            let declared = class.fields.iter().any(|field| {
+                class.get_utf8(field.name_index) == Some(name)
+                    && class.get_utf8(field.descriptor_index) == Some(descriptor)
+                    && (field.access_flags & ACC_STATIC != 0) == is_static
+            });
+            if declared {
+                return Some(current);
+            }
+        }
+        level = native.interpreter.superclass_of(native.loader, &current);
+    }
+    None
+}
+
+unsafe fn get_field_id(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    name: *const c_char,
+    signature: *const c_char,
+    is_static: bool,
+) -> jfieldID {
+    let (Some(class_name), Some(name), Some(signature)) = (
+        resolve_class(env, clazz),
+        utf_argument(name),
+        utf_argument(signature),
+    ) else {
+        throw(env, "java/lang/NoSuchFieldError", None);
+        return std::ptr::null_mut();
+    };
+    let native = native(env);
+    native
+        .interpreter
+        .ensure_class_initialized(native.loader, &class_name, native.heap);
+    if has_pending_exception(env) {
+        return std::ptr::null_mut();
+    }
+    match declaring_class(env, &class_name, &name, &signature, is_static) {
+        Some(owner) => field_id(&owner, &name, &signature, is_static),
+        None => {
+            throw(env, "java/lang/NoSuchFieldError", Some(&name));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn GetFieldID(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    name: *const c_char,
+    signature: *const c_char,
+) -> jfieldID {
+    get_field_id(env, clazz, name, signature, false)
+}
+
+unsafe extern "C" fn GetStaticFieldID(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    name: *const c_char,
+    signature: *const c_char,
+) -> jfieldID {
+    get_field_id(env, clazz, name, signature, true)
+}
+
+unsafe fn get_field(env: *mut JNIEnv, obj: jobject, field: jfieldID) -> HeapValue {
+    let Some(field) = field.as_ref() else {
+        return HeapValue::Null;
+    };
+    let HeapValue::Object(obj) = resolve(env, obj) else {
+        throw(env, "java/lang/NullPointerException", None);
+        return HeapValue::Null;
+    };
+    native(env)
+        .heap
+        .get(obj.id)
+        .and_then(|real| real.get_field(&field.name))
+        .cloned()
+        .unwrap_or_else(|| Interpreter::default_value_for_descriptor(&field.descriptor))
+}
+
+unsafe fn set_field(env: *mut JNIEnv, obj: jobject, field: jfieldID, value: HeapValue) {
+    let Some(field) = field.as_ref() else {
+        return;
+    };
+    let HeapValue::Object(obj) = resolve(env, obj) else {
+        throw(env, "java/lang/NullPointerException", None);
+        return;
+    };
+    if let Some(real) = native(env).heap.get_mut(obj.id) {
+        real.set_field(&field.name, value);
+    }
+}
+
+unsafe fn get_static_field(env: *mut JNIEnv, field: jfieldID) -> HeapValue {
+    let Some(field) = field.as_ref() else {
+        return HeapValue::Null;
+    };
+    native(env)
+        .loader
+        .get_static_field(&field.class_name, &field.name)
+        .unwrap_or_else(|| Interpreter::default_value_for_descriptor(&field.descriptor))
+}
+
+unsafe fn set_static_field(env: *mut JNIEnv, field: jfieldID, value: HeapValue) {
+    if let Some(field) = field.as_ref() {
+        native(env)
+            .loader
+            .set_static_field(&field.class_name, &field.name, value);
+    }
+}
+
+macro_rules! primitive_fields {
+    ($($ty:ty => $get:ident, $set:ident, $get_static:ident, $set_static:ident;)*) => {$(
+        unsafe extern "C" fn $get(env: *mut JNIEnv, obj: jobject, field: jfieldID) -> $ty {
+            <$ty as Primitive>::from_heap(&get_field(env, obj, field))
+        }
+
+        unsafe extern "C" fn $set(env: *mut JNIEnv, obj: jobject, field: jfieldID, value: $ty) {
+            set_field(env, obj, field, value.to_heap());
+        }
+
+        unsafe extern "C" fn $get_static(env: *mut JNIEnv, _clazz: jclass, field: jfieldID) -> $ty {
+            <$ty as Primitive>::from_heap(&get_static_field(env, field))
+        }
+
+        unsafe extern "C" fn $set_static(
+            env: *mut JNIEnv,
+            _clazz: jclass,
+            field: jfieldID,
+            value: $ty,
+        ) {
+            set_static_field(env, field, value.to_heap());
+        }
+    )*};
+}
+
+primitive_fields! {
+    jboolean => GetBooleanField, SetBooleanField, GetStaticBooleanField, SetStaticBooleanField;
+    jbyte => GetByteField, SetByteField, GetStaticByteField, SetStaticByteField;
+    jchar => GetCharField, SetCharField, GetStaticCharField, SetStaticCharField;
+    jshort => GetShortField, SetShortField, GetStaticShortField, SetStaticShortField;
+    jint => GetIntField, SetIntField, GetStaticIntField, SetStaticIntField;
+    jlong => GetLongField, SetLongField, GetStaticLongField, SetStaticLongField;
+    jfloat => GetFloatField, SetFloatField, GetStaticFloatField, SetStaticFloatField;
+    jdouble => GetDoubleField, SetDoubleField, GetStaticDoubleField, SetStaticDoubleField;
+}
+
+unsafe extern "C" fn GetObjectField(env: *mut JNIEnv, obj: jobject, field: jfieldID) -> jobject {
+    let value = get_field(env, obj, field);
+    new_local(env, value)
+}
+
+unsafe extern "C" fn SetObjectField(
+    env: *mut JNIEnv,
+    obj: jobject,
+    field: jfieldID,
+    value: jobject,
+) {
+    let value = resolve(env, value);
+    set_field(env, obj, field, value);
+}
+
+unsafe extern "C" fn GetStaticObjectField(
+    env: *mut JNIEnv,
+    _clazz: jclass,
+    field: jfieldID,
+) -> jobject {
+    let value = get_static_field(env, field);
+    new_local(env, value)
+}
+
+unsafe extern "C" fn SetStaticObjectField(
+    env: *mut JNIEnv,
+    _clazz: jclass,
+    field: jfieldID,
+    value: jobject,
+) {
+    let value = resolve(env, value);
+    set_static_field(env, field, value);
+}
+
+// ---- strings -----------------------------------------------------------------
+
+unsafe extern "C" fn NewString(env: *mut JNIEnv, unicode: *const jchar, length: jsize) -> jobject {
+    if length < 0 || (unicode.is_null() && length > 0) {
+        return std::ptr::null_mut();
+    }
+    let units = if length == 0 {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(unicode, length as usize)
+    };
+    new_string(env, &String::from_utf16_lossy(units))
+}
+
+unsafe extern "C" fn GetStringLength(env: *mut JNIEnv, string: jobject) -> jsize {
+    string_units(env, string).map_or(0, |units| units.len() as jsize)
+}
+
+unsafe extern "C" fn GetStringChars(
+    env: *mut JNIEnv,
+    string: jobject,
+    is_copy: *mut jboolean,
+) -> *const jchar {
+    let Some(units) = string_units(env, string) else {
+        return std::ptr::null();
+    };
+    set_is_copy(is_copy);
+    malloc_copy(&units, Some(0))
+}
+
+unsafe extern "C" fn ReleaseStringChars(_env: *mut JNIEnv, _string: jobject, chars: *const jchar) {
+    libc::free(chars as *mut c_void);
+}
+
+unsafe extern "C" fn GetStringCritical(
+    env: *mut JNIEnv,
+    string: jobject,
+    is_copy: *mut jboolean,
+) -> *const jchar {
+    GetStringChars(env, string, is_copy)
+}
+
+unsafe extern "C" fn ReleaseStringCritical(env: *mut JNIEnv, string: jobject, chars: *const jchar) {
+    ReleaseStringChars(env, string, chars);
+}
+
+unsafe extern "C" fn NewStringUTF(env: *mut JNIEnv, bytes: *const c_char) -> jobject {
+    match utf_argument(bytes) {
+        Some(text) => new_string(env, &text),
+        None => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn GetStringUTFLength(env: *mut JNIEnv, string: jobject) -> jsize {
+    string_units(env, string).map_or(0, |units| encode_modified_utf8(&units).len() as jsize)
+}
+
+unsafe extern "C" fn GetStringUTFChars(
+    env: *mut JNIEnv,
+    string: jobject,
+    is_copy: *mut jboolean,
+) -> *const c_char {
+    let Some(units) = string_units(env, string) else {
+        return std::ptr::null();
+    };
+    set_is_copy(is_copy);
+    malloc_copy(&encode_modified_utf8(&units), Some(0)) as *const c_char
+}
+
+unsafe extern "C" fn ReleaseStringUTFChars(
+    _env: *mut JNIEnv,
+    _string: jobject,
+    chars: *const c_char,
+) {
+    libc::free(chars as *mut c_void);
+}
+
+/// The UTF-16 units `start..start + length`, or `StringIndexOutOfBoundsException`.
+unsafe fn string_region(
+    env: *mut JNIEnv,
+    string: jobject,
+    start: jsize,
+    length: jsize,
+) -> Option<Vec<u16>> {
+    let units = string_units(env, string)?;
+    if start < 0 || length < 0 || start as usize + length as usize > units.len() {
+        throw(env, "java/lang/StringIndexOutOfBoundsException", None);
+        return None;
+    }
+    Some(units[start as usize..(start + length) as usize].to_vec())
+}
+
+unsafe extern "C" fn GetStringRegion(
+    env: *mut JNIEnv,
+    string: jobject,
+    start: jsize,
+    length: jsize,
+    buffer: *mut jchar,
+) {
+    if let Some(units) = string_region(env, string, start, length) {
+        std::ptr::copy_nonoverlapping(units.as_ptr(), buffer, units.len());
+    }
+}
+
+/// Writes the modified UTF-8 of the region followed by a NUL.
+unsafe extern "C" fn GetStringUTFRegion(
+    env: *mut JNIEnv,
+    string: jobject,
+    start: jsize,
+    length: jsize,
+    buffer: *mut c_char,
+) {
+    if let Some(units) = string_region(env, string, start, length) {
+        let bytes = encode_modified_utf8(&units);
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer as *mut u8, bytes.len());
+        *buffer.add(bytes.len()) = 0;
+    }
+}
+
+// ---- arrays ------------------------------------------------------------------
+
+unsafe fn array_id(env: *mut JNIEnv, array: jobject) -> Option<u64> {
+    match resolve(env, array) {
+        HeapValue::Array(arr) => Some(arr.id),
+        _ => {
+            throw(env, "java/lang/NullPointerException", None);
+            None
+        }
+    }
+}
+
+unsafe fn array_length(env: *mut JNIEnv, id: u64) -> usize {
+    native(env)
+        .heap
+        .get_array(id)
+        .map_or(0, |arr| arr.content.len())
+}
+
+unsafe extern "C" fn GetArrayLength(env: *mut JNIEnv, array: jobject) -> jsize {
+    match array_id(env, array) {
+        Some(id) => array_length(env, id) as jsize,
+        None => 0,
+    }
+}
+
+unsafe fn check_length(env: *mut JNIEnv, length: jsize) -> bool {
+    if length < 0 {
+        throw(
+            env,
+            "java/lang/NegativeArraySizeException",
+            Some(&length.to_string()),
+        );
+        return false;
+    }
+    true
+}
+
+unsafe extern "C" fn NewObjectArray(
+    env: *mut JNIEnv,
+    length: jsize,
+    element_class: jclass,
+    initial: jobject,
+) -> jobject {
+    let Some(component) = resolve_class(env, element_class) else {
+        throw(env, "java/lang/NullPointerException", None);
+        return std::ptr::null_mut();
+    };
+    if !check_length(env, length) {
+        return std::ptr::null_mut();
+    }
+    let initial = resolve(env, initial);
+    let heap = &mut native(env).heap;
+    let arr = heap.alloc_reference_array(length as usize, &component);
+    if !initial.is_null() {
+        if let Some(real) = heap.get_array_mut(arr.id) {
+            real.content.fill(initial);
+        }
+    }
+    new_local(env, HeapValue::Array(arr))
+}
+
+unsafe fn check_index(env: *mut JNIEnv, index: jsize, length: usize) -> bool {
+    if index < 0 || index as usize >= length {
+        let message = format!("Index {} out of bounds for length {}", index, length);
+        throw(
+            env,
+            "java/lang/ArrayIndexOutOfBoundsException",
+            Some(&message),
+        );
+        return false;
+    }
+    true
+}
+
+unsafe extern "C" fn GetObjectArrayElement(
+    env: *mut JNIEnv,
+    array: jobject,
+    index: jsize,
+) -> jobject {
+    let Some(id) = array_id(env, array) else {
+        return std::ptr::null_mut();
+    };
+    if !check_index(env, index, array_length(env, id)) {
+        return std::ptr::null_mut();
+    }
+    let value = native(env)
+        .heap
+        .get_array(id)
+        .map(|arr| arr.content[index as usize].clone());
+    new_local(env, value.unwrap_or(HeapValue::Null))
+}
+
+unsafe extern "C" fn SetObjectArrayElement(
+    env: *mut JNIEnv,
+    array: jobject,
+    index: jsize,
+    value: jobject,
+) {
+    let Some(id) = array_id(env, array) else {
+        return;
+    };
+    if !check_index(env, index, array_length(env, id)) {
+        return;
+    }
+    let value = resolve(env, value);
+    let native = native(env);
+    let Some(arr) = native.heap.get_array(id).cloned() else {
+        return;
+    };
+    if let Some(from) = class_of(&value) {
+        let component = arr.component_class.as_deref().unwrap_or("java/lang/Object");
+        if !native
+            .interpreter
+            .is_assignable(native.loader, &from, component)
+        {
+            let message = format!(
+                "type mismatch: can not store {} to {}[{}]",
+                from.replace('/', "."),
+                array_class_name(&arr).replace('/', "."),
+                index
+            );
+            throw(env, "java/lang/ArrayStoreException", Some(&message));
+            return;
+        }
+    }
+    if let Some(real) = native.heap.get_array_mut(id) {
+        real.content[index as usize] = value;
+    }
+}
+
+unsafe fn new_primitive_array(env: *mut JNIEnv, length: jsize, element_type: ArrayType) -> jobject {
+    if !check_length(env, length) {
+        return std::ptr::null_mut();
+    }
+    let arr = native(env).heap.alloc_array(length as usize, element_type);
+    new_local(env, HeapValue::Array(arr))
+}
+
+unsafe fn get_elements<T: Primitive>(
+    env: *mut JNIEnv,
+    array: jobject,
+    is_copy: *mut jboolean,
+) -> *mut T {
+    let Some(id) = array_id(env, array) else {
+        return std::ptr::null_mut();
+    };
+    let items: Vec<T> = native(env)
+        .heap
+        .get_array(id)
+        .map(|arr| arr.content.iter().map(T::from_heap).collect())
+        .unwrap_or_default();
+    set_is_copy(is_copy);
+    malloc_copy(&items, None)
+}
+
+/// Mode 0 copies back and frees, `JNI_COMMIT` only copies back and
+/// `JNI_ABORT` only frees.
+unsafe fn release_elements<T: Primitive>(
+    env: *mut JNIEnv,
+    array: jobject,
+    elements: *mut T,
+    mode: jint,
+) {
+    if elements.is_null() {
+        return;
+    }
+    if mode != JNI_ABORT {
+        if let HeapValue::Array(arr) = resolve(env, array) {
+            if let Some(real) = native(env).heap.get_array_mut(arr.id) {
+                for (index, slot) in real.content.iter_mut().enumerate() {
+                    *slot = elements.add(index).read().to_heap();
+                }
+            }
+        }
+    }
+    if mode != JNI_COMMIT {
+        libc::free(elements as *mut c_void);
+    }
+}
+
+/// `start..start + length` within an array, or `ArrayIndexOutOfBoundsException`.
+unsafe fn array_region(
+    env: *mut JNIEnv,
+    array: jobject,
+    start: jsize,
+    length: jsize,
+) -> Option<u64> {
+    let id = array_id(env, array)?;
+    let array_length = array_length(env, id) as i64;
+    let message = if length < 0 {
+        format!("Length {} is negative", length)
+    } else if start < 0 || i64::from(start) > array_length - i64::from(length) {
+        format!(
+            "Array region {}..{} out of bounds for length {}",
+            start,
+            i64::from(start) + i64::from(length),
+            array_length
+        )
+    } else {
+        return Some(id);
+    };
+    throw(
+        env,
+        "java/lang/ArrayIndexOutOfBoundsException",
+        Some(&message),
+    );
+    None
+}
+
+unsafe fn get_region<T: Primitive>(
+    env: *mut JNIEnv,
+    array: jobject,
+    start: jsize,
+    length: jsize,
+    buffer: *mut T,
+) {
+    let Some(id) = array_region(env, array, start, length) else {
+        return;
+    };
+    if let Some(arr) = native(env).heap.get_array(id) {
+        let region = &arr.content[start as usize..(start + length) as usize];
+        for (index, value) in region.iter().enumerate() {
+            buffer.add(index).write(T::from_heap(value));
+        }
+    }
+}
+
+unsafe fn set_region<T: Primitive>(
+    env: *mut JNIEnv,
+    array: jobject,
+    start: jsize,
+    length: jsize,
+    buffer: *const T,
+) {
+    let Some(id) = array_region(env, array, start, length) else {
+        return;
+    };
+    if let Some(arr) = native(env).heap.get_array_mut(id) {
+        let region = &mut arr.content[start as usize..(start + length) as usize];
+        for (index, slot) in region.iter_mut().enumerate() {
+            *slot = buffer.add(index).read().to_heap();
+        }
+    }
+}
+
+macro_rules! primitive_arrays {
+    ($($ty:ty => $new:ident, $get:ident, $release:ident, $get_region:ident, $set_region:ident;)*) => {$(
+        unsafe extern "C" fn $new(env: *mut JNIEnv, length: jsize) -> jobject {
+            new_primitive_array(env, length, <$ty as Primitive>::ARRAY_TYPE)
+        }
+
+        unsafe extern "C" fn $get(env: *mut JNIEnv, array: jobject, is_copy: *mut jboolean) -> *mut $ty {
+            get_elements::<$ty>(env, array, is_copy)
+        }
+
+        unsafe extern "C" fn $release(env: *mut JNIEnv, array: jobject, elements: *mut $ty, mode: jint) {
+            release_elements::<$ty>(env, array, elements, mode);
+        }
+
+        unsafe extern "C" fn $get_region(
+            env: *mut JNIEnv,
+            array: jobject,
+            start: jsize,
+            length: jsize,
+            buffer: *mut $ty,
+        ) {
+            get_region::<$ty>(env, array, start, length, buffer);
+        }
+
+        unsafe extern "C" fn $set_region(
+            env: *mut JNIEnv,
+            array: jobject,
+            start: jsize,
+            length: jsize,
+            buffer: *const $ty,
+        ) {
+            set_region::<$ty>(env, array, start, length, buffer);
+        }
+    )*};
+}
+
+primitive_arrays! {
+    jboolean => NewBooleanArray, GetBooleanArrayElements, ReleaseBooleanArrayElements,
+        GetBooleanArrayRegion, SetBooleanArrayRegion;
+    jbyte => NewByteArray, GetByteArrayElements, ReleaseByteArrayElements,
+        GetByteArrayRegion, SetByteArrayRegion;
+    jchar => NewCharArray, GetCharArrayElements, ReleaseCharArrayElements,
+        GetCharArrayRegion, SetCharArrayRegion;
+    jshort => NewShortArray, GetShortArrayElements, ReleaseShortArrayElements,
+        GetShortArrayRegion, SetShortArrayRegion;
+    jint => NewIntArray, GetIntArrayElements, ReleaseIntArrayElements,
+        GetIntArrayRegion, SetIntArrayRegion;
+    jlong => NewLongArray, GetLongArrayElements, ReleaseLongArrayElements,
+        GetLongArrayRegion, SetLongArrayRegion;
+    jfloat => NewFloatArray, GetFloatArrayElements, ReleaseFloatArrayElements,
+        GetFloatArrayRegion, SetFloatArrayRegion;
+    jdouble => NewDoubleArray, GetDoubleArrayElements, ReleaseDoubleArrayElements,
+        GetDoubleArrayRegion, SetDoubleArrayRegion;
+}
+
+unsafe fn element_type(env: *mut JNIEnv, array: jobject) -> Option<ArrayType> {
+    match resolve(env, array) {
+        HeapValue::Array(arr) => native(env).heap.get_array(arr.id).map(|a| a.element_type),
+        _ => None,
+    }
+}
+
+unsafe extern "C" fn GetPrimitiveArrayCritical(
+    env: *mut JNIEnv,
+    array: jobject,
+    is_copy: *mut jboolean,
+) -> *mut c_void {
+    match element_type(env, array) {
+        Some(ArrayType::Boolean) => get_elements::<jboolean>(env, array, is_copy) as *mut c_void,
+        Some(ArrayType::Byte) => get_elements::<jbyte>(env, array, is_copy) as *mut c_void,
+        Some(ArrayType::Char) => get_elements::<jchar>(env, array, is_copy) as *mut c_void,
+        Some(ArrayType::Short) => get_elements::<jshort>(env, array, is_copy) as *mut c_void,
+        Some(ArrayType::Int) => get_elements::<jint>(env, array, is_copy) as *mut c_void,
+        Some(ArrayType::Long) => get_elements::<jlong>(env, array, is_copy) as *mut c_void,
+        Some(ArrayType::Float) => get_elements::<jfloat>(env, array, is_copy) as *mut c_void,
+        Some(ArrayType::Double) => get_elements::<jdouble>(env, array, is_copy) as *mut c_void,
+        _ => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn ReleasePrimitiveArrayCritical(
+    env: *mut JNIEnv,
+    array: jobject,
+    elements: *mut c_void,
+    mode: jint,
+) {
+    match element_type(env, array) {
+        Some(ArrayType::Boolean) => release_elements(env, array, elements as *mut jboolean, mode),
+        Some(ArrayType::Byte) => release_elements(env, array, elements as *mut jbyte, mode),
+        Some(ArrayType::Char) => release_elements(env, array, elements as *mut jchar, mode),
+        Some(ArrayType::Short) => release_elements(env, array, elements as *mut jshort, mode),
+        Some(ArrayType::Int) => release_elements(env, array, elements as *mut jint, mode),
+        Some(ArrayType::Long) => release_elements(env, array, elements as *mut jlong, mode),
+        Some(ArrayType::Float) => release_elements(env, array, elements as *mut jfloat, mode),
+        Some(ArrayType::Double) => release_elements(env, array, elements as *mut jdouble, mode),
+        _ => libc::free(elements),
+    }
+}
+
+// ---- natives, monitors and the VM ----------------------------------------------
+
+/// `JNINativeMethod`.
+#[repr(C)]
+pub struct JNINativeMethod {
+    name: *const c_char,
+    signature: *const c_char,
+    fnPtr: *mut c_void,
+}
+
+/// Binds each entry to the `native` method it names, replacing symbol
+/// lookup for it. HotSpot's `NoSuchMethodError` messages are kept.
+unsafe extern "C" fn RegisterNatives(
+    env: *mut JNIEnv,
+    clazz: jclass,
+    methods: *const JNINativeMethod,
+    count: jint,
+) -> jint {
+    let Some(class_name) = resolve_class(env, clazz) else {
+        return JNI_ERR;
+    };
+    if count < 0 || (methods.is_null() && count > 0) {
+        return JNI_ERR;
+    }
+    for index in 0..count as usize {
+        let entry = &*methods.add(index);
+        let (Some(name), Some(signature)) =
+            (utf_argument(entry.name), utf_argument(entry.signature))
+        else {
+            return JNI_ERR;
+        };
+        let native = native(env);
+        let found = native
+            .interpreter
+            .find_method(native.loader, &class_name, &name, &signature);
+        let symbolic = native.loader.symbolic_name(&class_name);
+        let described = registry::describe_method(&symbolic, &name, &signature);
+        let Some((owner, flags)) = found else {
+            let message = format!("Method {} name or signature does not match", described);
+            throw(env, "java/lang/NoSuchMethodError", Some(&message));
+            return JNI_ERR;
+        };
+        if flags & ACC_NATIVE == 0 || is_builtin_class(&owner) {
+            let message = format!("Method {} is not declared as native", described);
+            throw(env, "java/lang/NoSuchMethodError", Some(&message));
+            return JNI_ERR;
+        }
+        let method = library::bind(entry.fnPtr, &owner, &signature, flags & ACC_STATIC != 0);
+        native
+            .interpreter
+            .bind_native(&owner, &name, &signature, method);
+    }
+    JNI_OK
+}
+
+unsafe extern "C" fn UnregisterNatives(env: *mut JNIEnv, clazz: jclass) -> jint {
+    let Some(class_name) = resolve_class(env, clazz) else {
+        return JNI_ERR;
+    };
+    if is_builtin_class(&class_name) {
+        return JNI_ERR;
+    }
+    native(env).interpreter.unbind_natives(&class_name);
+    JNI_OK
+}
+
+/// The interpreter runs a single Java thread, so monitors never contend.
+unsafe extern "C" fn MonitorEnter(_env: *mut JNIEnv, _obj: jobject) -> jint {
+    JNI_OK
+}
+
+unsafe extern "C" fn MonitorExit(_env: *mut JNIEnv, _obj: jobject) -> jint {
+    JNI_OK
+}
+
+unsafe extern "C" fn GetJavaVM(_env: *mut JNIEnv, vm: *mut *mut JavaVM) -> jint {
+    if vm.is_null() {
+        return JNI_ERR;
+    }
+    *vm = java_vm();
+    JNI_OK
+}
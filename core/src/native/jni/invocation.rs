@@ -0,0 +1,434 @@
+//! The Invocation API: `JNI_CreateJavaVM` and friends, exported from the
+//! library so a C or C++ host can embed the VM as it would embed libjvm,
+//! and the `JavaVM` invoke interface.
+//!
+//! The interpreter runs one Java thread at a time. A hosted VM therefore
+//! lets one native thread be attached at a time: the creating thread starts
+//! out attached, and `AttachCurrentThread` on another thread fails with
+//! `JNI_ERR` until the attached one calls `DetachCurrentThread`.
+
+#![allow(non_snake_case)]
+
+use super::{
+    current_env, enter, is_supported_version, jboolean, jint, jsize, leave, JNIEnv, JNI_EDETACHED,
+    JNI_EEXIST, JNI_EINVAL, JNI_ERR, JNI_EVERSION, JNI_OK, JNI_VERSION_1_1,
+};
+use crate::args;
+use crate::exec::interpreter::Interpreter;
+use crate::jit::JitMode;
+use crate::loader::class_loader::ClassLoader;
+use crate::native::{java_io_printstream, NativeEnv};
+use crate::runtime::assertions::AssertionStatus;
+use crate::runtime::heap::Heap;
+use std::cell::Cell;
+use std::ffi::{c_char, c_void, CStr};
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+/// `struct JNIInvokeInterface_`.
+#[repr(C)]
+pub struct JNIInvokeInterface {
+    reserved0: *const c_void,
+    reserved1: *const c_void,
+    reserved2: *const c_void,
+    DestroyJavaVM: unsafe extern "C" fn(*mut JavaVM) -> jint,
+    AttachCurrentThread: unsafe extern "C" fn(*mut JavaVM, *mut *mut c_void, *mut c_void) -> jint,
+    DetachCurrentThread: unsafe extern "C" fn(*mut JavaVM) -> jint,
+    GetEnv: unsafe extern "C" fn(*mut JavaVM, *mut *mut c_void, jint) -> jint,
+    AttachCurrentThreadAsDaemon:
+        unsafe extern "C" fn(*mut JavaVM, *mut *mut c_void, *mut c_void) -> jint,
+}
+
+/// `JavaVM` as C sees it: a pointer to the invoke interface.
+pub type JavaVM = *const JNIInvokeInterface;
+
+/// `JavaVMOption`.
+#[repr(C)]
+pub struct JavaVMOption {
+    pub optionString: *mut c_char,
+    pub extraInfo: *mut c_void,
+}
+
+/// `JavaVMInitArgs`.
+#[repr(C)]
+pub struct JavaVMInitArgs {
+    pub version: jint,
+    pub nOptions: jint,
+    pub options: *mut JavaVMOption,
+    pub ignoreUnrecognized: jboolean,
+}
+
+/// `JavaVMAttachArgs`.
+#[repr(C)]
+pub struct JavaVMAttachArgs {
+    pub version: jint,
+    pub name: *mut c_char,
+    pub group: *mut c_void,
+}
+
+struct SharedInterface(JNIInvokeInterface);
+struct SharedVm(JavaVM);
+
+// SAFETY: both are immutable and only hold code addresses.
+unsafe impl Sync for SharedInterface {}
+unsafe impl Sync for SharedVm {}
+
+static INVOKE_INTERFACE: SharedInterface = SharedInterface(JNIInvokeInterface {
+    reserved0: std::ptr::null(),
+    reserved1: std::ptr::null(),
+    reserved2: std::ptr::null(),
+    DestroyJavaVM,
+    AttachCurrentThread,
+    DetachCurrentThread,
+    GetEnv,
+    AttachCurrentThreadAsDaemon: AttachCurrentThread,
+});
+
+static VM: SharedVm = SharedVm(&INVOKE_INTERFACE.0);
+
+/// The process's `JavaVM*`, handed to `JNI_OnLoad` and returned by
+/// `GetJavaVM` and `JNI_CreateJavaVM`.
+pub fn java_vm() -> *mut JavaVM {
+    &VM.0 as *const JavaVM as *mut JavaVM
+}
+
+/// A VM created through `JNI_CreateJavaVM`. The parts are leaked boxes
+/// owned by this struct; `native` borrows the other three.
+struct HostedVm {
+    interpreter: *mut Interpreter,
+    loader: *mut ClassLoader,
+    heap: *mut Heap,
+    native: *mut NativeEnv<'static>,
+}
+
+impl Drop for HostedVm {
+    fn drop(&mut self) {
+        // SAFETY: every pointer came from `Box::into_raw` in `JNI_CreateJavaVM` and
+        // `native` goes first because it borrows the others.
+        unsafe {
+            drop(Box::from_raw(self.native));
+            drop(Box::from_raw(self.heap));
+            drop(Box::from_raw(self.loader));
+            drop(Box::from_raw(self.interpreter));
+        }
+    }
+}
+
+#[derive(Default)]
+struct VmState {
+    hosted: Option<HostedVm>,
+    destroyed: bool,
+    /// The thread currently attached to the hosted VM.
+    attached: Option<ThreadId>,
+}
+
+// SAFETY: the hosted VM is only touched by the attached thread.
+unsafe impl Send for VmState {}
+
+static STATE: Mutex<Option<VmState>> = Mutex::new(None);
+
+thread_local! {
+    /// This thread's `JNIEnv*` while it is attached to the hosted VM.
+    static HOST_ENV: Cell<Option<*mut JNIEnv>> = const { Cell::new(None) };
+}
+
+fn with_state<R>(f: impl FnOnce(&mut VmState) -> R) -> R {
+    let mut state = STATE.lock().unwrap();
+    f(state.get_or_insert_with(VmState::default))
+}
+
+/// Attaches this thread to the hosted VM. `None` if there is no hosted VM
+/// or another thread is attached to it.
+fn attach_hosted() -> Option<*mut JNIEnv> {
+    if let Some(env) = HOST_ENV.with(Cell::get) {
+        return Some(env);
+    }
+    with_state(|state| {
+        let native = state.hosted.as_ref()?.native;
+        if state.attached.is_some() {
+            return None;
+        }
+        state.attached = Some(thread::current().id());
+        let env = enter(native);
+        HOST_ENV.with(|host| host.set(Some(env)));
+        Some(env)
+    })
+}
+
+/// Detaches this thread. Fails while Java methods are running on it, i.e.
+/// from inside a native method.
+fn detach_hosted() -> jint {
+    let Some(env) = HOST_ENV.with(Cell::get) else {
+        return if current_env().is_some() {
+            JNI_ERR
+        } else {
+            JNI_OK
+        };
+    };
+    if current_env() != Some(env) {
+        return JNI_ERR;
+    }
+    HOST_ENV.with(|host| host.set(None));
+    // SAFETY: `env` is this thread's innermost context, checked above.
+    unsafe { leave(env) };
+    with_state(|state| state.attached = None);
+    JNI_OK
+}
+
+/// Runs the shutdown hooks and tears the hosted VM down. The VM cannot be
+/// created again afterwards, as with HotSpot.
+unsafe extern "C" fn DestroyJavaVM(_vm: *mut JavaVM) -> jint {
+    let Some(env) = attach_hosted() else {
+        return JNI_ERR;
+    };
+    if current_env() != Some(env) {
+        return JNI_ERR;
+    }
+    let native = super::native(env);
+    native
+        .interpreter
+        .run_shutdown_hooks(native.loader, native.heap);
+    java_io_printstream::flush_all();
+    HOST_ENV.with(|host| host.set(None));
+    leave(env);
+    let hosted = with_state(|state| {
+        state.attached = None;
+        state.destroyed = true;
+        state.hosted.take()
+    });
+    drop(hosted);
+    JNI_OK
+}
+
+/// In a hosted VM any thread may attach. Under the launcher only threads
+/// already running Java code have an environment.
+unsafe extern "C" fn AttachCurrentThread(
+    _vm: *mut JavaVM,
+    penv: *mut *mut c_void,
+    args: *mut c_void,
+) -> jint {
+    if let Some(args) = (args as *const JavaVMAttachArgs).as_ref() {
+        if args.version == JNI_VERSION_1_1 || !is_supported_version(args.version) {
+            return JNI_EVERSION;
+        }
+    }
+    match attach_hosted().or_else(current_env) {
+        Some(env) => {
+            *penv = env as *mut c_void;
+            JNI_OK
+        }
+        None => JNI_ERR,
+    }
+}
+
+unsafe extern "C" fn DetachCurrentThread(_vm: *mut JavaVM) -> jint {
+    detach_hosted()
+}
+
+unsafe extern "C" fn GetEnv(_vm: *mut JavaVM, penv: *mut *mut c_void, version: jint) -> jint {
+    if !is_supported_version(version) {
+        *penv = std::ptr::null_mut();
+        return JNI_EVERSION;
+    }
+    match current_env() {
+        Some(env) => {
+            *penv = env as *mut c_void;
+            JNI_OK
+        }
+        None => {
+            *penv = std::ptr::null_mut();
+            JNI_EDETACHED
+        }
+    }
+}
+
+/// The settings `JNI_CreateJavaVM` understood from its options.
+#[derive(Default)]
+struct VmOptions {
+    class_path: Option<String>,
+    properties: Vec<(String, String)>,
+    skip_verification: bool,
+    jit_mode: Option<JitMode>,
+    assertions: AssertionStatus,
+    max_heap_size: Option<usize>,
+}
+
+/// Options the interpreter accepts but has no use for.
+fn is_ignored_option(option: &str) -> bool {
+    matches!(
+        option,
+        "vfprintf" | "exit" | "abort" | "-Xrs" | "-Xverify:all" | "-Xverify:remote"
+    ) || option.starts_with("-verbose")
+        || ["-Xss", "-Xms", "-Xshare:", "-XX:SharedArchiveFile="]
+            .iter()
+            .any(|prefix| option.starts_with(prefix))
+}
+
+unsafe fn parse_options(args: &JavaVMInitArgs) -> Result<VmOptions, jint> {
+    let mut options = VmOptions::default();
+    let count = usize::try_from(args.nOptions).map_err(|_| JNI_EINVAL)?;
+    if count > 0 && args.options.is_null() {
+        return Err(JNI_EINVAL);
+    }
+    // Like HotSpot, `JAVA_TOOL_OPTIONS` come first so that the embedder's
+    // options override them.
+    let mut texts = args::java_tool_options().map_err(|message| {
+        eprintln!("{}", message);
+        JNI_ERR
+    })?;
+    for index in 0..count {
+        let option = &*args.options.add(index);
+        if option.optionString.is_null() {
+            return Err(JNI_EINVAL);
+        }
+        texts.push(
+            CStr::from_ptr(option.optionString)
+                .to_string_lossy()
+                .into_owned(),
+        );
+    }
+    for text in texts {
+        if let Some(property) = text.strip_prefix("-D") {
+            let (name, value) = property.split_once('=').unwrap_or((property, ""));
+            if name == "java.class.path" {
+                options.class_path = Some(value.to_string());
+            }
+            options
+                .properties
+                .push((name.to_string(), value.to_string()));
+        } else if text == "-Xverify:none" || text == "-noverify" {
+            options.skip_verification = true;
+        } else if text == "-Xint" {
+            options.jit_mode = Some(JitMode::Interpreted);
+        } else if text == "-Xcomp" {
+            options.jit_mode = Some(JitMode::Compiled);
+        } else if let Some(size) = text.strip_prefix("-Xmx") {
+            let size = args::parse_size(size).ok_or_else(|| {
+                eprintln!("Invalid maximum heap size: {}", text);
+                JNI_EINVAL
+            })?;
+            options.max_heap_size = Some(usize::try_from(size).unwrap_or(usize::MAX));
+        } else if !options.assertions.apply_option(&text)
+            && !is_ignored_option(&text)
+            && args.ignoreUnrecognized == 0
+        {
+            eprintln!("Unrecognized option: {}", text);
+            return Err(JNI_ERR);
+        }
+    }
+    Ok(options)
+}
+
+/// Creates the VM and attaches the calling thread to it. One VM may exist
+/// per process.
+///
+/// # Safety
+/// `pvm` and `penv` must be writable and `args` must point to a
+/// `JavaVMInitArgs` whose options are NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn JNI_CreateJavaVM(
+    pvm: *mut *mut JavaVM,
+    penv: *mut *mut c_void,
+    args: *mut c_void,
+) -> jint {
+    let Some(args) = (args as *const JavaVMInitArgs).as_ref() else {
+        return JNI_EINVAL;
+    };
+    if args.version == JNI_VERSION_1_1 || !is_supported_version(args.version) {
+        return JNI_EVERSION;
+    }
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+
+    let mut state = STATE.lock().unwrap();
+    let state = state.get_or_insert_with(VmState::default);
+    if state.hosted.is_some() {
+        return JNI_EEXIST;
+    }
+    if state.destroyed || state.attached.is_some() {
+        return JNI_ERR;
+    }
+
+    let class_path = options
+        .class_path
+        .or_else(|| std::env::var("CLASSPATH").ok())
+        .unwrap_or_else(|| ".".to_string());
+    let mut loader = ClassLoader::new();
+    loader.set_verify(!options.skip_verification);
+    for entry in std::env::split_paths(&class_path) {
+        if !entry.as_os_str().is_empty() {
+            loader.add_classpath(entry);
+        }
+    }
+    let interpreter = Interpreter::new(false);
+    if let Some(mode) = options.jit_mode {
+        interpreter.set_jit_mode(mode);
+    }
+    interpreter.set_assertion_status(options.assertions);
+    interpreter.set_property("java.class.path", &class_path);
+    for (name, value) in &options.properties {
+        interpreter.set_property(name, value);
+    }
+
+    let interpreter = Box::into_raw(Box::new(interpreter));
+    let loader = Box::into_raw(Box::new(loader));
+    let heap = options
+        .max_heap_size
+        .map_or_else(Heap::new, Heap::with_max_size);
+    let heap = Box::into_raw(Box::new(heap));
+    let native = Box::into_raw(Box::new(NativeEnv {
+        interpreter: &*interpreter,
+        loader: &mut *loader,
+        heap: &mut *heap,
+    }));
+    state.hosted = Some(HostedVm {
+        interpreter,
+        loader,
+        heap,
+        native,
+    });
+    state.attached = Some(thread::current().id());
+    let env = enter(native);
+    HOST_ENV.with(|host| host.set(Some(env)));
+
+    *pvm = java_vm();
+    *penv = env as *mut c_void;
+    JNI_OK
+}
+
+/// Reports whether the requested JNI version can be created.
+///
+/// # Safety
+/// `args` must point to a `JavaVMInitArgs`.
+#[no_mangle]
+pub unsafe extern "C" fn JNI_GetDefaultJavaVMInitArgs(args: *mut c_void) -> jint {
+    match (args as *const JavaVMInitArgs).as_ref() {
+        Some(args) if args.version != JNI_VERSION_1_1 && is_supported_version(args.version) => {
+            JNI_OK
+        }
+        Some(_) => JNI_EVERSION,
+        None => JNI_EINVAL,
+    }
+}
+
+/// Writes the hosted VM, if any, to `vm_buf` and the count to `n_vms`.
+///
+/// # Safety
+/// `vm_buf` must have room for `buf_len` entries; `n_vms` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn JNI_GetCreatedJavaVMs(
+    vm_buf: *mut *mut JavaVM,
+    buf_len: jsize,
+    n_vms: *mut jsize,
+) -> jint {
+    let count = with_state(|state| jsize::from(state.hosted.is_some()));
+    if count > 0 && buf_len > 0 && !vm_buf.is_null() {
+        *vm_buf = java_vm();
+    }
+    if !n_vms.is_null() {
+        *n_vms = count;
+    }
+    JNI_OK
+}
@@ -0,0 +1,195 @@
+//! Shared libraries loaded by `System.load` and `System.loadLibrary`, and
+//! the lookup of `Java_*` symbols in them.
+
+use super::{call, invocation, is_supported_version, jint, with_env};
+use crate::native::registry::NativeMethod;
+use crate::native::NativeEnv;
+use std::cell::RefCell;
+use std::ffi::{c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// One `dlopen`ed library.
+struct NativeLibrary {
+    path: String,
+    handle: *mut c_void,
+}
+
+/// The libraries loaded so far, searched in load order.
+#[derive(Default)]
+pub struct NativeLibraries {
+    loaded: RefCell<Vec<NativeLibrary>>,
+}
+
+impl NativeLibraries {
+    pub fn is_loaded(&self, path: &str) -> bool {
+        self.loaded.borrow().iter().any(|lib| lib.path == path)
+    }
+
+    /// Finds the implementation of a `native` method: the short symbol
+    /// name in every library first, then the long, overload-qualified one.
+    pub fn find_method(
+        &self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+    ) -> Option<*const c_void> {
+        let short = short_name(class_name, method_name);
+        let long = long_name(class_name, method_name, descriptor);
+        self.find_symbol(&short).or_else(|| self.find_symbol(&long))
+    }
+
+    fn find_symbol(&self, symbol: &str) -> Option<*const c_void> {
+        let symbol = CString::new(symbol).ok()?;
+        self.loaded.borrow().iter().find_map(|lib| {
+            // SAFETY: `handle` is a live `dlopen` handle.
+            let address = unsafe { libc::dlsym(lib.handle, symbol.as_ptr()) };
+            (!address.is_null()).then_some(address as *const c_void)
+        })
+    }
+}
+
+/// Wraps a JNI implementation as a registry binding.
+///
+/// # Safety
+/// `function` must implement a method with `descriptor` as its JNI
+/// signature.
+pub unsafe fn bind(
+    function: *const c_void,
+    class_name: &str,
+    descriptor: &str,
+    is_static: bool,
+) -> NativeMethod {
+    let class_name = class_name.to_string();
+    let descriptor = descriptor.to_string();
+    // SAFETY: upheld by the caller.
+    Rc::new(move |env, receiver, args| unsafe {
+        call::invoke(
+            env,
+            function,
+            &class_name,
+            &descriptor,
+            is_static,
+            receiver,
+            args,
+        )
+    })
+}
+
+/// `System.mapLibraryName`.
+pub fn map_library_name(name: &str) -> String {
+    if cfg!(target_os = "macos") {
+        format!("lib{}.dylib", name)
+    } else if cfg!(windows) {
+        format!("{}.dll", name)
+    } else {
+        format!("lib{}.so", name)
+    }
+}
+
+/// The first `java.library.path` entry holding the mapped library name.
+pub fn find_library(name: &str, library_path: &str) -> Option<PathBuf> {
+    let file_name = map_library_name(name);
+    std::env::split_paths(library_path)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Loads the library at `path` and runs its `JNI_OnLoad`. Loading a library
+/// twice is a no-op. `Err` carries the `UnsatisfiedLinkError` message; an
+/// exception thrown by `JNI_OnLoad` is left pending and the library is not
+/// kept.
+pub fn load(env: &mut NativeEnv, path: &Path) -> Result<(), String> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let name = canonical.to_string_lossy().to_string();
+    if env.interpreter.native_libraries().is_loaded(&name) {
+        return Ok(());
+    }
+    if !canonical.is_file() {
+        return Err(format!("Can't load library: {}", path.display()));
+    }
+    let c_path = CString::new(name.clone()).map_err(|e| e.to_string())?;
+    // SAFETY: `c_path` is NUL-terminated.
+    let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_LAZY) };
+    if handle.is_null() {
+        return Err(format!("{}: {}", name, dl_error()));
+    }
+
+    // SAFETY: `handle` was just returned by `dlopen`.
+    let on_load = unsafe { libc::dlsym(handle, c"JNI_OnLoad".as_ptr()) };
+    if !on_load.is_null() {
+        // SAFETY: `JNI_OnLoad` has the signature fixed by the specification.
+        let on_load: unsafe extern "C" fn(*mut invocation::JavaVM, *mut c_void) -> jint =
+            unsafe { std::mem::transmute(on_load) };
+        let version = with_env(env, |_| unsafe {
+            on_load(invocation::java_vm(), std::ptr::null_mut())
+        });
+        if env.interpreter.pending_exception().is_some() {
+            // SAFETY: nothing from the library has been bound yet.
+            unsafe { libc::dlclose(handle) };
+            return Ok(());
+        }
+        if !is_supported_version(version) {
+            // SAFETY: as above.
+            unsafe { libc::dlclose(handle) };
+            return Err(format!(
+                "unsupported JNI version 0x{:x} required by {}",
+                version, name
+            ));
+        }
+    }
+
+    env.interpreter
+        .native_libraries()
+        .loaded
+        .borrow_mut()
+        .push(NativeLibrary { path: name, handle });
+    Ok(())
+}
+
+fn dl_error() -> String {
+    // SAFETY: `dlerror` returns null or a NUL-terminated message.
+    let message = unsafe { libc::dlerror() };
+    if message.is_null() {
+        return "unknown error".to_string();
+    }
+    unsafe { CStr::from_ptr(message) }
+        .to_string_lossy()
+        .to_string()
+}
+
+/// `Java_<class>_<method>`.
+pub fn short_name(class_name: &str, method_name: &str) -> String {
+    format!("Java_{}_{}", mangle(class_name), mangle(method_name))
+}
+
+/// `Java_<class>_<method>__<parameter descriptors>`, used for overloads.
+pub fn long_name(class_name: &str, method_name: &str, descriptor: &str) -> String {
+    let params = descriptor
+        .strip_prefix('(')
+        .and_then(|rest| rest.split(')').next())
+        .unwrap_or("");
+    format!(
+        "{}__{}",
+        short_name(class_name, method_name),
+        mangle(params)
+    )
+}
+
+/// The JNI name mangling: `/` becomes `_`, `_`, `;` and `[` become `_1`,
+/// `_2` and `_3`, and any other non-alphanumeric UTF-16 unit `_0xxxx`.
+pub fn mangle(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for unit in name.encode_utf16() {
+        match char::from_u32(u32::from(unit)) {
+            Some('/') => out.push('_'),
+            Some('_') => out.push_str("_1"),
+            Some(';') => out.push_str("_2"),
+            Some('[') => out.push_str("_3"),
+            Some(c) if c.is_ascii_alphanumeric() => out.push(c),
+            _ => out.push_str(&format!("_0{:04x}", unit)),
+        }
+    }
+    out
+}
@@ -0,0 +1,442 @@
+//! The Java Native Interface: loading shared libraries, binding their
+//! `Java_*` symbols to `native` methods and the `JNIEnv` function table
+//! those libraries call back into.
+//!
+//! A `JNIEnv*` handed to native code points at a [`JniContext`] whose first
+//! field is the function table. Object references crossing the boundary are
+//! handles into the context's local table or the process-wide global table;
+//! `jclass` handles wrap the class's internal name, as `ldc` does for class
+//! constants.
+
+pub mod call;
+pub mod env;
+pub mod invocation;
+pub mod library;
+
+use crate::native::{java_lang_class, NativeEnv};
+use crate::runtime::gc;
+use crate::runtime::heap::{Heap, HeapValue};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CString};
+use std::sync::Mutex;
+
+#[allow(non_camel_case_types)]
+pub type jint = i32;
+#[allow(non_camel_case_types)]
+pub type jsize = jint;
+#[allow(non_camel_case_types)]
+pub type jlong = i64;
+#[allow(non_camel_case_types)]
+pub type jboolean = u8;
+#[allow(non_camel_case_types)]
+pub type jbyte = i8;
+#[allow(non_camel_case_types)]
+pub type jchar = u16;
+#[allow(non_camel_case_types)]
+pub type jshort = i16;
+#[allow(non_camel_case_types)]
+pub type jfloat = f32;
+#[allow(non_camel_case_types)]
+pub type jdouble = f64;
+#[allow(non_camel_case_types)]
+pub type jobject = *mut c_void;
+#[allow(non_camel_case_types)]
+pub type jclass = jobject;
+#[allow(non_camel_case_types)]
+pub type jmethodID = *mut JniMethod;
+#[allow(non_camel_case_types)]
+pub type jfieldID = *mut JniField;
+
+/// `JNIEnv` as C sees it: a pointer to the function table.
+pub type JNIEnv = *const env::JNINativeInterface;
+
+pub const JNI_OK: jint = 0;
+pub const JNI_ERR: jint = -1;
+pub const JNI_EDETACHED: jint = -2;
+pub const JNI_EVERSION: jint = -3;
+pub const JNI_ENOMEM: jint = -4;
+pub const JNI_EEXIST: jint = -5;
+pub const JNI_EINVAL: jint = -6;
+
+pub const JNI_FALSE: jboolean = 0;
+pub const JNI_TRUE: jboolean = 1;
+
+pub const JNI_COMMIT: jint = 1;
+pub const JNI_ABORT: jint = 2;
+
+pub const JNI_VERSION_1_1: jint = 0x0001_0001;
+pub const JNI_VERSION_1_2: jint = 0x0001_0002;
+pub const JNI_VERSION_1_4: jint = 0x0001_0004;
+pub const JNI_VERSION_1_6: jint = 0x0001_0006;
+pub const JNI_VERSION_1_8: jint = 0x0001_0008;
+pub const JNI_VERSION_9: jint = 0x0009_0000;
+pub const JNI_VERSION_10: jint = 0x000a_0000;
+pub const JNI_VERSION_11: jint = 0x000b_0000;
+pub const JNI_VERSION_17: jint = 0x0011_0000;
+
+/// Versions a library may ask for from `JNI_OnLoad` or `GetEnv`.
+pub fn is_supported_version(version: jint) -> bool {
+    matches!(
+        version,
+        JNI_VERSION_1_1
+            | JNI_VERSION_1_2
+            | JNI_VERSION_1_4
+            | JNI_VERSION_1_6
+            | JNI_VERSION_1_8
+            | JNI_VERSION_9
+            | JNI_VERSION_10
+            | JNI_VERSION_11
+            | JNI_VERSION_17
+    )
+}
+
+/// `union jvalue`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union JValue {
+    pub z: jboolean,
+    pub b: jbyte,
+    pub c: jchar,
+    pub s: jshort,
+    pub i: jint,
+    pub j: jlong,
+    pub f: jfloat,
+    pub d: jdouble,
+    pub l: jobject,
+}
+
+/// What a `jmethodID` points to. `varargs.c` reads `signature` to decode
+/// variadic arguments, so it must stay the first field.
+#[repr(C)]
+pub struct JniMethod {
+    signature: *const c_char,
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+    pub is_static: bool,
+}
+
+/// What a `jfieldID` points to. Static fields record their declaring class.
+pub struct JniField {
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+    pub is_static: bool,
+}
+
+type MemberKey = (String, String, String, bool);
+
+/// Member IDs stay valid for the life of the process, so they are leaked
+/// and interned; the maps hold their addresses.
+static METHOD_IDS: Mutex<Option<HashMap<MemberKey, usize>>> = Mutex::new(None);
+static FIELD_IDS: Mutex<Option<HashMap<MemberKey, usize>>> = Mutex::new(None);
+
+pub fn method_id(class_name: &str, name: &str, descriptor: &str, is_static: bool) -> jmethodID {
+    let key = (
+        class_name.to_string(),
+        name.to_string(),
+        descriptor.to_string(),
+        is_static,
+    );
+    let mut ids = METHOD_IDS.lock().unwrap();
+    let address = *ids
+        .get_or_insert_with(HashMap::new)
+        .entry(key)
+        .or_insert_with(|| {
+            let signature = CString::new(descriptor).unwrap_or_default().into_raw();
+            Box::into_raw(Box::new(JniMethod {
+                signature,
+                class_name: class_name.to_string(),
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+                is_static,
+            })) as usize
+        });
+    address as jmethodID
+}
+
+pub fn field_id(class_name: &str, name: &str, descriptor: &str, is_static: bool) -> jfieldID {
+    let key = (
+        class_name.to_string(),
+        name.to_string(),
+        descriptor.to_string(),
+        is_static,
+    );
+    let mut ids = FIELD_IDS.lock().unwrap();
+    let address = *ids
+        .get_or_insert_with(HashMap::new)
+        .entry(key)
+        .or_insert_with(|| {
+            Box::into_raw(Box::new(JniField {
+                class_name: class_name.to_string(),
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+                is_static,
+            })) as usize
+        });
+    address as jfieldID
+}
+
+/// The state behind one `JNIEnv*`: the function table, the VM services of
+/// the native call in progress and its local references.
+#[repr(C)]
+pub struct JniContext {
+    functions: *const env::JNINativeInterface,
+    native: *mut NativeEnv<'static>,
+    locals: Vec<Option<HeapValue>>,
+    /// Local table lengths saved by `PushLocalFrame`.
+    frames: Vec<usize>,
+}
+
+thread_local! {
+    /// Contexts of the native calls active on this thread, innermost last.
+    static CONTEXTS: RefCell<Vec<*mut JniContext>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `f` with a fresh `JNIEnv*` for `native`. Local references created
+/// through it are released when `f` returns.
+pub fn with_env<R>(native: &mut NativeEnv, f: impl FnOnce(*mut JNIEnv) -> R) -> R {
+    let env = enter(native);
+    let result = f(env);
+    // SAFETY: native code may not keep a `JNIEnv*` beyond the call it was
+    // passed to.
+    unsafe { leave(env) };
+    result
+}
+
+/// Creates a `JNIEnv*` for `native` and makes it this thread's innermost
+/// one. It stays valid until the matching [`leave`].
+pub(crate) fn enter(native: *mut NativeEnv) -> *mut JNIEnv {
+    let context = Box::into_raw(Box::new(JniContext {
+        functions: env::function_table(),
+        native: native.cast::<NativeEnv<'static>>(),
+        locals: Vec::new(),
+        frames: Vec::new(),
+    }));
+    CONTEXTS.with(|contexts| contexts.borrow_mut().push(context));
+    context as *mut JNIEnv
+}
+
+/// Releases a `JNIEnv*` from [`enter`] with all of its local references.
+///
+/// # Safety
+/// `env` must be this thread's innermost `JNIEnv*` and must not be used
+/// afterwards.
+pub(crate) unsafe fn leave(env: *mut JNIEnv) {
+    CONTEXTS.with(|contexts| contexts.borrow_mut().pop());
+    drop(Box::from_raw(env as *mut JniContext));
+}
+
+/// The innermost `JNIEnv*` on this thread, for `JavaVM::GetEnv`.
+pub fn current_env() -> Option<*mut JNIEnv> {
+    CONTEXTS.with(|contexts| contexts.borrow().last().map(|c| *c as *mut JNIEnv))
+}
+
+/// # Safety
+/// `env` must be a live `JNIEnv*` created by [`with_env`] or [`enter`].
+unsafe fn context<'a>(env: *mut JNIEnv) -> &'a mut JniContext {
+    &mut *(env as *mut JniContext)
+}
+
+/// The VM services behind `env`.
+///
+/// # Safety
+/// `env` must be a live `JNIEnv*` created by [`with_env`] or [`enter`].
+pub unsafe fn native<'a>(env: *mut JNIEnv) -> &'a mut NativeEnv<'static> {
+    &mut *context(env).native
+}
+
+const LOCAL_TAG: usize = 1;
+const GLOBAL_TAG: usize = 2;
+const WEAK_GLOBAL_TAG: usize = 3;
+
+struct GlobalRef {
+    value: HeapValue,
+    weak: bool,
+}
+
+static GLOBALS: Mutex<Vec<Option<GlobalRef>>> = Mutex::new(Vec::new());
+
+fn encode(index: usize, tag: usize) -> jobject {
+    (((index + 1) << 2) | tag) as jobject
+}
+
+fn decode(handle: jobject) -> Option<(usize, usize)> {
+    let raw = handle as usize;
+    (raw >> 2).checked_sub(1).map(|index| (index, raw & 3))
+}
+
+/// A new local reference to `value`; `null` for `HeapValue::Null`.
+///
+/// # Safety
+/// `env` must be a live `JNIEnv*` created by [`with_env`] or [`enter`].
+pub unsafe fn new_local(env: *mut JNIEnv, value: HeapValue) -> jobject {
+    if value.is_null() {
+        return std::ptr::null_mut();
+    }
+    let locals = &mut context(env).locals;
+    locals.push(Some(value));
+    encode(locals.len() - 1, LOCAL_TAG)
+}
+
+/// The value behind a local, global or weak global reference. Stale and
+/// invalid handles read as `null`.
+///
+/// # Safety
+/// `env` must be a live `JNIEnv*` created by [`with_env`] or [`enter`].
+pub unsafe fn resolve(env: *mut JNIEnv, handle: jobject) -> HeapValue {
+    let Some((index, tag)) = decode(handle) else {
+        return HeapValue::Null;
+    };
+    let value = match tag {
+        LOCAL_TAG => context(env).locals.get(index).cloned().flatten(),
+        GLOBAL_TAG | WEAK_GLOBAL_TAG => GLOBALS
+            .lock()
+            .unwrap()
+            .get(index)
+            .and_then(|slot| slot.as_ref().map(|global| global.value.clone())),
+        _ => None,
+    };
+    value.unwrap_or(HeapValue::Null)
+}
+
+/// The internal name a `jclass` handle refers to.
+///
+/// # Safety
+/// `env` must be a live `JNIEnv*` created by [`with_env`] or [`enter`].
+pub unsafe fn resolve_class(env: *mut JNIEnv, clazz: jclass) -> Option<String> {
+    java_lang_class::class_name(native(env).heap, &resolve(env, clazz))
+}
+
+pub(crate) unsafe fn delete_local(env: *mut JNIEnv, handle: jobject) {
+    if let Some((index, LOCAL_TAG)) = decode(handle) {
+        if let Some(slot) = context(env).locals.get_mut(index) {
+            *slot = None;
+        }
+    }
+}
+
+pub(crate) unsafe fn push_local_frame(env: *mut JNIEnv) {
+    let context = context(env);
+    context.frames.push(context.locals.len());
+}
+
+/// Releases every local created since the matching `push_local_frame`.
+/// `false` if no frame was pushed.
+pub(crate) unsafe fn pop_local_frame(env: *mut JNIEnv) -> bool {
+    let context = context(env);
+    match context.frames.pop() {
+        Some(mark) => {
+            context.locals.truncate(mark);
+            true
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn new_global(value: HeapValue, weak: bool) -> jobject {
+    if value.is_null() {
+        return std::ptr::null_mut();
+    }
+    let mut globals = GLOBALS.lock().unwrap();
+    let slot = Some(GlobalRef { value, weak });
+    let index = match globals.iter().position(Option::is_none) {
+        Some(free) => {
+            globals[free] = slot;
+            free
+        }
+        None => {
+            globals.push(slot);
+            globals.len() - 1
+        }
+    };
+    encode(index, if weak { WEAK_GLOBAL_TAG } else { GLOBAL_TAG })
+}
+
+pub(crate) fn delete_global(handle: jobject, weak: bool) {
+    let expected = if weak { WEAK_GLOBAL_TAG } else { GLOBAL_TAG };
+    if let Some((index, tag)) = decode(handle) {
+        if tag == expected {
+            let mut globals = GLOBALS.lock().unwrap();
+            if let Some(slot) = globals.get_mut(index) {
+                if slot.as_ref().is_some_and(|global| global.weak == weak) {
+                    *slot = None;
+                }
+            }
+        }
+    }
+}
+
+/// `jobjectRefType` of a handle: 1 local, 2 global, 3 weak global, 0 if
+/// it names nothing.
+pub(crate) unsafe fn ref_type(env: *mut JNIEnv, handle: jobject) -> jint {
+    match decode(handle) {
+        Some((_, tag)) if !resolve(env, handle).is_null() => tag as jint,
+        _ => 0,
+    }
+}
+
+/// Splits a method descriptor into the kind of each parameter and of the
+/// return value: the primitive descriptor character, `L` for every
+/// reference type (arrays included) and `V` for `void`.
+pub fn signature_kinds(descriptor: &str) -> (Vec<u8>, u8) {
+    let bytes = descriptor.as_bytes();
+    let mut kinds = Vec::new();
+    let mut pos = usize::from(bytes.first() == Some(&b'('));
+    let next = |pos: &mut usize| -> u8 {
+        let start = *pos;
+        while bytes.get(*pos) == Some(&b'[') {
+            *pos += 1;
+        }
+        let tag = bytes.get(*pos).copied().unwrap_or(b'V');
+        if tag == b'L' {
+            while *pos < bytes.len() && bytes[*pos] != b';' {
+                *pos += 1;
+            }
+        }
+        *pos += 1;
+        if *pos - start > 1 || tag == b'L' {
+            b'L'
+        } else {
+            tag
+        }
+    };
+    while pos < bytes.len() && bytes[pos] != b')' {
+        kinds.push(next(&mut pos));
+    }
+    pos += 1;
+    let ret = next(&mut pos);
+    (kinds, ret)
+}
+
+/// Adds the objects held by this thread's local references and by strong
+/// global references to the collector's `roots`.
+pub(crate) fn add_roots(roots: &mut Vec<u64>) {
+    CONTEXTS.with(|contexts| {
+        for &context in contexts.borrow().iter() {
+            // SAFETY: contexts stay alive while they are on the list.
+            let locals = unsafe { &(*context).locals };
+            roots.extend(locals.iter().flatten().filter_map(gc::reference_id));
+        }
+    });
+    let globals = GLOBALS.lock().unwrap();
+    roots.extend(
+        globals
+            .iter()
+            .flatten()
+            .filter(|global| !global.weak)
+            .filter_map(|global| gc::reference_id(&global.value)),
+    );
+}
+
+/// Clears weak global references whose object was collected; the handles
+/// stay valid and read as `null` until deleted.
+pub(crate) fn clear_dead_weak_globals(heap: &Heap) {
+    let mut globals = GLOBALS.lock().unwrap();
+    for global in globals.iter_mut().flatten().filter(|global| global.weak) {
+        if gc::reference_id(&global.value).is_some_and(|id| !heap.is_live(id)) {
+            global.value = HeapValue::Null;
+        }
+    }
+}
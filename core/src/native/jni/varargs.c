@@ -0,0 +1,217 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Variadic and va_list JNI entry points. Each one decodes its arguments
+ * against the method descriptor into a jvalue array and forwards to the
+ * matching `...A` function of the same table, which the VM implements.
+ */
+
+#include "jni.h"
+
+#define ARIA_MAX_ARGS 256
+
+/* The VM lays out every jmethodID with a pointer to its NUL-terminated JVM
+ * descriptor as the first member. */
+static const char* method_descriptor(jmethodID method) {
+    return *(const char* const*)method;
+}
+
+static void collect_args(jmethodID method, va_list args, jvalue* out) {
+    const char* p = method_descriptor(method);
+    int count = 0;
+    if (*p == '(') {
+        p++;
+    }
+    while (*p && *p != ')' && count < ARIA_MAX_ARGS) {
+        switch (*p) {
+        case 'Z':
+            out[count].z = (jboolean)va_arg(args, int);
+            break;
+        case 'B':
+            out[count].b = (jbyte)va_arg(args, int);
+            break;
+        case 'C':
+            out[count].c = (jchar)va_arg(args, int);
+            break;
+        case 'S':
+            out[count].s = (jshort)va_arg(args, int);
+            break;
+        case 'I':
+            out[count].i = va_arg(args, jint);
+            break;
+        case 'J':
+            out[count].j = va_arg(args, jlong);
+            break;
+        case 'F':
+            out[count].f = (jfloat)va_arg(args, double);
+            break;
+        case 'D':
+            out[count].d = va_arg(args, double);
+            break;
+        case '[':
+            while (*p == '[') {
+                p++;
+            }
+            if (*p == 'L') {
+                while (*p && *p != ';') {
+                    p++;
+                }
+            }
+            out[count].l = va_arg(args, jobject);
+            break;
+        case 'L':
+            while (*p && *p != ';') {
+                p++;
+            }
+            out[count].l = va_arg(args, jobject);
+            break;
+        default:
+            return;
+        }
+        if (*p) {
+            p++;
+        }
+        count++;
+    }
+}
+
+#define ARIA_CALL_FAMILY(Type, jtype)                                                            \
+    static jtype JNICALL aria_Call##Type##MethodV(JNIEnv* env, jobject obj, jmethodID method,     \
+                                                  va_list args) {                                \
+        jvalue values[ARIA_MAX_ARGS];                                                            \
+        collect_args(method, args, values);                                                      \
+        return (*env)->Call##Type##MethodA(env, obj, method, values);                            \
+    }                                                                                            \
+    static jtype JNICALL aria_Call##Type##Method(JNIEnv* env, jobject obj, jmethodID method,      \
+                                                 ...) {                                          \
+        va_list args;                                                                            \
+        jtype result;                                                                            \
+        va_start(args, method);                                                                  \
+        result = aria_Call##Type##MethodV(env, obj, method, args);                               \
+        va_end(args);                                                                            \
+        return result;                                                                           \
+    }                                                                                            \
+    static jtype JNICALL aria_CallNonvirtual##Type##MethodV(JNIEnv* env, jobject obj,            \
+                                                            jclass clazz, jmethodID method,      \
+                                                            va_list args) {                      \
+        jvalue values[ARIA_MAX_ARGS];                                                            \
+        collect_args(method, args, values);                                                      \
+        return (*env)->CallNonvirtual##Type##MethodA(env, obj, clazz, method, values);           \
+    }                                                                                            \
+    static jtype JNICALL aria_CallNonvirtual##Type##Method(JNIEnv* env, jobject obj,             \
+                                                           jclass clazz, jmethodID method, ...) { \
+        va_list args;                                                                            \
+        jtype result;                                                                            \
+        va_start(args, method);                                                                  \
+        result = aria_CallNonvirtual##Type##MethodV(env, obj, clazz, method, args);              \
+        va_end(args);                                                                            \
+        return result;                                                                           \
+    }                                                                                            \
+    static jtype JNICALL aria_CallStatic##Type##MethodV(JNIEnv* env, jclass clazz,               \
+                                                        jmethodID method, va_list args) {        \
+        jvalue values[ARIA_MAX_ARGS];                                                            \
+        collect_args(method, args, values);                                                      \
+        return (*env)->CallStatic##Type##MethodA(env, clazz, method, values);                    \
+    }                                                                                            \
+    static jtype JNICALL aria_CallStatic##Type##Method(JNIEnv* env, jclass clazz,                \
+                                                       jmethodID method, ...) {                  \
+        va_list args;                                                                            \
+        jtype result;                                                                            \
+        va_start(args, method);                                                                  \
+        result = aria_CallStatic##Type##MethodV(env, clazz, method, args);                       \
+        va_end(args);                                                                            \
+        return result;                                                                           \
+    }
+
+ARIA_CALL_FAMILY(Object, jobject)
+ARIA_CALL_FAMILY(Boolean, jboolean)
+ARIA_CALL_FAMILY(Byte, jbyte)
+ARIA_CALL_FAMILY(Char, jchar)
+ARIA_CALL_FAMILY(Short, jshort)
+ARIA_CALL_FAMILY(Int, jint)
+ARIA_CALL_FAMILY(Long, jlong)
+ARIA_CALL_FAMILY(Float, jfloat)
+ARIA_CALL_FAMILY(Double, jdouble)
+
+static void JNICALL aria_CallVoidMethodV(JNIEnv* env, jobject obj, jmethodID method,
+                                         va_list args) {
+    jvalue values[ARIA_MAX_ARGS];
+    collect_args(method, args, values);
+    (*env)->CallVoidMethodA(env, obj, method, values);
+}
+
+static void JNICALL aria_CallVoidMethod(JNIEnv* env, jobject obj, jmethodID method, ...) {
+    va_list args;
+    va_start(args, method);
+    aria_CallVoidMethodV(env, obj, method, args);
+    va_end(args);
+}
+
+static void JNICALL aria_CallNonvirtualVoidMethodV(JNIEnv* env, jobject obj, jclass clazz,
+                                                   jmethodID method, va_list args) {
+    jvalue values[ARIA_MAX_ARGS];
+    collect_args(method, args, values);
+    (*env)->CallNonvirtualVoidMethodA(env, obj, clazz, method, values);
+}
+
+static void JNICALL aria_CallNonvirtualVoidMethod(JNIEnv* env, jobject obj, jclass clazz,
+                                                  jmethodID method, ...) {
+    va_list args;
+    va_start(args, method);
+    aria_CallNonvirtualVoidMethodV(env, obj, clazz, method, args);
+    va_end(args);
+}
+
+static void JNICALL aria_CallStaticVoidMethodV(JNIEnv* env, jclass clazz, jmethodID method,
+                                               va_list args) {
+    jvalue values[ARIA_MAX_ARGS];
+    collect_args(method, args, values);
+    (*env)->CallStaticVoidMethodA(env, clazz, method, values);
+}
+
+static void JNICALL aria_CallStaticVoidMethod(JNIEnv* env, jclass clazz, jmethodID method, ...) {
+    va_list args;
+    va_start(args, method);
+    aria_CallStaticVoidMethodV(env, clazz, method, args);
+    va_end(args);
+}
+
+static jobject JNICALL aria_NewObjectV(JNIEnv* env, jclass clazz, jmethodID method,
+                                       va_list args) {
+    jvalue values[ARIA_MAX_ARGS];
+    collect_args(method, args, values);
+    return (*env)->NewObjectA(env, clazz, method, values);
+}
+
+static jobject JNICALL aria_NewObject(JNIEnv* env, jclass clazz, jmethodID method, ...) {
+    va_list args;
+    jobject result;
+    va_start(args, method);
+    result = aria_NewObjectV(env, clazz, method, args);
+    va_end(args);
+    return result;
+}
+
+#define ARIA_INSTALL_FAMILY(Type)                                          \
+    table->Call##Type##Method = aria_Call##Type##Method;                   \
+    table->Call##Type##MethodV = aria_Call##Type##MethodV;                 \
+    table->CallNonvirtual##Type##Method = aria_CallNonvirtual##Type##Method; \
+    table->CallNonvirtual##Type##MethodV = aria_CallNonvirtual##Type##MethodV; \
+    table->CallStatic##Type##Method = aria_CallStatic##Type##Method;       \
+    table->CallStatic##Type##MethodV = aria_CallStatic##Type##MethodV;
+
+/* Called once by the VM while it builds the function table. */
+void aria_jni_install_varargs(struct JNINativeInterface_* table) {
+    ARIA_INSTALL_FAMILY(Object)
+    ARIA_INSTALL_FAMILY(Boolean)
+    ARIA_INSTALL_FAMILY(Byte)
+    ARIA_INSTALL_FAMILY(Char)
+    ARIA_INSTALL_FAMILY(Short)
+    ARIA_INSTALL_FAMILY(Int)
+    ARIA_INSTALL_FAMILY(Long)
+    ARIA_INSTALL_FAMILY(Float)
+    ARIA_INSTALL_FAMILY(Double)
+    ARIA_INSTALL_FAMILY(Void)
+    table->NewObject = aria_NewObject;
+    table->NewObjectV = aria_NewObjectV;
+}
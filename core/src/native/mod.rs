@@ -1,24 +1,134 @@
+pub mod fdlibm;
+pub mod java_io_inputstream;
 pub mod java_io_printstream;
+pub mod java_lang_annotation;
+pub mod java_lang_boxing;
+pub mod java_lang_class;
+pub mod java_lang_classloader;
+pub mod java_lang_enum;
+pub mod java_lang_invoke;
+pub mod java_lang_invoke_varhandle;
 pub mod java_lang_math;
+pub mod java_lang_object;
+pub mod java_lang_reflect;
+pub mod java_lang_reflect_proxy;
+pub mod java_lang_runtime;
 pub mod java_lang_system;
+pub mod java_lang_thread;
+pub mod java_lang_throwable;
+pub mod java_util_formatter;
+pub mod jni;
+pub mod registry;
 
+use crate::exec::interpreter::Interpreter;
+use crate::loader::class_loader::ClassLoader;
 use crate::runtime::heap::{Heap, HeapValue};
 
-pub fn invoke_native(
-    class_name: &str,
-    method_name: &str,
-    descriptor: &str,
-    receiver: Option<HeapValue>,
-    args: &[HeapValue],
-    heap: &mut Heap,
-) -> Option<Option<HeapValue>> {
+/// VM services handed to natives that need more than the heap, e.g. to call
+/// back into Java for `toString()`.
+pub struct NativeEnv<'a> {
+    pub interpreter: &'a Interpreter,
+    pub loader: &'a mut ClassLoader,
+    pub heap: &'a mut Heap,
+}
+
+impl NativeEnv<'_> {
+    pub fn invoke_virtual(
+        &mut self,
+        receiver: &HeapValue,
+        method_name: &str,
+        descriptor: &str,
+        args: &[HeapValue],
+    ) -> Option<HeapValue> {
+        self.interpreter.invoke_virtual(
+            self.loader,
+            self.heap,
+            receiver,
+            method_name,
+            descriptor,
+            args,
+        )
+    }
+
+    /// `String.valueOf(Object)`: strings and boxes are rendered directly,
+    /// everything else goes through a virtual `toString()` call.
+    pub fn to_java_string(&mut self, value: &HeapValue) -> String {
+        match value {
+            HeapValue::Null => "null".to_string(),
+            HeapValue::String(s) => s.clone(),
+            HeapValue::Int(v) => v.to_string(),
+            HeapValue::Long(v) => v.to_string(),
+            HeapValue::Float(v) => java_lang_boxing::float_to_string(*v),
+            HeapValue::Double(v) => java_lang_boxing::double_to_string(*v),
+            HeapValue::Object(obj) => {
+                if let Some(s) = self.heap.string_value(value) {
+                    return s;
+                }
+                if let Some(s) = java_lang_boxing::box_to_string(self.heap, obj) {
+                    return s;
+                }
+                let rendered = self.invoke_virtual(value, "toString", "()Ljava/lang/String;", &[]);
+                match rendered {
+                    Some(s) => self
+                        .heap
+                        .string_value(&s)
+                        .unwrap_or_else(|| "null".to_string()),
+                    None => java_lang_object::default_to_string(self.loader, obj),
+                }
+            }
+            HeapValue::Array(arr) => java_lang_object::array_to_string(arr),
+        }
+    }
+}
+
+/// Classes whose behaviour lives entirely in Rust and that therefore have no
+/// `.class` file on the classpath.
+pub fn is_builtin_class(class_name: &str) -> bool {
+    matches!(
+        class_name,
+        "java/lang/Object"
+            | "java/lang/Class"
+            | "java/lang/String"
+            | "java/lang/System"
+            | "java/lang/Runtime"
+            | "java/lang/Thread"
+            | "java/lang/Enum"
+            | "java/lang/Record"
+            | java_lang_boxing::VOID
+            | java_lang_classloader::CLASS_LOADER
+            | java_lang_reflect_proxy::PROXY
+            | "java/io/PrintStream"
+            | "java/io/InputStream"
+            | "java/io/BufferedInputStream"
+            | "java/lang/Math"
+            | "java/lang/StrictMath"
+    ) || is_builtin_interface(class_name)
+        || java_lang_boxing::is_box_class(class_name)
+        || java_lang_reflect::is_reflect_class(class_name)
+        || java_lang_annotation::is_annotation_class(class_name)
+        || java_lang_invoke::is_invoke_class(class_name)
+        || java_lang_throwable::is_throwable_class(class_name)
+}
+
+/// The builtin classes that are interfaces.
+pub fn is_builtin_interface(class_name: &str) -> bool {
+    matches!(
+        class_name,
+        "java/lang/Runnable"
+            | java_lang_annotation::ANNOTATION
+            | java_lang_reflect_proxy::INVOCATION_HANDLER
+    )
+}
+
+/// Static initialization for builtin classes, run once in place of `<clinit>`.
+pub fn initialize_builtin_class(env: &mut NativeEnv, class_name: &str) {
     match class_name {
-        "java/lang/Object" if method_name == "<init>" && descriptor == "()V" => Some(None),
-        "java/lang/System" => java_lang_system::invoke(method_name, descriptor, args),
-        "java/lang/Math" => java_lang_math::invoke(method_name, descriptor, args),
-        "java/io/PrintStream" => {
-            java_io_printstream::invoke(method_name, descriptor, receiver.as_ref(), args, heap)
+        "java/lang/System" => java_lang_system::initialize(env),
+        "java/lang/Runtime" => java_lang_runtime::initialize(env),
+        java_lang_classloader::CLASS_LOADER => java_lang_classloader::initialize(env),
+        name if java_lang_boxing::is_box_class(name) || name == java_lang_boxing::VOID => {
+            java_lang_boxing::initialize(env, name)
         }
-        _ => None,
+        _ => {}
     }
 }
@@ -0,0 +1,204 @@
+use crate::native::{
+    java_io_inputstream, java_io_printstream, java_lang_annotation, java_lang_boxing,
+    java_lang_class, java_lang_classloader, java_lang_enum, java_lang_invoke,
+    java_lang_invoke_varhandle, java_lang_math, java_lang_object, java_lang_reflect,
+    java_lang_reflect_proxy, java_lang_runtime, java_lang_system, java_lang_thread,
+    java_lang_throwable, NativeEnv,
+};
+use crate::runtime::heap::HeapValue;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A bound native method. It receives `this` for instance methods and the
+/// declared arguments, and returns the result (`None` for `void`). Natives
+/// signal Java exceptions through `env.interpreter.throw_new`.
+pub type NativeMethod =
+    Rc<dyn Fn(&mut NativeEnv, Option<&HeapValue>, &[HeapValue]) -> Option<HeapValue>>;
+
+/// A builtin native, bound through `register_all` or `register_builtin`.
+/// `None` means the call could not be carried out.
+pub type BuiltinMethod =
+    fn(&mut NativeEnv, Option<&HeapValue>, &[HeapValue]) -> Option<Option<HeapValue>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct NativeKey {
+    class_name: String,
+    method_name: String,
+    descriptor: String,
+}
+
+/// Natives keyed by `(class, name, descriptor)`. The VM fills it with the
+/// builtin class library at startup; embedders may add their own entries
+/// before running code.
+#[derive(Default, Clone)]
+pub struct NativeRegistry {
+    methods: HashMap<NativeKey, NativeMethod>,
+}
+
+impl NativeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry holding every builtin native.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        java_lang_object::register(&mut registry);
+        java_lang_system::register(&mut registry);
+        java_lang_runtime::register(&mut registry);
+        java_lang_thread::register(&mut registry);
+        java_lang_math::register(&mut registry);
+        java_lang_boxing::register(&mut registry);
+        java_lang_class::register(&mut registry);
+        java_lang_classloader::register(&mut registry);
+        java_lang_reflect::register(&mut registry);
+        java_lang_reflect_proxy::register(&mut registry);
+        java_lang_annotation::register(&mut registry);
+        java_lang_enum::register(&mut registry);
+        java_lang_invoke::register(&mut registry);
+        java_lang_invoke_varhandle::register(&mut registry);
+        java_lang_throwable::register(&mut registry);
+        java_io_printstream::register(&mut registry);
+        java_io_inputstream::register(&mut registry);
+        registry
+    }
+
+    /// Binds `class_name.method_name descriptor`, replacing any earlier
+    /// binding for the same key.
+    pub fn register<F>(&mut self, class_name: &str, method_name: &str, descriptor: &str, method: F)
+    where
+        F: Fn(&mut NativeEnv, Option<&HeapValue>, &[HeapValue]) -> Option<HeapValue> + 'static,
+    {
+        self.bind(class_name, method_name, descriptor, Rc::new(method));
+    }
+
+    /// Like `register`, for a method that is already shared.
+    pub fn bind(
+        &mut self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        method: NativeMethod,
+    ) {
+        self.methods.insert(
+            NativeKey {
+                class_name: class_name.to_string(),
+                method_name: method_name.to_string(),
+                descriptor: descriptor.to_string(),
+            },
+            method,
+        );
+    }
+
+    /// Drops every binding of `class_name`.
+    pub fn unregister_class(&mut self, class_name: &str) {
+        self.methods.retain(|key, _| key.class_name != class_name);
+    }
+
+    /// Binds every `(name, descriptor, function)` in `methods`.
+    pub fn register_all(&mut self, class_name: &str, methods: &[(&str, &str, BuiltinMethod)]) {
+        for &(method_name, descriptor, method) in methods {
+            self.register_builtin(class_name, method_name, descriptor, method);
+        }
+    }
+
+    /// Binds a builtin native. One returning `None` could not carry out
+    /// the call, which is a VM bug and surfaces as `InternalError`.
+    pub fn register_builtin<F>(
+        &mut self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        method: F,
+    ) where
+        F: Fn(&mut NativeEnv, Option<&HeapValue>, &[HeapValue]) -> Option<Option<HeapValue>>
+            + 'static,
+    {
+        let message = format!(
+            "native {}.{}{} failed",
+            class_name.replace('/', "."),
+            method_name,
+            descriptor
+        );
+        self.register(
+            class_name,
+            method_name,
+            descriptor,
+            move |env, receiver, args| match method(env, receiver, args) {
+                Some(result) => result,
+                None => {
+                    env.interpreter
+                        .throw_new(env.heap, "java/lang/InternalError", Some(&message));
+                    None
+                }
+            },
+        );
+    }
+
+    pub fn lookup(
+        &self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+    ) -> Option<NativeMethod> {
+        self.methods
+            .get(&NativeKey {
+                class_name: class_name.to_string(),
+                method_name: method_name.to_string(),
+                descriptor: descriptor.to_string(),
+            })
+            .cloned()
+    }
+}
+
+/// HotSpot's rendering of a method in `UnsatisfiedLinkError` messages,
+/// e.g. `'int Main.add(int, int)'`.
+pub fn describe_method(class_name: &str, method_name: &str, descriptor: &str) -> String {
+    let (params, ret) = descriptor
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .unwrap_or(("", descriptor));
+    let mut param_names = Vec::new();
+    let mut rest = params;
+    while !rest.is_empty() {
+        let (name, tail) = java_type_name(rest);
+        param_names.push(name);
+        rest = tail;
+    }
+    format!(
+        "'{} {}.{}({})'",
+        java_type_name(ret).0,
+        class_name.replace('/', "."),
+        method_name,
+        param_names.join(", ")
+    )
+}
+
+/// Splits one field descriptor off the front of `desc` and returns its
+/// Java source spelling with the remainder.
+fn java_type_name(desc: &str) -> (String, &str) {
+    let Some(tag) = desc.chars().next() else {
+        return (String::new(), desc);
+    };
+    let primitive = match tag {
+        'B' => "byte",
+        'C' => "char",
+        'D' => "double",
+        'F' => "float",
+        'I' => "int",
+        'J' => "long",
+        'S' => "short",
+        'Z' => "boolean",
+        'V' => "void",
+        'L' => {
+            let end = desc.find(';').unwrap_or(desc.len() - 1);
+            return (desc[1..end].replace('/', "."), &desc[end + 1..]);
+        }
+        '[' => {
+            let (component, rest) = java_type_name(&desc[1..]);
+            return (format!("{}[]", component), rest);
+        }
+        _ => return (desc.to_string(), ""),
+    };
+    (primitive.to_string(), &desc[1..])
+}
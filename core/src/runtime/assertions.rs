@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+/// Which classes run their `assert` statements, as set by `-ea`, `-da`,
+/// `-esa` and `-dsa`. A setting for the class itself wins over one for
+/// its package, a package over its enclosing packages, and those over the
+/// default, as in `ClassLoader.desiredAssertionStatus`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssertionStatus {
+    default: bool,
+    system: bool,
+    /// By dotted package name; `""` is the unnamed package.
+    packages: HashMap<String, bool>,
+    /// By binary name.
+    classes: HashMap<String, bool>,
+}
+
+impl AssertionStatus {
+    /// Applies one launcher option. Returns false if `option` is not an
+    /// assertion option, leaving the status untouched.
+    pub fn apply_option(&mut self, option: &str) -> bool {
+        let (flag, scope) = option.split_once(':').unwrap_or((option, ""));
+        let enabled = match flag {
+            "-ea" | "-enableassertions" => true,
+            "-da" | "-disableassertions" => false,
+            "-esa" | "-enablesystemassertions" if scope.is_empty() => {
+                self.system = true;
+                return true;
+            }
+            "-dsa" | "-disablesystemassertions" if scope.is_empty() => {
+                self.system = false;
+                return true;
+            }
+            _ => return false,
+        };
+        if scope.is_empty() && !option.contains(':') {
+            self.default = enabled;
+        } else if let Some(package) = scope.strip_suffix("...") {
+            self.packages.insert(package.to_string(), enabled);
+        } else {
+            self.classes.insert(scope.to_string(), enabled);
+        }
+        true
+    }
+
+    /// Whether assertions are enabled for the class, given by binary or
+    /// internal name. Nested classes follow their top-level class.
+    pub fn desired_status(&self, class_name: &str) -> bool {
+        let name = class_name.replace('/', ".");
+        let top_level = name.split('$').next().unwrap_or(&name);
+        if let Some(&enabled) = self.classes.get(top_level) {
+            return enabled;
+        }
+        let mut package = top_level
+            .rsplit_once('.')
+            .map_or("", |(package, _)| package);
+        loop {
+            if let Some(&enabled) = self.packages.get(package) {
+                return enabled;
+            }
+            // `-ea:...` is the unnamed package alone, not a catch-all.
+            match package.rsplit_once('.') {
+                Some((outer, _)) => package = outer,
+                None => break,
+            }
+        }
+        if is_system_class(top_level) {
+            self.system
+        } else {
+            self.default
+        }
+    }
+}
+
+fn is_system_class(name: &str) -> bool {
+    ["java.", "javax.", "jdk.", "sun."]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
@@ -12,12 +12,14 @@ impl Gc {
         Self { debug_mode }
     }
 
-    pub fn collect(&self, heap: &mut Heap, stack: &Stack) {
+    /// Frees every object and array that neither `roots`, given as heap
+    /// ids, nor the interned strings reach.
+    pub fn collect(&self, heap: &mut Heap, roots: &[u64]) {
         if self.debug_mode {
             println!("Starting GC (Mark-Sweep) ...");
         }
 
-        let marked = self.mark_from_stack(heap, stack);
+        let marked = self.mark(heap, roots);
 
         let before = heap.object_count();
         heap.retain_alive(&marked);
@@ -33,15 +35,21 @@ impl Gc {
         }
     }
 
-    fn mark_from_stack(&self, heap: &Heap, stack: &Stack) -> HashSet<u64> {
+    /// Marks with a work list rather than recursion, so long linked
+    /// structures cannot overflow the native stack.
+    fn mark(&self, heap: &Heap, roots: &[u64]) -> HashSet<u64> {
         let mut marked: HashSet<u64> = HashSet::new();
+        let mut pending: Vec<u64> = roots.to_vec();
+        pending.extend(heap.interned_ids());
 
-        for frame in stack.iter_frames() {
-            for val in &frame.local_vars {
-                self.mark_value(heap, val, &mut marked);
+        while let Some(id) = pending.pop() {
+            if !marked.insert(id) {
+                continue;
             }
-            for val in &frame.operand_stack {
-                self.mark_value(heap, val, &mut marked);
+            if let Some(obj) = heap.get(id) {
+                pending.extend(obj.fields.values().filter_map(reference_id));
+            } else if let Some(arr) = heap.get_array(id) {
+                pending.extend(arr.content.iter().filter_map(reference_id));
             }
         }
 
@@ -51,26 +59,24 @@ impl Gc {
 
         marked
     }
+}
 
-    fn mark_value(&self, heap: &Heap, value: &HeapValue, marked: &mut HashSet<u64>) {
-        if let HeapValue::Object(obj_ref) = value {
-            self.mark_object_recursive(heap, obj_ref, marked);
-        }
+/// Heap id of an object or array reference.
+pub fn reference_id(value: &HeapValue) -> Option<u64> {
+    match value {
+        HeapValue::Object(ObjectRef { id, .. }) => Some(*id),
+        HeapValue::Array(arr) => Some(arr.id),
+        _ => None,
     }
+}
 
-    fn mark_object_recursive(&self, heap: &Heap, obj_ref: &ObjectRef, marked: &mut HashSet<u64>) {
-        if marked.contains(&obj_ref.id) {
-            return;
-        }
-
-        marked.insert(obj_ref.id);
-
-        if let Some(obj) = heap.get(obj_ref.id) {
-            for field_value in obj.fields.values() {
-                self.mark_value(heap, field_value, marked);
-            }
-        }
-    }
+/// Heap ids referenced from a frame's locals and operand stack.
+pub fn frame_roots(frame: &Frame) -> impl Iterator<Item = u64> + '_ {
+    frame
+        .local_vars
+        .iter()
+        .chain(&frame.operand_stack)
+        .filter_map(reference_id)
 }
 
 impl Heap {
@@ -80,11 +86,17 @@ impl Heap {
 
     pub fn retain_alive(&mut self, marked: &HashSet<u64>) {
         self.objects.retain(|id, _| marked.contains(id));
+        self.arrays.retain(|id, _| marked.contains(id));
     }
 
     pub fn iter_objects(&self) -> impl Iterator<Item = (&u64, &ObjectRef)> {
         self.objects.iter()
     }
+
+    /// Whether an object or array with this id is still allocated.
+    pub fn is_live(&self, id: u64) -> bool {
+        self.objects.contains_key(&id) || self.arrays.contains_key(&id)
+    }
 }
 
 impl Stack {
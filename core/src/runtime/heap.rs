@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::{collections::HashMap, fmt};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -29,6 +31,9 @@ pub enum HeapValue {
 pub struct ArrayRef {
     pub id: u64,
     pub element_type: ArrayType,
+    /// Component class of a reference array, e.g. `java/lang/String` or
+    /// `[I`; `None` is treated as `java/lang/Object`.
+    pub component_class: Option<String>,
     pub content: Vec<HeapValue>,
 }
 
@@ -97,6 +102,38 @@ impl HeapValue {
         }
     }
 
+    pub fn as_float(&self) -> f32 {
+        match self {
+            HeapValue::Float(v) => *v,
+            HeapValue::Double(v) => *v as f32,
+            HeapValue::Int(v) => *v as f32,
+            HeapValue::Long(v) => *v as f32,
+            _ => {
+                println!("TypeError: tried to read {:?} as Float", self);
+                0.0
+            }
+        }
+    }
+
+    pub fn as_double(&self) -> f64 {
+        match self {
+            HeapValue::Double(v) => *v,
+            HeapValue::Float(v) => *v as f64,
+            HeapValue::Int(v) => *v as f64,
+            HeapValue::Long(v) => *v as f64,
+            _ => {
+                println!("TypeError: tried to read {:?} as Double", self);
+                0.0
+            }
+        }
+    }
+
+    /// Long and double values take two stack slots in the JVM's accounting;
+    /// `pop2`/`dup2` and friends need to know which kind they are looking at.
+    pub fn is_wide(&self) -> bool {
+        matches!(self, HeapValue::Long(_) | HeapValue::Double(_))
+    }
+
     pub fn abs(&self) -> HeapValue {
         match self {
             HeapValue::Int(v) => HeapValue::Int(v.abs()),
@@ -114,20 +151,72 @@ impl HeapValue {
     }
 }
 
+/// Nominal footprint of one object, used to turn a heap size in bytes into
+/// the number of live objects the heap may hold.
+pub const OBJECT_SIZE: usize = 64;
+
+/// Heap size used when none is configured.
+pub const DEFAULT_MAX_HEAP_SIZE: usize = 256 * 1024 * 1024;
+
+/// Live objects at which the first collection runs.
+const INITIAL_GC_THRESHOLD: usize = 4096;
+
 pub struct Heap {
     next_id: u64,
+    /// Live objects allowed after a collection.
+    max_objects: usize,
+    /// Live objects at which the next collection runs.
+    gc_threshold: usize,
     pub(crate) objects: HashMap<u64, ObjectRef>,
     pub(crate) arrays: HashMap<u64, ArrayRef>,
     string_pool: HashMap<String, u64>,
+    /// Ids the embedding host holds, with how many handles hold each.
+    host_roots: Rc<RefCell<HashMap<u64, usize>>>,
+}
+
+/// Keeps an object alive while the host holds it; dropping the last
+/// handle to an id lets the collector have it.
+#[derive(Debug)]
+pub(crate) struct HostRoot {
+    id: u64,
+    roots: Rc<RefCell<HashMap<u64, usize>>>,
+}
+
+impl Drop for HostRoot {
+    fn drop(&mut self) {
+        let mut roots = self.roots.borrow_mut();
+        if let Some(count) = roots.get_mut(&self.id) {
+            *count -= 1;
+            if *count == 0 {
+                roots.remove(&self.id);
+            }
+        }
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Heap {
     pub fn new() -> Self {
+        Self::with_max_size(DEFAULT_MAX_HEAP_SIZE)
+    }
+
+    /// A heap that throws `OutOfMemoryError` once the objects surviving a
+    /// collection need more than `bytes`.
+    pub fn with_max_size(bytes: usize) -> Self {
+        let max_objects = (bytes / OBJECT_SIZE).max(1);
         Self {
             next_id: 1,
+            max_objects,
+            gc_threshold: INITIAL_GC_THRESHOLD.min(max_objects),
             objects: HashMap::new(),
             arrays: HashMap::new(),
             string_pool: HashMap::new(),
+            host_roots: Rc::default(),
         }
     }
 
@@ -166,12 +255,16 @@ impl Heap {
 
         let default_val = match etype {
             ArrayType::Reference => HeapValue::Null,
+            ArrayType::Long => HeapValue::Long(0),
+            ArrayType::Float => HeapValue::Float(0.0),
+            ArrayType::Double => HeapValue::Double(0.0),
             _ => HeapValue::Int(0),
         };
 
         let arr = ArrayRef {
             id,
             element_type: etype,
+            component_class: None,
             content: vec![default_val; size],
         };
 
@@ -180,10 +273,84 @@ impl Heap {
         arr
     }
 
+    /// Allocates `new T[size]` for a reference component type `T`.
+    pub fn alloc_reference_array(&mut self, size: usize, component_class: &str) -> ArrayRef {
+        let mut arr = self.alloc_array(size, ArrayType::Reference);
+        arr.component_class = Some(component_class.to_string());
+        if let Some(real) = self.arrays.get_mut(&arr.id) {
+            real.component_class = arr.component_class.clone();
+        }
+        arr
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_objects * OBJECT_SIZE
+    }
+
+    /// Bytes the heap has grown to: room up to the next collection, or
+    /// what is in use if that is more. `Runtime.totalMemory`.
+    pub fn committed_size(&self) -> usize {
+        self.gc_threshold.max(self.objects.len()) * OBJECT_SIZE
+    }
+
+    /// Bytes taken by live objects.
+    pub fn used_size(&self) -> usize {
+        self.objects.len() * OBJECT_SIZE
+    }
+
+    /// Ids of the interned strings, which stay alive for the VM's lifetime.
+    pub(crate) fn interned_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.string_pool.values().copied()
+    }
+
+    /// Roots `id` until the returned handle is dropped.
+    pub(crate) fn root_for_host(&self, id: u64) -> HostRoot {
+        *self.host_roots.borrow_mut().entry(id).or_insert(0) += 1;
+        HostRoot {
+            id,
+            roots: self.host_roots.clone(),
+        }
+    }
+
+    /// Ids the embedding host holds.
+    pub(crate) fn host_roots(&self) -> Vec<u64> {
+        self.host_roots.borrow().keys().copied().collect()
+    }
+
+    pub(crate) fn needs_collection(&self) -> bool {
+        self.objects.len() > self.gc_threshold
+    }
+
+    /// Sizes the next collection after the survivors of this one, the way
+    /// a growing heap would. `false` if the survivors exceed the heap size.
+    pub(crate) fn finish_collection(&mut self) -> bool {
+        let live = self.objects.len();
+        self.gc_threshold = (live * 2).max(INITIAL_GC_THRESHOLD).min(self.max_objects);
+        live <= self.max_objects
+    }
+
+    pub fn get_array(&self, id: u64) -> Option<&ArrayRef> {
+        self.arrays.get(&id)
+    }
+
     pub fn get_array_mut(&mut self, id: u64) -> Option<&mut ArrayRef> {
         self.arrays.get_mut(&id)
     }
 
+    /// Contents of a `java/lang/String` object, or of a raw string value.
+    pub fn string_value(&self, value: &HeapValue) -> Option<String> {
+        match value {
+            HeapValue::Object(obj) if obj.class_name == "java/lang/String" => {
+                match self.get(obj.id)?.get_field("value")? {
+                    HeapValue::String(s) => Some(s.clone()),
+                    _ => None,
+                }
+            }
+            HeapValue::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
     pub fn get(&self, id: u64) -> Option<&ObjectRef> {
         self.objects.get(&id)
     }
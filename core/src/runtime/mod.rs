@@ -1,4 +1,6 @@
+pub mod assertions;
 pub mod frame;
 pub mod gc;
 pub mod heap;
+pub mod signals;
 pub mod stack;
@@ -0,0 +1,61 @@
+//! SIGINT and SIGTERM start an orderly shutdown. The handler only records
+//! the signal; the interpreter notices it at its next safepoint and exits
+//! through the shutdown hooks, as `System.exit` does.
+
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// The signal waiting to be handled, 0 for none, or `HANDLED` once the
+/// shutdown it started is under way.
+static PENDING: AtomicI32 = AtomicI32::new(0);
+const HANDLED: i32 = -1;
+
+/// Routes SIGINT and SIGTERM to the VM. The launcher calls this unless
+/// `-Xrs` is given; embedders keep their own handlers.
+pub fn install_handlers() {
+    #[cfg(unix)]
+    // SAFETY: the handler only touches an atomic and calls `_exit`, both
+    // async-signal-safe.
+    unsafe {
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    match PENDING.compare_exchange(0, signal, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(_) | Err(HANDLED) => {}
+        // Code that never reaches a safepoint, such as a native method
+        // that does not return, can still be stopped by signalling again.
+        // SAFETY: `_exit` is async-signal-safe.
+        Err(_) => unsafe { libc::_exit(128 + signal) },
+    }
+}
+
+/// Whether a signal is waiting for the interpreter. Cheap enough to ask at
+/// every safepoint.
+#[inline]
+pub fn is_pending() -> bool {
+    PENDING.load(Ordering::Relaxed) > 0
+}
+
+/// Address of the pending signal, which compiled loops read on every
+/// backward branch.
+pub(crate) fn pending_address() -> usize {
+    PENDING.as_ptr() as usize
+}
+
+/// Claims the pending signal and returns the status to exit with, `128 +
+/// signal` as shells report it. Later signals are ignored while the hooks
+/// run.
+pub fn take_exit_status() -> Option<i32> {
+    let signal = PENDING.load(Ordering::SeqCst);
+    if signal <= 0 {
+        return None;
+    }
+    PENDING
+        .compare_exchange(signal, HANDLED, Ordering::SeqCst, Ordering::SeqCst)
+        .ok()
+        .map(|signal| 128 + signal)
+}
@@ -5,6 +5,12 @@ pub struct Stack {
     pub(crate) frames: Vec<Frame>,
 }
 
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Stack {
     pub fn new() -> Self {
         Self { frames: Vec::new() }
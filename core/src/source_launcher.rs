@@ -0,0 +1,46 @@
+//! Launching a single source file as a program (JEP 330): `java Foo.java`
+//! compiles the file in memory and runs the first class it declares.
+//!
+//! The compiler lives outside this crate, so launchers that can compile
+//! pass one to `run_cli_with`; plain `run_cli` reports source launch as
+//! unsupported.
+
+use std::fs;
+use std::path::Path;
+
+/// Classes compiled from a launched source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceProgram {
+    /// Internal name of the first top-level class, whose `main` runs.
+    pub main_class: String,
+    /// Internal names and class file bytes of every class compiled.
+    pub classes: Vec<(String, Vec<u8>)>,
+}
+
+/// Compiles the source text of the file at the given path. On failure the
+/// error holds the compiler's diagnostics, ready to print.
+pub type SourceCompiler = fn(&Path, &str) -> Result<SourceProgram, String>;
+
+/// Whether a launch target names a source file rather than a class.
+pub fn is_source_file(target: &str) -> bool {
+    target.ends_with(".java")
+}
+
+/// Reads a launched source file. A leading `#!` line, as in an executable
+/// script, is blanked so that the compiler still sees valid Java and line
+/// numbers stay right.
+pub fn read_source(path: &Path) -> Result<String, String> {
+    let source = fs::read_to_string(path)
+        .map_err(|_| format!("error: file not found: {}", path.display()))?;
+    Ok(strip_shebang(source))
+}
+
+fn strip_shebang(source: String) -> String {
+    if !source.starts_with("#!") {
+        return source;
+    }
+    match source.find('\n') {
+        Some(end) => source[end..].to_string(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,371 @@
+//! Instruction boundaries and operands, read straight from the code array.
+
+/// One decoded instruction. `wide` forms keep opcode `0xc4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Insn {
+    pub pc: usize,
+    pub opcode: u8,
+    pub len: usize,
+}
+
+const MNEMONICS: [&str; 202] = [
+    "nop",
+    "aconst_null",
+    "iconst_m1",
+    "iconst_0",
+    "iconst_1",
+    "iconst_2",
+    "iconst_3",
+    "iconst_4",
+    "iconst_5",
+    "lconst_0",
+    "lconst_1",
+    "fconst_0",
+    "fconst_1",
+    "fconst_2",
+    "dconst_0",
+    "dconst_1",
+    "bipush",
+    "sipush",
+    "ldc",
+    "ldc_w",
+    "ldc2_w",
+    "iload",
+    "lload",
+    "fload",
+    "dload",
+    "aload",
+    "iload_0",
+    "iload_1",
+    "iload_2",
+    "iload_3",
+    "lload_0",
+    "lload_1",
+    "lload_2",
+    "lload_3",
+    "fload_0",
+    "fload_1",
+    "fload_2",
+    "fload_3",
+    "dload_0",
+    "dload_1",
+    "dload_2",
+    "dload_3",
+    "aload_0",
+    "aload_1",
+    "aload_2",
+    "aload_3",
+    "iaload",
+    "laload",
+    "faload",
+    "daload",
+    "aaload",
+    "baload",
+    "caload",
+    "saload",
+    "istore",
+    "lstore",
+    "fstore",
+    "dstore",
+    "astore",
+    "istore_0",
+    "istore_1",
+    "istore_2",
+    "istore_3",
+    "lstore_0",
+    "lstore_1",
+    "lstore_2",
+    "lstore_3",
+    "fstore_0",
+    "fstore_1",
+    "fstore_2",
+    "fstore_3",
+    "dstore_0",
+    "dstore_1",
+    "dstore_2",
+    "dstore_3",
+    "astore_0",
+    "astore_1",
+    "astore_2",
+    "astore_3",
+    "iastore",
+    "lastore",
+    "fastore",
+    "dastore",
+    "aastore",
+    "bastore",
+    "castore",
+    "sastore",
+    "pop",
+    "pop2",
+    "dup",
+    "dup_x1",
+    "dup_x2",
+    "dup2",
+    "dup2_x1",
+    "dup2_x2",
+    "swap",
+    "iadd",
+    "ladd",
+    "fadd",
+    "dadd",
+    "isub",
+    "lsub",
+    "fsub",
+    "dsub",
+    "imul",
+    "lmul",
+    "fmul",
+    "dmul",
+    "idiv",
+    "ldiv",
+    "fdiv",
+    "ddiv",
+    "irem",
+    "lrem",
+    "frem",
+    "drem",
+    "ineg",
+    "lneg",
+    "fneg",
+    "dneg",
+    "ishl",
+    "lshl",
+    "ishr",
+    "lshr",
+    "iushr",
+    "lushr",
+    "iand",
+    "land",
+    "ior",
+    "lor",
+    "ixor",
+    "lxor",
+    "iinc",
+    "i2l",
+    "i2f",
+    "i2d",
+    "l2i",
+    "l2f",
+    "l2d",
+    "f2i",
+    "f2l",
+    "f2d",
+    "d2i",
+    "d2l",
+    "d2f",
+    "i2b",
+    "i2c",
+    "i2s",
+    "lcmp",
+    "fcmpl",
+    "fcmpg",
+    "dcmpl",
+    "dcmpg",
+    "ifeq",
+    "ifne",
+    "iflt",
+    "ifge",
+    "ifgt",
+    "ifle",
+    "if_icmpeq",
+    "if_icmpne",
+    "if_icmplt",
+    "if_icmpge",
+    "if_icmpgt",
+    "if_icmple",
+    "if_acmpeq",
+    "if_acmpne",
+    "goto",
+    "jsr",
+    "ret",
+    "tableswitch",
+    "lookupswitch",
+    "ireturn",
+    "lreturn",
+    "freturn",
+    "dreturn",
+    "areturn",
+    "return",
+    "getstatic",
+    "putstatic",
+    "getfield",
+    "putfield",
+    "invokevirtual",
+    "invokespecial",
+    "invokestatic",
+    "invokeinterface",
+    "invokedynamic",
+    "new",
+    "newarray",
+    "anewarray",
+    "arraylength",
+    "athrow",
+    "checkcast",
+    "instanceof",
+    "monitorenter",
+    "monitorexit",
+    "wide",
+    "multianewarray",
+    "ifnull",
+    "ifnonnull",
+    "goto_w",
+    "jsr_w",
+];
+
+pub(super) fn mnemonic(opcode: u8) -> &'static str {
+    MNEMONICS
+        .get(opcode as usize)
+        .copied()
+        .unwrap_or("<illegal>")
+}
+
+pub(super) fn u1(code: &[u8], at: usize) -> u8 {
+    code[at]
+}
+
+pub(super) fn u2(code: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([code[at], code[at + 1]])
+}
+
+pub(super) fn s2(code: &[u8], at: usize) -> i16 {
+    u2(code, at) as i16
+}
+
+pub(super) fn s4(code: &[u8], at: usize) -> i32 {
+    i32::from_be_bytes([code[at], code[at + 1], code[at + 2], code[at + 3]])
+}
+
+/// Splits the code into instructions. On failure, returns the offset of
+/// the bad instruction and what is wrong with it.
+pub(super) fn decode(code: &[u8]) -> Result<Vec<Insn>, (usize, String)> {
+    if code.is_empty() {
+        return Err((0, "Code attribute is empty".to_string()));
+    }
+    let mut insns = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let len = length(code, pc)?;
+        if pc + len > code.len() {
+            return Err((
+                pc,
+                "Instruction extends past the end of the code".to_string(),
+            ));
+        }
+        insns.push(Insn { pc, opcode, len });
+        pc += len;
+    }
+    Ok(insns)
+}
+
+fn length(code: &[u8], pc: usize) -> Result<usize, (usize, String)> {
+    let opcode = code[pc];
+    let len = match opcode {
+        0x10 | 0x12 | 0x15..=0x19 | 0x36..=0x3a | 0xa9 | 0xbc => 2,
+        0x11
+        | 0x13
+        | 0x14
+        | 0x84
+        | 0x99..=0xa8
+        | 0xb2..=0xb8
+        | 0xbb
+        | 0xbd
+        | 0xc0
+        | 0xc1
+        | 0xc6
+        | 0xc7 => 3,
+        0xc5 => 4,
+        0xb9 | 0xba | 0xc8 | 0xc9 => 5,
+        0xc4 => match code.get(pc + 1) {
+            Some(0x84) => 6,
+            Some(0x15..=0x19 | 0x36..=0x3a | 0xa9) => 4,
+            Some(_) => return Err((pc, "Bad instruction after wide".to_string())),
+            None => {
+                return Err((
+                    pc,
+                    "Instruction extends past the end of the code".to_string(),
+                ))
+            }
+        },
+        0xaa | 0xab => return switch_length(code, pc),
+        0x00..=0xc3 => 1,
+        _ => return Err((pc, format!("Bad instruction: {:x}", opcode))),
+    };
+    Ok(len)
+}
+
+fn switch_length(code: &[u8], pc: usize) -> Result<usize, (usize, String)> {
+    let truncated = || {
+        (
+            pc,
+            "Instruction extends past the end of the code".to_string(),
+        )
+    };
+    let operands = (pc + 4) & !3;
+    if operands + 12 > code.len() {
+        return Err(truncated());
+    }
+    let count = if code[pc] == 0xaa {
+        let (low, high) = (s4(code, operands + 4), s4(code, operands + 8));
+        if low > high {
+            return Err((
+                pc,
+                "low must be less than or equal to high in tableswitch".to_string(),
+            ));
+        }
+        (high as i64 - low as i64 + 1) as usize
+    } else {
+        let pairs = s4(code, operands + 4);
+        if pairs < 0 {
+            return Err((pc, "Negative number of pairs in lookupswitch".to_string()));
+        }
+        let pairs = pairs as usize;
+        if operands + 8 + pairs * 8 > code.len() {
+            return Err(truncated());
+        }
+        let keys: Vec<i32> = (0..pairs)
+            .map(|pair| s4(code, operands + 8 + pair * 8))
+            .collect();
+        if keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err((pc, "Bad lookupswitch instruction".to_string()));
+        }
+        pairs * 2
+    };
+    let header = if code[pc] == 0xaa { 12 } else { 8 };
+    let end = operands + header + count * 4;
+    if end > code.len() {
+        return Err(truncated());
+    }
+    Ok(end - pc)
+}
+
+/// Absolute branch targets of a branch or switch instruction; empty for
+/// everything else. Targets may be out of range until checked.
+pub(super) fn branch_targets(code: &[u8], insn: Insn) -> Vec<i64> {
+    let pc = insn.pc;
+    let relative: Vec<i64> = match insn.opcode {
+        0x99..=0xa8 | 0xc6 | 0xc7 => vec![s2(code, pc + 1) as i64],
+        0xc8 | 0xc9 => vec![s4(code, pc + 1) as i64],
+        0xaa => {
+            let operands = (pc + 4) & !3;
+            let (low, high) = (s4(code, operands + 4), s4(code, operands + 8));
+            let count = (high as i64 - low as i64 + 1) as usize;
+            std::iter::once(s4(code, operands) as i64)
+                .chain((0..count).map(|i| s4(code, operands + 12 + i * 4) as i64))
+                .collect()
+        }
+        0xab => {
+            let operands = (pc + 4) & !3;
+            let pairs = s4(code, operands + 4) as usize;
+            std::iter::once(s4(code, operands) as i64)
+                .chain((0..pairs).map(|i| s4(code, operands + 12 + i * 8) as i64))
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    relative
+        .into_iter()
+        .map(|offset| pc as i64 + offset)
+        .collect()
+}
@@ -0,0 +1,59 @@
+use super::code;
+use std::fmt;
+
+/// A method that failed verification, reported the way HotSpot words
+/// `java.lang.VerifyError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    /// Internal name of the class holding the method.
+    pub class_name: String,
+    /// Name and descriptor, e.g. `main([Ljava/lang/String;)V`.
+    pub method: String,
+    /// Bytecode offset of the offending instruction.
+    pub offset: usize,
+    pub mnemonic: &'static str,
+    pub message: String,
+    pub reason: Option<String>,
+}
+
+impl VerifyError {
+    pub(super) fn new(
+        class_name: &str,
+        method: &str,
+        bytecode: &[u8],
+        offset: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            class_name: class_name.to_string(),
+            method: method.to_string(),
+            offset,
+            mnemonic: bytecode
+                .get(offset)
+                .map_or("<end>", |opcode| code::mnemonic(*opcode)),
+            message: message.into(),
+            reason: None,
+        }
+    }
+
+    pub(super) fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\nException Details:\n  Location:\n    {}.{} @{}: {}",
+            self.message, self.class_name, self.method, self.offset, self.mnemonic
+        )?;
+        if let Some(reason) = &self.reason {
+            write!(f, "\n  Reason:\n    {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VerifyError {}
@@ -0,0 +1,165 @@
+//! Type inference for classes without a `StackMapTable` (JVMS 4.10.2):
+//! frames are merged at each instruction until nothing changes.
+
+use super::error::VerifyError;
+use super::method::{Frame, MethodVerifier};
+use super::types::VType;
+use std::collections::HashMap;
+
+pub(super) fn verify(m: &mut MethodVerifier) -> Result<(), VerifyError> {
+    let count = m.insns.len();
+    let mut frames: Vec<Option<Frame>> = vec![None; count];
+    let mut queued = vec![false; count];
+    let mut worklist = vec![0];
+    frames[0] = Some(m.initial_frame()?);
+    queued[0] = true;
+    // Subroutine entry -> the `jsr` instructions calling it, and the `ret`
+    // instructions seen leaving it.
+    let mut callers: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut returns: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    while let Some(index) = worklist.pop() {
+        queued[index] = false;
+        let insn = m.insns[index];
+        let pc = insn.pc;
+        let frame = frames[index]
+            .clone()
+            .expect("queued instructions have a frame");
+
+        let mut successors: Vec<(usize, Frame)> = Vec::new();
+        for (handler, caught) in m.handlers(pc) {
+            successors.push((
+                handler,
+                Frame {
+                    locals: frame.locals.clone(),
+                    stack: vec![caught],
+                },
+            ));
+        }
+        let step = m.execute(insn, frame)?;
+        for (handler, caught) in m.handlers(pc) {
+            successors.push((
+                handler,
+                Frame {
+                    locals: step.frame.locals.clone(),
+                    stack: vec![caught],
+                },
+            ));
+        }
+        if step.falls_through {
+            let Some(next) = m.insns.get(index + 1) else {
+                return Err(m.error(pc, "Falling off the end of the code"));
+            };
+            successors.push((next.pc, step.frame.clone()));
+        }
+        for target in &step.targets {
+            successors.push((*target, step.frame.clone()));
+        }
+        if let Some(subroutine) = step.jsr {
+            let Some(entry) = m.index_of(subroutine as i64) else {
+                return Err(m.error(pc, "Illegal target of jump or branch"));
+            };
+            let known = callers.entry(subroutine).or_default();
+            if !known.contains(&index) {
+                known.push(index);
+                // Returns already seen must now also flow back to this caller.
+                for ret in returns.get(&subroutine).into_iter().flatten() {
+                    enqueue(&mut worklist, &mut queued, *ret);
+                }
+            }
+            successors.push((m.insns[entry].pc, step.frame.clone()));
+        }
+        if let Some(subroutine) = step.ret {
+            let seen = returns.entry(subroutine).or_default();
+            if !seen.contains(&index) {
+                seen.push(index);
+            }
+            let entry = m
+                .index_of(subroutine as i64)
+                .and_then(|entry| frames[entry].clone());
+            for caller in callers.get(&subroutine).cloned().unwrap_or_default() {
+                let Some(next) = m.insns.get(caller + 1) else {
+                    return Err(m.error(m.insns[caller].pc, "Falling off the end of the code"));
+                };
+                let before = frames[caller].clone().expect("callers have a frame");
+                successors.push((
+                    next.pc,
+                    after_subroutine(&before, entry.as_ref(), &step.frame),
+                ));
+            }
+        }
+
+        for (target, incoming) in successors {
+            let target_index = m.index_of(target as i64).expect("targets were checked");
+            let merged = match &frames[target_index] {
+                None => incoming,
+                Some(existing) => merge(m, target, existing, &incoming)?,
+            };
+            if frames[target_index].as_ref() != Some(&merged) {
+                frames[target_index] = Some(merged);
+                enqueue(&mut worklist, &mut queued, target_index);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn enqueue(worklist: &mut Vec<usize>, queued: &mut [bool], index: usize) {
+    if !queued[index] {
+        queued[index] = true;
+        worklist.push(index);
+    }
+}
+
+/// The frame after a `jsr` once its subroutine returns: locals the
+/// subroutine changed come from the `ret`, the rest from before the call.
+fn after_subroutine(before: &Frame, entry: Option<&Frame>, at_ret: &Frame) -> Frame {
+    let locals = at_ret
+        .locals
+        .iter()
+        .enumerate()
+        .map(|(slot, value)| {
+            let untouched = entry.is_some_and(|entry| entry.locals[slot] == *value);
+            if untouched {
+                before.locals[slot].clone()
+            } else {
+                value.clone()
+            }
+        })
+        .collect();
+    Frame {
+        locals,
+        stack: at_ret.stack.clone(),
+    }
+}
+
+fn merge(m: &mut MethodVerifier, pc: usize, a: &Frame, b: &Frame) -> Result<Frame, VerifyError> {
+    if a.stack.len() != b.stack.len() {
+        return Err(m.error(
+            pc,
+            format!(
+                "Inconsistent stack height {} != {}",
+                a.stack.len(),
+                b.stack.len()
+            ),
+        ));
+    }
+    let mut stack = Vec::with_capacity(a.stack.len());
+    for (slot, (x, y)) in a.stack.iter().zip(&b.stack).enumerate() {
+        let merged = m.merge(x, y);
+        if merged == VType::Top {
+            return Err(m.error(pc, "Mismatched stack types").with_reason(format!(
+                "Type {} (stack[{}]) cannot be merged with {}",
+                x, slot, y
+            )));
+        }
+        stack.push(merged);
+    }
+    let locals = a
+        .locals
+        .iter()
+        .zip(&b.locals)
+        .map(|(x, y)| m.merge(x, y))
+        .collect();
+    Ok(Frame { locals, stack })
+}
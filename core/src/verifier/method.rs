@@ -0,0 +1,975 @@
+//! Per-method state, and the effect of each instruction on a frame. Both
+//! verifiers drive the same transfer function; they differ only in where
+//! the frame at each instruction comes from.
+
+use super::code::{self, s2, u1, u2, Insn};
+use super::error::VerifyError;
+use super::types::{self, VType};
+use super::ClassHierarchy;
+use crate::bytecode::parser::{ClassFile, CodeAttribute, ConstantPoolEntry, MethodInfo};
+
+const ACC_STATIC: u16 = 0x0008;
+
+/// The types in the local variables and on the operand stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Frame {
+    /// Always `max_locals` long; a `long` or `double` is followed by `Top`.
+    pub locals: Vec<VType>,
+    pub stack: Vec<VType>,
+}
+
+/// What executing one instruction did.
+pub(super) struct Step {
+    /// The frame after the instruction, which branch targets also see.
+    pub frame: Frame,
+    pub falls_through: bool,
+    pub targets: Vec<usize>,
+    /// `jsr`: the subroutine entered, with its return address pushed.
+    pub jsr: Option<usize>,
+    /// `ret`: the subroutine returned from.
+    pub ret: Option<usize>,
+}
+
+pub(super) struct MethodVerifier<'a> {
+    pub class: &'a ClassFile,
+    pub code: &'a CodeAttribute,
+    hierarchy: &'a mut dyn ClassHierarchy,
+    pub insns: Vec<Insn>,
+    /// Index into `insns` of the instruction starting at each offset.
+    starts: Vec<Option<usize>>,
+    class_name: String,
+    name: String,
+    /// Name and descriptor, for error messages.
+    signature: String,
+    is_static: bool,
+    parameters: Vec<VType>,
+    return_type: Option<VType>,
+}
+
+impl<'a> MethodVerifier<'a> {
+    /// Decodes the method's code and checks its branch targets and
+    /// exception table, which neither verifier can work without.
+    pub fn new(
+        class: &'a ClassFile,
+        method: &MethodInfo,
+        code: &'a CodeAttribute,
+        hierarchy: &'a mut dyn ClassHierarchy,
+    ) -> Result<Self, VerifyError> {
+        let name = class.get_utf8(method.name_index).unwrap_or("");
+        let descriptor = class.get_utf8(method.descriptor_index).unwrap_or("");
+        let mut verifier = Self {
+            class,
+            code,
+            hierarchy,
+            insns: Vec::new(),
+            starts: vec![None; code.code.len()],
+            class_name: class
+                .get_class_name(class.this_class)
+                .unwrap_or("")
+                .to_string(),
+            name: name.to_string(),
+            signature: format!("{}{}", name, descriptor),
+            is_static: method.access_flags & ACC_STATIC != 0,
+            parameters: Vec::new(),
+            return_type: None,
+        };
+
+        let (parameters, return_type) = types::method_type(descriptor).ok_or_else(|| {
+            verifier.error(0, format!("Method descriptor {} is invalid", descriptor))
+        })?;
+        verifier.parameters = parameters;
+        verifier.return_type = return_type;
+
+        verifier.insns =
+            code::decode(&code.code).map_err(|(pc, message)| verifier.error(pc, message))?;
+        for (index, insn) in verifier.insns.iter().enumerate() {
+            verifier.starts[insn.pc] = Some(index);
+        }
+        for insn in &verifier.insns {
+            for target in code::branch_targets(&code.code, *insn) {
+                if !verifier.is_start(target) {
+                    return Err(verifier.error(insn.pc, "Illegal target of jump or branch"));
+                }
+            }
+        }
+
+        for entry in &code.exception_table {
+            let (start, end) = (entry.start_pc as usize, entry.end_pc as usize);
+            let end_ok = end == code.code.len() || verifier.is_start(end as i64);
+            if start >= end || !verifier.is_start(start as i64) || !end_ok {
+                return Err(verifier.error(
+                    start,
+                    format!(
+                        "Illegal exception table range in class file {}",
+                        verifier.class_name
+                    ),
+                ));
+            }
+            if !verifier.is_start(entry.handler_pc as i64) {
+                return Err(verifier.error(
+                    start,
+                    format!(
+                        "Illegal exception table handler in class file {}",
+                        verifier.class_name
+                    ),
+                ));
+            }
+            if entry.catch_type != 0 && class.get_class_name(entry.catch_type).is_none() {
+                return Err(verifier.bad_constant(entry.handler_pc as usize, entry.catch_type));
+            }
+        }
+        Ok(verifier)
+    }
+
+    pub fn error(&self, pc: usize, message: impl Into<String>) -> VerifyError {
+        VerifyError::new(
+            &self.class_name,
+            &self.signature,
+            &self.code.code,
+            pc,
+            message,
+        )
+    }
+
+    pub fn is_start(&self, pc: i64) -> bool {
+        self.index_of(pc).is_some()
+    }
+
+    /// Index into `insns` of the instruction at `pc`.
+    pub fn index_of(&self, pc: i64) -> Option<usize> {
+        usize::try_from(pc)
+            .ok()
+            .and_then(|pc| self.starts.get(pc).copied().flatten())
+    }
+
+    /// The locals on entry, one entry per type as a `StackMapTable` lists
+    /// them: the receiver, then the parameters.
+    pub fn initial_locals(&self) -> Vec<VType> {
+        let mut locals = Vec::with_capacity(self.parameters.len() + 1);
+        if !self.is_static {
+            if self.name == "<init>" && self.class_name != "java/lang/Object" {
+                locals.push(VType::UninitializedThis);
+            } else {
+                locals.push(VType::from_class_name(&self.class_name));
+            }
+        }
+        locals.extend(self.parameters.iter().cloned());
+        locals
+    }
+
+    pub fn initial_frame(&self) -> Result<Frame, VerifyError> {
+        Ok(Frame {
+            locals: self.expand_locals(0, &self.initial_locals())?,
+            stack: Vec::new(),
+        })
+    }
+
+    /// Lays out `locals` one slot per word, padded with `Top` to
+    /// `max_locals`.
+    pub fn expand_locals(&self, pc: usize, locals: &[VType]) -> Result<Vec<VType>, VerifyError> {
+        let mut slots = Vec::with_capacity(self.code.max_locals as usize);
+        for local in locals {
+            slots.push(local.clone());
+            if local.size() == 2 {
+                slots.push(VType::Top);
+            }
+        }
+        if slots.len() > self.code.max_locals as usize {
+            return Err(self.error(pc, "Local variable table overflow"));
+        }
+        slots.resize(self.code.max_locals as usize, VType::Top);
+        Ok(slots)
+    }
+
+    /// Handlers covering `pc`, with the type each one catches.
+    pub fn handlers(&self, pc: usize) -> Vec<(usize, VType)> {
+        self.code
+            .exception_table
+            .iter()
+            .filter(|entry| (entry.start_pc as usize..entry.end_pc as usize).contains(&pc))
+            .map(|entry| {
+                let caught = self
+                    .class
+                    .get_class_name(entry.catch_type)
+                    .unwrap_or("java/lang/Throwable");
+                (entry.handler_pc as usize, VType::from_class_name(caught))
+            })
+            .collect()
+    }
+
+    pub fn is_assignable(&mut self, from: &VType, to: &VType) -> bool {
+        types::is_assignable(from, to, &mut *self.hierarchy)
+    }
+
+    pub fn merge(&mut self, a: &VType, b: &VType) -> VType {
+        types::merge(a, b, &mut *self.hierarchy)
+    }
+
+    /// Whether `from` may flow into a point whose frame is `to`; on failure,
+    /// the first slot that does not fit.
+    pub fn frame_assignable(&mut self, from: &Frame, to: &Frame, what: &str) -> Result<(), String> {
+        if from.stack.len() != to.stack.len() {
+            return Err("Current frame's stack size doesn't match stackmap.".to_string());
+        }
+        for (slot, (have, want)) in from.stack.iter().zip(&to.stack).enumerate() {
+            if !self.is_assignable(have, want) {
+                return Err(format!(
+                    "Type {} (current frame, stack[{}]) is not assignable to {} ({}, stack[{}])",
+                    have, slot, want, what, slot
+                ));
+            }
+        }
+        for (slot, (have, want)) in from.locals.iter().zip(&to.locals).enumerate() {
+            if !self.is_assignable(have, want) {
+                return Err(format!(
+                    "Type {} (current frame, locals[{}]) is not assignable to {} ({}, locals[{}])",
+                    have, slot, want, what, slot
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the instruction to `frame`.
+    pub fn execute(&mut self, insn: Insn, mut frame: Frame) -> Result<Step, VerifyError> {
+        let pc = insn.pc;
+        let bytes: &'a [u8] = &self.code.code;
+        let op = insn.opcode;
+        let f = &mut frame;
+        let mut falls_through = true;
+        let mut jsr = None;
+        let mut ret = None;
+
+        match op {
+            0x00 => {}
+            0x01 => self.push(pc, f, VType::Null)?,
+            0x02..=0x08 | 0x10 | 0x11 => self.push(pc, f, VType::Int)?,
+            0x09 | 0x0a => self.push(pc, f, VType::Long)?,
+            0x0b..=0x0d => self.push(pc, f, VType::Float)?,
+            0x0e | 0x0f => self.push(pc, f, VType::Double)?,
+            0x12 => {
+                let ty = self.ldc_type(pc, u1(bytes, pc + 1) as u16, false)?;
+                self.push(pc, f, ty)?;
+            }
+            0x13 | 0x14 => {
+                let ty = self.ldc_type(pc, u2(bytes, pc + 1), op == 0x14)?;
+                self.push(pc, f, ty)?;
+            }
+            0x15..=0x19 => self.load(pc, f, op - 0x15, u1(bytes, pc + 1) as usize)?,
+            0x1a..=0x2d => self.load(pc, f, (op - 0x1a) / 4, ((op - 0x1a) % 4) as usize)?,
+            0x2e..=0x35 => {
+                self.pop(pc, f, &VType::Int)?;
+                let element = match op {
+                    0x2e => self.pop_primitive_array(pc, f, &["[I"], VType::Int)?,
+                    0x2f => self.pop_primitive_array(pc, f, &["[J"], VType::Long)?,
+                    0x30 => self.pop_primitive_array(pc, f, &["[F"], VType::Float)?,
+                    0x31 => self.pop_primitive_array(pc, f, &["[D"], VType::Double)?,
+                    0x32 => match self.pop_reference_array(pc, f)? {
+                        Some(component) => component,
+                        None => VType::Null,
+                    },
+                    0x33 => self.pop_primitive_array(pc, f, &["[B", "[Z"], VType::Int)?,
+                    0x34 => self.pop_primitive_array(pc, f, &["[C"], VType::Int)?,
+                    _ => self.pop_primitive_array(pc, f, &["[S"], VType::Int)?,
+                };
+                self.push(pc, f, element)?;
+            }
+            0x36..=0x3a => self.store(pc, f, op - 0x36, u1(bytes, pc + 1) as usize)?,
+            0x3b..=0x4e => self.store(pc, f, (op - 0x3b) / 4, ((op - 0x3b) % 4) as usize)?,
+            0x4f..=0x56 => {
+                let value = match op {
+                    0x50 => VType::Long,
+                    0x51 => VType::Float,
+                    0x52 => VType::Double,
+                    0x53 => VType::object(),
+                    _ => VType::Int,
+                };
+                self.pop(pc, f, &value)?;
+                self.pop(pc, f, &VType::Int)?;
+                match op {
+                    0x4f => self.pop_primitive_array(pc, f, &["[I"], VType::Int)?,
+                    0x50 => self.pop_primitive_array(pc, f, &["[J"], VType::Long)?,
+                    0x51 => self.pop_primitive_array(pc, f, &["[F"], VType::Float)?,
+                    0x52 => self.pop_primitive_array(pc, f, &["[D"], VType::Double)?,
+                    0x53 => {
+                        self.pop_reference_array(pc, f)?;
+                        VType::Null
+                    }
+                    0x54 => self.pop_primitive_array(pc, f, &["[B", "[Z"], VType::Int)?,
+                    0x55 => self.pop_primitive_array(pc, f, &["[C"], VType::Int)?,
+                    _ => self.pop_primitive_array(pc, f, &["[S"], VType::Int)?,
+                };
+            }
+            0x57..=0x5f => {
+                let (top, below) = match op {
+                    0x57 | 0x59 => (1, 0),
+                    0x58 | 0x5c => (2, 0),
+                    0x5a | 0x5f => (1, 1),
+                    0x5b => (1, 2),
+                    0x5d => (2, 1),
+                    _ => (2, 2),
+                };
+                let a = self.pop_words(pc, f, top)?;
+                let b = self.pop_words(pc, f, below)?;
+                let pushes = match op {
+                    0x57 | 0x58 => vec![],
+                    0x59 | 0x5c => vec![&a, &a],
+                    0x5f => vec![&a, &b],
+                    _ => vec![&a, &b, &a],
+                };
+                for value in pushes.into_iter().flatten() {
+                    self.push(pc, f, value.clone())?;
+                }
+            }
+            0x60..=0x73 => {
+                let ty = kind((op - 0x60) % 4);
+                self.pop(pc, f, &ty)?;
+                self.pop(pc, f, &ty)?;
+                self.push(pc, f, ty)?;
+            }
+            0x74..=0x77 => {
+                let ty = kind(op - 0x74);
+                self.pop(pc, f, &ty)?;
+                self.push(pc, f, ty)?;
+            }
+            0x78..=0x7d => {
+                let ty = kind((op - 0x78) % 2);
+                self.pop(pc, f, &VType::Int)?;
+                self.pop(pc, f, &ty)?;
+                self.push(pc, f, ty)?;
+            }
+            0x7e..=0x83 => {
+                let ty = kind((op - 0x7e) % 2);
+                self.pop(pc, f, &ty)?;
+                self.pop(pc, f, &ty)?;
+                self.push(pc, f, ty)?;
+            }
+            0x84 => self.check_local(pc, f, u1(bytes, pc + 1) as usize, &VType::Int)?,
+            0x85..=0x93 => {
+                const CONVERSIONS: [(u8, u8); 15] = [
+                    (0, 1),
+                    (0, 2),
+                    (0, 3),
+                    (1, 0),
+                    (1, 2),
+                    (1, 3),
+                    (2, 0),
+                    (2, 1),
+                    (2, 3),
+                    (3, 0),
+                    (3, 1),
+                    (3, 2),
+                    (0, 0),
+                    (0, 0),
+                    (0, 0),
+                ];
+                let (from, to) = CONVERSIONS[(op - 0x85) as usize];
+                self.pop(pc, f, &kind(from))?;
+                self.push(pc, f, kind(to))?;
+            }
+            0x94..=0x98 => {
+                let ty = match op {
+                    0x94 => VType::Long,
+                    0x95 | 0x96 => VType::Float,
+                    _ => VType::Double,
+                };
+                self.pop(pc, f, &ty)?;
+                self.pop(pc, f, &ty)?;
+                self.push(pc, f, VType::Int)?;
+            }
+            0x99..=0x9e => {
+                self.pop(pc, f, &VType::Int)?;
+            }
+            0x9f..=0xa4 => {
+                self.pop(pc, f, &VType::Int)?;
+                self.pop(pc, f, &VType::Int)?;
+            }
+            0xa5 | 0xa6 => {
+                self.pop(pc, f, &VType::object())?;
+                self.pop(pc, f, &VType::object())?;
+            }
+            0xa7 | 0xc8 => falls_through = false,
+            0xa8 | 0xc9 => {
+                let offset = if op == 0xa8 {
+                    s2(bytes, pc + 1) as i64
+                } else {
+                    code::s4(bytes, pc + 1) as i64
+                };
+                let target = (pc as i64 + offset) as usize;
+                self.push(pc, f, VType::ReturnAddress(target))?;
+                jsr = Some(target);
+                falls_through = false;
+            }
+            0xa9 => {
+                ret = Some(self.return_address(pc, f, u1(bytes, pc + 1) as usize)?);
+                falls_through = false;
+            }
+            0xaa | 0xab => {
+                self.pop(pc, f, &VType::Int)?;
+                falls_through = false;
+            }
+            0xac..=0xb0 => {
+                self.check_return(pc, f, op - 0xac)?;
+                falls_through = false;
+            }
+            0xb1 => {
+                if self.return_type.is_some() {
+                    return Err(self.error(pc, "Method expects a return value"));
+                }
+                if self.name == "<init>" && f.locals.contains(&VType::UninitializedThis) {
+                    return Err(
+                        self.error(pc, "Constructor must call super() or this() before return")
+                    );
+                }
+                falls_through = false;
+            }
+            0xb2..=0xb5 => self.field(pc, f, op, u2(bytes, pc + 1))?,
+            0xb6..=0xba => self.invoke(pc, f, op)?,
+            0xbb => {
+                let name = self.class_operand(pc, u2(bytes, pc + 1))?;
+                if name.starts_with('[') {
+                    return Err(self.error(pc, "Illegal new instruction"));
+                }
+                let created = VType::Uninitialized(pc);
+                for local in f.locals.iter_mut().filter(|local| **local == created) {
+                    *local = VType::Top;
+                }
+                self.push(pc, f, created)?;
+            }
+            0xbc => {
+                let array = match u1(bytes, pc + 1) {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => return Err(self.error(pc, "Illegal newarray instruction")),
+                };
+                self.pop(pc, f, &VType::Int)?;
+                self.push(pc, f, VType::from_class_name(array))?;
+            }
+            0xbd => {
+                let name = self.class_operand(pc, u2(bytes, pc + 1))?;
+                let array = VType::from_class_name(name)
+                    .array_of()
+                    .filter(|array| VType::from_descriptor(&array_name(array)).is_some())
+                    .ok_or_else(|| self.error(pc, "Array with too many dimensions"))?;
+                self.pop(pc, f, &VType::Int)?;
+                self.push(pc, f, array)?;
+            }
+            0xbe => {
+                let depth = f.stack.len();
+                let array = self.pop_any(pc, f)?;
+                if array != VType::Null && !array.is_array() {
+                    return Err(self.bad_operand(pc, &array, depth, "array type"));
+                }
+                self.push(pc, f, VType::Int)?;
+            }
+            0xbf => {
+                self.pop(pc, f, &VType::from_class_name("java/lang/Throwable"))?;
+                falls_through = false;
+            }
+            0xc0 | 0xc1 => {
+                let name = self.class_operand(pc, u2(bytes, pc + 1))?;
+                self.pop(pc, f, &VType::object())?;
+                let result = if op == 0xc0 {
+                    VType::from_class_name(name)
+                } else {
+                    VType::Int
+                };
+                self.push(pc, f, result)?;
+            }
+            0xc2 | 0xc3 | 0xc6 | 0xc7 => {
+                self.pop(pc, f, &VType::object())?;
+            }
+            0xc4 => {
+                let index = u2(bytes, pc + 2) as usize;
+                match u1(bytes, pc + 1) {
+                    op @ 0x15..=0x19 => self.load(pc, f, op - 0x15, index)?,
+                    op @ 0x36..=0x3a => self.store(pc, f, op - 0x36, index)?,
+                    0x84 => self.check_local(pc, f, index, &VType::Int)?,
+                    _ => {
+                        ret = Some(self.return_address(pc, f, index)?);
+                        falls_through = false;
+                    }
+                }
+            }
+            0xc5 => {
+                let name = self.class_operand(pc, u2(bytes, pc + 1))?;
+                let dimensions = u1(bytes, pc + 3) as usize;
+                if dimensions == 0 || name.bytes().take_while(|b| *b == b'[').count() < dimensions {
+                    return Err(self.error(pc, "Illegal dimension in multianewarray instruction"));
+                }
+                for _ in 0..dimensions {
+                    self.pop(pc, f, &VType::Int)?;
+                }
+                self.push(pc, f, VType::from_class_name(name))?;
+            }
+            _ => return Err(self.error(pc, format!("Bad instruction: {:x}", op))),
+        }
+
+        let targets = if jsr.is_some() {
+            Vec::new()
+        } else {
+            code::branch_targets(bytes, insn)
+                .into_iter()
+                .map(|target| target as usize)
+                .collect()
+        };
+        Ok(Step {
+            frame,
+            falls_through,
+            targets,
+            jsr,
+            ret,
+        })
+    }
+
+    fn push(&self, pc: usize, frame: &mut Frame, value: VType) -> Result<(), VerifyError> {
+        let depth: usize = frame.stack.iter().map(VType::size).sum();
+        if depth + value.size() > self.code.max_stack as usize {
+            return Err(self.error(pc, "Operand stack overflow"));
+        }
+        frame.stack.push(value);
+        Ok(())
+    }
+
+    fn pop_any(&self, pc: usize, frame: &mut Frame) -> Result<VType, VerifyError> {
+        frame
+            .stack
+            .pop()
+            .ok_or_else(|| self.error(pc, "Operand stack underflow"))
+    }
+
+    fn pop(
+        &mut self,
+        pc: usize,
+        frame: &mut Frame,
+        expected: &VType,
+    ) -> Result<VType, VerifyError> {
+        let depth = frame.stack.len();
+        let value = self.pop_any(pc, frame)?;
+        if !self.is_assignable(&value, expected) {
+            return Err(self.bad_operand(pc, &value, depth, &expected.to_string()));
+        }
+        Ok(value)
+    }
+
+    fn bad_operand(&self, pc: usize, value: &VType, depth: usize, expected: &str) -> VerifyError {
+        self.error(pc, "Bad type on operand stack")
+            .with_reason(format!(
+                "Type {} (current frame, stack[{}]) is not assignable to {}",
+                value,
+                depth - 1,
+                expected
+            ))
+    }
+
+    /// Pops values making up exactly `words` stack words, in stack order.
+    /// `dup2` and friends must not split a `long` or `double`.
+    fn pop_words(
+        &self,
+        pc: usize,
+        frame: &mut Frame,
+        words: usize,
+    ) -> Result<Vec<VType>, VerifyError> {
+        let mut values = Vec::new();
+        let mut taken = 0;
+        while taken < words {
+            let value = self.pop_any(pc, frame)?;
+            taken += value.size();
+            values.push(value);
+        }
+        if taken != words {
+            let depth = frame.stack.len() + values.len();
+            let split = values.last().unwrap_or(&VType::Top).clone();
+            return Err(self.bad_operand(pc, &split, depth, "category1 type"));
+        }
+        values.reverse();
+        Ok(values)
+    }
+
+    /// Pops an array whose descriptor is one of `arrays`, or `null`.
+    fn pop_primitive_array(
+        &self,
+        pc: usize,
+        frame: &mut Frame,
+        arrays: &[&str],
+        element: VType,
+    ) -> Result<VType, VerifyError> {
+        let depth = frame.stack.len();
+        let array = self.pop_any(pc, frame)?;
+        match &array {
+            VType::Null => Ok(element),
+            VType::Reference(name) if arrays.contains(&name.as_str()) => Ok(element),
+            _ => Err(self.bad_operand(pc, &array, depth, &format!("'{}'", arrays[0]))),
+        }
+    }
+
+    /// Pops an array of references, returning its component type, or
+    /// `None` for `null`.
+    fn pop_reference_array(
+        &self,
+        pc: usize,
+        frame: &mut Frame,
+    ) -> Result<Option<VType>, VerifyError> {
+        let depth = frame.stack.len();
+        let array = self.pop_any(pc, frame)?;
+        match array.component() {
+            Some(component @ VType::Reference(_)) => Ok(Some(component)),
+            _ if array == VType::Null => Ok(None),
+            _ => Err(self.bad_operand(pc, &array, depth, "reference array type")),
+        }
+    }
+
+    fn local(
+        &self,
+        pc: usize,
+        frame: &Frame,
+        index: usize,
+        size: usize,
+    ) -> Result<VType, VerifyError> {
+        if index + size > frame.locals.len() {
+            return Err(self.error(pc, "Illegal local variable number"));
+        }
+        Ok(frame.locals[index].clone())
+    }
+
+    fn check_local(
+        &mut self,
+        pc: usize,
+        frame: &Frame,
+        index: usize,
+        expected: &VType,
+    ) -> Result<(), VerifyError> {
+        let value = self.local(pc, frame, index, expected.size())?;
+        if !self.is_assignable(&value, expected) {
+            return Err(self
+                .error(pc, "Bad local variable type")
+                .with_reason(format!(
+                    "Type {} (current frame, locals[{}]) is not assignable to {}",
+                    value, index, expected
+                )));
+        }
+        Ok(())
+    }
+
+    /// `iload` through `aload`; `family` counts from `iload`.
+    fn load(
+        &mut self,
+        pc: usize,
+        frame: &mut Frame,
+        family: u8,
+        index: usize,
+    ) -> Result<(), VerifyError> {
+        if family == 4 {
+            let value = self.local(pc, frame, index, 1)?;
+            if !value.is_reference() {
+                return Err(self
+                    .error(pc, "Bad local variable type")
+                    .with_reason(format!(
+                        "Type {} (current frame, locals[{}]) is not assignable to reference type",
+                        value, index
+                    )));
+            }
+            return self.push(pc, frame, value);
+        }
+        let ty = kind(family);
+        self.check_local(pc, frame, index, &ty)?;
+        self.push(pc, frame, ty)
+    }
+
+    /// `istore` through `astore`. `astore` also takes a return address.
+    fn store(
+        &mut self,
+        pc: usize,
+        frame: &mut Frame,
+        family: u8,
+        index: usize,
+    ) -> Result<(), VerifyError> {
+        let value = if family == 4 {
+            let depth = frame.stack.len();
+            let value = self.pop_any(pc, frame)?;
+            if !value.is_reference() && !matches!(value, VType::ReturnAddress(_)) {
+                return Err(self.bad_operand(pc, &value, depth, "reference type"));
+            }
+            value
+        } else {
+            self.pop(pc, frame, &kind(family))?
+        };
+        let size = value.size();
+        if index + size > frame.locals.len() {
+            return Err(self.error(pc, "Illegal local variable number"));
+        }
+        if index > 0 && frame.locals[index - 1].size() == 2 {
+            frame.locals[index - 1] = VType::Top;
+        }
+        frame.locals[index] = value;
+        if size == 2 {
+            frame.locals[index + 1] = VType::Top;
+        }
+        Ok(())
+    }
+
+    fn return_address(&self, pc: usize, frame: &Frame, index: usize) -> Result<usize, VerifyError> {
+        match self.local(pc, frame, index, 1)? {
+            VType::ReturnAddress(subroutine) => Ok(subroutine),
+            value => Err(self
+                .error(pc, "Bad local variable type")
+                .with_reason(format!(
+                    "Type {} (current frame, locals[{}]) is not assignable to returnAddress",
+                    value, index
+                ))),
+        }
+    }
+
+    /// `ireturn` through `areturn`; `family` counts from `ireturn`.
+    fn check_return(
+        &mut self,
+        pc: usize,
+        frame: &mut Frame,
+        family: u8,
+    ) -> Result<(), VerifyError> {
+        let Some(expected) = self.return_type.clone() else {
+            return Err(self.error(pc, "Method does not expect a return value"));
+        };
+        let depth = frame.stack.len();
+        let value = self.pop_any(pc, frame)?;
+        let family_ok = match family {
+            4 => matches!(expected, VType::Reference(_)),
+            _ => expected == kind(family),
+        };
+        if !family_ok || !self.is_assignable(&value, &expected) {
+            return Err(self.error(pc, "Bad return type").with_reason(format!(
+                "Type {} (current frame, stack[{}]) is not assignable to {} (from method signature)",
+                value,
+                depth - 1,
+                expected
+            )));
+        }
+        Ok(())
+    }
+
+    fn field(
+        &mut self,
+        pc: usize,
+        frame: &mut Frame,
+        op: u8,
+        index: u16,
+    ) -> Result<(), VerifyError> {
+        let (owner, _, descriptor) = self.member(pc, index, |entry| {
+            matches!(entry, ConstantPoolEntry::FieldRef { .. })
+        })?;
+        let field = VType::from_descriptor(descriptor)
+            .ok_or_else(|| self.error(pc, format!("Field descriptor {} is invalid", descriptor)))?;
+        let owner = VType::from_class_name(owner);
+        match op {
+            0xb2 => self.push(pc, frame, field),
+            0xb3 => self.pop(pc, frame, &field).map(drop),
+            0xb4 => {
+                self.pop(pc, frame, &owner)?;
+                self.push(pc, frame, field)
+            }
+            _ => {
+                self.pop(pc, frame, &field)?;
+                let depth = frame.stack.len();
+                let target = self.pop_any(pc, frame)?;
+                // A constructor may set its own fields before calling super().
+                let own_field = target == VType::UninitializedThis
+                    && owner == VType::from_class_name(&self.class_name);
+                if !own_field && !self.is_assignable(&target, &owner) {
+                    return Err(self.bad_operand(pc, &target, depth, &owner.to_string()));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn invoke(&mut self, pc: usize, frame: &mut Frame, op: u8) -> Result<(), VerifyError> {
+        let bytes: &'a [u8] = &self.code.code;
+        let index = u2(bytes, pc + 1);
+        let class: &'a ClassFile = self.class;
+        let (owner, name, descriptor) = if op == 0xba {
+            let Some(ConstantPoolEntry::InvokeDynamic {
+                name_and_type_index,
+                ..
+            }) = class.constant(index)
+            else {
+                return Err(self.bad_constant(pc, index));
+            };
+            if u2(bytes, pc + 3) != 0 {
+                return Err(self.error(
+                    pc,
+                    "Third and fourth operand bytes of invokedynamic must be zero",
+                ));
+            }
+            let (name, descriptor) = class
+                .get_name_and_type(*name_and_type_index)
+                .ok_or_else(|| self.bad_constant(pc, index))?;
+            ("", name, descriptor)
+        } else {
+            let interface_ok = op == 0xb9 || (op != 0xb6 && class.major_version >= 52);
+            self.member(pc, index, |entry| match entry {
+                ConstantPoolEntry::MethodRef { .. } => op != 0xb9,
+                ConstantPoolEntry::InterfaceMethodRef { .. } => interface_ok,
+                _ => false,
+            })?
+        };
+        let (parameters, result) = types::method_type(descriptor).ok_or_else(|| {
+            self.error(pc, format!("Method descriptor {} is invalid", descriptor))
+        })?;
+        let is_init = name == "<init>";
+        if name.starts_with('<') && !(op == 0xb7 && is_init) {
+            return Err(self.error(pc, "Illegal call to internal method"));
+        }
+        if op == 0xb9 {
+            let words = 1 + parameters.iter().map(VType::size).sum::<usize>();
+            if u1(bytes, pc + 3) as usize != words || u1(bytes, pc + 4) != 0 {
+                return Err(self.error(pc, "Inconsistent args count operand in invokeinterface"));
+            }
+        }
+
+        for parameter in parameters.iter().rev() {
+            self.pop(pc, frame, parameter)?;
+        }
+        if is_init {
+            let depth = frame.stack.len();
+            let receiver = self.pop_any(pc, frame)?;
+            let constructed = match receiver {
+                VType::UninitializedThis => VType::from_class_name(&self.class_name),
+                VType::Uninitialized(created) if bytes.get(created) == Some(&0xbb) => {
+                    let class_name = self.class_operand(pc, u2(bytes, created + 1))?;
+                    if class_name != owner {
+                        return Err(self.error(pc, "Call to wrong <init> method"));
+                    }
+                    VType::from_class_name(class_name)
+                }
+                _ => return Err(self.bad_operand(pc, &receiver, depth, "uninitialized")),
+            };
+            for slot in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+                if *slot == receiver {
+                    *slot = constructed.clone();
+                }
+            }
+        } else if op == 0xb7 {
+            let current = VType::from_class_name(&self.class_name);
+            self.pop(pc, frame, &current)?;
+        } else if op != 0xb8 && op != 0xba {
+            self.pop(pc, frame, &VType::from_class_name(owner))?;
+        }
+        if let Some(result) = result {
+            self.push(pc, frame, result)?;
+        }
+        Ok(())
+    }
+
+    fn ldc_type(&self, pc: usize, index: u16, wide: bool) -> Result<VType, VerifyError> {
+        let ty = match (self.class.constant(index), wide) {
+            (Some(ConstantPoolEntry::Integer(_)), false) => VType::Int,
+            (Some(ConstantPoolEntry::Float(_)), false) => VType::Float,
+            (Some(ConstantPoolEntry::Long(_)), true) => VType::Long,
+            (Some(ConstantPoolEntry::Double(_)), true) => VType::Double,
+            (Some(ConstantPoolEntry::String { .. }), false) => {
+                VType::from_class_name("java/lang/String")
+            }
+            (Some(ConstantPoolEntry::Class { .. }), false) if self.class.major_version >= 49 => {
+                VType::from_class_name("java/lang/Class")
+            }
+            (Some(ConstantPoolEntry::MethodType { .. }), false) => {
+                VType::from_class_name("java/lang/invoke/MethodType")
+            }
+            (Some(ConstantPoolEntry::MethodHandle { .. }), false) => {
+                VType::from_class_name("java/lang/invoke/MethodHandle")
+            }
+            (
+                Some(ConstantPoolEntry::Dynamic {
+                    name_and_type_index,
+                    ..
+                }),
+                _,
+            ) => {
+                let ty = self
+                    .class
+                    .get_name_and_type(*name_and_type_index)
+                    .and_then(|(_, descriptor)| VType::from_descriptor(descriptor))
+                    .ok_or_else(|| self.bad_constant(pc, index))?;
+                if (ty.size() == 2) != wide {
+                    return Err(self.bad_constant(pc, index));
+                }
+                ty
+            }
+            _ => return Err(self.bad_constant(pc, index)),
+        };
+        Ok(ty)
+    }
+
+    fn class_operand(&self, pc: usize, index: u16) -> Result<&'a str, VerifyError> {
+        let class: &'a ClassFile = self.class;
+        class
+            .get_class_name(index)
+            .ok_or_else(|| self.bad_constant(pc, index))
+    }
+
+    /// The owner, name and descriptor of a field or method reference whose
+    /// entry `accept`s.
+    fn member(
+        &self,
+        pc: usize,
+        index: u16,
+        accept: impl Fn(&ConstantPoolEntry) -> bool,
+    ) -> Result<(&'a str, &'a str, &'a str), VerifyError> {
+        let class: &'a ClassFile = self.class;
+        let resolved = match class.constant(index) {
+            Some(
+                entry @ (ConstantPoolEntry::FieldRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolEntry::MethodRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolEntry::InterfaceMethodRef {
+                    class_index,
+                    name_and_type_index,
+                }),
+            ) if accept(entry) => class
+                .get_class_name(*class_index)
+                .zip(class.get_name_and_type(*name_and_type_index))
+                .map(|(owner, (name, descriptor))| (owner, name, descriptor)),
+            _ => None,
+        };
+        resolved.ok_or_else(|| self.bad_constant(pc, index))
+    }
+
+    pub fn bad_constant(&self, pc: usize, index: u16) -> VerifyError {
+        self.error(
+            pc,
+            format!(
+                "Illegal type at constant pool entry {} in class {}",
+                index, self.class_name
+            ),
+        )
+    }
+}
+
+/// The operand type of the `i`, `l`, `f` and `d` forms of an instruction
+/// family, in that order.
+fn kind(family: u8) -> VType {
+    match family {
+        0 => VType::Int,
+        1 => VType::Long,
+        2 => VType::Float,
+        _ => VType::Double,
+    }
+}
+
+fn array_name(array: &VType) -> String {
+    match array {
+        VType::Reference(name) => name.clone(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,60 @@
+//! Bytecode verification at link time (JVMS 4.10). Classes of version 50
+//! and later are type checked against their `StackMapTable`; older ones go
+//! through type inference instead. As in HotSpot, a version 50 class the
+//! type checker rejects is given a second chance with inference.
+
+mod code;
+mod error;
+mod inference;
+mod method;
+mod typecheck;
+mod types;
+
+pub use self::error::VerifyError;
+
+use self::method::MethodVerifier;
+use crate::bytecode::parser::ClassFile;
+
+/// The first class file version whose methods must carry stack maps.
+const TYPE_CHECKING_VERSION: u16 = 50;
+
+/// What the verifier may learn about a class the code refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassKind {
+    /// `superclass` is `None` only for `java/lang/Object`.
+    Class {
+        superclass: Option<String>,
+    },
+    Interface,
+    /// Not available. Assignments involving it are allowed, as the check
+    /// is repeated at run time where it matters.
+    Unknown,
+}
+
+/// The class hierarchy assignability checks walk, usually the class
+/// loader's.
+pub trait ClassHierarchy {
+    fn class_kind(&mut self, name: &str) -> ClassKind;
+}
+
+/// Verifies every method with code in `class`.
+pub fn verify_class(
+    class: &ClassFile,
+    hierarchy: &mut dyn ClassHierarchy,
+) -> Result<(), VerifyError> {
+    for method in &class.methods {
+        let Some(code) = &method.code else {
+            continue;
+        };
+        let mut verifier = MethodVerifier::new(class, method, code, hierarchy)?;
+        if class.major_version < TYPE_CHECKING_VERSION {
+            inference::verify(&mut verifier)?;
+        } else if let Err(error) = typecheck::verify(&mut verifier) {
+            if class.major_version > TYPE_CHECKING_VERSION {
+                return Err(error);
+            }
+            inference::verify(&mut verifier)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Type checking against the `StackMapTable` (JVMS 4.10.1): one linear
+//! pass, with the frames the compiler recorded at every branch target.
+
+use super::code::mnemonic;
+use super::error::VerifyError;
+use super::method::{Frame, MethodVerifier};
+use super::types::VType;
+use crate::bytecode::attributes::{Attribute, StackMapFrame, VerificationType};
+use std::collections::HashMap;
+
+pub(super) fn verify(m: &mut MethodVerifier) -> Result<(), VerifyError> {
+    let frames = stack_map(m)?;
+    let mut current = Some(m.initial_frame()?);
+
+    for index in 0..m.insns.len() {
+        let insn = m.insns[index];
+        let pc = insn.pc;
+        if let Some(recorded) = frames.get(&pc) {
+            if let Some(frame) = &current {
+                if let Err(reason) = m.frame_assignable(frame, recorded, "stack map") {
+                    return Err(m
+                        .error(pc, "Instruction type does not match stack map")
+                        .with_reason(reason));
+                }
+            }
+            current = Some(recorded.clone());
+        }
+        let Some(frame) = current.take() else {
+            return Err(m.error(pc, "Expecting a stack map frame"));
+        };
+
+        check_handlers(m, pc, &frame, &frames)?;
+        let step = m.execute(insn, frame)?;
+        if step.jsr.is_some() || step.ret.is_some() {
+            return Err(m.error(
+                pc,
+                format!(
+                    "Illegal instruction {} in class file version {}",
+                    mnemonic(insn.opcode),
+                    m.class.major_version
+                ),
+            ));
+        }
+        check_handlers(m, pc, &step.frame, &frames)?;
+        for target in &step.targets {
+            let Some(recorded) = frames.get(target) else {
+                return Err(m.error(
+                    pc,
+                    format!("Expecting a stackmap frame at branch target {}", target),
+                ));
+            };
+            if let Err(reason) = m.frame_assignable(&step.frame, recorded, "stack map") {
+                return Err(m
+                    .error(
+                        pc,
+                        format!("Inconsistent stackmap frames at branch target {}", target),
+                    )
+                    .with_reason(reason));
+            }
+        }
+        current = step.falls_through.then_some(step.frame);
+    }
+
+    if current.is_some() {
+        let last = m.insns.last().map_or(0, |insn| insn.pc);
+        return Err(m.error(last, "Falling off the end of the code"));
+    }
+    Ok(())
+}
+
+/// Every handler covering `pc` must accept the locals as they are, with
+/// just the caught exception on the stack.
+fn check_handlers(
+    m: &mut MethodVerifier,
+    pc: usize,
+    frame: &Frame,
+    frames: &HashMap<usize, Frame>,
+) -> Result<(), VerifyError> {
+    for (handler, caught) in m.handlers(pc) {
+        let Some(recorded) = frames.get(&handler) else {
+            return Err(m.error(
+                pc,
+                format!("Expecting a stackmap frame at branch target {}", handler),
+            ));
+        };
+        let thrown = Frame {
+            locals: frame.locals.clone(),
+            stack: vec![caught],
+        };
+        if let Err(reason) = m.frame_assignable(&thrown, recorded, "stack map") {
+            return Err(m
+                .error(
+                    pc,
+                    format!(
+                        "Stack map does not match the one at exception handler {}",
+                        handler
+                    ),
+                )
+                .with_reason(reason));
+        }
+    }
+    Ok(())
+}
+
+/// Expands the method's `StackMapTable` into a full frame per offset.
+fn stack_map(m: &MethodVerifier) -> Result<HashMap<usize, Frame>, VerifyError> {
+    let entries: &[StackMapFrame] = m
+        .code
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::StackMapTable(entries) => Some(entries.as_slice()),
+            _ => None,
+        })
+        .unwrap_or(&[]);
+
+    let mut frames = HashMap::new();
+    let mut locals = m.initial_locals();
+    let mut offset: Option<usize> = None;
+    for entry in entries {
+        let pc = match offset {
+            None => entry.offset_delta() as usize,
+            Some(previous) => previous + entry.offset_delta() as usize + 1,
+        };
+        offset = Some(pc);
+        if !m.is_start(pc as i64) {
+            return Err(m.error(pc.min(m.code.code.len()), "StackMapTable error: bad offset"));
+        }
+        let stack = match entry {
+            StackMapFrame::Same { .. } => Vec::new(),
+            StackMapFrame::SameLocals1StackItem { stack, .. } => vec![convert(m, pc, stack)?],
+            StackMapFrame::Chop { chopped, .. } => {
+                let kept = locals
+                    .len()
+                    .checked_sub(*chopped as usize)
+                    .ok_or_else(|| m.error(pc, "StackMapTable error: bad chop frame"))?;
+                locals.truncate(kept);
+                Vec::new()
+            }
+            StackMapFrame::Append { locals: added, .. } => {
+                for local in added {
+                    locals.push(convert(m, pc, local)?);
+                }
+                Vec::new()
+            }
+            StackMapFrame::Full {
+                locals: full,
+                stack,
+                ..
+            } => {
+                locals = full
+                    .iter()
+                    .map(|local| convert(m, pc, local))
+                    .collect::<Result<_, _>>()?;
+                stack
+                    .iter()
+                    .map(|item| convert(m, pc, item))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+        let depth: usize = stack.iter().map(VType::size).sum();
+        if depth > m.code.max_stack as usize {
+            return Err(m.error(pc, "StackMapTable error: stack size too large"));
+        }
+        let frame = Frame {
+            locals: m.expand_locals(pc, &locals)?,
+            stack,
+        };
+        frames.insert(pc, frame);
+    }
+    Ok(frames)
+}
+
+fn convert(m: &MethodVerifier, pc: usize, ty: &VerificationType) -> Result<VType, VerifyError> {
+    Ok(match ty {
+        VerificationType::Top => VType::Top,
+        VerificationType::Integer => VType::Int,
+        VerificationType::Float => VType::Float,
+        VerificationType::Long => VType::Long,
+        VerificationType::Double => VType::Double,
+        VerificationType::Null => VType::Null,
+        VerificationType::UninitializedThis => VType::UninitializedThis,
+        VerificationType::Object(index) => VType::from_class_name(
+            m.class
+                .get_class_name(*index)
+                .ok_or_else(|| m.bad_constant(pc, *index))?,
+        ),
+        VerificationType::Uninitialized(offset) => {
+            let offset = *offset as usize;
+            if !m.is_start(offset as i64) || m.code.code[offset] != 0xbb {
+                return Err(m.error(pc, "StackMapTable error: bad uninitialized type offset"));
+            }
+            VType::Uninitialized(offset)
+        }
+    })
+}
@@ -0,0 +1,277 @@
+//! The verification type lattice of JVMS 4.10.1.2.
+
+use super::{ClassHierarchy, ClassKind};
+use std::fmt;
+
+const OBJECT: &str = "java/lang/Object";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum VType {
+    Top,
+    Int,
+    Float,
+    /// Occupies the next local as `Top`, but a single stack entry.
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// Made by the `new` at this offset, not yet constructed.
+    Uninitialized(usize),
+    /// A class or interface internal name, or an array descriptor.
+    Reference(String),
+    /// Pushed by a `jsr` to the subroutine at this offset.
+    ReturnAddress(usize),
+}
+
+impl VType {
+    pub fn object() -> Self {
+        VType::Reference(OBJECT.to_string())
+    }
+
+    /// Words taken on the operand stack.
+    pub fn size(&self) -> usize {
+        match self {
+            VType::Long | VType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    /// Anything `aload` may push: initialized or not, or `null`.
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            VType::Null | VType::Reference(_) | VType::Uninitialized(_) | VType::UninitializedThis
+        )
+    }
+
+    /// The type of a field descriptor such as `I` or `[Ljava/lang/String;`.
+    pub fn from_descriptor(descriptor: &str) -> Option<Self> {
+        match field_type(descriptor)? {
+            (ty, used) if used == descriptor.len() => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// The type held by a `Class` constant: its name, array or not.
+    pub fn from_class_name(name: &str) -> Self {
+        VType::Reference(name.to_string())
+    }
+
+    /// The element type of an array of references or primitives; `None`
+    /// for anything that is not an array.
+    pub fn component(&self) -> Option<VType> {
+        match self {
+            VType::Reference(name) => {
+                let component = name.strip_prefix('[')?;
+                VType::from_descriptor(component)
+            }
+            _ => None,
+        }
+    }
+
+    /// The array type with `self` as elements.
+    pub fn array_of(&self) -> Option<VType> {
+        let component = match self {
+            VType::Int => "I".to_string(),
+            VType::Float => "F".to_string(),
+            VType::Long => "J".to_string(),
+            VType::Double => "D".to_string(),
+            VType::Reference(name) if name.starts_with('[') => name.clone(),
+            VType::Reference(name) => format!("L{};", name),
+            _ => return None,
+        };
+        Some(VType::Reference(format!("[{}", component)))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, VType::Reference(name) if name.starts_with('['))
+    }
+}
+
+impl fmt::Display for VType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VType::Top => f.write_str("top"),
+            VType::Int => f.write_str("integer"),
+            VType::Float => f.write_str("float"),
+            VType::Long => f.write_str("long"),
+            VType::Double => f.write_str("double"),
+            VType::Null => f.write_str("null"),
+            VType::UninitializedThis => f.write_str("uninitializedThis"),
+            VType::Uninitialized(offset) => write!(f, "uninitialized({})", offset),
+            VType::Reference(name) => write!(f, "'{}'", name),
+            VType::ReturnAddress(_) => f.write_str("returnAddress"),
+        }
+    }
+}
+
+/// Reads one field type from the front of `descriptor`, returning it and
+/// the bytes it took. `byte`, `char`, `short` and `boolean` are `Int`.
+pub(super) fn field_type(descriptor: &str) -> Option<(VType, usize)> {
+    let ty = match descriptor.as_bytes().first()? {
+        b'B' | b'C' | b'I' | b'S' | b'Z' => VType::Int,
+        b'F' => VType::Float,
+        b'J' => VType::Long,
+        b'D' => VType::Double,
+        b'L' => {
+            let end = descriptor.find(';')?;
+            if end == 1 {
+                return None;
+            }
+            return Some((VType::Reference(descriptor[1..end].to_string()), end + 1));
+        }
+        b'[' => {
+            let dimensions = descriptor.bytes().take_while(|b| *b == b'[').count();
+            if dimensions > 255 {
+                return None;
+            }
+            let (_, used) = field_type(&descriptor[dimensions..])?;
+            let end = dimensions + used;
+            return Some((VType::Reference(descriptor[..end].to_string()), end));
+        }
+        _ => return None,
+    };
+    Some((ty, 1))
+}
+
+/// Parameter types and return type (`None` for `void`) of a method
+/// descriptor.
+pub(super) fn method_type(descriptor: &str) -> Option<(Vec<VType>, Option<VType>)> {
+    let mut rest = descriptor.strip_prefix('(')?;
+    let mut parameters = Vec::new();
+    while !rest.starts_with(')') {
+        let (ty, used) = field_type(rest)?;
+        parameters.push(ty);
+        rest = &rest[used..];
+    }
+    let result = &rest[1..];
+    if result == "V" {
+        return Some((parameters, None));
+    }
+    Some((parameters, Some(VType::from_descriptor(result)?)))
+}
+
+/// `from` may be used where `to` is expected (JVMS 4.10.1.2).
+pub(super) fn is_assignable(from: &VType, to: &VType, hierarchy: &mut dyn ClassHierarchy) -> bool {
+    match (from, to) {
+        _ if from == to => true,
+        (_, VType::Top) => true,
+        (VType::Null, VType::Reference(_)) => true,
+        (VType::Reference(from), VType::Reference(to)) => is_class_assignable(from, to, hierarchy),
+        _ => false,
+    }
+}
+
+/// Reference assignability. Interfaces are treated like `Object`, and
+/// classes the hierarchy cannot see are given the benefit of the doubt.
+fn is_class_assignable(from: &str, to: &str, hierarchy: &mut dyn ClassHierarchy) -> bool {
+    if from == to || to == OBJECT {
+        return true;
+    }
+    if let Some(to_component) = to.strip_prefix('[') {
+        let Some(from_component) = from.strip_prefix('[') else {
+            return false;
+        };
+        return match (
+            VType::from_descriptor(from_component),
+            VType::from_descriptor(to_component),
+        ) {
+            (Some(VType::Reference(from)), Some(VType::Reference(to))) => {
+                is_class_assignable(&from, &to, hierarchy)
+            }
+            (from, to) => from == to,
+        };
+    }
+    let target = hierarchy.class_kind(to);
+    if from.starts_with('[') {
+        return match target {
+            ClassKind::Class { .. } => false,
+            ClassKind::Interface => {
+                matches!(to, "java/lang/Cloneable" | "java/io/Serializable")
+            }
+            ClassKind::Unknown => true,
+        };
+    }
+    match target {
+        ClassKind::Interface | ClassKind::Unknown => return true,
+        ClassKind::Class { .. } => {}
+    }
+    let mut current = from.to_string();
+    // Bounded in case a broken hierarchy has a cycle.
+    for _ in 0..256 {
+        match hierarchy.class_kind(&current) {
+            ClassKind::Class {
+                superclass: Some(superclass),
+            } => {
+                if superclass == to {
+                    return true;
+                }
+                current = superclass;
+            }
+            ClassKind::Class { superclass: None } => return false,
+            ClassKind::Interface | ClassKind::Unknown => return true,
+        }
+    }
+    true
+}
+
+/// The least upper bound of two types, as the inference verifier merges
+/// frames; `Top` when they have none.
+pub(super) fn merge(a: &VType, b: &VType, hierarchy: &mut dyn ClassHierarchy) -> VType {
+    match (a, b) {
+        _ if a == b => a.clone(),
+        (VType::Null, VType::Reference(_)) => b.clone(),
+        (VType::Reference(_), VType::Null) => a.clone(),
+        (VType::Reference(a), VType::Reference(b)) => {
+            VType::Reference(common_superclass(a, b, hierarchy))
+        }
+        _ => VType::Top,
+    }
+}
+
+fn common_superclass(a: &str, b: &str, hierarchy: &mut dyn ClassHierarchy) -> String {
+    if let (Some(a_component), Some(b_component)) = (a.strip_prefix('['), b.strip_prefix('[')) {
+        return match (
+            VType::from_descriptor(a_component),
+            VType::from_descriptor(b_component),
+        ) {
+            (Some(VType::Reference(a)), Some(VType::Reference(b))) => {
+                let merged = VType::Reference(common_superclass(&a, &b, hierarchy));
+                match merged.array_of() {
+                    Some(VType::Reference(name)) => name,
+                    _ => OBJECT.to_string(),
+                }
+            }
+            _ => OBJECT.to_string(),
+        };
+    }
+    if a.starts_with('[') || b.starts_with('[') {
+        return OBJECT.to_string();
+    }
+    let Some(a_chain) = superclass_chain(a, hierarchy) else {
+        return OBJECT.to_string();
+    };
+    let Some(b_chain) = superclass_chain(b, hierarchy) else {
+        return OBJECT.to_string();
+    };
+    b_chain
+        .into_iter()
+        .find(|name| a_chain.contains(name))
+        .unwrap_or_else(|| OBJECT.to_string())
+}
+
+/// `name` and its superclasses, or `None` if it is an interface or part of
+/// the chain is unknown.
+fn superclass_chain(name: &str, hierarchy: &mut dyn ClassHierarchy) -> Option<Vec<String>> {
+    let mut chain = vec![name.to_string()];
+    while chain.len() < 256 {
+        match hierarchy.class_kind(chain.last()?) {
+            ClassKind::Class {
+                superclass: Some(superclass),
+            } => chain.push(superclass),
+            ClassKind::Class { superclass: None } => return Some(chain),
+            ClassKind::Interface | ClassKind::Unknown => return None,
+        }
+    }
+    None
+}
@@ -0,0 +1,833 @@
+//! A safe API for embedding the VM in a Rust program: build a [`Vm`] with
+//! its classpath, system properties, heap size and Rust natives, then call
+//! Java methods with Rust values.
+//!
+//! ```no_run
+//! use aria_core::vm::Vm;
+//!
+//! let mut vm = Vm::builder()
+//!     .classpath("plugins")
+//!     .property("plugin.mode", "strict")
+//!     .function("Plugin", "log", "(Ljava/lang/String;)V", |line: String| {
+//!         println!("{}", line);
+//!     })
+//!     .build()?;
+//! let score: i32 = vm.call_static("Plugin", "score", "(Ljava/lang/String;)I", ("input",))?;
+//! # Ok::<(), aria_core::vm::VmError>(())
+//! ```
+//!
+//! A Java exception escaping a call comes back as [`VmError::Exception`],
+//! carrying the Java stack trace.
+
+use crate::exec::interpreter::Interpreter;
+use crate::jit::JitMode;
+use crate::loader::class_loader::{ClassLoader, LoadError};
+use crate::native::jni::signature_kinds;
+use crate::native::registry::{describe_method, NativeMethod};
+use crate::native::{is_builtin_class, java_io_printstream, java_lang_throwable, NativeEnv};
+use crate::runtime::gc;
+use crate::runtime::heap::{ArrayType, Heap, HeapValue, HostRoot};
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const ACC_STATIC: u16 = 0x0008;
+
+/// Why a call into the VM failed.
+#[derive(Debug)]
+pub enum VmError {
+    /// The class is neither on the classpath nor built in.
+    ClassNotFound(String),
+    /// No method with the name and descriptor, rendered as HotSpot does,
+    /// e.g. `'int Plugin.score(java.lang.String)'`.
+    NoSuchMethod(String),
+    /// The Rust argument or result types do not fit the descriptor.
+    SignatureMismatch(String),
+    /// A Java value could not be converted to the requested Rust type.
+    Conversion(String),
+    /// The call threw.
+    Exception(JavaException),
+    /// A class could not be redefined; the message says why, e.g.
+    /// `class redefinition failed: attempted to add a method`.
+    Redefinition(String),
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::ClassNotFound(name) => write!(f, "class not found: {}", name),
+            VmError::NoSuchMethod(method) => write!(f, "no such method: {}", method),
+            VmError::SignatureMismatch(message)
+            | VmError::Conversion(message)
+            | VmError::Redefinition(message) => f.write_str(message),
+            VmError::Exception(exception) => exception.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+/// A Java exception thrown out of a call.
+#[derive(Debug, Clone)]
+pub struct JavaException {
+    /// Binary name, e.g. `java.lang.IllegalStateException`.
+    pub class_name: String,
+    pub message: Option<String>,
+    /// What `printStackTrace()` would print, causes included.
+    pub stack_trace: String,
+}
+
+impl fmt::Display for JavaException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.stack_trace)
+    }
+}
+
+impl std::error::Error for JavaException {}
+
+/// An exception for a typed native to throw by returning `Err`.
+#[derive(Debug, Clone)]
+pub struct Throw {
+    class_name: String,
+    message: Option<String>,
+}
+
+impl Throw {
+    /// `class_name` may use dots or slashes.
+    pub fn new(class_name: &str, message: &str) -> Self {
+        Self {
+            class_name: class_name.replace('.', "/"),
+            message: Some(message.to_string()),
+        }
+    }
+}
+
+/// A reference to a Java object or array living in a [`Vm`]. The object
+/// stays alive while any clone of the reference does.
+#[derive(Debug, Clone)]
+pub struct JavaObject {
+    value: HeapValue,
+    _root: Option<Rc<HostRoot>>,
+}
+
+impl JavaObject {
+    fn new(value: HeapValue, heap: &Heap) -> Self {
+        let root = gc::reference_id(&value).map(|id| Rc::new(heap.root_for_host(id)));
+        Self { value, _root: root }
+    }
+
+    /// Internal name of the object's class, or the descriptor of an array,
+    /// e.g. `[I`.
+    pub fn class_name(&self) -> String {
+        match &self.value {
+            HeapValue::Object(obj) => obj.class_name.clone(),
+            HeapValue::Array(arr) => match arr.element_type {
+                ArrayType::Reference => {
+                    let component = arr.component_class.as_deref().unwrap_or("java/lang/Object");
+                    if component.starts_with('[') {
+                        format!("[{}", component)
+                    } else {
+                        format!("[L{};", component)
+                    }
+                }
+                other => format!("[{}", char::from(primitive_kind(other))),
+            },
+            _ => "java/lang/String".to_string(),
+        }
+    }
+
+    pub fn as_value(&self) -> &HeapValue {
+        &self.value
+    }
+}
+
+fn primitive_kind(element_type: ArrayType) -> u8 {
+    match element_type {
+        ArrayType::Boolean => b'Z',
+        ArrayType::Char => b'C',
+        ArrayType::Float => b'F',
+        ArrayType::Double => b'D',
+        ArrayType::Byte => b'B',
+        ArrayType::Short => b'S',
+        ArrayType::Int => b'I',
+        ArrayType::Long => b'J',
+        ArrayType::Reference => b'L',
+    }
+}
+
+/// A Rust value that can be passed to Java.
+pub trait IntoJava {
+    /// The descriptor character of the Java type: a primitive one, `L` for
+    /// references and `V` for `()`.
+    const KIND: u8;
+    /// Component class used when a `Vec` of these becomes a Java array.
+    const CLASS: &'static str = "java/lang/Object";
+
+    fn into_java(self, heap: &mut Heap) -> HeapValue;
+}
+
+/// A Rust value that can be built from a Java one.
+pub trait FromJava: Sized {
+    const KIND: u8;
+
+    /// `value` is `None` for a `void` result.
+    fn from_java(value: Option<HeapValue>, heap: &Heap) -> Result<Self, VmError>;
+}
+
+macro_rules! primitives {
+    ($($ty:ty => $kind:expr, |$v:ident| $into:expr, |$h:ident| $from:expr;)*) => {$(
+        impl IntoJava for $ty {
+            const KIND: u8 = $kind;
+
+            fn into_java(self, _heap: &mut Heap) -> HeapValue {
+                let $v = self;
+                $into
+            }
+        }
+
+        impl FromJava for $ty {
+            const KIND: u8 = $kind;
+
+            fn from_java(value: Option<HeapValue>, _heap: &Heap) -> Result<Self, VmError> {
+                match value {
+                    Some($h) if !$h.is_null() && !$h.is_object() => Ok($from),
+                    other => Err(VmError::Conversion(format!(
+                        "cannot convert {:?} to {}",
+                        other,
+                        stringify!($ty)
+                    ))),
+                }
+            }
+        }
+    )*};
+}
+
+primitives! {
+    bool => b'Z', |v| HeapValue::Int(i32::from(v)), |h| h.as_int() != 0;
+    i8 => b'B', |v| HeapValue::Int(i32::from(v)), |h| h.as_int() as i8;
+    i16 => b'S', |v| HeapValue::Int(i32::from(v)), |h| h.as_int() as i16;
+    u16 => b'C', |v| HeapValue::Int(i32::from(v)), |h| h.as_int() as u16;
+    i32 => b'I', |v| HeapValue::Int(v), |h| h.as_int();
+    i64 => b'J', |v| HeapValue::Long(v), |h| h.as_long();
+    f32 => b'F', |v| HeapValue::Float(v), |h| h.as_float();
+    f64 => b'D', |v| HeapValue::Double(v), |h| h.as_double();
+}
+
+impl IntoJava for () {
+    const KIND: u8 = b'V';
+
+    fn into_java(self, _heap: &mut Heap) -> HeapValue {
+        HeapValue::Null
+    }
+}
+
+impl FromJava for () {
+    const KIND: u8 = b'V';
+
+    fn from_java(_value: Option<HeapValue>, _heap: &Heap) -> Result<Self, VmError> {
+        Ok(())
+    }
+}
+
+impl IntoJava for &str {
+    const KIND: u8 = b'L';
+    const CLASS: &'static str = "java/lang/String";
+
+    fn into_java(self, heap: &mut Heap) -> HeapValue {
+        heap.alloc_string(self)
+    }
+}
+
+impl IntoJava for String {
+    const KIND: u8 = b'L';
+    const CLASS: &'static str = "java/lang/String";
+
+    fn into_java(self, heap: &mut Heap) -> HeapValue {
+        heap.alloc_string(&self)
+    }
+}
+
+impl FromJava for String {
+    const KIND: u8 = b'L';
+
+    fn from_java(value: Option<HeapValue>, heap: &Heap) -> Result<Self, VmError> {
+        value
+            .as_ref()
+            .and_then(|value| heap.string_value(value))
+            .ok_or_else(|| VmError::Conversion(format!("cannot convert {:?} to String", value)))
+    }
+}
+
+impl IntoJava for JavaObject {
+    const KIND: u8 = b'L';
+
+    fn into_java(self, _heap: &mut Heap) -> HeapValue {
+        self.value
+    }
+}
+
+impl IntoJava for &JavaObject {
+    const KIND: u8 = b'L';
+
+    fn into_java(self, _heap: &mut Heap) -> HeapValue {
+        self.value.clone()
+    }
+}
+
+impl FromJava for JavaObject {
+    const KIND: u8 = b'L';
+
+    fn from_java(value: Option<HeapValue>, heap: &Heap) -> Result<Self, VmError> {
+        match value {
+            Some(value) if !value.is_null() && value.is_object() => {
+                Ok(JavaObject::new(value, heap))
+            }
+            other => Err(VmError::Conversion(format!(
+                "cannot convert {:?} to JavaObject",
+                other
+            ))),
+        }
+    }
+}
+
+/// `None` is `null`.
+impl<T: IntoJava> IntoJava for Option<T> {
+    const KIND: u8 = T::KIND;
+    const CLASS: &'static str = T::CLASS;
+
+    fn into_java(self, heap: &mut Heap) -> HeapValue {
+        match self {
+            Some(value) => value.into_java(heap),
+            None => HeapValue::Null,
+        }
+    }
+}
+
+impl<T: FromJava> FromJava for Option<T> {
+    const KIND: u8 = T::KIND;
+
+    fn from_java(value: Option<HeapValue>, heap: &Heap) -> Result<Self, VmError> {
+        match value {
+            None | Some(HeapValue::Null) => Ok(None),
+            value => T::from_java(value, heap).map(Some),
+        }
+    }
+}
+
+/// A `Vec` is a Java array of the element type.
+impl<T: IntoJava> IntoJava for Vec<T> {
+    const KIND: u8 = b'L';
+
+    fn into_java(self, heap: &mut Heap) -> HeapValue {
+        let mut array = match T::KIND {
+            b'Z' => heap.alloc_array(self.len(), ArrayType::Boolean),
+            b'B' => heap.alloc_array(self.len(), ArrayType::Byte),
+            b'S' => heap.alloc_array(self.len(), ArrayType::Short),
+            b'C' => heap.alloc_array(self.len(), ArrayType::Char),
+            b'I' => heap.alloc_array(self.len(), ArrayType::Int),
+            b'J' => heap.alloc_array(self.len(), ArrayType::Long),
+            b'F' => heap.alloc_array(self.len(), ArrayType::Float),
+            b'D' => heap.alloc_array(self.len(), ArrayType::Double),
+            _ => heap.alloc_reference_array(self.len(), T::CLASS),
+        };
+        array.content = self.into_iter().map(|v| v.into_java(heap)).collect();
+        if let Some(real) = heap.get_array_mut(array.id) {
+            real.content = array.content.clone();
+        }
+        HeapValue::Array(array)
+    }
+}
+
+impl<T: FromJava> FromJava for Vec<T> {
+    const KIND: u8 = b'L';
+
+    fn from_java(value: Option<HeapValue>, heap: &Heap) -> Result<Self, VmError> {
+        let Some(HeapValue::Array(array)) = &value else {
+            return Err(VmError::Conversion(format!(
+                "cannot convert {:?} to Vec",
+                value
+            )));
+        };
+        let content = heap
+            .get_array(array.id)
+            .map_or(&array.content, |real| &real.content);
+        content
+            .iter()
+            .map(|element| T::from_java(Some(element.clone()), heap))
+            .collect()
+    }
+}
+
+/// The arguments of a call: a tuple of [`IntoJava`] values.
+pub trait JavaArgs {
+    fn kinds() -> Vec<u8>;
+    fn into_java(self, heap: &mut Heap) -> Vec<HeapValue>;
+}
+
+macro_rules! java_args {
+    ($($name:ident: $ty:ident),*) => {
+        impl<$($ty: IntoJava),*> JavaArgs for ($($ty,)*) {
+            fn kinds() -> Vec<u8> {
+                vec![$($ty::KIND),*]
+            }
+
+            #[allow(unused_variables)]
+            fn into_java(self, heap: &mut Heap) -> Vec<HeapValue> {
+                let ($($name,)*) = self;
+                vec![$($name.into_java(heap)),*]
+            }
+        }
+    };
+}
+
+java_args!();
+java_args!(a: A);
+java_args!(a: A, b: B);
+java_args!(a: A, b: B, c: C);
+java_args!(a: A, b: B, c: C, d: D);
+java_args!(a: A, b: B, c: C, d: D, e: E);
+java_args!(a: A, b: B, c: C, d: D, e: E, f: F);
+
+/// What a typed native returns: an [`IntoJava`] value, or `Err` to throw.
+pub trait NativeResult {
+    const KIND: u8;
+
+    fn into_native(self, env: &mut NativeEnv) -> Option<HeapValue>;
+}
+
+impl<T: IntoJava> NativeResult for T {
+    const KIND: u8 = T::KIND;
+
+    fn into_native(self, env: &mut NativeEnv) -> Option<HeapValue> {
+        let value = self.into_java(env.heap);
+        (T::KIND != b'V').then_some(value)
+    }
+}
+
+impl<T: IntoJava> NativeResult for Result<T, Throw> {
+    const KIND: u8 = T::KIND;
+
+    fn into_native(self, env: &mut NativeEnv) -> Option<HeapValue> {
+        match self {
+            Ok(value) => value.into_native(env),
+            Err(throw) => {
+                env.interpreter
+                    .throw_new(env.heap, &throw.class_name, throw.message.as_deref());
+                None
+            }
+        }
+    }
+}
+
+/// A Rust closure usable as a Java `native` method, taking [`FromJava`]
+/// arguments. The receiver of an instance method is not passed; use
+/// [`VmBuilder::native`] when it is needed.
+pub trait NativeFunction<Args>: 'static {
+    fn kinds() -> (Vec<u8>, u8);
+    fn into_native(self) -> NativeMethod;
+}
+
+macro_rules! native_function {
+    ($($name:ident: $ty:ident),*) => {
+        impl<Func, Ret, $($ty),*> NativeFunction<($($ty,)*)> for Func
+        where
+            Func: Fn($($ty),*) -> Ret + 'static,
+            Ret: NativeResult,
+            $($ty: FromJava,)*
+        {
+            fn kinds() -> (Vec<u8>, u8) {
+                (vec![$($ty::KIND),*], Ret::KIND)
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn into_native(self) -> NativeMethod {
+                Rc::new(move |env, _receiver, args| {
+                    let mut args = args.iter().cloned();
+                    $(
+                        let $name = match $ty::from_java(args.next(), env.heap) {
+                            Ok(value) => value,
+                            Err(e) => {
+                                env.interpreter.throw_new(
+                                    env.heap,
+                                    "java/lang/IllegalArgumentException",
+                                    Some(&e.to_string()),
+                                );
+                                return None;
+                            }
+                        };
+                    )*
+                    self($($name),*).into_native(env)
+                })
+            }
+        }
+    };
+}
+
+native_function!();
+native_function!(a: A);
+native_function!(a: A, b: B);
+native_function!(a: A, b: B, c: C);
+native_function!(a: A, b: B, c: C, d: D);
+
+/// Checks Rust argument and result kinds against a method descriptor.
+fn check_signature(
+    class_name: &str,
+    method_name: &str,
+    descriptor: &str,
+    args: &[u8],
+    ret: u8,
+) -> Result<(), VmError> {
+    let (params, returns) = signature_kinds(descriptor);
+    if params != args || returns != ret {
+        return Err(VmError::SignatureMismatch(format!(
+            "Rust types ({}) -> {} do not match {}",
+            args.iter()
+                .map(|&k| char::from(k).to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            char::from(ret),
+            describe_method(class_name, method_name, descriptor)
+        )));
+    }
+    Ok(())
+}
+
+/// Configures a [`Vm`].
+#[derive(Default)]
+pub struct VmBuilder {
+    classpath: Vec<PathBuf>,
+    properties: Vec<(String, String)>,
+    heap_size: Option<usize>,
+    skip_verification: bool,
+    jit_mode: Option<JitMode>,
+    natives: Vec<(String, String, String, NativeMethod)>,
+    error: Option<VmError>,
+}
+
+impl VmBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a directory to search for classes, after the current directory.
+    pub fn classpath(mut self, path: impl Into<PathBuf>) -> Self {
+        self.classpath.push(path.into());
+        self
+    }
+
+    /// Sets a system property, as `-Dname=value` does.
+    pub fn property(mut self, name: &str, value: &str) -> Self {
+        self.properties.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The most the live objects may take, in bytes, as `-Xmx` does.
+    /// Exceeding it throws `OutOfMemoryError`.
+    pub fn heap_size(mut self, bytes: usize) -> Self {
+        self.heap_size = Some(bytes);
+        self
+    }
+
+    /// Whether loaded classes are verified, on by default; `false` is
+    /// `-Xverify:none`.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.skip_verification = !verify;
+        self
+    }
+
+    /// When methods are compiled; `JitMode::Interpreted` is `-Xint` and
+    /// `JitMode::Compiled` is `-Xcomp`.
+    pub fn jit_mode(mut self, mode: JitMode) -> Self {
+        self.jit_mode = Some(mode);
+        self
+    }
+
+    /// Binds a raw native, with the same signature as
+    /// [`Interpreter::register_native`].
+    pub fn native<F>(
+        mut self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        method: F,
+    ) -> Self
+    where
+        F: Fn(&mut NativeEnv, Option<&HeapValue>, &[HeapValue]) -> Option<HeapValue> + 'static,
+    {
+        self.natives.push((
+            class_name.to_string(),
+            method_name.to_string(),
+            descriptor.to_string(),
+            Rc::new(method),
+        ));
+        self
+    }
+
+    /// Binds a closure over Rust values. `build` fails if its types do not
+    /// match `descriptor`.
+    pub fn function<Args, F>(
+        mut self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        function: F,
+    ) -> Self
+    where
+        F: NativeFunction<Args>,
+    {
+        let (args, ret) = F::kinds();
+        if let Err(e) = check_signature(class_name, method_name, descriptor, &args, ret) {
+            self.error.get_or_insert(e);
+            return self;
+        }
+        self.natives.push((
+            class_name.to_string(),
+            method_name.to_string(),
+            descriptor.to_string(),
+            function.into_native(),
+        ));
+        self
+    }
+
+    pub fn build(self) -> Result<Vm, VmError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let mut loader = ClassLoader::new();
+        loader.set_verify(!self.skip_verification);
+        for entry in &self.classpath {
+            loader.add_classpath(entry);
+        }
+        let interpreter = Interpreter::new(false);
+        if let Some(mode) = self.jit_mode {
+            interpreter.set_jit_mode(mode);
+        }
+        if !self.classpath.is_empty() {
+            let joined = std::env::join_paths(&self.classpath)
+                .map(|paths| paths.to_string_lossy().to_string())
+                .unwrap_or_default();
+            interpreter.set_property("java.class.path", &joined);
+        }
+        for (name, value) in &self.properties {
+            interpreter.set_property(name, value);
+        }
+        for (class_name, method_name, descriptor, method) in self.natives {
+            interpreter.bind_native(&class_name, &method_name, &descriptor, method);
+        }
+        let heap = match self.heap_size {
+            Some(bytes) => Heap::with_max_size(bytes),
+            None => Heap::new(),
+        };
+        Ok(Vm {
+            interpreter,
+            loader,
+            heap,
+        })
+    }
+}
+
+/// An embedded VM. Dropping it runs the shutdown hooks.
+pub struct Vm {
+    interpreter: Interpreter,
+    loader: ClassLoader,
+    heap: Heap,
+}
+
+impl Vm {
+    pub fn builder() -> VmBuilder {
+        VmBuilder::new()
+    }
+
+    pub fn property(&self, name: &str) -> Option<String> {
+        self.interpreter.property(name)
+    }
+
+    pub fn set_property(&self, name: &str, value: &str) -> Option<String> {
+        self.interpreter.set_property(name, value)
+    }
+
+    /// Loads and initializes a class.
+    pub fn load_class(&mut self, class_name: &str) -> Result<(), VmError> {
+        let class_name = class_name.replace('.', "/");
+        match self.loader.load_class(&class_name) {
+            // Verification errors are thrown as VerifyError below.
+            Ok(_) | Err(LoadError::Verify(_)) => {}
+            Err(_) if is_builtin_class(&class_name) => {}
+            Err(_) => return Err(VmError::ClassNotFound(class_name.replace('/', "."))),
+        }
+        self.interpreter
+            .ensure_class_initialized(&mut self.loader, &class_name, &mut self.heap);
+        self.finish::<()>(None)
+    }
+
+    /// Replaces the method bodies of a loaded class with those of `bytes`,
+    /// a class file, keeping its instances and statics. Methods already
+    /// running finish on the old code.
+    pub fn redefine_class(&mut self, class_name: &str, bytes: &[u8]) -> Result<(), VmError> {
+        let class_name = class_name.replace('.', "/");
+        match self
+            .interpreter
+            .redefine_class(&mut self.loader, &class_name, bytes)
+        {
+            Ok(()) => Ok(()),
+            Err(LoadError::NotFound(name)) => Err(VmError::ClassNotFound(name)),
+            Err(e) => Err(VmError::Redefinition(e.to_string())),
+        }
+    }
+
+    /// A `java.lang.String` holding `value`.
+    pub fn new_string(&mut self, value: &str) -> JavaObject {
+        let value = self.heap.alloc_string(value);
+        JavaObject::new(value, &self.heap)
+    }
+
+    /// `new class_name(args)`, running the constructor with `descriptor`.
+    pub fn new_object<A: JavaArgs>(
+        &mut self,
+        class_name: &str,
+        descriptor: &str,
+        args: A,
+    ) -> Result<JavaObject, VmError> {
+        let class_name = class_name.replace('.', "/");
+        self.load_class(&class_name)?;
+        check_signature(&class_name, "<init>", descriptor, &A::kinds(), b'V')?;
+        self.lookup(&class_name, "<init>", descriptor, false)?;
+        let object = HeapValue::Object(self.heap.alloc_object(&class_name));
+        let args = args.into_java(&mut self.heap);
+        self.interpreter.invoke_nonvirtual(
+            &mut self.loader,
+            &mut self.heap,
+            &class_name,
+            "<init>",
+            descriptor,
+            Some(object.clone()),
+            &args,
+        );
+        self.finish::<()>(None)?;
+        Ok(JavaObject::new(object, &self.heap))
+    }
+
+    /// Calls a static method and converts its result.
+    pub fn call_static<R: FromJava>(
+        &mut self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        args: impl JavaArgs,
+    ) -> Result<R, VmError> {
+        self.call(None, class_name, method_name, descriptor, args)
+    }
+
+    /// Calls an instance method, dispatching on the receiver's class.
+    pub fn call_method<R: FromJava>(
+        &mut self,
+        receiver: &JavaObject,
+        method_name: &str,
+        descriptor: &str,
+        args: impl JavaArgs,
+    ) -> Result<R, VmError> {
+        let class_name = receiver.class_name();
+        self.call(Some(receiver), &class_name, method_name, descriptor, args)
+    }
+
+    fn call<A: JavaArgs, R: FromJava>(
+        &mut self,
+        receiver: Option<&JavaObject>,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        args: A,
+    ) -> Result<R, VmError> {
+        let class_name = class_name.replace('.', "/");
+        check_signature(&class_name, method_name, descriptor, &A::kinds(), R::KIND)?;
+        if receiver.is_none() {
+            self.load_class(&class_name)?;
+        }
+        self.lookup(&class_name, method_name, descriptor, receiver.is_none())?;
+        let args = args.into_java(&mut self.heap);
+        let result = match receiver {
+            Some(receiver) => self.interpreter.invoke_virtual(
+                &mut self.loader,
+                &mut self.heap,
+                receiver.as_value(),
+                method_name,
+                descriptor,
+                &args,
+            ),
+            None => self.interpreter.invoke_nonvirtual(
+                &mut self.loader,
+                &mut self.heap,
+                &class_name,
+                method_name,
+                descriptor,
+                None,
+                &args,
+            ),
+        };
+        self.finish(result)
+    }
+
+    /// Fails with `NoSuchMethod` unless `class_name` has the method, static
+    /// or not as asked.
+    fn lookup(
+        &mut self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        is_static: bool,
+    ) -> Result<(), VmError> {
+        match self
+            .interpreter
+            .find_method(&mut self.loader, class_name, method_name, descriptor)
+        {
+            Some((_, flags))
+                if is_builtin_class(class_name) || (flags & ACC_STATIC != 0) == is_static =>
+            {
+                Ok(())
+            }
+            _ => Err(VmError::NoSuchMethod(describe_method(
+                class_name,
+                method_name,
+                descriptor,
+            ))),
+        }
+    }
+
+    /// Turns a pending exception into `VmError::Exception`, or converts the
+    /// result.
+    fn finish<R: FromJava>(&mut self, result: Option<HeapValue>) -> Result<R, VmError> {
+        java_io_printstream::flush_all();
+        if let Some(exception) = self.interpreter.take_pending_exception() {
+            let mut env = NativeEnv {
+                interpreter: &self.interpreter,
+                loader: &mut self.loader,
+                heap: &mut self.heap,
+            };
+            let stack_trace = java_lang_throwable::describe(&mut env, &exception);
+            let (class_name, message) = match &exception {
+                HeapValue::Object(obj) => (
+                    obj.class_name.replace('/', "."),
+                    self.heap
+                        .get(obj.id)
+                        .and_then(|real| real.get_field("detailMessage"))
+                        .and_then(|message| self.heap.string_value(message)),
+                ),
+                other => (format!("{:?}", other), None),
+            };
+            return Err(VmError::Exception(JavaException {
+                class_name,
+                message,
+                stack_trace,
+            }));
+        }
+        R::from_java(result, &self.heap)
+    }
+}
+
+impl Drop for Vm {
+    fn drop(&mut self) {
+        self.interpreter
+            .run_shutdown_hooks(&mut self.loader, &mut self.heap);
+        java_io_printstream::flush_all();
+    }
+}
@@ -0,0 +1,230 @@
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+const ANNOS: &str = r#"
+import java.lang.annotation.Annotation;
+import java.lang.annotation.Inherited;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.reflect.Constructor;
+import java.lang.reflect.Field;
+import java.lang.reflect.Method;
+
+public class Annos {
+    enum Color { RED, GREEN, BLUE }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Info {
+        String name();
+        int count() default 3;
+        long big() default 1L << 40;
+        boolean flag() default true;
+        char letter() default 'q';
+        byte small() default 5;
+        short mid() default 300;
+        float ratio() default 1.5f;
+        double precise() default 2.25;
+        Color color() default Color.GREEN;
+        Class<?> kind() default String.class;
+        int[] numbers() default {};
+        String[] tags() default {"a", "b"};
+        Tag nested() default @Tag("inner");
+        Tag[] more() default {};
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @Inherited
+    @interface Tag {
+        String value();
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Marker {
+    }
+
+    @Retention(RetentionPolicy.CLASS)
+    @interface Invisible {
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Shapes {
+        char c();
+        byte b();
+        long l();
+        float f();
+        double d();
+        String s();
+        Class<?> k();
+        Color e();
+        int[] a();
+        Tag n();
+        boolean z();
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Numbers {
+        long l();
+        float f();
+        double d();
+        boolean[] z();
+        char c();
+        byte b();
+    }
+
+    @Info(name = "base", count = 7, color = Color.BLUE, kind = int[].class,
+          numbers = {1, 2, 3}, more = {@Tag("x"), @Tag("y")})
+    @Tag("base-tag")
+    @Marker
+    @Invisible
+    static class Base {
+        @Tag("field")
+        public int value;
+
+        @Tag("ctor")
+        public Base() {
+        }
+
+        @Marker
+        public void run(@Tag("first") String a, int b, @Marker @Tag("third") long c) {
+        }
+    }
+
+    static class Child extends Base {
+    }
+
+    @Tag("own")
+    static class Renamed extends Base {
+    }
+
+    @Info(name = "base", count = 7, color = Color.BLUE, kind = int[].class,
+          numbers = {1, 2, 3}, more = {@Tag("x"), @Tag("y")})
+    static class Twin {
+    }
+
+    @Info(name = "other")
+    static class Other {
+    }
+
+    @Shapes(c = '\n', b = (byte) 0x1f, l = 2L, f = 1.5f, d = 0.1, s = "q\"t", k = String[].class,
+            e = Color.RED, a = {4, 5}, n = @Tag("z"), z = false)
+    static class Shaped {
+    }
+
+    @Numbers(l = 1L << 40, f = 1.5f, d = 0.1, z = {true, false}, c = 'x', b = (byte) -2)
+    static class Numbered {
+    }
+
+    public static void main(String[] args) throws Exception {
+        Info info = Base.class.getAnnotation(Info.class);
+        System.out.println("r name " + info.name() + " " + info.count() + " " + info.big());
+        System.out.println("r flag " + (info.flag() ? "yes" : "no") + " " + info.small() + " " + info.mid()
+                + " " + (info.letter() == 'q' ? "q" : "?"));
+        System.out.println("r floats " + info.ratio() + " " + info.precise());
+        System.out.println("r enum " + info.color().name() + " " + info.color().ordinal() + " "
+                + (info.color() == Color.BLUE ? "same" : "different"));
+        System.out.println("r class " + info.kind().getName());
+        int[] numbers = info.numbers();
+        numbers[0] = 99;
+        System.out.println("r numbers " + numbers.length + " " + info.numbers()[0]);
+        System.out.println("r tags " + info.tags().length + " " + info.tags()[1]);
+        System.out.println("r nested " + info.nested().value() + " " + info.more()[1].value());
+        System.out.println("r type " + info.annotationType().getName() + " "
+                + (info instanceof Info ? "yes" : "no") + " " + (info instanceof Annotation ? "yes" : "no"));
+        System.out.println("r tag " + Base.class.getAnnotation(Tag.class).toString());
+        System.out.println("r shapes " + Shaped.class.getAnnotation(Shapes.class).toString());
+
+        System.out.println("r count " + Base.class.getAnnotations().length);
+        System.out.println("r present " + (Base.class.isAnnotationPresent(Marker.class) ? "yes" : "no") + " "
+                + (Base.class.isAnnotationPresent(Invisible.class) ? "yes" : "no"));
+        System.out.println("r inherited " + Child.class.getAnnotations().length + " "
+                + Child.class.getAnnotation(Tag.class).value() + " " + Child.class.getDeclaredAnnotations().length
+                + " " + (Child.class.getAnnotation(Info.class) == null ? "null" : "found"));
+        System.out.println("r renamed " + Renamed.class.getAnnotation(Tag.class).value() + " "
+                + Renamed.class.getAnnotations().length);
+
+        Info twin = Twin.class.getAnnotation(Info.class);
+        Info other = Other.class.getAnnotation(Info.class);
+        System.out.println("r equals " + (info.equals(twin) ? "yes" : "no") + " " + (info.equals(other) ? "yes" : "no")
+                + " " + (info.hashCode() == twin.hashCode() ? "yes" : "no") + " " + (info.equals("x") ? "yes" : "no"));
+        System.out.println("r hash " + Base.class.getAnnotation(Tag.class).hashCode() + " "
+                + Base.class.getAnnotation(Marker.class).hashCode() + " "
+                + Numbered.class.getAnnotation(Numbers.class).hashCode());
+
+        Field field = Base.class.getDeclaredField("value");
+        System.out.println("r field " + field.getAnnotation(Tag.class).value() + " " + field.getAnnotations().length);
+        Method run = Base.class.getDeclaredMethod("run", String.class, int.class, long.class);
+        Annotation[][] params = run.getParameterAnnotations();
+        System.out.println("r params " + params.length + " " + params[0].length + " " + params[1].length + " "
+                + params[2].length + " " + ((Tag) params[2][1]).value());
+        System.out.println("r method " + (run.isAnnotationPresent(Marker.class) ? "yes" : "no") + " "
+                + run.getDeclaredAnnotations().length);
+        Constructor<?> ctor = Base.class.getDeclaredConstructor();
+        System.out.println("r ctor " + ctor.getAnnotation(Tag.class).value() + " " + ctor.getParameterAnnotations().length);
+        Method count = Info.class.getDeclaredMethod("count");
+        System.out.println("r default " + ((Integer) count.getDefaultValue()).intValue() + " "
+                + (Info.class.getDeclaredMethod("name").getDefaultValue() == null ? "none" : "some"));
+        System.out.println("r isAnnotation " + (Info.class.isAnnotation() ? "yes" : "no") + " "
+                + (Base.class.isAnnotation() ? "yes" : "no"));
+
+        System.out.println("r values " + Color.valueOf("RED").ordinal() + " " + Color.values().length + " "
+                + Color.BLUE.compareTo(Color.RED) + " " + Color.GREEN.toString());
+        try {
+            Color.valueOf("PINK");
+        } catch (IllegalArgumentException e) {
+            System.out.println("r bad " + e.getMessage());
+        }
+    }
+}
+"#;
+
+#[test]
+fn annotations_are_materialized_as_proxies() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("annos");
+    compile_java(&dir, "Annos.java", ANNOS);
+
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "Annos"]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            results(&output),
+            [
+                "name base 7 1099511627776",
+                "flag yes 5 300 q",
+                "floats 1.5 2.25",
+                "enum BLUE 2 same",
+                "class [I",
+                "numbers 3 1",
+                "tags 2 b",
+                "nested inner y",
+                "type Annos$Info yes yes",
+                "tag @Annos$Tag(\"base-tag\")",
+                "shapes @Annos$Shapes(c='\\n', b=(byte)0x1f, l=2L, f=1.5f, d=0.1, s=\"q\\\"t\", k=java.lang.String[].class, e=RED, a={4, 5}, n=@Annos$Tag(\"z\"), z=false)",
+                "count 3",
+                "present yes no",
+                "inherited 1 base-tag 0 null",
+                "renamed own 1",
+                "equals yes no yes no",
+                "hash -691122799 0 -438224205",
+                "field field 1",
+                "params 3 1 0 2 third",
+                "method yes 1",
+                "ctor ctor 0",
+                "default 3 none",
+                "isAnnotation yes no",
+                "values 0 3 2 GREEN",
+                "bad No enum constant Annos.Color.PINK",
+            ],
+            "{}",
+            mode
+        );
+    }
+}
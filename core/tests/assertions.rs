@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+mod common;
+
+use common::{has_javac, results, run_aria, temp_dir};
+
+fn compile_java(temp_dir: &Path, sources: &[(&str, &str)]) {
+    let mut command = Command::new("javac");
+    command
+        .arg("--release")
+        .arg("17")
+        .arg("-d")
+        .arg(".")
+        .current_dir(temp_dir);
+    for (file_name, source) in sources {
+        let file_path = temp_dir.join(file_name);
+        fs::write(&file_path, source).expect("write java source");
+        command.arg(file_path);
+    }
+    let output = command.output().expect("spawn javac");
+
+    assert!(
+        output.status.success(),
+        "javac failed:\nstdout={}\nstderr={}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+const MAIN: &str = r#"
+package app;
+
+class Checks {
+    static String run(int x) {
+        try {
+            assert x > 0 : x;
+            return "ok";
+        } catch (AssertionError e) {
+            return "failed " + e.getMessage();
+        }
+    }
+
+    static class Inner {
+        static String run() {
+            try {
+                assert false;
+                return "ok";
+            } catch (AssertionError e) {
+                return "failed " + e.getMessage();
+            }
+        }
+    }
+}
+
+public class Main {
+    public static void main(String[] args) {
+        System.out.println("r " + Checks.run(-1) + " " + Checks.Inner.run() + " " + app.util.Util.run());
+        assert args.length == 0 : "unexpected " + args.length + " args";
+        System.out.println("r done");
+    }
+}
+"#;
+
+const UTIL: &str = r#"
+package app.util;
+
+public class Util {
+    public static String run() {
+        try {
+            assert 1 > 2 : "util";
+            return "ok";
+        } catch (AssertionError e) {
+            return "failed " + e.getMessage();
+        }
+    }
+}
+"#;
+
+fn compiled(tag: &str) -> std::path::PathBuf {
+    let dir = temp_dir(tag);
+    compile_java(&dir, &[("Util.java", UTIL), ("Main.java", MAIN)]);
+    dir
+}
+
+#[test]
+fn assertions_follow_package_and_class_flags() {
+    if !has_javac() {
+        return;
+    }
+    let dir = compiled("granularity");
+    let cases: &[(&[&str], &str)] = &[
+        (&[], "ok ok ok"),
+        (&["-ea"], "failed -1 failed null failed util"),
+        (&["-ea", "-da:app.util..."], "failed -1 failed null ok"),
+        (&["-enableassertions:app.util..."], "ok ok failed util"),
+        (&["-ea:app.Checks"], "failed -1 failed null ok"),
+        (&["-ea:app...", "-da:app.Checks"], "ok ok failed util"),
+        (&["-ea:..."], "ok ok ok"),
+    ];
+    for (flags, expected) in cases {
+        let mut args = flags.to_vec();
+        args.push("app.Main");
+        let output = run_aria(&dir, &args);
+        assert_eq!(
+            results(&output).first().map(String::as_str),
+            Some(*expected),
+            "{:?}",
+            flags
+        );
+    }
+}
+
+#[test]
+fn failed_assert_throws_assertion_error_with_detail() {
+    if !has_javac() {
+        return;
+    }
+    let dir = compiled("detail");
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "-ea", "app.Main", "extra"]);
+        assert_eq!(output.status.code(), Some(1), "{}", mode);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(
+                "Exception in thread \"main\" java.lang.AssertionError: unexpected 1 args"
+            ),
+            "{}: {}",
+            mode,
+            stderr
+        );
+        assert!(!results(&output).contains(&"done".to_string()));
+
+        let disabled = run_aria(&dir, &[mode, "app.Main", "extra"]);
+        assert!(disabled.status.success(), "{}", mode);
+        assert_eq!(results(&disabled), ["ok ok ok", "done"]);
+    }
+}
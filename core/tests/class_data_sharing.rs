@@ -0,0 +1,369 @@
+use aria_core::bytecode::parser::ClassFile;
+use aria_core::loader::class_loader::ClassLoader;
+use aria_core::loader::shared_archive::SharedArchive;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+fn run_shared(dir: &Path, archive: &Path, args: &[&str]) -> Output {
+    let archive_option = format!("-XX:SharedArchiveFile={}", archive.display());
+    let mut all = vec![archive_option.as_str()];
+    all.extend_from_slice(args);
+    run_aria(dir, &all)
+}
+
+fn shared_loads(output: &Output) -> usize {
+    String::from_utf8_lossy(&output.stdout)
+        .matches("source: shared objects file")
+        .count()
+}
+
+const SHAPES: &str = r#"
+interface Shape { int area(); }
+class Square implements Shape {
+    private final int side;
+    Square(int side) { this.side = side; }
+    public int area() { return side * side; }
+}
+class Rect extends Square {
+    private final int other;
+    Rect(int side, int other) { super(side); this.other = other; }
+    public int area() { return super.area() / 2 + other; }
+}
+public class Main {
+    static int total(Shape[] shapes) {
+        int sum = 0;
+        for (int i = 0; i < shapes.length; i++) sum += shapes[i].area();
+        return sum;
+    }
+    public static void main(String[] args) {
+        Shape[] shapes = { new Square(3), new Rect(4, 5), new Square(10) };
+        System.out.println("r " + total(shapes));
+    }
+}
+"#;
+
+#[test]
+fn dumped_archive_replaces_class_path_loading() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("dump");
+    compile_java(&dir, "Main.java", SHAPES);
+    let archive = dir.join("app.jsa");
+
+    let dump = run_shared(&dir, &archive, &["-Xshare:dump"]);
+    assert!(
+        dump.status.success(),
+        "{}",
+        String::from_utf8_lossy(&dump.stderr)
+    );
+    assert!(String::from_utf8_lossy(&dump.stdout).contains("Dumped 4 classes"));
+
+    let shared = run_shared(&dir, &archive, &["-Xshare:on", "Main"]);
+    assert!(shared.status.success());
+    assert_eq!(results(&shared), ["122"]);
+    let stdout = String::from_utf8_lossy(&shared.stdout);
+    for class in ["Main", "Square", "Rect"] {
+        let line = format!("Loading class: {} source: shared objects file", class);
+        assert!(stdout.contains(&line), "{}", stdout);
+    }
+
+    let unshared = run_shared(&dir, &archive, &["-Xshare:off", "Main"]);
+    assert_eq!(results(&unshared), ["122"]);
+    assert_eq!(shared_loads(&unshared), 0);
+}
+
+const FEATURES: &str = r#"
+import java.lang.annotation.*;
+import java.util.function.IntSupplier;
+
+@Retention(RetentionPolicy.RUNTIME)
+@interface Tag {
+    String value() default "none";
+    int[] sizes() default {1, 2};
+    Class<?> kind() default Object.class;
+    ElementType where() default ElementType.FIELD;
+}
+
+record Point(int x, int y) {}
+
+enum Color { RED, GREEN }
+
+@Tag(value = "main", sizes = {3}, kind = String.class, where = ElementType.TYPE)
+public class Features {
+    static final long BIG = 1L << 40;
+    static final double HALF = 0.5;
+    static final float THIRD = 1f / 3;
+    @Deprecated
+    static int counter;
+
+    class Inner {
+        int get() { return counter; }
+    }
+
+    static <T extends Comparable<T>> T max(T a, T b) throws IllegalStateException {
+        return a.compareTo(b) >= 0 ? a : b;
+    }
+
+    static int guarded(int divisor) {
+        try {
+            return 10 / divisor;
+        } catch (ArithmeticException e) {
+            return -1;
+        }
+    }
+
+    static IntSupplier supplier() {
+        return () -> counter;
+    }
+
+    public static void main(String[] args) {
+        int sum = new Point(2, 3).x() + Color.GREEN.ordinal();
+        System.out.println("r " + sum + " " + guarded(0) + " " + (BIG >> 40));
+    }
+}
+"#;
+
+#[test]
+fn archive_holds_parsed_classes() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("parsed");
+    compile_java(&dir, "Features.java", FEATURES);
+    let archive_path = dir.join("app.jsa");
+    assert!(run_shared(&dir, &archive_path, &["-Xshare:dump"])
+        .status
+        .success());
+
+    // Classes are archived in parsed form, not as class files to parse.
+    let bytes = fs::read(&archive_path).expect("read archive");
+    assert!(!bytes
+        .windows(4)
+        .any(|word| word == [0xCA, 0xFE, 0xBA, 0xBE]));
+
+    // The launcher's class path: the loader's own ".", then "-cp" after
+    // the default ".".
+    let mut loader = ClassLoader::new();
+    loader.add_classpath(".");
+    loader.add_classpath(&dir);
+    let archive = SharedArchive::open(&archive_path, loader.search_paths()).expect("open archive");
+    let mut compared = 0;
+    for entry in fs::read_dir(&dir).expect("read dir").flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "class") {
+            continue;
+        }
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let Some(archived) = archive.class(&name) else {
+            continue;
+        };
+        let parsed = ClassFile::parse(&path.to_string_lossy()).expect("parse class");
+        assert_eq!(archived, parsed, "{}", name);
+        compared += 1;
+    }
+    assert!(compared >= 5, "only {} classes archived", compared);
+
+    let shared = run_shared(&dir, &archive_path, &["-Xshare:on", "Features"]);
+    let unshared = run_shared(&dir, &archive_path, &["-Xshare:off", "Features"]);
+    let stdout = String::from_utf8_lossy(&shared.stdout);
+    assert!(stdout.contains("Loading class: Features source: shared objects file"));
+    assert_eq!(results(&shared), results(&unshared));
+    assert_eq!(results(&shared).len(), 1);
+}
+
+#[test]
+fn changed_class_path_invalidates_the_archive() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("stale");
+    compile_java(&dir, "Main.java", SHAPES);
+    let archive = dir.join("app.jsa");
+    assert!(run_shared(&dir, &archive, &["-Xshare:dump"])
+        .status
+        .success());
+
+    // A recompiled class must be picked up, not the archived copy.
+    compile_java(
+        &dir,
+        "Main.java",
+        &SHAPES.replace("new Square(10)", "new Square(100)"),
+    );
+    let auto = run_shared(&dir, &archive, &["Main"]);
+    assert_eq!(results(&auto), ["10022"]);
+    assert_eq!(shared_loads(&auto), 0);
+
+    let required = run_shared(&dir, &archive, &["-Xshare:on", "Main"]);
+    assert!(!required.status.success());
+    let stderr = String::from_utf8_lossy(&required.stderr);
+    assert!(
+        stderr.contains("Unable to use shared archive."),
+        "{}",
+        stderr
+    );
+
+    // So must a class added to a class path directory after the dump.
+    assert!(run_shared(&dir, &archive, &["-Xshare:dump"])
+        .status
+        .success());
+    compile_java(&dir, "Extra.java", "public class Extra {}");
+    assert_eq!(shared_loads(&run_shared(&dir, &archive, &["Main"])), 0);
+}
+
+#[test]
+fn archive_is_tied_to_its_class_path() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("paths");
+    compile_java(&dir, "Main.java", SHAPES);
+    let archive = dir.join("app.jsa");
+    assert!(run_shared(&dir, &archive, &["-Xshare:dump"])
+        .status
+        .success());
+
+    let other = temp_dir("paths-other");
+    let cp = format!("{}:{}", dir.display(), other.display());
+    let archive_option = format!("-XX:SharedArchiveFile={}", archive.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .args(["-cp", &cp, &archive_option, "-Xshare:on", "Main"])
+        .output()
+        .expect("run aria_core");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("shared class paths mismatch"));
+
+    let missing = run_shared(&dir, &dir.join("missing.jsa"), &["-Xshare:on", "Main"]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("Specified shared archive not found"));
+    let auto = run_shared(&dir, &dir.join("missing.jsa"), &["Main"]);
+    assert_eq!(results(&auto), ["122"]);
+}
+
+fn shared_stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn tampered_archive_is_verified_again() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("tampered");
+    compile_java(&dir, "Main.java", SHAPES);
+    let archive = dir.join("app.jsa");
+    assert!(run_shared(&dir, &archive, &["-Xshare:dump"])
+        .status
+        .success());
+
+    // `Square.area()`: two field loads, then `imul`, which becomes `fmul`.
+    let mut bytes = fs::read(&archive).expect("read archive");
+    let at = bytes
+        .windows(10)
+        .position(|w| w[0] == 0x2A && w[1] == 0xB4 && w[4] == 0x2A && w[8] == 0x68 && w[9] == 0xAC)
+        .expect("area() code in archive");
+    bytes[at + 8] = 0x6A;
+    fs::write(&archive, &bytes).expect("write archive");
+
+    let shared = run_shared(&dir, &archive, &["-Xshare:on", "Main"]);
+    let stdout = String::from_utf8_lossy(&shared.stdout);
+    assert!(stdout.contains("Loading class: Square source: shared objects file"));
+    assert!(!shared.status.success());
+    assert!(results(&shared).is_empty());
+    assert!(
+        shared_stderr(&shared).contains("java.lang.VerifyError"),
+        "{}",
+        shared_stderr(&shared)
+    );
+}
+
+/// Rewrites the archive at `path` as if its dump had stamped nothing.
+fn drop_stamps(path: &Path) {
+    let bytes = fs::read(path).expect("read archive");
+    let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+    let skip_str = |at: usize| at + 4 + u32_at(at);
+    // Magic, format version, build version and the class path.
+    let mut at = skip_str(12);
+    let entries = u32_at(at);
+    at += 4;
+    for _ in 0..entries {
+        at = skip_str(at);
+    }
+    let stamps_at = at;
+    let mut end = at + 4;
+    for _ in 0..u32_at(stamps_at) {
+        end = skip_str(end) + 16;
+    }
+    let removed = (end - stamps_at - 4) as u64;
+
+    let mut out = bytes[..stamps_at].to_vec();
+    out.extend_from_slice(&0u32.to_le_bytes());
+    let classes = u32_at(end);
+    out.extend_from_slice(&bytes[end..end + 4]);
+    let mut at = end + 4;
+    for _ in 0..classes {
+        let name_end = skip_str(at);
+        out.extend_from_slice(&bytes[at..name_end]);
+        let offset = u64::from_le_bytes(bytes[name_end..name_end + 8].try_into().unwrap());
+        out.extend_from_slice(&(offset - removed).to_le_bytes());
+        out.extend_from_slice(&bytes[name_end + 8..name_end + 16]);
+        at = name_end + 16;
+    }
+    out.extend_from_slice(&bytes[at..]);
+    fs::write(path, out).expect("write archive");
+}
+
+#[test]
+fn archive_without_stamps_is_stale() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("unstamped");
+    compile_java(&dir, "Main.java", SHAPES);
+    let archive = dir.join("app.jsa");
+    assert!(run_shared(&dir, &archive, &["-Xshare:dump"])
+        .status
+        .success());
+    drop_stamps(&archive);
+
+    let required = run_shared(&dir, &archive, &["-Xshare:on", "Main"]);
+    assert!(!required.status.success());
+    let stderr = shared_stderr(&required);
+    assert!(
+        stderr.contains("is not the one used while building the shared archive file"),
+        "{}",
+        stderr
+    );
+    let auto = run_shared(&dir, &archive, &["Main"]);
+    assert_eq!(results(&auto), ["122"]);
+    assert_eq!(shared_loads(&auto), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn archive_writable_by_others_is_refused() {
+    use std::os::unix::fs::PermissionsExt;
+
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("writable");
+    compile_java(&dir, "Main.java", SHAPES);
+    let archive = dir.join("app.jsa");
+    assert!(run_shared(&dir, &archive, &["-Xshare:dump"])
+        .status
+        .success());
+    fs::set_permissions(&archive, fs::Permissions::from_mode(0o666)).expect("chmod");
+
+    let required = run_shared(&dir, &archive, &["-Xshare:on", "Main"]);
+    assert!(!required.status.success());
+    assert!(shared_stderr(&required).contains("is writable by other users"));
+    let auto = run_shared(&dir, &archive, &["Main"]);
+    assert_eq!(results(&auto), ["122"]);
+    assert_eq!(shared_loads(&auto), 0);
+}
@@ -0,0 +1,533 @@
+use aria_core::bytecode::assembler::{Assembler, ValueKind};
+use aria_core::bytecode::attributes::{Attribute, ElementValue, StackMapFrame, VerificationType};
+use aria_core::bytecode::constant_pool::ConstantPoolBuilder;
+use aria_core::bytecode::error::ClassFormatError;
+use aria_core::bytecode::parser::{ClassFile, CodeAttribute, MethodInfo};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+mod common;
+
+use common::temp_dir;
+
+fn seed(name: &str) -> ClassFile {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/corpus/class_parser")
+        .join(name);
+    ClassFile::parse(&path.to_string_lossy()).expect("parse seed")
+}
+
+fn seeds() -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/class_parser");
+    let mut seeds: Vec<(String, Vec<u8>)> = fs::read_dir(&dir)
+        .expect("read seed corpus")
+        .map(|entry| {
+            let path = entry.expect("dir entry").path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            (name, fs::read(&path).expect("read seed"))
+        })
+        .collect();
+    seeds.sort();
+    seeds
+}
+
+/// `class A extends java.lang.Object {}`, assembled by hand.
+fn minimal_class() -> Vec<u8> {
+    let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52, 0, 5];
+    bytes.extend([7, 0, 2]);
+    bytes.extend([1, 0, 1, b'A']);
+    bytes.extend([7, 0, 4]);
+    bytes.extend([1, 0, 16]);
+    bytes.extend(b"java/lang/Object");
+    bytes.extend([0, 0x21, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0]);
+    bytes
+}
+
+#[test]
+fn seed_corpus_parses() {
+    let seeds = seeds();
+    assert!(seeds.len() >= 5, "seed corpus is missing");
+    for (name, bytes) in &seeds {
+        let class =
+            ClassFile::from_bytes(bytes).unwrap_or_else(|e| panic!("{} rejected: {}", name, e));
+        assert!(class.get_class_name(class.this_class).is_some(), "{}", name);
+    }
+    assert!(ClassFile::from_bytes(&minimal_class()).is_ok());
+}
+
+#[test]
+fn truncated_and_mutated_classes_are_rejected_without_panicking() {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for (name, bytes) in seeds() {
+        for len in 0..bytes.len() {
+            match ClassFile::from_bytes(&bytes[..len]) {
+                Err(_) => {}
+                Ok(_) => panic!("{} truncated to {} bytes was accepted", name, len),
+            }
+        }
+        for _ in 0..2000 {
+            let mut mutated = bytes.clone();
+            for _ in 0..=next() % 4 {
+                let at = (next() as usize) % mutated.len();
+                mutated[at] = next() as u8;
+            }
+            let _ = ClassFile::from_bytes(&mutated);
+        }
+    }
+}
+
+#[test]
+fn malformed_classes_report_the_defect() {
+    let class = minimal_class();
+    let with = |at: usize, patch: &[u8]| {
+        let mut bytes = class.clone();
+        bytes[at..at + patch.len()].copy_from_slice(patch);
+        ClassFile::from_bytes(&bytes).err()
+    };
+
+    assert_eq!(
+        with(0, &[0xCA, 0xFE, 0xBA, 0xBF]),
+        Some(ClassFormatError::BadMagic(0xCAFEBABF))
+    );
+    assert_eq!(
+        ClassFile::from_bytes(&class[..20]).err(),
+        Some(ClassFormatError::Truncated { offset: 20 })
+    );
+    // The Class entry #1 pointing at itself.
+    assert_eq!(
+        with(11, &[0, 1]),
+        Some(ClassFormatError::BadConstantIndex {
+            index: 1,
+            expected: "Utf8"
+        })
+    );
+    assert_eq!(
+        with(13, &[2]),
+        Some(ClassFormatError::UnknownConstantTag { index: 2, tag: 2 })
+    );
+    // 0xFF never appears in modified UTF-8, and neither does a raw NUL.
+    assert_eq!(
+        with(16, &[0xFF]),
+        Some(ClassFormatError::IllegalUtf8 { index: 2 })
+    );
+    assert_eq!(
+        with(16, &[0x00]),
+        Some(ClassFormatError::IllegalUtf8 { index: 2 })
+    );
+    // this_class = 0 and super_class naming a Utf8 entry.
+    let this_class = class.len() - 12;
+    assert_eq!(
+        with(this_class, &[0, 0]),
+        Some(ClassFormatError::BadConstantIndex {
+            index: 0,
+            expected: "Class"
+        })
+    );
+    assert_eq!(
+        with(this_class + 2, &[0, 4]),
+        Some(ClassFormatError::BadConstantIndex {
+            index: 4,
+            expected: "Class"
+        })
+    );
+
+    let mut trailing = class.clone();
+    trailing.push(0);
+    assert!(matches!(
+        ClassFile::from_bytes(&trailing),
+        Err(ClassFormatError::Invalid(message)) if message.starts_with("Extra bytes")
+    ));
+
+    // A SourceFile attribute must be exactly two bytes long.
+    let mut attribute = class.clone();
+    attribute[9] = 6;
+    let pool_end = 10 + 3 + 4 + 3 + 19;
+    let entry = [&[1u8, 0, 10][..], b"SourceFile"].concat();
+    attribute.splice(pool_end..pool_end, entry);
+    let count = attribute.len() - 2;
+    attribute.splice(count.., [0, 1, 0, 5, 0, 0, 0, 3, 0, 2, 0]);
+    assert_eq!(
+        ClassFile::from_bytes(&attribute).err(),
+        Some(ClassFormatError::BadAttributeLength {
+            name: "SourceFile".to_string(),
+            length: 3
+        })
+    );
+}
+
+#[test]
+fn standard_attributes_are_decoded() {
+    let class = seed("annotated.class");
+    let utf8 = |index: u16| class.get_utf8(index).unwrap();
+    let class_name = |index: u16| class.get_class_name(index).unwrap();
+    let find = |attributes: &'_ [Attribute], name: &str| {
+        attributes
+            .iter()
+            .find(|attr| attr.name() == Some(name))
+            .cloned()
+            .unwrap_or_else(|| panic!("no {} attribute", name))
+    };
+
+    assert_eq!(class.source_file(), Some("Annotated.java"));
+    match class.attribute("PermittedSubclasses") {
+        Some(Attribute::PermittedSubclasses(classes)) => {
+            assert_eq!(class_name(classes[0]), "Annotated$Leaf")
+        }
+        other => panic!("{:?}", other),
+    }
+    match class.attribute("InnerClasses") {
+        Some(Attribute::InnerClasses(inner)) => assert!(inner
+            .iter()
+            .any(|c| class_name(c.inner_class_info_index) == "Annotated$1"
+                && c.inner_name_index == 0)),
+        other => panic!("{:?}", other),
+    }
+
+    let limit = &class.fields[0];
+    assert_eq!(utf8(limit.name_index), "LIMIT");
+    assert!(matches!(
+        find(&limit.attributes, "ConstantValue"),
+        Attribute::ConstantValue(_)
+    ));
+    assert!(matches!(
+        find(&limit.attributes, "Deprecated"),
+        Attribute::Deprecated
+    ));
+    match find(&limit.attributes, "RuntimeVisibleAnnotations") {
+        Attribute::RuntimeVisibleAnnotations(annotations) => {
+            assert_eq!(utf8(annotations[0].type_index), "Ljava/lang/Deprecated;")
+        }
+        other => panic!("{:?}", other),
+    }
+    let names = &class.fields[1];
+    match find(&names.attributes, "Signature") {
+        Attribute::Signature(index) => {
+            assert_eq!(utf8(index), "Ljava/util/List<Ljava/lang/String;>;")
+        }
+        other => panic!("{:?}", other),
+    }
+    match find(&names.attributes, "RuntimeInvisibleTypeAnnotations") {
+        Attribute::RuntimeInvisibleTypeAnnotations(annotations) => {
+            assert_eq!(annotations[0].target_type, 0x13);
+            assert_eq!(annotations[0].type_path, vec![(3, 0)]);
+            assert_eq!(utf8(annotations[0].annotation.type_index), "LChecked;");
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let run = class
+        .methods
+        .iter()
+        .find(|m| utf8(m.name_index) == "run")
+        .expect("run method");
+    match find(&run.attributes, "Exceptions") {
+        Attribute::Exceptions(classes) => assert_eq!(class_name(classes[0]), "java/io/IOException"),
+        other => panic!("{:?}", other),
+    }
+    match find(&run.attributes, "MethodParameters") {
+        Attribute::MethodParameters(parameters) => {
+            let names: Vec<&str> = parameters.iter().map(|p| utf8(p.name_index)).collect();
+            assert_eq!(names, ["count", "label"]);
+            assert_eq!(parameters[0].access_flags, 0x0010);
+        }
+        other => panic!("{:?}", other),
+    }
+    match find(&run.attributes, "RuntimeVisibleParameterAnnotations") {
+        Attribute::RuntimeVisibleParameterAnnotations(parameters) => {
+            assert_eq!(parameters.len(), 2);
+            assert_eq!(parameters[0][0].elements.len(), 1);
+            assert!(parameters[1].is_empty());
+        }
+        other => panic!("{:?}", other),
+    }
+    let code = run.code.as_ref().expect("code");
+    match find(&code.attributes, "StackMapTable") {
+        Attribute::StackMapTable(frames) => {
+            assert!(
+                matches!(frames[0], StackMapFrame::Append { ref locals, .. } if locals.len() == 1)
+            )
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(
+        matches!(find(&code.attributes, "LineNumberTable"), Attribute::LineNumberTable(lines) if !lines.is_empty())
+    );
+    match find(&code.attributes, "LocalVariableTable") {
+        Attribute::LocalVariableTable(locals) => {
+            let names: Vec<&str> = locals.iter().map(|l| utf8(l.name_index)).collect();
+            assert_eq!(names, ["this", "count", "label", "task"]);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let anonymous = seed("annotated_1.class");
+    match anonymous.attribute("EnclosingMethod") {
+        Some(Attribute::EnclosingMethod {
+            class_index,
+            method_index,
+        }) => {
+            assert_eq!(anonymous.get_class_name(*class_index), Some("Annotated"));
+            assert_eq!(
+                anonymous.get_name_and_type(*method_index),
+                Some(("run", "(ILjava/lang/String;)I"))
+            );
+        }
+        other => panic!("{:?}", other),
+    }
+    let leaf = seed("annotated_leaf.class");
+    match leaf.attribute("RuntimeVisibleAnnotations") {
+        Some(Attribute::RuntimeVisibleAnnotations(annotations)) => {
+            let elements = &annotations[0].elements;
+            assert_eq!(leaf.get_utf8(elements[0].0), Some("value"));
+            assert!(matches!(
+                elements[0].1,
+                ElementValue::Const { tag: b's', .. }
+            ));
+            assert!(matches!(&elements[1].1, ElementValue::Array(values) if values.len() == 1));
+            assert!(matches!(elements[2].1, ElementValue::Class(_)));
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(
+        leaf.attribute("NestHost"),
+        Some(Attribute::NestHost(_))
+    ));
+
+    let tag = seed("tag.class");
+    let defaults: Vec<ElementValue> = tag
+        .methods
+        .iter()
+        .filter_map(|m| match m.attributes.first() {
+            Some(Attribute::AnnotationDefault(value)) => Some(value.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(defaults.len(), 4);
+    assert!(matches!(defaults[3], ElementValue::Enum { .. }));
+
+    let record = seed("shapes_square.class");
+    match record.attribute("Record") {
+        Some(Attribute::Record(components)) => assert!(!components.is_empty()),
+        other => panic!("{:?}", other),
+    }
+    assert!(!seed("shapes.class").bootstrap_methods().is_empty());
+    match seed("module_info.class").attribute("Module") {
+        Some(Attribute::Module(module)) => assert!(!module.requires.is_empty()),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn unknown_attributes_are_preserved() {
+    let mut class = minimal_class();
+    class[9] = 6;
+    let pool_end = 10 + 3 + 4 + 3 + 19;
+    let entry = [&[1u8, 0, 6][..], b"Vendor"].concat();
+    class.splice(pool_end..pool_end, entry);
+    let count = class.len() - 2;
+    class.splice(count.., [0, 1, 0, 5, 0, 0, 0, 3, 1, 2, 3]);
+
+    let parsed = ClassFile::from_bytes(&class).expect("parse");
+    match &parsed.attributes[..] {
+        [Attribute::Unknown { name_index, info }] => {
+            assert_eq!(parsed.get_utf8(*name_index), Some("Vendor"));
+            assert_eq!(info, &[1, 2, 3]);
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn classes_round_trip_through_the_writer() {
+    for (name, bytes) in seeds() {
+        let class = ClassFile::from_bytes(&bytes).expect("parse seed");
+        let written = class
+            .to_bytes()
+            .unwrap_or_else(|e| panic!("{} not written: {}", name, e));
+        assert!(written == bytes, "{} changed on the way through", name);
+        assert_eq!(
+            ClassFile::from_bytes(&written).as_ref(),
+            Ok(&class),
+            "{}",
+            name
+        );
+    }
+
+    let class = seed("shapes.class");
+    let mut pool = ConstantPoolBuilder::from_entries(class.constant_pool.clone());
+    assert_eq!(pool.class("Shapes"), class.this_class);
+    assert_eq!(pool.utf8("Code") as usize, {
+        let slot = class
+            .constant_pool
+            .iter()
+            .position(|e| format!("{:?}", e) == "Utf8(\"Code\")");
+        slot.expect("Code constant") + 1
+    });
+    let before = pool.len();
+    let index = pool.long(-7);
+    assert_eq!(index as usize, before + 1);
+    assert_eq!(pool.len(), before + 2);
+    assert_eq!(pool.long(-7), index);
+    assert_ne!(pool.double(0.0), pool.double(-0.0));
+}
+
+/// Assembles `Built.main`, which sums 1..=10 in a loop, from nothing.
+fn built_class() -> Vec<u8> {
+    let mut pool = ConstantPoolBuilder::new();
+    let this_class = pool.class("Built");
+    let super_class = pool.class("java/lang/Object");
+    let code_name = pool.utf8("Code");
+    let frames_name = pool.utf8("StackMapTable");
+    let system = pool.class("java/lang/System");
+    let out_type = pool.name_and_type("out", "Ljava/io/PrintStream;");
+    let out = pool.field_ref(system, out_type);
+    let print_stream = pool.class("java/io/PrintStream");
+    let print_type = pool.name_and_type("print", "(Ljava/lang/String;)V");
+    let print = pool.method_ref(print_stream, print_type);
+    let println_type = pool.name_and_type("println", "(I)V");
+    let println = pool.method_ref(print_stream, println_type);
+    let label = pool.string("r sum ");
+    assert_eq!(pool.utf8("Code"), code_name);
+
+    let mut asm = Assembler::new();
+    let top = asm.new_label();
+    let end = asm.new_label();
+    asm.push_int(0, &mut pool);
+    asm.store(ValueKind::Int, 1);
+    asm.push_int(1, &mut pool);
+    asm.store(ValueKind::Int, 2);
+    asm.bind(top).unwrap();
+    asm.load(ValueKind::Int, 2);
+    asm.push_int(10, &mut pool);
+    asm.jump(0xa3, end);
+    asm.load(ValueKind::Int, 1);
+    asm.load(ValueKind::Int, 2);
+    asm.emit(0x60);
+    asm.store(ValueKind::Int, 1);
+    asm.emit(0x84);
+    asm.emit_u1(2);
+    asm.emit_u1(1);
+    asm.jump(0xa7, top);
+    asm.bind(end).unwrap();
+    asm.emit(0xb2);
+    asm.emit_u2(out);
+    asm.ldc(label);
+    asm.emit(0xb6);
+    asm.emit_u2(print);
+    asm.emit(0xb2);
+    asm.emit_u2(out);
+    asm.load(ValueKind::Int, 1);
+    asm.emit(0xb6);
+    asm.emit_u2(println);
+    asm.return_value(None);
+
+    let top = asm.offset(top).unwrap() as u16;
+    let end = asm.offset(end).unwrap() as u16;
+    let frames = vec![
+        StackMapFrame::Append {
+            offset_delta: top,
+            locals: vec![VerificationType::Integer, VerificationType::Integer],
+        },
+        StackMapFrame::Same {
+            offset_delta: end - top - 1,
+        },
+    ];
+    let main = MethodInfo {
+        access_flags: 0x0009,
+        name_index: pool.utf8("main"),
+        descriptor_index: pool.utf8("([Ljava/lang/String;)V"),
+        code: Some(CodeAttribute {
+            max_stack: 2,
+            max_locals: 3,
+            code: asm.finish().expect("assemble"),
+            exception_table: Vec::new(),
+            attributes: vec![Attribute::StackMapTable(frames)],
+        }),
+        attributes: Vec::new(),
+    };
+    assert_eq!(frames_name, pool.utf8("StackMapTable"));
+
+    let constant_pool = pool.finish().expect("pool");
+    let class = ClassFile {
+        magic: 0xCAFEBABE,
+        minor_version: 0,
+        major_version: 61,
+        constant_pool_count: constant_pool.len() as u16 + 1,
+        constant_pool,
+        access_flags: 0x0021,
+        this_class,
+        super_class,
+        interfaces: Vec::new(),
+        fields: Vec::new(),
+        methods: vec![main],
+        attributes: Vec::new(),
+    };
+    let bytes = class.to_bytes().expect("write");
+    assert_eq!(ClassFile::from_bytes(&bytes), Ok(class));
+    bytes
+}
+
+#[test]
+fn assembled_class_runs() {
+    let dir = temp_dir("assembled");
+    fs::write(dir.join("Built.class"), built_class()).expect("write class");
+
+    let mut runs = vec![Command::new(env!("CARGO_BIN_EXE_aria_core"))];
+    // The host VM, when there is one, also checks the stack map frames.
+    if Command::new("java").arg("-version").output().is_ok() {
+        runs.push(Command::new("java"));
+    }
+    for mut command in runs {
+        let output = command
+            .arg("-cp")
+            .arg(&dir)
+            .arg("Built")
+            .output()
+            .expect("run class");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let results: Vec<&str> = stdout
+            .lines()
+            .filter_map(|l| l.strip_prefix("r "))
+            .collect();
+        assert_eq!(
+            results,
+            vec!["sum 55"],
+            "{:?} stderr: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn corrupt_main_class_is_reported_not_a_crash() {
+    let dir = temp_dir("classformat");
+    let mut bytes = minimal_class();
+    bytes.truncate(30);
+    fs::write(dir.join("A.class"), &bytes).expect("write class");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg("A")
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("Truncated class file at offset"),
+        "stderr: {}",
+        stderr
+    );
+}
@@ -0,0 +1,393 @@
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+/// The host's view of its plugins, on the class path of both sides.
+const API: &str = r#"
+public interface Api {
+    String describe();
+
+    String lookup() throws Exception;
+}
+"#;
+
+const SHARED: &str = r#"
+public class Shared {
+    public static String accept(Api api) {
+        return "accepted " + api.describe();
+    }
+}
+"#;
+
+/// Plugin classes, compiled apart and handed to the host only as bytes.
+const PLUGIN: &str = r#"
+public class Plugin implements Api {
+    static int count;
+
+    public String describe() {
+        count++;
+        return "plugin " + count + " " + Helper.tag();
+    }
+
+    public String lookup() throws Exception {
+        Class<?> helper = Class.forName("Helper");
+        return helper.getClassLoader() == Plugin.class.getClassLoader() ? "own loader" : "other loader";
+    }
+}
+"#;
+
+const HELPER: &str = r#"
+public class Helper {
+    static String tag() {
+        return "helper";
+    }
+}
+"#;
+
+/// Implements its own copy of `Api`, which a child-first loader defines.
+const CLIENT: &str = r#"
+public class Client implements Api, Runnable {
+    public String describe() {
+        return "client";
+    }
+
+    public String lookup() {
+        return "client";
+    }
+
+    public void run() {
+        System.out.println("r " + Shared.accept(this));
+    }
+}
+"#;
+
+/// A plugin host; `PLUGIN`, `HELPER`, `CLIENT` and `API` are replaced with
+/// the bytes of those class files.
+const MAIN: &str = r#"
+public class Main {
+    static class PluginLoader extends ClassLoader {
+        private final String label;
+        private final byte[][] classes;
+        private final boolean childFirst;
+
+        PluginLoader(String label, byte[][] classes, boolean childFirst) {
+            super(label, ClassLoader.getSystemClassLoader());
+            this.label = label;
+            this.classes = classes;
+            this.childFirst = childFirst;
+        }
+
+        protected Class<?> loadClass(String name, boolean resolve) throws ClassNotFoundException {
+            if (!childFirst) {
+                return super.loadClass(name, resolve);
+            }
+            Class<?> loaded = findLoadedClass(name);
+            if (loaded != null) {
+                return loaded;
+            }
+            try {
+                return findClass(name);
+            } catch (ClassNotFoundException e) {
+                return super.loadClass(name, resolve);
+            }
+        }
+
+        protected Class<?> findClass(String name) throws ClassNotFoundException {
+            for (byte[] bytes : classes) {
+                try {
+                    Class<?> defined = defineClass(name, bytes, 0, bytes.length);
+                    System.out.println("r " + label + " defines " + name);
+                    return defined;
+                } catch (NoClassDefFoundError | SecurityException e) {
+                    // Another class's bytes.
+                }
+            }
+            throw new ClassNotFoundException(name);
+        }
+    }
+
+    static byte[] plugin() {
+        return new byte[] {PLUGIN};
+    }
+
+    static byte[] helper() {
+        return new byte[] {HELPER};
+    }
+
+    static byte[] client() {
+        return new byte[] {CLIENT};
+    }
+
+    static byte[] api() {
+        return new byte[] {API};
+    }
+
+    static String yes(boolean value) {
+        return value ? "yes" : "no";
+    }
+
+    public static void main(String[] args) throws Exception {
+        PluginLoader first = new PluginLoader("first", new byte[][] {plugin(), helper()}, false);
+        PluginLoader second = new PluginLoader("second", new byte[][] {plugin(), helper()}, false);
+        Class<?> a = first.loadClass("Plugin");
+        Class<?> b = second.loadClass("Plugin");
+        System.out.println("r names " + a.getName() + " " + b.getName());
+        System.out.println("r same class " + yes(a == b));
+        System.out.println("r loaders " + yes(a.getClassLoader() == first) + " " + yes(b.getClassLoader() == second));
+        System.out.println("r loaded again " + yes(first.loadClass("Plugin") == a));
+        Api one = (Api) a.getDeclaredConstructor().newInstance();
+        Api two = (Api) b.getDeclaredConstructor().newInstance();
+        System.out.println("r " + one.describe());
+        System.out.println("r " + one.describe());
+        System.out.println("r " + two.describe());
+        System.out.println("r instance " + yes(a.isInstance(one)) + " " + yes(b.isInstance(one)));
+        System.out.println("r api " + yes(a.getInterfaces()[0] == Api.class));
+        System.out.println("r lookup " + one.lookup());
+        System.out.println("r helper " + yes(first.loadClass("Helper") != second.loadClass("Helper")));
+
+        ClassLoader app = ClassLoader.getSystemClassLoader();
+        ClassLoader platform = ClassLoader.getPlatformClassLoader();
+        System.out.println("r parent " + yes(first.getParent() == app) + " " + first.getName());
+        System.out.println("r chain " + app.getName() + " " + platform.getName() + " " + yes(app.getParent() == platform) + " " + yes(platform.getParent() == null));
+        System.out.println("r defined by " + yes(Main.class.getClassLoader() == app) + " " + yes(String.class.getClassLoader() == null));
+        try {
+            first.loadClass("Missing");
+        } catch (ClassNotFoundException e) {
+            System.out.println("r missing " + e.getMessage());
+        }
+        try {
+            Class.forName("Helper");
+        } catch (ClassNotFoundException e) {
+            System.out.println("r main cannot see " + e.getMessage());
+        }
+        try {
+            Class.forName("Main", false, platform);
+        } catch (ClassNotFoundException e) {
+            System.out.println("r platform cannot see " + e.getMessage());
+        }
+        System.out.println("r for name " + yes(Class.forName("Plugin", false, first) == a));
+
+        PluginLoader child = new PluginLoader("child", new byte[][] {client(), api()}, true);
+        Class<?> client = child.loadClass("Client");
+        Object instance = client.getDeclaredConstructor().newInstance();
+        System.out.println("r child api " + yes(instance instanceof Api) + " " + yes(client.getInterfaces()[0] == Api.class));
+        try {
+            ((Runnable) instance).run();
+        } catch (LinkageError e) {
+            System.out.println("r constraint " + e.getClass().getName());
+        }
+    }
+}
+"#;
+
+/// The bytes of a compiled class as the elements of a Java array literal.
+fn byte_literal(path: &Path) -> String {
+    fs::read(path)
+        .expect("read class")
+        .iter()
+        .map(|byte| (*byte as i8).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[test]
+fn user_defined_loaders_keep_plugins_apart() {
+    if !has_javac() {
+        return;
+    }
+    let plugin_dir = temp_dir("plugins");
+    fs::write(plugin_dir.join("Api.java"), API).expect("write java source");
+    fs::write(plugin_dir.join("Shared.java"), SHARED).expect("write java source");
+    compile_java(&plugin_dir, "Helper.java", HELPER);
+    compile_java(&plugin_dir, "Plugin.java", PLUGIN);
+    compile_java(&plugin_dir, "Client.java", CLIENT);
+    let mut main = MAIN.to_string();
+    for class in ["Plugin", "Helper", "Client", "Api"] {
+        let literal = byte_literal(&plugin_dir.join(format!("{}.class", class)));
+        main = main.replace(
+            &format!("{}}}", class.to_uppercase()),
+            &format!("{}}}", literal),
+        );
+    }
+
+    let dir = temp_dir("host");
+    compile_java(&dir, "Api.java", API);
+    compile_java(&dir, "Shared.java", SHARED);
+    compile_java(&dir, "Main.java", &main);
+
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "Main"]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            results(&output),
+            [
+                "first defines Plugin",
+                "second defines Plugin",
+                "names Plugin Plugin",
+                "same class no",
+                "loaders yes yes",
+                "loaded again yes",
+                "first defines Helper",
+                "plugin 1 helper",
+                "plugin 2 helper",
+                "second defines Helper",
+                "plugin 1 helper",
+                "instance yes no",
+                "api yes",
+                "lookup own loader",
+                "helper yes",
+                "parent yes first",
+                "chain app platform yes yes",
+                "defined by yes yes",
+                "missing Missing",
+                "main cannot see Helper",
+                "platform cannot see Main",
+                "for name yes",
+                "child defines Api",
+                "child defines Client",
+                "child api no no",
+                "constraint java.lang.LinkageError",
+            ],
+            "{}",
+            mode
+        );
+    }
+    let _ = fs::remove_dir_all(&plugin_dir);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Asks for a class's loader before anything touches `ClassLoader`.
+const ORDER: &str = r#"
+class A {
+}
+
+public class Order {
+    static String yes(boolean value) {
+        return value ? "yes" : "no";
+    }
+
+    public static void main(String[] args) {
+        ClassLoader early = Order.class.getClassLoader();
+        ClassLoader app = ClassLoader.getSystemClassLoader();
+        System.out.println("r same loader " + yes(early == app) + " " + yes(Order.class.getClassLoader() == app));
+        System.out.println("r one letter " + yes(A.class.getClassLoader() == app));
+        System.out.println("r arrays " + yes(A[].class.getClassLoader() == app) + " " + yes(int[].class.getClassLoader() == null));
+    }
+}
+"#;
+
+#[test]
+fn application_loader_is_created_once() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("order");
+    compile_java(&dir, "Order.java", ORDER);
+    let output = run_aria(&dir, &["Order"]);
+    let _ = fs::remove_dir_all(&dir);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        results(&output),
+        ["same loader yes yes", "one letter yes", "arrays yes yes"]
+    );
+}
+
+/// Compiled as `PluginX1`, then renamed to `Plugin#1`, a legal JVM name
+/// that javac cannot spell.
+const TAGGED: &str = r#"
+public class PluginX1 {
+    public static String tag() {
+        return "tagged";
+    }
+}
+"#;
+
+/// Defines `Plugin` through a user-defined loader next to the class-path
+/// class `Plugin#1`.
+const HOST: &str = r#"
+public class Host {
+    static class OneLoader extends ClassLoader {
+        OneLoader() {
+            super("one", ClassLoader.getSystemClassLoader());
+        }
+
+        protected Class<?> findClass(String name) throws ClassNotFoundException {
+            byte[] bytes = new byte[] {PLUGIN};
+            return defineClass(name, bytes, 0, bytes.length);
+        }
+    }
+
+    static String yes(boolean value) {
+        return value ? "yes" : "no";
+    }
+
+    public static void main(String[] args) throws Exception {
+        ClassLoader one = new OneLoader();
+        Class<?> plugin = one.loadClass("Plugin");
+        Class<?> tagged = Class.forName("Plugin#1");
+        System.out.println("r names " + plugin.getName() + " " + tagged.getName());
+        System.out.println("r loaders " + yes(plugin.getClassLoader() == one) + " " + yes(tagged.getClassLoader() == ClassLoader.getSystemClassLoader()));
+        System.out.println("r " + (String) tagged.getDeclaredMethod("tag").invoke(null));
+        System.out.println("r " + ((Api) plugin.getDeclaredConstructor().newInstance()).describe());
+    }
+}
+"#;
+
+#[test]
+fn loader_namespaces_do_not_depend_on_class_names() {
+    if !has_javac() {
+        return;
+    }
+    let plugin_dir = temp_dir("tagged-plugins");
+    fs::write(plugin_dir.join("Api.java"), API).expect("write java source");
+    compile_java(&plugin_dir, "Helper.java", HELPER);
+    compile_java(&plugin_dir, "Plugin.java", PLUGIN);
+    let host = HOST.replace(
+        "PLUGIN}",
+        &format!("{}}}", byte_literal(&plugin_dir.join("Plugin.class"))),
+    );
+
+    let dir = temp_dir("tagged");
+    compile_java(&dir, "Api.java", API);
+    compile_java(&dir, "Helper.java", HELPER);
+    compile_java(&dir, "PluginX1.java", TAGGED);
+    compile_java(&dir, "Host.java", &host);
+    let mut tagged = fs::read(dir.join("PluginX1.class")).expect("read class");
+    for start in 0..tagged.len() - 8 {
+        if &tagged[start..start + 8] == b"PluginX1" {
+            tagged[start + 6] = b'#';
+        }
+    }
+    fs::write(dir.join("Plugin#1.class"), tagged).expect("write class");
+    fs::remove_file(dir.join("PluginX1.class")).expect("remove class");
+
+    let output = run_aria(&dir, &["Host"]);
+    let _ = fs::remove_dir_all(&plugin_dir);
+    let _ = fs::remove_dir_all(&dir);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        results(&output),
+        [
+            "names Plugin Plugin#1",
+            "loaders yes yes",
+            "tagged",
+            "plugin 1 helper",
+        ]
+    );
+}
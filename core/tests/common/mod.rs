@@ -0,0 +1,72 @@
+//! Helpers shared by the integration tests. Each test crate uses only
+//! some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn has_javac() -> bool {
+    Command::new("javac").arg("-version").output().is_ok()
+}
+
+pub fn has_cc() -> bool {
+    Command::new("cc").arg("--version").output().is_ok()
+}
+
+pub fn compile_java(temp_dir: &Path, file_name: &str, source: &str) {
+    compile_java_with(temp_dir, file_name, source, &[]);
+}
+
+/// Like `compile_java`, passing `options` to `javac` as well.
+pub fn compile_java_with(temp_dir: &Path, file_name: &str, source: &str, options: &[&str]) {
+    let file_path = temp_dir.join(file_name);
+    fs::write(&file_path, source).expect("write java source");
+
+    let output = Command::new("javac")
+        .arg("--release")
+        .arg("17")
+        .args(options)
+        .arg(file_path.to_string_lossy().to_string())
+        .current_dir(temp_dir)
+        .output()
+        .expect("spawn javac");
+
+    assert!(
+        output.status.success(),
+        "javac failed:\nstdout={}\nstderr={}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// A fresh directory under the system temp directory.
+pub fn temp_dir(tag: &str) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock")
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("aria-core-{}-{}", tag, stamp));
+    fs::create_dir_all(&dir).expect("mkdir");
+    dir
+}
+
+/// Runs the VM with `dir` as the class path.
+pub fn run_aria(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(dir)
+        .args(args)
+        .output()
+        .expect("run aria_core")
+}
+
+/// The lines a test program printed with an `r ` prefix, without it.
+pub fn results(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.strip_prefix("r "))
+        .map(str::to_string)
+        .collect()
+}
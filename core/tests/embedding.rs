@@ -0,0 +1,245 @@
+use aria_core::runtime::heap::HeapValue;
+use aria_core::vm::{JavaObject, Throw, Vm, VmError};
+use std::fs;
+
+mod common;
+
+use common::{compile_java, has_javac, temp_dir};
+
+const PLUGIN: &str = r#"
+public class Plugin {
+  int base;
+
+  Plugin(int base) {
+    this.base = base;
+  }
+
+  int scale(int x) {
+    return base * x;
+  }
+
+  static int add(int a, int b) {
+    return a + b;
+  }
+
+  static long widen(long a, double d, boolean flip) {
+    long r = a + (long) d;
+    return flip ? -r : r;
+  }
+
+  static String greet(String who) {
+    return "hello " + who + " from " + System.getProperty("plugin.name");
+  }
+
+  static int sum(int[] values) {
+    int total = 0;
+    for (int i = 0; i < values.length; i++) {
+      total += values[i];
+    }
+    return total;
+  }
+
+  static String[] words() {
+    return new String[] {"alpha", null, "gamma"};
+  }
+
+  static String nothing() {
+    return null;
+  }
+
+  static void fail() {
+    throw new IllegalStateException("plugin failure");
+  }
+
+  static native int twice(int x);
+  static native String shout(String s);
+  static native int checked(int x);
+  native int offset(int x);
+
+  static String useNatives() {
+    String r = twice(20) + " " + shout("abc") + " " + new Plugin(7).offset(3);
+    try {
+      checked(-1);
+    } catch (IllegalArgumentException e) {
+      r += " caught " + e.getMessage();
+    }
+    return r + " " + checked(5);
+  }
+
+  static int fill(int n) {
+    Node head = null;
+    for (int i = 0; i < n; i++) {
+      Node node = new Node();
+      node.next = head;
+      head = node;
+    }
+    return n;
+  }
+}
+
+class Node {
+  Node next;
+}
+"#;
+
+#[test]
+fn rust_host_calls_java_plugins() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("embedding");
+    compile_java(&dir, "Plugin.java", PLUGIN);
+
+    let mut vm = Vm::builder()
+        .classpath(&dir)
+        .property("plugin.name", "demo")
+        .function("Plugin", "twice", "(I)I", |x: i32| x * 2)
+        .function(
+            "Plugin",
+            "shout",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            |s: String| s.to_uppercase() + "!",
+        )
+        .function("Plugin", "checked", "(I)I", |x: i32| {
+            if x < 0 {
+                Err(Throw::new("java.lang.IllegalArgumentException", "negative"))
+            } else {
+                Ok(x + 1)
+            }
+        })
+        .native("Plugin", "offset", "(I)I", |env, this, args| {
+            let HeapValue::Object(obj) = this? else {
+                return None;
+            };
+            let base = env.heap.get(obj.id)?.get_field("base")?.as_int();
+            Some(HeapValue::Int(base + args[0].as_int()))
+        })
+        .build()
+        .expect("build vm");
+
+    assert_eq!(vm.property("plugin.name").as_deref(), Some("demo"));
+    let add: i32 = vm.call_static("Plugin", "add", "(II)I", (2, 40)).unwrap();
+    assert_eq!(add, 42);
+    let widen: i64 = vm
+        .call_static("Plugin", "widen", "(JDZ)J", (1i64 << 40, 2.9, true))
+        .unwrap();
+    assert_eq!(widen, -((1i64 << 40) + 2));
+    let greet: String = vm
+        .call_static(
+            "Plugin",
+            "greet",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            ("host",),
+        )
+        .unwrap();
+    assert_eq!(greet, "hello host from demo");
+    let sum: i32 = vm
+        .call_static("Plugin", "sum", "([I)I", (vec![1, 2, 3, 4],))
+        .unwrap();
+    assert_eq!(sum, 10);
+    let words: Vec<Option<String>> = vm
+        .call_static("Plugin", "words", "()[Ljava/lang/String;", ())
+        .unwrap();
+    assert_eq!(
+        words,
+        vec![Some("alpha".to_string()), None, Some("gamma".to_string())]
+    );
+    let nothing: Option<String> = vm
+        .call_static("Plugin", "nothing", "()Ljava/lang/String;", ())
+        .unwrap();
+    assert_eq!(nothing, None);
+
+    let plugin: JavaObject = vm.new_object("Plugin", "(I)V", (6,)).unwrap();
+    assert_eq!(plugin.class_name(), "Plugin");
+    let scaled: i32 = vm.call_method(&plugin, "scale", "(I)I", (7,)).unwrap();
+    assert_eq!(scaled, 42);
+
+    let natives: String = vm
+        .call_static("Plugin", "useNatives", "()Ljava/lang/String;", ())
+        .unwrap();
+    assert_eq!(natives, "40 ABC! 10 caught negative 6");
+
+    match vm.call_static::<()>("Plugin", "fail", "()V", ()) {
+        Err(VmError::Exception(e)) => {
+            assert_eq!(e.class_name, "java.lang.IllegalStateException");
+            assert_eq!(e.message.as_deref(), Some("plugin failure"));
+            assert!(
+                e.stack_trace
+                    .starts_with("java.lang.IllegalStateException: plugin failure\n\tat "),
+                "trace: {}",
+                e.stack_trace
+            );
+            assert!(e.stack_trace.contains("Plugin.fail"), "{}", e.stack_trace);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    match vm.call_static::<i32>("Missing", "run", "()I", ()) {
+        Err(VmError::ClassNotFound(name)) => assert_eq!(name, "Missing"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match vm.call_static::<i32>("Plugin", "scale", "(I)I", (1,)) {
+        Err(VmError::NoSuchMethod(method)) => assert_eq!(method, "'int Plugin.scale(int)'"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(matches!(
+        vm.call_static::<String>("Plugin", "add", "(II)I", (1, 2)),
+        Err(VmError::SignatureMismatch(_))
+    ));
+    drop(vm);
+
+    let typo = Vm::builder()
+        .function("Plugin", "twice", "(I)I", |x: i64| x)
+        .build();
+    assert!(matches!(typo, Err(VmError::SignatureMismatch(_))));
+
+    let mut small = Vm::builder()
+        .classpath(&dir)
+        .heap_size(64 * 1024)
+        .build()
+        .expect("build vm");
+    let filled: i32 = small.call_static("Plugin", "fill", "(I)I", (100,)).unwrap();
+    assert_eq!(filled, 100);
+    match small.call_static::<i32>("Plugin", "fill", "(I)I", (100_000,)) {
+        Err(VmError::Exception(e)) => {
+            assert_eq!(e.class_name, "java.lang.OutOfMemoryError");
+            assert_eq!(e.message.as_deref(), Some("Java heap space"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn held_objects_survive_collection() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("embedding-roots");
+    compile_java(&dir, "Plugin.java", PLUGIN);
+    let mut vm = Vm::builder().classpath(&dir).build().expect("build vm");
+
+    let plugin: JavaObject = vm.new_object("Plugin", "(I)V", (41,)).unwrap();
+    let copy = plugin.clone();
+    let name = vm.new_string("kept");
+    drop(plugin);
+    let filled: i32 = vm.call_static("Plugin", "fill", "(I)I", (5_000,)).unwrap();
+    assert_eq!(filled, 5_000);
+    vm.call_static::<()>("java/lang/System", "gc", "()V", ())
+        .unwrap();
+
+    let scaled: i32 = vm.call_method(&copy, "scale", "(I)I", (1,)).unwrap();
+    assert_eq!(scaled, 41);
+    let greet: String = vm
+        .call_static(
+            "Plugin",
+            "greet",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            (&name,),
+        )
+        .unwrap();
+    assert!(greet.starts_with("hello kept"), "{}", greet);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,207 @@
+use aria_core::jit::JitMode;
+use aria_core::vm::{Vm, VmError};
+use std::fs;
+
+mod common;
+
+use common::{compile_java, has_javac, temp_dir};
+
+/// Compiles one version of `Counter` into its own directory and returns
+/// the class file.
+fn counter_version(dir: &std::path::Path, version: &str, source: &str) -> Vec<u8> {
+    let version_dir = dir.join(version);
+    fs::create_dir_all(&version_dir).expect("mkdir");
+    compile_java(&version_dir, "Counter.java", source);
+    fs::read(version_dir.join("Counter.class")).expect("read class file")
+}
+
+const COUNTER_V1: &str = r#"
+public class Counter {
+  static int total;
+  int hits;
+
+  int hit() {
+    hits++;
+    total++;
+    return hits;
+  }
+
+  String describe() {
+    return "v1 hits=" + hits + " total=" + total;
+  }
+
+  static native void swap();
+
+  static String run(Counter counter) {
+    String before = counter.describe();
+    swap();
+    return "v1 run " + before + " / " + counter.describe();
+  }
+}
+"#;
+
+const COUNTER_V2: &str = r#"
+public class Counter {
+  static int total;
+  int hits;
+
+  int hit() {
+    hits += 10;
+    total += 10;
+    return hits;
+  }
+
+  String describe() {
+    return "v2 hits=" + hits + " total=" + total;
+  }
+
+  static native void swap();
+
+  static String run(Counter counter) {
+    String before = counter.describe();
+    swap();
+    return "v2 run " + before + " / " + counter.describe();
+  }
+}
+"#;
+
+const COUNTER_FIELD: &str = r#"
+public class Counter {
+  static int total;
+  int hits;
+  int misses;
+
+  int hit() {
+    return ++hits;
+  }
+
+  String describe() {
+    return "field";
+  }
+
+  static native void swap();
+
+  static String run(Counter counter) {
+    return "field";
+  }
+}
+"#;
+
+const COUNTER_METHOD: &str = r#"
+public class Counter {
+  static int total;
+  int hits;
+
+  int hit() {
+    return ++hits;
+  }
+
+  int reset() {
+    return hits = 0;
+  }
+
+  String describe() {
+    return "method";
+  }
+
+  static native void swap();
+
+  static String run(Counter counter) {
+    return "method";
+  }
+}
+"#;
+
+#[test]
+fn redefinition_swaps_method_bodies_and_keeps_state() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("counter");
+    compile_java(&dir, "Counter.java", COUNTER_V1);
+    let v1 = fs::read(dir.join("Counter.class")).expect("read class file");
+    let v2 = counter_version(&dir, "v2", COUNTER_V2);
+    let with_field = counter_version(&dir, "field", COUNTER_FIELD);
+    let with_method = counter_version(&dir, "method", COUNTER_METHOD);
+
+    for mode in [JitMode::Interpreted, JitMode::Compiled] {
+        let swapped = v2.clone();
+        let mut vm = Vm::builder()
+            .classpath(&dir)
+            .jit_mode(mode)
+            .native("Counter", "swap", "()V", move |env, _, _| {
+                env.interpreter
+                    .redefine_class(env.loader, "Counter", &swapped)
+                    .expect("redefine Counter");
+                None
+            })
+            .build()
+            .expect("build vm");
+
+        let counter = vm.new_object("Counter", "()V", ()).unwrap();
+        let hits: i32 = vm.call_method(&counter, "hit", "()I", ()).unwrap();
+        assert_eq!(hits, 1, "{:?}", mode);
+
+        // The frame that swapped the class finishes on the old code.
+        let run: String = vm
+            .call_static(
+                "Counter",
+                "run",
+                "(LCounter;)Ljava/lang/String;",
+                (&counter,),
+            )
+            .unwrap();
+        assert_eq!(
+            run, "v1 run v1 hits=1 total=1 / v2 hits=1 total=1",
+            "{:?}",
+            mode
+        );
+        let hits: i32 = vm.call_method(&counter, "hit", "()I", ()).unwrap();
+        assert_eq!(hits, 11, "{:?}", mode);
+        let run: String = vm
+            .call_static(
+                "Counter",
+                "run",
+                "(LCounter;)Ljava/lang/String;",
+                (&counter,),
+            )
+            .unwrap();
+        assert_eq!(
+            run, "v2 run v2 hits=11 total=11 / v2 hits=11 total=11",
+            "{:?}",
+            mode
+        );
+
+        match vm.redefine_class("Counter", &with_field) {
+            Err(VmError::Redefinition(message)) => assert_eq!(
+                message,
+                "class redefinition failed: attempted to change the schema (add/remove fields)"
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match vm.redefine_class("Counter", &with_method) {
+            Err(VmError::Redefinition(message)) => assert_eq!(
+                message,
+                "class redefinition failed: attempted to add a method"
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match vm.redefine_class("Missing", &v1) {
+            Err(VmError::ClassNotFound(name)) => assert_eq!(name, "Missing"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let described: String = vm
+            .call_method(&counter, "describe", "()Ljava/lang/String;", ())
+            .unwrap();
+        assert_eq!(described, "v2 hits=11 total=11", "{:?}", mode);
+
+        vm.redefine_class("Counter", &v1).expect("redefine Counter");
+        let hits: i32 = vm.call_method(&counter, "hit", "()I", ()).unwrap();
+        assert_eq!(hits, 12, "{:?}", mode);
+        let described: String = vm
+            .call_method(&counter, "describe", "()Ljava/lang/String;", ())
+            .unwrap();
+        assert_eq!(described, "v1 hits=12 total=12", "{:?}", mode);
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
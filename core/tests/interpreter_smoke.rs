@@ -2,32 +2,10 @@ use aria_core::exec::interpreter::Interpreter;
 use aria_core::loader::class_loader::ClassLoader;
 use aria_core::runtime::heap::{Heap, HeapValue};
 use std::fs;
-use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
 
-fn has_javac() -> bool {
-    Command::new("javac").arg("-version").output().is_ok()
-}
+mod common;
 
-fn compile_java(temp_dir: &std::path::Path, file_name: &str, source: &str) {
-    let file_path = temp_dir.join(file_name);
-    fs::write(&file_path, source).expect("write java source");
-
-    let output = Command::new("javac")
-        .arg("--release")
-        .arg("17")
-        .arg(file_path.to_string_lossy().to_string())
-        .current_dir(temp_dir)
-        .output()
-        .expect("spawn javac");
-
-    assert!(
-        output.status.success(),
-        "javac failed:\nstdout={}\nstderr={}",
-        String::from_utf8_lossy(&output.stdout),
-        String::from_utf8_lossy(&output.stderr)
-    );
-}
+use common::{compile_java, has_javac, temp_dir};
 
 #[test]
 fn executes_branch_and_ireturn_path() {
@@ -35,12 +13,7 @@ fn executes_branch_and_ireturn_path() {
         return;
     }
 
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("clock")
-        .as_nanos();
-    let dir = std::env::temp_dir().join(format!("aria-core-sum-{}", stamp));
-    fs::create_dir_all(&dir).expect("mkdir");
+    let dir = temp_dir("sum");
 
     compile_java(
         &dir,
@@ -88,12 +61,7 @@ fn executes_new_constructor_and_field_access() {
         return;
     }
 
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("clock")
-        .as_nanos();
-    let dir = std::env::temp_dir().join(format!("aria-core-obj-{}", stamp));
-    fs::create_dir_all(&dir).expect("mkdir");
+    let dir = temp_dir("obj");
 
     compile_java(
         &dir,
@@ -139,12 +107,7 @@ fn executes_static_clinit_before_getstatic() {
         return;
     }
 
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("clock")
-        .as_nanos();
-    let dir = std::env::temp_dir().join(format!("aria-core-clinit-{}", stamp));
-    fs::create_dir_all(&dir).expect("mkdir");
+    let dir = temp_dir("clinit");
 
     compile_java(
         &dir,
@@ -184,12 +147,7 @@ fn executes_invokeinterface_dispatch() {
         return;
     }
 
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("clock")
-        .as_nanos();
-    let dir = std::env::temp_dir().join(format!("aria-core-invokeinterface-{}", stamp));
-    fs::create_dir_all(&dir).expect("mkdir");
+    let dir = temp_dir("invokeinterface");
 
     compile_java(
         &dir,
@@ -235,12 +193,7 @@ fn executes_invokedynamic_string_concat() {
         return;
     }
 
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("clock")
-        .as_nanos();
-    let dir = std::env::temp_dir().join(format!("aria-core-indy-{}", stamp));
-    fs::create_dir_all(&dir).expect("mkdir");
+    let dir = temp_dir("indy");
 
     compile_java(
         &dir,
@@ -285,3 +238,113 @@ fn executes_invokedynamic_string_concat() {
         other => panic!("unexpected result: {:?}", other),
     }
 }
+
+#[test]
+fn reuses_linked_call_sites_across_receivers_and_handlers() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("callsites");
+
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        class Shape {
+          int size() {
+            return 1;
+          }
+        }
+
+        class Square extends Shape {
+          int size() {
+            return 4;
+          }
+        }
+
+        public class Main {
+          static int calls;
+
+          static int divide(int a, int b) {
+            calls = calls + 1;
+            return a / b;
+          }
+
+          public static int run() {
+            Shape[] shapes = new Shape[] { new Shape(), new Square(), new Shape() };
+            int total = 0;
+            for (int i = 0; i < 30; i++) {
+              total += shapes[i % 3].size();
+              try {
+                total += divide(10, i % 5);
+              } catch (ArithmeticException e) {
+                total += 1000;
+              }
+            }
+            return total * 100 + calls;
+          }
+        }
+        "#,
+    );
+
+    let mut loader = ClassLoader::new();
+    loader.add_classpath(&dir);
+    let class = loader.load_class("Main").expect("load class");
+    let mut heap = Heap::new();
+    let interp = Interpreter::new(false);
+
+    let first = interp.execute_method(&mut loader, &class, "run", "()I", &mut heap, &[]);
+    let second = interp.execute_method(&mut loader, &class, "run", "()I", &mut heap, &[]);
+    let _ = fs::remove_dir_all(&dir);
+
+    // Per 5 iterations: 10 + 5 + 3 + 2 from divide, 1000 from the handler.
+    let shapes = 10 * (1 + 4 + 1);
+    let divides = 6 * (10 + 5 + 3 + 2 + 1000);
+    match (first, second) {
+        (Some(HeapValue::Int(a)), Some(HeapValue::Int(b))) => {
+            assert_eq!(a, (shapes + divides) * 100 + 30);
+            assert_eq!(b, (shapes + divides) * 100 + 60);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn relinks_call_sites_when_natives_are_rebound() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("relink");
+
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          static native int value();
+
+          public static int run() {
+            return value();
+          }
+        }
+        "#,
+    );
+
+    let mut loader = ClassLoader::new();
+    loader.add_classpath(&dir);
+    let class = loader.load_class("Main").expect("load class");
+    let mut heap = Heap::new();
+    let mut interp = Interpreter::new(false);
+    interp.register_native("Main", "value", "()I", |_, _, _| Some(HeapValue::Int(1)));
+    let first = interp.execute_method(&mut loader, &class, "run", "()I", &mut heap, &[]);
+    interp.register_native("Main", "value", "()I", |_, _, _| Some(HeapValue::Int(2)));
+    let second = interp.execute_method(&mut loader, &class, "run", "()I", &mut heap, &[]);
+    let _ = fs::remove_dir_all(&dir);
+
+    match (first, second) {
+        (Some(HeapValue::Int(a)), Some(HeapValue::Int(b))) => assert_eq!((a, b), (1, 2)),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,513 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+mod common;
+
+use common::{compile_java_with, has_javac, temp_dir};
+
+/// Compiles with `-g`, so that the debugger sees local variables.
+fn results(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix("r "))
+        .map(str::to_string)
+        .collect()
+}
+
+const MAIN: &str = r#"
+public class Main {
+    int count;
+    String label = "box";
+
+    static int square(int x) {
+        int result = x * x;
+        return result;
+    }
+
+    void bump(int by) {
+        count += by;
+    }
+
+    static void fail() {
+        throw new IllegalStateException("boom");
+    }
+
+    public static void main(String[] args) {
+        Main m = new Main();
+        int total = 0;
+        for (int i = 1; i <= 3; i++) {
+            total += square(i);
+        }
+        m.bump(total);
+        try {
+            fail();
+        } catch (IllegalStateException e) {
+            System.out.println("r caught " + total);
+        }
+        System.out.println("r count " + m.count);
+    }
+}
+"#;
+
+// Event kinds and suspend policies.
+const SINGLE_STEP: u8 = 1;
+const BREAKPOINT: u8 = 2;
+const EXCEPTION: u8 = 4;
+const CLASS_PREPARE: u8 = 8;
+const VM_START: u8 = 90;
+const VM_DEATH: u8 = 99;
+const SUSPEND_ALL: u8 = 2;
+
+// Step sizes and depths.
+const STEP_LINE: i32 = 1;
+const STEP_INTO: i32 = 0;
+const STEP_OVER: i32 = 1;
+const STEP_OUT: i32 = 2;
+
+/// Reads the fields of a reply or event.
+struct Data {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl Data {
+    fn take(&mut self, count: usize) -> &[u8] {
+        let start = self.position;
+        self.position += count;
+        &self.bytes[start..self.position]
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take(1)[0]
+    }
+
+    fn int(&mut self) -> i32 {
+        i32::from_be_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_be_bytes(self.take(8).try_into().unwrap())
+    }
+
+    fn string(&mut self) -> String {
+        let length = self.int() as usize;
+        String::from_utf8(self.take(length).to_vec()).unwrap()
+    }
+
+    /// A location: type tag, class, method and index.
+    fn location(&mut self) -> (u64, u64, u64) {
+        self.u8();
+        (self.u64(), self.u64(), self.u64())
+    }
+}
+
+/// Builds the data of a command.
+#[derive(Default)]
+struct Out(Vec<u8>);
+
+impl Out {
+    fn u8(mut self, value: u8) -> Self {
+        self.0.push(value);
+        self
+    }
+
+    fn int(mut self, value: i32) -> Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn u64(mut self, value: u64) -> Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn string(self, value: &str) -> Self {
+        let mut out = self.int(value.len() as i32);
+        out.0.extend_from_slice(value.as_bytes());
+        out
+    }
+
+    fn bytes(mut self, other: Out) -> Self {
+        self.0.extend_from_slice(&other.0);
+        self
+    }
+}
+
+/// A scripted debugger: sends commands one at a time and queues the
+/// events that arrive in between.
+struct Client {
+    stream: TcpStream,
+    next_id: u32,
+    events: VecDeque<Data>,
+}
+
+impl Client {
+    fn connect(port: u16) -> Self {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("connect");
+        stream
+            .set_read_timeout(Some(Duration::from_secs(60)))
+            .unwrap();
+        stream.write_all(b"JDWP-Handshake").unwrap();
+        let mut handshake = [0u8; 14];
+        stream.read_exact(&mut handshake).unwrap();
+        assert_eq!(&handshake, b"JDWP-Handshake");
+        Self {
+            stream,
+            next_id: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// `(flags, command set or error code, data)` of the next packet.
+    fn packet(&mut self) -> (u8, u16, Data) {
+        let mut header = [0u8; 11];
+        self.stream.read_exact(&mut header).expect("read packet");
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let mut bytes = vec![0; length - 11];
+        self.stream.read_exact(&mut bytes).unwrap();
+        let code = u16::from_be_bytes([header[9], header[10]]);
+        (header[8], code, Data { bytes, position: 0 })
+    }
+
+    /// Sends a command and returns the error code and data of its reply.
+    fn send(&mut self, command_set: u8, command: u8, data: Out) -> (u16, Data) {
+        self.next_id += 1;
+        let mut packet = ((11 + data.0.len()) as u32).to_be_bytes().to_vec();
+        packet.extend_from_slice(&self.next_id.to_be_bytes());
+        packet.extend_from_slice(&[0, command_set, command]);
+        packet.extend_from_slice(&data.0);
+        self.stream.write_all(&packet).unwrap();
+        loop {
+            let (flags, code, data) = self.packet();
+            if flags == 0x80 {
+                return (code, data);
+            }
+            self.events.push_back(data);
+        }
+    }
+
+    /// The data of a reply that must succeed.
+    fn call(&mut self, command_set: u8, command: u8, data: Out) -> Data {
+        let (error, data) = self.send(command_set, command, data);
+        assert_eq!(error, 0, "command {}/{} failed", command_set, command);
+        data
+    }
+
+    /// The next event: its kind and request id, with the rest of the data
+    /// left to read. Composites here always hold one event.
+    fn event(&mut self) -> (u8, i32, Data) {
+        let mut data = match self.events.pop_front() {
+            Some(data) => data,
+            None => self.packet().2,
+        };
+        assert_eq!(data.u8(), SUSPEND_ALL);
+        assert_eq!(data.int(), 1);
+        let kind = data.u8();
+        let request = data.int();
+        (kind, request, data)
+    }
+
+    fn resume(&mut self) {
+        self.call(1, 9, Out::default());
+    }
+
+    /// `EventRequest.Set` with modifiers already encoded.
+    fn request(&mut self, kind: u8, modifiers: i32, encoded: Out) -> i32 {
+        let data = Out::default()
+            .u8(kind)
+            .u8(SUSPEND_ALL)
+            .int(modifiers)
+            .bytes(encoded);
+        self.call(15, 1, data).int()
+    }
+
+    fn clear(&mut self, kind: u8, request: i32) {
+        self.call(15, 2, Out::default().u8(kind).int(request));
+    }
+
+    fn step(&mut self, thread: u64, depth: i32) -> i32 {
+        let step = Out::default().u8(10).u64(thread).int(STEP_LINE).int(depth);
+        self.request(SINGLE_STEP, 1, step)
+    }
+
+    /// `(id, name, signature)` of a type's methods or fields.
+    fn members(&mut self, command: u8, type_id: u64) -> Vec<(u64, String, String)> {
+        let mut data = self.call(2, command, Out::default().u64(type_id));
+        (0..data.int())
+            .map(|_| {
+                let member = (data.u64(), data.string(), data.string());
+                data.int();
+                member
+            })
+            .collect()
+    }
+
+    /// `(index, line)` pairs of a method.
+    fn lines(&mut self, type_id: u64, method: u64) -> Vec<(u64, i32)> {
+        let mut data = self.call(6, 1, Out::default().u64(type_id).u64(method));
+        data.u64();
+        data.u64();
+        (0..data.int()).map(|_| (data.u64(), data.int())).collect()
+    }
+
+    /// `(name, slot)` of a method's local variables.
+    fn variables(&mut self, type_id: u64, method: u64) -> Vec<(String, i32)> {
+        let mut data = self.call(6, 2, Out::default().u64(type_id).u64(method));
+        data.int();
+        (0..data.int())
+            .map(|_| {
+                data.u64();
+                let name = data.string();
+                data.string();
+                data.int();
+                (name, data.int())
+            })
+            .collect()
+    }
+
+    /// `(frame id, method)` of each frame, innermost first.
+    fn frames(&mut self, thread: u64) -> Vec<(u64, u64)> {
+        let mut data = self.call(11, 6, Out::default().u64(thread).int(0).int(-1));
+        (0..data.int())
+            .map(|_| {
+                let frame = data.u64();
+                (frame, data.location().1)
+            })
+            .collect()
+    }
+
+    /// One tagged local of a frame.
+    fn local(&mut self, thread: u64, frame: u64, slot: i32, tag: u8) -> Data {
+        let data = Out::default()
+            .u64(thread)
+            .u64(frame)
+            .int(1)
+            .int(slot)
+            .u8(tag);
+        let mut values = self.call(16, 1, data);
+        assert_eq!(values.int(), 1);
+        values
+    }
+}
+
+fn member_id(members: &[(u64, String, String)], name: &str) -> u64 {
+    members
+        .iter()
+        .find(|(_, member, _)| member == name)
+        .map(|(id, _, _)| *id)
+        .unwrap_or_else(|| panic!("no member {}", name))
+}
+
+fn slot(variables: &[(String, i32)], name: &str) -> i32 {
+    variables
+        .iter()
+        .find(|(variable, _)| variable == name)
+        .map(|(_, slot)| *slot)
+        .unwrap_or_else(|| panic!("no variable {}", name))
+}
+
+#[test]
+fn scripted_debugger_sets_breakpoints_steps_and_sees_exceptions() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("session");
+    compile_java_with(&dir, "Main.java", MAIN, &["-g"]);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .args([
+            "-agentlib:jdwp=transport=dt_socket,server=y,address=0",
+            "-cp",
+            ".",
+            "Main",
+        ])
+        .current_dir(&dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("run aria_core");
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    let port: u16 = line
+        .trim()
+        .strip_prefix("Listening for transport dt_socket at address: ")
+        .unwrap_or_else(|| panic!("unexpected first line {:?}", line))
+        .parse()
+        .unwrap();
+    let output = thread::spawn(move || {
+        let mut rest = String::new();
+        stdout.read_to_string(&mut rest).unwrap();
+        rest
+    });
+
+    let mut client = Client::connect(port);
+    let mut sizes = client.call(1, 7, Out::default());
+    assert_eq!([0; 5].map(|_| sizes.int()), [8; 5]);
+    let mut version = client.call(1, 1, Out::default());
+    version.string();
+    assert_eq!(version.int(), 17);
+
+    // suspend=y holds the VM at VMStart.
+    let (kind, request, mut start) = client.event();
+    assert_eq!((kind, request), (VM_START, 0));
+    let thread = start.u64();
+    let mut threads = client.call(1, 4, Out::default());
+    assert_eq!((threads.int(), threads.u64()), (1, thread));
+    let mut name = client.call(11, 1, Out::default().u64(thread));
+    assert_eq!(name.string(), "main");
+
+    let prepare = client.request(CLASS_PREPARE, 1, Out::default().u8(5).string("Main"));
+    let exceptions = Out::default().u8(8).u64(0).u8(1).u8(1);
+    let exception_request = client.request(EXCEPTION, 1, exceptions);
+    client.resume();
+
+    let (kind, request, mut prepared) = client.event();
+    assert_eq!((kind, request), (CLASS_PREPARE, prepare));
+    prepared.u64();
+    prepared.u8();
+    let main_type = prepared.u64();
+    assert_eq!(prepared.string(), "LMain;");
+    client.clear(CLASS_PREPARE, prepare);
+
+    let methods = client.members(5, main_type);
+    let square = member_id(&methods, "square");
+    let main = member_id(&methods, "main");
+    let square_lines = client.lines(main_type, square);
+    assert_eq!(square_lines.len(), 2);
+    let square_variables = client.variables(main_type, square);
+    assert_eq!(slot(&square_variables, "x"), 0);
+    assert_eq!(slot(&square_variables, "result"), 1);
+    let main_variables = client.variables(main_type, main);
+
+    let breakpoint = Out::default().u8(7).u8(1).u64(main_type).u64(square).u64(0);
+    let breakpoint = client.request(BREAKPOINT, 1, breakpoint);
+    client.resume();
+
+    // Stopped at the first call of square(1).
+    let (kind, request, mut hit) = client.event();
+    assert_eq!((kind, request), (BREAKPOINT, breakpoint));
+    hit.u64();
+    assert_eq!(hit.location(), (main_type, square, 0));
+    let frames = client.frames(thread);
+    assert_eq!(
+        frames.iter().map(|(_, method)| *method).collect::<Vec<_>>(),
+        [square, main]
+    );
+    let mut x = client.local(thread, frames[0].0, 0, b'I');
+    assert_eq!((x.u8(), x.int()), (b'I', 1));
+    let mut this = client.call(16, 3, Out::default().u64(thread).u64(frames[0].0));
+    assert_eq!((this.u8(), this.u64()), (b'L', 0));
+
+    let m_slot = slot(&main_variables, "m");
+    let mut m = client.local(thread, frames[1].0, m_slot, b'L');
+    assert_eq!(m.u8(), b'L');
+    let m = m.u64();
+    let mut m_type = client.call(9, 1, Out::default().u64(m));
+    m_type.u8();
+    assert_eq!(m_type.u64(), main_type);
+    let fields = client.members(4, main_type);
+    let label = member_id(&fields, "label");
+    let mut label_value = client.call(9, 2, Out::default().u64(m).int(1).u64(label));
+    label_value.int();
+    assert_eq!(label_value.u8(), b's');
+    let label_string = label_value.u64();
+    let mut text = client.call(10, 1, Out::default().u64(label_string));
+    assert_eq!(text.string(), "box");
+
+    // Step over to the next line of square, then out to main.
+    client.clear(BREAKPOINT, breakpoint);
+    let over = client.step(thread, STEP_OVER);
+    client.resume();
+    let (kind, request, mut stepped) = client.event();
+    assert_eq!((kind, request), (SINGLE_STEP, over));
+    stepped.u64();
+    assert_eq!(stepped.location(), (main_type, square, square_lines[1].0));
+    client.clear(SINGLE_STEP, over);
+
+    let out = client.step(thread, STEP_OUT);
+    client.resume();
+    let (kind, request, mut stepped) = client.event();
+    assert_eq!((kind, request), (SINGLE_STEP, out));
+    stepped.u64();
+    assert_eq!(stepped.location().1, main);
+    client.clear(SINGLE_STEP, out);
+
+    // Stepping into from main reaches square(2) within a few lines.
+    let into = client.step(thread, STEP_INTO);
+    let mut reached = None;
+    for _ in 0..8 {
+        client.resume();
+        let (kind, request, mut stepped) = client.event();
+        assert_eq!((kind, request), (SINGLE_STEP, into));
+        stepped.u64();
+        let (_, method, _) = stepped.location();
+        if method == square {
+            let frames = client.frames(thread);
+            reached = Some(client.local(thread, frames[0].0, 0, b'I').take(5).to_vec());
+            break;
+        }
+    }
+    assert_eq!(reached, Some(vec![b'I', 0, 0, 0, 2]));
+    client.clear(SINGLE_STEP, into);
+    client.resume();
+
+    // The exception thrown in fail() is caught back in main.
+    let fail = member_id(&methods, "fail");
+    let (kind, request, mut thrown) = client.event();
+    assert_eq!((kind, request), (EXCEPTION, exception_request));
+    thrown.u64();
+    assert_eq!(thrown.location().1, fail);
+    assert_eq!(thrown.u8(), b'L');
+    let exception = thrown.u64();
+    let (_, catch_method, _) = thrown.location();
+    assert_eq!(catch_method, main);
+    let mut exception_type = client.call(9, 1, Out::default().u64(exception));
+    exception_type.u8();
+    let exception_type = exception_type.u64();
+    let mut signature = client.call(2, 1, Out::default().u64(exception_type));
+    assert_eq!(signature.string(), "Ljava/lang/IllegalStateException;");
+    client.clear(EXCEPTION, exception_request);
+    client.resume();
+
+    // Nothing asked for VMDeath, so only the automatic request arrives.
+    let mut death = match client.events.pop_front() {
+        Some(data) => data,
+        None => client.packet().2,
+    };
+    assert_eq!((death.u8(), death.int()), (0, 1));
+    assert_eq!((death.u8(), death.int()), (VM_DEATH, 0));
+
+    let status = child.wait().expect("wait for aria_core");
+    assert!(status.success());
+    let stdout = output.join().unwrap();
+    assert_eq!(results(&stdout), ["caught 14", "count 14"]);
+}
+
+#[test]
+fn agent_options_are_checked() {
+    let output = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .args(["-agentlib:jdwp=server=y", "Main"])
+        .output()
+        .expect("run aria_core");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim_end(),
+        "ERROR: JDWP no transport specified: -agentlib:jdwp=server=y"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .args([
+            "-agentlib:jdwp=transport=dt_socket,server=y,suspend=x",
+            "Main",
+        ])
+        .output()
+        .expect("run aria_core");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("ERROR: JDWP option syntax error: -agentlib:jdwp=transport=dt_socket"));
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+const PROGRAM: &str = r#"
+public class Main {
+  static int sfield = 3;
+  int ifield;
+  static class Base { int get(int x) { return x + 1; } }
+  static class Twice extends Base { int get(int x) { return x * 2; } }
+  static class Late extends Base { int get(int x) { return x - 100; } }
+
+  static int arith(int a, int b) {
+    int r = a * b - (a ^ b) + (a | 7) - (b & 12);
+    r += a << (b & 31);
+    r += a >> 3;
+    r += a >>> 5;
+    r += -a;
+    r += (byte) (a + 200) + (char) (a - 3) + (short) (a * 1000);
+    return r;
+  }
+  static int deep(int a, int b, int c, int d, int e, int f, int g, int h) {
+    return a + (b - (c + (d * (e - (f + (g * (h + a)))))));
+  }
+  static int divide(int a, int b) {
+    try {
+      return a / b + a % b;
+    } catch (ArithmeticException ex) {
+      return -1;
+    }
+  }
+  static int thrower(int x) {
+    if (x > 5) throw new IllegalStateException("big");
+    return x;
+  }
+  static int catcher(int x) {
+    try { return thrower(x); } catch (IllegalStateException e) { return 99; }
+  }
+  static int arrays(int n) {
+    int[] a = new int[n];
+    byte[] bs = new byte[n];
+    char[] cs = new char[n];
+    for (int i = 0; i < n; i++) { a[i] = i * i; bs[i] = (byte) (i * 50); cs[i] = (char) (65 + i); }
+    int s = 0;
+    for (int i = 0; i < a.length; i++) { s += a[i] + bs[i] + cs[i]; a[i]++; }
+    return s + a[n - 1];
+  }
+  static int dispatch(Base[] bases, int n) {
+    int s = 0;
+    for (int i = 0; i < n; i++) s += bases[i % bases.length].get(i);
+    return s;
+  }
+  static int nulls(Object o) { return o == null ? 1 : 2; }
+  int bump(int x) { ifield += x; sfield += 1; return ifield; }
+  static String text(int a) { return "v" + a + ":" + (a * 2); }
+
+  public static void main(String[] args) {
+    System.out.println("r arith " + arith(12345, 7) + " " + arith(-99, 33) + " " + arith(Integer.MIN_VALUE, -1));
+    System.out.println("r deep " + deep(1, 2, 3, 4, 5, 6, 7, 8));
+    System.out.println("r div " + divide(17, 5) + " " + divide(3, 0) + " " + divide(Integer.MIN_VALUE, -1) + " " + divide(-17, 5));
+    System.out.println("r catch " + catcher(3) + " " + catcher(9));
+    System.out.println("r arrays " + arrays(40));
+    Base[] bases = { new Base(), new Twice() };
+    System.out.println("r dispatch " + dispatch(bases, 5000));
+    Base[] more = { new Base(), new Late(), new Twice() };
+    System.out.println("r late " + dispatch(more, 5000));
+    System.out.println("r nulls " + nulls(null) + " " + nulls(bases));
+    Main m = new Main();
+    int t = 0;
+    for (int i = 0; i < 2000; i++) t = m.bump(i);
+    System.out.println("r fields " + t + " " + sfield + " " + m.ifield);
+    System.out.println("r text " + text(21));
+    int loop = 0;
+    for (int i = 0; i < 50000; i++) { loop = (loop + i * 31) % 65521; }
+    System.out.println("r loop " + loop);
+  }
+}
+"#;
+
+const EXPECTED: &[&str] = &[
+    "arith 1692730 134311923 -201261119",
+    "deep 256",
+    "div 5 -1 -2147483648 -5",
+    "catch 3 99",
+    "arrays 25530",
+    "dispatch 18750000",
+    "late 16496634",
+    "nulls 1 2",
+    "fields 1999000 2003 1999000",
+    "text v21:42",
+    "loop 40079",
+];
+
+#[test]
+fn compiled_code_agrees_with_the_interpreter() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("modes");
+    compile_java(&dir, "Main.java", PROGRAM);
+
+    for mode in ["-Xint", "-Xmixed", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "Main"]);
+        assert!(
+            output.status.success(),
+            "{} failed: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(results(&output), EXPECTED, "{}", mode);
+    }
+}
+
+#[test]
+fn print_compilation_logs_compiles_and_deoptimizations() {
+    if !has_javac() || !aria_core::jit::is_supported() {
+        return;
+    }
+    let dir = temp_dir("print");
+    compile_java(&dir, "Main.java", PROGRAM);
+
+    let output = run_aria(&dir, &["-Xcomp", "-XX:+PrintCompilation", "Main"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Main::arith ("), "{}", stdout);
+    // Loading Main$Late invalidates code that devirtualized Base::get.
+    assert!(
+        stdout.contains("made not entrant  Main::dispatch"),
+        "{}",
+        stdout
+    );
+    assert_eq!(results(&output), EXPECTED);
+
+    let output = run_aria(&dir, &["-Xint", "-XX:+PrintCompilation", "Main"]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("bytes)"));
+
+    // Hot loops move to compiled code without waiting for another call.
+    let output = run_aria(&dir, &["-XX:+PrintCompilation", "Main"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Main::main ("), "{}", stdout);
+}
+
+#[test]
+fn version_reports_the_execution_mode() {
+    let dir = temp_dir("version");
+    let version = |args: &[&str]| {
+        let output = run_aria(&dir, args);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+
+    assert!(version(&["-Xint", "-version"]).contains("interpreted mode"));
+    if aria_core::jit::is_supported() {
+        assert!(version(&["-version"]).contains("mixed mode"));
+        assert!(version(&["-Xcomp", "-version"]).contains("compiled mode"));
+    }
+}
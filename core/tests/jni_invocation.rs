@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+mod common;
+
+use common::{compile_java, has_cc, has_javac, temp_dir};
+
+/// `cargo test` only builds the rlib, so the staticlib is built into a
+/// target directory of its own.
+fn build_staticlib() -> PathBuf {
+    let target_dir = Path::new(env!("CARGO_BIN_EXE_aria_core"))
+        .parent()
+        .and_then(Path::parent)
+        .expect("target dir")
+        .join("invocation-test");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = Command::new(cargo)
+        .arg("build")
+        .arg("--lib")
+        .arg("--manifest-path")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .output()
+        .expect("spawn cargo");
+    assert!(
+        output.status.success(),
+        "cargo build failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    target_dir.join("debug").join("libaria_core.a")
+}
+
+const HOST: &str = r#"
+#include <jni.h>
+#include <pthread.h>
+#include <stdio.h>
+
+static JavaVM* vm;
+
+static void* busy(void* arg) {
+    JNIEnv* env;
+    printf("r busy %d\n", (int)(*vm)->AttachCurrentThread(vm, (void**)&env, NULL));
+    return NULL;
+}
+
+static void* worker(void* arg) {
+    JNIEnv* env;
+    JavaVMAttachArgs attach = {JNI_VERSION_1_8, "worker", NULL};
+    if ((*vm)->AttachCurrentThread(vm, (void**)&env, &attach) != JNI_OK) {
+        printf("r attach failed\n");
+        return NULL;
+    }
+    jclass plugin = (*env)->FindClass(env, "Plugin");
+    jmethodID scale = (*env)->GetStaticMethodID(env, plugin, "scale", "(I)I");
+    printf("r worker %d\n", (int)(*env)->CallStaticIntMethod(env, plugin, scale, 7));
+    (*vm)->DetachCurrentThread(vm);
+    return NULL;
+}
+
+int main(void) {
+    JNIEnv* env;
+    JNIEnv* current;
+    JavaVM* found;
+    jsize count;
+    pthread_t thread;
+    jint status;
+    int detached;
+
+    setvbuf(stdout, NULL, _IONBF, 0);
+
+    JavaVMInitArgs probe = {JNI_VERSION_1_1, 0, NULL, JNI_FALSE};
+    printf("r default 1.1 %d\n", (int)JNI_GetDefaultJavaVMInitArgs(&probe));
+    probe.version = JNI_VERSION_10;
+    printf("r default 10 %d\n", (int)JNI_GetDefaultJavaVMInitArgs(&probe));
+
+    JavaVMOption bad[1] = {{"--bogus", NULL}};
+    JavaVMInitArgs bad_args = {JNI_VERSION_10, 1, bad, JNI_FALSE};
+    printf("r bad %d\n", (int)JNI_CreateJavaVM(&vm, (void**)&env, &bad_args));
+
+    JavaVMOption options[3] = {
+        {"-Djava.class.path=.", NULL}, {"-Dplugin.name=demo", NULL}, {"-Xmx64m", NULL}};
+    JavaVMInitArgs args = {JNI_VERSION_10, 3, options, JNI_FALSE};
+    JNI_GetCreatedJavaVMs(&found, 1, &count);
+    printf("r created before %d\n", (int)count);
+    printf("r create %d\n", (int)JNI_CreateJavaVM(&vm, (void**)&env, &args));
+    printf("r again %d\n", (int)JNI_CreateJavaVM(&vm, (void**)&env, &args));
+    JNI_GetCreatedJavaVMs(&found, 1, &count);
+    printf("r created %d %d\n", (int)count, found == vm);
+    status = (*vm)->GetEnv(vm, (void**)&current, JNI_VERSION_1_8);
+    printf("r getenv %d %d\n", (int)status, current == env);
+
+    jclass plugin = (*env)->FindClass(env, "Plugin");
+    jmethodID greet =
+        (*env)->GetStaticMethodID(env, plugin, "greet", "(Ljava/lang/String;)Ljava/lang/String;");
+    jstring result =
+        (*env)->CallStaticObjectMethod(env, plugin, greet, (*env)->NewStringUTF(env, "host"));
+    const char* chars = (*env)->GetStringUTFChars(env, result, NULL);
+    printf("r greet %s\n", chars);
+    (*env)->ReleaseStringUTFChars(env, result, chars);
+
+    jmethodID fail = (*env)->GetStaticMethodID(env, plugin, "fail", "()V");
+    (*env)->CallStaticVoidMethod(env, plugin, fail);
+    printf("r exception %d\n", (int)(*env)->ExceptionCheck(env));
+    (*env)->ExceptionDescribe(env);
+    printf("r cleared %d\n", (int)(*env)->ExceptionCheck(env));
+
+    pthread_create(&thread, NULL, busy, NULL);
+    pthread_join(thread, NULL);
+    detached = (*vm)->DetachCurrentThread(vm);
+    pthread_create(&thread, NULL, worker, NULL);
+    pthread_join(thread, NULL);
+    printf("r detach %d\n", detached);
+    printf("r getenv detached %d\n", (int)(*vm)->GetEnv(vm, (void**)&current, JNI_VERSION_1_8));
+
+    printf("r destroy %d\n", (int)(*vm)->DestroyJavaVM(vm));
+    JNI_GetCreatedJavaVMs(&found, 1, &count);
+    printf("r created after %d\n", (int)count);
+    printf("r recreate %d\n", (int)JNI_CreateJavaVM(&vm, (void**)&env, &args));
+    return 0;
+}
+"#;
+
+#[test]
+fn c_host_embeds_the_vm() {
+    if !has_javac() || !has_cc() {
+        return;
+    }
+
+    let dir = temp_dir("invocation");
+
+    compile_java(
+        &dir,
+        "Plugin.java",
+        r#"
+        public class Plugin {
+          static String greet(String who) {
+            return "hello " + who + " from " + System.getProperty("plugin.name");
+          }
+
+          static int scale(int x) {
+            return x * 6;
+          }
+
+          static void fail() {
+            throw new IllegalStateException("plugin failure");
+          }
+        }
+        "#,
+    );
+
+    let staticlib = build_staticlib();
+    let host_source = dir.join("host.c");
+    fs::write(&host_source, HOST).expect("write host source");
+    let host = dir.join("host");
+    let cc = Command::new("cc")
+        .arg(concat!("-I", env!("CARGO_MANIFEST_DIR"), "/include"))
+        .arg("-o")
+        .arg(&host)
+        .arg(&host_source)
+        .arg(&staticlib)
+        .args([
+            "-lgcc_s",
+            "-lutil",
+            "-lrt",
+            "-lpthread",
+            "-lm",
+            "-ldl",
+            "-lc",
+        ])
+        .output()
+        .expect("spawn cc");
+    assert!(
+        cc.status.success(),
+        "cc failed:\n{}",
+        String::from_utf8_lossy(&cc.stderr)
+    );
+
+    let run = Command::new(&host)
+        .current_dir(&dir)
+        .output()
+        .expect("run host");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    let stderr = String::from_utf8_lossy(&run.stderr);
+    let results: Vec<&str> = stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix("r "))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            "default 1.1 -3",
+            "default 10 0",
+            "bad -1",
+            "created before 0",
+            "create 0",
+            "again -5",
+            "created 1 1",
+            "getenv 0 1",
+            "greet hello host from demo",
+            "exception 1",
+            "cleared 0",
+            "busy -1",
+            "worker 42",
+            "detach 0",
+            "getenv detached -2",
+            "destroy 0",
+            "created after 0",
+            "recreate -1",
+        ],
+        "stdout:\n{}\nstderr:\n{}",
+        stdout,
+        stderr
+    );
+    assert!(
+        stderr.contains("Unrecognized option: --bogus"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("java.lang.IllegalStateException: plugin failure"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(run.status.code(), Some(0), "stderr: {}", stderr);
+}
@@ -0,0 +1,387 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+mod common;
+
+use common::{compile_java, has_cc, has_javac, temp_dir};
+
+fn compile_library(temp_dir: &Path, name: &str, source: &str) {
+    let file_path = temp_dir.join(format!("{}.c", name));
+    fs::write(&file_path, source).expect("write c source");
+
+    let output = Command::new("cc")
+        .arg("-shared")
+        .arg("-fPIC")
+        .arg(concat!("-I", env!("CARGO_MANIFEST_DIR"), "/include"))
+        .arg("-o")
+        .arg(temp_dir.join(format!("lib{}.so", name)))
+        .arg(&file_path)
+        .output()
+        .expect("spawn cc");
+
+    assert!(
+        output.status.success(),
+        "cc failed:\nstdout={}\nstderr={}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+const LIBRARY: &str = r#"
+#include <jni.h>
+#include <stdio.h>
+#include <string.h>
+
+static jobject kept;
+
+JNIEXPORT jint JNICALL Java_Main_add(JNIEnv* env, jclass clazz, jint a, jint b) {
+    return a + b;
+}
+
+JNIEXPORT jdouble JNICALL Java_Main_scale(JNIEnv* env, jobject self, jdouble x) {
+    jfieldID base = (*env)->GetFieldID(env, (*env)->GetObjectClass(env, self), "base", "I");
+    return x * (*env)->GetIntField(env, self, base);
+}
+
+JNIEXPORT jstring JNICALL Java_Main_greet(JNIEnv* env, jclass clazz, jstring name) {
+    char buffer[128];
+    const char* chars = (*env)->GetStringUTFChars(env, name, NULL);
+    snprintf(buffer, sizeof buffer, "hello, %s (%d)", chars,
+             (int)(*env)->GetStringLength(env, name));
+    (*env)->ReleaseStringUTFChars(env, name, chars);
+    return (*env)->NewStringUTF(env, buffer);
+}
+
+JNIEXPORT jint JNICALL Java_Main_sum(JNIEnv* env, jclass clazz, jintArray values) {
+    jint total = 0;
+    jsize length = (*env)->GetArrayLength(env, values);
+    jint* elements = (*env)->GetIntArrayElements(env, values, NULL);
+    for (jsize i = 0; i < length; i++) {
+        total += elements[i];
+        elements[i] = 0;
+    }
+    (*env)->ReleaseIntArrayElements(env, values, elements, JNI_ABORT);
+    return total;
+}
+
+JNIEXPORT void JNICALL Java_Main_fill(JNIEnv* env, jclass clazz, jintArray values, jint v) {
+    jint region[2] = {v, v + 1};
+    (*env)->SetIntArrayRegion(env, values, 1, 2, region);
+    (*env)->SetIntArrayRegion(env, values, 3, 2, region);
+}
+
+JNIEXPORT jobjectArray JNICALL Java_Main_split(JNIEnv* env, jclass clazz, jstring text) {
+    jclass string_class = (*env)->FindClass(env, "java/lang/String");
+    jobjectArray parts = (*env)->NewObjectArray(env, 2, string_class, NULL);
+    jsize length = (*env)->GetStringLength(env, text);
+    jchar chars[64];
+    (*env)->GetStringRegion(env, text, 0, length, chars);
+    (*env)->SetObjectArrayElement(env, parts, 0, (*env)->NewString(env, chars, length / 2));
+    (*env)->SetObjectArrayElement(env, parts, 1,
+                                  (*env)->NewString(env, chars + length / 2, length - length / 2));
+    return parts;
+}
+
+JNIEXPORT jint JNICALL Java_Main_over__I(JNIEnv* env, jclass clazz, jint x) {
+    return x + 1;
+}
+
+JNIEXPORT jint JNICALL Java_Main_over__Ljava_lang_String_2(JNIEnv* env, jclass clazz, jstring s) {
+    return (*env)->GetStringUTFLength(env, s);
+}
+
+JNIEXPORT jint JNICALL Java_Main_callback(JNIEnv* env, jobject self) {
+    jclass clazz = (*env)->GetObjectClass(env, self);
+    jmethodID twice = (*env)->GetMethodID(env, clazz, "twice", "(I)I");
+    jfieldID base = (*env)->GetFieldID(env, clazz, "base", "I");
+    return (*env)->CallIntMethod(env, self, twice, (*env)->GetIntField(env, self, base));
+}
+
+JNIEXPORT jint JNICALL Java_Main_safeDiv(JNIEnv* env, jclass clazz, jint x) {
+    jmethodID div = (*env)->GetStaticMethodID(env, clazz, "div", "(I)I");
+    jint result = (*env)->CallStaticIntMethod(env, clazz, div, x);
+    if ((*env)->ExceptionCheck(env)) {
+        (*env)->ExceptionClear(env);
+        return -1;
+    }
+    return result;
+}
+
+JNIEXPORT void JNICALL Java_Main_bump(JNIEnv* env, jclass clazz) {
+    jfieldID counter = (*env)->GetStaticFieldID(env, clazz, "counter", "I");
+    (*env)->SetStaticIntField(env, clazz, counter,
+                              (*env)->GetStaticIntField(env, clazz, counter) + 5);
+}
+
+JNIEXPORT void JNICALL Java_Main_boom(JNIEnv* env, jclass clazz) {
+    jclass exception = (*env)->FindClass(env, "java/lang/IllegalStateException");
+    (*env)->ThrowNew(env, exception, "from C");
+}
+
+JNIEXPORT jstring JNICALL Java_Main_keep(JNIEnv* env, jclass clazz, jstring s) {
+    jstring previous = kept == NULL ? NULL : (*env)->NewLocalRef(env, kept);
+    if (kept != NULL) {
+        (*env)->DeleteGlobalRef(env, kept);
+    }
+    kept = (*env)->NewGlobalRef(env, s);
+    return previous;
+}
+
+static jint registered(JNIEnv* env, jclass clazz, jint x) {
+    return x * 100;
+}
+
+JNIEXPORT jint JNICALL JNI_OnLoad(JavaVM* vm, void* reserved) {
+    JNIEnv* env;
+    JNINativeMethod methods[] = {{"registered", "(I)I", (void*)registered}};
+    if ((*vm)->GetEnv(vm, (void**)&env, JNI_VERSION_1_8) != JNI_OK) {
+        return JNI_ERR;
+    }
+    (*env)->RegisterNatives(env, (*env)->FindClass(env, "Main"), methods, 1);
+    return JNI_VERSION_1_8;
+}
+"#;
+
+#[test]
+fn jni_libraries_bind_native_methods() {
+    if !has_javac() || !has_cc() {
+        return;
+    }
+
+    let dir = temp_dir("jni");
+
+    compile_library(&dir, "ariatest", LIBRARY);
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          static int counter = 1;
+          int base = 10;
+
+          static native int add(int a, int b);
+          native double scale(double x);
+          static native String greet(String name);
+          static native int sum(int[] values);
+          static native void fill(int[] values, int v);
+          static native String[] split(String text);
+          static native int over(int x);
+          static native int over(String s);
+          native int callback();
+          static native int safeDiv(int x);
+          static native void bump();
+          static native void boom();
+          static native String keep(String s);
+          static native int registered(int x);
+          static native int missing();
+
+          int twice(int x) {
+            return 2 * x;
+          }
+
+          static int div(int x) {
+            return 100 / x;
+          }
+
+          static void print(Object value) {
+            System.out.print("r ");
+            System.out.println(value);
+          }
+
+          public static void main(String[] args) {
+            try {
+              System.load("libariatest.so");
+            } catch (UnsatisfiedLinkError e) {
+              print(e);
+            }
+            try {
+              System.loadLibrary("nosuchlib");
+            } catch (UnsatisfiedLinkError e) {
+              print(e);
+            }
+            System.loadLibrary("ariatest");
+
+            Main m = new Main();
+            print(add(2, 40));
+            print(m.scale(1.5));
+            print(greet("w\u00f6rld"));
+            int[] values = new int[] {1, 2, 3, 4, 5, 6};
+            print(sum(values));
+            fill(values, 7);
+            print(values[0] + "," + values[1] + "," + values[2] + "," + values[3] + "," + values[4] + "," + values[5]);
+            String[] parts = split("abcdef");
+            print(parts.length + " " + parts[0] + "|" + parts[1]);
+            print(over(41));
+            print(over("\u00e9t\u00e9"));
+            print(m.callback());
+            print(safeDiv(4));
+            print(safeDiv(0));
+            bump();
+            print(counter);
+            try {
+              boom();
+              print("not thrown");
+            } catch (IllegalStateException e) {
+              print(e);
+            }
+            print(keep("first"));
+            print(keep("second"));
+            print(registered(3));
+            try {
+              missing();
+            } catch (UnsatisfiedLinkError e) {
+              print(e);
+            }
+          }
+        }
+        "#,
+    );
+
+    let aria = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg(format!("-Djava.library.path={}", dir.display()))
+        .arg("Main")
+        .current_dir(&dir)
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&aria.stdout);
+    let stderr = String::from_utf8_lossy(&aria.stderr);
+    let results: Vec<&str> = stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix("r "))
+        .collect();
+    let missing = format!(
+        "java.lang.UnsatisfiedLinkError: no nosuchlib in java.library.path: {}",
+        dir.display()
+    );
+    assert_eq!(
+        results,
+        vec![
+            "java.lang.UnsatisfiedLinkError: Expecting an absolute path of the library: libariatest.so",
+            missing.as_str(),
+            "42",
+            "15.0",
+            "hello, w\u{f6}rld (5)",
+            "21",
+            "1,7,8,7,8,6",
+            "2 abc|def",
+            "42",
+            "5",
+            "20",
+            "25",
+            "-1",
+            "6",
+            "java.lang.IllegalStateException: from C",
+            "null",
+            "first",
+            "300",
+            "java.lang.UnsatisfiedLinkError: 'int Main.missing()'",
+        ],
+        "stdout:\n{}\nstderr:\n{}",
+        stdout,
+        stderr
+    );
+    assert_eq!(aria.status.code(), Some(0), "stderr: {}", stderr);
+}
+
+/// Defines `Nat`, whose natives live in a JNI library, through a
+/// user-defined loader; `NAT` is replaced with the bytes of its class file.
+const LOADER_HOST: &str = r#"
+public class Main {
+    static class NatLoader extends ClassLoader {
+        NatLoader() {
+            super("nat", ClassLoader.getSystemClassLoader());
+        }
+
+        protected Class<?> findClass(String name) throws ClassNotFoundException {
+            byte[] bytes = new byte[] {NAT};
+            return defineClass(name, bytes, 0, bytes.length);
+        }
+    }
+
+    public static void main(String[] args) throws Exception {
+        System.loadLibrary("arianat");
+        Class<?> nat = new NatLoader().loadClass("Nat");
+        ((Runnable) nat.getDeclaredConstructor().newInstance()).run();
+    }
+}
+"#;
+
+const LOADER_LIBRARY: &str = r#"
+#include <jni.h>
+
+JNIEXPORT jint JNICALL Java_Nat_answer(JNIEnv* env, jclass clazz) {
+    return 42;
+}
+"#;
+
+#[test]
+fn jni_natives_bind_for_classes_of_user_loaders() {
+    if !has_javac() || !has_cc() {
+        return;
+    }
+
+    let dir = temp_dir("jni-loader");
+    let nat_dir = dir.join("nat");
+    fs::create_dir_all(&nat_dir).expect("mkdir");
+
+    compile_library(&dir, "arianat", LOADER_LIBRARY);
+    compile_java(
+        &nat_dir,
+        "Nat.java",
+        r#"
+        public class Nat implements Runnable {
+          static native int answer();
+          static native int missing();
+
+          public void run() {
+            System.out.println("r " + answer());
+            try {
+              missing();
+            } catch (UnsatisfiedLinkError e) {
+              System.out.println("r " + e.getMessage());
+            }
+          }
+        }
+        "#,
+    );
+    let bytes = fs::read(nat_dir.join("Nat.class")).expect("read class");
+    let literal = bytes
+        .iter()
+        .map(|byte| (*byte as i8).to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    compile_java(
+        &dir,
+        "Main.java",
+        &LOADER_HOST.replace("NAT}", &format!("{}}}", literal)),
+    );
+
+    let aria = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg(format!("-Djava.library.path={}", dir.display()))
+        .arg("Main")
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&aria.stdout);
+    let stderr = String::from_utf8_lossy(&aria.stderr);
+    let results: Vec<&str> = stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix("r "))
+        .collect();
+    assert_eq!(
+        results,
+        vec!["42", "'int Nat.missing()'"],
+        "stdout:\n{}\nstderr:\n{}",
+        stdout,
+        stderr
+    );
+    assert_eq!(aria.status.code(), Some(0), "stderr: {}", stderr);
+}
@@ -0,0 +1,272 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+mod common;
+
+use common::{compile_java, has_javac, results, temp_dir};
+
+/// Runs in `dir` with only the given launcher environment variables set.
+fn run_aria(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_aria_core"));
+    command
+        .args(args)
+        .current_dir(dir)
+        .env_remove("JDK_JAVA_OPTIONS")
+        .env_remove("JAVA_TOOL_OPTIONS");
+    for (name, value) in env {
+        command.env(name, value);
+    }
+    command.output().expect("run aria_core")
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+const MAIN: &str = r#"
+public class Main {
+    public static void main(String[] args) {
+        System.out.println("r " + System.getProperty("greeting") + " " + args.length);
+        for (int i = 0; i < args.length; i++) {
+            System.out.println("r arg " + args[i]);
+        }
+    }
+}
+"#;
+
+fn compiled_main(tag: &str) -> std::path::PathBuf {
+    let dir = temp_dir(tag);
+    compile_java(&dir, "Main.java", MAIN);
+    dir
+}
+
+#[test]
+fn argfiles_expand_up_to_the_main_class() {
+    if !has_javac() {
+        return;
+    }
+    let dir = compiled_main("argfile");
+    fs::write(
+        dir.join("options.txt"),
+        "# launcher options\n-cp .\n\"-Dgreeting=hello world\"\n",
+    )
+    .expect("write argfile");
+    fs::write(dir.join("main.txt"), "Main first").expect("write argfile");
+
+    let output = run_aria(&dir, &["@options.txt", "@main.txt", "@options.txt"], &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        results(&output),
+        ["hello world 2", "arg first", "arg @options.txt"]
+    );
+
+    let escaped = run_aria(&dir, &["-cp", ".", "Main", "@@kept"], &[]);
+    assert_eq!(results(&escaped), ["null 1", "arg @@kept"]);
+
+    let disabled = run_aria(&dir, &["--disable-@files", "@options.txt", "Main"], &[]);
+    assert_eq!(disabled.status.code(), Some(1));
+    assert!(stderr(&disabled).contains("@options.txt"));
+
+    let missing = run_aria(&dir, &["@missing.txt", "Main"], &[]);
+    assert_eq!(missing.status.code(), Some(1));
+    assert_eq!(stderr(&missing), "Error: could not open `missing.txt'\n");
+}
+
+#[test]
+fn environment_options_come_before_the_command_line() {
+    if !has_javac() {
+        return;
+    }
+    let dir = compiled_main("env");
+
+    let jdk = run_aria(&dir, &["Main"], &[("JDK_JAVA_OPTIONS", "-Dgreeting=jdk")]);
+    assert!(jdk.status.success(), "{}", stderr(&jdk));
+    assert!(stderr(&jdk).contains("NOTE: Picked up JDK_JAVA_OPTIONS: -Dgreeting=jdk"));
+    assert_eq!(results(&jdk), ["jdk 0"]);
+
+    let overridden = run_aria(
+        &dir,
+        &["-Dgreeting=cli", "Main"],
+        &[
+            ("JAVA_TOOL_OPTIONS", "-Dgreeting=tool"),
+            ("JDK_JAVA_OPTIONS", "-Dgreeting=jdk"),
+        ],
+    );
+    assert!(stderr(&overridden).contains("Picked up JAVA_TOOL_OPTIONS: -Dgreeting=tool"));
+    assert_eq!(results(&overridden), ["cli 0"]);
+
+    let tool = run_aria(&dir, &["Main"], &[("JAVA_TOOL_OPTIONS", "-Dgreeting=tool")]);
+    assert_eq!(results(&tool), ["tool 0"]);
+
+    let main_class = run_aria(&dir, &["Main"], &[("JDK_JAVA_OPTIONS", "-Xint Main")]);
+    assert_eq!(main_class.status.code(), Some(1));
+    assert!(stderr(&main_class)
+        .contains("Error: Cannot specify main class in environment variable JDK_JAVA_OPTIONS"));
+}
+
+#[test]
+fn rejected_options_use_hotspot_messages() {
+    let dir = temp_dir("errors");
+    let creation_failed = "Error: Could not create the Java Virtual Machine.\n\
+                           Error: A fatal exception has occurred. Program will exit.\n";
+    let cases: &[(&[&str], String)] = &[
+        (
+            &["--bogus"],
+            format!("Unrecognized option: --bogus\n{}", creation_failed),
+        ),
+        (
+            &["-XX:+NoSuchFlag", "Main"],
+            format!("Unrecognized VM option 'NoSuchFlag'\n{}", creation_failed),
+        ),
+        (
+            &["-Xmx12q", "Main"],
+            format!("Invalid maximum heap size: -Xmx12q\n{}", creation_failed),
+        ),
+        (
+            &["-Xmsbig", "Main"],
+            format!("Invalid initial heap size: -Xmsbig\n{}", creation_failed),
+        ),
+        (
+            &["-Xss2g", "Main"],
+            format!(
+                "Invalid thread stack size: -Xss2g. The specified size exceeds the maximum representable size.\n{}",
+                creation_failed
+            ),
+        ),
+        (
+            &["-Xss64k", "Main"],
+            format!(
+                "The Java thread stack size specified is too small. Specify at least 136k\n{}",
+                creation_failed
+            ),
+        ),
+        (
+            &["-Xmx1k", "Main"],
+            "Error occurred during initialization of VM\nToo small maximum heap\n".to_string(),
+        ),
+        (
+            &["-cp"],
+            "Error: -cp requires class path specification\n".to_string(),
+        ),
+        (
+            &["-cp", ".", "a.b.Nope"],
+            "Error: Could not find or load main class a.b.Nope\n\
+             Caused by: java.lang.ClassNotFoundException: a.b.Nope\n"
+                .to_string(),
+        ),
+        (
+            &["-Xms64m", "-Xmx32m", "Main"],
+            "Error occurred during initialization of VM\nInitial heap size set to a larger value than the maximum heap size\n"
+                .to_string(),
+        ),
+    ];
+    for (args, expected) in cases {
+        let output = run_aria(&dir, args, &[]);
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert_eq!(&stderr(&output), expected, "{:?}", args);
+    }
+}
+
+#[test]
+fn malformed_main_class_reports_a_linkage_error() {
+    let dir = temp_dir("malformed");
+    // The magic, the version and a constant pool count, then nothing.
+    let truncated = [0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D, 0x00, 0x10];
+    fs::write(dir.join("Main.class"), truncated).expect("write class");
+
+    let output = run_aria(&dir, &["Main"], &[]);
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "Error: LinkageError occurred while loading main class Main\n\
+         \tjava.lang.ClassFormatError: Truncated class file at offset 10\n"
+    );
+}
+
+#[test]
+fn help_and_version_go_to_the_requested_stream() {
+    let dir = temp_dir("help");
+
+    let extra = run_aria(&dir, &["-X"], &[]);
+    assert_eq!(extra.status.code(), Some(0));
+    assert!(stderr(&extra).contains("-Xmx<size>"));
+    assert!(extra.stdout.is_empty());
+
+    let extra = run_aria(&dir, &["--help-extra"], &[]);
+    assert!(String::from_utf8_lossy(&extra.stdout).contains("-Xss<size>"));
+
+    let help = run_aria(&dir, &["-?"], &[]);
+    assert_eq!(help.status.code(), Some(0));
+    assert!(stderr(&help).starts_with("Usage: java [options] <mainclass> [args...]"));
+
+    let version = run_aria(&dir, &["--version"], &[]);
+    assert_eq!(version.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&version.stdout).starts_with("openjdk 17"));
+
+    let settings = run_aria(
+        &dir,
+        &["-XshowSettings:vm", "-Xms16m", "-Xmx64M", "-version"],
+        &[],
+    );
+    assert_eq!(settings.status.code(), Some(0));
+    let text = stderr(&settings);
+    assert!(
+        text.starts_with("VM settings:\n    Min. Heap Size: 16.00M\n    Max. Heap Size: 64.00M\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains("openjdk version \"17"));
+
+    let properties = run_aria(
+        &dir,
+        &["-XshowSettings:properties", "-Dgreeting=hi", "-version"],
+        &[],
+    );
+    assert!(stderr(&properties).contains("Property settings:\n"));
+    assert!(stderr(&properties).contains("    greeting = hi\n"));
+}
+
+#[test]
+fn launch_options_apply_to_the_program() {
+    if !has_javac() {
+        return;
+    }
+    let dir = compiled_main("launch");
+
+    let shown = run_aria(
+        &dir,
+        &["-showversion", "-Xss1m", "-Xmx64m", "Main", "x"],
+        &[],
+    );
+    assert!(shown.status.success(), "{}", stderr(&shown));
+    assert!(stderr(&shown).starts_with("openjdk version \"17"));
+    assert_eq!(results(&shown), ["null 1", "arg x"]);
+
+    let shown = run_aria(&dir, &["--show-version", "Main"], &[]);
+    assert!(String::from_utf8_lossy(&shown.stdout).starts_with("openjdk 17"));
+    assert_eq!(results(&shown), ["null 0"]);
+
+    let assertions = run_aria(
+        &dir,
+        &[
+            "-ea",
+            "-da:com.example...",
+            "-disableassertions:Main",
+            "-esa",
+            "Main",
+        ],
+        &[],
+    );
+    assert_eq!(results(&assertions), ["null 0"]);
+
+    let dry_run = run_aria(&dir, &["--dry-run", "Main"], &[]);
+    assert_eq!(dry_run.status.code(), Some(0));
+    assert!(results(&dry_run).is_empty());
+    assert!(String::from_utf8_lossy(&dry_run.stdout).contains("Loading class: Main"));
+
+    let missing = run_aria(&dir, &["--dry-run", "Missing"], &[]);
+    assert_eq!(missing.status.code(), Some(1));
+}
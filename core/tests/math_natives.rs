@@ -0,0 +1,271 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+use common::{compile_java, has_javac, temp_dir};
+
+fn has_java() -> bool {
+    Command::new("java").arg("-version").output().is_ok()
+}
+
+/// Lines tagged with "r " — the interpreter's own trace output is ignored.
+fn results(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .filter_map(|l| l.strip_prefix("r ").map(str::to_string))
+        .collect()
+}
+
+/// JDK 17's `Double.toString` sometimes prints more digits than the
+/// shortest round-tripping form, so numbers are compared by value.
+fn same_result(want: &str, got: &str) -> bool {
+    match (want.parse::<f64>(), got.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan()),
+        _ => want == got,
+    }
+}
+
+#[test]
+fn math_results_match_hotspot() {
+    if !has_javac() || !has_java() {
+        return;
+    }
+
+    let dir = temp_dir("math");
+
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          static void r(double v) { System.out.print("r "); System.out.println(v); }
+          static void r(float v) { System.out.print("r "); System.out.println(v); }
+          static void r(long v) { System.out.print("r "); System.out.println(v); }
+          static void r(int v) { System.out.print("r "); System.out.println(v); }
+
+          public static void main(String[] args) {
+            double x = 0.3;
+            for (int i = 0; i < 24; i++) {
+              r(StrictMath.sin(x));
+              r(StrictMath.cos(x));
+              r(StrictMath.tan(x));
+              r(StrictMath.exp(x));
+              r(StrictMath.log(x));
+              r(StrictMath.log10(x));
+              r(StrictMath.pow(x, 1.37));
+              r(StrictMath.atan2(x, 3.0));
+              r(StrictMath.hypot(x, 2.5));
+              r(StrictMath.sinh(x / 10));
+              r(StrictMath.expm1(x / 100));
+              x = x * 2.7 + 0.11;
+            }
+            r(Math.sqrt(2.0));
+            r(Math.pow(2.0, 10.0));
+            r(Math.floor(-2.5));
+            r(Math.ceil(-2.5));
+            r(Math.rint(2.5));
+            r(Math.rint(3.5));
+            r(Math.round(-2.5));
+            r(Math.round(2.5));
+            r(Math.round(0.49999999999999994));
+            r(Math.round(-0.5f));
+            r(Math.round(Double.NaN));
+            r(Math.signum(-3.0));
+            r(Math.min(-0.0, 0.0));
+            r(Math.max(Double.NaN, 1.0));
+            r(Math.max(-0.0f, 0.0f));
+            r(Math.abs(-0.0));
+            r(Math.abs(Integer.MIN_VALUE));
+            r(Math.min(3L, -4L));
+            r(Math.fma(0.1, 10.0, -1.0));
+            r(Math.toRadians(180.0));
+            r(Math.toDegrees(1.0));
+            r(Math.ulp(1.0));
+            r(Math.ulp(1.0f));
+            r(Math.nextUp(1.0));
+            r(Math.nextAfter(1.0f, 0.0));
+            r(Math.copySign(3.0, -0.0));
+            r(Math.getExponent(1024.0));
+            r(Math.scalb(1.5, -1074));
+            r(Math.IEEEremainder(10.0, 3.0));
+            r(Math.cbrt(27.0));
+            r(Math.floorDiv(-7, 2));
+            r(Math.floorMod(-7, 2));
+            r(Math.floorDiv(-7L, 2L));
+            r(Math.floorMod(7L, -3L));
+            r(Math.floorDiv(Long.MIN_VALUE, -1L));
+            r(Math.floorMod(Long.MIN_VALUE, -1L));
+            r(Math.floorDiv(Integer.MIN_VALUE, -1));
+            r(Math.floorMod(Integer.MIN_VALUE, -1));
+            r(Math.multiplyHigh(Long.MAX_VALUE, 3L));
+            r(Math.multiplyFull(Integer.MAX_VALUE, 2));
+            r(Math.addExact(1, 2));
+            r(7 % -3);
+            r(-7.5 % 2.0);
+            r((int) 3.9e10);
+            r((long) Float.NaN);
+            r(Long.MIN_VALUE / -1L);
+            r(Integer.MAX_VALUE + 1);
+            r(-16 >>> 28);
+            r(-16L >> 2);
+
+            double random = Math.random();
+            r(random >= 0.0 && random < 1.0 ? 1 : 0);
+
+            try {
+              Math.addExact(Integer.MAX_VALUE, 1);
+              r(-1);
+            } catch (ArithmeticException e) {
+              System.out.print("r ");
+              System.out.println(e.getMessage());
+            }
+            try {
+              Math.multiplyExact(Long.MAX_VALUE, 2L);
+              r(-1);
+            } catch (ArithmeticException e) {
+              System.out.print("r ");
+              System.out.println(e.getMessage());
+            }
+            try {
+              Math.floorMod(1, 0);
+              r(-1);
+            } catch (ArithmeticException e) {
+              System.out.print("r ");
+              System.out.println(e.getMessage());
+            }
+            try {
+              r(10 / (args.length));
+            } catch (RuntimeException e) {
+              System.out.print("r ");
+              System.out.println(e);
+            }
+            Math.toIntExact(1L << 40);
+          }
+        }
+        "#,
+    );
+
+    let aria = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg("Main")
+        .output()
+        .expect("run aria_core");
+    let hotspot = Command::new("java")
+        .arg("-cp")
+        .arg(&dir)
+        .arg("Main")
+        .output()
+        .expect("run java");
+    let _ = fs::remove_dir_all(&dir);
+
+    let expected = results(&hotspot.stdout);
+    let actual = results(&aria.stdout);
+    assert_eq!(expected.len(), 24 * 11 + 54, "hotspot output changed");
+    for (i, (want, got)) in expected.iter().zip(actual.iter()).enumerate() {
+        assert!(
+            same_result(want, got),
+            "result #{} differs: {} vs {}",
+            i,
+            want,
+            got
+        );
+    }
+    assert_eq!(expected.len(), actual.len());
+
+    assert_eq!(aria.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&aria.stderr);
+    assert!(
+        stderr.contains(
+            "Exception in thread \"main\" java.lang.ArithmeticException: integer overflow"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("\tat Main.main(Main.java)"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+/// Negative and huge-ratio arguments, where msun's rewrites of these
+/// routines round differently from fdlibm. Expected values are HotSpot's.
+#[test]
+fn strict_math_matches_fdlibm_off_the_easy_path() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("strict-math");
+    let cases = [
+        ("StrictMath.atan2(1e300, -1.0)", "1.5707963267948968"),
+        ("StrictMath.atan2(-1e300, -1.0)", "-1.5707963267948968"),
+        ("StrictMath.atan2(1.0, -1e-300)", "1.5707963267948968"),
+        ("StrictMath.atan2(-3.0, -1e-290)", "-1.5707963267948968"),
+        ("StrictMath.atan2(1e-300, -1e10)", "3.141592653589793"),
+        ("StrictMath.atan2(-0.5, -2.0)", "-2.896613990462929"),
+        ("StrictMath.pow(-2.0, 3.0)", "-8.0"),
+        ("StrictMath.pow(-8.0, 1.0 / 3)", "NaN"),
+        (
+            "StrictMath.pow(-1.0000001, 1e9 + 1)",
+            "-2.6881041270248506E43",
+        ),
+        ("StrictMath.pow(1.0000001, 1e10)", "Infinity"),
+        ("StrictMath.pow(0.5, 1074.5)", "4.9E-324"),
+        ("StrictMath.pow(-3.7, -401.0)", "-1.4161480188871456E-228"),
+        ("StrictMath.exp(-745.0)", "4.9E-324"),
+        ("StrictMath.exp(-700.5)", "5.980196118639791E-305"),
+        ("StrictMath.expm1(-40.0)", "-1.0"),
+        ("StrictMath.expm1(-0.3)", "-0.2591817793182821"),
+        ("StrictMath.tan(-1e300)", "-1.4214488238747245"),
+        (
+            "StrictMath.tan(-1.5707963267948966)",
+            "-1.633123935319537E16",
+        ),
+        ("StrictMath.asin(-0.98)", "-1.3704614844717768"),
+        ("StrictMath.acos(-0.7)", "2.34619382340565"),
+        ("StrictMath.acos(-1.0)", "3.141592653589793"),
+        ("StrictMath.atan(-1e20)", "-1.5707963267948966"),
+        ("StrictMath.atan(-2.0)", "-1.1071487177940904"),
+        ("StrictMath.IEEEremainder(-1e300, 3.0)", "-0.0"),
+        ("StrictMath.IEEEremainder(-7.5, 2.0)", "0.5"),
+        ("StrictMath.IEEEremainder(5.0, -1e-320)", "-4.74E-322"),
+    ];
+    let calls = cases
+        .iter()
+        .map(|(call, _)| format!("r({});", call))
+        .collect::<Vec<_>>()
+        .join("\n            ");
+    compile_java(
+        &dir,
+        "Main.java",
+        &format!(
+            r#"
+        public class Main {{
+          static void r(double v) {{ System.out.print("r "); System.out.println(v); }}
+
+          public static void main(String[] args) {{
+            {}
+          }}
+        }}
+        "#,
+            calls
+        ),
+    );
+
+    let aria = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg("Main")
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let actual = results(&aria.stdout);
+    assert_eq!(actual.len(), cases.len());
+    for ((call, want), got) in cases.iter().zip(&actual) {
+        assert!(same_result(want, got), "{}: {} vs {}", call, want, got);
+    }
+}
@@ -0,0 +1,607 @@
+use aria_core::bytecode::assembler::{Assembler, ValueKind};
+use aria_core::bytecode::attributes::{Attribute, BootstrapMethod};
+use aria_core::bytecode::constant_pool::ConstantPoolBuilder;
+use aria_core::bytecode::parser::{ClassFile, CodeAttribute, MethodInfo};
+use std::fs;
+
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+const HANDLES: &str = r#"
+import java.lang.invoke.MethodHandle;
+import java.lang.invoke.MethodHandles;
+import java.lang.invoke.MethodType;
+import java.lang.invoke.VarHandle;
+import java.lang.reflect.Method;
+
+public class Handles {
+    private int count = 3;
+    private String label = "start";
+    static long total = 10;
+    static final int LIMIT = limit();
+
+    static int limit() {
+        return 7;
+    }
+
+    public Handles() {}
+
+    public Handles(int count) {
+        this.count = count;
+    }
+
+    public int add(int a, int b) {
+        return a + b + count;
+    }
+
+    public static String join(String a, String b) {
+        return a + "-" + b;
+    }
+
+    public static int sum(int... values) {
+        int total = 0;
+        for (int v : values) {
+            total += v;
+        }
+        return total;
+    }
+
+    private String secret() {
+        return "secret " + count;
+    }
+
+    public String describe() {
+        return "Handles " + count;
+    }
+
+    static boolean isA(String s) {
+        return s == "a";
+    }
+
+    static String wrap(String s) {
+        return "[" + s + "]";
+    }
+
+    static String echo(String s) {
+        return s;
+    }
+
+    static int size(String s) {
+        return s == null ? 0 : 1;
+    }
+
+    static class Child extends Handles {
+        public String describe() {
+            return "Child";
+        }
+    }
+
+    static void fail(Throwable t) {
+        System.out.println("r " + t.getClass().getName() + ": " + t.getMessage());
+    }
+
+    public static void main(String[] args) throws Throwable {
+        MethodHandles.Lookup lookup = MethodHandles.lookup();
+        System.out.println("r lookup " + lookup.toString() + " " + MethodHandles.publicLookup().toString());
+
+        MethodType addType = MethodType.methodType(int.class, int.class, int.class);
+        System.out.println("r type " + addType.toString() + " " + addType.toMethodDescriptorString()
+                + " " + addType.parameterCount() + " " + addType.returnType().getName());
+        MethodType changed = addType.changeReturnType(String.class).appendParameterTypes(long[].class)
+                .insertParameterTypes(0, Handles.class).dropParameterTypes(1, 2);
+        System.out.println("r changed " + changed.toString() + " " + changed.toMethodDescriptorString());
+        System.out.println("r equal " + (addType.equals(MethodType.fromMethodDescriptorString("(II)I", null)) ? "yes" : "no")
+                + " " + (addType.hashCode() == MethodType.methodType(int.class, int.class, int.class).hashCode() ? "same" : "differ"));
+        System.out.println("r generic " + addType.generic().toString() + " " + addType.erase().toString()
+                + " " + MethodType.genericMethodType(2).toString());
+
+        Handles h = new Handles();
+        MethodHandle add = lookup.findVirtual(Handles.class, "add", addType);
+        System.out.println("r add " + add.toString() + " " + (int) add.invokeExact(h, 1, 2));
+        System.out.println("r invoke " + (int) add.invoke(h, (byte) 4, (short) 5));
+        Object boxed = add.invoke(h, Integer.valueOf(6), 7);
+        System.out.println("r boxed " + boxed.toString());
+        long widened = (long) add.invoke(h, 1, 1);
+        System.out.println("r widened " + widened);
+        try {
+            int r = (int) add.invokeExact(h, 1L, 2);
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            String r = (String) add.invoke(h, "x", 2);
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            int r = (int) add.invoke(h, (Object) null, 2);
+        } catch (Throwable t) {
+            System.out.println("r null " + t.getClass().getName());
+        }
+        try {
+            int r = (int) add.invoke(h, (Object) "x", 2);
+        } catch (Throwable t) {
+            System.out.println("r unbox " + t.getClass().getName());
+        }
+
+        MethodHandle join = lookup.findStatic(Handles.class, "join", MethodType.methodType(String.class, String.class, String.class));
+        System.out.println("r join " + (String) join.invokeExact("a", "b"));
+        MethodHandle bound = add.bindTo(h);
+        System.out.println("r bound " + bound.toString() + " " + (int) bound.invokeExact(10, 20));
+        MethodHandle inserted = MethodHandles.insertArguments(join, 1, "tail");
+        System.out.println("r inserted " + inserted.toString() + " " + (String) inserted.invokeExact("head"));
+        MethodHandle dropped = MethodHandles.dropArguments(join, 0, int.class, Object.class);
+        System.out.println("r dropped " + dropped.toString() + " " + (String) dropped.invokeExact(1, (Object) h, "c", "d"));
+        MethodHandle size = lookup.findStatic(Handles.class, "size", MethodType.methodType(int.class, String.class));
+        MethodHandle filtered = MethodHandles.filterReturnValue(join, size);
+        System.out.println("r filtered " + filtered.toString() + " " + (int) filtered.invokeExact("abc", "de"));
+        MethodHandle wrap = lookup.findStatic(Handles.class, "wrap", MethodType.methodType(String.class, String.class));
+        MethodHandle args2 = MethodHandles.filterArguments(join, 1, wrap);
+        System.out.println("r filterArguments " + (String) args2.invokeExact("x", "y"));
+        MethodHandle guard = MethodHandles.guardWithTest(
+                lookup.findStatic(Handles.class, "isA", MethodType.methodType(boolean.class, String.class)),
+                wrap,
+                lookup.findStatic(Handles.class, "echo", MethodType.methodType(String.class, String.class)));
+        System.out.println("r guard " + (String) guard.invokeExact("a") + " " + (String) guard.invokeExact("b"));
+        MethodHandle swapped = MethodHandles.permuteArguments(join, MethodType.methodType(String.class, String.class, String.class), 1, 0);
+        System.out.println("r permuted " + (String) swapped.invokeExact("first", "second"));
+        MethodHandle constant = MethodHandles.constant(String.class, "fixed");
+        System.out.println("r constant " + constant.toString() + " " + (String) constant.invokeExact());
+        MethodHandle identity = MethodHandles.identity(int.class);
+        System.out.println("r identity " + identity.toString() + " " + (int) identity.invokeExact(42));
+        MethodHandle asType = add.asType(MethodType.methodType(Object.class, Handles.class, Integer.class, int.class));
+        System.out.println("r asType " + asType.toString() + " " + asType.invokeExact(h, Integer.valueOf(2), 3).toString());
+        try {
+            add.asType(MethodType.methodType(int.class, Handles.class, String.class, int.class));
+        } catch (Throwable t) {
+            fail(t);
+        }
+
+        MethodHandle sum = lookup.findStatic(Handles.class, "sum", MethodType.methodType(int.class, int[].class));
+        System.out.println("r varargs " + (sum.isVarargsCollector() ? "yes" : "no") + " "
+                + sum.invokeWithArguments(1, 2, 3).toString());
+        MethodHandle spreader = join.asSpreader(String[].class, 2);
+        System.out.println("r spread " + spreader.toString() + " " + (String) spreader.invokeExact(new String[] {"p", "q"}));
+        System.out.println("r withArguments " + (String) join.invokeWithArguments("m", "n"));
+
+        MethodHandle ctor = lookup.findConstructor(Handles.class, MethodType.methodType(void.class, int.class));
+        Handles made = (Handles) ctor.invokeExact(9);
+        System.out.println("r ctor " + ctor.toString() + " " + made.describe());
+        MethodHandle describe = lookup.findVirtual(Handles.class, "describe", MethodType.methodType(String.class));
+        System.out.println("r virtual " + (String) describe.invokeExact((Handles) new Child()));
+        MethodHandle special = lookup.findSpecial(Handles.class, "describe", MethodType.methodType(String.class), Handles.class);
+        System.out.println("r special " + (String) special.invokeExact((Handles) new Child()));
+        MethodHandle secret = lookup.findVirtual(Handles.class, "secret", MethodType.methodType(String.class));
+        System.out.println("r private " + (String) secret.invokeExact(h));
+
+        MethodHandle getter = lookup.findGetter(Handles.class, "count", int.class);
+        MethodHandle setter = lookup.findSetter(Handles.class, "count", int.class);
+        setter.invokeExact(h, 11);
+        System.out.println("r field " + getter.toString() + " " + setter.toString() + " " + (int) getter.invokeExact(h));
+        MethodHandle staticGetter = lookup.findStaticGetter(Handles.class, "total", long.class);
+        MethodHandle staticSetter = lookup.findStaticSetter(Handles.class, "total", long.class);
+        staticSetter.invokeExact(25L);
+        System.out.println("r static " + (long) staticGetter.invokeExact() + " " + total);
+
+        Method reflected = Handles.class.getDeclaredMethod("add", int.class, int.class);
+        System.out.println("r unreflect " + (int) lookup.unreflect(reflected).invoke(h, 1, 1));
+
+        try {
+            lookup.findVirtual(Handles.class, "nope", MethodType.methodType(void.class));
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            lookup.findStatic(Handles.class, "add", addType);
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            MethodHandles.publicLookup().findGetter(Handles.class, "count", int.class);
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            lookup.findGetter(Handles.class, "nope", int.class);
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            lookup.findStaticSetter(Handles.class, "LIMIT", int.class);
+        } catch (Throwable t) {
+            System.out.println("r final " + t.getClass().getName());
+        }
+        try {
+            lookup.findConstructor(Handles.class, MethodType.methodType(int.class));
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            add.bindTo("wrong");
+        } catch (Throwable t) {
+            fail(t);
+        }
+
+        VarHandle countHandle = lookup.findVarHandle(Handles.class, "count", int.class);
+        System.out.println("r varhandle " + countHandle.toString() + " " + countHandle.varType().getName());
+        countHandle.set(h, 1);
+        System.out.println("r vh get " + (int) countHandle.get(h));
+        System.out.println("r vh cas " + (countHandle.compareAndSet(h, 1, 5) ? "yes" : "no") + " "
+                + (countHandle.compareAndSet(h, 1, 6) ? "yes" : "no") + " " + (int) countHandle.get(h));
+        System.out.println("r vh getAndAdd " + (int) countHandle.getAndAdd(h, 10) + " " + (int) countHandle.getVolatile(h));
+        System.out.println("r vh exchange " + (int) countHandle.compareAndExchange(h, 15, 1) + " " + (int) countHandle.getAndSet(h, 2)
+                + " " + (int) countHandle.getAndBitwiseOr(h, 4) + " " + (int) countHandle.get(h));
+        VarHandle labelHandle = lookup.findVarHandle(Handles.class, "label", String.class);
+        String start = (String) labelHandle.get(h);
+        System.out.println("r vh label " + (labelHandle.compareAndSet(h, start, "next") ? "yes" : "no") + " " + (String) labelHandle.get(h));
+        try {
+            labelHandle.getAndAdd(h, "x");
+        } catch (Throwable t) {
+            fail(t);
+        }
+        try {
+            countHandle.set(h, "wrong");
+        } catch (Throwable t) {
+            fail(t);
+        }
+        VarHandle totalHandle = lookup.findStaticVarHandle(Handles.class, "total", long.class);
+        System.out.println("r vh static " + totalHandle.toString() + " " + (long) totalHandle.getAndAdd(5L) + " " + total);
+        VarHandle limitHandle = lookup.findStaticVarHandle(Handles.class, "LIMIT", int.class);
+        System.out.println("r vh final " + (int) limitHandle.get());
+        try {
+            limitHandle.set(8);
+        } catch (Throwable t) {
+            fail(t);
+        }
+
+        VarHandle ints = MethodHandles.arrayElementVarHandle(int[].class);
+        int[] values = {1, 2};
+        ints.set(values, 1, 20);
+        System.out.println("r vh array " + ints.toString() + " " + (int) ints.getAndAdd(values, 0, 3) + " " + values[0] + " " + values[1]);
+        try {
+            ints.get(values, 5);
+        } catch (Throwable t) {
+            fail(t);
+        }
+        VarHandle objects = MethodHandles.arrayElementVarHandle(Object[].class);
+        Object[] strings = new String[1];
+        try {
+            objects.set(strings, 0, Integer.valueOf(1));
+        } catch (Throwable t) {
+            fail(t);
+        }
+        MethodHandle elementGetter = MethodHandles.arrayElementGetter(String[].class);
+        MethodHandle arrayLength = MethodHandles.arrayLength(String[].class);
+        String[] words = {"one", "two"};
+        MethodHandles.arrayElementSetter(String[].class).invokeExact(words, 0, "uno");
+        System.out.println("r array " + elementGetter.toString() + " " + (String) elementGetter.invokeExact(words, 0) + " " + (int) arrayLength.invokeExact(words));
+    }
+}
+"#;
+
+const CONSTANTS_MAIN: &str = r#"
+import java.lang.invoke.MethodHandle;
+import java.lang.invoke.MethodHandles;
+import java.lang.invoke.MethodType;
+
+public class Main {
+    static int limit = 5;
+    static final int CAP = cap();
+    static int calls;
+
+    static int cap() {
+        return 9;
+    }
+
+    public static int twice(int x) {
+        return x * 2;
+    }
+
+    public static Object bootstrap(MethodHandles.Lookup lookup, String name, Class<?> type) {
+        calls++;
+        return "made " + name + " in " + lookup.lookupClass().getName() + " as " + type.getName();
+    }
+
+    public static Object fail(MethodHandles.Lookup lookup, String name, Class<?> type) {
+        throw new IllegalStateException("no " + name);
+    }
+
+    public static void main(String[] args) throws Throwable {
+        MethodType type = Constants.type();
+        System.out.println("r type " + type.toString() + " " + (type == Constants.type() ? "same" : "new"));
+        MethodHandle handle = Constants.handle();
+        System.out.println("r handle " + handle.toString() + " " + (int) handle.invokeExact(5)
+                + " " + (handle == Constants.handle() ? "same" : "new"));
+        MethodHandle getter = Constants.getter();
+        System.out.println("r getter " + getter.toString() + " " + (int) getter.invokeExact());
+        System.out.println("r answer " + Constants.answer());
+        System.out.println("r null " + (Constants.nothing() == null ? "yes" : "no"));
+        System.out.println("r primitive " + Constants.longType().getName());
+        System.out.println("r final " + Constants.cap());
+        String first = Constants.counted();
+        String second = Constants.counted();
+        System.out.println("r counted " + first + " " + (first == second ? "same" : "new") + " " + calls);
+        try {
+            Constants.failing();
+        } catch (BootstrapMethodError e) {
+            Throwable cause = e.getCause();
+            System.out.println("r failing " + e.getClass().getName() + " " + cause.getClass().getName() + ": " + cause.getMessage());
+        }
+    }
+}
+"#;
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_SUPER: u16 = 0x0020;
+const REF_GET_STATIC: u8 = 2;
+const REF_INVOKE_STATIC: u8 = 6;
+
+/// `Constants`, whose static methods each `ldc` one `MethodType`,
+/// `MethodHandle` or dynamic constant and return it; javac has no
+/// syntax for these.
+fn constants_class() -> Vec<u8> {
+    let mut pool = ConstantPoolBuilder::new();
+    pool.utf8("Code");
+    pool.utf8("BootstrapMethods");
+    let this_class = pool.class("Constants");
+    let object = pool.class("java/lang/Object");
+    let main = pool.class("Main");
+    let bootstraps = pool.class("java/lang/invoke/ConstantBootstraps");
+
+    let static_method = |pool: &mut ConstantPoolBuilder, class, name, descriptor| {
+        let name_and_type = pool.name_and_type(name, descriptor);
+        let method = pool.method_ref(class, name_and_type);
+        pool.method_handle(REF_INVOKE_STATIC, method)
+    };
+    let twice = static_method(&mut pool, main, "twice", "(I)I");
+    let bootstrap = "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/Object;";
+    let counting = static_method(&mut pool, main, "bootstrap", bootstrap);
+    let failing = static_method(&mut pool, main, "fail", bootstrap);
+    let invoke = static_method(
+        &mut pool,
+        bootstraps,
+        "invoke",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/invoke/MethodHandle;[Ljava/lang/Object;)Ljava/lang/Object;",
+    );
+    let null_constant = static_method(&mut pool, bootstraps, "nullConstant", bootstrap);
+    let primitive_class = static_method(
+        &mut pool,
+        bootstraps,
+        "primitiveClass",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;)Ljava/lang/Class;",
+    );
+    let get_static_final = static_method(
+        &mut pool,
+        bootstraps,
+        "getStaticFinal",
+        "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/Object;",
+    );
+    let limit = pool.name_and_type("limit", "I");
+    let limit = pool.field_ref(main, limit);
+    let getter = pool.method_handle(REF_GET_STATIC, limit);
+    let twenty_one = pool.integer(21);
+
+    let bootstrap_methods = vec![
+        BootstrapMethod {
+            method_ref: invoke,
+            arguments: vec![twice, twenty_one],
+        },
+        BootstrapMethod {
+            method_ref: null_constant,
+            arguments: Vec::new(),
+        },
+        BootstrapMethod {
+            method_ref: primitive_class,
+            arguments: Vec::new(),
+        },
+        BootstrapMethod {
+            method_ref: get_static_final,
+            arguments: vec![main],
+        },
+        BootstrapMethod {
+            method_ref: counting,
+            arguments: Vec::new(),
+        },
+        BootstrapMethod {
+            method_ref: failing,
+            arguments: Vec::new(),
+        },
+    ];
+    let constants = [
+        (
+            "type",
+            "()Ljava/lang/invoke/MethodType;",
+            pool.method_type("(ILjava/lang/String;)J"),
+        ),
+        ("handle", "()Ljava/lang/invoke/MethodHandle;", twice),
+        ("getter", "()Ljava/lang/invoke/MethodHandle;", getter),
+        ("answer", "()I", pool.dynamic(0, "answer", "I")),
+        (
+            "nothing",
+            "()Ljava/lang/Object;",
+            pool.dynamic(1, "nothing", "Ljava/lang/Object;"),
+        ),
+        (
+            "longType",
+            "()Ljava/lang/Class;",
+            pool.dynamic(2, "J", "Ljava/lang/Class;"),
+        ),
+        ("cap", "()I", pool.dynamic(3, "CAP", "I")),
+        (
+            "counted",
+            "()Ljava/lang/String;",
+            pool.dynamic(4, "counted", "Ljava/lang/String;"),
+        ),
+        (
+            "failing",
+            "()Ljava/lang/Object;",
+            pool.dynamic(5, "failing", "Ljava/lang/Object;"),
+        ),
+    ];
+    let methods = constants
+        .iter()
+        .map(|(name, descriptor, constant)| {
+            let mut asm = Assembler::new();
+            asm.ldc(*constant);
+            let kind = match descriptor.ends_with(")I") {
+                true => ValueKind::Int,
+                false => ValueKind::Reference,
+            };
+            asm.return_value(Some(kind));
+            MethodInfo {
+                access_flags: ACC_PUBLIC | ACC_STATIC,
+                name_index: pool.utf8(name),
+                descriptor_index: pool.utf8(descriptor),
+                code: Some(CodeAttribute {
+                    max_stack: 1,
+                    max_locals: 0,
+                    code: asm.finish().expect("assemble"),
+                    exception_table: Vec::new(),
+                    attributes: Vec::new(),
+                }),
+                attributes: Vec::new(),
+            }
+        })
+        .collect();
+
+    let constant_pool = pool.finish().expect("constant pool");
+    ClassFile {
+        magic: 0xCAFEBABE,
+        minor_version: 0,
+        major_version: 55,
+        constant_pool_count: constant_pool.len() as u16 + 1,
+        constant_pool,
+        access_flags: ACC_PUBLIC | ACC_SUPER,
+        this_class,
+        super_class: object,
+        interfaces: Vec::new(),
+        fields: Vec::new(),
+        methods,
+        attributes: vec![Attribute::BootstrapMethods(bootstrap_methods)],
+    }
+    .to_bytes()
+    .expect("write Constants.class")
+}
+
+#[test]
+fn method_handles_and_var_handles() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("handles");
+    compile_java(&dir, "Handles.java", HANDLES);
+
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "Handles"]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            results(&output),
+            [
+                "lookup Handles java.lang.Object/publicLookup",
+                "type (int,int)int (II)I 2 int",
+                "changed (Handles,int,long[])String (LHandles;I[J)Ljava/lang/String;",
+                "equal yes same",
+                "generic (Object,Object)Object (int,int)int (Object,Object)Object",
+                "add MethodHandle(Handles,int,int)int 6",
+                "invoke 12",
+                "boxed 16",
+                "widened 5",
+                "java.lang.invoke.WrongMethodTypeException: expected (Handles,int,int)int but found (Handles,long,int)int",
+                "java.lang.invoke.WrongMethodTypeException: cannot convert MethodHandle(Handles,int,int)int to (Handles,String,int)String",
+                "null java.lang.NullPointerException",
+                "unbox java.lang.ClassCastException",
+                "join a-b",
+                "bound MethodHandle(int,int)int 33",
+                "inserted MethodHandle(String)String head-tail",
+                "dropped MethodHandle(int,Object,String,String)String c-d",
+                "filtered MethodHandle(String,String)int 1",
+                "filterArguments x-[y]",
+                "guard [a] b",
+                "permuted second-first",
+                "constant MethodHandle()String fixed",
+                "identity MethodHandle(int)int 42",
+                "asType MethodHandle(Handles,Integer,int)Object 8",
+                "java.lang.invoke.WrongMethodTypeException: cannot convert MethodHandle(Handles,int,int)int to (Handles,String,int)int",
+                "varargs yes 6",
+                "spread MethodHandle(String[])String p-q",
+                "withArguments m-n",
+                "ctor MethodHandle(int)Handles Handles 9",
+                "virtual Child",
+                "special Handles 3",
+                "private secret 3",
+                "field MethodHandle(Handles)int MethodHandle(Handles,int)void 11",
+                "static 25 25",
+                "unreflect 13",
+                "java.lang.NoSuchMethodException: no such method: Handles.nope()void/invokeVirtual",
+                "java.lang.IllegalAccessException: no such method: Handles.add(int,int)int/invokeStatic",
+                "java.lang.IllegalAccessException: member is private: Handles.count/int/getField, from public Lookup",
+                "java.lang.NoSuchFieldException: no such field: Handles.nope/int/getField",
+                "final java.lang.IllegalAccessException",
+                "java.lang.NoSuchMethodException: no such constructor: Handles.<init>()int/newInvokeSpecial",
+                "java.lang.ClassCastException: Cannot cast java.lang.String to Handles",
+                "varhandle VarHandle[varType=int, coord=[class Handles]] int",
+                "vh get 1",
+                "vh cas yes no 5",
+                "vh getAndAdd 5 15",
+                "vh exchange 15 1 2 6",
+                "vh label yes next",
+                "java.lang.UnsupportedOperationException: null",
+                "java.lang.invoke.WrongMethodTypeException: cannot convert MethodHandle(VarHandle,Handles,int)void to (VarHandle,Handles,String)void",
+                "vh static VarHandle[varType=long, coord=[]] 25 30",
+                "vh final 7",
+                "java.lang.UnsupportedOperationException: null",
+                "vh array VarHandle[varType=int, coord=[class [I, int]] 1 4 20",
+                "java.lang.ArrayIndexOutOfBoundsException: Index 5 out of bounds for length 2",
+                "java.lang.ArrayStoreException: java.lang.Integer",
+                "array MethodHandle(String[],int)String uno 2",
+            ],
+            "{}",
+            mode
+        );
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn constant_pool_handles_and_dynamic_constants_resolve_once() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("constants");
+    fs::write(dir.join("Constants.class"), constants_class()).expect("write class");
+    compile_java(&dir, "Main.java", CONSTANTS_MAIN);
+
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "Main"]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            results(&output),
+            [
+                "type (int,String)long same",
+                "handle MethodHandle(int)int 10 same",
+                "getter MethodHandle()int 5",
+                "answer 42",
+                "null yes",
+                "primitive long",
+                "final 9",
+                "counted made counted in Constants as java.lang.String same 1",
+                "failing java.lang.BootstrapMethodError java.lang.IllegalStateException: no failing",
+            ],
+            "{}",
+            mode
+        );
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,160 @@
+use aria_core::exec::interpreter::Interpreter;
+use aria_core::loader::class_loader::ClassLoader;
+use aria_core::runtime::heap::{Heap, HeapValue};
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+use common::{compile_java, has_javac, temp_dir};
+
+#[test]
+fn embedder_natives_bind_to_native_methods() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("natives");
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          int base = 2;
+
+          static native int twice(int x);
+          native int scaled(int x);
+          static native void missing();
+
+          public static int run() {
+            int r = twice(20) + new Main().scaled(10);
+            for (int i = 0; i < 3; i++) {
+              r += Math.abs(-1);
+            }
+            try {
+              missing();
+            } catch (UnsatisfiedLinkError e) {
+              r += 1000;
+            }
+            return r;
+          }
+        }
+        "#,
+    );
+
+    let mut loader = ClassLoader::new();
+    loader.add_classpath(&dir);
+    let class = loader.load_class("Main").expect("load class");
+    let mut heap = Heap::new();
+    let mut interp = Interpreter::new(false);
+    interp.register_native("Main", "twice", "(I)I", |_, _, args| {
+        Some(HeapValue::Int(args[0].as_int() * 2))
+    });
+    interp.register_native("Main", "scaled", "(I)I", |env, this, args| {
+        let HeapValue::Object(obj) = this? else {
+            return None;
+        };
+        let base = env.heap.get(obj.id)?.get_field("base")?.as_int();
+        Some(HeapValue::Int(base * args[0].as_int()))
+    });
+    // Embedder bindings take precedence over builtins.
+    interp.register_native("java/lang/Math", "abs", "(I)I", |_, _, _| {
+        Some(HeapValue::Int(100))
+    });
+
+    let result = interp.execute_method(&mut loader, &class, "run", "()I", &mut heap, &[]);
+    let _ = fs::remove_dir_all(&dir);
+
+    match result {
+        Some(HeapValue::Int(v)) => assert_eq!(v, 40 + 20 + 300 + 1000),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn unbound_native_method_throws_unsatisfied_link_error() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("unbound");
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          static native void missing(int[] a, String s, long l);
+
+          public static void main(String[] args) {
+            missing(null, null, 0L);
+          }
+        }
+        "#,
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg("Main")
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr.contains(
+            "Exception in thread \"main\" java.lang.UnsatisfiedLinkError: 'void Main.missing(int[], java.lang.String, long)'"
+        ),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn unbound_builtin_method_throws_unsatisfied_link_error() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("unbound-builtin");
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          public static void main(String[] args) {
+            try {
+              String.format("%d", 1);
+            } catch (UnsatisfiedLinkError e) {
+              System.out.println("caught");
+            }
+            String.format("%d", 2);
+            System.out.println("unreachable");
+          }
+        }
+        "#,
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg("Main")
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("caught\n"), "stdout: {}", stdout);
+    assert!(!stdout.contains("unreachable"), "stdout: {}", stdout);
+    assert!(!stdout.contains("not found"), "stdout: {}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr.contains(
+            "Exception in thread \"main\" java.lang.UnsatisfiedLinkError: 'java.lang.String java.lang.String.format(java.lang.String, java.lang.Object[])'"
+        ),
+        "stderr: {}",
+        stderr
+    );
+}
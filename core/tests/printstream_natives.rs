@@ -0,0 +1,106 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+use common::{compile_java, has_javac, temp_dir};
+
+#[test]
+fn prints_all_overloads_to_stdout_and_stderr() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("printstream");
+
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          public String toString() {
+            return "custom-main";
+          }
+
+          public static void main(String[] args) {
+            System.out.print('x');
+            System.out.print(true);
+            System.out.println(2.5);
+            System.out.println(1.0e10);
+            System.out.println(0.1f);
+            System.out.println(Double.MIN_VALUE);
+            System.out.println(2 * Double.MIN_VALUE);
+            System.out.println(Float.MIN_VALUE);
+            System.out.println(1.0e23);
+            System.out.println(new char[] {'h', 'i'});
+            System.out.println(new Main());
+            System.out.println((Object) null);
+            System.out.printf("%5d|%-5s|%08.3f|%x|%,d%n", 42, "ab", 3.14159, -1, 1234567);
+            System.out.printf("%e %g %b %c %10.2e%n", 12345.678, 0.0001, null, 'z', 0.000123);
+            System.err.println("to stderr");
+            try {
+              System.out.print((char[]) null);
+            } catch (NullPointerException e) {
+              System.out.println("npe chars");
+            }
+            try {
+              System.out.write(null, 0, 1);
+            } catch (NullPointerException e) {
+              System.out.println("npe bytes");
+            }
+            try {
+              System.out.write(new byte[] {65, 66, 67}, 1, Integer.MAX_VALUE);
+            } catch (IndexOutOfBoundsException e) {
+              System.out.println("out of bounds bytes");
+            }
+            System.out.write(new byte[] {65, 66, 10}, 0, 3);
+            System.out.print("tail");
+          }
+        }
+        "#,
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg("Main")
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stdout.lines().collect();
+    for expected in [
+        "xtrue2.5",
+        "1.0E10",
+        "0.1",
+        "4.9E-324",
+        "9.9E-324",
+        "1.4E-45",
+        "1.0E23",
+        "hi",
+        "custom-main",
+        "null",
+        "   42|ab   |0003.142|ffffffff|1,234,567",
+        "1.234568e+04 0.000100000 false z   1.23e-04",
+        "npe chars",
+        "npe bytes",
+        "out of bounds bytes",
+        "AB",
+    ] {
+        assert!(
+            lines.contains(&expected),
+            "missing line {:?} in stdout:\n{}",
+            expected,
+            stdout
+        );
+    }
+    assert!(stdout.ends_with("tail"), "unflushed tail: {}", stdout);
+    assert!(
+        stderr.lines().any(|l| l == "to stderr"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(!lines.contains(&"to stderr"));
+}
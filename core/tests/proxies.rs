@@ -0,0 +1,378 @@
+use std::fs;
+
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+const PROXIES: &str = r#"
+import java.io.IOException;
+import java.lang.reflect.InvocationHandler;
+import java.lang.reflect.Method;
+import java.lang.reflect.Modifier;
+import java.lang.reflect.Proxy;
+import java.lang.reflect.UndeclaredThrowableException;
+
+public class Proxies {
+    public interface Greeter {
+        String greet(String name);
+        int add(int a, int b);
+        long twice(long value);
+        double half(double value);
+        boolean flip(boolean value);
+        char next(char value);
+        void ping();
+        default String hello() {
+            return "default body";
+        }
+    }
+
+    public interface Narrow extends Greeter {
+        short triple(short value);
+        byte[] bytes(int size);
+    }
+
+    public interface Remote {
+        Object fetch(int id) throws IOException;
+    }
+
+    static class Recorder implements InvocationHandler {
+        int calls;
+
+        public Object invoke(Object proxy, Method method, Object[] args) throws Throwable {
+            calls++;
+            String name = method.getName();
+            int count = args == null ? 0 : args.length;
+            System.out.println("r call " + name + " " + count + " " + method.getDeclaringClass().getName());
+            if (name == "greet") {
+                return "hello " + (String) args[0];
+            }
+            if (name == "add") {
+                return (Integer) args[0] + (Integer) args[1];
+            }
+            if (name == "twice") {
+                return (Long) args[0] * 2;
+            }
+            if (name == "half") {
+                return (Double) args[0] / 2;
+            }
+            if (name == "flip") {
+                return !(Boolean) args[0];
+            }
+            if (name == "next") {
+                return (char) ((Character) args[0] + 1);
+            }
+            if (name == "triple") {
+                return (short) ((Short) args[0] * 3);
+            }
+            if (name == "bytes") {
+                return new byte[(Integer) args[0]];
+            }
+            if (name == "hello") {
+                return "handled default";
+            }
+            if (name == "hashCode") {
+                return 42;
+            }
+            if (name == "equals") {
+                return proxy == args[0];
+            }
+            if (name == "toString") {
+                return "Recorder proxy";
+            }
+            return null;
+        }
+    }
+
+    static class Failing implements InvocationHandler {
+        public Object invoke(Object proxy, Method method, Object[] args) throws Throwable {
+            int id = (Integer) args[0];
+            if (id == 1) {
+                throw new IOException("io " + id);
+            }
+            if (id == 2) {
+                throw new Exception("checked " + id);
+            }
+            if (id == 3) {
+                throw new IllegalStateException("unchecked " + id);
+            }
+            if (id == 4) {
+                throw new AssertionError("error " + id);
+            }
+            return "fetched " + id;
+        }
+    }
+
+    static class Wrong implements InvocationHandler {
+        public Object invoke(Object proxy, Method method, Object[] args) {
+            if (method.getName() == "greet") {
+                return Integer.valueOf(1);
+            }
+            return null;
+        }
+    }
+
+    static String yes(boolean value) {
+        return value ? "yes" : "no";
+    }
+
+    static void fetch(Remote remote, int id) {
+        try {
+            System.out.println("r fetch " + (String) remote.fetch(id));
+        } catch (IOException e) {
+            System.out.println("r fetch io " + e.getMessage());
+        } catch (UndeclaredThrowableException e) {
+            System.out.println("r fetch undeclared " + e.getMessage() + " " + e.getUndeclaredThrowable().toString() + " " + yes(e.getCause() == e.getUndeclaredThrowable()));
+        } catch (RuntimeException e) {
+            System.out.println("r fetch runtime " + e.toString());
+        } catch (Error e) {
+            System.out.println("r fetch error " + e.toString());
+        }
+    }
+
+    public static void main(String[] args) throws Exception {
+        ClassLoader loader = Proxies.class.getClassLoader();
+        Recorder recorder = new Recorder();
+        Narrow narrow = (Narrow) Proxy.newProxyInstance(loader, new Class<?>[] {Narrow.class}, recorder);
+        System.out.println("r " + narrow.greet("proxy"));
+        System.out.println("r " + narrow.add(2, 3));
+        System.out.println("r " + narrow.twice(21L));
+        System.out.println("r " + narrow.half(5.0));
+        System.out.println("r " + yes(narrow.flip(true)));
+        System.out.println("r " + (int) narrow.next('a'));
+        System.out.println("r " + narrow.triple((short) 7));
+        System.out.println("r " + narrow.bytes(4).length);
+        narrow.ping();
+        System.out.println("r " + narrow.hello());
+        System.out.println("r " + narrow.hashCode());
+        System.out.println("r " + yes(narrow.equals(narrow)) + " " + yes(narrow.equals("other")));
+        System.out.println("r " + narrow.toString());
+        System.out.println("r calls " + recorder.calls);
+
+        Class<?> type = narrow.getClass();
+        System.out.println("r proxy class " + yes(Proxy.isProxyClass(type)) + " " + yes(Proxy.isProxyClass(Recorder.class)));
+        System.out.println("r handler " + yes(Proxy.getInvocationHandler(narrow) == recorder));
+        System.out.println("r super " + type.getSuperclass().getName());
+        System.out.println("r final " + yes(Modifier.isFinal(type.getModifiers())) + " " + yes(Modifier.isPublic(type.getModifiers())));
+        System.out.println("r instance " + yes(narrow instanceof Greeter) + " " + yes(narrow instanceof Narrow) + " " + yes(narrow instanceof Proxy));
+        Object again = Proxy.newProxyInstance(loader, new Class<?>[] {Narrow.class}, new Recorder());
+        System.out.println("r same class " + yes(again.getClass() == type));
+        Object both = Proxy.newProxyInstance(loader, new Class<?>[] {Remote.class, Runnable.class}, recorder);
+        System.out.println("r other class " + yes(both.getClass() != type) + " " + yes(both instanceof Runnable) + " " + yes(both instanceof Remote));
+        ((Runnable) both).run();
+        System.out.println("r interfaces " + both.getClass().getInterfaces().length);
+
+        Remote remote = (Remote) Proxy.newProxyInstance(loader, new Class<?>[] {Remote.class}, new Failing());
+        for (int id = 0; id <= 4; id++) {
+            fetch(remote, id);
+        }
+
+        Greeter wrong = (Greeter) Proxy.newProxyInstance(loader, new Class<?>[] {Greeter.class}, new Wrong());
+        try {
+            wrong.greet("x");
+        } catch (ClassCastException e) {
+            System.out.println("r wrong type ClassCastException");
+        }
+        try {
+            wrong.add(1, 2);
+        } catch (NullPointerException e) {
+            System.out.println("r null primitive NullPointerException");
+        }
+        wrong.ping();
+        System.out.println("r null void ok");
+
+        try {
+            Proxy.newProxyInstance(loader, new Class<?>[] {Recorder.class}, recorder);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            Proxy.newProxyInstance(loader, new Class<?>[] {Remote.class, Remote.class}, recorder);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            Proxy.newProxyInstance(loader, new Class<?>[] {Remote.class}, null);
+        } catch (NullPointerException e) {
+            System.out.println("r null handler NullPointerException");
+        }
+        try {
+            Proxy.getInvocationHandler("text");
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+    }
+}
+"#;
+
+const HIDDEN: &str = r#"
+public class Hidden implements Runnable {
+    static int created;
+
+    public Hidden() {
+        created++;
+    }
+
+    public void run() {
+        System.out.println("r hidden run " + created);
+    }
+}
+"#;
+
+/// Defines `Hidden` from bytes it holds as an array literal; `BYTES` is
+/// replaced with them.
+const DEFINING: &str = r#"
+public class Defining {
+    static class BytesLoader extends ClassLoader {
+        Class<?> define(String name, byte[] bytes, int offset, int length) {
+            return defineClass(name, bytes, offset, length);
+        }
+    }
+
+    static byte[] bytes() {
+        return new byte[] {BYTES};
+    }
+
+    public static void main(String[] args) throws Exception {
+        BytesLoader loader = new BytesLoader();
+        byte[] bytes = bytes();
+        Class<?> hidden = loader.define("Hidden", bytes, 0, bytes.length);
+        System.out.println("r defined " + hidden.getName());
+        Runnable runnable = (Runnable) hidden.getDeclaredConstructor().newInstance();
+        runnable.run();
+        System.out.println("r loaded " + (Class.forName("Hidden", false, loader) == hidden ? "same" : "other"));
+        try {
+            loader.define("Hidden", bytes, 0, bytes.length);
+        } catch (LinkageError e) {
+            System.out.println("r duplicate " + e.getClass().getName());
+        }
+        try {
+            loader.define("Other", bytes, 0, bytes.length);
+        } catch (NoClassDefFoundError e) {
+            System.out.println("r wrong name " + e.getMessage());
+        }
+        try {
+            loader.define(null, bytes, 0, 12);
+        } catch (ClassFormatError e) {
+            System.out.println("r truncated " + e.getClass().getName());
+        }
+        try {
+            loader.define(null, bytes, 4, bytes.length);
+        } catch (IndexOutOfBoundsException e) {
+            System.out.println("r bounds " + e.getClass().getName());
+        }
+    }
+}
+"#;
+
+#[test]
+fn proxies_route_calls_to_the_invocation_handler() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("proxies");
+    compile_java(&dir, "Proxies.java", PROXIES);
+
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "Proxies"]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            results(&output),
+            [
+                "call greet 1 Proxies$Greeter",
+                "hello proxy",
+                "call add 2 Proxies$Greeter",
+                "5",
+                "call twice 1 Proxies$Greeter",
+                "42",
+                "call half 1 Proxies$Greeter",
+                "2.5",
+                "call flip 1 Proxies$Greeter",
+                "no",
+                "call next 1 Proxies$Greeter",
+                "98",
+                "call triple 1 Proxies$Narrow",
+                "21",
+                "call bytes 1 Proxies$Narrow",
+                "4",
+                "call ping 0 Proxies$Greeter",
+                "call hello 0 Proxies$Greeter",
+                "handled default",
+                "call hashCode 0 java.lang.Object",
+                "42",
+                "call equals 1 java.lang.Object",
+                "call equals 1 java.lang.Object",
+                "yes no",
+                "call toString 0 java.lang.Object",
+                "Recorder proxy",
+                "calls 14",
+                "proxy class yes no",
+                "handler yes",
+                "super java.lang.reflect.Proxy",
+                "final yes yes",
+                "instance yes yes yes",
+                "same class yes",
+                "other class yes yes yes",
+                "call run 0 java.lang.Runnable",
+                "interfaces 2",
+                "fetch fetched 0",
+                "fetch io io 1",
+                "fetch undeclared null java.lang.Exception: checked 2 yes",
+                "fetch runtime java.lang.IllegalStateException: unchecked 3",
+                "fetch error java.lang.AssertionError: error 4",
+                "wrong type ClassCastException",
+                "null primitive NullPointerException",
+                "null void ok",
+                "Proxies$Recorder is not an interface",
+                "repeated interface: Proxies$Remote",
+                "null handler NullPointerException",
+                "not a proxy instance",
+            ],
+            "{}",
+            mode
+        );
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn class_loader_defines_classes_from_bytes() {
+    if !has_javac() {
+        return;
+    }
+    let hidden_dir = temp_dir("hidden");
+    compile_java(&hidden_dir, "Hidden.java", HIDDEN);
+    let bytes = fs::read(hidden_dir.join("Hidden.class")).expect("read class");
+    let literal = bytes
+        .iter()
+        .map(|byte| (*byte as i8).to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let dir = temp_dir("defining");
+    compile_java(&dir, "Defining.java", &DEFINING.replace("BYTES", &literal));
+
+    let output = run_aria(&dir, &["Defining"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        results(&output),
+        [
+            "defined Hidden",
+            "hidden run 1",
+            "loaded same",
+            "duplicate java.lang.LinkageError",
+            "wrong name Other (wrong name: Hidden)",
+            "truncated java.lang.ClassFormatError",
+            "bounds java.lang.ArrayIndexOutOfBoundsException",
+        ]
+    );
+    let _ = fs::remove_dir_all(&hidden_dir);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,647 @@
+use std::fs;
+
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+const REFLECT: &str = r#"
+import java.lang.reflect.Constructor;
+import java.lang.reflect.Field;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.lang.reflect.Modifier;
+
+public class Reflect {
+    interface Greeter {
+        String greet(String name);
+    }
+
+    interface Named {
+    }
+
+    static class Base {
+        protected int id = 7;
+    }
+
+    public static class Service extends Base implements Greeter, Named {
+        public static int created;
+        private String prefix = "Hello";
+        public int calls;
+        public final int limit = 3;
+
+        public Service() {
+            created++;
+        }
+
+        public Service(String prefix) {
+            this();
+            this.prefix = prefix;
+        }
+
+        public String greet(String name) {
+            calls++;
+            return prefix + ", " + name;
+        }
+
+        public static int add(int a, int b) {
+            return a + b;
+        }
+
+        public long scale(long value, double factor) {
+            return (long) (value * factor);
+        }
+
+        public void fail(String why) {
+            throw new IllegalStateException(why);
+        }
+    }
+
+    static class Repo {
+        String find() {
+            return "row";
+        }
+    }
+
+    static class Clock {
+        int now() {
+            return 42;
+        }
+    }
+
+    static class Controller {
+        final Repo repo;
+        final Clock clock;
+
+        Controller() {
+            this(null, null);
+        }
+
+        Controller(Repo repo, Clock clock) {
+            this.repo = repo;
+            this.clock = clock;
+        }
+
+        String handle() {
+            return repo.find() + "@" + clock.now();
+        }
+    }
+
+    static Object create(Class<?> type) throws Exception {
+        Constructor<?>[] constructors = type.getDeclaredConstructors();
+        Constructor<?> best = constructors[0];
+        for (Constructor<?> constructor : constructors) {
+            if (constructor.getParameterCount() > best.getParameterCount()) {
+                best = constructor;
+            }
+        }
+        Class<?>[] types = best.getParameterTypes();
+        Object[] args = new Object[types.length];
+        for (int i = 0; i < types.length; i++) {
+            args[i] = create(types[i]);
+        }
+        return best.newInstance(args);
+    }
+
+    static String names(Class<?>[] classes) {
+        String out = "";
+        for (Class<?> c : classes) {
+            out += " " + c.getName();
+        }
+        return out;
+    }
+
+    public static void main(String[] args) throws Exception {
+        Controller controller = (Controller) create(Controller.class);
+        System.out.println("r di " + controller.handle());
+
+        Class<?> lazy = Class.forName("Lazy", false, Reflect.class.getClassLoader());
+        System.out.println("r loaded " + lazy.getName());
+        Class<?> again = Class.forName("Lazy");
+        System.out.println("r same " + (lazy == again ? "yes" : "no"));
+        try {
+            Class.forName("does.not.Exist");
+        } catch (ClassNotFoundException e) {
+            System.out.println("r missing " + e.getMessage());
+        }
+
+        Class<?> service = Class.forName("Reflect$Service");
+        System.out.println("r name " + service.getName() + " " + service.getSimpleName());
+        System.out.println("r super " + service.getSuperclass().getName());
+        System.out.println("r interfaces" + names(service.getInterfaces()));
+        System.out.println("r " + Greeter.class.toString() + " " + int.class.toString() + " " + String[].class.getName());
+        System.out.println("r type " + (Integer.TYPE == int.class ? "yes" : "no"));
+
+        Service instance = new Service("Hi");
+        System.out.println("r class " + (instance.getClass() == Service.class ? "yes" : "no"));
+        System.out.println("r instance " + (Greeter.class.isInstance(instance) ? "yes" : "no")
+                + " " + (Repo.class.isInstance(instance) ? "yes" : "no")
+                + " " + (Base.class.isAssignableFrom(service) ? "yes" : "no"));
+
+        Field[] fields = service.getDeclaredFields();
+        String fieldNames = "";
+        for (Field field : fields) {
+            fieldNames += " " + field.getName();
+        }
+        System.out.println("r fields" + fieldNames);
+        Method[] methods = service.getDeclaredMethods();
+        String methodNames = "";
+        for (Method method : methods) {
+            methodNames += " " + method.getName() + "/" + method.getParameterCount();
+        }
+        System.out.println("r methods" + methodNames);
+        System.out.println("r constructors " + service.getDeclaredConstructors().length);
+
+        Method greet = service.getDeclaredMethod("greet", String.class);
+        System.out.println("r " + greet.toString());
+        System.out.println("r invoke " + (String) greet.invoke(instance, "Ada") + " " + instance.calls);
+        Method add = service.getDeclaredMethod("add", int.class, int.class);
+        System.out.println("r " + Modifier.toString(add.getModifiers()) + " " + add.getReturnType().getName());
+        System.out.println("r static " + ((Integer) add.invoke(null, 2, 3)).intValue());
+        Method scale = service.getDeclaredMethod("scale", long.class, double.class);
+        System.out.println("r widened " + ((Long) scale.invoke(instance, 10, 2.5f)).longValue());
+        try {
+            add.invoke(null, "two", 3);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r bad " + e.getMessage());
+        }
+        try {
+            greet.invoke(null, "nobody");
+        } catch (NullPointerException e) {
+            System.out.println("r npe");
+        }
+
+        try {
+            service.getDeclaredMethod("fail", String.class).invoke(instance, "broken");
+        } catch (InvocationTargetException e) {
+            System.out.println("r target " + e.getCause().getClass().getName() + " " + e.getCause().getMessage()
+                    + " " + (e.getTargetException() == e.getCause() ? "same" : "different"));
+        }
+
+        Field calls = service.getDeclaredField("calls");
+        calls.set(instance, 41);
+        System.out.println("r calls " + ((Integer) calls.get(instance)).intValue() + " " + instance.calls);
+        Field created = service.getDeclaredField("created");
+        System.out.println("r created " + ((Integer) created.get(null)).intValue() + " " + created.toString());
+        Field limit = service.getDeclaredField("limit");
+        try {
+            limit.set(instance, 9);
+        } catch (IllegalAccessException e) {
+            System.out.println("r final " + e.getMessage());
+        }
+        limit.setAccessible(true);
+        limit.set(instance, 9);
+        System.out.println("r limit " + ((Integer) limit.get(instance)).intValue());
+
+        Constructor<?> named = service.getDeclaredConstructor(String.class);
+        Service made = (Service) named.newInstance("Hey");
+        System.out.println("r made " + made.greet("Bob") + " " + Service.created);
+        try {
+            service.getDeclaredMethod("greet", int.class);
+        } catch (NoSuchMethodException e) {
+            System.out.println("r nsme " + e.getMessage());
+        }
+
+        Method secret = Vault.class.getDeclaredMethod("secret");
+        try {
+            secret.invoke(new Vault());
+        } catch (IllegalAccessException e) {
+            System.out.println("r denied " + e.getMessage());
+        }
+        secret.setAccessible(true);
+        System.out.println("r opened " + (String) secret.invoke(new Vault()));
+        Field code = Vault.class.getDeclaredField("code");
+        code.setAccessible(true);
+        System.out.println("r code " + ((Integer) code.get(new Vault())).intValue());
+    }
+}
+
+class Lazy {
+    static {
+        System.out.println("r Lazy init");
+    }
+}
+
+class Vault {
+    private int code = 1234;
+
+    private String secret() {
+        return "gold";
+    }
+}
+"#;
+
+#[test]
+fn reflection_covers_classes_members_and_access_checks() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("reflect");
+    compile_java(&dir, "Reflect.java", REFLECT);
+
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "Reflect"]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            results(&output),
+            [
+                "di row@42",
+                "loaded Lazy",
+                "Lazy init",
+                "same yes",
+                "missing does.not.Exist",
+                "name Reflect$Service Service",
+                "super Reflect$Base",
+                "interfaces Reflect$Greeter Reflect$Named",
+                "interface Reflect$Greeter int [Ljava.lang.String;",
+                "type yes",
+                "class yes",
+                "instance yes no yes",
+                "fields created prefix calls limit",
+                "methods greet/1 add/2 scale/2 fail/1",
+                "constructors 2",
+                "public java.lang.String Reflect$Service.greet(java.lang.String)",
+                "invoke Hi, Ada 1",
+                "public static int",
+                "static 5",
+                "widened 25",
+                "bad argument type mismatch",
+                "npe",
+                "target java.lang.IllegalStateException broken same",
+                "calls 41 41",
+                "created 1 public static int Reflect$Service.created",
+                "final Can not set final int field Reflect$Service.limit to java.lang.Integer",
+                "limit 9",
+                "made Hey, Bob 2",
+                "nsme Reflect$Service.greet(int)",
+                "denied class Reflect cannot access a member of class Vault with modifiers \"private\"",
+                "opened gold",
+                "code 1234",
+            ],
+            "{}",
+            mode
+        );
+    }
+}
+
+const MEMBERS: &str = r#"
+import java.lang.reflect.Constructor;
+import java.lang.reflect.Field;
+import java.lang.reflect.Method;
+
+interface Named {
+    String PREFIX = "id-";
+
+    String name();
+
+    default String label() {
+        return PREFIX + name();
+    }
+
+    static Named none() {
+        return null;
+    }
+}
+
+class Animal {
+    public int legs = 4;
+    protected int age;
+
+    public Animal() {}
+
+    Animal(int legs) {
+        this.legs = legs;
+    }
+
+    public String sound() {
+        return "...";
+    }
+
+    public String describe() {
+        return "animal";
+    }
+
+    void hidden() {}
+}
+
+class Dog extends Animal implements Named {
+    public String breed = "lab";
+    private int secret;
+
+    public Dog() {}
+
+    protected Dog(String breed) {
+        this.breed = breed;
+    }
+
+    public String sound() {
+        return "woof";
+    }
+
+    public String name() {
+        return "rex";
+    }
+
+    private void sleep() {}
+}
+
+public class Members {
+    static String owner(Method[] methods, String name) {
+        for (Method method : methods) {
+            if (method.getName().equals(name)) {
+                return name + ":" + method.getDeclaringClass().getName();
+            }
+        }
+        return name + ":-";
+    }
+
+    public static void main(String[] args) throws Exception {
+        Class<?> dog = Dog.class;
+        String fields = "";
+        for (Field field : dog.getFields()) {
+            fields += " " + field.getName() + ":" + field.getDeclaringClass().getName();
+        }
+        System.out.println("r fields" + fields);
+
+        Method[] methods = dog.getMethods();
+        int own = 0;
+        for (Method method : methods) {
+            if (method.getDeclaringClass() != Object.class) {
+                own++;
+            }
+        }
+        String names = "";
+        String[] wanted = {"sound", "name", "describe", "label", "hidden", "sleep", "none"};
+        for (String name : wanted) {
+            names += " " + owner(methods, name);
+        }
+        System.out.println("r methods " + own + names);
+        System.out.println("r interface " + owner(Named.class.getMethods(), "none"));
+
+        Dog rex = new Dog();
+        System.out.println("r sound " + (String) dog.getMethod("sound").invoke(rex));
+        System.out.println("r describe " + (String) dog.getMethod("describe").invoke(rex)
+                + " " + dog.getMethod("describe").getDeclaringClass().getName());
+        System.out.println("r legs " + ((Integer) dog.getField("legs").get(rex)).intValue()
+                + " " + dog.getField("PREFIX").getDeclaringClass().getName());
+        String[] missingMethods = {"hidden", "sleep", "none"};
+        for (String name : missingMethods) {
+            try {
+                dog.getMethod(name);
+            } catch (NoSuchMethodException e) {
+                System.out.println("r nsme " + e.getMessage());
+            }
+        }
+        String[] missingFields = {"age", "secret"};
+        for (String name : missingFields) {
+            try {
+                dog.getField(name);
+            } catch (NoSuchFieldException e) {
+                System.out.println("r nsfe " + e.getMessage());
+            }
+        }
+
+        Constructor<?>[] constructors = dog.getConstructors();
+        System.out.println("r constructors " + constructors.length + " "
+                + constructors[0].getParameterCount() + " " + dog.getDeclaredConstructors().length);
+        Dog made = (Dog) dog.getConstructor().newInstance();
+        System.out.println("r made " + made.breed);
+        try {
+            dog.getConstructor(String.class);
+        } catch (NoSuchMethodException e) {
+            System.out.println("r nsme " + e.getMessage());
+        }
+    }
+}
+"#;
+
+#[test]
+fn public_lookups_include_inherited_members() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("members");
+    compile_java(&dir, "Members.java", MEMBERS);
+
+    let output = run_aria(&dir, &["Members"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        results(&output),
+        [
+            "fields breed:Dog PREFIX:Named legs:Animal",
+            "methods 4 sound:Dog name:Dog describe:Animal label:Named hidden:- sleep:- none:-",
+            "interface none:Named",
+            "sound woof",
+            "describe animal Animal",
+            "legs 4 Named",
+            "nsme Dog.hidden()",
+            "nsme Dog.sleep()",
+            "nsme Dog.none()",
+            "nsfe age",
+            "nsfe secret",
+            "constructors 1 0 2",
+            "made lab",
+            "nsme Dog.<init>(java.lang.String)",
+        ]
+    );
+}
+
+/// The primitive `Field` accessors, with widening and the conversions
+/// HotSpot rejects.
+const TYPED: &str = r#"
+import java.lang.reflect.Field;
+
+public class Typed {
+    public int count = 7;
+    public long total = 1L << 40;
+    public byte small = -3;
+    public char letter = 'x';
+    public boolean flag = true;
+    public double ratio = 0.5;
+    public float part = 1.5f;
+    public short mid = 300;
+    public String name = "typed";
+    public static int shared = 11;
+    public final int fixed = 1;
+
+    static void show(String label, long value) {
+        System.out.print("r " + label + " ");
+        System.out.println(value);
+    }
+
+    static void show(String label, double value) {
+        System.out.print("r " + label + " ");
+        System.out.println(value);
+    }
+
+    static void show(String label, float value) {
+        System.out.print("r " + label + " ");
+        System.out.println(value);
+    }
+
+    static void show(String label, char value) {
+        System.out.print("r " + label + " ");
+        System.out.println(value);
+    }
+
+    static void show(String label, boolean value) {
+        System.out.print("r " + label + " ");
+        System.out.println(value);
+    }
+
+    public static void main(String[] args) throws Exception {
+        Typed t = new Typed();
+        Class<?> c = Typed.class;
+        Field count = c.getField("count");
+        Field total = c.getField("total");
+        Field small = c.getField("small");
+        Field letter = c.getField("letter");
+        Field flag = c.getField("flag");
+        Field ratio = c.getField("ratio");
+        Field part = c.getField("part");
+        Field mid = c.getField("mid");
+        Field name = c.getField("name");
+        Field shared = c.getField("shared");
+        Field fixed = c.getField("fixed");
+
+        show("getInt", count.getInt(t));
+        show("getLong", count.getLong(t));
+        show("getDouble", count.getDouble(t));
+        show("getLong", total.getLong(t));
+        show("getFloat", count.getFloat(t));
+        show("getByte", small.getByte(t));
+        show("getShort", small.getShort(t));
+        show("getChar", letter.getChar(t));
+        show("getInt", letter.getInt(t));
+        show("getBoolean", flag.getBoolean(t));
+        show("getDouble", ratio.getDouble(t));
+        show("getFloat", part.getFloat(t));
+        show("getShort", mid.getShort(t));
+        show("static", shared.getInt(null));
+
+        count.setInt(t, 42);
+        total.setInt(t, 5);
+        ratio.setLong(t, 3L);
+        letter.setChar(t, 'q');
+        flag.setBoolean(t, false);
+        small.setByte(t, (byte) 9);
+        part.setShort(t, (short) 2);
+        shared.setInt(null, 12);
+        show("setInt", t.count);
+        show("setInt", t.total);
+        show("setLong", t.ratio);
+        show("setChar", t.letter);
+        show("setBoolean", t.flag);
+        show("setByte", t.small);
+        show("setShort", t.part);
+        show("static", Typed.shared);
+
+        try {
+            total.getInt(t);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            flag.getInt(t);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            name.getInt(t);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            count.getChar(t);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            count.setLong(t, 5L);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            flag.setInt(t, 1);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            part.setDouble(t, 2.5);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            name.setInt(t, 3);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+        }
+        try {
+            fixed.setInt(t, 2);
+        } catch (IllegalAccessException e) {
+            System.out.println("r " + e.getMessage());
+        }
+    }
+}
+"#;
+
+#[test]
+fn typed_field_accessors_widen_and_reject_conversions() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("typed");
+    compile_java(&dir, "Typed.java", TYPED);
+
+    let output = run_aria(&dir, &["Typed"]);
+    let _ = fs::remove_dir_all(&dir);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        results(&output),
+        [
+            "getInt 7",
+            "getLong 7",
+            "getDouble 7.0",
+            "getLong 1099511627776",
+            "getFloat 7.0",
+            "getByte -3",
+            "getShort -3",
+            "getChar x",
+            "getInt 120",
+            "getBoolean true",
+            "getDouble 0.5",
+            "getFloat 1.5",
+            "getShort 300",
+            "static 11",
+            "setInt 42",
+            "setInt 5",
+            "setLong 3.0",
+            "setChar q",
+            "setBoolean false",
+            "setByte 9",
+            "setShort 2.0",
+            "static 12",
+            "Attempt to get long field \"Typed.total\" with illegal data type conversion to int",
+            "Attempt to get boolean field \"Typed.flag\" with illegal data type conversion to int",
+            "Attempt to get java.lang.String field \"Typed.name\" with illegal data type conversion to int",
+            "Attempt to get int field \"Typed.count\" with illegal data type conversion to char",
+            "Can not set int field Typed.count to (long)5",
+            "Can not set boolean field Typed.flag to (int)1",
+            "Can not set float field Typed.part to (double)2.5",
+            "Can not set java.lang.String field Typed.name to (int)3",
+            "Can not set final int field Typed.fixed to (int)2",
+        ]
+    );
+}
@@ -0,0 +1,289 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+const HOOKS: &str = r#"
+public class Hooks {
+    static class Pool extends Thread {
+        private final String label;
+
+        Pool(String label) {
+            this.label = label;
+        }
+
+        public void run() {
+            System.out.println("r closed " + label);
+        }
+    }
+
+    static class Task implements Runnable {
+        public void run() {
+            System.out.println("r task");
+        }
+    }
+
+    static class Failing extends Thread {
+        Failing() {
+            super("failing-hook");
+        }
+
+        public void run() {
+            throw new IllegalStateException("boom");
+        }
+    }
+
+    public static void main(String[] args) {
+        Runtime runtime = Runtime.getRuntime();
+        runtime.addShutdownHook(new Pool("db"));
+        Thread removed = new Pool("removed");
+        runtime.addShutdownHook(removed);
+        runtime.addShutdownHook(new Thread(new Task()));
+        runtime.addShutdownHook(new Failing());
+        boolean first = runtime.removeShutdownHook(removed);
+        boolean second = runtime.removeShutdownHook(removed);
+        System.out.println("r removed " + (first ? "yes" : "no") + " " + (second ? "yes" : "no"));
+        try {
+            runtime.addShutdownHook(removed);
+            runtime.addShutdownHook(removed);
+        } catch (IllegalArgumentException e) {
+            System.out.println("r " + e.getMessage());
+            runtime.removeShutdownHook(removed);
+        }
+        System.out.print("r unflushed\n");
+        String mode = args.length > 0 ? args[0] : "normal";
+        if (mode.equals("exit")) {
+            System.exit(3);
+        } else if (mode.equals("runtime-exit")) {
+            runtime.exit(4);
+        } else if (mode.equals("halt")) {
+            runtime.halt(5);
+        } else if (mode.equals("throw")) {
+            throw new RuntimeException("uncaught");
+        }
+    }
+}
+"#;
+
+#[test]
+fn shutdown_hooks_run_on_every_orderly_exit() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("hooks");
+    compile_java(&dir, "Hooks.java", HOOKS);
+
+    let hooks_ran = [
+        "removed yes no",
+        "Hook already registered",
+        "unflushed",
+        "closed db",
+        "task",
+    ];
+    for (mode, status) in [
+        ("normal", 0),
+        ("exit", 3),
+        ("runtime-exit", 4),
+        ("throw", 1),
+    ] {
+        let output = run_aria(&dir, &["Hooks", mode]);
+        assert_eq!(output.status.code(), Some(status), "{}", mode);
+        assert_eq!(results(&output), hooks_ran, "{}", mode);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(
+                "Exception in thread \"failing-hook\" java.lang.IllegalStateException: boom"
+            ),
+            "{}: {}",
+            mode,
+            stderr
+        );
+    }
+
+    let halted = run_aria(&dir, &["Hooks", "halt"]);
+    assert_eq!(halted.status.code(), Some(5));
+    assert_eq!(
+        results(&halted),
+        ["removed yes no", "Hook already registered", "unflushed"]
+    );
+}
+
+const MEMORY: &str = r#"
+public class Memory {
+    static Node kept;
+
+    static class Node {
+        final int value;
+        final Node next;
+        final int[] payload;
+
+        Node(int value, Node next) {
+            this.value = value;
+            this.next = next;
+            this.payload = new int[] { value, value * 2 };
+        }
+    }
+
+    static Node build(int count) {
+        Node head = null;
+        for (int i = 0; i < count; i++) {
+            head = new Node(i, head);
+        }
+        return head;
+    }
+
+    static int sum(Node node) {
+        int total = 0;
+        for (; node != null; node = node.next) {
+            total += node.value + node.payload[1];
+        }
+        return total;
+    }
+
+    static int churn(Node local) {
+        for (int round = 0; round < 20; round++) {
+            build(500);
+            Runtime.getRuntime().gc();
+        }
+        System.gc();
+        return sum(local);
+    }
+
+    public static void main(String[] args) {
+        Runtime runtime = Runtime.getRuntime();
+        int same = System.identityHashCode(runtime) - System.identityHashCode(Runtime.getRuntime());
+        System.out.println("r same " + same);
+        System.out.println("r processors " + (runtime.availableProcessors() > 0 ? "some" : "none"));
+        long max = runtime.maxMemory();
+        long total = runtime.totalMemory();
+        long free = runtime.freeMemory();
+        System.out.println("r max " + max);
+        System.out.println("r bounds " + (free >= 0 && free <= total && total <= max ? "ok" : "bad"));
+
+        kept = build(100);
+        Node local = build(100);
+        System.out.println("r sums " + churn(local) + " " + sum(kept));
+    }
+}
+"#;
+
+#[test]
+fn runtime_reports_heap_and_gc_keeps_reachable_objects() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("memory");
+    compile_java(&dir, "Memory.java", MEMORY);
+
+    for mode in ["-Xint", "-Xcomp"] {
+        let output = run_aria(&dir, &[mode, "-Xmx64m", "Memory"]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            results(&output),
+            [
+                "same 0",
+                "processors some",
+                "max 67108864",
+                "bounds ok",
+                "sums 14850 14850",
+            ],
+            "{}",
+            mode
+        );
+    }
+}
+
+const SERVER: &str = r#"
+public class Server {
+    static class Close extends Thread {
+        public void run() {
+            System.out.println("r hook ran");
+        }
+    }
+
+    public static void main(String[] args) {
+        Runtime.getRuntime().addShutdownHook(new Close());
+        System.out.println("r started");
+        int n = 0;
+        while (true) {
+            n = n * 31 + 7;
+        }
+    }
+}
+"#;
+
+#[test]
+fn sigterm_runs_shutdown_hooks() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("signal");
+    compile_java(&dir, "Server.java", SERVER);
+
+    // In the default mode the signal is sent once the loop runs as
+    // compiled code, which must still notice it.
+    for mode in ["-Xint", "-XX:+PrintCompilation"] {
+        let ready = |line: &str| match mode {
+            "-Xint" => line == "r started",
+            _ => line.contains("Server::main ("),
+        };
+        let mut child = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+            .arg(mode)
+            .arg("-cp")
+            .arg(&dir)
+            .arg("Server")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("run aria_core");
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout"));
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        while stdout.read_line(&mut line).expect("read stdout") > 0 {
+            let seen = ready(line.trim_end());
+            lines.push(std::mem::take(&mut line));
+            if seen {
+                break;
+            }
+        }
+        let killed = Command::new("kill")
+            .args(["-TERM", &child.id().to_string()])
+            .status()
+            .expect("run kill");
+        assert!(killed.success());
+
+        let rest = thread::spawn(move || {
+            stdout
+                .lines()
+                .map(|line| line.expect("read stdout"))
+                .collect::<Vec<_>>()
+        });
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let status = loop {
+            if let Some(status) = child.try_wait().expect("wait for aria_core") {
+                break status;
+            }
+            if Instant::now() > deadline {
+                let _ = child.kill();
+                panic!("{} ignored SIGTERM", mode);
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+        lines.extend(rest.join().expect("read stdout"));
+        let results: Vec<&str> = lines
+            .iter()
+            .filter_map(|l| l.trim_end().strip_prefix("r "))
+            .collect();
+        assert_eq!(status.code(), Some(143), "{}", mode);
+        assert_eq!(results, ["started", "hook ran"], "{}", mode);
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+
+use common::{compile_java, has_javac, temp_dir};
+
+#[test]
+fn system_natives_follow_hotspot_semantics() {
+    if !has_javac() {
+        return;
+    }
+
+    let dir = temp_dir("system");
+
+    compile_java(
+        &dir,
+        "Main.java",
+        r#"
+        public class Main {
+          static void copy(Object src, int srcPos, Object dest, int destPos, int length) {
+            try {
+              System.arraycopy(src, srcPos, dest, destPos, length);
+              System.out.println("r ok");
+            } catch (RuntimeException e) {
+              System.out.print("r ");
+              System.out.println(e);
+            }
+          }
+
+          public static void main(String[] args) {
+            int[] ints = new int[10];
+            for (int i = 0; i < ints.length; i++) {
+              ints[i] = i;
+            }
+            copy(ints, 0, ints, 2, 6);
+            for (int i = 0; i < ints.length; i++) {
+              System.out.print(ints[i]);
+            }
+            System.out.println();
+            copy(ints, 5, ints, 0, 6);
+            copy(ints, -1, ints, 0, 6);
+            copy(ints, 0, ints, 8, 6);
+            copy(ints, 0, ints, 0, -1);
+            copy(ints, 0, new long[10], 0, 1);
+            Object[] mixed = new Object[] {"a", Integer.valueOf(1), "b"};
+            copy(mixed, 0, ints, 0, 1);
+            copy("x", 0, ints, 0, 1);
+            copy(ints, 0, "x", 0, 1);
+            copy(null, 0, ints, 0, 1);
+            String[] strings = new String[10];
+            copy(mixed, 0, strings, 0, 3);
+            System.out.print("r ");
+            System.out.println(strings[0] + " " + strings[1]);
+            copy(mixed, 0, strings, 9, 3);
+            copy(strings, 0, mixed, 0, 3);
+
+            Object o = new Object();
+            System.out.print("r ");
+            System.out.println(System.identityHashCode(o) == o.hashCode());
+            System.out.print("r ");
+            System.out.println(System.identityHashCode(null));
+            long t0 = System.nanoTime();
+            long t1 = System.nanoTime();
+            System.out.print("r ");
+            System.out.println(t1 >= t0);
+            System.out.print("r ");
+            System.out.println(System.lineSeparator().equals("\n"));
+            System.out.print("r ");
+            System.out.println(System.getenv("ARIA_SYSTEM_TEST"));
+            System.out.print("r ");
+            System.out.println(System.getenv("ARIA_SYSTEM_TEST_UNSET"));
+
+            System.setOut(System.err);
+            System.out.println("moved to stderr");
+            System.out.print("unflushed");
+            System.exit(3);
+          }
+        }
+        "#,
+    );
+
+    let aria = Command::new(env!("CARGO_BIN_EXE_aria_core"))
+        .arg("-cp")
+        .arg(&dir)
+        .arg("Main")
+        .env("ARIA_SYSTEM_TEST", "from-env")
+        .env_remove("ARIA_SYSTEM_TEST_UNSET")
+        .output()
+        .expect("run aria_core");
+    let _ = fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&aria.stdout);
+    let stderr = String::from_utf8_lossy(&aria.stderr);
+    let results: Vec<&str> = stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix("r "))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            "ok",
+            "java.lang.ArrayIndexOutOfBoundsException: arraycopy: last source index 11 out of bounds for int[10]",
+            "java.lang.ArrayIndexOutOfBoundsException: arraycopy: source index -1 out of bounds for int[10]",
+            "java.lang.ArrayIndexOutOfBoundsException: arraycopy: last destination index 14 out of bounds for int[10]",
+            "java.lang.ArrayIndexOutOfBoundsException: arraycopy: length -1 is negative",
+            "java.lang.ArrayStoreException: arraycopy: type mismatch: can not copy int[] into long[]",
+            "java.lang.ArrayStoreException: arraycopy: type mismatch: can not copy object array[] into int[]",
+            "java.lang.ArrayStoreException: arraycopy: source type java.lang.String is not an array",
+            "java.lang.ArrayStoreException: arraycopy: destination type java.lang.String is not an array",
+            "java.lang.NullPointerException",
+            "java.lang.ArrayStoreException: arraycopy: element type mismatch: can not cast one of the elements of java.lang.Object[] to the type of the destination array, java.lang.String",
+            "a null",
+            "java.lang.ArrayIndexOutOfBoundsException: arraycopy: last destination index 12 out of bounds for object array[10]",
+            "ok",
+            "true",
+            "0",
+            "true",
+            "true",
+            "from-env",
+            "null",
+        ],
+        "stdout:\n{}",
+        stdout
+    );
+    assert!(
+        stdout.lines().any(|l| l == "0101234589"),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stderr.contains("moved to stderr\nunflushed"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(aria.status.code(), Some(3));
+}
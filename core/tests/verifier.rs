@@ -0,0 +1,466 @@
+use aria_core::bytecode::assembler::{Assembler, ValueKind};
+use aria_core::bytecode::attributes::{Attribute, StackMapFrame, VerificationType};
+use aria_core::bytecode::constant_pool::ConstantPoolBuilder;
+use aria_core::bytecode::parser::{ClassFile, CodeAttribute, MethodInfo};
+use aria_core::loader::class_loader::ClassLoader;
+use aria_core::verifier::{verify_class, VerifyError};
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{compile_java, has_javac, results, run_aria, temp_dir};
+
+fn method(
+    pool: &mut ConstantPoolBuilder,
+    name: &str,
+    descriptor: &str,
+    max_stack: u16,
+    max_locals: u16,
+    code: Vec<u8>,
+    frames: Vec<StackMapFrame>,
+) -> MethodInfo {
+    let mut attributes = Vec::new();
+    if !frames.is_empty() {
+        pool.utf8("StackMapTable");
+        attributes.push(Attribute::StackMapTable(frames));
+    }
+    pool.utf8("Code");
+    MethodInfo {
+        access_flags: if name == "<init>" { 0x0001 } else { 0x0009 },
+        name_index: pool.utf8(name),
+        descriptor_index: pool.utf8(descriptor),
+        code: Some(CodeAttribute {
+            max_stack,
+            max_locals,
+            code,
+            exception_table: Vec::new(),
+            attributes,
+        }),
+        attributes: Vec::new(),
+    }
+}
+
+fn class_file(
+    major_version: u16,
+    mut pool: ConstantPoolBuilder,
+    name: &str,
+    methods: Vec<MethodInfo>,
+) -> ClassFile {
+    let this_class = pool.class(name);
+    let super_class = pool.class("java/lang/Object");
+    let constant_pool = pool.finish().expect("pool");
+    ClassFile {
+        magic: 0xCAFEBABE,
+        minor_version: 0,
+        major_version,
+        constant_pool_count: constant_pool.len() as u16 + 1,
+        constant_pool,
+        access_flags: 0x0021,
+        this_class,
+        super_class,
+        interfaces: Vec::new(),
+        fields: Vec::new(),
+        methods,
+        attributes: Vec::new(),
+    }
+}
+
+/// `main` summing 1..=10 in a loop and printing "r sum 55"; `frames`
+/// decides whether the loop's stack map frames are recorded.
+fn sum_class(major_version: u16, frames: bool) -> ClassFile {
+    let mut pool = ConstantPoolBuilder::new();
+    let system = pool.class("java/lang/System");
+    let out_type = pool.name_and_type("out", "Ljava/io/PrintStream;");
+    let out = pool.field_ref(system, out_type);
+    let print_stream = pool.class("java/io/PrintStream");
+    let println_type = pool.name_and_type("println", "(Ljava/lang/String;)V");
+    let println = pool.method_ref(print_stream, println_type);
+    let label = pool.string("r sum 55");
+
+    let mut asm = Assembler::new();
+    let top = asm.new_label();
+    let end = asm.new_label();
+    asm.push_int(0, &mut pool);
+    asm.store(ValueKind::Int, 1);
+    asm.push_int(1, &mut pool);
+    asm.store(ValueKind::Int, 2);
+    asm.bind(top).unwrap();
+    asm.load(ValueKind::Int, 2);
+    asm.push_int(10, &mut pool);
+    asm.jump(0xa3, end);
+    asm.load(ValueKind::Int, 1);
+    asm.load(ValueKind::Int, 2);
+    asm.emit(0x60);
+    asm.store(ValueKind::Int, 1);
+    asm.emit(0x84);
+    asm.emit_u1(2);
+    asm.emit_u1(1);
+    asm.jump(0xa7, top);
+    asm.bind(end).unwrap();
+    asm.emit(0xb2);
+    asm.emit_u2(out);
+    asm.ldc(label);
+    asm.emit(0xb6);
+    asm.emit_u2(println);
+    asm.return_value(None);
+
+    let top = asm.offset(top).unwrap() as u16;
+    let end = asm.offset(end).unwrap() as u16;
+    let frames = if frames {
+        vec![
+            StackMapFrame::Append {
+                offset_delta: top,
+                locals: vec![VerificationType::Integer, VerificationType::Integer],
+            },
+            StackMapFrame::Same {
+                offset_delta: end - top - 1,
+            },
+        ]
+    } else {
+        Vec::new()
+    };
+    let code = asm.finish().expect("assemble");
+    let main = method(
+        &mut pool,
+        "main",
+        "([Ljava/lang/String;)V",
+        2,
+        3,
+        code,
+        frames,
+    );
+    class_file(major_version, pool, "Sum", vec![main])
+}
+
+/// `main` passing an `int` to `println(String)`.
+fn bad_operand_class() -> ClassFile {
+    let mut pool = ConstantPoolBuilder::new();
+    let system = pool.class("java/lang/System");
+    let out_type = pool.name_and_type("out", "Ljava/io/PrintStream;");
+    let out = pool.field_ref(system, out_type);
+    let print_stream = pool.class("java/io/PrintStream");
+    let println_type = pool.name_and_type("println", "(Ljava/lang/String;)V");
+    let println = pool.method_ref(print_stream, println_type);
+
+    let mut asm = Assembler::new();
+    asm.emit(0xb2);
+    asm.emit_u2(out);
+    asm.push_int(1, &mut pool);
+    asm.emit(0xb6);
+    asm.emit_u2(println);
+    asm.return_value(None);
+    let code = asm.finish().expect("assemble");
+    let main = method(
+        &mut pool,
+        "main",
+        "([Ljava/lang/String;)V",
+        2,
+        1,
+        code,
+        Vec::new(),
+    );
+    class_file(61, pool, "Bad", vec![main])
+}
+
+/// A one-method class whose `run()I` has the given code.
+fn single_method(major_version: u16, max_stack: u16, max_locals: u16, code: Vec<u8>) -> ClassFile {
+    let mut pool = ConstantPoolBuilder::new();
+    let run = method(
+        &mut pool,
+        "run",
+        "()I",
+        max_stack,
+        max_locals,
+        code,
+        Vec::new(),
+    );
+    class_file(major_version, pool, "Single", vec![run])
+}
+
+fn verify(class: &ClassFile) -> Result<(), VerifyError> {
+    verify_class(class, &mut ClassLoader::new())
+}
+
+#[test]
+fn bad_operand_type_is_rejected_with_location() {
+    let error = verify(&bad_operand_class()).expect_err("int passed as String");
+    assert_eq!(error.class_name, "Bad");
+    assert_eq!(error.method, "main([Ljava/lang/String;)V");
+    assert_eq!(error.offset, 4);
+    assert_eq!(error.mnemonic, "invokevirtual");
+    assert_eq!(
+        error.to_string(),
+        "Bad type on operand stack\n\
+         Exception Details:\n  \
+         Location:\n    \
+         Bad.main([Ljava/lang/String;)V @4: invokevirtual\n  \
+         Reason:\n    \
+         Type integer (current frame, stack[1]) is not assignable to 'java/lang/String'"
+    );
+}
+
+#[test]
+fn structural_errors_are_rejected() {
+    // goto into the middle of sipush.
+    let error = verify(&single_method(
+        61,
+        1,
+        0,
+        vec![0xa7, 0x00, 0x04, 0x11, 0x00, 0x01, 0xac],
+    ))
+    .expect_err("branch into an instruction");
+    assert_eq!(error.message, "Illegal target of jump or branch");
+    assert_eq!(error.offset, 0);
+
+    let error = verify(&single_method(61, 1, 0, vec![0x04, 0x57])).expect_err("no return");
+    assert_eq!(error.message, "Falling off the end of the code");
+
+    let error = verify(&single_method(61, 1, 0, vec![0x04, 0x04, 0x60, 0xac]))
+        .expect_err("max_stack exceeded");
+    assert_eq!(
+        (error.message.as_str(), error.offset),
+        ("Operand stack overflow", 1)
+    );
+
+    let error = verify(&single_method(61, 1, 1, vec![0x0b, 0x43, 0x1a, 0xac]))
+        .expect_err("float loaded as int");
+    assert_eq!(
+        (error.message.as_str(), error.offset),
+        ("Bad local variable type", 2)
+    );
+
+    let error = verify(&single_method(61, 2, 0, vec![0x0a, 0xac])).expect_err("long for int");
+    assert_eq!(error.message, "Bad return type");
+}
+
+#[test]
+fn branch_targets_need_stack_map_frames() {
+    let error = verify(&sum_class(61, false)).expect_err("no frames");
+    assert!(
+        error
+            .message
+            .starts_with("Expecting a stackmap frame at branch target"),
+        "{}",
+        error
+    );
+    assert_eq!(verify(&sum_class(61, true)), Ok(()));
+
+    // The frame at the loop head claims a float where an int is stored.
+    let mut class = sum_class(61, true);
+    let code = class.methods[0].code.as_mut().unwrap();
+    let Attribute::StackMapTable(frames) = &mut code.attributes[0] else {
+        panic!("frames");
+    };
+    frames[0] = match &frames[0] {
+        StackMapFrame::Append { offset_delta, .. } => StackMapFrame::Append {
+            offset_delta: *offset_delta,
+            locals: vec![VerificationType::Integer, VerificationType::Float],
+        },
+        other => other.clone(),
+    };
+    let error = verify(&class).expect_err("mismatched frame");
+    assert_eq!(error.message, "Instruction type does not match stack map");
+    assert!(
+        error.reason.as_deref().unwrap_or("").contains("locals[2]"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn old_class_files_are_verified_by_inference() {
+    assert_eq!(verify(&sum_class(49, false)), Ok(()));
+
+    let mut class = bad_operand_class();
+    class.major_version = 49;
+    let error = verify(&class).expect_err("int passed as String");
+    assert_eq!(error.message, "Bad type on operand stack");
+
+    // Version 50 falls back to inference when the stack maps are missing.
+    assert_eq!(verify(&sum_class(50, false)), Ok(()));
+
+    // Merging an int and a float leaves nothing usable in local 0.
+    let code = vec![
+        0x03, 0x3b, // iconst_0; istore_0
+        0x1a, 0x99, 0x00, 0x05, // iload_0; ifeq +5
+        0x0b, 0x43, // fconst_0; fstore_0
+        0x1a, 0xac, // iload_0; ireturn
+    ];
+    let error = verify(&single_method(49, 1, 1, code)).expect_err("merged local");
+    assert_eq!(
+        (error.message.as_str(), error.offset),
+        ("Bad local variable type", 8)
+    );
+}
+
+#[test]
+fn subroutines_are_followed_through_jsr_and_ret() {
+    // A finally-style subroutine that leaves the int in local 0 alone.
+    let code = vec![
+        0x10, 0x07, // bipush 7
+        0x3b, // istore_0
+        0xa8, 0x00, 0x05, // jsr +5
+        0x1a, // iload_0
+        0xac, // ireturn
+        0x4c, // astore_1
+        0xa9, 0x01, // ret 1
+    ];
+    assert_eq!(verify(&single_method(49, 1, 2, code.clone())), Ok(()));
+
+    let error = verify(&single_method(51, 1, 2, code)).expect_err("jsr after version 50");
+    assert_eq!(
+        (error.message.as_str(), error.offset),
+        ("Illegal instruction jsr in class file version 51", 3)
+    );
+
+    // A subroutine clobbering local 0 with a float is seen by the caller.
+    let code = vec![
+        0x10, 0x07, 0x3b, 0xa8, 0x00, 0x05, 0x1a, 0xac, 0x4c, 0x0b, 0x43, 0xa9, 0x01,
+    ];
+    let error = verify(&single_method(49, 1, 2, code)).expect_err("clobbered local");
+    assert_eq!(
+        (error.message.as_str(), error.offset),
+        ("Bad local variable type", 6)
+    );
+}
+
+#[test]
+fn constructors_must_call_super() {
+    let mut pool = ConstantPoolBuilder::new();
+    let init = method(&mut pool, "<init>", "()V", 1, 1, vec![0xb1], Vec::new());
+    let error = verify(&class_file(61, pool, "NoSuper", vec![init])).expect_err("no super()");
+    assert_eq!(
+        error.message,
+        "Constructor must call super() or this() before return"
+    );
+
+    let mut pool = ConstantPoolBuilder::new();
+    let object = pool.class("java/lang/Object");
+    let object_init = pool.name_and_type("<init>", "()V");
+    let object_init = pool.method_ref(object, object_init);
+    let [high, low] = object_init.to_be_bytes();
+    let code = vec![0x2a, 0xb7, high, low, 0xb1];
+    let init = method(&mut pool, "<init>", "()V", 1, 1, code, Vec::new());
+    assert_eq!(
+        verify(&class_file(61, pool, "WithSuper", vec![init])),
+        Ok(())
+    );
+}
+
+#[test]
+fn seed_classes_verify() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/class_parser");
+    let mut loader = ClassLoader::new();
+    loader.add_classpath(&dir);
+    for entry in fs::read_dir(&dir).expect("corpus") {
+        let bytes = fs::read(entry.expect("entry").path()).expect("read seed");
+        let class = ClassFile::from_bytes(&bytes).expect("parse seed");
+        assert_eq!(verify_class(&class, &mut loader), Ok(()));
+    }
+}
+
+#[test]
+fn cli_reports_verify_errors_and_honors_xverify_none() {
+    let dir = temp_dir("cli");
+    fs::write(
+        dir.join("Bad.class"),
+        bad_operand_class().to_bytes().expect("write"),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("Sum.class"),
+        sum_class(61, false).to_bytes().expect("write"),
+    )
+    .unwrap();
+
+    let bad = run_aria(&dir, &["Bad"]);
+    let stderr = String::from_utf8_lossy(&bad.stderr);
+    assert_eq!(bad.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("Error: Unable to initialize main class Bad\nCaused by: java.lang.VerifyError: Bad type on operand stack"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Bad.main([Ljava/lang/String;)V @4: invokevirtual"),
+        "stderr: {}",
+        stderr
+    );
+
+    let unverified = run_aria(&dir, &["Sum"]);
+    assert_eq!(unverified.status.code(), Some(1));
+    assert!(results(&unverified).is_empty());
+
+    let skipped = run_aria(&dir, &["-Xverify:none", "Sum"]);
+    assert_eq!(
+        results(&skipped),
+        vec!["sum 55"],
+        "stderr: {}",
+        String::from_utf8_lossy(&skipped.stderr)
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn javac_output_verifies_and_runs() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("javac");
+    compile_java(
+        &dir,
+        "Checked.java",
+        r#"
+        public class Checked {
+          interface Shape { int area(); }
+          static class Square implements Shape {
+            final int side;
+            Square(int side) { this.side = side; }
+            public int area() { return side * side; }
+          }
+          static class Rect extends Square {
+            final int other;
+            Rect(int side, int other) { super(side); this.other = other; }
+            public int area() { return side * other; }
+          }
+
+          static long mix(long a, double b, int[] values) {
+            long total = a;
+            for (int value : values) {
+              total += value;
+            }
+            return total + (long) b;
+          }
+
+          static int guarded(int n) {
+            int result = 0;
+            try {
+              result = 10 / n;
+            } catch (ArithmeticException e) {
+              result = -1;
+            } finally {
+              result += 100;
+            }
+            return result;
+          }
+
+          public static void main(String[] args) {
+            Shape shape = args.length > 5 ? new Square(3) : new Rect(3, 4);
+            System.out.println("r area " + shape.area());
+            System.out.println("r mix " + mix(1L, 2.5, new int[] {3, 4}));
+            System.out.println("r guarded " + guarded(0) + " " + guarded(5));
+          }
+        }
+        "#,
+    );
+
+    let output = run_aria(&dir, &["Checked"]);
+    assert_eq!(
+        results(&output),
+        vec!["area 12", "mix 10", "guarded 99 102"],
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
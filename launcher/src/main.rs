@@ -1,4 +1,7 @@
 fn main() {
     let args: Vec<String> = std::env::args().skip(1).collect();
-    std::process::exit(aria_core::run_cli(&args));
+    std::process::exit(aria_core::run_cli_with(
+        &args,
+        Some(aria_javac::source_launch::compile),
+    ));
 }
@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn has_javac() -> bool {
+    Command::new("javac").arg("-version").output().is_ok()
+}
+
+fn temp_dir(tag: &str) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock")
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("aria-launcher-{}-{}", tag, stamp));
+    fs::create_dir_all(&dir).expect("mkdir");
+    dir
+}
+
+fn run_aria(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_aria"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("run aria")
+}
+
+fn results(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.strip_prefix("r "))
+        .map(str::to_string)
+        .collect()
+}
+
+const SCRIPT: &str = r#"package ops.tools;
+
+class Sum {
+    public static void main(String[] args) {
+        int total = 0;
+        for (int i = 0; i < args.length; i++) total += Integer.parseInt(args[i]);
+        System.out.println("r sum " + total);
+        Report.done(args.length);
+    }
+}
+
+public class Report {
+    static void done(int count) { System.out.println("r count " + count); }
+}
+"#;
+
+#[test]
+fn aria_frontend_compiles_supported_sources_in_memory() {
+    let dir = temp_dir("frontend");
+    fs::write(
+        dir.join("squares.java"),
+        "public class Squares {\n    static int square(int x) { return x * x; }\n    public static void main(String[] args) {\n        int total = 0;\n        int i = 0;\n        while (i < 4) { total = total + square(i); i = i + 1; }\n        System.out.println(total);\n    }\n}\n",
+    )
+    .expect("write source");
+
+    let output = run_aria(&dir, &["squares.java"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Loading class: Squares source: memory"));
+    assert!(stdout.lines().any(|line| line == "14"), "{}", stdout);
+    // Nothing is written next to the source.
+    assert_eq!(fs::read_dir(&dir).expect("read dir").count(), 1);
+}
+
+#[test]
+fn first_class_runs_with_remaining_arguments() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("args");
+    fs::write(dir.join("script.java"), SCRIPT).expect("write source");
+
+    let output = run_aria(&dir, &["-Xint", "script.java", "4", "5", "6"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(results(&output), ["sum 15", "count 3"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn shebang_scripts_run_with_source_option() {
+    use std::os::unix::fs::PermissionsExt;
+
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("shebang");
+    let script = dir.join("sum");
+    let text = format!("#!{} --source 17\n{}", env!("CARGO_BIN_EXE_aria"), SCRIPT);
+    fs::write(&script, text).expect("write script");
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    let output = Command::new(&script)
+        .args(["10", "20"])
+        .output()
+        .expect("run script");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(results(&output), ["sum 30", "count 2"]);
+
+    // Without `--source` a file that is not `.java` is not source.
+    let output = run_aria(&dir, &["sum"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn compile_errors_and_missing_main_are_reported() {
+    if !has_javac() {
+        return;
+    }
+    let dir = temp_dir("errors");
+    fs::write(
+        dir.join("bad.java"),
+        "class Bad {\n    void f() { int x = \"s\"; }\n}\n",
+    )
+    .expect("write source");
+    let output = run_aria(&dir, &["bad.java"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("bad.java:2: error: incompatible types"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with("error: compilation failed\n"),
+        "{}",
+        stderr
+    );
+
+    fs::write(dir.join("nomain.java"), "class NoMain {}\n").expect("write source");
+    let output = run_aria(&dir, &["nomain.java"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("error: can't find main(String[]) method in class: NoMain"));
+
+    let output = run_aria(&dir, &["missing.java"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("error: file not found: missing.java"));
+    let output = run_aria(&dir, &["--source", "seventeen", "bad.java"]);
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("error: invalid value for --source option: seventeen"));
+}
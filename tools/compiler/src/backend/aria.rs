@@ -5,6 +5,7 @@ pub mod lexer;
 pub mod parser;
 pub mod sema;
 
+pub use self::driver::compile_source_program;
 use self::driver::run_frontend_pipeline;
 use crate::backend::CompilerBackend;
 use crate::cli::CompileRequest;
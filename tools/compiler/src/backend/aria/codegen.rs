@@ -2,6 +2,11 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use aria_core::bytecode::assembler::{Assembler, Label, ValueKind};
+use aria_core::bytecode::attributes::{self, Attribute, StackMapFrame};
+use aria_core::bytecode::constant_pool::ConstantPoolBuilder;
+use aria_core::bytecode::parser::{ClassFile, CodeAttribute, FieldInfo, MethodInfo, JAVA_MAGIC};
+
 use crate::backend::aria::ast::*;
 use crate::backend::aria::sema::SourceFileAst;
 
@@ -20,7 +25,9 @@ pub fn emit_classes(files: &[SourceFileAst], out_dir: &Path) -> Result<(), Vec<C
     let class_members = build_class_members(files);
     for file in files {
         for class in &file.unit.classes {
-            if let Err(err) = emit_class(file.path.as_path(), class, out_dir, &class_members) {
+            let emitted = generate_class(file.path.as_path(), class, &class_members)
+                .and_then(|bytes| write_class(file.path.as_path(), class, out_dir, &bytes));
+            if let Err(err) = emitted {
                 errors.push(err);
             }
         }
@@ -32,6 +39,29 @@ pub fn emit_classes(files: &[SourceFileAst], out_dir: &Path) -> Result<(), Vec<C
     }
 }
 
+/// Generates every class in memory, in declaration order, as pairs of
+/// internal name and class file bytes.
+pub fn generate_classes(
+    files: &[SourceFileAst],
+) -> Result<Vec<(String, Vec<u8>)>, Vec<CodegenError>> {
+    let mut classes = Vec::new();
+    let mut errors = Vec::new();
+    let class_members = build_class_members(files);
+    for file in files {
+        for class in &file.unit.classes {
+            match generate_class(file.path.as_path(), class, &class_members) {
+                Ok(bytes) => classes.push((class.name.replace('.', "/"), bytes)),
+                Err(err) => errors.push(err),
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(classes)
+    } else {
+        Err(errors)
+    }
+}
+
 fn build_class_members(files: &[SourceFileAst]) -> HashMap<String, ClassMembers> {
     let mut out = HashMap::<String, ClassMembers>::new();
     for file in files {
@@ -56,53 +86,38 @@ fn build_class_members(files: &[SourceFileAst]) -> HashMap<String, ClassMembers>
     out
 }
 
-fn emit_class(
+fn generate_class(
     path: &Path,
     class: &ClassDecl,
-    out_dir: &Path,
     class_members: &HashMap<String, ClassMembers>,
-) -> Result<(), CodegenError> {
-    fs::create_dir_all(out_dir).map_err(|e| CodegenError {
-        path: path.to_path_buf(),
-        line: class.span.line,
-        col: class.span.col,
-        message: format!("failed to create output directory: {}", e),
-    })?;
-
+) -> Result<Vec<u8>, CodegenError> {
     let class_name_slash = class.name.replace('.', "/");
-    let mut cp = ConstantPool::new();
+    let mut cp = ConstantPoolBuilder::new();
     let this_class = cp.class(&class_name_slash);
     let super_class = cp.class("java/lang/Object");
-    let code_attr_name = cp.utf8("Code");
-    let stack_map_attr_name = cp.utf8("StackMapTable");
+    cp.utf8("Code");
+    cp.utf8("StackMapTable");
 
-    let mut fields_out = Vec::new();
+    let mut fields = Vec::new();
     for member in &class.members {
         if let MemberDecl::Field(field) = member {
-            let name_idx = cp.utf8(&field.name);
+            let name_index = cp.utf8(&field.name);
             let desc = type_descriptor(&field.ty).map_err(|m| CodegenError {
                 path: path.to_path_buf(),
                 line: field.span.line,
                 col: field.span.col,
                 message: m,
             })?;
-            let desc_idx = cp.utf8(&desc);
-            let access = 0x0001u16;
-            fields_out.push(FieldOut {
-                access,
-                name_idx,
-                desc_idx,
+            fields.push(FieldInfo {
+                access_flags: 0x0001,
+                name_index,
+                descriptor_index: cp.utf8(&desc),
+                attributes: Vec::new(),
             });
         }
     }
 
-    let mut methods_out = Vec::new();
-    methods_out.push(default_constructor_method(
-        class,
-        &mut cp,
-        code_attr_name,
-        stack_map_attr_name,
-    ));
+    let mut methods = vec![default_constructor_method(path, class, &mut cp)?];
     for member in &class.members {
         if let MemberDecl::Method(method) = member {
             let method_ctx = MethodContext {
@@ -110,14 +125,13 @@ fn emit_class(
                 class_members,
             };
             let code = compile_method(path, &method_ctx, method, &mut cp)?;
-            let name_idx = cp.utf8(&method.name);
+            let name_index = cp.utf8(&method.name);
             let desc = method_descriptor(method).map_err(|m| CodegenError {
                 path: path.to_path_buf(),
                 line: method.span.line,
                 col: method.span.col,
                 message: m,
             })?;
-            let desc_idx = cp.utf8(&desc);
             let mut access = 0u16;
             if method.is_public {
                 access |= 0x0001;
@@ -125,76 +139,50 @@ fn emit_class(
             if method.is_static {
                 access |= 0x0008;
             }
-            methods_out.push(MethodOut {
-                access,
-                name_idx,
-                desc_idx,
-                code,
-                code_attr_name,
-                stack_map_attr_name,
+            methods.push(MethodInfo {
+                access_flags: access,
+                name_index,
+                descriptor_index: cp.utf8(&desc),
+                code: Some(code),
+                attributes: Vec::new(),
             });
         }
     }
 
-    let mut bytes = Vec::new();
-    push_u4(&mut bytes, 0xCAFEBABE);
-    push_u2(&mut bytes, 0);
-    push_u2(&mut bytes, CLASSFILE_MAJOR_VERSION);
-
-    let cp_bytes = cp.to_bytes();
-    push_u2(
-        &mut bytes,
-        u16::try_from(cp.count_with_implicit_zero()).unwrap_or(u16::MAX),
-    );
-    bytes.extend(cp_bytes);
-
-    push_u2(&mut bytes, 0x0021);
-    push_u2(&mut bytes, this_class);
-    push_u2(&mut bytes, super_class);
-
-    push_u2(&mut bytes, 0);
-    push_u2(&mut bytes, u16::try_from(fields_out.len()).unwrap_or(0));
-    for f in &fields_out {
-        push_u2(&mut bytes, f.access);
-        push_u2(&mut bytes, f.name_idx);
-        push_u2(&mut bytes, f.desc_idx);
-        push_u2(&mut bytes, 0);
-    }
-
-    push_u2(&mut bytes, u16::try_from(methods_out.len()).unwrap_or(0));
-    for m in &methods_out {
-        push_u2(&mut bytes, m.access);
-        push_u2(&mut bytes, m.name_idx);
-        push_u2(&mut bytes, m.desc_idx);
-        push_u2(&mut bytes, 1);
-        push_u2(&mut bytes, m.code_attr_name);
-        let code_len_u32 = u32::try_from(m.code.code.len()).unwrap_or(u32::MAX);
-        let mut code_nested_attr_len = 0u32;
-        let mut code_nested_attr_count = 0u16;
-        if m.code.stack_map_table.len() > 2 {
-            code_nested_attr_len +=
-                6 + u32::try_from(m.code.stack_map_table.len()).unwrap_or(u32::MAX);
-            code_nested_attr_count += 1;
-        }
-        let attr_len = 12 + code_len_u32 + code_nested_attr_len;
-        push_u4(&mut bytes, attr_len);
-        push_u2(&mut bytes, m.code.max_stack);
-        push_u2(&mut bytes, m.code.max_locals);
-        push_u4(&mut bytes, code_len_u32);
-        bytes.extend(&m.code.code);
-        push_u2(&mut bytes, 0);
-        push_u2(&mut bytes, code_nested_attr_count);
-        if m.code.stack_map_table.len() > 2 {
-            push_u2(&mut bytes, m.stack_map_attr_name);
-            push_u4(
-                &mut bytes,
-                u32::try_from(m.code.stack_map_table.len()).unwrap_or(u32::MAX),
-            );
-            bytes.extend(&m.code.stack_map_table);
-        }
-    }
+    let constant_pool = cp
+        .finish()
+        .map_err(|e| cg_err(path, class.span, e.to_string()))?;
+    let class_file = ClassFile {
+        magic: JAVA_MAGIC,
+        minor_version: 0,
+        major_version: CLASSFILE_MAJOR_VERSION,
+        constant_pool_count: constant_pool.len() as u16 + 1,
+        constant_pool,
+        access_flags: 0x0021,
+        this_class,
+        super_class,
+        interfaces: Vec::new(),
+        fields,
+        methods,
+        attributes: Vec::new(),
+    };
+    class_file
+        .to_bytes()
+        .map_err(|e| cg_err(path, class.span, e.to_string()))
+}
 
-    push_u2(&mut bytes, 0);
+fn write_class(
+    path: &Path,
+    class: &ClassDecl,
+    out_dir: &Path,
+    bytes: &[u8],
+) -> Result<(), CodegenError> {
+    fs::create_dir_all(out_dir).map_err(|e| CodegenError {
+        path: path.to_path_buf(),
+        line: class.span.line,
+        col: class.span.col,
+        message: format!("failed to create output directory: {}", e),
+    })?;
 
     let out_file = out_dir.join(format!("{}.class", class.name.replace('.', "/")));
     if let Some(parent) = out_file.parent() {
@@ -219,34 +207,36 @@ fn emit_class(
 }
 
 fn default_constructor_method(
+    path: &Path,
     class: &ClassDecl,
-    cp: &mut ConstantPool,
-    code_attr_name: u16,
-    stack_map_attr_name: u16,
-) -> MethodOut {
+    cp: &mut ConstantPoolBuilder,
+) -> Result<MethodInfo, CodegenError> {
     let owner = cp.class("java/lang/Object");
     let nat = cp.name_and_type("<init>", "()V");
     let super_init = cp.method_ref(owner, nat);
 
-    let mut code = Vec::new();
-    code.push(0x2a);
-    code.push(0xb7);
-    push_u2(&mut code, super_init);
-    code.push(0xb1);
-
-    MethodOut {
-        access: if class.is_public { 0x0001 } else { 0x0000 },
-        name_idx: cp.utf8("<init>"),
-        desc_idx: cp.utf8("()V"),
-        code: MethodCode {
+    let mut asm = Assembler::new();
+    asm.load(ValueKind::Reference, 0);
+    asm.emit(0xb7);
+    asm.emit_u2(super_init);
+    asm.return_value(None);
+    let code = asm
+        .finish()
+        .map_err(|e| cg_err(path, class.span, e.to_string()))?;
+
+    Ok(MethodInfo {
+        access_flags: if class.is_public { 0x0001 } else { 0x0000 },
+        name_index: cp.utf8("<init>"),
+        descriptor_index: cp.utf8("()V"),
+        code: Some(CodeAttribute {
             max_stack: 1,
             max_locals: 1,
             code,
-            stack_map_table: vec![0, 0],
-        },
-        code_attr_name,
-        stack_map_attr_name,
-    }
+            exception_table: Vec::new(),
+            attributes: Vec::new(),
+        }),
+        attributes: Vec::new(),
+    })
 }
 
 #[derive(Default, Clone)]
@@ -312,29 +302,6 @@ struct MethodContext<'a> {
     class_members: &'a HashMap<String, ClassMembers>,
 }
 
-struct FieldOut {
-    access: u16,
-    name_idx: u16,
-    desc_idx: u16,
-}
-
-struct MethodOut {
-    access: u16,
-    name_idx: u16,
-    desc_idx: u16,
-    code: MethodCode,
-    code_attr_name: u16,
-    stack_map_attr_name: u16,
-}
-
-#[derive(Clone)]
-struct MethodCode {
-    max_stack: u16,
-    max_locals: u16,
-    code: Vec<u8>,
-    stack_map_table: Vec<u8>,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum EvalType {
     Int,
@@ -406,21 +373,13 @@ impl LocalScopes {
 }
 
 struct CodeBuilder {
-    bytes: Vec<u8>,
+    asm: Assembler,
     labels: Vec<LabelInfo>,
-    patches: Vec<JumpPatch>,
-}
-
-#[derive(Clone, Copy)]
-struct JumpPatch {
-    opcode_pos: usize,
-    offset_pos: usize,
-    label: usize,
 }
 
 #[derive(Clone)]
 struct LabelInfo {
-    offset: Option<usize>,
+    label: Label,
     frame: Option<FrameState>,
     referenced: bool,
 }
@@ -442,23 +401,23 @@ enum VerificationType {
 impl CodeBuilder {
     fn new() -> Self {
         Self {
-            bytes: Vec::new(),
+            asm: Assembler::new(),
             labels: Vec::new(),
-            patches: Vec::new(),
         }
     }
 
     fn emit_u1(&mut self, v: u8) {
-        self.bytes.push(v);
+        self.asm.emit_u1(v);
     }
 
     fn emit_u2(&mut self, v: u16) {
-        push_u2(&mut self.bytes, v);
+        self.asm.emit_u2(v);
     }
 
     fn new_label(&mut self) -> usize {
+        let label = self.asm.new_label();
         self.labels.push(LabelInfo {
-            offset: None,
+            label,
             frame: None,
             referenced: false,
         });
@@ -466,14 +425,10 @@ impl CodeBuilder {
     }
 
     fn bind_label(&mut self, label: usize) -> Result<(), String> {
-        let Some(info) = self.labels.get_mut(label) else {
+        let Some(info) = self.labels.get(label) else {
             return Err(format!("invalid label id {}", label));
         };
-        if info.offset.is_some() {
-            return Err(format!("label {} already bound", label));
-        }
-        info.offset = Some(self.bytes.len());
-        Ok(())
+        self.asm.bind(info.label).map_err(|e| e.to_string())
     }
 
     fn bind_label_with_frame(&mut self, label: usize, frame: FrameState) -> Result<(), String> {
@@ -500,114 +455,25 @@ impl CodeBuilder {
             return Err(format!("invalid label id {}", label));
         };
         info.referenced = true;
-        let opcode_pos = self.bytes.len();
-        self.emit_u1(opcode);
-        let offset_pos = self.bytes.len();
-        self.emit_u1(0);
-        self.emit_u1(0);
-        self.patches.push(JumpPatch {
-            opcode_pos,
-            offset_pos,
-            label,
-        });
+        self.asm.jump(opcode, info.label);
         Ok(())
     }
 
-    fn patch_labels(&mut self) -> Result<(), String> {
-        for patch in &self.patches {
-            let Some(target) = self.labels.get(patch.label).and_then(|x| x.offset) else {
-                return Err(format!("unresolved label {}", patch.label));
-            };
-            let offset = target as isize - patch.opcode_pos as isize;
-            if offset < i16::MIN as isize || offset > i16::MAX as isize {
-                return Err("branch offset out of i16 range".to_string());
-            }
-            let off = offset as i16 as u16;
-            self.bytes[patch.offset_pos] = (off >> 8) as u8;
-            self.bytes[patch.offset_pos + 1] = (off & 0xff) as u8;
-        }
-        Ok(())
-    }
-
-    fn emit_push_int(&mut self, v: i64, cp: &mut ConstantPool) -> Result<(), String> {
-        if v == -1 {
-            self.emit_u1(0x02);
-            return Ok(());
-        }
-        if (0..=5).contains(&v) {
-            self.emit_u1(0x03 + u8::try_from(v).map_err(|_| "int literal overflow".to_string())?);
-            return Ok(());
-        }
-        if (-128..=127).contains(&v) {
-            self.emit_u1(0x10);
-            self.emit_u1(v as i8 as u8);
-            return Ok(());
-        }
-        if (-32768..=32767).contains(&v) {
-            self.emit_u1(0x11);
-            self.emit_u2(v as i16 as u16);
-            return Ok(());
-        }
-        let idx = cp.integer(i32::try_from(v).map_err(|_| "int literal overflow".to_string())?);
-        if idx > u8::MAX as u16 {
-            return Err("constant pool too large for ldc".to_string());
-        }
-        self.emit_u1(0x12);
-        self.emit_u1(idx as u8);
+    fn emit_push_int(&mut self, v: i64, cp: &mut ConstantPoolBuilder) -> Result<(), String> {
+        let v = i32::try_from(v).map_err(|_| "int literal overflow".to_string())?;
+        self.asm.push_int(v, cp);
         Ok(())
     }
 
     fn emit_load(&mut self, slot: u16, ty: &EvalType) -> Result<(), String> {
-        match ty {
-            EvalType::Int | EvalType::Bool => {
-                if slot <= 3 {
-                    self.emit_u1(0x1a + slot as u8);
-                } else if slot <= 255 {
-                    self.emit_u1(0x15);
-                    self.emit_u1(slot as u8);
-                } else {
-                    return Err("local variable slot too large".to_string());
-                }
-            }
-            EvalType::Ref(_) | EvalType::ClassRef(_) => {
-                if slot <= 3 {
-                    self.emit_u1(0x2a + slot as u8);
-                } else if slot <= 255 {
-                    self.emit_u1(0x19);
-                    self.emit_u1(slot as u8);
-                } else {
-                    return Err("local variable slot too large".to_string());
-                }
-            }
-            EvalType::Void => return Err("cannot load void".to_string()),
-        }
+        let kind = value_kind(ty).ok_or_else(|| "cannot load void".to_string())?;
+        self.asm.load(kind, slot);
         Ok(())
     }
 
     fn emit_store(&mut self, slot: u16, ty: &EvalType) -> Result<(), String> {
-        match ty {
-            EvalType::Int | EvalType::Bool => {
-                if slot <= 3 {
-                    self.emit_u1(0x3b + slot as u8);
-                } else if slot <= 255 {
-                    self.emit_u1(0x36);
-                    self.emit_u1(slot as u8);
-                } else {
-                    return Err("local variable slot too large".to_string());
-                }
-            }
-            EvalType::Ref(_) | EvalType::ClassRef(_) => {
-                if slot <= 3 {
-                    self.emit_u1(0x4b + slot as u8);
-                } else if slot <= 255 {
-                    self.emit_u1(0x3a);
-                    self.emit_u1(slot as u8);
-                } else {
-                    return Err("local variable slot too large".to_string());
-                }
-            }
-            EvalType::Void => return Err("cannot store void".to_string()),
-        }
+        let kind = value_kind(ty).ok_or_else(|| "cannot store void".to_string())?;
+        self.asm.store(kind, slot);
         Ok(())
     }
 
@@ -615,25 +481,30 @@ impl CodeBuilder {
         mut self,
         max_locals: u16,
         initial_frame: FrameState,
-        cp: &mut ConstantPool,
-    ) -> Result<MethodCode, String> {
+        cp: &mut ConstantPoolBuilder,
+    ) -> Result<CodeAttribute, String> {
         self.materialize_terminal_labels();
-        self.patch_labels()?;
         let stack_map_table = self.build_stack_map_table(&initial_frame, cp)?;
-        Ok(MethodCode {
+        let code = self.asm.finish().map_err(|e| e.to_string())?;
+        let mut attributes = Vec::new();
+        if !stack_map_table.is_empty() {
+            attributes.push(Attribute::StackMapTable(stack_map_table));
+        }
+        Ok(CodeAttribute {
             max_stack: 64,
             max_locals,
-            code: self.bytes,
-            stack_map_table,
+            code,
+            exception_table: Vec::new(),
+            attributes,
         })
     }
 
     fn materialize_terminal_labels(&mut self) {
-        let end = self.bytes.len();
+        let end = self.asm.position();
         if self
             .labels
             .iter()
-            .any(|label| label.referenced && label.offset == Some(end))
+            .any(|label| label.referenced && self.asm.offset(label.label) == Some(end))
         {
             self.emit_u1(0x00);
         }
@@ -642,14 +513,14 @@ impl CodeBuilder {
     fn build_stack_map_table(
         &self,
         initial_frame: &FrameState,
-        cp: &mut ConstantPool,
-    ) -> Result<Vec<u8>, String> {
+        cp: &mut ConstantPoolBuilder,
+    ) -> Result<Vec<StackMapFrame>, String> {
         let mut targets = Vec::<(usize, FrameState)>::new();
         for (idx, label) in self.labels.iter().enumerate() {
             if !label.referenced {
                 continue;
             }
-            let Some(offset) = label.offset else {
+            let Some(offset) = self.asm.offset(label.label) else {
                 return Err(format!("unbound referenced label {}", idx));
             };
             let Some(frame) = label.frame.clone() else {
@@ -673,11 +544,6 @@ impl CodeBuilder {
         }
 
         let mut out = Vec::new();
-        push_u2(
-            &mut out,
-            u16::try_from(dedup.len()).map_err(|_| "too many stack map frames".to_string())?,
-        );
-
         let mut prev_offset: isize = -1;
         let mut prev_frame = initial_frame.clone();
         for (offset, frame) in dedup {
@@ -685,7 +551,7 @@ impl CodeBuilder {
             if !(0..=u16::MAX as isize).contains(&delta) {
                 return Err(format!("invalid frame offset delta at {}", offset));
             }
-            emit_frame(&mut out, delta as u16, &prev_frame, &frame, cp)?;
+            out.push(stack_map_frame(delta as u16, &prev_frame, &frame, cp));
             prev_offset = offset as isize;
             prev_frame = frame;
         }
@@ -698,8 +564,8 @@ fn compile_method(
     path: &Path,
     ctx: &MethodContext<'_>,
     method: &MethodDecl,
-    cp: &mut ConstantPool,
-) -> Result<MethodCode, CodegenError> {
+    cp: &mut ConstantPoolBuilder,
+) -> Result<CodeAttribute, CodegenError> {
     let mut code = CodeBuilder::new();
     let mut locals = LocalScopes::new(0);
     if !method.is_static {
@@ -716,10 +582,8 @@ fn compile_method(
 
     compile_stmt(path, ctx, &mut code, &mut locals, &method.body, cp)?;
 
-    if method.return_type == TypeName::Void {
-        if !matches!(code.bytes.last(), Some(0xb1)) {
-            code.emit_u1(0xb1);
-        }
+    if method.return_type == TypeName::Void && !matches!(code.asm.code().last(), Some(0xb1)) {
+        code.emit_u1(0xb1);
     }
 
     code.finish(locals.next_slot, initial_frame, cp)
@@ -732,7 +596,7 @@ fn compile_stmt(
     code: &mut CodeBuilder,
     locals: &mut LocalScopes,
     stmt: &Stmt,
-    cp: &mut ConstantPool,
+    cp: &mut ConstantPoolBuilder,
 ) -> Result<(), CodegenError> {
     match stmt {
         Stmt::Block(stmts, _) => {
@@ -869,7 +733,7 @@ fn emit_condition_jump_false(
     code: &mut CodeBuilder,
     locals: &mut LocalScopes,
     cond: &Expr,
-    cp: &mut ConstantPool,
+    cp: &mut ConstantPoolBuilder,
     false_label: usize,
 ) -> Result<(), CodegenError> {
     let cond_ty = compile_expr(path, ctx, code, locals, cond, cp)?;
@@ -927,7 +791,7 @@ fn compile_expr(
     code: &mut CodeBuilder,
     locals: &mut LocalScopes,
     expr: &Expr,
-    cp: &mut ConstantPool,
+    cp: &mut ConstantPoolBuilder,
 ) -> Result<EvalType, CodegenError> {
     match &expr.kind {
         ExprKind::IntLiteral(v) => {
@@ -1449,7 +1313,7 @@ fn compile_expr(
 fn emit_default_value(
     code: &mut CodeBuilder,
     ty: &EvalType,
-    cp: &mut ConstantPool,
+    cp: &mut ConstantPoolBuilder,
 ) -> Result<(), String> {
     match ty {
         EvalType::Int | EvalType::Bool => code.emit_push_int(0, cp),
@@ -1610,225 +1474,52 @@ fn cg_err(path: &Path, span: Span, message: impl Into<String>) -> CodegenError {
     }
 }
 
-fn emit_frame(
-    out: &mut Vec<u8>,
+fn stack_map_frame(
     offset_delta: u16,
     previous: &FrameState,
     current: &FrameState,
-    cp: &mut ConstantPool,
-) -> Result<(), String> {
-    if current.stack.is_empty() && current.locals == previous.locals && offset_delta <= 63 {
-        out.push(offset_delta as u8);
-        return Ok(());
-    }
-    if current.locals == previous.locals && current.stack.len() == 1 && offset_delta <= 63 {
-        out.push(64 + offset_delta as u8);
-        emit_verification_type(out, &current.stack[0], cp)?;
-        return Ok(());
-    }
-    out.push(255);
-    push_u2(out, offset_delta);
-    push_u2(
-        out,
-        u16::try_from(current.locals.len())
-            .map_err(|_| "too many locals in stack map frame".to_string())?,
-    );
-    for local in &current.locals {
-        emit_verification_type(out, local, cp)?;
+    cp: &mut ConstantPoolBuilder,
+) -> StackMapFrame {
+    if current.stack.is_empty() && current.locals == previous.locals {
+        return StackMapFrame::Same { offset_delta };
+    }
+    if current.locals == previous.locals && current.stack.len() == 1 {
+        return StackMapFrame::SameLocals1StackItem {
+            offset_delta,
+            stack: frame_verification_type(&current.stack[0], cp),
+        };
     }
-    push_u2(
-        out,
-        u16::try_from(current.stack.len())
-            .map_err(|_| "too many stack entries in stack map frame".to_string())?,
-    );
-    for stack_ty in &current.stack {
-        emit_verification_type(out, stack_ty, cp)?;
+    StackMapFrame::Full {
+        offset_delta,
+        locals: current
+            .locals
+            .iter()
+            .map(|ty| frame_verification_type(ty, cp))
+            .collect(),
+        stack: current
+            .stack
+            .iter()
+            .map(|ty| frame_verification_type(ty, cp))
+            .collect(),
     }
-    Ok(())
 }
 
-fn emit_verification_type(
-    out: &mut Vec<u8>,
+fn frame_verification_type(
     ty: &VerificationType,
-    cp: &mut ConstantPool,
-) -> Result<(), String> {
+    cp: &mut ConstantPoolBuilder,
+) -> attributes::VerificationType {
     match ty {
-        VerificationType::Top => out.push(0),
-        VerificationType::Integer => out.push(1),
-        VerificationType::Null => out.push(5),
-        VerificationType::Object(name) => {
-            out.push(7);
-            push_u2(out, cp.class(name));
-        }
+        VerificationType::Top => attributes::VerificationType::Top,
+        VerificationType::Integer => attributes::VerificationType::Integer,
+        VerificationType::Null => attributes::VerificationType::Null,
+        VerificationType::Object(name) => attributes::VerificationType::Object(cp.class(name)),
     }
-    Ok(())
-}
-
-fn push_u2(out: &mut Vec<u8>, v: u16) {
-    out.push((v >> 8) as u8);
-    out.push((v & 0xff) as u8);
-}
-
-fn push_u4(out: &mut Vec<u8>, v: u32) {
-    out.push((v >> 24) as u8);
-    out.push(((v >> 16) & 0xff) as u8);
-    out.push(((v >> 8) & 0xff) as u8);
-    out.push((v & 0xff) as u8);
-}
-
-#[derive(Clone)]
-enum CpEntry {
-    Utf8(String),
-    Integer(i32),
-    Class(u16),
-    String(u16),
-    FieldRef(u16, u16),
-    MethodRef(u16, u16),
-    NameAndType(u16, u16),
 }
 
-struct ConstantPool {
-    entries: Vec<CpEntry>,
-    utf8_map: HashMap<String, u16>,
-    int_map: HashMap<i32, u16>,
-    class_map: HashMap<String, u16>,
-    string_map: HashMap<String, u16>,
-    nat_map: HashMap<(String, String), u16>,
-    field_ref_map: HashMap<(u16, u16), u16>,
-    method_ref_map: HashMap<(u16, u16), u16>,
-}
-
-impl ConstantPool {
-    fn new() -> Self {
-        Self {
-            entries: Vec::new(),
-            utf8_map: HashMap::new(),
-            int_map: HashMap::new(),
-            class_map: HashMap::new(),
-            string_map: HashMap::new(),
-            nat_map: HashMap::new(),
-            field_ref_map: HashMap::new(),
-            method_ref_map: HashMap::new(),
-        }
-    }
-
-    fn count_with_implicit_zero(&self) -> usize {
-        self.entries.len() + 1
-    }
-
-    fn utf8(&mut self, s: &str) -> u16 {
-        if let Some(idx) = self.utf8_map.get(s) {
-            return *idx;
-        }
-        let idx = self.push(CpEntry::Utf8(s.to_string()));
-        self.utf8_map.insert(s.to_string(), idx);
-        idx
-    }
-
-    fn integer(&mut self, i: i32) -> u16 {
-        if let Some(idx) = self.int_map.get(&i) {
-            return *idx;
-        }
-        let idx = self.push(CpEntry::Integer(i));
-        self.int_map.insert(i, idx);
-        idx
-    }
-
-    fn class(&mut self, name: &str) -> u16 {
-        if let Some(idx) = self.class_map.get(name) {
-            return *idx;
-        }
-        let utf = self.utf8(name);
-        let idx = self.push(CpEntry::Class(utf));
-        self.class_map.insert(name.to_string(), idx);
-        idx
-    }
-
-    fn string(&mut self, value: &str) -> u16 {
-        if let Some(idx) = self.string_map.get(value) {
-            return *idx;
-        }
-        let utf = self.utf8(value);
-        let idx = self.push(CpEntry::String(utf));
-        self.string_map.insert(value.to_string(), idx);
-        idx
-    }
-
-    fn name_and_type(&mut self, name: &str, desc: &str) -> u16 {
-        let key = (name.to_string(), desc.to_string());
-        if let Some(idx) = self.nat_map.get(&key) {
-            return *idx;
-        }
-        let name_idx = self.utf8(name);
-        let desc_idx = self.utf8(desc);
-        let idx = self.push(CpEntry::NameAndType(name_idx, desc_idx));
-        self.nat_map.insert(key, idx);
-        idx
-    }
-
-    fn field_ref(&mut self, class_idx: u16, nat_idx: u16) -> u16 {
-        let key = (class_idx, nat_idx);
-        if let Some(idx) = self.field_ref_map.get(&key) {
-            return *idx;
-        }
-        let idx = self.push(CpEntry::FieldRef(class_idx, nat_idx));
-        self.field_ref_map.insert(key, idx);
-        idx
-    }
-
-    fn method_ref(&mut self, class_idx: u16, nat_idx: u16) -> u16 {
-        let key = (class_idx, nat_idx);
-        if let Some(idx) = self.method_ref_map.get(&key) {
-            return *idx;
-        }
-        let idx = self.push(CpEntry::MethodRef(class_idx, nat_idx));
-        self.method_ref_map.insert(key, idx);
-        idx
-    }
-
-    fn push(&mut self, entry: CpEntry) -> u16 {
-        self.entries.push(entry);
-        u16::try_from(self.entries.len()).unwrap_or(u16::MAX)
-    }
-
-    fn to_bytes(&self) -> Vec<u8> {
-        let mut out = Vec::new();
-        for e in &self.entries {
-            match e {
-                CpEntry::Utf8(s) => {
-                    out.push(1);
-                    push_u2(&mut out, u16::try_from(s.len()).unwrap_or(0));
-                    out.extend(s.as_bytes());
-                }
-                CpEntry::Integer(v) => {
-                    out.push(3);
-                    push_u4(&mut out, *v as u32);
-                }
-                CpEntry::Class(name_idx) => {
-                    out.push(7);
-                    push_u2(&mut out, *name_idx);
-                }
-                CpEntry::String(utf_idx) => {
-                    out.push(8);
-                    push_u2(&mut out, *utf_idx);
-                }
-                CpEntry::FieldRef(c, nt) => {
-                    out.push(9);
-                    push_u2(&mut out, *c);
-                    push_u2(&mut out, *nt);
-                }
-                CpEntry::MethodRef(c, nt) => {
-                    out.push(10);
-                    push_u2(&mut out, *c);
-                    push_u2(&mut out, *nt);
-                }
-                CpEntry::NameAndType(n, d) => {
-                    out.push(12);
-                    push_u2(&mut out, *n);
-                    push_u2(&mut out, *d);
-                }
-            }
-        }
-        out
+fn value_kind(ty: &EvalType) -> Option<ValueKind> {
+    match ty {
+        EvalType::Int | EvalType::Bool => Some(ValueKind::Int),
+        EvalType::Ref(_) | EvalType::ClassRef(_) => Some(ValueKind::Reference),
+        EvalType::Void => None,
     }
 }
@@ -1,10 +1,10 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::backend::aria::codegen::emit_classes;
+use crate::backend::aria::codegen::{emit_classes, generate_classes};
 use crate::backend::aria::lexer::lex;
 use crate::backend::aria::parser::parse;
-use crate::backend::aria::sema::{analyze, Diagnostic, SourceFileAst};
+use crate::backend::aria::sema::{analyze, analyze_source_program, Diagnostic, SourceFileAst};
 use crate::config::{target_java_major, TARGET_JAVA_VERSION};
 
 pub fn run_frontend_pipeline(args: &[String]) -> Result<(), String> {
@@ -48,33 +48,10 @@ pub fn run_frontend_pipeline(args: &[String]) -> Result<(), String> {
             }
         };
 
-        let tokens = match lex(&source) {
-            Ok(tokens) => tokens,
-            Err(err) => {
-                diagnostics.push(Diagnostic {
-                    path: path.clone(),
-                    line: err.line,
-                    col: err.col,
-                    message: format!("lex error: {}", err.message),
-                });
-                continue;
-            }
-        };
-
-        let unit = match parse(tokens) {
-            Ok(unit) => unit,
-            Err(err) => {
-                diagnostics.push(Diagnostic {
-                    path: path.clone(),
-                    line: err.span.line,
-                    col: err.span.col,
-                    message: format!("parse error: {}", err.message),
-                });
-                continue;
-            }
-        };
-
-        parsed_files.push(SourceFileAst { path, unit });
+        match parse_source(path, &source) {
+            Ok(file) => parsed_files.push(file),
+            Err(diag) => diagnostics.push(diag),
+        }
     }
 
     diagnostics.extend(analyze(&parsed_files));
@@ -109,6 +86,47 @@ pub fn run_frontend_pipeline(args: &[String]) -> Result<(), String> {
     }
 }
 
+/// Compiles one source file launched as a program (JEP 330) without
+/// touching the file system. Returns the classes in declaration order as
+/// pairs of internal name and class file bytes.
+pub fn compile_source_program(
+    path: &Path,
+    source: &str,
+) -> Result<Vec<(String, Vec<u8>)>, Vec<Diagnostic>> {
+    let files = vec![parse_source(path.to_path_buf(), source).map_err(|diag| vec![diag])?];
+    let diagnostics = analyze_source_program(&files);
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+    generate_classes(&files).map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|err| Diagnostic {
+                path: err.path,
+                line: err.line,
+                col: err.col,
+                message: err.message,
+            })
+            .collect()
+    })
+}
+
+fn parse_source(path: PathBuf, source: &str) -> Result<SourceFileAst, Diagnostic> {
+    let tokens = lex(source).map_err(|err| Diagnostic {
+        path: path.clone(),
+        line: err.line,
+        col: err.col,
+        message: format!("lex error: {}", err.message),
+    })?;
+    let unit = parse(tokens).map_err(|err| Diagnostic {
+        path: path.clone(),
+        line: err.span.line,
+        col: err.span.col,
+        message: format!("parse error: {}", err.message),
+    })?;
+    Ok(SourceFileAst { path, unit })
+}
+
 #[derive(Debug, Clone)]
 struct FrontendInputs {
     sources: Vec<PathBuf>,
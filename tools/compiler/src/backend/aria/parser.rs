@@ -696,11 +696,11 @@ impl Parser {
         }
     }
 
-    fn at<F>(&self, mut pred: F) -> bool
+    fn at<F>(&self, pred: F) -> bool
     where
         F: FnMut(&TokenKind) -> bool,
     {
-        self.peek_kind().is_some_and(|k| pred(k))
+        self.peek_kind().is_some_and(pred)
     }
 
     fn at_eof(&self) -> bool {
@@ -30,12 +30,22 @@ struct ClassInfo {
 }
 
 pub fn analyze(files: &[SourceFileAst]) -> Vec<Diagnostic> {
+    analyze_files(files, true)
+}
+
+/// Like `analyze`, but for a file launched as a program, which may be
+/// named anything (JEP 330).
+pub fn analyze_source_program(files: &[SourceFileAst]) -> Vec<Diagnostic> {
+    analyze_files(files, false)
+}
+
+fn analyze_files(files: &[SourceFileAst], check_file_names: bool) -> Vec<Diagnostic> {
     let mut diagnostics = Vec::new();
     let class_table = collect_classes(files, &mut diagnostics);
 
     for file in files {
         for class in &file.unit.classes {
-            if class.is_public {
+            if check_file_names && class.is_public {
                 let stem = file
                     .path
                     .file_stem()
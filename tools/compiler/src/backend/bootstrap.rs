@@ -1,8 +1,11 @@
+use std::fs;
+use std::path::Path;
 use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::backend::CompilerBackend;
 use crate::cli::CompileRequest;
-use crate::config::BackendKind;
+use crate::config::{target_java_major, BackendKind};
 use crate::toolchain::resolve_tool;
 
 pub struct BootstrapBackend;
@@ -28,3 +31,91 @@ impl CompilerBackend for BootstrapBackend {
         Ok(status.code().unwrap_or(1))
     }
 }
+
+impl BootstrapBackend {
+    /// Compiles a launched source file with host javac in a scratch
+    /// directory, under `file_name` since javac insists a public class be
+    /// in a file of its name. Returns every class written, by internal
+    /// name; diagnostics refer to `path`.
+    pub fn compile_source_program(
+        &self,
+        path: &Path,
+        source: &str,
+        file_name: &str,
+    ) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let javac = resolve_tool("javac")
+            .ok_or_else(|| "host javac not found. Set JAVA_HOME or PATH.".to_string())?;
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos());
+        let work = std::env::temp_dir().join(format!(
+            "aria-source-launch-{}-{}",
+            std::process::id(),
+            stamp
+        ));
+        let result = compile_in(&javac, &work, path, source, file_name);
+        let _ = fs::remove_dir_all(&work);
+        result
+    }
+}
+
+fn compile_in(
+    javac: &Path,
+    work: &Path,
+    path: &Path,
+    source: &str,
+    file_name: &str,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let out = work.join("classes");
+    fs::create_dir_all(&out).map_err(|e| format!("failed to create {:?}: {}", out, e))?;
+    let file = work.join(file_name);
+    fs::write(&file, source).map_err(|e| format!("failed to write {:?}: {}", file, e))?;
+
+    let output = Command::new(javac)
+        .arg("--release")
+        .arg(target_java_major())
+        .arg("-proc:none")
+        .arg("-d")
+        .arg(&out)
+        .arg(&file)
+        .output()
+        .map_err(|e| format!("failed to run {:?}: {}", javac, e))?;
+    if !output.status.success() {
+        let diagnostics = String::from_utf8_lossy(&output.stderr);
+        return Err(diagnostics.replace(&file.display().to_string(), &path.display().to_string()));
+    }
+
+    let mut classes = Vec::new();
+    collect_classes(&out, &out, &mut classes)?;
+    Ok(classes)
+}
+
+fn collect_classes(
+    root: &Path,
+    dir: &Path,
+    classes: &mut Vec<(String, Vec<u8>)>,
+) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("failed to read {:?}: {}", dir, e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_classes(root, &path, classes)?;
+            continue;
+        }
+        let Ok(relative) = path
+            .with_extension("")
+            .strip_prefix(root)
+            .map(Path::to_path_buf)
+        else {
+            continue;
+        };
+        let name = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let bytes = fs::read(&path).map_err(|e| format!("failed to read {:?}: {}", path, e))?;
+        classes.push((name, bytes));
+    }
+    Ok(())
+}
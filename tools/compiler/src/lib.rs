@@ -0,0 +1,6 @@
+pub mod app;
+mod backend;
+mod cli;
+mod config;
+pub mod source_launch;
+mod toolchain;
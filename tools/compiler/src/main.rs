@@ -1,10 +1,4 @@
-mod app;
-mod backend;
-mod cli;
-mod config;
-mod toolchain;
-
 fn main() {
     let args: Vec<String> = std::env::args().skip(1).collect();
-    std::process::exit(app::run(args));
+    std::process::exit(aria_javac::app::run(args));
 }
@@ -0,0 +1,178 @@
+//! In-memory compilation for the source launcher (`java Foo.java`).
+//!
+//! The Aria frontend is tried first; whatever it cannot handle yet goes to
+//! host javac through the bootstrap backend. The result is handed to the
+//! runtime as class file bytes, so nothing is left on disk.
+
+use std::path::Path;
+
+use aria_core::source_launcher::SourceProgram;
+
+use crate::backend::aria::compile_source_program;
+use crate::backend::bootstrap::BootstrapBackend;
+use crate::toolchain::resolve_tool;
+
+/// Compiles the source of the launched file at `path`. The first
+/// top-level class is the one to run. On failure, returns the diagnostics
+/// of the last backend tried.
+pub fn compile(path: &Path, source: &str) -> Result<SourceProgram, String> {
+    let aria_diagnostics = match compile_source_program(path, source) {
+        Ok(classes) => {
+            let main_class = classes
+                .first()
+                .map(|(name, _)| name.clone())
+                .ok_or_else(no_class_declared)?;
+            return Ok(SourceProgram {
+                main_class,
+                classes,
+            });
+        }
+        Err(diagnostics) => diagnostics
+            .iter()
+            .map(|diag| {
+                format!(
+                    "{}:{}:{}: error: {}",
+                    diag.path.display(),
+                    diag.line,
+                    diag.col,
+                    diag.message
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    if resolve_tool("javac").is_none() {
+        return Err(aria_diagnostics);
+    }
+
+    let declared = declared_types(source);
+    let first = declared.types.first().ok_or_else(no_class_declared)?;
+    let file_stem = declared
+        .types
+        .iter()
+        .find(|(_, is_public)| *is_public)
+        .unwrap_or(first);
+    let classes =
+        BootstrapBackend.compile_source_program(path, source, &format!("{}.java", file_stem.0))?;
+    let main_class = match &declared.package {
+        Some(package) => format!("{}/{}", package.replace('.', "/"), first.0),
+        None => first.0.clone(),
+    };
+    Ok(SourceProgram {
+        main_class,
+        classes,
+    })
+}
+
+fn no_class_declared() -> String {
+    "error: no class declared in source file".to_string()
+}
+
+/// The package and the top-level types of a compilation unit, in order,
+/// with whether each is public.
+#[derive(Debug, Default, PartialEq)]
+struct DeclaredTypes {
+    package: Option<String>,
+    types: Vec<(String, bool)>,
+}
+
+/// Finds the top-level declarations by scanning words outside comments,
+/// literals and braces. Enough to name a class javac compiled, without
+/// needing a parser that understands all of Java.
+fn declared_types(source: &str) -> DeclaredTypes {
+    let mut declared = DeclaredTypes::default();
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut words: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            b'"' | b'\'' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != c {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'{' => depth += 1,
+            b'}' => depth = depth.saturating_sub(1),
+            b';' if depth == 0 => words.clear(),
+            _ if c.is_ascii_alphabetic() || c == b'_' || c == b'$' => {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'_' | b'$' | b'.'))
+                {
+                    i += 1;
+                }
+                if depth == 0 {
+                    let word = &source[start..i];
+                    match words.last() {
+                        Some(&"package") if declared.package.is_none() => {
+                            declared.package = Some(word.to_string());
+                        }
+                        Some(&("class" | "interface" | "enum" | "record")) => {
+                            let is_public = words.contains(&"public");
+                            declared.types.push((word.to_string(), is_public));
+                            words.clear();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    words.push(word);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if c == b'{' {
+            words.clear();
+        }
+        i += 1;
+    }
+    declared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_top_level_types_in_order() {
+        let source = r#"
+            package ops.tools;
+            import java.util.List;
+            // class Commented {}
+            class Helper { String s = "class Quoted"; class Inner {} }
+            public final class Script { enum Mode { A } }
+            interface Marker {}
+        "#;
+        assert_eq!(
+            declared_types(source),
+            DeclaredTypes {
+                package: Some("ops.tools".to_string()),
+                types: vec![
+                    ("Helper".to_string(), false),
+                    ("Script".to_string(), true),
+                    ("Marker".to_string(), false),
+                ],
+            }
+        );
+    }
+}